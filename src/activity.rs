@@ -0,0 +1,782 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, ChildStdin, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::deadline;
+use crate::progress::Phase;
+use crate::timing;
+
+// ── Current activity ───────────────────────────────────────────────────
+
+/// Longest activity description kept; longer command lines are truncated.
+const MAX_ACTIVITY_LEN: usize = 200;
+
+/// Default time without progress before a stall warning is raised.
+pub const DEFAULT_STALL_AFTER: Duration = Duration::from_secs(120);
+
+/// What a worker is doing right now, and since when.
+#[derive(Clone, Debug)]
+pub struct Activity {
+    pub text: String,
+    pub started: Instant,
+}
+
+/// A shared "current activity" cell.
+///
+/// Workers update it before each significant operation (scanning a path,
+/// copying a file, running a child command); the UI reads it to show what
+/// a transfer is doing and to notice when it stops making progress.
+#[derive(Clone, Debug, Default)]
+pub struct ActivityCell(Arc<Mutex<Option<Activity>>>);
+
+impl ActivityCell {
+    /// Record a new activity, truncating overly long descriptions.
+    pub fn set(&self, text: &str) {
+        let activity = Activity { text: truncate(text), started: Instant::now() };
+        if let Ok(mut cur) = self.0.lock() {
+            *cur = Some(activity);
+        }
+    }
+
+    /// Record that `cmd` is about to run.
+    pub fn set_command(&self, cmd: &Command) {
+        self.set(&describe_command(cmd));
+    }
+
+    /// Forget the current activity, e.g. when a new transfer starts.
+    pub fn clear(&self) {
+        if let Ok(mut cur) = self.0.lock() {
+            *cur = None;
+        }
+    }
+
+    /// The current activity, if any.
+    pub fn current(&self) -> Option<Activity> {
+        self.0.lock().ok().and_then(|cur| cur.clone())
+    }
+}
+
+/// The process-wide activity cell updated by the transfer workers.
+///
+/// Only one transfer runs at a time, so a single cell lets the helpers a
+/// worker calls (remote listing, hashing, …) report what they are doing
+/// without threading a handle through every signature.
+pub fn global() -> &'static ActivityCell {
+    static CELL: OnceLock<ActivityCell> = OnceLock::new();
+    CELL.get_or_init(ActivityCell::default)
+}
+
+/// Record a new activity on the global cell.
+pub fn set(text: &str) {
+    global().set(text);
+}
+
+/// Extension for marking a child command as the current activity just
+/// before it runs: `Command::new("scp")…tracked_status()`.
+pub trait TrackActivity {
+    fn tracked(&mut self) -> &mut Self;
+    /// Print the command if commands are being printed (see
+    /// [`print_commands`]), for one run without tracking.
+    fn printed(&mut self) -> &mut Self;
+    /// `tracked().status()`, registering the child so that
+    /// [`terminate_children`] can stop it, timing it against the host it
+    /// reaches (see `timing`), and stopping it with a `TimedOut` error once
+    /// it passes the file's deadline (see `deadline`).
+    fn tracked_status(&mut self) -> io::Result<ExitStatus>;
+    /// `tracked().output()`, registering the child likewise.
+    fn tracked_output(&mut self) -> io::Result<Output>;
+    /// Like [`tracked_status`](TrackActivity::tracked_status), but with
+    /// stderr captured in the returned `Output` rather than passed through,
+    /// and stdout discarded.
+    fn tracked_stderr(&mut self) -> io::Result<Output>;
+    /// Like [`tracked_stderr`](TrackActivity::tracked_stderr), with stdout
+    /// written to `out` instead.
+    fn tracked_into(&mut self, out: File) -> io::Result<Output>;
+    /// Like [`tracked_stderr`](TrackActivity::tracked_stderr), with the
+    /// child's stdin handed to `feed` and closed once `feed` returns.  No
+    /// file deadline applies: the child carries a whole run's files.
+    fn tracked_feed<T>(&mut self, feed: impl FnOnce(ChildStdin) -> T) -> io::Result<(T, Output)>;
+    /// Like [`tracked_stderr`](TrackActivity::tracked_stderr), with each
+    /// line the child prints handed to `on_line` as it comes.  No file
+    /// deadline applies here either.
+    fn tracked_lines(&mut self, on_line: impl FnMut(&[u8])) -> io::Result<Output> {
+        self.tracked_records(b'\n', on_line)
+    }
+    /// `tracked_lines` with records ending in `separator`, e.g. the NULs
+    /// of `find -print0`.
+    fn tracked_records(&mut self, separator: u8, on_record: impl FnMut(&[u8])) -> io::Result<Output>;
+}
+
+impl TrackActivity for Command {
+    fn tracked(&mut self) -> &mut Self {
+        global().set_command(self);
+        self.printed()
+    }
+
+    fn printed(&mut self) -> &mut Self {
+        print_command(self);
+        self
+    }
+
+    fn tracked_status(&mut self) -> io::Result<ExitStatus> {
+        if suppressed(self) {
+            return Ok(ExitStatus::from_raw(0));
+        }
+        timing::timed(self, |cmd| {
+            let child = cmd.tracked().spawn()?;
+            wait_registered(child, deadline::limit(), |mut c| c.wait())
+        })
+    }
+
+    fn tracked_output(&mut self) -> io::Result<Output> {
+        if suppressed(self) {
+            return Ok(not_run());
+        }
+        timing::timed(self, |cmd| {
+            // The same stdio `Command::output` uses.
+            let child = cmd
+                .tracked()
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()?;
+            wait_registered(child, deadline::limit(), |c| c.wait_with_output())
+        })
+    }
+
+    fn tracked_stderr(&mut self) -> io::Result<Output> {
+        if suppressed(self) {
+            return Ok(not_run());
+        }
+        timing::timed(self, |cmd| {
+            let child = cmd.tracked().stdout(Stdio::null()).stderr(Stdio::piped()).spawn()?;
+            wait_registered(child, deadline::limit(), |c| c.wait_with_output())
+        })
+    }
+
+    fn tracked_feed<T>(&mut self, feed: impl FnOnce(ChildStdin) -> T) -> io::Result<(T, Output)> {
+        if suppressed(self) {
+            return Err(io::Error::other("not run with --dry-run"));
+        }
+        timing::timed(self, |cmd| {
+            let mut child =
+                cmd.tracked().stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped()).spawn()?;
+            let (Some(stdin), Some(mut stderr)) = (child.stdin.take(), child.stderr.take()) else {
+                return Err(io::Error::other("the child's stdin or stderr was not piped"));
+            };
+            // Drained alongside, so a child with a lot to say cannot stall
+            // on a full pipe while it is being fed.
+            let drain = thread::spawn(move || {
+                let mut text = Vec::new();
+                let _ = stderr.read_to_end(&mut text);
+                text
+            });
+            wait_registered(child, None, |mut c| {
+                let fed = feed(stdin);
+                let status = c.wait()?;
+                let stderr = drain.join().unwrap_or_default();
+                Ok((fed, Output { status, stdout: Vec::new(), stderr }))
+            })
+        })
+    }
+
+    fn tracked_into(&mut self, out: File) -> io::Result<Output> {
+        if suppressed(self) {
+            return Ok(not_run());
+        }
+        timing::timed(self, |cmd| {
+            let child = cmd.tracked().stdout(out).stderr(Stdio::piped()).spawn()?;
+            wait_registered(child, deadline::limit(), |c| c.wait_with_output())
+        })
+    }
+
+    fn tracked_records(&mut self, separator: u8, mut on_record: impl FnMut(&[u8])) -> io::Result<Output> {
+        if suppressed(self) {
+            return Ok(not_run());
+        }
+        timing::timed(self, |cmd| {
+            let mut child = cmd.tracked().stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+            let (Some(stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+                return Err(io::Error::other("the child's stdout or stderr was not piped"));
+            };
+            let drain = thread::spawn(move || {
+                let mut text = Vec::new();
+                let _ = stderr.read_to_end(&mut text);
+                text
+            });
+            wait_registered(child, None, |mut c| {
+                for record in io::BufReader::new(stdout).split(separator) {
+                    on_record(&record?);
+                }
+                let status = c.wait()?;
+                let stderr = drain.join().unwrap_or_default();
+                Ok(Output { status, stdout: Vec::new(), stderr })
+            })
+        })
+    }
+}
+
+// ── Running child commands ─────────────────────────────────────────────
+//
+// A cancel is only noticed between files, so a worker waiting on a long
+// `scp` would not stop until it exits.  Tracked children are registered
+// while they run so a cancel can ask them to stop.  A child with a
+// deadline is watched from a second thread, which stops it once the
+// deadline passes: politely first, so scp and rsync can remove what they
+// were writing, then with SIGKILL.
+
+/// How long a child past its deadline gets to exit after SIGTERM.
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+fn children() -> &'static Mutex<Vec<u32>> {
+    static CHILDREN: OnceLock<Mutex<Vec<u32>>> = OnceLock::new();
+    CHILDREN.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn wait_registered<T>(
+    child: Child,
+    limit: Option<Duration>,
+    wait: impl FnOnce(Child) -> io::Result<T>,
+) -> io::Result<T> {
+    let pid = child.id();
+    if let Ok(mut pids) = children().lock() {
+        pids.push(pid);
+    }
+    let watchdog = limit.map(|limit| {
+        deadline::running(Some(Instant::now() + limit));
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let watcher = thread::spawn(move || {
+            if done_rx.recv_timeout(limit) != Err(mpsc::RecvTimeoutError::Timeout) {
+                return false;
+            }
+            signal(pid, "-TERM");
+            if done_rx.recv_timeout(KILL_GRACE) == Err(mpsc::RecvTimeoutError::Timeout) {
+                signal(pid, "-KILL");
+            }
+            true
+        });
+        (done_tx, watcher)
+    });
+    let result = wait(child);
+    let timed_out = watchdog.is_some_and(|(done_tx, watcher)| {
+        let _ = done_tx.send(());
+        watcher.join().unwrap_or(false)
+    });
+    if limit.is_some() {
+        deadline::running(None);
+    }
+    if let Ok(mut pids) = children().lock() {
+        pids.retain(|&p| p != pid);
+    }
+    match limit {
+        Some(limit) if timed_out => Err(deadline::timed_out(limit)),
+        _ => result,
+    }
+}
+
+fn signal(pid: u32, signal: &str) {
+    let _ = Command::new("kill").args([signal, &pid.to_string()]).printed().status();
+}
+
+/// Send SIGTERM to every tracked child command still running.
+pub fn terminate_children() {
+    let pids = children().lock().map(|pids| pids.clone()).unwrap_or_default();
+    for pid in pids {
+        signal(pid, "-TERM");
+    }
+}
+
+/// Describe a command line for display.
+///
+/// The `-o Option=value` pairs that Kosmokopy adds to every ssh/scp call are
+/// dropped so the interesting part (host, remote command, file) survives
+/// truncation; everything else is shown as given.
+pub fn describe_command(cmd: &Command) -> String {
+    let mut parts = vec![cmd.get_program().to_string_lossy().to_string()];
+    let mut args = cmd.get_args().map(|a| a.to_string_lossy().to_string());
+    while let Some(arg) = args.next() {
+        if arg == "-o" {
+            args.next();
+            continue;
+        }
+        parts.push(arg);
+    }
+    truncate(&parts.join(" "))
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_ACTIVITY_LEN {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(MAX_ACTIVITY_LEN - 1).collect();
+    out.push('…');
+    out
+}
+
+// ── Printing commands ──────────────────────────────────────────────────
+//
+// Debugging a remote run used to mean guessing which ssh, scp and rsync
+// lines were built.  With `--print-commands` each external command is
+// printed to stderr as it starts, numbered in the order they ran and
+// tagged with the run's stage.  Every word is quoted for a POSIX shell,
+// byte for byte, so a printed line pasted into `sh` runs the same argv.
+
+static PRINT_COMMANDS: AtomicBool = AtomicBool::new(false);
+static PRINTED_COUNT: AtomicUsize = AtomicUsize::new(0);
+static STAGE: Mutex<Phase> = Mutex::new(Phase::Scanning);
+/// What was printed, for the GUI's result dialog.
+static PRINTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Print each external command from now on, or stop; either way the
+/// numbering starts again, at the scanning stage, with nothing kept.
+pub fn print_commands(on: bool) {
+    PRINT_COMMANDS.store(on, Ordering::Relaxed);
+    PRINTED_COUNT.store(0, Ordering::Relaxed);
+    set_stage(Phase::Scanning);
+    if let Ok(mut printed) = PRINTED.lock() {
+        printed.clear();
+    }
+}
+
+/// The stage printed with the commands that follow.
+pub fn set_stage(stage: Phase) {
+    if let Ok(mut cur) = STAGE.lock() {
+        *cur = stage;
+    }
+}
+
+/// Print `cmd` to stderr, numbered and tagged with the stage, if commands
+/// are being printed.
+pub fn print_command(cmd: &Command) {
+    print_tagged(cmd, "");
+}
+
+fn print_tagged(cmd: &Command, tag: &str) {
+    if !PRINT_COMMANDS.load(Ordering::Relaxed) {
+        return;
+    }
+    let n = PRINTED_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+    let stage = STAGE.lock().map(|s| *s).unwrap_or_default();
+    let mut entry = format!("command {} ({}{}): ", n, stage.name(), tag).into_bytes();
+    entry.extend(command_line(cmd));
+    let _ = io::stderr().lock().write_all(&[b"[kosmokopy] ", &entry[..], b"\n"].concat());
+    if let Ok(mut printed) = PRINTED.lock() {
+        printed.push(String::from_utf8_lossy(&entry).into_owned());
+    }
+}
+
+/// The commands printed since [`print_commands`], leaving none.
+pub fn take_printed() -> Vec<String> {
+    PRINTED.lock().map(|mut printed| std::mem::take(&mut *printed)).unwrap_or_default()
+}
+
+/// `cmd`'s program and arguments quoted for a POSIX shell.
+pub fn command_line(cmd: &Command) -> Vec<u8> {
+    let words: Vec<Vec<u8>> = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|word| shell_word(word.as_bytes()))
+        .collect();
+    words.join(&b' ')
+}
+
+/// `word` as it is when the shell leaves it alone, otherwise in single
+/// quotes with each `'` written as `'\''`.
+fn shell_word(word: &[u8]) -> Vec<u8> {
+    let plain = |b: &u8| b.is_ascii_alphanumeric() || b"-_./=:,+@%".contains(b);
+    if !word.is_empty() && word.iter().all(plain) {
+        return word.to_vec();
+    }
+    let mut out = vec![b'\''];
+    for &b in word {
+        if b == b'\'' {
+            out.extend_from_slice(b"'\\''");
+        } else {
+            out.push(b);
+        }
+    }
+    out.push(b'\'');
+    out
+}
+
+// ── Dry runs ───────────────────────────────────────────────────────────
+//
+// With `--dry-run` nothing may change, here or on a host, even where the
+// plan needs a listing or a lookup from one.  A tracked command that
+// would write (scp, rsync, or ssh running rm, rmdir, mkdir, mv, cp, …
+// or redirecting into a file) is not started: it is printed with
+// `--print-commands` as `suppressed` and reported as having succeeded
+// with no output, so the plan goes on as if it had.  Commands that only
+// read run as usual.
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Suppress every command that would write from now on, or stop.
+pub fn set_dry_run(on: bool) {
+    DRY_RUN.store(on, Ordering::Relaxed);
+}
+
+/// Programs a remote command changes something with.
+const WRITING_WORDS: &[&str] = &["rm", "rmdir", "mkdir", "mv", "cp", "chmod", "chown", "touch", "ln", "tee", "dd", "truncate"];
+
+/// Whether running `cmd` would change something on this machine or a host.
+pub fn writes(cmd: &Command) -> bool {
+    let program = cmd.get_program().as_bytes();
+    let args: Vec<&[u8]> = cmd.get_args().map(|a| a.as_bytes()).collect();
+    match program {
+        b"scp" => true,
+        b"rsync" => !args.iter().any(|a| matches!(*a, b"--dry-run" | b"-n" | b"--list-only" | b"--version")),
+        b"ssh" => args.iter().any(|a| script_writes(a)),
+        _ => false,
+    }
+}
+
+/// Whether the shell text `script` runs one of `WRITING_WORDS`, extracts
+/// a tar archive or redirects output anywhere but /dev/null or another
+/// descriptor.  What is in single quotes (every path the workers send) is
+/// data and never counts.
+fn script_writes(script: &[u8]) -> bool {
+    let mut bare = Vec::with_capacity(script.len());
+    let mut quoted = false;
+    for &b in script {
+        if b == b'\'' {
+            quoted = !quoted;
+            bare.push(b' ');
+        } else if !quoted {
+            bare.push(b);
+        }
+    }
+    let text = String::from_utf8_lossy(&bare);
+    let redirects = text.match_indices('>').any(|(at, _)| {
+        let target = text[at + 1..].trim_start_matches('>').trim_start();
+        !(target.starts_with('&') || target.starts_with("/dev/null"))
+    });
+    let words: Vec<&str> = text.split(|c: char| c.is_whitespace() || ";&|(){}".contains(c)).filter(|w| !w.is_empty()).collect();
+    let untars = words.windows(2).any(|w| w[0] == "tar" && w[1].trim_start_matches('-').starts_with('x'));
+    redirects || untars || words.iter().any(|w| WRITING_WORDS.contains(w))
+}
+
+/// With `--dry-run`, print `cmd` as suppressed instead of running it, if
+/// it would write.  Whether it was suppressed.
+fn suppressed(cmd: &Command) -> bool {
+    if !DRY_RUN.load(Ordering::Relaxed) || !writes(cmd) {
+        return false;
+    }
+    global().set_command(cmd);
+    print_tagged(cmd, ", suppressed");
+    true
+}
+
+/// What a suppressed command reports: success, with nothing said.
+fn not_run() -> Output {
+    Output { status: ExitStatus::from_raw(0), stdout: Vec::new(), stderr: Vec::new() }
+}
+
+// ── Stall detection ────────────────────────────────────────────────────
+
+/// Raises a warning when the current activity has run for longer than the
+/// threshold without any progress being reported.  Nothing is killed; each
+/// stalled activity is reported once.
+#[derive(Debug)]
+pub struct StallWatch {
+    threshold: Duration,
+    last_progress: Instant,
+    warned: Option<Instant>,
+}
+
+impl StallWatch {
+    pub fn new(threshold: Duration) -> Self {
+        StallWatch { threshold, last_progress: Instant::now(), warned: None }
+    }
+
+    /// Note that the transfer made progress (a file finished, bytes moved).
+    pub fn note_progress(&mut self) {
+        self.last_progress = Instant::now();
+    }
+
+    /// Check `activity` for a stall, returning the warning text the first
+    /// time it has run past the threshold with no progress.
+    pub fn check(&mut self, activity: Option<&Activity>) -> Option<String> {
+        let activity = activity?;
+        let running = activity.started.elapsed();
+        let idle = self.last_progress.elapsed();
+        if running < self.threshold || idle < self.threshold {
+            return None;
+        }
+        if self.warned == Some(activity.started) {
+            return None;
+        }
+        self.warned = Some(activity.started);
+        let mut warning = format!("possible stall: {} running for {}", activity.text, format_running(running));
+        if let Some(left) = deadline::remaining() {
+            warning.push_str(&format!("; --file-timeout stops it in {}", format_running(left)));
+        }
+        Some(warning)
+    }
+}
+
+/// Format a running time as `45 s` or `6 m`.
+fn format_running(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs < 60 {
+        format!("{} s", secs)
+    } else {
+        format!("{} m", secs / 60)
+    }
+}
+
+// ── Event output ───────────────────────────────────────────────────────
+//
+// `--events` lines go to stderr, mixed with anything else written there
+// (ssh's own messages, `--print-commands`).  With `--events-fd` a wrapper
+// can hand over a descriptor of its own, e.g. a pipe, and read nothing
+// but events from it.
+
+static EVENTS_TO: Mutex<Option<File>> = Mutex::new(None);
+
+/// Open descriptor `fd`, inherited from whoever started Kosmokopy, to
+/// write events to from now on.
+pub fn send_events_to_fd(fd: u32) -> io::Result<()> {
+    let file = OpenOptions::new().append(true).open(format!("/dev/fd/{}", fd))?;
+    if let Ok(mut to) = EVENTS_TO.lock() {
+        *to = Some(file);
+    }
+    Ok(())
+}
+
+/// Write one NDJSON event line, a `kind` event with `fields` (JSON members,
+/// comma-separated): to the `--events-fd` descriptor if one was given,
+/// otherwise to stderr.  The kind goes in as both `type`, as `--progress`
+/// readers look for it, and `event`.  A reader that went away loses the
+/// events, not the run.
+pub fn event(kind: &str, fields: &str) {
+    let line = event_line(kind, fields);
+    match EVENTS_TO.lock().as_deref_mut() {
+        Ok(Some(file)) => {
+            let _ = file.write_all(line.as_bytes());
+        }
+        _ => {
+            let _ = io::stderr().lock().write_all(line.as_bytes());
+        }
+    }
+}
+
+fn event_line(kind: &str, fields: &str) -> String {
+    let sep = if fields.is_empty() { "" } else { "," };
+    format!("{{\"type\":\"{}\",\"event\":\"{}\"{}{}}}\n", kind, kind, sep, fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn describes_commands_without_ssh_options() {
+        let mut cmd = Command::new("ssh");
+        cmd.args(["-o", "ControlMaster=auto", "-o", "ConnectTimeout=10"])
+            .arg("nas")
+            .arg("sha256sum '/srv/big file.iso'");
+        assert_eq!(describe_command(&cmd), "ssh nas sha256sum '/srv/big file.iso'");
+    }
+
+    #[test]
+    fn printed_command_lines_give_the_shell_the_same_argv() {
+        use std::ffi::OsStr;
+        let mut cmd = Command::new("scp");
+        cmd.args(["-o", "ControlPath=/run/user/1000/kosmokopy/ssh-%h_%p_%r", "-p", "--"])
+            .arg("/src/it's $(rm -rf ~) `x`.txt")
+            .arg(OsStr::from_bytes(b"/src/caf\xe9\n\"quoted\" *.txt"))
+            .arg("")
+            .arg("nas:/backup/a b.txt");
+        let line = command_line(&cmd);
+        // sh re-splits the line; printf writes back what each word became.
+        let mut script = b"set -- ".to_vec();
+        script.extend(&line);
+        script.extend(b"; printf '%s\\0' \"$@\"");
+        let out = Command::new("sh").arg("-c").arg(OsStr::from_bytes(&script)).output().unwrap();
+        let mut expected = Vec::new();
+        for word in std::iter::once(cmd.get_program()).chain(cmd.get_args()) {
+            expected.extend(word.as_bytes());
+            expected.push(0);
+        }
+        assert_eq!(out.stdout, expected);
+        assert!(line.starts_with(b"scp -o ControlPath=/run/user/1000/kosmokopy/ssh-%h_%p_%r -p -- '/src/it'\\''s"));
+    }
+
+    #[test]
+    fn only_commands_that_write_are_suppressed() {
+        let ssh = |script: &str| {
+            let mut cmd = Command::new("ssh");
+            cmd.args(["-o", "ControlMaster=auto", "nas", script]);
+            cmd
+        };
+        for script in [
+            "rm -f -- '/backup/a.txt'",
+            "mkdir -p '/backup/new'",
+            "s=0; for d in '/a' '/a/b'; do [ -d \"$d\" ] && continue; { mkdir -p \"$d\" && printf '%s\\0' \"$d\"; } || s=1; done; exit $s",
+            "mv -n -- '/a' '/b'",
+            "cat > '/backup/x.txt'",
+            "tar -xf - -C '/backup'",
+            "for d in '/a'; do rmdir -- \"$d\" 2>/dev/null && echo; done; true",
+        ] {
+            assert!(writes(&ssh(script)), "{}", script);
+        }
+        for script in [
+            "find '/backup/rm -rf x' -print0 2>/dev/null",
+            "test -d '/b' || { test -f '/b' && echo file; } || true",
+            "sha256sum -- '/b/mkdir > it' 2>&1",
+            "date +%s",
+            "tar -cf - -C '/src' .",
+        ] {
+            assert!(!writes(&ssh(script)), "{}", script);
+        }
+        assert!(writes(Command::new("scp").args(["-p", "--", "/a", "nas:/b"])));
+        assert!(writes(Command::new("rsync").args(["-a", "/a/", "/b/"])));
+        assert!(!writes(Command::new("rsync").arg("--version")));
+        assert!(!writes(Command::new("getent").args(["passwd", "root"])));
+    }
+
+    #[test]
+    fn truncates_long_command_lines() {
+        let mut cmd = Command::new("rsync");
+        cmd.arg("x".repeat(500));
+        let text = describe_command(&cmd);
+        assert_eq!(text.chars().count(), MAX_ACTIVITY_LEN);
+        assert!(text.starts_with("rsync xxx"));
+        assert!(text.ends_with('…'));
+    }
+
+    #[test]
+    fn slow_command_raises_one_stall_warning() {
+        let cell = ActivityCell::default();
+        let worker_cell = cell.clone();
+        let worker = thread::spawn(move || {
+            let mut cmd = Command::new("sleep");
+            cmd.arg("1");
+            worker_cell.set_command(&cmd);
+            let _ = cmd.status();
+        });
+
+        let mut watch = StallWatch::new(Duration::from_millis(200));
+        let mut warnings = Vec::new();
+        while !worker.is_finished() {
+            if let Some(w) = watch.check(cell.current().as_ref()) {
+                warnings.push(w);
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        worker.join().unwrap();
+        assert_eq!(warnings, vec!["possible stall: sleep 1 running for 0 s".to_string()]);
+    }
+
+    /// Held by tests that run tracked children, since `terminate_children`
+    /// stops every one.
+    static CHILD_TESTS: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn terminated_children_stop_waiting() {
+        let _serial = CHILD_TESTS.lock();
+        let started = Instant::now();
+        let worker = thread::spawn(|| Command::new("sleep").arg("30").tracked_status());
+        while children().lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        terminate_children();
+        let status = worker.join().unwrap().unwrap();
+        assert!(!status.success());
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(children().lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn children_past_their_deadline_are_stopped() {
+        let _serial = CHILD_TESTS.lock();
+        let started = Instant::now();
+        let child = Command::new("sleep").arg("30").spawn().unwrap();
+        let err = wait_registered(child, Some(Duration::from_millis(100)), |mut c| c.wait()).unwrap_err();
+        assert!(deadline::is_timeout(&err));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(deadline::remaining(), None);
+        let quick = Command::new("true").spawn().unwrap();
+        assert!(wait_registered(quick, Some(Duration::from_secs(30)), |mut c| c.wait()).unwrap().success());
+        assert!(children().lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn tracked_output_captures_like_output() {
+        let out = Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]).tracked_output().unwrap();
+        assert_eq!(out.stdout, b"out\n");
+        assert_eq!(out.stderr, b"err\n");
+        assert_eq!(out.status.code(), Some(3));
+    }
+
+    #[test]
+    fn tracked_stderr_keeps_only_stderr() {
+        let out = Command::new("sh").args(["-c", "echo out; echo err >&2; exit 23"]).tracked_stderr().unwrap();
+        assert!(out.stdout.is_empty());
+        assert_eq!(out.stderr, b"err\n");
+        assert_eq!(out.status.code(), Some(23));
+    }
+
+    #[test]
+    fn tracked_feed_closes_stdin_when_fed() {
+        let ((), out) = Command::new("sh")
+            .args(["-c", "wc -c >&2"])
+            .tracked_feed(|mut stdin| {
+                let _ = stdin.write_all(b"twelve bytes");
+            })
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&out.stderr).trim(), "12");
+        assert!(out.status.success());
+    }
+
+    #[test]
+    fn tracked_lines_hands_over_each_line() {
+        let mut lines = Vec::new();
+        let out = Command::new("sh")
+            .args(["-c", "echo one; echo err >&2; printf 'two\\nthree'"])
+            .tracked_lines(|line| lines.push(String::from_utf8_lossy(line).to_string()))
+            .unwrap();
+        assert_eq!(lines, ["one", "two", "three"]);
+        assert_eq!(out.stderr, b"err\n");
+        assert!(out.status.success());
+
+        let mut records = Vec::new();
+        Command::new("sh")
+            .args(["-c", "printf 'new\\nline\\0two\\0'"])
+            .tracked_records(0, |record| records.push(String::from_utf8_lossy(record).to_string()))
+            .unwrap();
+        assert_eq!(records, ["new\nline", "two"]);
+    }
+
+    #[test]
+    fn progress_holds_off_the_warning() {
+        let cell = ActivityCell::default();
+        cell.set("copying /data/big.iso");
+        let mut watch = StallWatch::new(Duration::from_millis(50));
+        thread::sleep(Duration::from_millis(80));
+        watch.note_progress();
+        assert_eq!(watch.check(cell.current().as_ref()), None);
+        thread::sleep(Duration::from_millis(80));
+        assert!(watch.check(cell.current().as_ref()).unwrap().contains("copying /data/big.iso"));
+        // A new activity can warn again.
+        cell.set("hashing /data/big.iso");
+        assert_eq!(watch.check(cell.current().as_ref()), None);
+    }
+
+    #[test]
+    fn events_carry_their_kind_under_both_names() {
+        assert_eq!(event_line("phase", "\"total\":3"), "{\"type\":\"phase\",\"event\":\"phase\",\"total\":3}\n");
+        assert_eq!(event_line("done", ""), "{\"type\":\"done\",\"event\":\"done\"}\n");
+    }
+}
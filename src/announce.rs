@@ -0,0 +1,103 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use crate::progress::{Phase, ProgressModel};
+
+// ── Screen reader announcements ────────────────────────────────────────
+//
+// The progress bar's text changes with every file, far too often to be
+// read out.  What a screen reader hears is thinned to every tenth of the
+// run and to the phases a listener cares about: the transfer starting and
+// the run finalizing.  A file's verification is part of transferring it,
+// so the flip between the two after each file is not announced.
+
+/// Decides which progress updates are worth announcing.
+#[derive(Debug)]
+pub struct Announcer {
+    /// The phase last announced, with verifying heard as transferring.
+    phase: Phase,
+    /// Tenths of the run done when last announced.
+    tenths: u32,
+}
+
+impl Default for Announcer {
+    /// Nothing announced yet; scanning is not announced on its own.
+    fn default() -> Self {
+        Announcer { phase: Phase::Scanning, tenths: 0 }
+    }
+}
+
+impl Announcer {
+    /// What to announce now that `model` has changed, if anything:
+    /// `Transferring, 30% done, 120 of 400 files` on entering a phase or
+    /// passing another tenth of the run, `Finalizing` at the end.
+    pub fn update(&mut self, model: &ProgressModel) -> Option<String> {
+        let phase = match model.phase() {
+            Phase::Verifying => Phase::Transferring,
+            phase => phase,
+        };
+        let tenths = (model.bar_fraction() * 10.0).floor() as u32;
+        if phase == self.phase && (phase != Phase::Transferring || tenths <= self.tenths) {
+            return None;
+        }
+        self.phase = phase;
+        self.tenths = tenths;
+        Some(match phase {
+            Phase::Transferring => {
+                format!("Transferring, {}% done, {} of {} files", tenths * 10, model.done(), model.total())
+            }
+            Phase::Preflight => "Checking destination folders".to_string(),
+            Phase::Finalizing => "Finalizing".to_string(),
+            Phase::Scanning | Phase::Verifying => "Scanning".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_is_announced_every_tenth() {
+        let total = 100;
+        let mut model = ProgressModel::new(vec![Some(10); total]);
+        let mut announcer = Announcer::default();
+        let mut heard = vec![announcer.update(&model)];
+        for done in 1..=total {
+            model.in_flight(5);
+            heard.push(announcer.update(&model));
+            model.set_phase(Phase::Verifying);
+            heard.push(announcer.update(&model));
+            model.file_done(done, total);
+            heard.push(announcer.update(&model));
+        }
+        model.set_phase(Phase::Finalizing);
+        heard.push(announcer.update(&model));
+        let heard: Vec<String> = heard.into_iter().flatten().collect();
+
+        assert_eq!(heard.len(), 11, "{:?}", heard);
+        assert_eq!(heard[0], "Transferring, 0% done, 0 of 100 files");
+        assert_eq!(heard[1], "Transferring, 10% done, 10 of 100 files");
+        // The bar is held short of full until the result, which is
+        // announced on its own.
+        assert_eq!(heard[9], "Transferring, 90% done, 90 of 100 files");
+        assert_eq!(heard[10], "Finalizing");
+    }
+
+    #[test]
+    fn large_steps_are_announced_once() {
+        let mut model = ProgressModel::new(vec![Some(10), Some(1000)]);
+        let mut announcer = Announcer::default();
+        announcer.update(&model);
+        model.file_done(1, 2);
+        assert_eq!(announcer.update(&model), None);
+        model.in_flight(600);
+        assert_eq!(announcer.update(&model).as_deref(), Some("Transferring, 60% done, 1 of 2 files"));
+        model.in_flight(650);
+        assert_eq!(announcer.update(&model), None);
+
+        // A run with nothing planned yet stays quiet.
+        assert_eq!(Announcer::default().update(&ProgressModel::default()), None);
+    }
+}
@@ -0,0 +1,216 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use crate::coalesce::group_thousands;
+use crate::progress::format_bytes;
+use crate::TransferMethod;
+
+// ── Automatic transfer method ──────────────────────────────────────────
+//
+// scp starts a copy per file and verifies each one with its own ssh round
+// trip, which is quick for a handful of files and slow for thousands,
+// where one rsync does better.  With the method left on Auto, the run
+// measures its source once it knows where it is going and picks rsync
+// for a large job to another host when rsync is installed on every end,
+// and the standard method otherwise.  Local copies always use the
+// standard method, which verifies as it goes without a second process.
+//
+// The thresholds live in settings.conf; an explicit `--method` skips the
+// choice altogether.
+
+/// Files above which a remote transfer goes by rsync.
+pub const DEFAULT_FILES: usize = 200;
+/// Bytes above which a remote transfer goes by rsync.
+pub const DEFAULT_BYTES: u64 = 500_000_000;
+
+/// The transfer method asked for, before Auto is settled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MethodChoice {
+    Standard,
+    Rsync,
+    Tar,
+    Auto,
+}
+
+impl MethodChoice {
+    /// `--method` and settings.conf's `method`.
+    pub fn parse(word: &str) -> Option<MethodChoice> {
+        match word {
+            "standard" => Some(MethodChoice::Standard),
+            "rsync" => Some(MethodChoice::Rsync),
+            "tar" => Some(MethodChoice::Tar),
+            "auto" => Some(MethodChoice::Auto),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MethodChoice::Standard => "standard",
+            MethodChoice::Rsync => "rsync",
+            MethodChoice::Tar => "tar",
+            MethodChoice::Auto => "auto",
+        }
+    }
+
+    /// The method itself, or `None` for Auto, which waits for the plan.
+    pub fn fixed(self) -> Option<TransferMethod> {
+        match self {
+            MethodChoice::Standard => Some(TransferMethod::Standard),
+            MethodChoice::Rsync => Some(TransferMethod::Rsync),
+            MethodChoice::Tar => Some(TransferMethod::Tar),
+            MethodChoice::Auto => None,
+        }
+    }
+}
+
+/// Past either of these a remote transfer goes by rsync; 0 leaves that
+/// measure out of the choice.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Thresholds {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds { files: DEFAULT_FILES, bytes: DEFAULT_BYTES }
+    }
+}
+
+/// How big a run is, as far as choosing its method goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlanShape {
+    pub files: usize,
+    /// `None` when the source's size could not be measured.
+    pub bytes: Option<u64>,
+    /// The source or the destination is on another host.
+    pub remote: bool,
+}
+
+/// The method Auto settled on and why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decision {
+    pub method: TransferMethod,
+    /// e.g. `14,203 files`, or `1.9 GiB` when the size alone decided.
+    pub reason: String,
+}
+
+impl Decision {
+    pub fn method_name(&self) -> &'static str {
+        match self.method {
+            TransferMethod::Standard => "standard",
+            TransferMethod::Rsync => "rsync",
+            TransferMethod::Tar => "tar",
+        }
+    }
+
+    /// e.g. `Method: rsync (auto — 14,203 files)`.
+    pub fn describe(&self) -> String {
+        format!("Method: {} (auto — {})", self.method_name(), self.reason)
+    }
+}
+
+/// The method for a run of `shape`, given whether rsync is installed on
+/// every machine it would involve.
+pub fn decide(shape: &PlanShape, rsync_installed: bool, thresholds: Thresholds) -> Decision {
+    let standard = |reason: String| Decision { method: TransferMethod::Standard, reason };
+    if !shape.remote {
+        return standard("local copy".to_string());
+    }
+    let files = format!("{} file{}", group_thousands(shape.files), if shape.files == 1 { "" } else { "s" });
+    let many_files = thresholds.files > 0 && shape.files > thresholds.files;
+    let many_bytes = thresholds.bytes > 0 && shape.bytes.is_some_and(|b| b > thresholds.bytes);
+    let size = shape.bytes.map(format_bytes);
+    if !many_files && !many_bytes {
+        return standard(match size {
+            Some(size) => format!("{}, {}", files, size),
+            None => files,
+        });
+    }
+    if !rsync_installed {
+        return standard(format!("{}, but rsync is not installed", if many_files { files } else { size.unwrap_or(files) }));
+    }
+    let reason = match size {
+        Some(size) if many_bytes && !many_files => size,
+        _ => files,
+    };
+    Decision { method: TransferMethod::Rsync, reason }
+}
+
+/// The file count, size in KiB and whether rsync was found, from the
+/// source probe run on a remote host: `find … | wc -l`, `du -sk` and
+/// `command -v rsync`, one per line.  The size is `None` when `du` gave
+/// nothing.
+pub fn parse_probe(stdout: &str) -> Option<(usize, Option<u64>, bool)> {
+    let mut lines = stdout.lines().map(str::trim);
+    let files = lines.next()?.parse().ok()?;
+    let kib = lines.next().and_then(|l| l.split_whitespace().next()).and_then(|n| n.parse::<u64>().ok());
+    let rsync = lines.any(|l| l == "rsync");
+    Some((files, kib.map(|k| k * 1024), rsync))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choices_parse_and_print() {
+        for choice in [MethodChoice::Standard, MethodChoice::Rsync, MethodChoice::Tar, MethodChoice::Auto] {
+            assert_eq!(MethodChoice::parse(choice.as_str()), Some(choice));
+        }
+        assert_eq!(MethodChoice::parse("ftp"), None);
+        assert_eq!(MethodChoice::Auto.fixed(), None);
+        assert_eq!(MethodChoice::Rsync.fixed(), Some(TransferMethod::Rsync));
+    }
+
+    #[test]
+    fn decides_over_plan_shapes() {
+        const MB: u64 = 1_000_000;
+        let t = Thresholds::default();
+        let shape = |files, bytes: Option<u64>, remote| PlanShape { files, bytes, remote };
+        // (shape, rsync installed, method, reason)
+        let cases = [
+            (shape(14_203, Some(9 * MB), false), true, TransferMethod::Standard, "local copy"),
+            (shape(3, Some(2_000 * MB), false), true, TransferMethod::Standard, "local copy"),
+            (shape(14_203, Some(9 * MB), true), true, TransferMethod::Rsync, "14,203 files"),
+            (shape(14_203, Some(9 * MB), true), false, TransferMethod::Standard, "14,203 files, but rsync is not installed"),
+            (shape(201, None, true), true, TransferMethod::Rsync, "201 files"),
+            (shape(200, Some(500 * MB), true), true, TransferMethod::Standard, "200 files, 476.8 MiB"),
+            (shape(3, Some(2_000 * MB), true), true, TransferMethod::Rsync, "1.9 GiB"),
+            (shape(3, Some(2_000 * MB), true), false, TransferMethod::Standard, "1.9 GiB, but rsync is not installed"),
+            (shape(1, Some(10), true), true, TransferMethod::Standard, "1 file, 10 B"),
+            (shape(12, None, true), true, TransferMethod::Standard, "12 files"),
+            (shape(0, Some(0), true), false, TransferMethod::Standard, "0 files, 0 B"),
+        ];
+        for (shape, installed, method, reason) in cases {
+            let d = decide(&shape, installed, t);
+            assert_eq!((d.method, d.reason.as_str()), (method, reason), "{:?}, rsync installed: {}", shape, installed);
+        }
+    }
+
+    #[test]
+    fn a_zero_threshold_is_left_out() {
+        let big = PlanShape { files: 50_000, bytes: Some(u64::MAX), remote: true };
+        let off = Thresholds { files: 0, bytes: 0 };
+        assert_eq!(decide(&big, true, off).method, TransferMethod::Standard);
+        let files_only = Thresholds { files: 10, bytes: 0 };
+        assert_eq!(decide(&big, true, files_only).reason, "50,000 files");
+    }
+
+    #[test]
+    fn describes_the_choice() {
+        let d = Decision { method: TransferMethod::Rsync, reason: "14,203 files".to_string() };
+        assert_eq!(d.describe(), "Method: rsync (auto — 14,203 files)");
+    }
+
+    #[test]
+    fn parses_the_remote_probe() {
+        assert_eq!(parse_probe("  14203\n88120\t/data\nrsync\n"), Some((14_203, Some(88_120 * 1024), true)));
+        assert_eq!(parse_probe("3\n\n"), Some((3, None, false)));
+        assert_eq!(parse_probe("0\n\nrsync\n"), Some((0, None, true)));
+        assert_eq!(parse_probe("ssh: connect to host"), None);
+        assert_eq!(parse_probe(""), None);
+    }
+}
@@ -0,0 +1,181 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// ── Cancelling a run ───────────────────────────────────────────────────
+//
+// Cancel used to mean one thing: stop at the next file.  Part-way through
+// a long, fully verified transfer the user may instead decide the hash
+// checks are not worth the hours, and want the files transferred and the
+// run finished without them.  Both requests travel through the one flag
+// the workers already watch.  A request can be strengthened (skipping
+// verification, then stopping) but never taken back.
+//
+// Pausing rides on the same flag but, unlike a cancel, is taken back.  A
+// paused worker parks before its next file, so the file in flight
+// finishes first: no relay copy is left staged and no partial file is
+// written.  The ssh connection may time out while parked; the next
+// command opens a new one.
+
+/// How often a paused worker looks to see whether it may go on.
+const PAUSE_POLL: Duration = Duration::from_millis(100);
+
+/// What the user has asked a running transfer to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CancelIntent {
+    /// Nothing: carry on.
+    Run,
+    /// Transfer the remaining files without verifying them and finish
+    /// normally, listing them as unverified.
+    SkipVerify,
+    /// Stop at the next file and report the run as cancelled.
+    Stop,
+}
+
+impl CancelIntent {
+    fn from_u8(value: u8) -> CancelIntent {
+        match value {
+            0 => CancelIntent::Run,
+            1 => CancelIntent::SkipVerify,
+            _ => CancelIntent::Stop,
+        }
+    }
+}
+
+/// The flag shared between a worker and whoever can cancel or pause it.
+#[derive(Debug, Default)]
+pub struct CancelFlag {
+    intent: AtomicU8,
+    paused: AtomicBool,
+}
+
+impl CancelFlag {
+    /// Ask for `intent`; a stronger request already made stands.
+    pub fn request(&self, intent: CancelIntent) {
+        self.intent.fetch_max(intent as u8, Ordering::SeqCst);
+    }
+
+    pub fn intent(&self) -> CancelIntent {
+        CancelIntent::from_u8(self.intent.load(Ordering::SeqCst))
+    }
+
+    /// Pause the worker before its next file, or let it go on.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Park the calling worker while the run is paused.  A stop ends the
+    /// wait, so a paused run can still be cancelled.
+    pub fn wait_while_paused(&self) {
+        while self.paused() && !self.stopping() {
+            thread::sleep(PAUSE_POLL);
+        }
+    }
+
+    /// Whether the worker should stop at the next file.
+    pub fn stopping(&self) -> bool {
+        self.intent() == CancelIntent::Stop
+    }
+
+    /// Whether files are to be transferred without verifying them.
+    pub fn skipping_verify(&self) -> bool {
+        self.intent() == CancelIntent::SkipVerify
+    }
+}
+
+/// What Ctrl+C does while a `--cli` run is verifying files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnCancelDuringVerify {
+    /// Skip the remaining verification, keeping the files transferred; a
+    /// second Ctrl+C stops the run.
+    Keep,
+    /// Stop the run, as Ctrl+C does at any other time.
+    Discard,
+}
+
+impl OnCancelDuringVerify {
+    pub fn parse(word: &str) -> Option<OnCancelDuringVerify> {
+        match word {
+            "keep" => Some(OnCancelDuringVerify::Keep),
+            "discard" => Some(OnCancelDuringVerify::Discard),
+            _ => None,
+        }
+    }
+
+    /// The request a Ctrl+C makes, given whether the run is `verifying`
+    /// and what has already been asked for.
+    pub fn intent(self, verifying: bool, current: CancelIntent) -> CancelIntent {
+        if self == OnCancelDuringVerify::Keep && verifying && current == CancelIntent::Run {
+            CancelIntent::SkipVerify
+        } else {
+            CancelIntent::Stop
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_only_get_stronger() {
+        let flag = CancelFlag::default();
+        assert_eq!(flag.intent(), CancelIntent::Run);
+        assert!(!flag.stopping() && !flag.skipping_verify());
+
+        flag.request(CancelIntent::SkipVerify);
+        assert!(flag.skipping_verify());
+        assert!(!flag.stopping());
+        flag.request(CancelIntent::Run);
+        assert_eq!(flag.intent(), CancelIntent::SkipVerify);
+
+        flag.request(CancelIntent::Stop);
+        assert!(flag.stopping());
+        flag.request(CancelIntent::SkipVerify);
+        assert_eq!(flag.intent(), CancelIntent::Stop);
+    }
+
+    #[test]
+    fn a_stop_ends_a_pause() {
+        let flag = std::sync::Arc::new(CancelFlag::default());
+        flag.set_paused(true);
+        let parked = {
+            let flag = flag.clone();
+            thread::spawn(move || flag.wait_while_paused())
+        };
+        thread::sleep(PAUSE_POLL * 2);
+        assert!(!parked.is_finished());
+
+        flag.request(CancelIntent::Stop);
+        parked.join().unwrap();
+        assert!(flag.paused() && flag.stopping());
+
+        // Resuming lets a worker that was not stopped straight through.
+        let flag = CancelFlag::default();
+        flag.set_paused(true);
+        flag.set_paused(false);
+        flag.wait_while_paused();
+    }
+
+    #[test]
+    fn ctrl_c_during_verification() {
+        use CancelIntent::*;
+        let keep = OnCancelDuringVerify::parse("keep").unwrap();
+        let discard = OnCancelDuringVerify::parse("discard").unwrap();
+        assert_eq!(OnCancelDuringVerify::parse("drop"), None);
+
+        assert_eq!(keep.intent(true, Run), SkipVerify);
+        // A second Ctrl+C stops the run after all.
+        assert_eq!(keep.intent(true, SkipVerify), Stop);
+        assert_eq!(keep.intent(false, Run), Stop);
+        assert_eq!(discard.intent(true, Run), Stop);
+    }
+}
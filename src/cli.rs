@@ -0,0 +1,720 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+// ── Command-line definition ────────────────────────────────────────────
+//
+// The options `--cli` accepts are listed once here.  The parser looks each
+// argument up in `OPTIONS`, and the man page and shell completions are
+// generated from the same table, so they cannot drift apart.
+
+/// What an option expects after it.
+#[derive(Clone, Copy, Debug)]
+pub enum Value {
+    /// A bare switch.
+    Flag,
+    /// Free text, shown as the given placeholder.
+    Text(&'static str),
+    /// A local path (or `host:/path` where noted), shown as the placeholder.
+    Path(&'static str),
+    /// One of a fixed set of words.
+    Choice(&'static [&'static str]),
+}
+
+/// One `--cli` option.
+#[derive(Debug)]
+pub struct CliOption {
+    pub name: &'static str,
+    pub value: Value,
+    pub help: &'static str,
+    /// May be given more than once.
+    pub repeatable: bool,
+    /// Maintenance flags left out of the man page and completions.
+    pub hidden: bool,
+}
+
+impl CliOption {
+    pub fn takes_value(&self) -> bool {
+        !matches!(self.value, Value::Flag)
+    }
+
+    /// Placeholder for the option's value, e.g. `skip|overwrite|rename`.
+    fn placeholder(&self) -> Option<String> {
+        match self.value {
+            Value::Flag => None,
+            Value::Text(p) | Value::Path(p) => Some(p.to_string()),
+            Value::Choice(words) => Some(words.join("|")),
+        }
+    }
+}
+
+const fn opt(name: &'static str, value: Value, help: &'static str) -> CliOption {
+    CliOption { name, value, help, repeatable: false, hidden: false }
+}
+
+const fn repeatable(name: &'static str, value: Value, help: &'static str) -> CliOption {
+    CliOption { name, value, help, repeatable: true, hidden: false }
+}
+
+const fn hidden(name: &'static str, value: Value, help: &'static str) -> CliOption {
+    CliOption { name, value, help, repeatable: false, hidden: true }
+}
+
+/// Shells `--generate-completions` can write a script for.
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+/// Every option `kosmokopy --cli` accepts.
+pub const OPTIONS: &[CliOption] = &[
+    opt("--src", Value::Path("path|host:/path"), "Source directory, local or remote: `[user@]host:/path`, `[2001:db8::7]:/path`, or `ssh://[user@]host:port/path` for another port."),
+    opt("--dst", Value::Path("path|host:/path"), "Destination directory, local or remote. With a single source file, a local path that is not an existing directory, has no trailing slash and whose parent exists names the file itself: it is copied to exactly that path. A trailing slash or an existing directory always means a directory."),
+    opt("--src-files", Value::Text("file1,file2"), "Comma-separated list of individual source files."),
+    opt("--src-files-from", Value::Path("path"), "Read source files from a list, e.g. a failed-files list."),
+    opt("--profile", Value::Text("name"), "Start from a profile saved in the window: its source, destination, method, mode, conflict policy, exclusions and strip-spaces. Options given as well override its choices; --exclude, --exclude-from and --include replace its exclusions."),
+    opt("--move", Value::Flag, "Move instead of copy."),
+    opt("--move-strategy", Value::Choice(&["per-file", "all-then-delete"]), "Delete each source once its copy verifies, or only after every file has copied and verified (default: per-file)."),
+    opt("--remove-source-root", Value::Flag, "After a move out of a source folder with no errors, remove the source folder itself too once it is empty; the folders under it that the move emptied are always removed."),
+    opt("--conflict", Value::Choice(&["skip", "overwrite", "rename", "newer"]), "What to do when a destination file exists (default: skip). newer overwrites it only when the source was modified more recently."),
+    opt("--strip-spaces", Value::Flag, "Remove spaces from destination file and directory names; runs before any --rename-rule."),
+    repeatable("--rename-rule", Value::Text("rule"), "Rename destination names, in the order given: replace-chars/CHARS/WITH, case-fold-extension, regex/PATTERN/REPLACEMENT or date-prefix/SEPARATOR."),
+    opt("--rename-preview", Value::Flag, "Print each source file the rename rules would rename, as before → after, and exit; --dst is not needed."),
+    opt("--chmod-dirs", Value::Text("mode"), "Give the folders the run creates this octal mode, e.g. 750, whatever the umask; folders that already exist are left alone."),
+    opt("--preserve", Value::Flag, "Give copies their sources' access and modification times, and the folders the run creates their sources' times and mode (local transfers with the standard method)."),
+    opt("--mode", Value::Choice(&["files", "parent", "folders"]), "Flatten into one directory, keep each file's parent folder, or keep the folder structure (default: folders)."),
+    opt("--files-keep-parents", Value::Text("n"), "For individual source files, recreate the last n parent folder names at the destination (default: 0)."),
+    opt("--method", Value::Choice(&["standard", "rsync", "tar", "auto"]), "Transfer method; auto picks rsync for a large transfer to or from another host, and tar sends an upload to another host as one tar stream over ssh, skipping or overwriting existing files only (default: the method saved in settings.conf, auto if none)."),
+    opt("--parallel", Value::Text("n"), "Copy up to n files at once with the standard method, 1 to 32; 1 copies them one at a time (default: 4)."),
+    opt("--retries", Value::Text("n"), "Send a file whose scp or rsync failed, or whose copy did not match, up to n more times on a remote transfer, waiting 1s, 2s, 4s… in between, 0 to 10 (default: 0)."),
+    opt("--bwlimit", Value::Text("KB/s"), "Keep transfers to about this many KB/s (1024 bytes), all files together: passed to rsync as --bwlimit and to scp as -l (shared out between uploads sent at once), and kept to by the standard method's own copies; 0 for no limit (default: 0). Names scp can't carry, sent through cat over ssh, and moves within one host are not limited."),
+    opt("--compress", Value::Choice(&["auto", "always", "never"]), "Have rsync compress with -z: auto for transfers to or from a host only, or always or never (default: auto)."),
+    repeatable("--exclude", Value::Text("pattern"), "Exclusion pattern: /dir, file, ~dirpattern, ~filepattern, or a path below the source such as docs/drafts/* (** for any folders)."),
+    repeatable("--exclude-from", Value::Text("file"), "Add the patterns in a file, one per line as for --exclude, where *.tmp means ~*.tmp; blank lines and # comments are skipped."),
+    repeatable("--include", Value::Text("pattern"), "Only transfer files whose name matches the pattern."),
+    repeatable("--only-user", Value::Text("name|uid"), "Only transfer files owned by this user; give it more than once for any of several."),
+    repeatable("--only-group", Value::Text("name|gid"), "Only transfer files whose group is this one; give it more than once for any of several."),
+    repeatable("--exclude-user", Value::Text("name|uid"), "Leave out files owned by this user."),
+    repeatable("--exclude-group", Value::Text("name|gid"), "Leave out files whose group is this one."),
+    opt("--symlinks", Value::Choice(&["skip", "follow", "preserve"]), "Symbolic links in a local source folder: leave them out and list them as skipped, copy what they point to, or recreate the links themselves (default: skip)."),
+    opt("--failed-files-out", Value::Path("path"), "Write the source paths of files that errored to the given file."),
+    opt("--detail-report", Value::Path("path"), "Write one record per file (copied, renamed, skipped, excluded, failed, and why) to a .csv, .tsv or .jsonl file as the run goes."),
+    opt("--null", Value::Flag, "NUL-delimit the failed-files list and --list-only output instead of using newlines."),
+    opt("--events", Value::Flag, "Write NDJSON progress and stall events to stderr."),
+    opt("--events-fd", Value::Text("fd"), "Write the --events lines to this inherited file descriptor, e.g. a pipe, instead of stderr; implies --events."),
+    opt("--progress", Value::Flag, "Same as --events."),
+    opt("--progress-fd", Value::Text("fd"), "Same as --events-fd."),
+    opt("--stall-after", Value::Text("secs"), "Seconds without progress before a stall warning, or a duration such as 5m (default: 120)."),
+    opt("--move-fallback", Value::Choice(&["copy", "fail"]), "When moving from a read-only source: copy instead, or fail (default: fail)."),
+    opt("--scan-limit", Value::Text("files"), "Pause a local scan that has found more than this many files and ask whether to go on (default: 5000000, or scan_limit_files in settings.conf; 0 for no limit)."),
+    opt("--scan-time-limit", Value::Text("secs"), "Pause a local scan that has run this long and ask whether to go on (default: 600, or scan_limit_secs in settings.conf; 0 for no limit)."),
+    opt("--scan-limit-action", Value::Choice(&["ask", "continue", "abort"]), "At a scan limit: prompt for yes or no on stdin, keep scanning, or stop with a scan-limit error (default: ask)."),
+    opt("--on-quota", Value::Choice(&["continue", "skip", "cancel"]), "When a file fails because the destination quota is full: keep trying the remaining files, skip them, or cancel the run (default: continue)."),
+    opt("--on-collision", Value::Choice(&["rename", "abort"]), "When two source files would land on the same name, e.g. a/report.pdf and b/report.pdf with --mode files: rename all but the first in path order after their folder, report (from b).pdf, or copy nothing and list them (default: rename)."),
+    opt("--on-unwritable", Value::Choice(&["abort", "skip", "continue"]), "When existing destination folders cannot be written to: copy nothing, leave out the files bound for them, or copy anyway (default: abort)."),
+    opt("--stale-artifacts", Value::Choice(&["resume", "clean", "ignore"]), "When the destination holds partial copies (*.kosmokopy-partial) or probe files left by an earlier run: continue the partial copies, delete the leftovers, or leave them (required when there are any; only these two patterns are ever deleted)."),
+    opt("--on-cancel-during-verify", Value::Choice(&["keep", "discard"]), "What Ctrl+C does while a file is being verified: skip the remaining verification and finish, keeping the files transferred, or cancel the run (default: discard)."),
+    opt("--duplicate-window", Value::Text("hours"), "Warn when an identical transfer completed within this many hours (default: 12, or duplicate_window_hours in settings.conf; 0 turns the check off)."),
+    opt("--no-source-config", Value::Flag, "Ignore the source folder's .kosmokopy.toml, e.g. for a tree you don't trust."),
+    opt("--case-insensitive-paths", Value::Flag, "Local folders are on filesystems that ignore case: a destination differing from the source folder only in case is refused as the same folder."),
+    opt("--refuse-duplicate-runs", Value::Flag, "Exit with a duplicate-run error instead of warning when an identical transfer completed within the --duplicate-window."),
+    opt("--verify", Value::Choice(&["full", "hash", "quick", "none"]), "How each local copy is checked against its source: byte for byte, by SHA-256, by size (and time, with --preserve), or not at all; a move can't skip it (default: full)."),
+    opt("--hash-algo", Value::Choice(&["sha256", "blake3", "xxh128"]), "Hash remote copies with this algorithm, where the host has b3sum or xxhsum; other hosts use SHA-256 (default: sha256). Also used by --verify hash."),
+    opt("--hash-cache", Value::Path("path"), "Local copies: remember each compared file's hash in this file, and skip files whose source and destination are both unchanged since."),
+    opt("--paranoid", Value::Flag, "With --hash-cache, never skip a file on the cache's word: always compare contents, still updating the cache."),
+    opt("--verify-sample", Value::Text("percent"), "Verify only this share of the files, picked at random, plus every file above --verify-sample-min-size; the others count as copied unchecked. Not with --move or --paranoid."),
+    opt("--verify-sample-min-size", Value::Text("bytes"), "With --verify-sample, always verify files larger than this."),
+    opt("--verify-sample-seed", Value::Text("n"), "With --verify-sample, pick the files from this seed instead of a fresh one; the seed used is in the result."),
+    opt("--ignore-free-space", Value::Flag, "Transfer even when the destination seems to have too little free space for the planned files."),
+    opt("--low-priority", Value::Flag, "Run the transfer at nice 19 and, on Linux, in the idle IO class, starting rsync and scp under ionice/nice where those exist."),
+    opt("--timestamps", Value::Choice(&["utc", "local"]), "Show dates and times, and fill in {date} and the date-prefix rename rule, in UTC or the local time zone (default: utc, or timestamps in settings.conf)."),
+    opt("--max-clock-skew", Value::Text("secs"), "Stop with an error when a remote host's clock is off from this machine's by more than this many seconds (or a duration such as 5m); otherwise a skew over 30 s is only a warning."),
+    opt("--recheck-conflicts", Value::Flag, "Re-test each remote destination path just before uploading."),
+    opt("--no-recheck-rename", Value::Flag, "With --conflict rename, trust the lookup made before the run instead of re-testing each name picked for a remote destination just before uploading."),
+    opt("--relay", Value::Choice(&["auto", "direct", "scp3", "staged"]), "Between two hosts: have the source host send each file itself, relay it through this machine's memory with scp -3, or download it to a local staging folder and upload it; auto tries them in that order, keeping the first that works (default: auto)."),
+    opt("--accept-new-hostkeys", Value::Flag, "Trust hosts ssh has never connected to (StrictHostKeyChecking=accept-new); a changed host key is still refused."),
+    opt("--file-timeout", Value::Text("secs|rate"), "Stop an scp, rsync or remote hash of one file that runs longer than this many seconds, or than its size at a rate such as 1M/s-min (at least 5 minutes), and record the file as timed out (default: 0, none)."),
+    opt("--ssh-control-dir", Value::Path("dir"), "Keep ssh's shared-connection sockets in this folder (created owner-only if missing) instead of $XDG_RUNTIME_DIR/kosmokopy or a private temp folder."),
+    opt("--retain", Value::Text("n"), "With a {date} in the destination's last folder name, keep only the newest n dated folders after a clean run."),
+    opt("--retain-dry-run", Value::Flag, "List the dated folders --retain would delete without deleting them."),
+    opt("--retain-to-trash", Value::Flag, "Move the dated folders --retain prunes to the trash instead of deleting them."),
+    opt("--list-only", Value::Flag, "Print the source files a run would transfer, one per line, and exit; --dst is not needed."),
+    opt("--fail-if-empty", Value::Flag, "With --list-only, exit with status 1 when no files are selected."),
+    opt("--dry-run", Value::Flag, "Print what the run would do with each file, as a JSON array of {src, dst, action} entries, and exit without writing anything. A remote source or destination is only listed and looked up."),
+    opt("--no-coalesce-errors", Value::Flag, "List every error instead of folding long runs of the same failure."),
+    opt("--print-commands", Value::Flag, "Print each external command (ssh, scp, rsync, …) to stderr as it starts, numbered in order and tagged with the run's stage, every word quoted for a POSIX shell. With --dry-run, commands that would write (scp, rsync, rm, mkdir, mv, …) are not run and are printed tagged as suppressed."),
+    opt("--detail-limit", Value::Text("n"), "Keep at most n skipped and n error entries in the result; the rest are counted, and with KOSMOKOPY_DEBUG set written to a file in the state directory (default: 10000)."),
+    hidden("--print-paths", Value::Flag, "Print the config, state, cache and data directories as JSON."),
+    hidden("--generate-man", Value::Flag, "Print the man page (roff) to stdout."),
+    hidden("--generate-completions", Value::Choice(SHELLS), "Print a shell completion script to stdout."),
+    hidden("--capabilities", Value::Flag, "Print the version, options, hash algorithms and transports available here as JSON."),
+];
+
+/// Look up an option by name.
+pub fn find(name: &str) -> Option<&'static CliOption> {
+    OPTIONS.iter().find(|o| o.name == name)
+}
+
+/// The value `args` gives the option `name`, the last one if given more
+/// than once.  Other options' values are skipped, so `--exclude --profile`
+/// is not taken for a profile.
+pub fn value_of<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let mut found = None;
+    let mut i = 0;
+    while i < args.len() {
+        match find(&args[i]) {
+            Some(o) if o.takes_value() => {
+                if o.name == name {
+                    found = args.get(i + 1).map(String::as_str);
+                }
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+    found
+}
+
+fn public_options() -> impl Iterator<Item = &'static CliOption> {
+    OPTIONS.iter().filter(|o| !o.hidden)
+}
+
+/// What each option belongs to, for `--capabilities`.  Every option in
+/// `OPTIONS` is in exactly one group.
+pub const FEATURES: &[(&str, &[&str])] = &[
+    ("sources", &["--src", "--dst", "--src-files", "--src-files-from", "--profile", "--mode", "--files-keep-parents", "--no-source-config", "--case-insensitive-paths"]),
+    ("transfer", &["--move", "--move-strategy", "--remove-source-root", "--move-fallback", "--method", "--parallel", "--retries", "--bwlimit", "--compress", "--conflict", "--chmod-dirs", "--preserve", "--low-priority", "--ignore-free-space", "--on-quota", "--on-unwritable", "--stale-artifacts", "--on-collision", "--on-cancel-during-verify"]),
+    ("rename", &["--strip-spaces", "--rename-rule", "--rename-preview"]),
+    ("selection", &["--exclude", "--exclude-from", "--include", "--only-user", "--only-group", "--exclude-user", "--exclude-group", "--symlinks", "--list-only", "--fail-if-empty", "--dry-run", "--scan-limit", "--scan-time-limit", "--scan-limit-action"]),
+    ("verification", &["--verify", "--hash-algo", "--hash-cache", "--paranoid", "--verify-sample", "--verify-sample-min-size", "--verify-sample-seed"]),
+    ("remote", &["--relay", "--recheck-conflicts", "--no-recheck-rename", "--accept-new-hostkeys", "--max-clock-skew", "--file-timeout", "--ssh-control-dir"]),
+    ("reporting", &["--failed-files-out", "--detail-report", "--null", "--events", "--events-fd", "--progress", "--progress-fd", "--stall-after", "--timestamps", "--no-coalesce-errors", "--detail-limit", "--print-commands"]),
+    ("duplicates", &["--duplicate-window", "--refuse-duplicate-runs"]),
+    ("retention", &["--retain", "--retain-dry-run", "--retain-to-trash"]),
+    ("maintenance", &["--print-paths", "--generate-man", "--generate-completions", "--capabilities"]),
+];
+
+// ── Starting without --cli ─────────────────────────────────────────────
+//
+// Without `--cli` first the window opens, so `kosmokopy --src a --dst b`
+// used to start the GUI (or, over plain ssh, fail with a GTK error that
+// never mentions `--cli`).  Such a command line is answered with the one
+// that was meant, and a missing display with how to run headless.
+
+/// Exit status when the window was asked for and there is no display.
+pub const EXIT_NO_DISPLAY: i32 = 4;
+
+/// Options shown in the usage line after `--src` and `--dst`.
+const SYNOPSIS_OPTIONS: [&str; 4] = ["--move", "--conflict", "--method", "--exclude"];
+
+/// `kosmokopy --cli --src <path|host:path> --dst … [--move] …`.
+pub fn synopsis() -> String {
+    let mut line = "kosmokopy --cli --src <path|host:path> --dst <path|host:path>".to_string();
+    for o in SYNOPSIS_OPTIONS.iter().filter_map(|name| find(name)) {
+        match o.placeholder() {
+            Some(p) => line.push_str(&format!(" [{} {}]", o.name, p)),
+            None => line.push_str(&format!(" [{}]", o.name)),
+        }
+    }
+    line.push_str(" …");
+    line
+}
+
+/// The command line that was meant when `args` (with the program name
+/// first) holds `--cli` options but does not start with `--cli`.
+pub fn meant_cli(args: &[String]) -> Option<String> {
+    let (program, rest) = args.split_first()?;
+    if rest.first().is_some_and(|a| a == "--cli") {
+        return None;
+    }
+    if !rest.iter().any(|a| a == "--cli" || find(a).is_some()) {
+        return None;
+    }
+    let words = std::iter::once(program.as_str())
+        .chain(std::iter::once("--cli"))
+        .chain(rest.iter().map(String::as_str).filter(|a| *a != "--cli"));
+    Some(words.map(shell_word).collect::<Vec<_>>().join(" "))
+}
+
+/// `word` as a shell reads it back, single-quoted if need be.
+fn shell_word(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// Why no window can open, going by the environment `var` reads: `None`
+/// when there is a display to try.  macOS needs no variable.
+pub fn missing_display(var: impl Fn(&str) -> Option<String>) -> Option<&'static str> {
+    if cfg!(target_os = "macos") {
+        return None;
+    }
+    let set = |name: &str| var(name).is_some_and(|v| !v.is_empty());
+    // GDK_BACKEND=broadway and the like need neither.
+    if set("DISPLAY") || set("WAYLAND_DISPLAY") || set("GDK_BACKEND") {
+        return None;
+    }
+    Some("DISPLAY and WAYLAND_DISPLAY are not set")
+}
+
+/// What to print when the window cannot open because of `reason`.
+pub fn no_display_message(reason: &str) -> String {
+    format!(
+        "Kosmokopy's window needs a display, but {}.\nTo transfer without the window, use CLI mode:\n  {}\nkosmokopy --cli --generate-man prints every option.",
+        reason,
+        synopsis()
+    )
+}
+
+// ── Output schema ──────────────────────────────────────────────────────
+
+/// Version of the JSON written by `--cli`, reported by `--capabilities`.
+/// Raised when a field below is removed or changes meaning; adding a
+/// field leaves it alone.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A field of a JSON object written by `--cli`.
+pub struct JsonField {
+    pub name: &'static str,
+    pub kind: &'static str,
+    pub help: &'static str,
+}
+
+const fn field(name: &'static str, kind: &'static str, help: &'static str) -> JsonField {
+    JsonField { name, kind, help }
+}
+
+/// The result line printed to stdout when a run ends.
+pub const RESULT_FIELDS: &[JsonField] = &[
+    field("status", "string", "finished, cancelled, cancelled-forced or error."),
+    field("stage", "string", "cancelled and cancelled-forced only: the phase the run had reached, scanning (including remote listing), preflight, transferring, verifying or finalizing."),
+    field("kind", "string", "Errors with a specific cause only: host-key-unknown, host-key-changed, duplicate-run, no-space or stale-artifacts (leftovers of an earlier run found and no --stale-artifacts given)."),
+    field("copied", "number", "Files transferred and verified."),
+    field("skipped", "array of string", "Files left alone because the destination already existed."),
+    field("excluded_files", "number", "Files left out by exclusion or include patterns."),
+    field("excluded_dirs", "number", "Directories left out by exclusion patterns, each counted once by its path; one inside another excluded directory is not counted."),
+    field("files_in_excluded_dirs", "number", "Remote sources only: files left out because a folder above them was excluded (a local scan skips such folders unread)."),
+    field("filtered_by_owner", "number", "With --only-user, --only-group, --exclude-user or --exclude-group: files those left out (not counted in excluded_files)."),
+    field(
+        "errors",
+        "array",
+        "One message per file that failed; a run of more than 20 identical failures ends in a {message, coalesced, count} summary object.",
+    ),
+    field("outcomes", "object", "Every file counted by how it ended: copied, identical, conflicts (a different file at the destination kept), skipped (for other reasons), excluded and failed; counted in full even when skipped or errors is cut short."),
+    field("groups", "array", "Finished runs: one {name, files, copied, skipped, errors, bytes} object per top-level source folder; files directly in the source form \"(root)\"."),
+    field("move_downgraded", "boolean", "Present when a move from a read-only source ran as a copy."),
+    field("move_breakdown", "object", "Local moves only: renamed, copied, copied_bytes and identical counts."),
+    field("source_dirs_removed", "number", "Present when a move out of a source folder with no errors removed folders it left empty."),
+    field("pruned", "array of string", "With --retain: dated folders deleted (or moved to the trash, with --retain-to-trash), or with --retain-dry-run, that would be."),
+    field("prune_dry_run", "boolean", "Present when --retain-dry-run kept the pruned folders."),
+    field("deletion", "object", "--move-strategy all-then-delete only: ran, and when it ran, deleted and failed (or cancelled and kept) source counts; when it did not run because files were left unverified, their unverified count."),
+    field("rsync", "array of string", "rsync method only: the rsync version and protocol found on each end."),
+    field("auto_method", "object", "With the auto method on a remote run: method (standard or rsync) and reason, e.g. \"14,203 files\"."),
+    field("relay", "object", "Runs between two hosts: strategy (direct, scp3 or staged), the fallbacks that gave up a quicker way and why, and staged_for_names, files staged because the quicker way couldn't carry their names."),
+    field("warnings", "array of string", "Present when the transfer ran degraded, e.g. with an old remote rsync, or repeated one completed shortly before."),
+    field("done", "number", "cancelled-forced only: files processed at the last progress report."),
+    field("total", "number", "cancelled-forced only: files in the run."),
+    field("file", "string", "cancelled-forced only: the last file reported, or null."),
+    field("activity", "string", "cancelled-forced only: what the transfer was doing when it was abandoned, or null."),
+    field("scan_limit", "object", "Present when the scan passed --scan-limit or --scan-time-limit: files found and elapsed_secs at that point, the deepest path reached, by_time (the time limit was passed), and continued."),
+    field("stale_artifacts", "object", "Present when a local destination held leftovers of an earlier run: {partials, probes, choice, removed, failed}, choice being resume, clean or ignore and failed the leftovers that could not be deleted, with why."),
+    field("unwritable", "array", "Present when existing destination folders were not writable: one {dir, files} object per folder, files being how many planned files were bound for it."),
+    field("unverified", "array of string", "Present when verification was skipped part-way: files transferred but not verified. They are not counted in copied, and a move keeps their sources."),
+    field("renamed", "number", "Present when rename rules renamed files: how many."),
+    field("renamed_samples", "array", "With renamed: up to 5 {from, to} examples, relative to the destination; non_utf8 is true when a name isn't UTF-8 and has its other bytes written \\xNN."),
+    field("non_utf8", "boolean", "Present when a file name in the result isn't UTF-8: its other bytes are written \\xNN and backslashes doubled."),
+    field("truncated", "object", "Present when skipped or errors holds only the first --detail-limit entries: per list, shown, total, and the file holding the rest, or null."),
+    field("verify_sample", "object", "With --verify-sample: percent, min_size, seed, verified (files checked), files (files copied) and a summary such as \"verified 1,042 of 98,300 files (sample, seed 7)\"."),
+    field("dirs_created", "array of string", "Finished and cancelled runs: the destination folders the run created, parents first; host:path on a remote destination."),
+    field("dirs_reused", "number", "Finished and cancelled runs: destination folders written into that already existed."),
+    field("timing", "object", "Finished and cancelled runs that reached another host: hosts, each with host and download_secs, upload_secs, hashing_secs and ssh_secs (connecting, making folders, listing), and local_secs, time spent hashing on this machine."),
+    field("quota_exceeded", "object", "Present when files failed because the destination quota was full: failed, skipped (files skipped afterwards) and action (continue, skip or cancel, or null when none was needed)."),
+    field("clock_skew", "array of object", "Remote runs: host and seconds its clock is ahead of this machine's (negative when behind), for each host reached."),
+    field("low_priority", "object", "With --low-priority: nice and io_idle (whether the worker's CPU and IO priority were lowered) and child_prefix (what rsync and scp were started under, empty if nothing)."),
+    field("source_config", "string", "Present when the source folder's .kosmokopy.toml changed options or had entries ignored, e.g. \"options adjusted by source config: +1 exclusion(s), conflict=rename\"."),
+    field("summary", "object", "Finished and cancelled runs: ending, stage (cancelled only), copied, bytes (null when sizes were unknown), elapsed_secs, skipped, errors, excluded_files, excluded_dirs, files_in_excluded_dirs (remote sources) and text, the same sentence the GUI shows, e.g. \"Copied 3 files (1.2 MiB) in 0:04, 0 skipped, nothing excluded.\""),
+    field("detail_report", "object", "With --detail-report: path and records, how many rows it holds."),
+    field("hash_cache", "object", "With --hash-cache: hits (files found identical from the cache alone) and misses (files compared in full)."),
+    field("message", "string", "Error and cancelled-forced results: why the run could not start or finish."),
+    field("needed_bytes", "number", "no-space errors only: the bytes the run needs at the destination, with a margin."),
+    field("free_bytes", "number", "no-space errors only: the bytes free there."),
+];
+
+/// The NDJSON events written to stderr with `--events`.
+pub const EVENT_FIELDS: &[JsonField] = &[
+    field("type", "string", "progress, stall, warning, phase or stale_artifacts."),
+    field("event", "string", "The same as type."),
+    field("done", "number", "progress: files processed so far."),
+    field("total", "number", "progress: files in the run."),
+    field("bytes_done", "number", "progress: bytes transferred so far, or null when the file sizes are not known."),
+    field("bytes_total", "number", "progress: bytes in the run, or null when the file sizes are not known."),
+    field("file", "string", "progress: the file just processed."),
+    field("activity", "string", "What the transfer is doing right now."),
+    field(
+        "phase",
+        "string",
+        "progress and stall: scanning, preflight, transferring, verifying or finalizing. \
+         phase: delete, when a two-phase move starts deleting sources (total is the number of sources).",
+    ),
+    field("seconds", "number", "stall: how long the activity has been running."),
+    field("message", "string", "stall and warning: the warning text."),
+    field("partials", "number", "stale_artifacts: partial copies an earlier run left at the destination."),
+    field("probes", "number", "stale_artifacts: probe files an earlier run left there."),
+    field("choice", "string", "stale_artifacts: resume, clean or ignore."),
+    field("removed", "number", "stale_artifacts: leftovers deleted (clean only)."),
+];
+
+// ── Man page ───────────────────────────────────────────────────────────
+
+/// Escape text for roff.
+fn roff(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+fn man_fields(out: &mut String, fields: &[JsonField]) {
+    for f in fields {
+        out.push_str(&format!(".TP\n.B {}\n({}) {}\n", f.name, f.kind, roff(f.help)));
+    }
+}
+
+/// The `kosmokopy(1)` man page.
+pub fn man_page() -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        ".TH KOSMOKOPY 1 \"\" \"kosmokopy {}\" \"User Commands\"\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    out.push_str(".SH NAME\nkosmokopy \\- copy or move files locally and over SSH\n");
+    out.push_str(".SH SYNOPSIS\n.B kosmokopy\n.br\n.B kosmokopy \\-\\-cli\n[\\fIOPTIONS\\fR]\n");
+    out.push_str(
+        ".SH DESCRIPTION\nWithout arguments, Kosmokopy opens its GTK window. With \\fB\\-\\-cli\\fR it runs \
+         a single transfer headlessly and prints one JSON result line to stdout.\n",
+    );
+    out.push_str(".SH OPTIONS\n");
+    for o in public_options() {
+        out.push_str(".TP\n");
+        match o.placeholder() {
+            Some(p) => out.push_str(&format!("\\fB{}\\fR \\fI{}\\fR\n", roff(o.name), roff(&p))),
+            None => out.push_str(&format!("\\fB{}\\fR\n", roff(o.name))),
+        }
+        out.push_str(&roff(o.help));
+        if o.repeatable {
+            out.push_str(" May be given more than once.");
+        }
+        out.push('\n');
+    }
+    out.push_str(".SH \"JSON OUTPUT\"\nWhen a run ends, one JSON object is printed to stdout with these fields:\n");
+    man_fields(&mut out, RESULT_FIELDS);
+    out.push_str(".PP\nWith \\fB\\-\\-events\\fR, one JSON object per line is written to stderr while the transfer runs:\n");
+    man_fields(&mut out, EVENT_FIELDS);
+    out.push_str(
+        ".SH \"EXIT STATUS\"\n.TP\n.B 0\nThe run finished without errors.\n\
+         .TP\n.B 1\nThe options were invalid or the run could not start, or \\fB\\-\\-list\\-only \\-\\-fail\\-if\\-empty\\fR selected nothing.\n\
+         .TP\n.B 2\nThe run finished, but some files failed.\n\
+         .TP\n.B 3\nA second Ctrl+C was pressed and the transfer did not stop within 5 seconds; the result is a \\fBcancelled\\-forced\\fR snapshot.\n\
+         .TP\n.B 4\nWithout \\fB\\-\\-cli\\fR, the window could not open because there is no display.\n",
+    );
+    out.push_str(".SH ENVIRONMENT\n.TP\n.B KOSMOKOPY_DEBUG\nPrint diagnostic messages to stderr, and write skipped and error entries past \\fB\\-\\-detail\\-limit\\fR to a file in the state directory.\n");
+    out
+}
+
+// ── Shell completions ──────────────────────────────────────────────────
+
+/// A completion script for `shell` (one of `SHELLS`).
+pub fn completions(shell: &str) -> Option<String> {
+    match shell {
+        "bash" => Some(bash_completions()),
+        "zsh" => Some(zsh_completions()),
+        "fish" => Some(fish_completions()),
+        _ => None,
+    }
+}
+
+fn bash_completions() -> String {
+    let mut words = vec!["--cli"];
+    words.extend(public_options().map(|o| o.name));
+    let mut cases = String::new();
+    for o in public_options() {
+        let action = match o.value {
+            Value::Flag => continue,
+            Value::Choice(c) => format!("COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", c.join(" ")),
+            Value::Path(_) => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+            Value::Text(_) => "COMPREPLY=()".to_string(),
+        };
+        cases.push_str(&format!("        {})\n            {}\n            return ;;\n", o.name, action));
+    }
+    format!(
+        "# bash completion for kosmokopy\n\
+         _kosmokopy() {{\n    \
+             local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    \
+             local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n    \
+             case \"$prev\" in\n{}    esac\n    \
+             COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n\
+         }}\n\
+         complete -F _kosmokopy kosmokopy\n",
+        cases,
+        words.join(" ")
+    )
+}
+
+/// Escape an `_arguments` description.
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''").replace('[', "\\[").replace(']', "\\]").replace(':', "\\:")
+}
+
+fn zsh_completions() -> String {
+    let mut out = String::from("#compdef kosmokopy\n\n_arguments \\\n    '--cli[Run headless, printing a JSON result]' \\\n");
+    for o in public_options() {
+        let repeat = if o.repeatable { "*" } else { "" };
+        let action = match (o.value, o.placeholder()) {
+            (Value::Flag, _) | (_, None) => String::new(),
+            (Value::Choice(c), Some(p)) => format!(":{}:({})", zsh_escape(&p), c.join(" ")),
+            (Value::Path(_), Some(p)) => format!(":{}:_files", zsh_escape(&p)),
+            (Value::Text(_), Some(p)) => format!(":{}: ", zsh_escape(&p)),
+        };
+        out.push_str(&format!("    '{}{}[{}]{}' \\\n", repeat, o.name, zsh_escape(o.help), action));
+    }
+    out.truncate(out.len() - " \\\n".len());
+    out.push('\n');
+    out
+}
+
+fn fish_completions() -> String {
+    let mut out = String::from(
+        "# fish completion for kosmokopy\n\
+         complete -c kosmokopy -l cli -d 'Run headless, printing a JSON result'\n",
+    );
+    for o in public_options() {
+        let long = o.name.trim_start_matches("--");
+        let args = match o.value {
+            Value::Flag => String::new(),
+            Value::Choice(c) => format!(" -x -a '{}'", c.join(" ")),
+            Value::Path(_) => " -r -F".to_string(),
+            Value::Text(_) => " -x".to_string(),
+        };
+        out.push_str(&format!(
+            "complete -c kosmokopy -l {}{} -d '{}'\n",
+            long,
+            args,
+            o.help.replace('\'', "\\'")
+        ));
+    }
+    out
+}
+
+// ── Capabilities ───────────────────────────────────────────────────────
+//
+// `--capabilities` tells a wrapper script or file manager extension what
+// this build can do without parsing `--help` text: the version, every
+// option by feature group, the hash algorithms and which transports the
+// programs on PATH allow.  Options and fields come from the tables above;
+// only the tool probes are made at run time, by the caller.
+
+/// Hash algorithms used to compare and verify files.
+pub const HASH_ALGORITHMS: &[&str] = &["sha256", "blake3", "xxh128"];
+
+/// Each transport and the programs it needs on PATH.
+pub const TRANSPORTS: &[(&str, &[&str])] =
+    &[("local", &[]), ("scp", &["scp", "ssh"]), ("rsync", &["rsync"])];
+
+/// What was found for one external program.
+pub struct Tool {
+    pub name: &'static str,
+    pub path: Option<String>,
+    /// Its version, where the program reports one.
+    pub version: Option<String>,
+}
+
+/// Every program some transport needs, in first-use order.
+pub fn transport_tools() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = Vec::new();
+    for name in TRANSPORTS.iter().flat_map(|(_, needs)| needs.iter()) {
+        if !names.contains(name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn json_list<T>(items: impl IntoIterator<Item = T>, f: impl Fn(T) -> String) -> String {
+    format!("[{}]", items.into_iter().map(f).collect::<Vec<_>>().join(","))
+}
+
+fn json_option(o: &CliOption) -> String {
+    let (kind, choices) = match o.value {
+        Value::Flag => ("flag", String::new()),
+        Value::Text(_) => ("text", String::new()),
+        Value::Path(_) => ("path", String::new()),
+        Value::Choice(words) => ("choice", format!(",\"choices\":{}", json_list(words, |w| crate::json_string(w)))),
+    };
+    format!(
+        "{{\"name\":{},\"value\":\"{}\"{},\"repeatable\":{},\"hidden\":{}}}",
+        crate::json_string(o.name),
+        kind,
+        choices,
+        o.repeatable,
+        o.hidden
+    )
+}
+
+/// The `--capabilities` document, given what was found of each of
+/// `transport_tools()`.
+pub fn capabilities(tools: &[Tool]) -> String {
+    let opt_str = |s: &Option<String>| s.as_deref().map_or("null".to_string(), crate::json_string);
+    let found = |name: &str| tools.iter().any(|t| t.name == name && t.path.is_some());
+    let features = FEATURES
+        .iter()
+        .map(|(group, names)| {
+            let options = json_list(names.iter().filter_map(|n| find(n)), json_option);
+            format!("{}:{}", crate::json_string(group), options)
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let transports = json_list(TRANSPORTS, |(name, needs)| {
+        format!(
+            "{{\"name\":{},\"available\":{},\"requires\":{}}}",
+            crate::json_string(name),
+            needs.iter().all(|n| found(n)),
+            json_list(needs.iter(), |n| crate::json_string(n))
+        )
+    });
+    let tools = json_list(tools, |t| {
+        format!(
+            "{{\"name\":{},\"path\":{},\"version\":{}}}",
+            crate::json_string(t.name),
+            opt_str(&t.path),
+            opt_str(&t.version)
+        )
+    });
+    format!(
+        "{{\"name\":\"kosmokopy\",\"version\":{},\"schema_version\":{},\"features\":{{{}}},\"hash_algorithms\":{},\"transports\":{},\"tools\":{},\"result_fields\":{},\"event_fields\":{}}}",
+        crate::json_string(env!("CARGO_PKG_VERSION")),
+        SCHEMA_VERSION,
+        features,
+        json_list(HASH_ALGORITHMS, |h| crate::json_string(h)),
+        transports,
+        tools,
+        json_list(RESULT_FIELDS, |f| crate::json_string(f.name)),
+        json_list(EVENT_FIELDS, |f| crate::json_string(f.name))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generators_cover_every_public_option() {
+        let man = man_page();
+        let scripts: Vec<(&str, String)> =
+            SHELLS.iter().map(|s| (*s, completions(s).unwrap())).collect();
+        for o in public_options() {
+            assert!(man.contains(&roff(o.name)), "man page is missing {}", o.name);
+            for (shell, script) in &scripts {
+                let needle = if *shell == "fish" {
+                    format!("-l {}", o.name.trim_start_matches("--"))
+                } else {
+                    o.name.to_string()
+                };
+                assert!(script.contains(&needle), "{} completions are missing {}", shell, o.name);
+            }
+        }
+        for o in OPTIONS.iter().filter(|o| o.hidden) {
+            assert!(!man.contains(&roff(o.name)), "man page lists hidden {}", o.name);
+        }
+    }
+
+    #[test]
+    fn man_page_documents_the_json_schema() {
+        let man = man_page();
+        assert!(man.contains(".SH \"JSON OUTPUT\""));
+        for f in RESULT_FIELDS.iter().chain(EVENT_FIELDS) {
+            assert!(man.contains(&format!(".B {}\n", f.name)), "schema is missing {}", f.name);
+        }
+    }
+
+    #[test]
+    fn capabilities_group_every_option_once() {
+        for o in OPTIONS {
+            let groups = FEATURES.iter().filter(|(_, names)| names.contains(&o.name)).count();
+            assert_eq!(groups, 1, "{} is in {} feature groups", o.name, groups);
+        }
+        for name in FEATURES.iter().flat_map(|(_, names)| names.iter()) {
+            assert!(find(name).is_some(), "feature group lists unknown {}", name);
+        }
+        let tools = [
+            Tool { name: "scp", path: Some("/usr/bin/scp".into()), version: None },
+            Tool { name: "ssh", path: Some("/usr/bin/ssh".into()), version: Some("OpenSSH_9.6p1".into()) },
+            Tool { name: "rsync", path: None, version: None },
+        ];
+        let doc = capabilities(&tools);
+        for o in OPTIONS {
+            assert_eq!(doc.matches(&format!("\"name\":\"{}\"", o.name)).count(), 1, "{}", o.name);
+        }
+        assert!(doc.contains(r#"{"name":"scp","available":true,"requires":["scp","ssh"]}"#), "{}", doc);
+        assert!(doc.contains(r#"{"name":"rsync","available":false,"requires":["rsync"]}"#), "{}", doc);
+        assert!(doc.contains(r#"{"name":"rsync","path":null,"version":null}"#), "{}", doc);
+        assert!(doc.contains(r#""choices":["skip","overwrite","rename","newer"]"#), "{}", doc);
+        assert_eq!(transport_tools(), ["scp", "ssh", "rsync"]);
+    }
+
+    #[test]
+    fn unknown_shell_has_no_completions() {
+        assert!(completions("tcsh").is_none());
+    }
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(String::from).collect()
+    }
+
+    #[test]
+    fn cli_options_without_cli_name_the_command_meant() {
+        assert_eq!(meant_cli(&args("kosmokopy")), None);
+        assert_eq!(meant_cli(&args("kosmokopy --simple")), None);
+        assert_eq!(meant_cli(&args("kosmokopy --cli --src a --dst b")), None);
+        assert_eq!(
+            meant_cli(&args("kosmokopy --src a --dst host:/b --move")).as_deref(),
+            Some("kosmokopy --cli --src a --dst host:/b --move")
+        );
+        assert_eq!(
+            meant_cli(&args("./kk --src a --cli --dst b")).as_deref(),
+            Some("./kk --cli --src a --dst b")
+        );
+        let mut spaced = args("kosmokopy --src");
+        spaced.push("My Photos/it's".to_string());
+        assert_eq!(meant_cli(&spaced).as_deref(), Some("kosmokopy --cli --src 'My Photos/it'\\''s'"));
+        // A prompt ssh passes to --ssh-askpass is not an option.
+        assert_eq!(meant_cli(&args("kosmokopy password:")), None);
+    }
+
+    #[test]
+    fn option_values_skip_other_options_values() {
+        assert_eq!(value_of(&args("--profile nas --move"), "--profile"), Some("nas"));
+        assert_eq!(value_of(&args("--exclude --profile --move"), "--profile"), None);
+        assert_eq!(value_of(&args("--profile a --profile b"), "--profile"), Some("b"));
+        assert_eq!(value_of(&args("--move --profile"), "--profile"), None);
+    }
+
+    #[test]
+    fn a_display_is_needed_off_macos() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string())
+        };
+        if cfg!(target_os = "macos") {
+            assert_eq!(missing_display(env(&[])), None);
+            return;
+        }
+        assert!(missing_display(env(&[])).is_some());
+        assert!(missing_display(env(&[("DISPLAY", "")])).is_some());
+        assert_eq!(missing_display(env(&[("DISPLAY", ":0")])), None);
+        assert_eq!(missing_display(env(&[("WAYLAND_DISPLAY", "wayland-0")])), None);
+        assert_eq!(missing_display(env(&[("GDK_BACKEND", "broadway")])), None);
+        let message = no_display_message("DISPLAY and WAYLAND_DISPLAY are not set");
+        assert!(message.contains("kosmokopy --cli --src <path|host:path> --dst <path|host:path> [--move] [--conflict skip|overwrite|rename|newer]"), "{}", message);
+    }
+}
@@ -0,0 +1,185 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::timeutil::Timestamp;
+
+// ── Clock skew ─────────────────────────────────────────────────────────
+//
+// A remote host whose clock is minutes off gives its files modification
+// times that look wrong next to local ones.  The ssh call that checks a
+// host answers also prints its clock (`date +%s`), and the difference from
+// the local clock, taken halfway through the call, is the skew.  Anything
+// beyond `WARN_AFTER_SECS` is named as a warning; with `--max-clock-skew`
+// it stops the run instead.  Newest wins compares times from two clocks,
+// so the skews measured are also allowed for there (see `allowance`).
+
+/// Skew tolerated without a warning, in seconds.
+pub const WARN_AFTER_SECS: i64 = 30;
+
+/// No `--max-clock-skew`: skew is only ever warned about.
+const NO_LIMIT: i64 = -1;
+
+static MAX_SECS: AtomicI64 = AtomicI64::new(NO_LIMIT);
+
+/// Refuse hosts skewed by more than `secs` from now on (`--max-clock-skew`).
+pub fn set_max(secs: i64) {
+    MAX_SECS.store(secs.max(0), Ordering::SeqCst);
+}
+
+fn max() -> Option<i64> {
+    Some(MAX_SECS.load(Ordering::SeqCst)).filter(|&m| m != NO_LIMIT)
+}
+
+/// The local time, in seconds since the epoch.
+pub fn now() -> f64 {
+    Timestamp::now().as_secs_f64()
+}
+
+/// How far `remote` (the seconds `date +%s` printed) is ahead of the
+/// local clock, given the local times just before and after asking.
+/// `None` when the host printed something else.
+pub fn measure(before: f64, after: f64, remote: &str) -> Option<i64> {
+    let read = Timestamp::parse_epoch(remote)?;
+    // Whole seconds drop the fraction, so the host's clock read somewhere
+    // in the second after `remote`; take its middle.
+    let middle = if remote.contains('.') { 0.0 } else { 0.5 };
+    Some((read.as_secs_f64() + middle - (before + after) / 2.0).round() as i64)
+}
+
+/// What to do about a skew of `skew` seconds on `host`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+    Fine,
+    Warn(String),
+    Refuse(String),
+}
+
+/// Judge `skew` against the warning threshold and the `--max-clock-skew`
+/// limit, if any.
+pub fn assess(host: &str, skew: i64) -> Verdict {
+    assess_with(host, skew, max())
+}
+
+fn assess_with(host: &str, skew: i64, max: Option<i64>) -> Verdict {
+    let off = skew.unsigned_abs();
+    let direction = if skew > 0 { "ahead of" } else { "behind" };
+    let text = format!("The clock on {} is {}s {} this machine's", host, off, direction);
+    match max {
+        Some(max) if off > max.unsigned_abs() => {
+            Verdict::Refuse(format!("{}, more than the {}s --max-clock-skew allows", text, max))
+        }
+        _ if off > WARN_AFTER_SECS.unsigned_abs() => {
+            Verdict::Warn(format!("{}; modification times from it will look {}", text, if skew > 0 { "newer" } else { "older" }))
+        }
+        _ => Verdict::Fine,
+    }
+}
+
+/// The skew measured on each host reached in this run.
+static SKEWS: Mutex<Vec<(String, i64)>> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// Whether the last Newest wins comparison on this thread kept the
+    /// destination only because of the allowance.
+    static KEPT_FOR_SKEW: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Forget the hosts of an earlier run.
+pub fn reset() {
+    if let Ok(mut skews) = SKEWS.lock() {
+        skews.clear();
+    }
+}
+
+/// Remember `host`'s skew for comparing its times.
+pub fn record(host: &str, skew: i64) {
+    if let Ok(mut skews) = SKEWS.lock() {
+        skews.retain(|(h, _)| h != host);
+        skews.push((host.to_string(), skew));
+    }
+}
+
+/// How far apart times from the run's clocks can be without either file
+/// being newer: the spread between the most ahead and the most behind,
+/// this machine's clock included.
+pub fn allowance() -> Duration {
+    let skews: Vec<i64> = SKEWS.lock().map(|s| s.iter().map(|&(_, skew)| skew).collect()).unwrap_or_default();
+    Duration::from_secs(spread(&skews))
+}
+
+fn spread(skews: &[i64]) -> u64 {
+    let ahead = skews.iter().copied().fold(0, i64::max);
+    let behind = skews.iter().copied().fold(0, i64::min);
+    ahead.abs_diff(behind)
+}
+
+/// Note whether the comparison just made kept a file for the allowance.
+pub fn note_kept_for_skew(kept: bool) {
+    KEPT_FOR_SKEW.with(|k| k.set(kept));
+}
+
+/// Whether the last comparison on this thread kept its file only because
+/// of the allowance, clearing the note.
+pub fn kept_for_skew() -> bool {
+    KEPT_FOR_SKEW.with(|k| k.replace(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skew_is_measured_from_the_middle_of_the_call() {
+        // A host 90 s ahead, asked over a 2 s round trip.
+        assert_eq!(measure(1000.0, 1002.0, "1090\n"), Some(90));
+        assert_eq!(measure(1000.2, 1000.4, "1000"), Some(0));
+        assert_eq!(measure(5000.0, 5000.5, " 4700 "), Some(-300));
+        assert_eq!(measure(1000.0, 1002.0, "1091.4"), Some(90));
+        assert_eq!(measure(1000.0, 1001.0, "ok"), None);
+        assert_eq!(measure(1000.0, 1001.0, ""), None);
+    }
+
+    #[test]
+    fn only_large_skews_are_reported() {
+        assert_eq!(assess_with("nas", 0, None), Verdict::Fine);
+        assert_eq!(assess_with("nas", 30, None), Verdict::Fine);
+        assert_eq!(assess_with("nas", -30, Some(60)), Verdict::Fine);
+        assert_eq!(
+            assess_with("nas", 90, None),
+            Verdict::Warn("The clock on nas is 90s ahead of this machine's; modification times from it will look newer".to_string())
+        );
+        assert_eq!(
+            assess_with("nas", -45, Some(60)),
+            Verdict::Warn("The clock on nas is 45s behind this machine's; modification times from it will look older".to_string())
+        );
+    }
+
+    #[test]
+    fn the_limit_refuses_larger_skews() {
+        assert_eq!(
+            assess_with("nas", -300, Some(60)),
+            Verdict::Refuse("The clock on nas is 300s behind this machine's, more than the 60s --max-clock-skew allows".to_string())
+        );
+        // A strict limit below the warning threshold still applies.
+        assert!(matches!(assess_with("nas", 10, Some(5)), Verdict::Refuse(_)));
+        assert_eq!(assess_with("nas", 5, Some(5)), Verdict::Fine);
+    }
+
+    #[test]
+    fn the_allowance_spans_every_clock_in_the_run() {
+        assert_eq!(spread(&[]), 0);
+        // One host 90 s ahead: its times against this machine's.
+        assert_eq!(spread(&[90]), 90);
+        assert_eq!(spread(&[-45]), 45);
+        // Two hosts, one ahead and one behind.
+        assert_eq!(spread(&[90, -45]), 135);
+        // Both ahead: neither is further from the other than from here.
+        assert_eq!(spread(&[20, 90]), 90);
+    }
+}
@@ -0,0 +1,109 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+// ── Coalescing repeated errors ─────────────────────────────────────────
+//
+// When the destination goes read-only or a host drops mid-run, every
+// remaining file fails the same way.  Runs of errors with the same cause
+// are cut down to their first few entries plus one summary line, so the
+// result dialog and the CLI JSON stay small.
+
+/// Consecutive errors with the same cause kept before the rest of the run
+/// is folded into a summary entry.
+pub const DEFAULT_COALESCE_AFTER: usize = 20;
+
+/// An error as reported to the user.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorEntry {
+    pub message: String,
+    /// For a summary entry, how many errors it stands for; otherwise 0.
+    pub coalesced: usize,
+}
+
+/// The cause of a worker error message.
+///
+/// Worker errors read `"<source path>: <what went wrong>"`, so the cause is
+/// whatever follows the first `": "`; a message without one is its own
+/// cause.
+pub fn cause(message: &str) -> &str {
+    message.split_once(": ").map_or(message, |(_, cause)| cause)
+}
+
+/// Fold each run of more than `after` consecutive errors with the same
+/// cause into its first `after` entries and one summary entry.
+pub fn coalesce(errors: &[String], after: usize) -> Vec<ErrorEntry> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < errors.len() {
+        let run_cause = cause(&errors[i]);
+        let run = errors[i..].iter().take_while(|e| cause(e) == run_cause).count();
+        let kept = run.min(after);
+        out.extend(errors[i..i + kept].iter().map(|e| ErrorEntry { message: e.clone(), coalesced: 0 }));
+        if run > kept {
+            let extra = run - kept;
+            out.push(ErrorEntry {
+                message: format!("…and {} more files failed with '{}'", group_thousands(extra), run_cause),
+                coalesced: extra,
+            });
+        }
+        i += run;
+    }
+    out
+}
+
+/// Format a count with thousands separators, e.g. `4,312`.
+pub fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_failures_are_bounded() {
+        let errors: Vec<String> = (0..1000)
+            .map(|i| format!("/src/file{}.txt: Read-only file system (os error 30)", i))
+            .collect();
+        let entries = coalesce(&errors, DEFAULT_COALESCE_AFTER);
+        assert_eq!(entries.len(), DEFAULT_COALESCE_AFTER + 1);
+        let summary = entries.last().unwrap();
+        assert_eq!(summary.coalesced, 980);
+        assert_eq!(summary.message, "…and 980 more files failed with 'Read-only file system (os error 30)'");
+        assert!(entries[..DEFAULT_COALESCE_AFTER].iter().all(|e| e.coalesced == 0));
+    }
+
+    #[test]
+    fn only_consecutive_runs_are_folded() {
+        let mut errors: Vec<String> = (0..30).map(|i| format!("/a/{}: No space left on device", i)).collect();
+        errors.push("/b/x: hash mismatch".to_string());
+        errors.extend((0..5).map(|i| format!("/c/{}: No space left on device", i)));
+        let entries = coalesce(&errors, 20);
+        assert_eq!(entries.len(), 20 + 1 + 1 + 5);
+        assert_eq!(entries[20].coalesced, 10);
+        assert_eq!(entries[21].message, "/b/x: hash mismatch");
+    }
+
+    #[test]
+    fn short_runs_are_untouched() {
+        let errors: Vec<String> = (0..20).map(|i| format!("/a/{}: boom", i)).collect();
+        let entries = coalesce(&errors, 20);
+        assert_eq!(entries.iter().map(|e| e.message.clone()).collect::<Vec<_>>(), errors);
+    }
+
+    #[test]
+    fn counts_are_grouped() {
+        assert_eq!(group_thousands(7), "7");
+        assert_eq!(group_thousands(4312), "4,312");
+        assert_eq!(group_thousands(1234567), "1,234,567");
+    }
+}
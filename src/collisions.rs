@@ -0,0 +1,231 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// ── Files of one batch bound for the same name ─────────────────────────
+//
+// "Files only" from a folder puts `a/report.pdf` and `b/report.pdf` both
+// at `<dst>/report.pdf`, and "Remove spaces" makes `my file.txt` and
+// `myfile.txt` one name in any mode.  The conflict handling only sees
+// what was already at the destination, so whichever file came second
+// used to overwrite, be skipped or be renamed depending on the order the
+// pool finished in.  (Files picked one by one, or kept under their parent
+// folders' names, still meet as conflicts: the user named them.)
+//
+// Before a worker sends anything it places every file of the batch and
+// settles the clashes here, as the run's choice (`--on-collision`, or the
+// window's choice for files of the run with the same name) says:
+//
+// - Rename, the default, keeps the name for the first source in path
+//   order and gives each other one its folder's name, `report (from
+//   b).pdf`, numbered on if that is taken too (`report (from b 2).pdf`);
+// - Abort copies nothing and lists the clashes.
+//
+// The new names are kept by source path until the next run settles, and
+// `destpath::build_destination` puts each file there, so every worker
+// and every later placement of the same file agree.  What was renamed
+// comes back as a warning.  The choice is the run's (see `options`); the
+// names it settled on are kept until the next batch is placed.
+
+/// What to do with files of one batch bound for the same path.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OnCollision {
+    #[default]
+    Rename,
+    Abort,
+}
+
+impl OnCollision {
+    /// `--on-collision`.
+    pub fn parse(word: &str) -> Option<OnCollision> {
+        match word {
+            "rename" => Some(OnCollision::Rename),
+            "abort" => Some(OnCollision::Abort),
+            _ => None,
+        }
+    }
+}
+
+/// One file of the batch placed below the destination.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placed {
+    pub source: PathBuf,
+    /// Its path below the destination before the rename rules.
+    pub named: PathBuf,
+    /// And after them.
+    pub target: PathBuf,
+}
+
+/// Sources bound for one path below the destination.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collision {
+    pub target: PathBuf,
+    /// In path order; the first keeps the name.
+    pub sources: Vec<PathBuf>,
+}
+
+impl Collision {
+    pub fn describe(&self) -> String {
+        let sources: Vec<String> = self.sources.iter().map(|s| s.display().to_string()).collect();
+        format!("{} → {}", sources.join(", "), self.target.display())
+    }
+}
+
+/// The settled run's new names by source, and the warning saying so.
+static RENAMED: Mutex<Option<HashMap<PathBuf, PathBuf>>> = Mutex::new(None);
+static WARNING: Mutex<Option<String>> = Mutex::new(None);
+
+/// Forget the last batch's new names, e.g. before placing a new one.
+pub fn reset() {
+    *RENAMED.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    *WARNING.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Where the settled batch puts `source` instead of its own name.
+pub fn renamed(source: &Path) -> Option<PathBuf> {
+    RENAMED.lock().unwrap_or_else(|e| e.into_inner()).as_ref()?.get(source).cloned()
+}
+
+/// The warning for the files renamed apart, once.
+pub fn take() -> Option<String> {
+    WARNING.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+/// The paths below the destination that more than one of `placed` is
+/// bound for: every one when `flat` (files only, from a folder), or else
+/// those the rename rules made.
+pub fn find(placed: &[Placed], flat: bool) -> Vec<Collision> {
+    let mut by_target: BTreeMap<&Path, Vec<&Placed>> = BTreeMap::new();
+    for file in placed {
+        by_target.entry(&file.target).or_default().push(file);
+    }
+    by_target
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1 && (flat || files.iter().any(|f| f.named != files[0].named)))
+        .map(|(target, files)| {
+            let mut sources: Vec<PathBuf> = files.iter().map(|f| f.source.clone()).collect();
+            sources.sort();
+            Collision { target: target.to_path_buf(), sources }
+        })
+        .collect()
+}
+
+/// New names for all but the first source of each of `collisions`, none
+/// of them among `placed`'s targets or each other.
+pub fn rename_apart(collisions: &[Collision], placed: &[Placed]) -> Vec<(PathBuf, PathBuf)> {
+    let mut taken: HashSet<PathBuf> = placed.iter().map(|file| file.target.clone()).collect();
+    let mut renames = Vec::new();
+    for collision in collisions {
+        let target = &collision.target;
+        let stem = target.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = target.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+        for source in &collision.sources[1..] {
+            let from = source
+                .parent()
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "source".to_string());
+            let mut n = 1;
+            let new = loop {
+                let label = if n == 1 { from.clone() } else { format!("{} {}", from, n) };
+                let candidate = target.with_file_name(format!("{} (from {}){}", stem, label, ext));
+                if taken.insert(candidate.clone()) {
+                    break candidate;
+                }
+                n += 1;
+            };
+            renames.push((source.clone(), new));
+        }
+    }
+    renames
+}
+
+/// Settle the batch `placed` (see `find` for `flat`) as `choice` says:
+/// the clashes when that is to abort, otherwise how many files were
+/// renamed apart.
+pub fn settle(placed: &[Placed], flat: bool, choice: OnCollision) -> Result<usize, Vec<Collision>> {
+    reset();
+    let collisions = find(placed, flat);
+    if collisions.is_empty() {
+        return Ok(0);
+    }
+    if choice == OnCollision::Abort {
+        return Err(collisions);
+    }
+    let renames = rename_apart(&collisions, placed);
+    let shown: Vec<String> = renames.iter().take(5).map(|(s, t)| format!("{} as {}", s.display(), t.display())).collect();
+    let more = if renames.len() > shown.len() { format!(" and {} more", renames.len() - shown.len()) } else { String::new() };
+    *WARNING.lock().unwrap_or_else(|e| e.into_inner()) = Some(format!(
+        "{} file(s) would have had the same name as another in this run, so they were renamed: {}{}",
+        renames.len(),
+        shown.join(", "),
+        more
+    ));
+    let count = renames.len();
+    *RENAMED.lock().unwrap_or_else(|e| e.into_inner()) = Some(renames.into_iter().collect());
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Files placed flat, by name.
+    fn placed(pairs: &[(&str, &str)]) -> Vec<Placed> {
+        pairs
+            .iter()
+            .map(|(s, t)| {
+                let named = Path::new(s).file_name().unwrap().into();
+                Placed { source: s.into(), named, target: t.into() }
+            })
+            .collect()
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(PathBuf, PathBuf)> {
+        pairs.iter().map(|(s, t)| (PathBuf::from(s), PathBuf::from(t))).collect()
+    }
+
+    #[test]
+    fn clashes_are_renamed_apart_by_folder() {
+        let batch = placed(&[
+            ("/in/b/report.pdf", "report.pdf"),
+            ("/in/a/report.pdf", "report.pdf"),
+            ("/in/c/b/report.pdf", "report.pdf"),
+            ("/in/a/report (from b).pdf", "report (from b).pdf"),
+            ("/in/a/notes.txt", "notes.txt"),
+        ]);
+        let collisions = find(&batch, true);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].sources[0], PathBuf::from("/in/a/report.pdf"));
+        assert_eq!(
+            collisions[0].describe(),
+            "/in/a/report.pdf, /in/b/report.pdf, /in/c/b/report.pdf → report.pdf"
+        );
+        // `report (from b).pdf` is already one of the batch's names.
+        assert_eq!(
+            rename_apart(&collisions, &batch),
+            pairs(&[("/in/b/report.pdf", "report (from b 2).pdf"), ("/in/c/b/report.pdf", "report (from b 3).pdf")])
+        );
+        // Out of "Files only", only a clash the rename rules made counts.
+        assert!(find(&batch, false).is_empty());
+    }
+
+    #[test]
+    fn settling_keeps_the_new_names_or_aborts() {
+        let batch = placed(&[("/in/my file.txt", "docs/myfile.txt"), ("/in/myfile.txt", "docs/myfile.txt")]);
+        assert_eq!(settle(&batch, false, OnCollision::Abort).unwrap_err().len(), 1);
+        assert_eq!(renamed(Path::new("/in/myfile.txt")), None);
+        assert_eq!(settle(&batch, false, OnCollision::Rename), Ok(1));
+        assert_eq!(renamed(Path::new("/in/myfile.txt")), Some(PathBuf::from("docs/myfile (from in).txt")));
+        assert_eq!(renamed(Path::new("/in/my file.txt")), None);
+        assert!(take().unwrap().starts_with("1 file(s) would have had the same name"));
+        assert_eq!(take(), None);
+        reset();
+        assert_eq!(OnCollision::parse("abort"), Some(OnCollision::Abort));
+        assert_eq!(OnCollision::parse("skip"), None);
+    }
+}
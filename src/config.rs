@@ -0,0 +1,213 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::automethod::{self, MethodChoice, Thresholds};
+use crate::history;
+use crate::scanguard;
+use crate::timeutil::Timestamps;
+use crate::paths::{self, Dir};
+
+// ── User settings ──────────────────────────────────────────────────────
+//
+// A small `key = value` file in the config directory, written by the
+// first-run setup page.  Unknown keys and unreadable values are ignored so
+// older and newer versions can share one file.  A source folder's own
+// `.kosmokopy.toml` is read with the same line reader (see `sourceconf`).
+
+const FILE_NAME: &str = "settings.conf";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// The transfer method selected at start.
+    pub method: MethodChoice,
+    /// Auto picks rsync for a remote transfer of more files than this; 0
+    /// for no file limit.
+    pub auto_rsync_files: usize,
+    /// Auto picks rsync for a remote transfer of more bytes than this; 0
+    /// for no size limit.
+    pub auto_rsync_bytes: u64,
+    /// Restore the last source, destination and options on launch.
+    pub remember_state: bool,
+    /// Send a desktop notification when a transfer ends.
+    pub notifications: bool,
+    /// Open in simple mode (source, destination and Copy/Move only).
+    pub simple_mode: bool,
+    /// Hours after a run that starting an identical one is pointed out;
+    /// 0 turns the check off.
+    pub duplicate_window_hours: u64,
+    /// Files a scan may find before asking whether to go on; 0 for no
+    /// limit.
+    pub scan_limit_files: usize,
+    /// Seconds a scan may run before asking; 0 for no limit.
+    pub scan_limit_secs: u64,
+    /// Local folders are on filesystems that ignore case, so `/data/photos`
+    /// is the same folder as `/data/Photos`.
+    pub case_insensitive_paths: bool,
+    /// Where ssh keeps its connection sockets; `None` for
+    /// `$XDG_RUNTIME_DIR/kosmokopy` or a private temp folder.
+    pub ssh_control_dir: Option<PathBuf>,
+    /// Read a source folder's `.kosmokopy.toml`.
+    pub source_config: bool,
+    /// Show dates and times, and date folders and file names, in UTC or
+    /// the local time zone.
+    pub timestamps: Timestamps,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            method: MethodChoice::Auto,
+            auto_rsync_files: automethod::DEFAULT_FILES,
+            auto_rsync_bytes: automethod::DEFAULT_BYTES,
+            remember_state: true,
+            notifications: false,
+            simple_mode: false,
+            duplicate_window_hours: history::DEFAULT_WINDOW_HOURS,
+            scan_limit_files: scanguard::DEFAULT_FILES,
+            scan_limit_secs: scanguard::DEFAULT_SECS,
+            case_insensitive_paths: false,
+            ssh_control_dir: None,
+            source_config: true,
+            timestamps: Timestamps::Utc,
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Config {
+        let mut config = Config::default();
+        for (key, value) in entries(text) {
+            let flag = flag(value);
+            match (key, value) {
+                // tar only uploads, so it cannot be every run's default.
+                ("method", method) => {
+                    config.method = MethodChoice::parse(method).filter(|m| *m != MethodChoice::Tar).unwrap_or(config.method)
+                }
+                ("auto_rsync_files", files) => config.auto_rsync_files = files.parse().unwrap_or(config.auto_rsync_files),
+                ("auto_rsync_bytes", bytes) => config.auto_rsync_bytes = bytes.parse().unwrap_or(config.auto_rsync_bytes),
+                ("remember_state", _) => config.remember_state = flag.unwrap_or(config.remember_state),
+                ("notifications", _) => config.notifications = flag.unwrap_or(config.notifications),
+                ("simple_mode", _) => config.simple_mode = flag.unwrap_or(config.simple_mode),
+                ("duplicate_window_hours", hours) => {
+                    config.duplicate_window_hours = hours.parse().unwrap_or(config.duplicate_window_hours)
+                }
+                ("scan_limit_files", files) => config.scan_limit_files = files.parse().unwrap_or(config.scan_limit_files),
+                ("scan_limit_secs", secs) => config.scan_limit_secs = secs.parse().unwrap_or(config.scan_limit_secs),
+                ("case_insensitive_paths", _) => {
+                    config.case_insensitive_paths = flag.unwrap_or(config.case_insensitive_paths)
+                }
+                ("ssh_control_dir", "") => config.ssh_control_dir = None,
+                ("ssh_control_dir", dir) => config.ssh_control_dir = Some(PathBuf::from(dir)),
+                ("source_config", _) => config.source_config = flag.unwrap_or(config.source_config),
+                ("timestamps", zone) => config.timestamps = Timestamps::parse(zone).unwrap_or(config.timestamps),
+                _ => {}
+            }
+        }
+        config
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "# Kosmokopy settings\nmethod = {}\nauto_rsync_files = {}\nauto_rsync_bytes = {}\nremember_state = {}\nnotifications = {}\nsimple_mode = {}\nduplicate_window_hours = {}\nscan_limit_files = {}\nscan_limit_secs = {}\ncase_insensitive_paths = {}\nssh_control_dir = {}\nsource_config = {}\ntimestamps = {}\n",
+            self.method.as_str(),
+            self.auto_rsync_files,
+            self.auto_rsync_bytes,
+            self.remember_state,
+            self.notifications,
+            self.simple_mode,
+            self.duplicate_window_hours,
+            self.scan_limit_files,
+            self.scan_limit_secs,
+            self.case_insensitive_paths,
+            self.ssh_control_dir.as_ref().map(|d| d.display().to_string()).unwrap_or_default(),
+            self.source_config,
+            self.timestamps.as_str()
+        )
+    }
+
+    /// When Auto picks rsync.
+    pub fn auto_thresholds(&self) -> Thresholds {
+        Thresholds { files: self.auto_rsync_files, bytes: self.auto_rsync_bytes }
+    }
+}
+
+/// The `key = value` lines of `text`, trimmed, skipping blank lines,
+/// `#` comments and anything else without an `=`.
+pub fn entries(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+}
+
+/// `true` or `false`; anything else is unreadable.
+pub fn flag(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// No config directory yet, so the setup page has never been completed.
+pub fn is_first_run() -> bool {
+    paths::dir(Dir::Config).is_some_and(|d| !d.exists())
+}
+
+/// The saved settings, or the defaults when there are none.
+pub fn load() -> Config {
+    paths::dir(Dir::Config)
+        .and_then(|d| fs::read_to_string(d.join(FILE_NAME)).ok())
+        .map(|text| Config::parse(&text))
+        .unwrap_or_default()
+}
+
+pub fn save(config: &Config) -> io::Result<()> {
+    fs::write(paths::file(Dir::Config, FILE_NAME)?, config.to_text())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let config = Config {
+            method: MethodChoice::Rsync,
+            auto_rsync_files: 0,
+            auto_rsync_bytes: 1_000,
+            remember_state: false,
+            notifications: true,
+            simple_mode: true,
+            duplicate_window_hours: 0,
+            scan_limit_files: 0,
+            scan_limit_secs: 30,
+            case_insensitive_paths: true,
+            ssh_control_dir: Some(PathBuf::from("/run/user/1000/kk")),
+            source_config: false,
+            timestamps: Timestamps::Local,
+        };
+        assert_eq!(Config::parse(&config.to_text()), config);
+        assert_eq!(Config::parse(&Config::default().to_text()), Config::default());
+    }
+
+    #[test]
+    fn bad_lines_keep_defaults() {
+        let config = Config::parse("method = ftp\nauto_rsync_files = many\nremember_state = maybe\nduplicate_window_hours = -1\nscan_limit_files = lots\ncase_insensitive_paths = yes\ntimestamps = cet\ncolour = blue\nnonsense\n");
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn saved_methods_are_kept() {
+        assert_eq!(Config::parse("method = standard\n").method, MethodChoice::Standard);
+        assert_eq!(Config::parse("method = rsync\n").method, MethodChoice::Rsync);
+        assert_eq!(Config::parse("method = tar\n").method, MethodChoice::Auto);
+        assert_eq!(Config::parse("remember_state = true\n").method, MethodChoice::Auto);
+    }
+}
@@ -0,0 +1,178 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::cell::Cell;
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::progress::format_eta;
+
+// ── Per-file timeouts ──────────────────────────────────────────────────
+//
+// An scp to a host whose disk has hung blocks for ever, and once every
+// lane of the pool is held by one the whole run freezes with no error and
+// no progress.
+// With `--file-timeout`, each transfer or verification command started
+// for a file gets a deadline; past it the command is stopped, the file is
+// recorded as timed out and the run moves on.  A fixed number of seconds
+// suits files of similar size.  The rate form, `1M/s-min`, gives each file
+// as long as it takes at that average rate, but never less than five
+// minutes (`1M/s-10min` for ten), so huge files are not cut off early.
+
+/// The least time the rate form allows unless it names another.
+const DEFAULT_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// How long a command working on one file may run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileTimeout {
+    Off,
+    Fixed(Duration),
+    /// The file's size at `bytes_per_sec`, and at least `window`.
+    Rate { bytes_per_sec: u64, window: Duration },
+}
+
+impl FileTimeout {
+    /// `3600`, `0` (off), `1M/s-min` or `512K/s-10min`; K, M and G are
+    /// binary units.
+    pub fn parse(text: &str) -> Option<FileTimeout> {
+        let Some((rate, window)) = text.split_once("/s-") else {
+            let secs: u64 = text.parse().ok()?;
+            return Some(if secs == 0 { FileTimeout::Off } else { FileTimeout::Fixed(Duration::from_secs(secs)) });
+        };
+        let (digits, scale) = match rate.char_indices().last()? {
+            (i, 'K' | 'k') => (&rate[..i], 1u64 << 10),
+            (i, 'M' | 'm') => (&rate[..i], 1 << 20),
+            (i, 'G' | 'g') => (&rate[..i], 1 << 30),
+            _ => (rate, 1),
+        };
+        let bytes_per_sec = digits.parse::<u64>().ok()?.checked_mul(scale).filter(|&b| b > 0)?;
+        let window = match window.strip_suffix("min")? {
+            "" => DEFAULT_WINDOW,
+            mins => Duration::from_secs(mins.parse::<u64>().ok().filter(|&m| m > 0)?.checked_mul(60)?),
+        };
+        Some(FileTimeout::Rate { bytes_per_sec, window })
+    }
+
+    /// The time allowed for a command working on a file of `size` bytes.
+    /// The rate form needs the size; without it there is no limit.
+    pub fn limit(self, size: Option<u64>) -> Option<Duration> {
+        match self {
+            FileTimeout::Off => None,
+            FileTimeout::Fixed(limit) => Some(limit),
+            FileTimeout::Rate { bytes_per_sec, window } => {
+                let at_rate = Duration::from_secs(size?.div_ceil(bytes_per_sec));
+                Some(at_rate.max(window))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    policy: FileTimeout,
+    /// The plan's file sizes, in processing order.
+    sizes: Vec<Option<u64>>,
+    /// When the command started last will be stopped.
+    deadline: Option<Instant>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State { policy: FileTimeout::Off, sizes: Vec::new(), deadline: None });
+
+thread_local! {
+    /// The file this thread is working on, as a place in the plan; with
+    /// files in flight side by side (see `parallel`) each has its own.
+    static FILE: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Give the commands of every file a deadline from now on
+/// (`--file-timeout`).
+pub fn set(policy: FileTimeout) {
+    if let Ok(mut state) = STATE.lock() {
+        state.policy = policy;
+    }
+}
+
+/// Take the sizes of a new run's files; no file is current until
+/// [`begin`].
+pub fn plan(sizes: &[Option<u64>]) {
+    if let Ok(mut state) = STATE.lock() {
+        state.sizes = sizes.to_vec();
+    }
+    FILE.with(|file| file.set(None));
+}
+
+/// The file at `index` in the plan is being worked on now, on this thread.
+pub fn begin(index: usize) {
+    FILE.with(|file| file.set(Some(index)));
+}
+
+/// The time a command started now may run, if this thread is working on a
+/// file and a timeout is set.
+pub fn limit() -> Option<Duration> {
+    let file = FILE.with(Cell::get)?;
+    let state = STATE.lock().ok()?;
+    let size = state.sizes.get(file).copied().flatten();
+    state.policy.limit(size)
+}
+
+/// Note when the command running now will be stopped; `None` once it has
+/// ended.
+pub fn running(deadline: Option<Instant>) {
+    if let Ok(mut state) = STATE.lock() {
+        state.deadline = deadline;
+    }
+}
+
+/// How long the command running now has left, if it has a deadline.
+pub fn remaining() -> Option<Duration> {
+    let deadline = STATE.lock().ok()?.deadline?;
+    Some(deadline.saturating_duration_since(Instant::now()))
+}
+
+/// The error of a command stopped after running for `limit`.
+pub fn timed_out(limit: Duration) -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, format!("timed out after {} (--file-timeout)", format_eta(limit)))
+}
+
+/// Whether `e` is a command stopped by its deadline.
+pub fn is_timeout(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::TimedOut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1 << 20;
+
+    #[test]
+    fn timeouts_parse_in_both_forms() {
+        assert_eq!(FileTimeout::parse("0"), Some(FileTimeout::Off));
+        assert_eq!(FileTimeout::parse("3600"), Some(FileTimeout::Fixed(Duration::from_secs(3600))));
+        assert_eq!(
+            FileTimeout::parse("1M/s-min"),
+            Some(FileTimeout::Rate { bytes_per_sec: MIB, window: DEFAULT_WINDOW })
+        );
+        assert_eq!(
+            FileTimeout::parse("512K/s-10min"),
+            Some(FileTimeout::Rate { bytes_per_sec: 512 * 1024, window: Duration::from_secs(600) })
+        );
+        for bad in ["", "-5", "1h", "1M/s", "0M/s-min", "1M/s-0min", "xM/s-min", "1M/s-5"] {
+            assert_eq!(FileTimeout::parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn rate_limits_scale_with_the_file() {
+        let rate = FileTimeout::parse("1M/s-min").unwrap();
+        assert_eq!(rate.limit(Some(10 * MIB)), Some(DEFAULT_WINDOW));
+        assert_eq!(rate.limit(Some(50 * 1024 * MIB)), Some(Duration::from_secs(50 * 1024)));
+        assert_eq!(rate.limit(None), None);
+        assert_eq!(FileTimeout::Fixed(Duration::from_secs(5)).limit(None), Some(Duration::from_secs(5)));
+        assert_eq!(FileTimeout::Off.limit(Some(1)), None);
+        assert!(is_timeout(&timed_out(Duration::from_secs(3600))));
+        assert_eq!(timed_out(Duration::from_secs(3600)).to_string(), "timed out after 1:00:00 (--file-timeout)");
+    }
+}
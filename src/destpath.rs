@@ -0,0 +1,404 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf};
+
+use crate::collisions;
+use crate::TransferMode;
+
+// ── Destination paths ──────────────────────────────────────────────────
+//
+// Where each source file lands below the destination folder.  Every worker
+// builds its targets here, so a folder copied to this machine and the same
+// folder uploaded to a server come out laid the same way.  The rename rules
+// only ever touch the part below the destination folder the user chose.
+
+/// Where the files of a run go.
+#[derive(Clone, Copy, Debug)]
+pub enum Dest<'a> {
+    Local(&'a Path),
+    /// A folder on the destination host.
+    Remote(&'a str),
+}
+
+/// One source file to place.
+pub struct PlanInput<'a> {
+    /// The file: a local path, or its path on the source host.
+    pub file: &'a Path,
+    /// The folder the file was found in, when a folder was picked; `None`
+    /// for lists of individual files and a single remote file.
+    pub src_dir: Option<&'a Path>,
+    pub transfer_mode: TransferMode,
+    /// Parent folder names kept above the file (see `parent_levels`).
+    pub parent_levels: usize,
+    pub dest: Dest<'a>,
+}
+
+impl<'a> PlanInput<'a> {
+    /// A file listed from a remote source: from the folder `src_base`, or
+    /// from a list of files when that is `None`.  A single file named as
+    /// the source is placed like an individual file.
+    pub fn listed(
+        file: &'a Path,
+        src_base: Option<&'a Path>,
+        transfer_mode: TransferMode,
+        parent_levels: usize,
+        dest: Dest<'a>,
+    ) -> PlanInput<'a> {
+        PlanInput {
+            file,
+            src_dir: src_base.filter(|base| *base != file),
+            transfer_mode,
+            parent_levels,
+            dest,
+        }
+    }
+}
+
+/// A target on the destination host.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemotePath {
+    /// The destination folder, without a trailing slash.
+    pub base: String,
+    /// The renamed path below `base`, as bytes that need not be UTF-8.
+    pub rel: PathBuf,
+}
+
+impl RemotePath {
+    pub fn full(&self) -> PathBuf {
+        let mut full = OsString::from(format!("{}/", self.base));
+        full.push(&self.rel);
+        PathBuf::from(full)
+    }
+}
+
+/// Where one file goes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DestPath {
+    Local(PathBuf),
+    Remote(RemotePath),
+}
+
+impl DestPath {
+    /// The local target; a remote one as its path on the destination host.
+    pub fn into_local(self) -> PathBuf {
+        match self {
+            DestPath::Local(path) => path,
+            DestPath::Remote(remote) => remote.full(),
+        }
+    }
+
+    /// The remote target's path on the destination host; a local one as
+    /// it is.
+    pub fn into_remote(self) -> PathBuf {
+        match self {
+            DestPath::Local(path) => path,
+            DestPath::Remote(remote) => remote.full(),
+        }
+    }
+}
+
+/// Why a file has no place at the destination; it is skipped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unplaced {
+    /// A "Folders and files" copy found it outside the picked folder.
+    OutsideSource,
+    /// Its path ends in no name (`/` or `..`).
+    NoFilename,
+}
+
+impl Unplaced {
+    /// The skipped-list reason, after `"<source>: "`.
+    pub fn reason(self) -> &'static str {
+        match self {
+            Unplaced::OutsideSource => "outside source directory",
+            Unplaced::NoFilename => "no filename",
+        }
+    }
+}
+
+/// Where `input.file` goes.  `rename` applies the rename rules to the path
+/// below the destination folder: workers pass `Renamer::rename_path`, which
+/// records the rename for the report, and previews `Renamer::apply_path`.
+///
+/// * "Folders and files" from a folder: `<folder name>/<path inside it>`.
+///   A folder with no name (`/`) contributes none.
+/// * Kept parent names: `<parent names>/<file name>`.
+/// * Otherwise the bare file name.
+///
+/// A file the run renamed apart from another of its batch (see
+/// `collisions`) goes to its new name instead.
+pub fn build_destination(input: &PlanInput, rename: impl FnOnce(&Path) -> PathBuf) -> Result<DestPath, Unplaced> {
+    let rel = renamed_relative(input, rename)?;
+    let rel = collisions::renamed(input.file).unwrap_or(rel);
+    Ok(match input.dest {
+        Dest::Local(base) => DestPath::Local(base.join(rel)),
+        Dest::Remote(base) => DestPath::Remote(RemotePath {
+            base: base.trim_end_matches('/').to_string(),
+            rel,
+        }),
+    })
+}
+
+/// The path below the destination folder `input.file` is bound for, as
+/// `rename` leaves it, before clashes within the batch are settled.
+pub fn renamed_relative(input: &PlanInput, rename: impl FnOnce(&Path) -> PathBuf) -> Result<PathBuf, Unplaced> {
+    Ok(rename(&relative(input)?))
+}
+
+/// The destination-relative path of `input.file`, before renaming.
+fn relative(input: &PlanInput) -> Result<PathBuf, Unplaced> {
+    match input.src_dir {
+        Some(dir) if input.transfer_mode == TransferMode::FoldersAndFiles => {
+            let rel = input.file.strip_prefix(dir).map_err(|_| Unplaced::OutsideSource)?;
+            Ok(match dir.file_name() {
+                Some(root) => Path::new(root).join(rel),
+                None => rel.to_path_buf(),
+            })
+        }
+        _ if input.parent_levels > 0 => with_parent_names(input.file, input.parent_levels).ok_or(Unplaced::NoFilename),
+        _ => input.file.file_name().map(PathBuf::from).ok_or(Unplaced::NoFilename),
+    }
+}
+
+/// Destination-relative path keeping the names of the last `levels`
+/// directories above the file: `levels == 1` gives
+/// `<immediate parent dir name>/<filename>`.  Files nearer the filesystem
+/// root keep as many names as they have.
+pub fn with_parent_names(file: &Path, levels: usize) -> Option<PathBuf> {
+    let fname = file.file_name()?;
+    let parents: Vec<&OsStr> = file
+        .parent()
+        .map(|p| {
+            p.components()
+                .filter_map(|c| match c {
+                    Component::Normal(name) => Some(name),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut rel: PathBuf = parents[parents.len().saturating_sub(levels)..].iter().collect();
+    rel.push(fname);
+    Some(rel)
+}
+
+/// How many parent directory names to keep above each file (0 for none).
+/// "Files with parent folder" keeps one; for lists of individual files,
+/// `keep_parents` (`--files-keep-parents`) can ask for more, in any mode.
+pub fn parent_levels(transfer_mode: TransferMode, files_source: bool, keep_parents: usize) -> usize {
+    let mode_levels = usize::from(transfer_mode == TransferMode::FilesWithParent);
+    if files_source {
+        mode_levels.max(keep_parents)
+    } else {
+        mode_levels
+    }
+}
+
+// ── Conflict renames ───────────────────────────────────────────────────
+
+/// `original` with `_<n>` before its extension: `a.txt` → `a_1.txt`.
+/// The name's other bytes are kept as they are.
+fn numbered(original: &Path, n: u32) -> PathBuf {
+    let parent = original.parent().unwrap_or_else(|| Path::new("."));
+    let mut name = original.file_stem().unwrap_or_default().to_os_string();
+    name.push(format!("_{}", n));
+    if let Some(ext) = original.extension() {
+        name.push(".");
+        name.push(ext);
+    }
+    parent.join(name)
+}
+
+/// Find a unique local path by appending "_1", "_2", etc. before the extension.
+pub fn unique_local(original: &Path) -> PathBuf {
+    unique_local_where(original, |candidate| candidate.exists())
+}
+
+/// `unique_local` with `taken` saying which paths are in use, so a plan
+/// can count the files it has already placed.
+pub fn unique_local_where(original: &Path, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    (1..).map(|n| numbered(original, n)).find(|candidate| !taken(candidate)).unwrap_or_default()
+}
+
+/// Find a unique remote path using the pre-fetched set of existing files.
+pub fn unique_remote(original: &Path, existing: &HashSet<PathBuf>) -> PathBuf {
+    (1..).map(|n| numbered(original, n)).find(|candidate| !existing.contains(candidate)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rename::{Renamer, Rule};
+
+    const LOCAL_BASE: &str = "/mnt/My Backups";
+    const REMOTE_BASE: &str = "/srv/My Backups/";
+
+    /// How a source was picked.
+    #[derive(Clone, Copy, Debug)]
+    enum Source {
+        /// A folder, `/data/Holiday Photos`.
+        Folder,
+        /// A list of individual files.
+        Files { keep_parents: usize },
+        /// One remote file named as the source.
+        SingleRemoteFile,
+    }
+
+    const FOLDER: &str = "/data/Holiday Photos";
+    const FILE: &str = "/data/Holiday Photos/road trip/map 1.png";
+
+    fn input(source: Source, mode: TransferMode, dest: Dest) -> PlanInput {
+        let (src_dir, files_source, keep_parents) = match source {
+            Source::Folder => (Some(Path::new(FOLDER)), false, 0),
+            Source::Files { keep_parents } => (None, true, keep_parents),
+            Source::SingleRemoteFile => {
+                let file = Path::new(FILE);
+                return PlanInput::listed(file, Some(file), mode, parent_levels(mode, false, 0), dest);
+            }
+        };
+        PlanInput {
+            file: Path::new(FILE),
+            src_dir,
+            transfer_mode: mode,
+            parent_levels: parent_levels(mode, files_source, keep_parents),
+            dest,
+        }
+    }
+
+    #[test]
+    fn every_worker_lays_files_out_alike() {
+        use TransferMode::*;
+        let plain = Renamer::new(Vec::new());
+        let strip = Renamer::new(vec![Rule::strip_spaces()]);
+        #[rustfmt::skip]
+        let table: &[(Source, TransferMode, &Renamer, &str)] = &[
+            (Source::Folder, FoldersAndFiles, &plain, "Holiday Photos/road trip/map 1.png"),
+            (Source::Folder, FoldersAndFiles, &strip, "HolidayPhotos/roadtrip/map1.png"),
+            (Source::Folder, FilesWithParent, &plain, "road trip/map 1.png"),
+            (Source::Folder, FilesWithParent, &strip, "roadtrip/map1.png"),
+            (Source::Folder, FilesOnly, &plain, "map 1.png"),
+            (Source::Folder, FilesOnly, &strip, "map1.png"),
+            (Source::Files { keep_parents: 0 }, FoldersAndFiles, &plain, "map 1.png"),
+            (Source::Files { keep_parents: 0 }, FilesWithParent, &strip, "roadtrip/map1.png"),
+            (Source::Files { keep_parents: 0 }, FilesOnly, &plain, "map 1.png"),
+            (Source::Files { keep_parents: 2 }, FoldersAndFiles, &plain, "Holiday Photos/road trip/map 1.png"),
+            (Source::Files { keep_parents: 2 }, FilesOnly, &strip, "HolidayPhotos/roadtrip/map1.png"),
+            (Source::Files { keep_parents: 9 }, FilesOnly, &plain, "data/Holiday Photos/road trip/map 1.png"),
+            (Source::SingleRemoteFile, FoldersAndFiles, &plain, "map 1.png"),
+            (Source::SingleRemoteFile, FilesWithParent, &strip, "roadtrip/map1.png"),
+            (Source::SingleRemoteFile, FilesOnly, &strip, "map1.png"),
+        ];
+        for &(source, mode, renamer, expected) in table {
+            let case = format!("{:?} {:?}", source, mode);
+            let local = build_destination(&input(source, mode, Dest::Local(Path::new(LOCAL_BASE))), |rel| {
+                renamer.apply_path(rel, None)
+            });
+            assert_eq!(local, Ok(DestPath::Local(Path::new(LOCAL_BASE).join(expected))), "{}", case);
+            let remote =
+                build_destination(&input(source, mode, Dest::Remote(REMOTE_BASE)), |rel| renamer.apply_path(rel, None));
+            assert_eq!(
+                remote,
+                Ok(DestPath::Remote(RemotePath { base: "/srv/My Backups".to_string(), rel: PathBuf::from(expected) })),
+                "{}",
+                case
+            );
+        }
+    }
+
+    #[test]
+    fn renaming_leaves_the_destination_folder_alone() {
+        let strip = Renamer::new(vec![Rule::strip_spaces()]);
+        let dest = Dest::Remote("/srv/Nas Share");
+        let placed = build_destination(&input(Source::Folder, TransferMode::FilesOnly, dest), |rel| {
+            strip.apply_path(rel, None)
+        });
+        assert_eq!(placed.unwrap().into_remote(), Path::new("/srv/Nas Share/map1.png"));
+        let placed = build_destination(&input(Source::Folder, TransferMode::FilesOnly, Dest::Remote("/")), |rel| {
+            rel.to_path_buf()
+        });
+        assert_eq!(placed.unwrap().into_remote(), Path::new("/map 1.png"));
+    }
+
+    #[test]
+    fn files_that_have_no_place_are_skipped_with_a_reason() {
+        for dest in [Dest::Local(Path::new(LOCAL_BASE)), Dest::Remote(REMOTE_BASE)] {
+            let outside = PlanInput {
+                file: Path::new("/elsewhere/a.txt"),
+                ..input(Source::Folder, TransferMode::FoldersAndFiles, dest)
+            };
+            assert_eq!(build_destination(&outside, Path::to_path_buf), Err(Unplaced::OutsideSource));
+            for mode in [TransferMode::FilesOnly, TransferMode::FilesWithParent] {
+                let nameless = PlanInput { file: Path::new("/"), ..input(Source::Folder, mode, dest) };
+                assert_eq!(build_destination(&nameless, Path::to_path_buf), Err(Unplaced::NoFilename));
+            }
+        }
+        assert_eq!(Unplaced::OutsideSource.reason(), "outside source directory");
+    }
+
+    #[test]
+    fn a_folder_without_a_name_adds_no_root() {
+        let input = PlanInput {
+            file: Path::new("/etc/hosts"),
+            src_dir: Some(Path::new("/")),
+            transfer_mode: TransferMode::FoldersAndFiles,
+            parent_levels: 0,
+            dest: Dest::Local(Path::new("/backup")),
+        };
+        assert_eq!(build_destination(&input, Path::to_path_buf), Ok(DestPath::Local(PathBuf::from("/backup/etc/hosts"))));
+    }
+
+    #[test]
+    fn files_with_parent_keeps_only_the_immediate_folder() {
+        assert_eq!(
+            with_parent_names(Path::new("/photos/2023/summer/beach.jpg"), 1),
+            Some(PathBuf::from("summer/beach.jpg"))
+        );
+        assert_eq!(with_parent_names(Path::new("/beach.jpg"), 1), Some(PathBuf::from("beach.jpg")));
+        assert_eq!(with_parent_names(Path::new("/"), 1), None);
+    }
+
+    #[test]
+    fn kept_parent_names_for_individual_files() {
+        let file = Path::new("/home/me/2023/summer/beach.jpg");
+        assert_eq!(with_parent_names(file, 0), Some(PathBuf::from("beach.jpg")));
+        assert_eq!(with_parent_names(file, 2), Some(PathBuf::from("2023/summer/beach.jpg")));
+        assert_eq!(with_parent_names(file, 9), Some(PathBuf::from("home/me/2023/summer/beach.jpg")));
+        // Only lists of individual files use --files-keep-parents.
+        assert_eq!(parent_levels(TransferMode::FoldersAndFiles, true, 2), 2);
+        assert_eq!(parent_levels(TransferMode::FilesOnly, false, 2), 0);
+        assert_eq!(parent_levels(TransferMode::FilesWithParent, false, 2), 1);
+        assert_eq!(parent_levels(TransferMode::FilesWithParent, true, 0), 1);
+    }
+
+    #[test]
+    fn conflict_renames_number_before_the_extension() {
+        let existing: HashSet<PathBuf> = ["/b/a.txt", "/b/a_1.txt", "/b/notes"].iter().map(PathBuf::from).collect();
+        assert_eq!(unique_remote(Path::new("/b/a.txt"), &existing), Path::new("/b/a_2.txt"));
+        assert_eq!(unique_remote(Path::new("/b/notes"), &existing), Path::new("/b/notes_1"));
+        assert_eq!(unique_remote(Path::new("/a.tar.gz"), &existing), Path::new("/a.tar_1.gz"));
+        let taken = |p: &Path| existing.contains(p);
+        assert_eq!(unique_local_where(Path::new("/b/a.txt"), taken), Path::new("/b/a_2.txt"));
+        let dir = std::env::temp_dir().join(format!("kosmokopy_destpath_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), b"").unwrap();
+        std::fs::write(dir.join("a_1.txt"), b"").unwrap();
+        assert_eq!(unique_local(&dir.join("a.txt")), dir.join("a_2.txt"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn names_that_are_not_utf8_keep_their_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+        let file = Path::new(OsStr::from_bytes(b"/data/Holiday Photos/caf\xe9.txt"));
+        let folder = Path::new(FOLDER);
+        let input = PlanInput::listed(file, Some(folder), TransferMode::FoldersAndFiles, 0, Dest::Remote(REMOTE_BASE));
+        let placed = build_destination(&input, Path::to_path_buf).unwrap().into_remote();
+        assert_eq!(placed.as_os_str().as_bytes(), b"/srv/My Backups/Holiday Photos/caf\xe9.txt");
+        let existing = HashSet::from([placed.clone()]);
+        assert_eq!(unique_remote(&placed, &existing).as_os_str().as_bytes(), b"/srv/My Backups/Holiday Photos/caf\xe9_1.txt");
+    }
+}
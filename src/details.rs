@@ -0,0 +1,237 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::coalesce::group_thousands;
+use crate::paths::{self, Dir};
+use crate::report::{FileOutcome, Tally};
+
+// ── Bounded detail lists ───────────────────────────────────────────────
+//
+// A re-sync of millions of files that are nearly all identical at the
+// destination would otherwise hold one message per file until the run
+// ends, and hand them all to the result dialog.  Workers collect skips and
+// errors in a `Details` instead: every entry is counted, but only the
+// first few thousand are kept.  With `KOSMOKOPY_DEBUG` set, the rest are
+// written to a file in the state directory as they arrive, so nothing is
+// lost when a full list is wanted.
+//
+// The per-folder counts and the failed-files list are worked out from the
+// entries kept, so past the limit they cover only those.  The counts by
+// outcome take in every entry.
+
+/// Entries kept per list unless `--detail-limit` says otherwise.
+pub const DEFAULT_LIMIT: usize = 10_000;
+
+static LIMIT: AtomicUsize = AtomicUsize::new(DEFAULT_LIMIT);
+
+/// Keep at most `limit` entries per list from now on (`--detail-limit`).
+pub fn set_limit(limit: usize) {
+    LIMIT.store(limit, Ordering::SeqCst);
+}
+
+/// Entries kept per list.
+pub fn limit() -> usize {
+    LIMIT.load(Ordering::SeqCst)
+}
+
+/// Skipped files or errors from one run: the first entries, and how many
+/// there were in all.
+#[derive(Debug)]
+pub struct Details {
+    /// Names the spill file, e.g. `skipped`.
+    kind: &'static str,
+    kept: Vec<String>,
+    total: usize,
+    /// Every entry, by what it says happened to its file.
+    tally: Tally,
+    limit: usize,
+    spill: Spill,
+}
+
+#[derive(Debug)]
+enum Spill {
+    /// Entries past the limit are only counted.
+    Off,
+    /// Not needed yet; opened at the first entry past the limit.
+    Ready,
+    Open(PathBuf, BufWriter<File>),
+    /// Writing failed part way; the file holds only some of the rest.
+    Broken(PathBuf),
+}
+
+impl Details {
+    pub fn skipped() -> Details {
+        Details::new("skipped")
+    }
+
+    pub fn errors() -> Details {
+        Details::new("errors")
+    }
+
+    fn new(kind: &'static str) -> Details {
+        let spill = if std::env::var_os("KOSMOKOPY_DEBUG").is_some() { Spill::Ready } else { Spill::Off };
+        Details::with_limit(kind, limit(), spill)
+    }
+
+    fn with_limit(kind: &'static str, limit: usize, spill: Spill) -> Details {
+        Details { kind, kept: Vec::new(), total: 0, tally: Tally::default(), limit, spill }
+    }
+
+    pub fn push(&mut self, entry: String) {
+        let entry = if self.kind == "errors" { crate::quota::reword(entry) } else { entry };
+        let file = FileOutcome::from_message(&entry, self.kind == "errors");
+        self.tally.add(&file.outcome);
+        crate::filereport::outcome(file, &entry);
+        self.total += 1;
+        if self.kept.len() < self.limit {
+            self.kept.push(entry);
+            return;
+        }
+        if let Spill::Ready = self.spill {
+            self.spill = match open_spill(self.kind) {
+                Ok((path, file)) => Spill::Open(path, BufWriter::new(file)),
+                Err(_) => Spill::Off,
+            };
+        }
+        if let Spill::Open(path, out) = &mut self.spill {
+            if writeln!(out, "{}", entry).is_err() {
+                self.spill = Spill::Broken(path.clone());
+            }
+        }
+    }
+
+    /// Every entry pushed, kept or not.
+    pub fn len(&self) -> usize {
+        self.total
+    }
+
+    /// Every entry pushed, by outcome.
+    pub fn tally(&self) -> Tally {
+        self.tally
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// The entries kept, oldest first.
+    pub fn shown(&self) -> &[String] {
+        &self.kept
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, String> {
+        self.kept.iter()
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.total > self.kept.len()
+    }
+
+    /// Where the entries past the limit were written, if anywhere.
+    pub fn spill_path(&self) -> Option<&Path> {
+        match &self.spill {
+            Spill::Open(path, _) | Spill::Broken(path) => Some(path),
+            Spill::Off | Spill::Ready => None,
+        }
+    }
+
+    /// Write out any entries still buffered for the spill file.
+    pub fn flush(&mut self) {
+        if let Spill::Open(path, out) = &mut self.spill {
+            if out.flush().is_err() {
+                self.spill = Spill::Broken(path.clone());
+            }
+        }
+    }
+
+    /// `showing 10,000 of 2,914,301`, with where the rest went, when
+    /// entries were left out.
+    pub fn truncation(&self) -> Option<String> {
+        if !self.is_truncated() {
+            return None;
+        }
+        let mut text = format!("showing {} of {}", group_thousands(self.kept.len()), group_thousands(self.total));
+        match &self.spill {
+            Spill::Open(path, _) => text.push_str(&format!("; the rest are in {}", path.display())),
+            Spill::Broken(path) => text.push_str(&format!("; some of the rest are in {}", path.display())),
+            Spill::Off | Spill::Ready => {}
+        }
+        Some(text)
+    }
+
+    /// A heading for the list, e.g. `Skipped (42):` or `Skipped (showing
+    /// 10,000 of 2,914,301):`.
+    pub fn heading(&self, label: &str) -> String {
+        match self.truncation() {
+            Some(text) => format!("{} ({}):", label, text),
+            None => format!("{} ({}):", label, self.total),
+        }
+    }
+}
+
+fn open_spill(kind: &str) -> io::Result<(PathBuf, File)> {
+    let stamp = crate::history::now();
+    let path = paths::file(Dir::State, &format!("{}-{}-{}.log", kind, stamp, std::process::id()))?;
+    let file = File::create(&path)?;
+    Ok((path, file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(i: usize) -> String {
+        format!("/library/photos/{:07}.jpg: identical at destination", i)
+    }
+
+    #[test]
+    fn a_million_skips_keep_only_the_limit() {
+        let mut skipped = Details::with_limit("skipped", DEFAULT_LIMIT, Spill::Off);
+        for i in 0..1_000_000 {
+            skipped.push(message(i));
+        }
+        assert_eq!(skipped.len(), 1_000_000);
+        assert_eq!(skipped.shown().len(), DEFAULT_LIMIT);
+        assert!(skipped.kept.capacity() < 2 * DEFAULT_LIMIT);
+        assert_eq!(skipped.shown().last(), Some(&message(DEFAULT_LIMIT - 1)));
+        assert_eq!(skipped.heading("Skipped"), "Skipped (showing 10,000 of 1,000,000):");
+        assert_eq!(skipped.spill_path(), None);
+    }
+
+    #[test]
+    fn short_lists_are_untouched() {
+        let mut errors = Details::with_limit("errors", 5, Spill::Off);
+        assert!(errors.is_empty());
+        for i in 0..5 {
+            errors.push(message(i));
+        }
+        assert!(!errors.is_truncated());
+        assert_eq!(errors.truncation(), None);
+        assert_eq!(errors.heading("Errors"), "Errors (5):");
+        assert_eq!(errors.iter().count(), 5);
+    }
+
+    #[test]
+    fn the_rest_spill_to_a_file() {
+        let dir = std::env::temp_dir().join(format!("kosmokopy_details_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("errors.log");
+        let file = File::create(&path).unwrap();
+        let mut errors = Details::with_limit("errors", 3, Spill::Open(path.clone(), BufWriter::new(file)));
+        for i in 0..10 {
+            errors.push(message(i));
+        }
+        errors.flush();
+        assert_eq!(errors.spill_path(), Some(path.as_path()));
+        let spilled: Vec<String> = std::fs::read_to_string(&path).unwrap().lines().map(String::from).collect();
+        assert_eq!(spilled, (3..10).map(message).collect::<Vec<_>>());
+        assert!(errors.truncation().unwrap().ends_with(&format!("the rest are in {}", path.display())));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,149 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::folders;
+
+// ── Emptied source folders ─────────────────────────────────────────────
+//
+// A move out of a source folder takes the files and leaves their folders
+// behind.  When such a move finishes with no errors, the folders its
+// files were in are removed once they are empty, deepest first, on this
+// machine or on the source host.  A folder is only ever removed empty, so
+// one still holding an excluded or skipped file, or anything the scan
+// never listed, stays; so does a folder no moved file was in.  The source
+// folder itself stays too, unless `--remove-source-root` (or the window's
+// "Remove the emptied source folder too") lets it go.  A folder in the
+// destination folder record, made by the run or written into by it, is
+// never removed, even when the destination lies inside the source.
+//
+// Only one transfer runs at a time, so the count is process-wide, like
+// the destination folder log.
+
+static REMOVED: AtomicUsize = AtomicUsize::new(0);
+
+/// Forget the count of the last run, e.g. when a new transfer starts.
+pub fn reset() {
+    REMOVED.store(0, Ordering::SeqCst);
+}
+
+/// Add `count` folders removed on another host.
+pub fn note_removed(count: usize) {
+    REMOVED.fetch_add(count, Ordering::SeqCst);
+}
+
+/// The folders removed since the last `reset`, leaving the count at zero.
+pub fn take() -> usize {
+    REMOVED.swap(0, Ordering::SeqCst)
+}
+
+/// `Removed 4 emptied source folder(s)`, or `None` when none went.
+pub fn describe(count: usize) -> Option<String> {
+    (count > 0).then(|| format!("Removed {} emptied source folder(s)", count))
+}
+
+/// The folders under `root` that held `files`, deepest first: each file's
+/// folder and those above it, up to the source folder, which is included
+/// only with `remove_root`.  Folders the run wrote into as destination
+/// folders (on `host`, for a remote source) are left out.
+pub fn candidates<'a>(
+    root: &Path,
+    host: Option<&str>,
+    remove_root: bool,
+    files: impl Iterator<Item = &'a Path>,
+) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    for file in files {
+        for dir in file.ancestors().skip(1) {
+            if !dir.starts_with(root) || dir == root {
+                break;
+            }
+            if !seen.insert(dir.to_path_buf()) {
+                break;
+            }
+        }
+    }
+    if remove_root {
+        seen.insert(root.to_path_buf());
+    }
+    let destination = |dir: &Path| match host {
+        Some(host) => folders::recorded(&format!("{}:{}", host, crate::shown(dir))),
+        None => folders::recorded(&crate::shown(dir)),
+    };
+    let mut dirs: Vec<PathBuf> = seen.into_iter().filter(|dir| !destination(dir)).collect();
+    dirs.sort_by(|a, b| b.components().count().cmp(&a.components().count()).then_with(|| a.cmp(b)));
+    dirs
+}
+
+/// Remove the local folders under `root` that moving `files` emptied,
+/// and with `remove_root` `root` too.  Returns how many went; a folder
+/// that is not empty, or can't be removed, is left where it is.
+pub fn tidy_local(root: &Path, remove_root: bool, files: &[PathBuf]) -> usize {
+    let removed = candidates(root, None, remove_root, files.iter().map(PathBuf::as_path))
+        .iter()
+        .filter(|dir| fs::remove_dir(dir).is_ok())
+        .count();
+    note_removed(removed);
+    removed
+}
+
+/// The shell loop removing `dirs` on another host where they are empty,
+/// printing a line for each that went.
+pub fn remote_script(dirs: &[PathBuf], quote: impl Fn(&OsStr) -> OsString) -> OsString {
+    let mut script = OsString::from("for d in");
+    for dir in dirs {
+        script.push(" ");
+        script.push(quote(dir.as_os_str()));
+    }
+    script.push("; do rmdir -- \"$d\" 2>/dev/null && echo; done; true");
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_emptied_folders_go_deepest_first() {
+        let root = std::env::temp_dir().join(format!("kosmokopy_emptied_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        fs::create_dir_all(root.join("kept")).unwrap();
+        fs::create_dir_all(root.join("untouched")).unwrap();
+        fs::write(root.join("kept/excluded.tmp"), "stays").unwrap();
+        let moved = vec![root.join("a/b/c/one.txt"), root.join("a/two.txt"), root.join("kept/three.txt")];
+
+        assert_eq!(
+            candidates(&root, None, false, moved.iter().map(PathBuf::as_path)),
+            vec![root.join("a/b/c"), root.join("a/b"), root.join("a"), root.join("kept")]
+        );
+        assert_eq!(tidy_local(&root, false, &moved), 3);
+        assert!(!root.join("a").exists());
+        assert!(root.join("kept/excluded.tmp").exists());
+        assert!(root.join("untouched").is_dir());
+        assert_eq!(take(), 3);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn destination_folders_stay_even_when_emptied() {
+        let root = std::env::temp_dir().join(format!("kosmokopy_emptied_dest_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("in")).unwrap();
+        fs::create_dir_all(root.join("sorted")).unwrap();
+        // Moving root/ into root/sorted/: `sorted` is written into, `sorted/in` made.
+        folders::create_all(&root.join("sorted/in")).unwrap();
+        folders::create_all(&root.join("sorted")).unwrap();
+        let moved = [root.join("in/a.txt"), root.join("sorted/in/b.txt"), root.join("sorted/c.txt")];
+
+        assert_eq!(candidates(&root, None, false, moved.iter().map(PathBuf::as_path)), vec![root.join("in")]);
+        assert_eq!(candidates(&root, Some("nas"), false, moved.iter().map(PathBuf::as_path)).len(), 3);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,474 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::coalesce::group_thousands;
+use crate::json_string;
+use crate::options::RunOptions;
+use crate::report::{FileOutcome, Outcome};
+use crate::reverify::Location;
+use crate::sample;
+use crate::symlinks;
+use crate::verify::VerifyMode;
+
+// ── Per-file detail reports ────────────────────────────────────────────
+//
+// One record per file the run dealt with — copied, renamed, skipped and
+// why, excluded and by which pattern, failed — for a spreadsheet of what
+// happened to everything.  `--detail-report report.csv` (or `.tsv`,
+// `.jsonl`) streams the records to a file as the run goes, flushed every
+// second so a crash still leaves the report up to that point.  The GUI
+// keeps them in memory instead, up to the detail limit, for the result
+// dialog's "Save detailed report…".
+//
+// Skips and errors are taken from the workers' messages, which read
+// `<source>: <reason>`, sorted as the run's report sorts them; copies and
+// exclusions are noted where they happen.  The hash column holds the
+// digest verification or the hash cache came to, as `<algorithm>:<hex>`
+// (a byte-for-byte comparison hashes as it reads while a report is
+// open), and is empty for a file that was never hashed: checked by size
+// and time, kept as a link, or left out of a `--verify-sample`.  A
+// sampled run's report says which files those were and ends with how
+// many were verified, so it doubles as the run's manifest.
+
+/// Columns, in order.
+pub const COLUMNS: [&str; 6] = ["source", "destination", "action", "reason", "size", "hash"];
+
+const FLUSH_EVERY: Duration = Duration::from_secs(1);
+
+/// The reason given for a file `--verify-sample` left unchecked.
+pub const NOT_SAMPLED: &str = "not in the verification sample";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Csv,
+    Tsv,
+    Jsonl,
+}
+
+impl Format {
+    /// The format a report path's extension asks for.
+    pub fn of(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(Format::Csv),
+            "tsv" => Some(Format::Tsv),
+            "jsonl" => Some(Format::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Copied,
+    /// Copied under another name, by the rename rules or a conflict.
+    Renamed,
+    Identical,
+    /// An existing destination file was kept.
+    ConflictSkipped,
+    Skipped,
+    Excluded,
+    /// Transferred, but verification was skipped.
+    Unverified,
+    Error,
+}
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Copied => "copied",
+            Action::Renamed => "renamed",
+            Action::Identical => "identical",
+            Action::ConflictSkipped => "conflict-skipped",
+            Action::Skipped => "skipped",
+            Action::Excluded => "excluded",
+            Action::Unverified => "unverified",
+            Action::Error => "error",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub source: String,
+    pub dest: Option<String>,
+    pub action: Action,
+    pub reason: String,
+    pub size: Option<u64>,
+    pub hash: Option<String>,
+}
+
+impl Record {
+    fn new(source: String, action: Action, reason: &str) -> Record {
+        Record { source, dest: None, action, reason: reason.to_string(), size: None, hash: None }
+    }
+
+    /// The record of `file`, giving `reason` for what became of it.
+    fn of(file: FileOutcome, reason: &str) -> Record {
+        let action = match file.outcome {
+            Outcome::Copied => Action::Copied,
+            Outcome::SkippedIdentical => Action::Identical,
+            Outcome::SkippedConflict => Action::ConflictSkipped,
+            Outcome::Skipped(_) => Action::Skipped,
+            Outcome::Excluded(_) => Action::Excluded,
+            Outcome::Failed(_) => Action::Error,
+        };
+        Record { dest: file.dst, size: file.bytes, ..Record::new(file.src, action, reason) }
+    }
+
+    /// A worker's skip or error message, `<source>: <reason>`.
+    #[cfg(test)]
+    fn from_message(message: &str, error: bool) -> Record {
+        Record::of(FileOutcome::from_message(message, error), message.split_once(": ").map_or("", |(_, r)| r))
+    }
+
+    /// The record as one line of `format`, without the line ending.
+    pub fn to_line(&self, format: Format) -> String {
+        let size = self.size.map(|s| s.to_string()).unwrap_or_default();
+        let fields = [
+            self.source.as_str(),
+            self.dest.as_deref().unwrap_or(""),
+            self.action.name(),
+            self.reason.as_str(),
+            size.as_str(),
+            self.hash.as_deref().unwrap_or(""),
+        ];
+        match format {
+            Format::Csv => fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","),
+            Format::Tsv => fields.iter().map(|f| tsv_field(f)).collect::<Vec<_>>().join("\t"),
+            Format::Jsonl => format!(
+                "{{\"source\":{},\"destination\":{},\"action\":\"{}\",\"reason\":{},\"size\":{},\"hash\":{}}}",
+                json_string(&self.source),
+                self.dest.as_deref().map_or("null".to_string(), json_string),
+                self.action.name(),
+                json_string(&self.reason),
+                self.size.map_or("null".to_string(), |s| s.to_string()),
+                self.hash.as_deref().map_or("null".to_string(), json_string)
+            ),
+        }
+    }
+}
+
+/// Quoted, with quotes doubled, when it holds a comma, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Tabs, line breaks and backslashes written `\t`, `\n`, `\r`, `\\`.
+fn tsv_field(field: &str) -> String {
+    field.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+/// The header line, or none for JSON Lines.
+fn header(format: Format) -> Option<String> {
+    match format {
+        Format::Csv => Some(COLUMNS.join(",")),
+        Format::Tsv => Some(COLUMNS.join("\t")),
+        Format::Jsonl => None,
+    }
+}
+
+struct Stream {
+    format: Format,
+    out: BufWriter<File>,
+    written: usize,
+    flushed: Instant,
+    failed: Option<io::Error>,
+}
+
+/// The GUI's records of the current run: the first `limit`, and how many
+/// there were in all.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Kept {
+    pub records: Vec<Record>,
+    pub total: usize,
+    limit: usize,
+}
+
+impl Kept {
+    /// Write the records to `path`, in the format its extension asks for
+    /// (CSV otherwise).  When records were left out, a first row says so.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let format = Format::of(path).unwrap_or(Format::Csv);
+        let mut out = BufWriter::new(File::create(path)?);
+        if self.total > self.records.len() {
+            let note = format!(
+                "kept the first {} of {} records (the detail limit)",
+                group_thousands(self.records.len()),
+                group_thousands(self.total)
+            );
+            match format {
+                Format::Jsonl => writeln!(out, "{{\"truncated\":{}}}", json_string(&note))?,
+                _ => writeln!(out, "# {}", note)?,
+            }
+        }
+        if let Some(header) = header(format) {
+            writeln!(out, "{}", header)?;
+        }
+        for record in &self.records {
+            writeln!(out, "{}", record.to_line(format))?;
+        }
+        out.flush()
+    }
+}
+
+/// Set while a stream or the GUI's record is open, so that runs without a
+/// report skip the locking.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static STREAM: Mutex<Option<Stream>> = Mutex::new(None);
+static KEPT: Mutex<Option<Kept>> = Mutex::new(None);
+/// Digests taken of files whose records are still to come, by location.
+static HASHES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Stream this run's records to `path` (`--detail-report`).
+pub fn open(path: &Path) -> io::Result<()> {
+    let format = Format::of(path).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "the report must end in .csv, .tsv or .jsonl")
+    })?;
+    let mut out = BufWriter::new(File::create(path)?);
+    if let Some(header) = header(format) {
+        writeln!(out, "{}", header)?;
+    }
+    out.flush()?;
+    if let Ok(mut stream) = STREAM.lock() {
+        *stream = Some(Stream { format, out, written: 0, flushed: Instant::now(), failed: None });
+    }
+    ACTIVE.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Finish the stream, after a last row with how many files a sampled run
+/// verified: how many records it holds, or why writing failed.
+pub fn close(verify_sample: Option<&str>) -> Option<io::Result<usize>> {
+    let mut stream = STREAM.lock().ok()?.take()?;
+    settle_active(KEPT.lock().is_ok_and(|k| k.is_some()));
+    if let (Some(note), None) = (verify_sample, &stream.failed) {
+        let written = match stream.format {
+            Format::Jsonl => writeln!(stream.out, "{{\"verify_sample\":{}}}", json_string(note)),
+            _ => writeln!(stream.out, "# {}", note),
+        };
+        stream.failed = written.err();
+    }
+    Some(match (stream.failed.take(), stream.out.flush()) {
+        (Some(e), _) | (None, Err(e)) => Err(e),
+        (None, Ok(())) => Ok(stream.written),
+    })
+}
+
+/// Keep this run's records in memory, the first `limit` of them.
+pub fn keep(limit: usize) {
+    if let Ok(mut kept) = KEPT.lock() {
+        *kept = Some(Kept { limit, ..Kept::default() });
+    }
+    ACTIVE.store(true, Ordering::SeqCst);
+}
+
+/// The records kept since `keep`; keeping stops.
+pub fn take() -> Kept {
+    let kept = KEPT.lock().ok().and_then(|mut k| k.take()).unwrap_or_default();
+    settle_active(STREAM.lock().is_ok_and(|s| s.is_some()));
+    kept
+}
+
+/// Whether this run's records are being kept or written.
+pub fn recording() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Whether records are still wanted; digests left over are dropped once
+/// they aren't.
+fn settle_active(active: bool) {
+    ACTIVE.store(active, Ordering::SeqCst);
+    if !active {
+        if let Ok(mut hashes) = HASHES.lock() {
+            hashes.clear();
+        }
+    }
+}
+
+/// Note the digest `hex` verification took of the file at `at` with
+/// `algo`, for the hash column of its record.
+pub fn hashed(at: &Location, algo: &str, hex: &str) {
+    if ACTIVE.load(Ordering::Relaxed) {
+        if let Ok(mut hashes) = HASHES.lock() {
+            hashes.insert(at.describe(), format!("{}:{}", algo, hex));
+        }
+    }
+}
+
+/// The digest noted for the file described as `at`, if one was.
+fn take_hash(at: &str) -> Option<String> {
+    HASHES.lock().ok()?.remove(at)
+}
+
+fn note(record: Record) {
+    if let Ok(mut stream) = STREAM.lock() {
+        if let Some(stream) = stream.as_mut().filter(|s| s.failed.is_none()) {
+            let written = writeln!(stream.out, "{}", record.to_line(stream.format)).and_then(|()| {
+                if stream.flushed.elapsed() < FLUSH_EVERY {
+                    return Ok(());
+                }
+                stream.flushed = Instant::now();
+                stream.out.flush()
+            });
+            match written {
+                Ok(()) => stream.written += 1,
+                Err(e) => stream.failed = Some(e),
+            }
+        }
+    }
+    if let Ok(mut kept) = KEPT.lock() {
+        if let Some(kept) = kept.as_mut() {
+            kept.total += 1;
+            if kept.records.len() < kept.limit {
+                kept.records.push(record);
+            }
+        }
+    }
+}
+
+/// Note a skip or error from a worker: `file`, sorted from its `message`.
+pub fn outcome(file: FileOutcome, message: &str) {
+    if ACTIVE.load(Ordering::Relaxed) {
+        let hash = take_hash(&file.src);
+        note(Record { hash, ..Record::of(file, message.split_once(": ").map_or("", |(_, r)| r)) });
+    }
+}
+
+/// Note a file transferred to `dest` by a run with `opts`: renamed if its
+/// name changed.
+pub fn copied(source: &Location, dest: &Location, opts: &RunOptions) {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    let name = |loc: &Location| match loc {
+        Location::Local(path) => path.file_name().map(|n| n.to_os_string()),
+        Location::Remote { path, .. } => Path::new(path).file_name().map(|n| n.to_os_string()),
+    };
+    // A move's source is gone by now, so the size comes from whichever end
+    // is here.
+    let size = [dest, source].into_iter().find_map(|loc| match loc {
+        Location::Local(path) => fs::metadata(path).ok().map(|m| m.len()),
+        Location::Remote { .. } => None,
+    });
+    let file = FileOutcome { src: source.describe(), dst: Some(dest.describe()), outcome: Outcome::Copied, bytes: size };
+    // Verification may have noted either end, or both.
+    let [at_dest, at_source] = [dest, source].map(|at| take_hash(&at.describe()));
+    let hash = at_dest.or(at_source);
+    // A sampled run hashes every file it checks, but for links kept as
+    // links and checks by size and time.
+    let unsampled = hash.is_none()
+        && sample::report().is_some()
+        && opts.verify != VerifyMode::QuickSizeMtime
+        && !matches!(source, Location::Local(path) if symlinks::preserved(opts.symlinks, path));
+    let mut reasons: Vec<String> = Vec::new();
+    let renamed = name(dest) != name(source);
+    if renamed {
+        reasons.push(format!("renamed to {}", name(dest).unwrap_or_default().to_string_lossy()));
+    }
+    if unsampled {
+        reasons.push(NOT_SAMPLED.to_string());
+    }
+    let record = Record::of(file, &reasons.join("; "));
+    note(Record { action: if renamed { Action::Renamed } else { Action::Copied }, hash, ..record });
+}
+
+/// Note a file or folder the exclusion patterns left out; `reason` names
+/// the pattern.
+pub fn excluded(path: &Path, reason: &str) {
+    if ACTIVE.load(Ordering::Relaxed) {
+        let file = FileOutcome {
+            src: path.display().to_string(),
+            dst: None,
+            outcome: Outcome::Excluded(reason.to_string()),
+            bytes: None,
+        };
+        note(Record::of(file, reason));
+    }
+}
+
+/// Note the files transferred without verification.
+pub fn unverified(sources: &[String]) {
+    if ACTIVE.load(Ordering::Relaxed) {
+        for source in sources {
+            note(Record::new(source.clone(), Action::Unverified, "verification was skipped"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn awkward() -> Record {
+        Record {
+            source: "/in/a, \"b\"\nc.txt".to_string(),
+            dest: Some("/out/a\tb.txt".to_string()),
+            action: Action::Renamed,
+            reason: "renamed to a\\b".to_string(),
+            size: Some(12),
+            hash: None,
+        }
+    }
+
+    #[test]
+    fn fields_are_escaped_for_each_format() {
+        assert_eq!(
+            awkward().to_line(Format::Csv),
+            "\"/in/a, \"\"b\"\"\nc.txt\",/out/a\tb.txt,renamed,renamed to a\\b,12,"
+        );
+        assert_eq!(awkward().to_line(Format::Tsv), "/in/a, \"b\"\\nc.txt\t/out/a\\tb.txt\trenamed\trenamed to a\\\\b\t12\t");
+        assert_eq!(
+            awkward().to_line(Format::Jsonl),
+            "{\"source\":\"/in/a, \\\"b\\\"\\nc.txt\",\"destination\":\"/out/a\\tb.txt\",\"action\":\"renamed\",\
+             \"reason\":\"renamed to a\\\\b\",\"size\":12,\"hash\":null}"
+        );
+    }
+
+    #[test]
+    fn messages_are_sorted_by_reason() {
+        let record = Record::from_message("/in/x.txt: identical at destination", false);
+        assert_eq!((record.source.as_str(), record.action), ("/in/x.txt", Action::Identical));
+        let record = Record::from_message("/in/y.txt: already exists at destination", false);
+        assert_eq!(record.action, Action::ConflictSkipped);
+        let record = Record::from_message("/in/z.txt: no filename", false);
+        assert_eq!((record.action, record.reason.as_str()), (Action::Skipped, "no filename"));
+        let record = Record::from_message("/in/w.txt: copy failed: disk full", true);
+        assert_eq!((record.action, record.reason.as_str()), (Action::Error, "copy failed: disk full"));
+    }
+
+    #[test]
+    fn formats_come_from_the_extension() {
+        assert_eq!(Format::of(Path::new("run.CSV")), Some(Format::Csv));
+        assert_eq!(Format::of(Path::new("run.tsv")), Some(Format::Tsv));
+        assert_eq!(Format::of(Path::new("run.jsonl")), Some(Format::Jsonl));
+        assert_eq!(Format::of(Path::new("run.txt")), None);
+    }
+
+    #[test]
+    fn a_truncated_record_says_so_first() {
+        let dir = std::env::temp_dir().join(format!("kosmokopy_filereport_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let kept = Kept { records: vec![awkward()], total: 3, limit: 1 };
+        let path = dir.join("report.csv");
+        kept.write(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# kept the first 1 of 3 records (the detail limit)\nsource,destination,action,"));
+        let path = dir.join("report.jsonl");
+        kept.write(&path).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("{\"truncated\":\"kept the first 1 of 3"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
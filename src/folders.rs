@@ -0,0 +1,453 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+// ── Destination folders ────────────────────────────────────────────────
+//
+// A run tells apart the destination folders it made, which are what to
+// look at when tidying up after it, from the ones that were already
+// there.  Every folder the workers create goes through `create_all` (or,
+// on a remote host, the `mkdir_script` they run there), which notes
+// which of them existed beforehand.  With `--chmod-dirs`, the folders a
+// run makes are given that mode explicitly, whatever the umask; folders
+// that were already there are left alone.
+//
+// Only one transfer runs at a time, so the record is process-wide, like
+// the current activity.
+//
+// The window can also make the destination while a run is still being
+// set up ("Create now" under the destination).  Those folders are held
+// as made early until a run into them starts and takes them over as its
+// own.  Any still unclaimed when the window closes are removed again if
+// they are empty, so an abandoned setup leaves nothing behind.
+
+/// No `--chmod-dirs`: new folders get whatever mode the umask gives.
+const NO_MODE: u32 = u32::MAX;
+
+static MODE: AtomicU32 = AtomicU32::new(NO_MODE);
+
+/// Give every folder made from now on `mode` (`--chmod-dirs`).
+pub fn set_mode(mode: u32) {
+    MODE.store(mode, Ordering::SeqCst);
+}
+
+/// The `--chmod-dirs` mode, if one was given.
+pub fn mode() -> Option<u32> {
+    Some(MODE.load(Ordering::SeqCst)).filter(|&m| m != NO_MODE)
+}
+
+/// Parse an octal mode such as `755` or `0750`.
+pub fn parse_mode(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if text.is_empty() || text.len() > 5 || !text.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+        return None;
+    }
+    u32::from_str_radix(text, 8).ok().filter(|&m| m <= 0o7777)
+}
+
+/// The destination folders a run made, and how many it wrote into that
+/// were already there.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FolderLog {
+    /// Parents first; `host:path` on a remote host.
+    pub created: Vec<String>,
+    pub reused: usize,
+    seen: HashSet<String>,
+}
+
+impl FolderLog {
+    fn note_created(&mut self, name: String) {
+        if self.seen.insert(name.clone()) {
+            self.created.push(name);
+        }
+    }
+
+    fn note_reused(&mut self, name: String) {
+        if self.seen.insert(name) {
+            self.reused += 1;
+        }
+    }
+
+    /// `Created 3 folder(s), 12 already existed`, or `None` when the run
+    /// wrote into no folder at all.
+    pub fn describe(&self) -> Option<String> {
+        match (self.created.len(), self.reused) {
+            (0, 0) => None,
+            (0, reused) => Some(format!("{} folder(s) already existed", reused)),
+            (created, 0) => Some(format!("Created {} folder(s)", created)),
+            (created, reused) => Some(format!("Created {} folder(s), {} already existed", created, reused)),
+        }
+    }
+}
+
+fn log() -> &'static Mutex<FolderLog> {
+    static LOG: OnceLock<Mutex<FolderLog>> = OnceLock::new();
+    LOG.get_or_init(Mutex::default)
+}
+
+/// Forget the folders of the last run, e.g. when a new transfer starts.
+pub fn reset() {
+    if let Ok(mut log) = log().lock() {
+        *log = FolderLog::default();
+    }
+}
+
+/// The folders noted since the last `reset`, leaving the record empty.
+pub fn take() -> FolderLog {
+    log().lock().map(|mut log| std::mem::take(&mut *log)).unwrap_or_default()
+}
+
+/// Whether the run wrote into the destination folder `name` (`shown`, or
+/// `host:path` on a remote host), whether it made it or it was there.
+pub fn recorded(name: &str) -> bool {
+    log().lock().map(|log| log.seen.contains(name)).unwrap_or(false)
+}
+
+/// What the hint under the destination entry points out about it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Hint {
+    /// Nothing: no destination, one that exists, or one naming a file.
+    None,
+    /// Local folders a run would have to create, parents first.
+    Missing(Vec<PathBuf>),
+    /// A remote folder, which may or may not exist yet.
+    Remote { host: String, path: String },
+}
+
+impl Hint {
+    /// What the destination (`host` and `path`, as `parse_destination`
+    /// splits it) needs before a run.  `names_file` when a single file is
+    /// sent to a path naming it, which is then no folder at all.
+    pub fn of(host: Option<&str>, path: &str, names_file: bool) -> Hint {
+        let path = path.trim();
+        if path.is_empty() || names_file || path.contains(crate::retention::DATE_TOKEN) {
+            return Hint::None;
+        }
+        if let Some(host) = host {
+            return Hint::Remote { host: host.to_string(), path: path.to_string() };
+        }
+        let mut missing: Vec<PathBuf> =
+            Path::new(path).ancestors().take_while(|p| !p.as_os_str().is_empty() && !p.exists()).map(Path::to_path_buf).collect();
+        missing.reverse();
+        // A run would refuse a destination below a file anyway.
+        match missing.first().and_then(|m| m.parent()) {
+            Some(parent) if !parent.as_os_str().is_empty() && !parent.is_dir() => Hint::None,
+            _ if missing.is_empty() => Hint::None,
+            _ => Hint::Missing(missing),
+        }
+    }
+
+    /// The hint's text, e.g. `Folder will be created` or
+    /// `3 folders will be created, from /srv/new`.
+    pub fn describe(&self) -> Option<String> {
+        match self {
+            Hint::None => None,
+            Hint::Missing(missing) if missing.len() == 1 => Some("Folder will be created".to_string()),
+            Hint::Missing(missing) => {
+                Some(format!("{} folders will be created, from {}", missing.len(), missing[0].display()))
+            }
+            Hint::Remote { host, .. } => Some(format!("Missing folders are created on {} when the run starts", host)),
+        }
+    }
+}
+
+/// `fs::create_dir_all`, noting whether `path` was already there or which
+/// of it and its parents this made, and giving those the `--chmod-dirs`
+/// mode.
+pub fn create_all(path: &Path) -> io::Result<()> {
+    create_all_in(path, &mut log().lock().unwrap_or_else(|e| e.into_inner()), mode())
+}
+
+fn create_all_in(path: &Path, log: &mut FolderLog, mode: Option<u32>) -> io::Result<()> {
+    let name = crate::shown(path);
+    if log.seen.contains(&name) && path.is_dir() {
+        return Ok(());
+    }
+    let missing: Vec<&Path> = path.ancestors().take_while(|p| !p.as_os_str().is_empty() && !p.exists()).collect();
+    fs::create_dir_all(path)?;
+    if missing.is_empty() {
+        log.note_reused(name);
+        return Ok(());
+    }
+    for dir in missing.into_iter().rev() {
+        if let Some(mode) = mode {
+            fs::set_permissions(dir, fs::Permissions::from_mode(mode))?;
+        }
+        log.note_created(crate::shown(dir));
+    }
+    Ok(())
+}
+
+fn early() -> &'static Mutex<Vec<String>> {
+    static EARLY: OnceLock<Mutex<Vec<String>>> = OnceLock::new();
+    EARLY.get_or_init(Mutex::default)
+}
+
+/// Create `path` and its missing parents before a run, as `create_all`
+/// would during one, and hold them as made early.  The number made.
+pub fn create_early(path: &Path) -> io::Result<usize> {
+    let mut made = FolderLog::default();
+    create_all_in(path, &mut made, mode())?;
+    let count = made.created.len();
+    early().lock().unwrap_or_else(|e| e.into_inner()).extend(made.created);
+    Ok(count)
+}
+
+/// Hold what `mkdir_script` made of `targets` on `host` before a run as
+/// made early.  The number made.
+pub fn note_early_remote(host: &str, targets: &HashSet<PathBuf>, stdout: &[u8]) -> usize {
+    let mut made = FolderLog::default();
+    note_remote_in(host, targets, stdout, &mut made);
+    let count = made.created.len();
+    early().lock().unwrap_or_else(|e| e.into_inner()).extend(made.created);
+    count
+}
+
+/// A run into `dst` (a local path or `host:path`) is starting: the
+/// folders made early for it, `dst` and those above it, count as made by
+/// the run.  Call after `reset`.
+pub fn adopt_early(dst: &str) {
+    let mut early = early().lock().unwrap_or_else(|e| e.into_inner());
+    let mut log = log().lock().unwrap_or_else(|e| e.into_inner());
+    adopt_early_in(dst, &mut early, &mut log);
+}
+
+fn adopt_early_in(dst: &str, early: &mut Vec<String>, log: &mut FolderLog) {
+    let dst = dst.trim().trim_end_matches('/');
+    let (claimed, rest): (Vec<String>, Vec<String>) = early
+        .drain(..)
+        .partition(|name| dst == name || dst.strip_prefix(name.as_str()).is_some_and(|below| below.starts_with('/')));
+    *early = rest;
+    for name in claimed {
+        log.note_created(name);
+    }
+}
+
+/// Remove the folders made early that no run took over, deepest first,
+/// where they are still empty.  Local ones are removed here; those on a
+/// remote host are handed to `remove_remote` with the host and the paths
+/// to `rmdir` there, in order.
+pub fn remove_unclaimed(remove_remote: impl Fn(&str, &[String])) {
+    let unclaimed = std::mem::take(&mut *early().lock().unwrap_or_else(|e| e.into_inner()));
+    remove_in(unclaimed, remove_remote);
+}
+
+fn remove_in(unclaimed: Vec<String>, remove_remote: impl Fn(&str, &[String])) {
+    let mut remote: Vec<(String, Vec<String>)> = Vec::new();
+    for name in unclaimed.into_iter().rev() {
+        match name.split_once(':').filter(|(host, _)| !host.contains('/')) {
+            Some((host, path)) => match remote.iter_mut().find(|(h, _)| h == host) {
+                Some((_, paths)) => paths.push(path.to_string()),
+                None => remote.push((host.to_string(), vec![path.to_string()])),
+            },
+            // `remove_dir` leaves a folder that has anything in it.
+            None => {
+                let _ = fs::remove_dir(&name);
+            }
+        }
+    }
+    for (host, paths) in remote {
+        remove_remote(&host, &paths);
+    }
+}
+
+/// Every folder in `targets` and above them, parents first.
+fn with_parents(targets: &HashSet<PathBuf>) -> Vec<PathBuf> {
+    let mut all: HashSet<&Path> = HashSet::new();
+    for target in targets {
+        for dir in target.ancestors() {
+            if matches!(dir.as_os_str().as_bytes(), b"" | b"/" | b"." | b"..") {
+                break;
+            }
+            all.insert(dir);
+        }
+    }
+    let mut all: Vec<PathBuf> = all.into_iter().map(Path::to_path_buf).collect();
+    all.sort_by(|a, b| a.components().count().cmp(&b.components().count()).then_with(|| a.cmp(b)));
+    all
+}
+
+/// The remote command that creates `targets` like `mkdir -p` in one call,
+/// printing each folder it makes (NUL-terminated) so no listing is needed
+/// afterwards.  `quote` is the shell quoting the workers use.
+pub fn mkdir_script(targets: &HashSet<PathBuf>, quote: impl Fn(&OsStr) -> OsString) -> OsString {
+    let chmod = match mode() {
+        Some(mode) => format!(" && chmod {:o} \"$d\"", mode),
+        None => String::new(),
+    };
+    let mut script = OsString::from("s=0; for d in");
+    for dir in with_parents(targets) {
+        script.push(" ");
+        script.push(quote(dir.as_os_str()));
+    }
+    script.push(format!(
+        "; do [ -d \"$d\" ] && continue; \
+         {{ mkdir -p \"$d\"{} && printf '%s\\0' \"$d\"; }} || s=1; done; exit $s",
+        chmod
+    ));
+    script
+}
+
+/// Note what `mkdir_script` printed for `targets` on `host`: the folders
+/// it made, and so which targets were already there.
+pub fn note_remote(host: &str, targets: &HashSet<PathBuf>, stdout: &[u8]) {
+    if let Ok(mut log) = log().lock() {
+        note_remote_in(host, targets, stdout, &mut log);
+    }
+}
+
+fn note_remote_in(host: &str, targets: &HashSet<PathBuf>, stdout: &[u8], log: &mut FolderLog) {
+    let made: HashSet<&Path> =
+        stdout.split(|&b| b == 0).filter(|d| !d.is_empty()).map(|d| Path::new(OsStr::from_bytes(d))).collect();
+    for dir in with_parents(targets) {
+        if made.contains(dir.as_path()) {
+            log.note_created(format!("{}:{}", host, crate::shown(&dir)));
+        }
+    }
+    let mut reused: Vec<&PathBuf> = targets.iter().filter(|t| !made.contains(t.as_path())).collect();
+    reused.sort();
+    for dir in reused {
+        log.note_reused(format!("{}:{}", host, crate::shown(dir)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    fn mixed_trees_tell_made_from_existing() {
+        let root = std::env::temp_dir().join(format!("kosmokopy_folders_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("photos/2025")).unwrap();
+        fs::set_permissions(root.join("photos"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut log = FolderLog::default();
+        for dir in ["photos", "photos/2025", "photos/2026/03", "photos/2026/04", "photos/2026/03", "photos/2025"] {
+            create_all_in(&root.join(dir), &mut log, Some(0o750)).unwrap();
+        }
+        let name = |d: &str| root.join(d).display().to_string();
+        assert_eq!(log.created, vec![name("photos/2026"), name("photos/2026/03"), name("photos/2026/04")]);
+        assert_eq!(log.reused, 2);
+        assert_eq!(log.describe().as_deref(), Some("Created 3 folder(s), 2 already existed"));
+
+        // The mode is set outright on new folders only.
+        let mode = |d: &str| fs::metadata(root.join(d)).unwrap().mode() & 0o7777;
+        assert_eq!(mode("photos/2026"), 0o750);
+        assert_eq!(mode("photos/2026/04"), 0o750);
+        assert_eq!(mode("photos"), 0o755);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn remote_folders_come_from_the_mkdir_output() {
+        let targets: HashSet<PathBuf> = ["/srv/backup", "/srv/backup/a", "/srv/backup/b/c"].iter().map(PathBuf::from).collect();
+        let all = ["/srv", "/srv/backup", "/srv/backup/a", "/srv/backup/b", "/srv/backup/b/c"];
+        assert_eq!(with_parents(&targets), all.map(PathBuf::from));
+
+        let mut log = FolderLog::default();
+        note_remote_in("nas", &targets, b"/srv/backup/b\0/srv/backup/b/c\0", &mut log);
+        assert_eq!(log.created, vec!["nas:/srv/backup/b", "nas:/srv/backup/b/c"]);
+        assert_eq!(log.reused, 2);
+
+        let script = mkdir_script(&targets, |s| {
+            let mut quoted = OsString::from("'");
+            quoted.push(s);
+            quoted.push("'");
+            quoted
+        });
+        let script = script.into_string().unwrap();
+        assert!(script.starts_with("s=0; for d in '/srv' '/srv/backup' '/srv/backup/a' '/srv/backup/b' "));
+        assert!(script.ends_with("done; exit $s"));
+
+        // Folder names that aren't UTF-8 are made with their bytes.
+        let odd = HashSet::from([PathBuf::from(OsStr::from_bytes(b"/srv/caf\xe9"))]);
+        let script = mkdir_script(&odd, OsStr::to_os_string);
+        assert!(script.as_bytes().windows(9).any(|w| w == b"/srv/caf\xe9"));
+        let mut log = FolderLog::default();
+        note_remote_in("nas", &odd, b"/srv/caf\xe9\0", &mut log);
+        assert_eq!(log.created, vec!["nas:/srv/caf\\xe9"]);
+    }
+
+    #[test]
+    fn hints_point_out_missing_destinations() {
+        let root = std::env::temp_dir().join(format!("kosmokopy_hint_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("notes.txt"), "x").unwrap();
+        let at = |rel: &str| root.join(rel).display().to_string();
+
+        assert_eq!(Hint::of(None, &at("new"), false), Hint::Missing(vec![root.join("new")]));
+        assert_eq!(Hint::of(None, &at("new"), false).describe().as_deref(), Some("Folder will be created"));
+        let deep = Hint::of(None, &at("a/b/c"), false);
+        assert_eq!(deep, Hint::Missing(vec![root.join("a"), root.join("a/b"), root.join("a/b/c")]));
+        assert_eq!(deep.describe(), Some(format!("3 folders will be created, from {}", at("a"))));
+        assert_eq!(Hint::of(None, &root.display().to_string(), false), Hint::None);
+        assert_eq!(Hint::of(None, "", false), Hint::None);
+        assert_eq!(Hint::of(None, &at("a.conf.new"), true), Hint::None);
+        assert_eq!(Hint::of(None, &at("notes.txt/sub"), false), Hint::None);
+        assert_eq!(Hint::of(None, &at("weekly-{date}"), false), Hint::None);
+        assert_eq!(
+            Hint::of(Some("nas"), "/srv/new", false),
+            Hint::Remote { host: "nas".to_string(), path: "/srv/new".to_string() }
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn folders_made_early_are_claimed_or_removed() {
+        let root = std::env::temp_dir().join(format!("kosmokopy_early_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let name = |d: &str| root.join(d).display().to_string();
+        let mut made = FolderLog::default();
+        create_all_in(&root.join("used/deeper"), &mut made, Some(0o700)).unwrap();
+        create_all_in(&root.join("dropped/a"), &mut made, None).unwrap();
+        create_all_in(&root.join("kept/b"), &mut made, None).unwrap();
+        fs::write(root.join("kept/b/file"), "x").unwrap();
+        let mut early = made.created;
+        early.push("nas:/srv/new".to_string());
+        early.push("nas:/srv/new/sub".to_string());
+        assert_eq!(fs::metadata(root.join("used")).unwrap().permissions().mode() & 0o7777, 0o700);
+
+        // A run into used/deeper takes over both of its folders.
+        let mut log = FolderLog::default();
+        adopt_early_in(&format!("{}/", name("used/deeper")), &mut early, &mut log);
+        assert_eq!(log.created, vec![name("used"), name("used/deeper")]);
+        // A name sharing only a prefix doesn't claim anything.
+        adopt_early_in(&name("dropped-too"), &mut early, &mut log);
+        assert_eq!(log.created.len(), 2);
+
+        let removed_remote = Mutex::new(Vec::new());
+        remove_in(early, |host, paths| removed_remote.lock().unwrap().push((host.to_string(), paths.to_vec())));
+        assert!(!root.join("dropped").exists());
+        assert!(root.join("kept/b/file").exists());
+        assert!(root.join("used/deeper").exists());
+        assert_eq!(
+            removed_remote.into_inner().unwrap(),
+            vec![("nas".to_string(), vec!["/srv/new/sub".to_string(), "/srv/new".to_string()])]
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn modes_are_octal() {
+        assert_eq!(parse_mode("755"), Some(0o755));
+        assert_eq!(parse_mode("0750"), Some(0o750));
+        assert_eq!(parse_mode("2775"), Some(0o2775));
+        for bad in ["", "rwx", "789", "17777", "-1", "+7"] {
+            assert_eq!(parse_mode(bad), None, "{:?}", bad);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+// ── Hash algorithms for verifying copies ───────────────────────────────
+//
+// A remote copy is verified by hashing it on its host and comparing that
+// with the hash of the local file.  On a fast LAN `sha256sum` of a large
+// file takes longer than sending it, so `--hash-algo` (the window's Hash
+// choice) can pick BLAKE3 (`b3sum`) or XXH128 (`xxhsum -H2`) instead.
+//
+// Those tools are rarely installed everywhere, so the first check on each
+// host asks whether its tool is there, once per run; a host without it is
+// hashed with SHA-256, as before, and the local side follows suit.  The
+// local side hashes in-process whatever the algorithm, so no local tool
+// is needed.  A failed check names the algorithm it used.
+//
+// The choice is the run's (see `options`); what each host offered is
+// process-wide until the next run starts.
+
+/// How files are hashed to compare a copy with its source.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum HashAlgo {
+    #[default]
+    Sha256,
+    Blake3,
+    Xxh128,
+}
+
+impl HashAlgo {
+    /// `--hash-algo`.
+    pub fn parse(word: &str) -> Option<HashAlgo> {
+        match word {
+            "sha256" => Some(HashAlgo::Sha256),
+            "blake3" => Some(HashAlgo::Blake3),
+            "xxh128" => Some(HashAlgo::Xxh128),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgo::Sha256 => "sha256",
+            HashAlgo::Blake3 => "blake3",
+            HashAlgo::Xxh128 => "xxh128",
+        }
+    }
+
+    /// The program hashing with it on another host, when it isn't
+    /// `sha256sum`, whose absence `shasum -a 256` covers.
+    pub fn tool(self) -> Option<&'static str> {
+        match self {
+            HashAlgo::Sha256 => None,
+            HashAlgo::Blake3 => Some("b3sum"),
+            HashAlgo::Xxh128 => Some("xxhsum"),
+        }
+    }
+
+    /// Hex digits in one of its hashes.
+    pub fn hex_len(self) -> usize {
+        match self {
+            HashAlgo::Sha256 | HashAlgo::Blake3 => 64,
+            HashAlgo::Xxh128 => 32,
+        }
+    }
+
+    /// The shell command printing `<hash>  <path>` for the one already
+    /// quoted `path` on another host.
+    pub fn remote_command_one(self, path: &str) -> String {
+        match self {
+            HashAlgo::Sha256 => format!("sha256sum {0} 2>/dev/null || shasum -a 256 {0} 2>/dev/null", path),
+            _ => self.remote_command(path),
+        }
+    }
+
+    /// The shell command printing `<hash>  <path>` for each of the
+    /// already quoted `paths` on another host.
+    pub fn remote_command(self, paths: &str) -> String {
+        match self {
+            HashAlgo::Sha256 => format!(
+                "if command -v sha256sum >/dev/null 2>&1; then sha256sum -- {0}; else shasum -a 256 -- {0}; fi 2>/dev/null",
+                paths
+            ),
+            HashAlgo::Blake3 => format!("b3sum -- {} 2>/dev/null", paths),
+            HashAlgo::Xxh128 => format!("xxhsum -H2 -- {} 2>/dev/null", paths),
+        }
+    }
+}
+
+/// What each host checked this run hashes with.
+static HOSTS: Mutex<Option<HashMap<String, HashAlgo>>> = Mutex::new(None);
+
+/// Forget what hosts offered, e.g. when a new transfer starts.
+pub fn reset() {
+    *HOSTS.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// The algorithm `host` hashes with this run: `chosen` if `has_tool`
+/// finds its program there, asked once per host, otherwise SHA-256.
+pub fn for_host(host: &str, chosen: HashAlgo, has_tool: impl FnOnce(&str) -> bool) -> HashAlgo {
+    let Some(tool) = chosen.tool() else {
+        return chosen;
+    };
+    if let Some(&algo) = HOSTS.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert_with(HashMap::new).get(host) {
+        return algo;
+    }
+    // Asked without the lock held: the probe goes over ssh.
+    let algo = match has_tool(tool) {
+        true => chosen,
+        false => HashAlgo::Sha256,
+    };
+    HOSTS.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert_with(HashMap::new).insert(host.to_string(), algo);
+    algo
+}
+
+/// The hash of the local file at `path` with `algo`, in lowercase hex.
+pub fn hash_file(path: &Path, algo: HashAlgo) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut buf = [0u8; 8192];
+    let mut feed = |update: &mut dyn FnMut(&[u8])| -> io::Result<()> {
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                return Ok(());
+            }
+            update(&buf[..n]);
+        }
+    };
+    Ok(match algo {
+        HashAlgo::Sha256 => {
+            let mut hasher = Sha256::new();
+            feed(&mut |b| hasher.update(b))?;
+            format!("{:x}", hasher.finalize())
+        }
+        HashAlgo::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            feed(&mut |b| {
+                hasher.update(b);
+            })?;
+            hasher.finalize().to_hex().to_string()
+        }
+        HashAlgo::Xxh128 => {
+            let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+            feed(&mut |b| hasher.update(b))?;
+            format!("{:032x}", hasher.digest128())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_match_the_command_line_tools() {
+        let path = std::env::temp_dir().join(format!("kosmokopy_hashalgo_{}", std::process::id()));
+        fs::write(&path, "abc").unwrap();
+        // `printf abc | sha256sum`, `| b3sum` and `| xxhsum -H2`.
+        assert_eq!(
+            hash_file(&path, HashAlgo::Sha256).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash_file(&path, HashAlgo::Blake3).unwrap(),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        assert_eq!(hash_file(&path, HashAlgo::Xxh128).unwrap(), "06b05ab6733a618578af5f94892f3950");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn a_host_without_the_tool_falls_back_once() {
+        reset();
+        let mut asked = 0;
+        assert_eq!(for_host("old-nas", HashAlgo::Blake3, |_| { asked += 1; false }), HashAlgo::Sha256);
+        assert_eq!(for_host("old-nas", HashAlgo::Blake3, |_| { asked += 1; true }), HashAlgo::Sha256);
+        assert_eq!(for_host("new-nas", HashAlgo::Blake3, |tool| { asked += 1; tool == "b3sum" }), HashAlgo::Blake3);
+        assert_eq!(asked, 2);
+        assert_eq!(for_host("old-nas", HashAlgo::Sha256, |_| unreachable!()), HashAlgo::Sha256);
+        reset();
+    }
+}
@@ -0,0 +1,383 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::activity;
+use crate::filereport;
+use crate::reverify::Location;
+use crate::timeutil::Timestamp;
+
+// ── Hash cache ─────────────────────────────────────────────────────────
+//
+// Re-running a backup of a large, mostly unchanged library spends nearly
+// all its time reading both copies of every file only to find them equal.
+// With `--hash-cache`, each file compared or verified in full has its
+// SHA-256 stored under its path, size, modification time and inode.  When
+// a source and its existing destination both still match their entries
+// and the hashes agree, the file is taken as identical without being read.
+// Anything else falls back to the full comparison, which refreshes the
+// entries.  A file rewritten in place with its size and mtime restored
+// would fool the cache, so `--paranoid` records but never trusts it.
+
+const MAGIC: &str = "kosmokopy-hash-cache";
+const VERSION: u32 = 1;
+
+/// What identifies one state of a file without reading it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileKey {
+    pub size: u64,
+    /// Modification time in nanoseconds since the epoch.
+    pub mtime_ns: i128,
+    pub inode: u64,
+}
+
+impl FileKey {
+    pub fn of(meta: &fs::Metadata) -> FileKey {
+        FileKey {
+            size: meta.len(),
+            mtime_ns: meta.modified().map_or(0, |t| Timestamp::of(t).as_nanos()),
+            inode: meta.ino(),
+        }
+    }
+}
+
+type Hash = [u8; 32];
+
+/// How the cache did in one run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    /// Files taken as identical from the cache alone.
+    pub hits: usize,
+    /// Files compared in full because an entry was missing or stale (or,
+    /// with `--paranoid`, because the cache is not trusted).
+    pub misses: usize,
+    /// Why the cache file was discarded at the start, if it was.
+    pub reset: Option<String>,
+    /// Why the updated cache could not be written, if it couldn't.
+    pub save_error: Option<String>,
+}
+
+pub struct HashCache {
+    path: PathBuf,
+    trusted: bool,
+    entries: HashMap<String, (FileKey, Hash)>,
+    stats: CacheStats,
+}
+
+impl HashCache {
+    /// Load the cache at `path`; a missing, unreadable or damaged file
+    /// starts an empty one.  An untrusted cache is only written to.
+    pub fn load(path: &Path, trusted: bool) -> HashCache {
+        let mut stats = CacheStats::default();
+        let entries = match fs::read_to_string(path) {
+            Ok(text) => parse(&text).unwrap_or_else(|| {
+                stats.reset = Some(format!("hash cache {} was damaged or from another version; starting afresh", path.display()));
+                HashMap::new()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                stats.reset = Some(format!("hash cache {} could not be read ({}); starting afresh", path.display(), e));
+                HashMap::new()
+            }
+        };
+        HashCache { path: path.to_path_buf(), trusted, entries, stats }
+    }
+
+    /// Whether the files at `a` and `b` have the same contents, from the
+    /// cache when both entries are current and it is trusted.
+    pub fn files_identical(&mut self, a: &Path, b: &Path) -> io::Result<bool> {
+        let key_a = FileKey::of(&fs::metadata(a)?);
+        let key_b = FileKey::of(&fs::metadata(b)?);
+        if key_a.size != key_b.size {
+            return Ok(false);
+        }
+        if self.trusted {
+            if let (Some(ha), Some(hb)) = (self.lookup(a, key_a), self.lookup(b, key_b)) {
+                if ha == hb {
+                    self.stats.hits += 1;
+                    filereport::hashed(&Location::Local(a.to_path_buf()), "sha256", &hex(&ha));
+                    return Ok(true);
+                }
+            }
+        }
+        self.stats.misses += 1;
+        self.verify(a, b)
+    }
+
+    /// Compare `a` and `b` in full, recording both when they match.
+    pub fn verify(&mut self, a: &Path, b: &Path) -> io::Result<bool> {
+        let (same, matched) = compare_in_full(a, b)?;
+        if let Some(matched) = matched {
+            self.remember(matched);
+        }
+        Ok(same)
+    }
+
+    /// Record a comparison made away from the cache.
+    pub fn remember(&mut self, matched: Matched) {
+        filereport::hashed(&Location::Local(matched.a.clone()), "sha256", &hex(&matched.hash));
+        for (path, key) in [(&matched.a, matched.keys.0), (&matched.b, matched.keys.1)] {
+            self.record(path, key, matched.hash);
+        }
+    }
+
+    fn lookup(&self, path: &Path, key: FileKey) -> Option<Hash> {
+        let (stored, hash) = self.entries.get(path.to_str()?)?;
+        (*stored == key).then_some(*hash)
+    }
+
+    fn record(&mut self, path: &Path, key: FileKey, hash: Hash) {
+        // Paths that aren't UTF-8 or hold a newline can't be written back.
+        if let Some(p) = path.to_str().filter(|p| !p.contains('\n')) {
+            self.entries.insert(p.to_string(), (key, hash));
+        }
+    }
+
+    /// Write the cache back (via a temporary file, so an interrupted write
+    /// leaves the old one) and return the run's statistics.
+    pub fn save(mut self) -> CacheStats {
+        let tmp = self.path.with_extension("tmp");
+        let result = self
+            .path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&tmp, serialize(&self.entries)))
+            .and_then(|_| fs::rename(&tmp, &self.path));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&tmp);
+            self.stats.save_error = Some(format!("hash cache {} could not be saved: {}", self.path.display(), e));
+        }
+        self.stats
+    }
+}
+
+/// Two files found equal, for [`HashCache::remember`].
+#[derive(Debug)]
+pub struct Matched {
+    a: PathBuf,
+    b: PathBuf,
+    keys: (FileKey, FileKey),
+    hash: Hash,
+}
+
+/// Compare `a` and `b` in full without the cache, e.g. on a thread of the
+/// pool while the run holds it: whether they are equal, and what the
+/// cache may remember of them.
+pub fn compare_in_full(a: &Path, b: &Path) -> io::Result<(bool, Option<Matched>)> {
+    let before = (FileKey::of(&fs::metadata(a)?), FileKey::of(&fs::metadata(b)?));
+    let Some(hash) = compare_and_hash(a, b)? else {
+        return Ok((false, None));
+    };
+    // A file that changed while it was read is not worth remembering.
+    let after = (FileKey::of(&fs::metadata(a)?), FileKey::of(&fs::metadata(b)?));
+    let matched = (before == after).then(|| Matched { a: a.to_path_buf(), b: b.to_path_buf(), keys: before, hash });
+    Ok((true, matched))
+}
+
+/// Read `a` and `b` side by side; the SHA-256 of their contents if they
+/// are byte-for-byte equal.
+pub fn compare_and_hash(a: &Path, b: &Path) -> io::Result<Option<Hash>> {
+    activity::set(&format!("comparing {} with {}", a.display(), b.display()));
+    let mut fa = fs::File::open(a)?;
+    let mut fb = fs::File::open(b)?;
+    let mut buf_a = vec![0u8; 64 * 1024];
+    let mut buf_b = vec![0u8; 64 * 1024];
+    let mut hasher = Sha256::new();
+    loop {
+        let n_a = read_full(&mut fa, &mut buf_a)?;
+        let n_b = read_full(&mut fb, &mut buf_b)?;
+        if n_a != n_b || buf_a[..n_a] != buf_b[..n_b] {
+            return Ok(None);
+        }
+        if n_a == 0 {
+            return Ok(Some(hasher.finalize().into()));
+        }
+        hasher.update(&buf_a[..n_a]);
+    }
+}
+
+/// Fill `buf` unless the file ends first; the number of bytes read.
+fn read_full(f: &mut fs::File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match f.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Option<Hash> {
+    if text.len() != 64 || !text.is_ascii() {
+        return None;
+    }
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(out)
+}
+
+/// The file: a header naming the format version and the SHA-256 of the
+/// body, then one `hash size mtime inode path` line (tab-separated) per
+/// file, sorted so unchanged caches are written identically.
+fn serialize(entries: &HashMap<String, (FileKey, Hash)>) -> String {
+    let mut lines: Vec<String> = entries
+        .iter()
+        .map(|(path, (key, hash))| format!("{}\t{}\t{}\t{}\t{}\n", hex(hash), key.size, key.mtime_ns, key.inode, path))
+        .collect();
+    lines.sort();
+    let body = lines.concat();
+    format!("{} {} {}\n{}", MAGIC, VERSION, hex(&Sha256::digest(body.as_bytes())), body)
+}
+
+/// The entries of a cache file, or `None` if anything about it is off.
+fn parse(text: &str) -> Option<HashMap<String, (FileKey, Hash)>> {
+    let (header, body) = text.split_once('\n')?;
+    let mut words = header.split(' ');
+    if words.next()? != MAGIC || words.next()?.parse::<u32>().ok()? != VERSION {
+        return None;
+    }
+    if unhex(words.next()?)? != <Hash>::from(Sha256::digest(body.as_bytes())) || words.next().is_some() {
+        return None;
+    }
+    let mut entries = HashMap::new();
+    for line in body.lines() {
+        let mut fields = line.splitn(5, '\t');
+        let hash = unhex(fields.next()?)?;
+        let key = FileKey {
+            size: fields.next()?.parse().ok()?,
+            mtime_ns: fields.next()?.parse().ok()?,
+            inode: fields.next()?.parse().ok()?,
+        };
+        entries.insert(fields.next()?.to_string(), (key, hash));
+    }
+    Some(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kosmokopy-hashcache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn round_trips_through_the_file_format() {
+        let mut entries = HashMap::new();
+        let key = FileKey { size: 12, mtime_ns: 1_700_000_000_123_456_789, inode: 42 };
+        entries.insert("/photos/a\tb.jpg".to_string(), (key, [7u8; 32]));
+        entries.insert("/photos/c.jpg".to_string(), (FileKey { mtime_ns: -5, ..key }, [9u8; 32]));
+        let text = serialize(&entries);
+        assert!(text.starts_with("kosmokopy-hash-cache 1 "));
+        assert_eq!(parse(&text), Some(entries));
+        assert_eq!(parse(&serialize(&HashMap::new())), Some(HashMap::new()));
+    }
+
+    #[test]
+    fn damage_is_detected() {
+        let mut entries = HashMap::new();
+        entries.insert("/a".to_string(), (FileKey { size: 1, mtime_ns: 2, inode: 3 }, [1u8; 32]));
+        let text = serialize(&entries);
+        assert_eq!(parse(&text.replace("\t1\t2\t3\t", "\t1\t2\t4\t")), None);
+        assert_eq!(parse(&text.replace("cache 1 ", "cache 2 ")), None);
+        assert_eq!(parse(&text[..text.len() - 3]), None);
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("not a cache\n"), None);
+    }
+
+    #[test]
+    fn warm_comparisons_skip_reading() {
+        let dir = scratch("warm");
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        fs::write(&a, b"same contents").unwrap();
+        fs::write(&b, b"same contents").unwrap();
+        let cache_path = dir.join("cache");
+
+        let mut cache = HashCache::load(&cache_path, true);
+        assert!(cache.files_identical(&a, &b).unwrap());
+        let stats = cache.save();
+        assert_eq!((stats.hits, stats.misses, stats.reset), (0, 1, None));
+
+        let mut cache = HashCache::load(&cache_path, true);
+        assert!(cache.files_identical(&a, &b).unwrap());
+        assert_eq!(cache.save().hits, 1);
+
+        // Untrusted, the cache is only written to.
+        let mut cache = HashCache::load(&cache_path, false);
+        assert!(cache.files_identical(&a, &b).unwrap());
+        assert_eq!((cache.stats.hits, cache.stats.misses), (0, 1));
+
+        // A changed file misses and is compared for real.
+        fs::write(&b, b"other content").unwrap();
+        let mut cache = HashCache::load(&cache_path, true);
+        assert!(!cache.files_identical(&a, &b).unwrap());
+        assert_eq!((cache.stats.hits, cache.stats.misses), (0, 1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn comparisons_made_away_from_the_cache_are_remembered() {
+        let dir = scratch("away");
+        let (a, b, c) = (dir.join("a"), dir.join("b"), dir.join("c"));
+        fs::write(&a, b"copied").unwrap();
+        fs::write(&b, b"copied").unwrap();
+        fs::write(&c, b"other!").unwrap();
+        assert!(matches!(compare_in_full(&a, &c).unwrap(), (false, None)));
+        let (same, matched) = compare_in_full(&a, &b).unwrap();
+        assert!(same);
+
+        let mut cache = HashCache::load(&dir.join("cache"), true);
+        cache.remember(matched.unwrap());
+        assert!(cache.files_identical(&a, &b).unwrap());
+        assert_eq!((cache.stats.hits, cache.stats.misses), (1, 0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_damaged_file_starts_afresh() {
+        let dir = scratch("damaged");
+        let cache_path = dir.join("cache");
+        fs::write(&cache_path, "kosmokopy-hash-cache 1 00\nrubbish\n").unwrap();
+        let cache = HashCache::load(&cache_path, true);
+        assert!(cache.entries.is_empty());
+        assert!(cache.stats.reset.as_deref().unwrap().contains("starting afresh"));
+        assert!(HashCache::load(&dir.join("missing"), true).stats.reset.is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compares_across_buffer_boundaries() {
+        let dir = scratch("compare");
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        let mut data = vec![1u8; 200 * 1024];
+        fs::write(&a, &data).unwrap();
+        fs::write(&b, &data).unwrap();
+        let hash = compare_and_hash(&a, &b).unwrap().unwrap();
+        assert_eq!(hash, <Hash>::from(Sha256::digest(&data)));
+        data[150 * 1024] = 2;
+        fs::write(&b, &data).unwrap();
+        assert_eq!(compare_and_hash(&a, &b).unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
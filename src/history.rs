@@ -0,0 +1,291 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::fs;
+use std::io;
+
+use sha2::{Digest, Sha256};
+
+use crate::paths::{self, Dir};
+use crate::timeutil::Timestamp;
+
+// ── Run history ────────────────────────────────────────────────────────
+//
+// Each completed run leaves one line in the state directory: when it
+// finished, how many files it copied, and a fingerprint of what it was
+// asked to do.  Starting the same transfer again within a few hours is
+// far more often a mistake (a forgotten second launch of last night's
+// backup) than intended, so a new run whose fingerprint matches a recent
+// record is pointed out before it starts.
+//
+// The fingerprint is taken over `TransferOptions` after normalizing it,
+// so `/data/` and `/data`, `Host:/x` and `host:/x`, or the same patterns
+// in another order still count as the same transfer.
+
+const FILE_NAME: &str = "history.log";
+
+/// Only the newest records are kept; the file is rewritten on each run.
+const MAX_RECORDS: usize = 500;
+
+/// How long after a run an identical one is pointed out, unless
+/// configured otherwise.
+pub const DEFAULT_WINDOW_HOURS: u64 = 12;
+
+/// What a transfer was asked to do, as far as its outcome is concerned.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransferOptions {
+    /// The source folder, or each picked file; `host:path` when remote.
+    pub sources: Vec<String>,
+    /// The destination folder; `host:path` when remote.
+    pub destination: String,
+    pub do_move: bool,
+    /// Only matters when moving.
+    pub move_strategy: &'static str,
+    pub conflict: &'static str,
+    pub mode: &'static str,
+    pub method: &'static str,
+    /// Rename rules, in the order they run.
+    pub rename_rules: Vec<String>,
+    /// The name a single file is given when the destination names it.
+    pub file_name: Option<String>,
+    pub keep_parents: usize,
+    pub patterns: Vec<String>,
+}
+
+impl TransferOptions {
+    /// The same options with formatting differences removed.
+    pub fn normalized(&self) -> TransferOptions {
+        let mut sources: Vec<String> = self.sources.iter().map(|s| normalize_location(s)).collect();
+        sources.sort();
+        sources.dedup();
+        let mut patterns: Vec<String> = self
+            .patterns
+            .iter()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+        patterns.sort();
+        patterns.dedup();
+        TransferOptions {
+            sources,
+            destination: normalize_location(&self.destination),
+            move_strategy: if self.do_move { self.move_strategy } else { "" },
+            patterns,
+            ..self.clone()
+        }
+    }
+
+    /// A hex SHA-256 of the normalized options.
+    pub fn fingerprint(&self) -> String {
+        let o = self.normalized();
+        let mut hasher = Sha256::new();
+        // One field per line, each list item on its own line, so no two
+        // different option sets can produce the same text.
+        let mut line = |key: &str, value: &str| hasher.update(format!("{}={}\n", key, value.escape_default()));
+        for source in &o.sources {
+            line("source", source);
+        }
+        line("destination", &o.destination);
+        line("move", &o.do_move.to_string());
+        line("move_strategy", o.move_strategy);
+        line("conflict", o.conflict);
+        line("mode", o.mode);
+        line("method", o.method);
+        for rule in &o.rename_rules {
+            line("rename", rule);
+        }
+        if let Some(name) = &o.file_name {
+            line("file_name", name);
+        }
+        line("keep_parents", &o.keep_parents.to_string());
+        for pattern in &o.patterns {
+            line("pattern", pattern);
+        }
+        hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Tidy a local path or `host:path` without touching the filesystem:
+/// repeated and trailing slashes and `.` components go, and the host name
+/// (not the user) is lower-cased.  `..` is kept, since it can't be resolved
+/// safely without following symlinks.
+fn normalize_location(text: &str) -> String {
+    let text = text.trim();
+    if let Some((host, path)) = text.split_once(':').filter(|(h, _)| !h.is_empty() && !h.contains('/')) {
+        let host = match host.rsplit_once('@') {
+            Some((user, name)) => format!("{}@{}", user, name.to_lowercase()),
+            None => host.to_lowercase(),
+        };
+        return format!("{}:{}", host, normalize_path(path));
+    }
+    normalize_path(text)
+}
+
+fn normalize_path(path: &str) -> String {
+    let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty() && *p != ".").collect();
+    let joined = parts.join("/");
+    if path.starts_with('/') {
+        format!("/{}", joined)
+    } else if joined.is_empty() {
+        ".".to_string()
+    } else {
+        joined
+    }
+}
+
+/// One completed run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// Seconds since the epoch.
+    pub finished: u64,
+    pub copied: usize,
+    pub fingerprint: String,
+}
+
+/// Parse the history file, one `finished<TAB>copied<TAB>fingerprint`
+/// line per run; lines that don't fit are ignored.
+pub fn parse(text: &str) -> Vec<Record> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let record = Record {
+                finished: fields.next()?.parse().ok()?,
+                copied: fields.next()?.parse().ok()?,
+                fingerprint: fields.next()?.to_string(),
+            };
+            fields.next().is_none().then_some(record)
+        })
+        .collect()
+}
+
+fn to_text(records: &[Record]) -> String {
+    records
+        .iter()
+        .map(|r| format!("{}\t{}\t{}\n", r.finished, r.copied, r.fingerprint))
+        .collect()
+}
+
+/// The newest run with `fingerprint` that finished no more than
+/// `window_hours` before `now`.  A window of 0 turns the check off.
+pub fn recent_duplicate<'a>(records: &'a [Record], fingerprint: &str, now: u64, window_hours: u64) -> Option<&'a Record> {
+    if window_hours == 0 {
+        return None;
+    }
+    let since = now.saturating_sub(window_hours * 3600);
+    records
+        .iter()
+        .filter(|r| r.fingerprint == fingerprint && r.finished >= since && r.finished <= now)
+        .max_by_key(|r| r.finished)
+}
+
+pub fn now() -> u64 {
+    Timestamp::now().secs.max(0) as u64
+}
+
+/// Every recorded run, oldest first; none when there is no history yet.
+pub fn load() -> Vec<Record> {
+    paths::dir(Dir::State)
+        .and_then(|d| fs::read_to_string(d.join(FILE_NAME)).ok())
+        .map(|text| parse(&text))
+        .unwrap_or_default()
+}
+
+/// Record a run that has just finished, dropping the oldest records
+/// beyond `MAX_RECORDS`.
+pub fn record(fingerprint: &str, copied: usize) -> io::Result<()> {
+    let mut records = load();
+    records.push(Record { finished: now(), copied, fingerprint: fingerprint.to_string() });
+    let excess = records.len().saturating_sub(MAX_RECORDS);
+    fs::write(paths::file(Dir::State, FILE_NAME)?, to_text(&records[excess..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> TransferOptions {
+        TransferOptions {
+            sources: vec!["/home/ann/photos".to_string()],
+            destination: "backup@NAS:/srv/photos".to_string(),
+            do_move: false,
+            move_strategy: "per-file",
+            conflict: "skip",
+            mode: "folders",
+            method: "standard",
+            rename_rules: vec!["case-fold-extension".to_string()],
+            file_name: None,
+            keep_parents: 0,
+            patterns: vec!["~*.tmp".to_string(), "/cache".to_string()],
+        }
+    }
+
+    #[test]
+    fn formatting_differences_still_match() {
+        let mut same = options();
+        same.sources = vec!["/home//ann/./photos/".to_string()];
+        same.destination = " backup@nas:/srv/photos/ ".to_string();
+        same.patterns = vec!["/cache".to_string(), " ~*.tmp".to_string(), "/cache".to_string()];
+        // The move strategy is ignored when copying.
+        same.move_strategy = "all-then-delete";
+        assert_eq!(same.fingerprint(), options().fingerprint());
+
+        let mut files = options();
+        files.sources = vec!["/a/x".to_string(), "/a/y".to_string()];
+        let mut reordered = files.clone();
+        reordered.sources.reverse();
+        assert_eq!(files.fingerprint(), reordered.fingerprint());
+    }
+
+    #[test]
+    fn real_differences_do_not() {
+        let base = options().fingerprint();
+        let changes: Vec<fn(&mut TransferOptions)> = vec![
+            |o| o.sources = vec!["/home/ann/photo".to_string()],
+            |o| o.destination = "Backup@nas:/srv/photos".to_string(),
+            |o| o.destination = "/srv/photos".to_string(),
+            |o| o.do_move = true,
+            |o| o.conflict = "overwrite",
+            |o| o.mode = "files",
+            |o| o.method = "rsync",
+            |o| o.rename_rules.insert(0, "replace-chars/ /".to_string()),
+            |o| o.file_name = Some("photos".to_string()),
+            |o| o.keep_parents = 1,
+            |o| {
+                o.patterns.pop();
+            },
+            |o| o.sources.push("/home/ann/videos".to_string()),
+        ];
+        for change in changes {
+            let mut other = options();
+            change(&mut other);
+            assert_ne!(other.fingerprint(), base, "{:?}", other);
+        }
+        // Rename rules run in order, so their order counts.
+        let mut reordered = options();
+        reordered.rename_rules.push("date-prefix/_".to_string());
+        let mut swapped = reordered.clone();
+        swapped.rename_rules.reverse();
+        assert_ne!(reordered.fingerprint(), swapped.fingerprint());
+
+        let mut moving = options();
+        moving.do_move = true;
+        let mut two_phase = moving.clone();
+        two_phase.move_strategy = "all-then-delete";
+        assert_ne!(moving.fingerprint(), two_phase.fingerprint());
+    }
+
+    #[test]
+    fn only_recent_matching_runs_are_duplicates() {
+        let text = "1000\t5\taaa\n40000\t7\taaa\nnonsense\n41000\t9\tbbb\n50000\t3\taaa\textra\n";
+        let records = parse(text);
+        assert_eq!(records.len(), 3);
+        assert_eq!(parse(&to_text(&records)), records);
+
+        let hit = recent_duplicate(&records, "aaa", 42000, 12).unwrap();
+        assert_eq!((hit.finished, hit.copied), (40000, 7));
+        assert_eq!(recent_duplicate(&records, "aaa", 40000 + 12 * 3600 + 1, 12), None);
+        assert_eq!(recent_duplicate(&records, "aaa", 42000, 0), None);
+        assert_eq!(recent_duplicate(&records, "ccc", 42000, 12), None);
+    }
+}
@@ -5,69 +5,255 @@
 // This code was primarily authored using artificial intelligence
 // (Claude Opus 4.6 model).
 
+mod activity;
+mod announce;
+mod automethod;
+mod cancel;
+mod cli;
+mod clock;
+mod coalesce;
+mod collisions;
+mod details;
+mod config;
+mod deadline;
+mod destpath;
+mod emptied;
+mod filereport;
+mod folders;
+mod hashalgo;
+mod hashcache;
+mod history;
+mod options;
+mod owners;
+mod parallel;
+mod paths;
+mod patterns;
+mod preflight;
+mod preserve;
+mod priority;
+mod profiles;
+mod progress;
+mod quota;
+mod relay;
+mod rename;
+mod report;
+mod retention;
+mod retry;
+mod remote;
+mod reverify;
+mod rsync;
+mod sample;
+mod samehost;
+mod scanguard;
+mod selection;
+mod sourceconf;
+mod space;
+mod ssh;
+mod stale;
+mod state;
+mod stats;
+mod summary;
+mod symlinks;
+mod tarstream;
+mod throttle;
+mod timeutil;
+mod timing;
+mod trash;
+mod verify;
+
 use std::cell::{Cell, RefCell};
 use std::fs;
-use std::io::Read;
+use std::io::{IsTerminal, Read, Write};
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::rc::Rc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{mpsc, Arc};
 use std::thread;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use gtk4::glib;
+use gtk4::pango::EllipsizeMode;
 use gtk4::prelude::*;
 use gtk4::{
-    Align, Application, ApplicationWindow, Box as GtkBox, Button, CheckButton, Entry,
-    FileDialog, Label, ListBox, Orientation, PolicyType, ProgressBar, ScrolledWindow, SelectionMode,
-    Separator, TextView, Window, WrapMode,
+    Align, Application, ApplicationWindow, Box as GtkBox, Button, CheckButton, DropDown, Entry,
+    FileDialog, Grid, HeaderBar, Label, ListBox, Orientation, PasswordEntry, PolicyType, ProgressBar, ScrolledWindow, SelectionMode,
+    Separator, SpinButton, Switch, TextView, ToggleButton, Window, WrapMode,
 };
-use sha2::{Sha256, Digest};
+use gtk4::accessible::{Property, Relation, State};
 use walkdir::WalkDir;
 
+use activity::{Activity, StallWatch, TrackActivity, DEFAULT_STALL_AFTER};
+use announce::Announcer;
+use automethod::MethodChoice;
+use coalesce::{ErrorEntry, DEFAULT_COALESCE_AFTER};
+use collisions::OnCollision;
+use destpath::{parent_levels, Dest, PlanInput};
+use details::Details;
+use cancel::{CancelFlag, CancelIntent, OnCancelDuringVerify};
+use hashalgo::HashAlgo;
+use options::RunOptions;
+use hashcache::{CacheStats, HashCache};
+use history::TransferOptions;
+use patterns::PatternKind;
+use preflight::{OnUnwritable, UnwritableDir};
+use progress::{ellipsize_middle, format_bytes, format_eta, Phase, ProgressModel, RunClock, Throughput, MAX_NAME_LEN};
+use relay::{Relay, RelayChoice};
+use rename::{RenameLog, Renamer, Rule};
+use report::TransferReport;
+use retention::Template;
+use scanguard::ScanGuard;
+use selection::{Check, ItemTree};
+use stats::{GroupStats, RunStats};
+use summary::{Ending, RunSummary};
+use throttle::{Compress, Throttle};
+use timeutil::{Date, Timestamp, Timestamps};
+use verify::VerifyMode;
+
 const APP_ID: &str = "dev.kosmokopy.app";
 
+/// Print a diagnostic line to stderr when `KOSMOKOPY_DEBUG` is set.
+fn debug_log(msg: &str) {
+    if std::env::var_os("KOSMOKOPY_DEBUG").is_some() {
+        eprintln!("[kosmokopy] {}", msg);
+    }
+}
+
 // ── Source selection state ──────────────────────────────────────────────
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 enum SourceSelection {
     None,
     Directory(PathBuf),
+    DirectoryItems(PathBuf, Vec<PathBuf>), // (folder, files ticked in "Select items…")
     Files(Vec<PathBuf>),
-    Remote(String, String), // (host, remote_path)
+    Remote(String, String),           // (host, remote_path)
+    RemoteFiles(String, Vec<PathBuf>), // (host, remote_file_paths)
+    RemoteItems(String, String, Vec<PathBuf>), // (host, remote_path, files under it to retry)
+}
+
+impl SourceSelection {
+    /// `(host, remote_base, remote_files)` for remote sources.  A list of
+    /// individual remote files has no base and is always copied flat; files
+    /// with a base land where a run of the whole folder would put them.
+    fn remote_parts(&self) -> Option<(&str, &str, &[PathBuf])> {
+        match self {
+            SourceSelection::Remote(h, p) => Some((h, p, &[])),
+            SourceSelection::RemoteFiles(h, files) => Some((h, "", files)),
+            SourceSelection::RemoteItems(h, p, files) => Some((h, p, files)),
+            _ => None,
+        }
+    }
+
+    /// The folder whose top-level subfolders the results are grouped by;
+    /// `None` for individually picked files.
+    fn group_root(&self) -> Option<String> {
+        match self {
+            SourceSelection::Directory(d) | SourceSelection::DirectoryItems(d, _) => {
+                Some(d.to_string_lossy().to_string())
+            }
+            SourceSelection::Remote(_, p) | SourceSelection::RemoteItems(_, p, _) => Some(p.clone()),
+            _ => None,
+        }
+    }
 }
 
 // ── Transfer mode ──────────────────────────────────────────────────────
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum TransferMode {
     FilesOnly,
+    /// Each file under its immediate parent directory's name.
+    FilesWithParent,
     FoldersAndFiles,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum TransferMethod {
     Standard,
     Rsync,
+    /// One tar stream over ssh for a local→remote upload.
+    Tar,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum ConflictMode {
     Skip,
     Overwrite,
     Rename,
+    /// Overwrite only when the source was modified later ("Newest wins").
+    Newer,
+}
+
+/// When a move deletes its sources.
+#[derive(Clone, Copy, PartialEq)]
+enum MoveStrategy {
+    /// Delete each source as soon as its copy is verified.
+    PerFile,
+    /// Copy everything first, then delete the sources only if every file
+    /// verified, so a cancelled or failed move never leaves the files split
+    /// between the two places.
+    AllThenDelete,
 }
 
 fn main() -> glib::ExitCode {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
     if args.len() > 1 && args[1] == "--cli" {
-        std::process::exit(run_cli(&args[2..]));
+        let code = run_cli(&args[2..]);
+        ssh::close_connections();
+        std::process::exit(code);
+    }
+    // With --ssh-askpass, ssh runs this binary with the prompt as its only
+    // argument and reads the answer from stdout.
+    if std::env::var_os(ssh::ASKPASS_ENV).is_some() && args.len() == 2 && args[1] != "--ssh-askpass" {
+        std::process::exit(run_askpass(&args[1]));
+    }
+    if let Some(pos) = args.iter().position(|a| a == "--ssh-askpass") {
+        args.remove(pos);
+        match std::env::current_exe() {
+            Ok(exe) => ssh::enable_askpass(&exe),
+            Err(e) => eprintln!("--ssh-askpass: cannot locate the kosmokopy binary: {}", e),
+        }
+    }
+    let start_simple = match args.iter().position(|a| a == "--simple") {
+        Some(pos) => {
+            args.remove(pos);
+            true
+        }
+        None => false,
+    };
+    if let Some(command) = cli::meant_cli(&args) {
+        eprintln!("These are CLI options; run them with --cli first:\n  {}", command);
+        std::process::exit(1);
+    }
+    if let Some(reason) = display_problem() {
+        eprintln!("{}", cli::no_display_message(&reason));
+        std::process::exit(cli::EXIT_NO_DISPLAY);
     }
+    let settings = config::load();
+    if let Some(dir) = settings.ssh_control_dir {
+        ssh::set_control_dir(dir);
+    }
+    timeutil::set_timestamps(settings.timestamps);
     let app = Application::builder().application_id(APP_ID).build();
-    app.connect_activate(build_ui);
-    app.run()
+    app.connect_activate(move |app| build_ui(app, start_simple));
+    app.connect_shutdown(|_| {
+        folders::remove_unclaimed(remove_remote_folders);
+        ssh::close_connections();
+    });
+    app.run_with_args(&args)
+}
+
+/// Why the window cannot open here: no display in the environment, or
+/// GTK failing to start on the one there is.
+fn display_problem() -> Option<String> {
+    if let Some(reason) = cli::missing_display(|name| std::env::var(name).ok()) {
+        return Some(reason.to_string());
+    }
+    gtk4::init().err().map(|e| format!("GTK could not start ({})", e))
 }
 
 // ── CLI (headless) mode ────────────────────────────────────────────────
@@ -77,4052 +263,14816 @@ fn main() -> glib::ExitCode {
 /// Usage:
 ///   kosmokopy --cli [OPTIONS]
 ///
-/// Helper to emit CLI JSON result and return an exit code.
-fn cli_output_json(
-    status: &str,
-    copied: usize,
-    skipped: &[String],
-    excluded_files: usize,
-    excluded_dirs: usize,
-    errors: &[String],
-) -> i32 {
-    let skipped_json: Vec<String> = skipped
-        .iter()
-        .map(|s| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")))
-        .collect();
-    let errors_json: Vec<String> = errors
-        .iter()
-        .map(|s| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")))
-        .collect();
-    println!(
-        "{{\"status\":\"{}\",\"copied\":{},\"skipped\":[{}],\"excluded_files\":{},\"excluded_dirs\":{},\"errors\":[{}]}}",
-        status,
-        copied,
-        skipped_json.join(","),
+/// `--list-only`: print the files a run would transfer, one per line (or
+/// NUL-terminated), and a summary on stderr.  The list comes from the same
+/// collection code the workers use, so it matches a real run.  Local paths
+/// are written as their bytes, whether or not they are UTF-8.
+fn list_sources(source: &SourceSelection, patterns: &[String], links: symlinks::Policy, nul: bool, fail_if_empty: bool) -> i32 {
+    let listing = match source.remote_parts() {
+        Some((host, base, files)) => collect_remote_source(host, &ssh::ctl_args(host), base, files, patterns, &mut ScanGuard::unlimited()).map(
+            |(entries, excluded_files, excluded_dirs)| {
+                let entries = entries.into_iter().map(|(path, size)| (path.into_os_string().into_vec(), size)).collect();
+                (entries, excluded_files, excluded_dirs)
+            },
+        ),
+        None => collect_files(source, patterns, links, &mut ScanGuard::unlimited()).map(|(files, excluded_files, excluded_dirs)| {
+            let entries: Vec<(Vec<u8>, Option<u64>)> = files
+                .into_iter()
+                .map(|f| {
+                    let size = fs::metadata(&f).ok().map(|m| m.len());
+                    (f.into_os_string().into_vec(), size)
+                })
+                .collect();
+            (entries, excluded_files, excluded_dirs)
+        }),
+    };
+    let (entries, excluded_files, excluded_dirs) = match listing {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let sep = if nul { b'\0' } else { b'\n' };
+    let mut out = std::io::stdout().lock();
+    for (path, _) in &entries {
+        // A closed pipe (`| head`) just ends the listing.
+        if out.write_all(path).and_then(|()| out.write_all(&[sep])).is_err() {
+            break;
+        }
+    }
+    let _ = out.flush();
+
+    let bytes: u64 = entries.iter().filter_map(|(_, size)| *size).sum();
+    let unknown = entries.iter().filter(|(_, size)| size.is_none()).count();
+    let unknown_note = if unknown > 0 { format!(" ({} of unknown size)", unknown) } else { String::new() };
+    let owner_note = match owners::filtered() {
+        0 => String::new(),
+        n => format!("; {} file(s) left out by owner", n),
+    };
+    let dir_files_note = match patterns::in_excluded_dirs() {
+        Some(n) if excluded_dirs > 0 => format!(" holding {} file(s)", n),
+        _ => String::new(),
+    };
+    eprintln!(
+        "{} file(s), {}{}; excluded {} file(s), {} dir(s){}{}",
+        entries.len(),
+        format_bytes(bytes),
+        unknown_note,
         excluded_files,
         excluded_dirs,
-        errors_json.join(","),
+        dir_files_note,
+        owner_note
     );
-    if !errors.is_empty() { 2 } else { 0 }
+    if entries.is_empty() && fail_if_empty {
+        1
+    } else {
+        0
+    }
 }
 
-/// Required:
-///   --src <path|host:/path>      Source directory or remote
-///   --dst <path|host:/path>      Destination directory or remote
-///
-/// Optional:
-///   --move                       Move instead of copy
-///   --conflict <skip|overwrite|rename>   Conflict mode (default: skip)
-///   --strip-spaces               Remove spaces from filenames
-///   --mode <files|folders>       Transfer mode (default: folders)
-///   --method <standard|rsync>    Transfer method (default: standard)
-///   --exclude <pattern>          Exclusion pattern (repeatable)
-///   --src-files <file1,file2>    Comma-separated list of individual source files
-fn run_cli(args: &[String]) -> i32 {
-    let mut src: Option<String> = None;
-    let mut dst: Option<String> = None;
-    let mut do_move = false;
-    let mut conflict_mode = ConflictMode::Skip;
-    let mut strip_spaces = false;
-    let mut transfer_mode = TransferMode::FoldersAndFiles;
-    let mut transfer_method = TransferMethod::Standard;
-    let mut patterns: Vec<String> = Vec::new();
-    let mut src_files: Option<Vec<PathBuf>> = None;
+/// The files of `source` the rename rules would rename, as (before,
+/// after) paths below the destination folder, and how many files there
+/// are in all.  Paths start with the source folder's name, as in a
+/// "Folders and files" copy; picked files are shown by name.
+fn rename_preview(source: &SourceSelection, patterns: &[String], links: symlinks::Policy, renamer: &Renamer) -> Result<(Vec<(String, String)>, usize), String> {
+    // Relative to the folder holding the source folder, so its own name
+    // is renamed as the workers rename it.
+    let below = |root: Option<&Path>, file: &Path| -> PathBuf {
+        root.and_then(|r| file.strip_prefix(r).ok())
+            .or_else(|| file.file_name().map(Path::new))
+            .unwrap_or(file)
+            .to_path_buf()
+    };
+    let files: Vec<(PathBuf, Option<std::time::SystemTime>)> = match source.remote_parts() {
+        Some((host, base, files)) => {
+            let (listing, _, _) = collect_remote_source(host, &ssh::ctl_args(host), base, files, patterns, &mut ScanGuard::unlimited())?;
+            let root = Path::new(base).parent();
+            listing.iter().map(|(f, _)| (below(root, f), None)).collect()
+        }
+        None => {
+            let (files, _, _) = collect_files(source, patterns, links, &mut ScanGuard::unlimited())?;
+            let root = match source {
+                SourceSelection::Directory(d) | SourceSelection::DirectoryItems(d, _) => d.parent(),
+                _ => None,
+            };
+            files.iter().map(|f| (below(root, f), modified(f))).collect()
+        }
+    };
+    let renamed = files
+        .iter()
+        .filter_map(|(rel, mtime)| {
+            let after = renamer.apply_path(rel, *mtime);
+            (after != *rel).then(|| (shown(rel), shown(after)))
+        })
+        .collect();
+    Ok((renamed, files.len()))
+}
 
-    let mut i = 0;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--src" => {
-                i += 1;
-                src = args.get(i).cloned();
-            }
-            "--dst" => {
-                i += 1;
-                dst = args.get(i).cloned();
-            }
-            "--move" => do_move = true,
-            "--conflict" => {
-                i += 1;
-                if let Some(val) = args.get(i) {
-                    conflict_mode = match val.as_str() {
-                        "overwrite" => ConflictMode::Overwrite,
-                        "rename" => ConflictMode::Rename,
-                        _ => ConflictMode::Skip,
-                    };
-                }
-            }
-            "--strip-spaces" => strip_spaces = true,
-            "--mode" => {
-                i += 1;
-                if let Some(val) = args.get(i) {
-                    transfer_mode = match val.as_str() {
-                        "files" => TransferMode::FilesOnly,
-                        _ => TransferMode::FoldersAndFiles,
-                    };
-                }
-            }
-            "--method" => {
-                i += 1;
-                if let Some(val) = args.get(i) {
-                    transfer_method = match val.as_str() {
-                        "rsync" => TransferMethod::Rsync,
-                        _ => TransferMethod::Standard,
-                    };
-                }
-            }
-            "--exclude" => {
-                i += 1;
-                if let Some(val) = args.get(i) {
-                    patterns.push(val.clone());
-                }
-            }
-            "--src-files" => {
-                i += 1;
-                if let Some(val) = args.get(i) {
-                    src_files = Some(
-                        val.split(',')
-                            .map(|s| PathBuf::from(s.trim()))
-                            .collect(),
-                    );
-                }
-            }
-            other => {
-                eprintln!("Unknown option: {}", other);
-                return 1;
-            }
+/// `--rename-preview`: print `before → after` for each file the rename
+/// rules would rename, without transferring anything.
+fn preview_renames(source: &SourceSelection, patterns: &[String], links: symlinks::Policy, renamer: &Renamer) -> i32 {
+    let (renamed, total) = match rename_preview(source, patterns, links, renamer) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let mut out = std::io::stdout().lock();
+    for (before, after) in &renamed {
+        if writeln!(out, "{} → {}", before, after).is_err() {
+            break;
         }
-        i += 1;
     }
+    let _ = out.flush();
+    eprintln!("{} of {} file(s) would be renamed", renamed.len(), total);
+    0
+}
 
-    let dst = match dst {
-        Some(d) => d,
-        None => {
-            eprintln!("--dst is required");
+/// `--dry-run`: print what a run would do with each file as a JSON array
+/// of `{src, dst, action}` entries, with a `reason` for files left out or
+/// that could not be compared, and a summary on stderr.  The plan comes
+/// from the same collection and destination rules as the workers:
+/// `plan_local_file` into a local folder, and into a folder on a host the
+/// existing-file lookup and `resolve_remote_conflict` the uploads use.  A
+/// remote source is listed, and a remote destination looked up, over SSH
+/// as a run does; what a run would write there is only printed (see
+/// `activity::set_dry_run`).  Files the plan has already placed count as
+/// written, so two sources bound for one name are planned as the run
+/// would handle them.
+fn print_dry_run(
+    source: &SourceSelection,
+    dst: &str,
+    patterns: &[String],
+    opts: &RunOptions,
+    renamer: &Renamer,
+    transfer_mode: TransferMode,
+    keep_parents: usize,
+    conflict_mode: ConflictMode,
+    do_move: bool,
+) -> i32 {
+    let src_host = source.remote_parts().map(|(host, ..)| host);
+    let (dst_host, dst_base) = parse_destination(dst);
+    for host in src_host.into_iter().chain(dst_host.as_deref()) {
+        if !dry_run_reaches(host) {
             return 1;
         }
+    }
+    let collected = match source.remote_parts() {
+        Some((host, base, files)) => collect_remote_source(host, &ssh::ctl_args(host), base, files, patterns, &mut ScanGuard::unlimited())
+            .map(|(listing, excluded_files, excluded_dirs)| (listing.into_iter().map(|(path, _)| path).collect(), excluded_files, excluded_dirs)),
+        None => collect_files(source, patterns, opts.symlinks, &mut ScanGuard::unlimited()),
     };
-
-    // Build source selection
-    let source_sel = if let Some(files) = src_files {
-        SourceSelection::Files(files)
-    } else if let Some(s) = src {
-        let (host, path) = parse_destination(&s);
-        match host {
-            Some(h) => SourceSelection::Remote(h, path),
-            None => SourceSelection::Directory(PathBuf::from(path)),
+    let (files, excluded_files, excluded_dirs): (Vec<PathBuf>, usize, usize) = match collected {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
         }
-    } else {
-        eprintln!("--src or --src-files is required");
+    };
+    let src_dir = match source {
+        SourceSelection::Directory(d) | SourceSelection::DirectoryItems(d, _) => Some(d.clone()),
+        _ => source.remote_parts().map(|(_, base, _)| PathBuf::from(base.trim_end_matches('/'))).filter(|b| !b.as_os_str().is_empty()),
+    };
+    let parent_levels = parent_levels(transfer_mode, src_dir.is_none(), keep_parents);
+    let dst_base = match &dst_host {
+        Some(_) => dst_base.trim_end_matches('/').to_string(),
+        None => dst_base,
+    };
+    let dest = match &dst_host {
+        Some(_) => Dest::Remote(&dst_base),
+        None => Dest::Local(Path::new(&dst_base)),
+    };
+    let input = |file| match src_host {
+        Some(_) => PlanInput::listed(file, src_dir.as_deref(), transfer_mode, parent_levels, dest),
+        None => PlanInput { file, src_dir: src_dir.as_deref(), transfer_mode, parent_levels, dest },
+    };
+    if let Err(e) = settle_collisions(files.iter().map(PathBuf::as_path).map(input), renamer, src_host.is_some(), opts.on_collision) {
+        println!("{{\"status\":\"error\",\"message\":{}}}", json_string(&e));
         return 1;
+    }
+    // Newest wins compares with the sources' times, read on their host.
+    let source_times = match (src_host, conflict_mode) {
+        (Some(host), ConflictMode::Newer) => {
+            let sources: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+            remote_source_times(host, &ssh::ctl_args(host), &sources)
+        }
+        _ => RemoteTimes::new(),
+    };
+    let source_time = |file: &Path| match src_host {
+        Some(_) => source_times.get(file).copied(),
+        None => modified(file).map(Timestamp::of),
     };
 
-    let (tx, rx) = mpsc::channel::<WorkerMsg>();
-    let cancel_flag = Arc::new(AtomicBool::new(false));
+    let actions: Vec<LocalAction> = match (&dst_host, src_host) {
+        (None, None) => plan_local_run(&files, &input, renamer, conflict_mode, opts.symlinks),
+        (None, Some(_)) => plan_download(&files, &input, renamer, conflict_mode, source_time),
+        (Some(host), _) => plan_upload(host, &files, &input, renamer, conflict_mode, source_time, src_host.is_none(), opts),
+    };
 
-    // Handle Ctrl+C gracefully in CLI mode
-    {
-        let cancel_flag_c = cancel_flag.clone();
-        let _ = ctrlc::set_handler(move || {
-            cancel_flag_c.store(true, Ordering::SeqCst);
-            eprintln!("\nCancelling…");
-        });
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    let mut entries = Vec::with_capacity(files.len());
+    for (file, action) in files.iter().zip(&actions) {
+        let (src, src_escaped) = dry_run_name(src_host, file);
+        let (dst, dst_escaped) = match action.dest() {
+            Some(dest) => {
+                let (name, escaped) = dry_run_name(dst_host.as_deref(), dest);
+                (json_string(&name), escaped)
+            }
+            None => ("null".to_string(), false),
+        };
+        let reason = match action {
+            LocalAction::Unplaced(why) => format!(",\"reason\":{}", json_string(why.reason())),
+            LocalAction::CompareFailed(_, e) => format!(",\"reason\":{}", json_string(&e.to_string())),
+            _ => String::new(),
+        };
+        let non_utf8 = if src_escaped || dst_escaped { ",\"non_utf8\":true" } else { "" };
+        let name = action.name(do_move);
+        entries.push(format!("{{\"src\":{},\"dst\":{},\"action\":\"{}\"{}{}}}", json_string(&src), dst, name, reason, non_utf8));
+        match counts.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((name, 1)),
+        }
     }
 
-    let src_is_remote = matches!(&source_sel, SourceSelection::Remote(_, _));
-    let (dst_host, dest_path) = parse_destination(&dst);
+    let mut out = std::io::stdout().lock();
+    let _ = writeln!(out, "[{}]", entries.join(",\n"));
+    let _ = out.flush();
+    let counts: Vec<String> = counts.iter().map(|(name, count)| format!("{} {}", count, name)).collect();
+    eprintln!(
+        "{} file(s){}{}; excluded {} file(s), {} dir(s); nothing was transferred",
+        files.len(),
+        if counts.is_empty() { "" } else { ": " },
+        counts.join(", "),
+        excluded_files,
+        excluded_dirs
+    );
+    0
+}
 
-    match (src_is_remote, dst_host, transfer_method) {
-        (true, Some(dhost), TransferMethod::Standard) => {
-            if let SourceSelection::Remote(shost, spath) = &source_sel {
-                run_remote_to_remote_worker(
-                    shost, spath, &dhost, &dest_path, do_move, conflict_mode,
-                    strip_spaces, transfer_mode, &patterns, cancel_flag.clone(), tx,
-                );
+/// A `--dry-run` path as the result writes names, `host:path` on a remote
+/// host, and whether it had to be escaped (see `escape_non_utf8`).
+fn dry_run_name(host: Option<&str>, path: &Path) -> (String, bool) {
+    let (text, escaped) = escape_non_utf8(path.as_os_str().as_bytes());
+    match host {
+        Some(host) => (format!("{}:{}", host, text), escaped),
+        None => (text, escaped),
+    }
+}
+
+/// `check_ssh_host` for a dry run, which has no window to ask about a
+/// host key: an unknown key is refused, as without `--accept-new-hostkeys`.
+fn dry_run_reaches(host: &str) -> bool {
+    let (tx, rx) = mpsc::channel();
+    let checking = {
+        let host = host.to_string();
+        thread::spawn(move || check_ssh_host(&host, &ssh::ctl_args(&host), &tx))
+    };
+    for msg in rx {
+        match msg {
+            WorkerMsg::HostKey { reply, .. } => {
+                let _ = reply.send(false);
             }
+            WorkerMsg::Error(e) => eprintln!("{}", e),
+            WorkerMsg::ClockSkew { warning: Some(w), .. } => eprintln!("Warning: {}", w),
+            _ => {}
         }
-        (true, Some(dhost), TransferMethod::Rsync) => {
-            if let SourceSelection::Remote(shost, spath) = &source_sel {
-                run_remote_to_remote_rsync_worker(
-                    shost, spath, &dhost, &dest_path, do_move, conflict_mode,
-                    strip_spaces, transfer_mode, &patterns, cancel_flag.clone(), tx,
-                );
-            }
+    }
+    checking.join().unwrap_or(false)
+}
+
+/// What a local run does with each of `files`, as `run_worker` decides.
+fn plan_local_run<'a>(
+    files: &'a [PathBuf],
+    input: &impl Fn(&'a Path) -> PlanInput<'a>,
+    renamer: &Renamer,
+    conflict_mode: ConflictMode,
+    links: symlinks::Policy,
+) -> Vec<LocalAction> {
+    // Destinations written so far, with the source written to each.
+    let mut planned: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut actions = Vec::with_capacity(files.len());
+    for file in files {
+        let action = plan_local_file(
+            &input(file),
+            renamer,
+            conflict_mode,
+            links,
+            |p| planned.contains_key(p) || p.exists(),
+            |src, dest| files_are_identical(src, planned.get(dest).map_or(dest, PathBuf::as_path)),
+        );
+        if let LocalAction::Copy(dest) | LocalAction::Overwrite(dest) | LocalAction::Rename(dest) = &action {
+            planned.insert(dest.clone(), file.clone());
         }
-        (true, None, method) => {
-            if let SourceSelection::Remote(shost, spath) = &source_sel {
-                run_remote_to_local_worker(
-                    shost, spath, &dest_path, do_move, conflict_mode,
-                    strip_spaces, transfer_mode, &patterns, method, cancel_flag.clone(), tx,
-                );
+        actions.push(action);
+    }
+    actions
+}
+
+/// What a download does with each of the remote `files`, as
+/// `run_remote_to_local_worker` decides: a file already there is handled
+/// by `conflict_mode` alone, without comparing.
+fn plan_download<'a>(
+    files: &'a [PathBuf],
+    input: &impl Fn(&'a Path) -> PlanInput<'a>,
+    renamer: &Renamer,
+    conflict_mode: ConflictMode,
+    source_time: impl Fn(&Path) -> Option<Timestamp>,
+) -> Vec<LocalAction> {
+    let mut planned: HashSet<PathBuf> = HashSet::new();
+    let mut actions = Vec::with_capacity(files.len());
+    for file in files {
+        let dest = match destpath::build_destination(&input(file), |rel| renamer.rename_path(rel, None)) {
+            Ok(dest) => dest.into_local(),
+            Err(why) => {
+                actions.push(LocalAction::Unplaced(why));
+                continue;
             }
+        };
+        let taken = |p: &Path| planned.contains(p) || p.exists();
+        let action = match conflict_mode {
+            _ if !taken(&dest) => LocalAction::Copy(dest),
+            ConflictMode::Overwrite => LocalAction::Overwrite(dest),
+            ConflictMode::Newer if source_is_newer(source_time(file), modified(&dest).map(Timestamp::of)) => LocalAction::Overwrite(dest),
+            ConflictMode::Newer => LocalAction::SkipNewer(dest),
+            ConflictMode::Skip => LocalAction::SkipExisting(dest),
+            ConflictMode::Rename => LocalAction::Rename(destpath::unique_local_where(&dest, taken)),
+        };
+        if let LocalAction::Copy(dest) | LocalAction::Overwrite(dest) | LocalAction::Rename(dest) = &action {
+            planned.insert(dest.clone());
         }
-        (false, Some(host), TransferMethod::Standard) => run_remote_worker(
-            source_sel, &host, &dest_path, do_move, conflict_mode,
-            strip_spaces, transfer_mode, &patterns, cancel_flag.clone(), tx,
-        ),
-        (false, Some(host), TransferMethod::Rsync) => run_remote_rsync_worker(
-            source_sel, &host, &dest_path, do_move, conflict_mode,
-            strip_spaces, transfer_mode, &patterns, cancel_flag.clone(), tx,
-        ),
-        (false, None, TransferMethod::Rsync) => run_local_rsync_worker(
-            source_sel, dest_path, do_move, conflict_mode,
-            strip_spaces, transfer_mode, &patterns, cancel_flag.clone(), tx,
-        ),
-        (false, None, TransferMethod::Standard) => run_worker(
-            source_sel, dest_path, do_move, conflict_mode,
-            strip_spaces, transfer_mode, &patterns, cancel_flag.clone(), tx,
-        ),
+        actions.push(action);
     }
+    actions
+}
 
-    // Collect results from the worker
-    for msg in rx {
-        match msg {
-            WorkerMsg::Finished { copied, skipped, excluded_files, excluded_dirs, errors } => {
-                return cli_output_json("finished", copied, &skipped, excluded_files, excluded_dirs, &errors);
-            }
-            WorkerMsg::Cancelled { copied, skipped, excluded_files, excluded_dirs, errors } => {
-                return cli_output_json("cancelled", copied, &skipped, excluded_files, excluded_dirs, &errors);
-            }
-            WorkerMsg::Error(e) => {
-                let escaped = e.replace('\\', "\\\\").replace('"', "\\\"");
-                println!("{{\"status\":\"error\",\"message\":\"{}\"}}", escaped);
-                return 1;
+/// What an upload to `host` does with each of `files`, as the remote
+/// workers decide from the existing-file lookup.  The folders they would
+/// make and, from a local source, the copies they would send with `opts`
+/// go through the suppressed runner, so `--print-commands` shows them.
+fn plan_upload<'a>(
+    host: &str,
+    files: &'a [PathBuf],
+    input: &impl Fn(&'a Path) -> PlanInput<'a>,
+    renamer: &Renamer,
+    conflict_mode: ConflictMode,
+    source_time: impl Fn(&Path) -> Option<Timestamp>,
+    local_source: bool,
+    opts: &RunOptions,
+) -> Vec<LocalAction> {
+    let ctl = ssh::ctl_args(host);
+    let placed: Vec<Result<PathBuf, destpath::Unplaced>> = files
+        .iter()
+        .map(|file| {
+            let mtime = if local_source { modified(file) } else { None };
+            destpath::build_destination(&input(file), |rel| renamer.rename_path(rel, mtime)).map(|d| d.into_remote())
+        })
+        .collect();
+    let targets: Vec<&Path> = placed.iter().filter_map(|p| p.as_deref().ok()).collect();
+    if let Some(Dest::Remote(base)) = files.first().map(|f| input(f).dest) {
+        let dirs = remote_target_dirs(base, targets.iter().copied());
+        let _ = Command::new("ssh").args(&ctl).arg(host).arg(folders::mkdir_script(&dirs, |p| quote_path(p))).tracked_output();
+    }
+    // Looked up whatever the conflict mode, to tell overwriting from copying.
+    let lookup_mode = if conflict_mode == ConflictMode::Overwrite { ConflictMode::Skip } else { conflict_mode };
+    let (mut existing, mut dest_times) = existing_remote_files_ssh(host, &ctl, &targets, lookup_mode);
+    let mut actions = Vec::with_capacity(files.len());
+    for (file, target) in files.iter().zip(placed) {
+        let target = match target {
+            Ok(target) => target,
+            Err(why) => {
+                actions.push(LocalAction::Unplaced(why));
+                continue;
             }
-            WorkerMsg::Progress { .. } => {
-                // Silently consume progress messages in CLI mode
+        };
+        let there = existing.contains(&target);
+        let newer = |p: &Path| source_is_newer(source_time(file), dest_times.remove(p));
+        let action = match resolve_remote_conflict(&target, &mut existing, conflict_mode, Recheck::Nothing, newer, |_| false) {
+            None if conflict_mode == ConflictMode::Newer => LocalAction::SkipNewer(target.clone()),
+            None => LocalAction::SkipExisting(target.clone()),
+            Some(std::borrow::Cow::Owned(renamed)) => LocalAction::Rename(renamed),
+            Some(_) if there => LocalAction::Overwrite(target.clone()),
+            Some(_) => LocalAction::Copy(target.clone()),
+        };
+        if let Some(dest) = action.dest().filter(|_| matches!(action, LocalAction::Copy(_) | LocalAction::Overwrite(_) | LocalAction::Rename(_))) {
+            existing.insert(dest.to_path_buf());
+            if local_source {
+                let _ = scp_send(host, &ctl, file, dest, 1, opts);
             }
         }
+        actions.push(action);
     }
+    actions
+}
 
-    eprintln!("Worker channel closed without result");
-    1
+/// Tell the CLI's caller about `message`: a `warning` event with
+/// `--events`, otherwise a line on stderr.
+fn warn(events: bool, message: &str) {
+    if events {
+        activity::event("warning", &format!("\"message\":{}", json_string(message)));
+    } else {
+        eprintln!("Warning: {}", message);
+    }
 }
 
-// ── Messages from worker thread to UI ──────────────────────────────────
+/// The CLI's answer at a scan limit: `--scan-limit-action`, or with `ask`
+/// a yes or no read from stdin, where anything else, or nothing, stops.
+fn cli_scan_answer(action: scanguard::Action, trip: &scanguard::Trip) -> bool {
+    match action {
+        scanguard::Action::Continue => true,
+        scanguard::Action::Abort => false,
+        scanguard::Action::Ask => {
+            eprint!("Scan limit reached: {}. Keep scanning? [y/N] ", trip.describe());
+            let mut answer = String::new();
+            std::io::stdin().read_line(&mut answer).is_ok()
+                && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+        }
+    }
+}
 
-enum WorkerMsg {
-    Progress {
-        done: usize,
-        total: usize,
-        file: String,
-    },
-    Finished {
-        copied: usize,
-        skipped: Vec<String>,
-        excluded_files: usize,
-        excluded_dirs: usize,
-        errors: Vec<String>,
-    },
-    Cancelled {
-        copied: usize,
-        skipped: Vec<String>,
-        excluded_files: usize,
-        excluded_dirs: usize,
-        errors: Vec<String>,
-    },
-    Error(String),
+/// Helper to emit CLI JSON result and return an exit code.
+fn cli_output_json(status: &str, report: &TransferReport, extras: &CliExtras) -> i32 {
+    let TransferReport { copied, skipped, excluded_files, excluded_dirs, errors, .. } = report;
+    let skipped_json: Vec<String> = skipped
+        .iter()
+        .map(|s| json_string(s))
+        .collect();
+    let entries = match extras.coalesce_after {
+        Some(after) => coalesce::coalesce(errors.shown(), after),
+        None => errors.iter().map(|e| ErrorEntry { message: e.clone(), coalesced: 0 }).collect(),
+    };
+    if entries.len() < errors.shown().len() {
+        for e in errors.iter() {
+            debug_log(&format!("error: {}", e));
+        }
+    }
+    let errors_json: Vec<String> = entries
+        .iter()
+        .map(|e| {
+            if e.coalesced > 0 {
+                format!(
+                    "{{\"message\":{},\"coalesced\":true,\"count\":{}}}",
+                    json_string(&e.message),
+                    e.coalesced
+                )
+            } else {
+                json_string(&e.message)
+            }
+        })
+        .collect();
+    // Lists cut short say so, with where the rest went.
+    let truncated: Vec<String> = [("skipped", skipped), ("errors", errors)]
+        .iter()
+        .filter(|(_, list)| list.is_truncated())
+        .map(|(name, list)| {
+            let file = list.spill_path().map(|p| json_string(&p.display().to_string())).unwrap_or_else(|| "null".to_string());
+            format!("\"{}\":{{\"shown\":{},\"total\":{},\"file\":{}}}", name, list.shown().len(), list.len(), file)
+        })
+        .collect();
+    let truncated_json =
+        if truncated.is_empty() { String::new() } else { format!(",\"truncated\":{{{}}}", truncated.join(",")) };
+    // Read last, once every path in the result has been shown.
+    let extra_fields = extras.to_json_fields();
+    let non_utf8 = if NON_UTF8_SHOWN.load(Ordering::Relaxed) { ",\"non_utf8\":true" } else { "" };
+    println!(
+        "{{\"status\":\"{}\",\"copied\":{},\"skipped\":[{}],\"excluded_files\":{},\"excluded_dirs\":{},\"errors\":[{}],\"outcomes\":{}{}{}{}}}",
+        status,
+        copied,
+        skipped_json.join(","),
+        excluded_files,
+        excluded_dirs,
+        errors_json.join(","),
+        report.tally().to_json(),
+        truncated_json,
+        extra_fields,
+        non_utf8,
+    );
+    if !errors.is_empty() { 2 } else { 0 }
 }
 
-// ── UI construction ────────────────────────────────────────────────────
+/// The sentences a run's summary adds after its counts, for what the scan
+/// guard, a move, the rename rules and folder creation did.
+fn summary_notes(
+    auto_method: Option<&automethod::Decision>,
+    relay: Option<&relay::Decision>,
+    scan_limit: Option<&scanguard::Report>,
+    move_stats: Option<MoveStats>,
+    deletion: Option<Deletion>,
+    emptied: usize,
+    renames: Option<&RenameLog>,
+    made: Option<&folders::FolderLog>,
+    times: Option<&timing::Breakdown>,
+    quota: Option<&quota::Report>,
+    verify_sample: Option<&(sample::SampleReport, usize)>,
+    stale: Option<&stale::Decision>,
+) -> Vec<String> {
+    let mut notes: Vec<String> = stale.map(stale::Decision::describe).into_iter().collect();
+    notes.extend(auto_method.map(automethod::Decision::describe));
+    notes.extend(relay.map(relay::Decision::describe));
+    notes.extend(scan_limit.map(scanguard::Report::describe));
+    if let Some(st) = move_stats.filter(|st| *st != MoveStats::default()) {
+        notes.push(st.describe());
+    }
+    if let Some(d) = deletion {
+        notes.push(d.describe());
+    }
+    notes.extend(emptied::describe(emptied));
+    notes.extend(renames.filter(|r| r.count > 0).map(RenameLog::describe));
+    notes.extend(made.and_then(folders::FolderLog::describe));
+    notes.extend(times.and_then(timing::Breakdown::describe));
+    notes.extend(quota.map(quota::Report::describe));
+    notes.extend(verify_sample.map(|(report, files)| report.describe(*files)));
+    notes
+}
 
-fn build_ui(app: &Application) {
-    let window = ApplicationWindow::builder()
-        .application(app)
-        .title("Kosmokopy")
-        .default_width(560)
-        .default_height(520)
-        .resizable(true)
-        .build();
+/// The `stale_artifacts` result field: what was found and done.
+fn stale_json(decision: &stale::Decision) -> String {
+    let failed: Vec<String> = decision.failed.iter().map(|f| json_string(f)).collect();
+    format!(
+        "{{\"partials\":{},\"probes\":{},\"choice\":\"{}\",\"removed\":{},\"failed\":[{}]}}",
+        decision.partials,
+        decision.probes,
+        decision.choice.name(),
+        decision.removed,
+        failed.join(",")
+    )
+}
 
-    let root = GtkBox::new(Orientation::Vertical, 12);
-    root.set_margin_top(16);
-    root.set_margin_bottom(16);
-    root.set_margin_start(16);
-    root.set_margin_end(16);
+/// The fields of the `--events` line for what was done about stale
+/// leftovers.
+fn stale_event_json(decision: &stale::Decision) -> String {
+    format!(
+        "\"partials\":{},\"probes\":{},\"choice\":\"{}\",\"removed\":{}",
+        decision.partials,
+        decision.probes,
+        decision.choice.name(),
+        decision.removed
+    )
+}
 
-    // ── Source selection ───────────────────────────────────────────────
-    let src_heading = Label::new(Some("Source:"));
-    src_heading.set_halign(Align::Start);
-    root.append(&src_heading);
+/// Bytes a run transferred, when the sizes of its files were known.
+fn transferred_bytes(model: &ProgressModel) -> Option<u64> {
+    model.is_size_aware().then(|| model.bytes_done())
+}
 
-    let src_row = GtkBox::new(Orientation::Horizontal, 8);
-    let src_entry = Entry::new();
-    src_entry.set_hexpand(true);
-    src_entry.set_placeholder_text(Some("Local path or host:/remote/path"));
+/// Optional fields appended to the CLI result line.
+#[derive(Default)]
+struct CliExtras {
+    /// A move from a read-only source ran as a copy.
+    move_downgraded: bool,
+    move_stats: Option<MoveStats>,
+    /// Fold runs of identical failures after this many entries.
+    coalesce_after: Option<usize>,
+    /// Dated folders removed by `--retain` (or listed, with a dry run).
+    pruned: Option<Vec<String>>,
+    prune_dry_run: bool,
+    /// Results per top-level source folder (finished runs only).
+    groups: Option<Vec<GroupStats>>,
+    /// What a two-phase move did with its sources.
+    deletion: Option<Deletion>,
+    /// Emptied source folders a move removed.
+    source_dirs_removed: usize,
+    /// `rsync --version` on each end of an rsync transfer.
+    rsync_versions: Vec<String>,
+    /// The method Auto chose, and why.
+    auto_method: Option<automethod::Decision>,
+    /// A run between two hosts: how it sent the files.
+    relay: Option<relay::Decision>,
+    warnings: Vec<String>,
+    /// Destination folders the preflight found unwritable.
+    unwritable: Vec<UnwritableDir>,
+    /// Leftovers of an earlier run at the destination, and what was done.
+    stale: Option<stale::Decision>,
+    /// How `--hash-cache` did.
+    hash_cache: Option<CacheStats>,
+    /// Files transferred after verification was skipped.
+    unverified: Vec<String>,
+    /// What the rename rules changed.
+    renames: Option<RenameLog>,
+    /// Destination folders the run made and reused.
+    folders: Option<folders::FolderLog>,
+    /// Where the time of a run that reached other hosts went.
+    timing: Option<timing::Breakdown>,
+    /// Files that failed or were skipped for the destination quota.
+    quota: Option<quota::Report>,
+    /// With `--verify-sample`: how many files were verified, of how many.
+    verify_sample: Option<(sample::SampleReport, usize)>,
+    /// With `--low-priority`: what lowering the worker's priority achieved.
+    low_priority: Option<priority::Outcome>,
+    /// Seconds each remote host's clock is ahead of this machine's.
+    clock_skew: Vec<(String, i64)>,
+    /// With an owner filter: files it left out.
+    filtered_by_owner: Option<usize>,
+    /// Remote sources: files left out under excluded folders.
+    in_excluded_dirs: Option<usize>,
+    /// What happened when the scan passed a scan limit.
+    scan_limit: Option<scanguard::Report>,
+    /// What the source's `.kosmokopy.toml` changed or had ignored.
+    source_config: Option<String>,
+    /// The phase a cancelled run stopped in.
+    stage: Option<Phase>,
+    /// The run's summary, as the GUI words it.
+    summary: Option<RunSummary>,
+    /// `--detail-report`: where it went and how many records it holds.
+    detail_report: Option<(String, usize)>,
+}
 
-    let btn_browse_folder = Button::with_label("Browse Folder…");
-    let btn_browse_files = Button::with_label("Browse Files…");
-    let btn_browse_remote_src = Button::with_label("Browse Remote…");
+impl CliExtras {
+    fn to_json_fields(&self) -> String {
+        let mut out = String::new();
+        if let Some(stage) = self.stage {
+            out.push_str(&format!(",\"stage\":\"{}\"", stage.name()));
+        }
+        if self.move_downgraded {
+            out.push_str(",\"move_downgraded\":true");
+        }
+        if let Some(st) = &self.move_stats {
+            out.push_str(&format!(
+                ",\"move_breakdown\":{{\"renamed\":{},\"copied\":{},\"copied_bytes\":{},\"identical\":{}}}",
+                st.renamed, st.copied, st.copied_bytes, st.identical
+            ));
+        }
+        if let Some(pruned) = &self.pruned {
+            let list: Vec<String> = pruned.iter().map(|p| json_string(p)).collect();
+            out.push_str(&format!(",\"pruned\":[{}]", list.join(",")));
+            if self.prune_dry_run {
+                out.push_str(",\"prune_dry_run\":true");
+            }
+        }
+        if let Some(groups) = &self.groups {
+            let list: Vec<String> = groups
+                .iter()
+                .map(|g| {
+                    format!(
+                        "{{\"name\":{},\"files\":{},\"copied\":{},\"skipped\":{},\"errors\":{},\"bytes\":{}}}",
+                        json_string(&g.name),
+                        g.files,
+                        g.copied,
+                        g.skipped,
+                        g.errors,
+                        g.bytes
+                    )
+                })
+                .collect();
+            out.push_str(&format!(",\"groups\":[{}]", list.join(",")));
+        }
+        if let Some(deletion) = self.deletion {
+            out.push_str(&format!(",\"deletion\":{}", deletion.to_json()));
+        }
+        if self.source_dirs_removed > 0 {
+            out.push_str(&format!(",\"source_dirs_removed\":{}", self.source_dirs_removed));
+        }
+        if !self.rsync_versions.is_empty() {
+            let list: Vec<String> = self.rsync_versions.iter().map(|v| json_string(v)).collect();
+            out.push_str(&format!(",\"rsync\":[{}]", list.join(",")));
+        }
+        if let Some(decision) = &self.auto_method {
+            out.push_str(&format!(
+                ",\"auto_method\":{{\"method\":\"{}\",\"reason\":{}}}",
+                decision.method_name(),
+                json_string(&decision.reason)
+            ));
+        }
+        if let Some(decision) = &self.relay {
+            let list: Vec<String> = decision.fallbacks.iter().map(|f| json_string(f)).collect();
+            out.push_str(&format!(
+                ",\"relay\":{{\"strategy\":\"{}\",\"fallbacks\":[{}],\"staged_for_names\":{}}}",
+                decision.relay.name(),
+                list.join(","),
+                decision.staged
+            ));
+        }
+        if !self.warnings.is_empty() {
+            let list: Vec<String> = self.warnings.iter().map(|w| json_string(w)).collect();
+            out.push_str(&format!(",\"warnings\":[{}]", list.join(",")));
+        }
+        if !self.unwritable.is_empty() {
+            let list: Vec<String> = self
+                .unwritable
+                .iter()
+                .map(|u| format!("{{\"dir\":{},\"files\":{}}}", json_string(&u.dir), u.files))
+                .collect();
+            out.push_str(&format!(",\"unwritable\":[{}]", list.join(",")));
+        }
+        if let Some(decision) = &self.stale {
+            out.push_str(&format!(",\"stale_artifacts\":{}", stale_json(decision)));
+        }
+        if let Some(cache) = &self.hash_cache {
+            out.push_str(&format!(",\"hash_cache\":{{\"hits\":{},\"misses\":{}}}", cache.hits, cache.misses));
+        }
+        if !self.unverified.is_empty() {
+            let list: Vec<String> = self.unverified.iter().map(|f| json_string(f)).collect();
+            out.push_str(&format!(",\"unverified\":[{}]", list.join(",")));
+        }
+        if let Some(renames) = self.renames.as_ref().filter(|r| r.count > 0) {
+            let samples: Vec<String> = renames
+                .samples
+                .iter()
+                .map(|s| {
+                    let flag = if s.non_utf8 { ",\"non_utf8\":true" } else { "" };
+                    format!("{{\"from\":{},\"to\":{}{}}}", json_string(&s.from), json_string(&s.to), flag)
+                })
+                .collect();
+            out.push_str(&format!(",\"renamed\":{},\"renamed_samples\":[{}]", renames.count, samples.join(",")));
+        }
+        if let Some((report, files)) = &self.verify_sample {
+            out.push_str(&format!(
+                ",\"verify_sample\":{{\"percent\":{},\"min_size\":{},\"seed\":{},\"verified\":{},\"files\":{},\"summary\":{}}}",
+                report.spec.percent,
+                report.spec.min_size.map_or("null".to_string(), |m| m.to_string()),
+                report.spec.seed,
+                report.verified,
+                files,
+                json_string(&report.describe(*files))
+            ));
+        }
+        if let Some(folders) = &self.folders {
+            let list: Vec<String> = folders.created.iter().map(|d| json_string(d)).collect();
+            out.push_str(&format!(",\"dirs_created\":[{}],\"dirs_reused\":{}", list.join(","), folders.reused));
+        }
+        if let Some(times) = self.timing.as_ref().filter(|t| !t.hosts.is_empty()) {
+            out.push_str(&format!(",\"timing\":{}", times.to_json_value()));
+        }
+        if let Some(quota) = &self.quota {
+            out.push_str(&format!(",\"quota_exceeded\":{}", quota.to_json()));
+        }
+        if !self.clock_skew.is_empty() {
+            let list: Vec<String> = self
+                .clock_skew
+                .iter()
+                .map(|(host, seconds)| format!("{{\"host\":{},\"seconds\":{}}}", json_string(host), seconds))
+                .collect();
+            out.push_str(&format!(",\"clock_skew\":[{}]", list.join(",")));
+        }
+        if let Some(count) = self.filtered_by_owner {
+            out.push_str(&format!(",\"filtered_by_owner\":{}", count));
+        }
+        if let Some(count) = self.in_excluded_dirs {
+            out.push_str(&format!(",\"files_in_excluded_dirs\":{}", count));
+        }
+        if let Some(low) = &self.low_priority {
+            out.push_str(&format!(
+                ",\"low_priority\":{{\"nice\":{},\"io_idle\":{},\"child_prefix\":{}}}",
+                low.nice,
+                low.io_idle,
+                json_string(&low.child_prefix.join(" "))
+            ));
+        }
+        if let Some(report) = &self.scan_limit {
+            out.push_str(&format!(
+                ",\"scan_limit\":{{\"files\":{},\"elapsed_secs\":{},\"deepest\":{},\"by_time\":{},\"continued\":{}}}",
+                report.trip.files,
+                report.trip.elapsed.as_secs(),
+                json_string(&report.trip.deepest),
+                report.trip.by_time,
+                report.continued
+            ));
+        }
+        if let Some(note) = &self.source_config {
+            out.push_str(&format!(",\"source_config\":{}", json_string(note)));
+        }
+        if let Some(summary) = &self.summary {
+            out.push_str(&format!(",\"summary\":{}", summary.to_json_value()));
+        }
+        if let Some((path, records)) = &self.detail_report {
+            out.push_str(&format!(",\"detail_report\":{{\"path\":{},\"records\":{}}}", json_string(path), records));
+        }
+        out
+    }
+}
 
-    src_row.append(&src_entry);
-    src_row.append(&btn_browse_folder);
-    src_row.append(&btn_browse_files);
-    src_row.append(&btn_browse_remote_src);
-    root.append(&src_row);
+/// Finish `--detail-report` at `path`: where it is and how many records
+/// it holds, or a warning when writing it failed.  A sampled run's report
+/// ends with how many files were verified.
+fn close_detail_report(
+    path: Option<&Path>,
+    verify_sample: Option<&(sample::SampleReport, usize)>,
+    warnings: &mut Vec<String>,
+) -> Option<(String, usize)> {
+    let path = path?;
+    match filereport::close(verify_sample.map(|(report, files)| report.describe(*files)).as_deref())? {
+        Ok(records) => Some((path.display().to_string(), records)),
+        Err(e) => {
+            warnings.push(format!("could not write the detail report {}: {}", path.display(), e));
+            None
+        }
+    }
+}
 
+/// Quote a string for a JSON event line.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
 
+/// A file name's bytes as text, and whether they weren't UTF-8.  UTF-8
+/// names come back as they are; in any other, backslashes are doubled and
+/// the bytes that aren't UTF-8 are written `\xNN`, so the text tells the
+/// name apart from any other.
+fn escape_non_utf8(bytes: &[u8]) -> (String, bool) {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (text.to_string(), false);
+    }
+    let mut out = String::with_capacity(bytes.len() + 8);
+    for chunk in bytes.utf8_chunks() {
+        out.push_str(&chunk.valid().replace('\\', "\\\\"));
+        for b in chunk.invalid() {
+            out.push_str(&format!("\\x{:02x}", b));
+        }
+    }
+    (out, true)
+}
 
-    // ── Destination directory ─────────────────────────────────────────
-    let dst_row = dir_row_editable("Destination Directory:");
-    let dst_entry: Entry = dst_row.2.clone();
-    let btn_browse_remote_dst = Button::with_label("Browse Remote…");
-    dst_row.0.append(&btn_browse_remote_dst);
-    root.append(&dst_row.0);
+/// Set once a path in this run's messages had to be escaped (see `shown`).
+static NON_UTF8_SHOWN: AtomicBool = AtomicBool::new(false);
 
-    // ── Copy / Move toggle ────────────────────────────────────────────
-    let mode_box = GtkBox::new(Orientation::Horizontal, 12);
-    let chk_copy = CheckButton::with_label("Copy");
-    let chk_move = CheckButton::with_label("Move");
-    chk_move.set_group(Some(&chk_copy));
-    chk_copy.set_active(true);
-    mode_box.append(&chk_copy);
-    mode_box.append(&chk_move);
-    root.append(&mode_box);
+/// A path for messages, reports and lists shown to the user: as it is when
+/// it is UTF-8, otherwise escaped by `escape_non_utf8`.  Escaping one has
+/// the JSON result say `"non_utf8": true`.
+fn shown(path: impl AsRef<OsStr>) -> String {
+    let (text, escaped) = escape_non_utf8(path.as_ref().as_bytes());
+    if escaped {
+        NON_UTF8_SHOWN.store(true, Ordering::Relaxed);
+    }
+    text
+}
 
-    // ── Transfer mode: Files only / Folders and files ─────────────────
-    let transfer_box = GtkBox::new(Orientation::Horizontal, 12);
-    let chk_files_only = CheckButton::with_label("Files only");
-    let chk_folders_files = CheckButton::with_label("Folders and files");
-    chk_folders_files.set_group(Some(&chk_files_only));
-    chk_files_only.set_active(true);
-    transfer_box.append(&chk_files_only);
-    transfer_box.append(&chk_folders_files);
-    root.append(&transfer_box);
+/// What the options given to the CLI have chosen so far.
+struct CliArgs {
+    src: Option<String>,
+    dst: Option<String>,
+    do_move: bool,
+    move_strategy: MoveStrategy,
+    conflict_mode: Option<ConflictMode>,
+    strip_spaces: bool,
+    rename_rules: Vec<Rule>,
+    rename_preview: bool,
+    transfer_mode: TransferMode,
+    keep_parents: usize,
+    patterns: Vec<String>,
+    profile_patterns: bool,
+    src_files: Option<Vec<PathBuf>>,
+    src_files_from: Option<PathBuf>,
+    failed_files_out: Option<PathBuf>,
+    detail_report: Option<PathBuf>,
+    null_delimited: bool,
+    events: bool,
+    stall_after: std::time::Duration,
+    move_fallback_copy: bool,
+    recheck_conflicts: bool,
+    recheck_rename: bool,
+    coalesce_errors: bool,
+    list_only: bool,
+    dry_run: bool,
+    fail_if_empty: bool,
+    retain: Option<usize>,
+    retain_dry_run: bool,
+    retain_to_trash: bool,
+    on_unwritable: OnUnwritable,
+    stale_artifacts: Option<stale::OnStale>,
+    on_quota: quota::OnQuota,
+    on_cancel_during_verify: OnCancelDuringVerify,
+    method_choice: MethodChoice,
+    duplicate_window: u64,
+    opts: RunOptions,
+    scan_limit_action: scanguard::Action,
+    refuse_duplicate_runs: bool,
+    case_insensitive_paths: bool,
+    source_config: bool,
+    hash_cache: Option<PathBuf>,
+    paranoid: bool,
+    owner_filter: owners::OwnerFilter,
+    verify_sample: Option<f64>,
+    verify_sample_min_size: Option<u64>,
+    verify_sample_seed: Option<u64>,
+}
 
-    // ── Transfer method ──────────────────────────────────────────────
-    let method_box = GtkBox::new(Orientation::Horizontal, 12);
-    let method_label = Label::new(Some("Transfer method:"));
-    method_label.set_halign(Align::Start);
-    let chk_standard = CheckButton::with_label("Standard (cp/scp)");
-    let chk_rsync = CheckButton::with_label("rsync");
-    chk_rsync.set_group(Some(&chk_standard));
-    chk_standard.set_active(true);
-    method_box.append(&method_label);
-    method_box.append(&chk_standard);
-    method_box.append(&chk_rsync);
-    root.append(&method_box);
+impl CliArgs {
+    /// The choices before any profile or option, from the settings.
+    fn new(settings: &config::Config) -> Self {
+        CliArgs {
+            src: None,
+            dst: None,
+            do_move: false,
+            move_strategy: MoveStrategy::PerFile,
+            conflict_mode: None,
+            strip_spaces: false,
+            rename_rules: Vec::new(),
+            rename_preview: false,
+            transfer_mode: TransferMode::FoldersAndFiles,
+            keep_parents: 0,
+            patterns: Vec::new(),
+            profile_patterns: false,
+            src_files: None,
+            src_files_from: None,
+            failed_files_out: None,
+            detail_report: None,
+            null_delimited: false,
+            events: false,
+            stall_after: DEFAULT_STALL_AFTER,
+            move_fallback_copy: false,
+            recheck_conflicts: false,
+            recheck_rename: true,
+            coalesce_errors: true,
+            list_only: false,
+            dry_run: false,
+            fail_if_empty: false,
+            retain: None,
+            retain_dry_run: false,
+            retain_to_trash: false,
+            on_unwritable: OnUnwritable::Abort,
+            stale_artifacts: None,
+            on_quota: quota::OnQuota::Continue,
+            on_cancel_during_verify: OnCancelDuringVerify::Discard,
+            method_choice: settings.method,
+            duplicate_window: settings.duplicate_window_hours,
+            opts: RunOptions {
+                scan_limits: scanguard::Limits { files: settings.scan_limit_files, secs: settings.scan_limit_secs },
+                ..RunOptions::default()
+            },
+            scan_limit_action: scanguard::Action::Ask,
+            refuse_duplicate_runs: false,
+            case_insensitive_paths: settings.case_insensitive_paths,
+            source_config: settings.source_config,
+            hash_cache: None,
+            paranoid: false,
+            owner_filter: owners::OwnerFilter::default(),
+            verify_sample: None,
+            verify_sample_min_size: None,
+            verify_sample_seed: None,
+            }
+    }
+}
 
-    root.append(&Separator::new(Orientation::Horizontal));
+/// `--events-fd` and its `--progress-fd` alias.
+fn events_to_fd(a: &mut CliArgs, name: &str, v: &str) -> Result<Parsed, String> {
+    // 1 is where the result goes.
+    let sent = match v.parse::<u32>() {
+        Ok(fd) if fd != 0 && fd != 1 => activity::send_events_to_fd(fd).map_err(|e| e.to_string()),
+        _ => Err("expects a file descriptor open for writing, 2 or above".to_string()),
+    };
+    if let Err(e) = sent {
+        return Err(format!("{} {}: {}", name, v, e));
+    }
+    a.events = true;
+    Ok(Parsed::Next)
+}
 
-    // ── Exclusions ────────────────────────────────────────────────────
-    let excl_heading = Label::new(Some("Exclusions:"));
-    excl_heading.set_halign(Align::Start);
-    root.append(&excl_heading);
+/// What the CLI does once an option is handled.
+enum Parsed {
+    /// Go on to the next option.
+    Next,
+    /// The option has done all there is to do, e.g. printed the man page.
+    Exit(i32),
+}
 
-    let excl_btn_row = GtkBox::new(Orientation::Horizontal, 8);
-    let btn_excl_dirs = Button::with_label("Exclude Directories…");
-    let btn_excl_files = Button::with_label("Exclude Files…");
-    let btn_excl_clear = Button::with_label("Clear");
-    excl_btn_row.append(&btn_excl_dirs);
-    excl_btn_row.append(&btn_excl_files);
-    excl_btn_row.append(&btn_excl_clear);
-    root.append(&excl_btn_row);
+/// Handles one option given its name and value ("" for a flag). An error is
+/// printed and the CLI exits with 1.
+type CliHandler = fn(&mut CliArgs, &str, &str) -> Result<Parsed, String>;
+
+/// The handler of each option in [`cli::OPTIONS`].
+const CLI_HANDLERS: &[(&str, CliHandler)] = &[
+    ("--src", |a, _, v| {
+        a.src = Some(v.to_string());
+        Ok(Parsed::Next)
+    }),
+    ("--dst", |a, _, v| {
+        a.dst = Some(v.to_string());
+        Ok(Parsed::Next)
+    }),
+    ("--move", |a, _, _| {
+        a.do_move = true;
+        Ok(Parsed::Next)
+    }),
+    ("--remove-source-root", |a, _, _| {
+        a.opts.remove_source_root = true;
+        Ok(Parsed::Next)
+    }),
+    ("--move-strategy", |a, _, v| {
+        a.move_strategy = match v {
+            "per-file" => MoveStrategy::PerFile,
+            "all-then-delete" => MoveStrategy::AllThenDelete,
+            unknown => {
+                return Err(format!("--move-strategy expects per-file or all-then-delete, got '{}'", unknown));
+            }
+        };
+        Ok(Parsed::Next)
+    }),
+    ("--conflict", |a, _, v| {
+        a.conflict_mode = Some(match v {
+            "overwrite" => ConflictMode::Overwrite,
+            "rename" => ConflictMode::Rename,
+            "newer" => ConflictMode::Newer,
+            _ => ConflictMode::Skip,
+        });
+        Ok(Parsed::Next)
+    }),
+    ("--strip-spaces", |a, _, _| {
+        a.strip_spaces = true;
+        Ok(Parsed::Next)
+    }),
+    ("--rename-rule", |a, _, v| {
+        match Rule::parse(v) {
+            Ok(rule) => a.rename_rules.push(rule),
+            Err(e) => {
+                return Err(format!("--rename-rule {}", e));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--rename-preview", |a, _, _| {
+        a.rename_preview = true;
+        Ok(Parsed::Next)
+    }),
+    ("--mode", |a, _, v| {
+        a.transfer_mode = match v {
+            "files" => TransferMode::FilesOnly,
+            "parent" => TransferMode::FilesWithParent,
+            _ => TransferMode::FoldersAndFiles,
+        };
+        Ok(Parsed::Next)
+    }),
+    ("--method", |a, _, v| {
+        a.method_choice = MethodChoice::parse(v).unwrap_or(MethodChoice::Standard);
+        Ok(Parsed::Next)
+    }),
+    ("--exclude", cli_pattern),
+    ("--include", cli_pattern),
+    ("--exclude-from", |a, _, v| {
+        if a.profile_patterns {
+            a.patterns.clear();
+            a.profile_patterns = false;
+        }
+        match patterns::read_file(Path::new(v)) {
+            Ok(from_file) => {
+                for pattern in from_file {
+                    patterns::add_unique(&mut a.patterns, pattern);
+                }
+            }
+            Err(e) => {
+                return Err(format!("--exclude-from {}", e));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--only-user", cli_owner),
+    ("--only-group", cli_owner),
+    ("--exclude-user", cli_owner),
+    ("--exclude-group", cli_owner),
+    ("--src-files", |a, _, v| {
+        a.src_files = Some(v.split(',').map(|s| PathBuf::from(s.trim())).collect());
+        Ok(Parsed::Next)
+    }),
+    ("--src-files-from", |a, _, v| {
+        a.src_files_from = Some(PathBuf::from(v));
+        Ok(Parsed::Next)
+    }),
+    // Read before the other options.
+    ("--profile", |_, _, _| Ok(Parsed::Next)),
+    ("--failed-files-out", |a, _, v| {
+        a.failed_files_out = Some(PathBuf::from(v));
+        Ok(Parsed::Next)
+    }),
+    ("--detail-report", |a, _, v| {
+        let path = PathBuf::from(v);
+        if filereport::Format::of(&path).is_none() {
+            return Err(format!("--detail-report expects a path ending in .csv, .tsv or .jsonl, got '{}'", v));
+        }
+        a.detail_report = Some(path);
+        Ok(Parsed::Next)
+    }),
+    ("--null", |a, _, _| {
+        a.null_delimited = true;
+        Ok(Parsed::Next)
+    }),
+    ("--events", |a, _, _| {
+        a.events = true;
+        Ok(Parsed::Next)
+    }),
+    ("--events-fd", events_to_fd),
+    ("--progress", |a, _, _| {
+        a.events = true;
+        Ok(Parsed::Next)
+    }),
+    ("--progress-fd", events_to_fd),
+    ("--recheck-conflicts", |a, _, _| {
+        a.recheck_conflicts = true;
+        Ok(Parsed::Next)
+    }),
+    ("--no-recheck-rename", |a, _, _| {
+        a.recheck_rename = false;
+        Ok(Parsed::Next)
+    }),
+    ("--no-coalesce-errors", |a, _, _| {
+        a.coalesce_errors = false;
+        Ok(Parsed::Next)
+    }),
+    ("--detail-limit", |_, _, v| {
+        match v.parse::<usize>() {
+            Ok(limit) => details::set_limit(limit),
+            Err(_) => {
+                return Err(format!("--detail-limit expects a number of entries, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--chmod-dirs", |_, _, v| {
+        match folders::parse_mode(v) {
+            Some(mode) => folders::set_mode(mode),
+            None => {
+                return Err(format!("--chmod-dirs expects an octal mode such as 755, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--list-only", |a, _, _| {
+        a.list_only = true;
+        Ok(Parsed::Next)
+    }),
+    ("--dry-run", |a, _, _| {
+        a.dry_run = true;
+        activity::set_dry_run(true);
+        Ok(Parsed::Next)
+    }),
+    ("--fail-if-empty", |a, _, _| {
+        a.fail_if_empty = true;
+        Ok(Parsed::Next)
+    }),
+    ("--move-fallback", |a, _, v| {
+        a.move_fallback_copy = v == "copy";
+        Ok(Parsed::Next)
+    }),
+    ("--files-keep-parents", |a, _, v| {
+        match v.parse::<usize>() {
+            Ok(n) => a.keep_parents = n,
+            Err(_) => {
+                return Err(format!("--files-keep-parents expects a number, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--retain", |a, _, v| {
+        match v.parse::<usize>() {
+            Ok(n) if n > 0 => a.retain = Some(n),
+            _ => {
+                return Err("--retain expects the number of dated folders to keep (1 or more)".to_string());
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--retain-dry-run", |a, _, _| {
+        a.retain_dry_run = true;
+        Ok(Parsed::Next)
+    }),
+    ("--retain-to-trash", |a, _, _| {
+        a.retain_to_trash = true;
+        Ok(Parsed::Next)
+    }),
+    ("--on-quota", |a, _, v| {
+        a.on_quota = match quota::OnQuota::parse(v) {
+            Some(policy) => policy,
+            None => {
+                return Err(format!("--on-quota expects continue, skip or cancel, got '{}'", v));
+            }
+        };
+        Ok(Parsed::Next)
+    }),
+    ("--on-collision", |a, _, v| {
+        match OnCollision::parse(v) {
+            Some(choice) => a.opts.on_collision = choice,
+            None => {
+                return Err(format!("--on-collision expects rename or abort, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--relay", |a, _, v| {
+        match RelayChoice::parse(v) {
+            Some(choice) => a.opts.relay = choice,
+            None => {
+                return Err(format!("--relay expects auto, direct, scp3 or staged, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--on-unwritable", |a, _, v| {
+        a.on_unwritable = match OnUnwritable::parse(v) {
+            Some(policy) => policy,
+            None => {
+                return Err(format!("--on-unwritable expects abort, skip or continue, got '{}'", v));
+            }
+        };
+        Ok(Parsed::Next)
+    }),
+    ("--stale-artifacts", |a, _, v| {
+        match stale::OnStale::parse(v) {
+            Some(choice) => a.stale_artifacts = Some(choice),
+            None => {
+                return Err(format!("--stale-artifacts expects resume, clean or ignore, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--scan-limit", |a, _, v| {
+        match v.parse::<usize>() {
+            Ok(files) => a.opts.scan_limits.files = files,
+            Err(_) => {
+                return Err(format!("--scan-limit expects a number of files (0 for no limit), got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--scan-time-limit", |a, _, v| {
+        match v.parse::<u64>() {
+            Ok(secs) => a.opts.scan_limits.secs = secs,
+            Err(_) => {
+                return Err(format!("--scan-time-limit expects a number of seconds (0 for no limit), got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--scan-limit-action", |a, _, v| {
+        a.scan_limit_action = match scanguard::Action::parse(v) {
+            Some(action) => action,
+            None => {
+                return Err(format!("--scan-limit-action expects ask, continue or abort, got '{}'", v));
+            }
+        };
+        Ok(Parsed::Next)
+    }),
+    ("--on-cancel-during-verify", |a, _, v| {
+        a.on_cancel_during_verify = match OnCancelDuringVerify::parse(v) {
+            Some(policy) => policy,
+            None => {
+                return Err(format!("--on-cancel-during-verify expects keep or discard, got '{}'", v));
+            }
+        };
+        Ok(Parsed::Next)
+    }),
+    ("--duplicate-window", |a, _, v| {
+        match v.parse::<u64>() {
+            Ok(hours) => a.duplicate_window = hours,
+            Err(_) => {
+                return Err(format!("--duplicate-window expects a number of hours (0 to turn the check off), got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--refuse-duplicate-runs", |a, _, _| {
+        a.refuse_duplicate_runs = true;
+        Ok(Parsed::Next)
+    }),
+    ("--case-insensitive-paths", |a, _, _| {
+        a.case_insensitive_paths = true;
+        Ok(Parsed::Next)
+    }),
+    ("--no-source-config", |a, _, _| {
+        a.source_config = false;
+        Ok(Parsed::Next)
+    }),
+    ("--hash-algo", |a, _, v| {
+        match HashAlgo::parse(v) {
+            Some(algo) => a.opts.hash_algo = algo,
+            None => {
+                return Err(format!("--hash-algo expects sha256, blake3 or xxh128, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--verify", |a, _, v| {
+        match VerifyMode::parse(v) {
+            Some(mode) => a.opts.verify = mode,
+            None => {
+                return Err(format!("--verify expects full, hash, quick or none, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--hash-cache", |a, _, v| {
+        a.hash_cache = Some(PathBuf::from(v));
+        Ok(Parsed::Next)
+    }),
+    ("--paranoid", |a, _, _| {
+        a.paranoid = true;
+        Ok(Parsed::Next)
+    }),
+    ("--verify-sample", |a, _, v| {
+        match v.trim_end_matches('%').parse::<f64>() {
+            Ok(p) if p > 0.0 && p <= 100.0 => a.verify_sample = Some(p),
+            _ => {
+                return Err(format!("--verify-sample expects a percentage above 0 and at most 100, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--verify-sample-min-size", |a, _, v| {
+        match v.parse::<u64>() {
+            Ok(bytes) => a.verify_sample_min_size = Some(bytes),
+            Err(_) => {
+                return Err(format!("--verify-sample-min-size expects a size in bytes, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--verify-sample-seed", |a, _, v| {
+        match v.parse::<u64>() {
+            Ok(seed) => a.verify_sample_seed = Some(seed),
+            Err(_) => {
+                return Err(format!("--verify-sample-seed expects a number, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--accept-new-hostkeys", |_, _, _| {
+        ssh::accept_new_hostkeys();
+        Ok(Parsed::Next)
+    }),
+    ("--file-timeout", |_, _, v| {
+        match deadline::FileTimeout::parse(v) {
+            Some(policy) => deadline::set(policy),
+            None => {
+                return Err(format!("--file-timeout expects seconds (0 for none) or a rate such as 1M/s-min, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--ssh-control-dir", |_, _, v| {
+        ssh::set_control_dir(PathBuf::from(v));
+        Ok(Parsed::Next)
+    }),
+    ("--low-priority", |a, _, _| {
+        a.opts.low_priority = true;
+        Ok(Parsed::Next)
+    }),
+    ("--ignore-free-space", |a, _, _| {
+        a.opts.ignore_free_space = true;
+        Ok(Parsed::Next)
+    }),
+    ("--preserve", |a, _, _| {
+        a.opts.preserve = true;
+        Ok(Parsed::Next)
+    }),
+    ("--symlinks", |a, _, v| {
+        match symlinks::Policy::parse(v) {
+            Some(policy) => a.opts.symlinks = policy,
+            None => {
+                return Err(format!("--symlinks expects skip, follow or preserve, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--parallel", |a, _, v| {
+        match v.parse::<usize>() {
+            Ok(n) if (1..=parallel::MAX_WORKERS).contains(&n) => a.opts.workers = n,
+            _ => {
+                return Err(format!("--parallel expects a number of files from 1 to {}, got '{}'", parallel::MAX_WORKERS, v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--retries", |a, _, v| {
+        match v.parse::<u32>() {
+            Ok(n) if n <= retry::MAX_RETRIES => a.opts.retries = n,
+            _ => {
+                return Err(format!("--retries expects a number from 0 to {}, got '{}'", retry::MAX_RETRIES, v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--bwlimit", |a, _, v| {
+        match v.parse::<u64>() {
+            Ok(n) if n <= throttle::MAX_LIMIT => a.opts.throttle.limit = n,
+            _ => {
+                return Err(format!("--bwlimit expects KB/s from 0 to {}, got '{}'", throttle::MAX_LIMIT, v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--compress", |a, _, v| {
+        match Compress::parse(v) {
+            Some(compress) => a.opts.throttle.compress = compress,
+            None => {
+                return Err(format!("--compress expects auto, always or never, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--print-commands", |_, _, _| {
+        activity::print_commands(true);
+        Ok(Parsed::Next)
+    }),
+    ("--timestamps", |_, _, v| {
+        match Timestamps::parse(v) {
+            Some(zone) => timeutil::set_timestamps(zone),
+            None => {
+                return Err(format!("--timestamps expects utc or local, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--max-clock-skew", |_, _, v| {
+        match timeutil::parse_duration(v, std::time::Duration::from_secs(1)) {
+            Some(skew) => clock::set_max(skew.as_secs() as i64),
+            None => {
+                return Err(format!("--max-clock-skew expects seconds or a duration such as 5m, got '{}'", v));
+            }
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--stall-after", |a, _, v| {
+        if let Some(after) = timeutil::parse_duration(v, std::time::Duration::from_secs(1)) {
+            a.stall_after = after;
+        }
+        Ok(Parsed::Next)
+    }),
+    ("--generate-man", |_, _, _| {
+        print!("{}", cli::man_page());
+        Ok(Parsed::Exit(0))
+    }),
+    ("--print-paths", |_, _, _| {
+        let fields: Vec<String> = paths::Dir::ALL
+            .iter()
+            .map(|&d| {
+                let path = paths::dir(d).map_or("null".to_string(), |p| json_string(&p.to_string_lossy()));
+                format!("\"{}\":{}", d.name(), path)
+            })
+            .collect();
+        println!("{{{}}}", fields.join(","));
+        Ok(Parsed::Exit(0))
+    }),
+    ("--capabilities", |_, _, _| {
+        let tools: Vec<cli::Tool> = cli::transport_tools().into_iter().map(probe_tool).collect();
+        println!("{}", cli::capabilities(&tools));
+        Ok(Parsed::Exit(0))
+    }),
+    ("--generate-completions", |_, _, v| {
+        match cli::completions(v) {
+            Some(script) => {
+                print!("{}", script);
+                Ok(Parsed::Exit(0))
+            }
+            None => Err(format!("--generate-completions expects one of: {}", cli::SHELLS.join(", "))),
+        }
+    }),
+];
 
-    // Manual pattern entry row
-    let pattern_row = GtkBox::new(Orientation::Horizontal, 8);
-    let pattern_entry = Entry::new();
-    pattern_entry.set_hexpand(true);
-    pattern_entry.set_placeholder_text(Some("Pattern (e.g. *.jpg, /tmp*, test_*)"));
-    let btn_add_file_pattern = Button::with_label("+ File Pattern");
-    let btn_add_dir_pattern = Button::with_label("+ Dir Pattern");
-    pattern_row.append(&pattern_entry);
-    pattern_row.append(&btn_add_file_pattern);
-    pattern_row.append(&btn_add_dir_pattern);
-    root.append(&pattern_row);
+fn cli_handler(name: &str) -> Option<CliHandler> {
+    CLI_HANDLERS.iter().find(|(n, _)| *n == name).map(|&(_, handle)| handle)
+}
 
-    let excl_view = TextView::new();
-    excl_view.set_editable(false);
-    excl_view.set_cursor_visible(false);
-    excl_view.set_wrap_mode(WrapMode::WordChar);
-    excl_view.set_monospace(true);
+/// `--exclude` and `--include`; the first one replaces a profile's list.
+fn cli_pattern(a: &mut CliArgs, name: &str, v: &str) -> Result<Parsed, String> {
+    if a.profile_patterns {
+        a.patterns.clear();
+        a.profile_patterns = false;
+    }
+    let checked = if name == "--exclude" {
+        patterns::normalize_stored(v)
+    } else {
+        patterns::normalize(PatternKind::Include, v)
+    };
+    match checked {
+        Ok(pattern) => {
+            patterns::add_unique(&mut a.patterns, pattern);
+        }
+        Err(e) => {
+            return Err(format!("{} {}: {}", name, v, e));
+        }
+    }
+    Ok(Parsed::Next)
+}
 
-    let excl_scroll = ScrolledWindow::builder()
-        .child(&excl_view)
-        .min_content_height(80)
-        .vexpand(true)
-        .build();
-    root.append(&excl_scroll);
+/// `--only-user`, `--only-group`, `--exclude-user` and `--exclude-group`.
+fn cli_owner(a: &mut CliArgs, name: &str, v: &str) -> Result<Parsed, String> {
+    let resolved = match name {
+        "--only-user" | "--exclude-user" => owners::resolve_user(v),
+        _ => owners::resolve_group(v),
+    };
+    let owner = match resolved {
+        Ok(owner) => owner,
+        Err(e) => {
+            return Err(format!("{} {}: {}", name, v, e));
+        }
+    };
+    match name {
+        "--only-user" => a.owner_filter.only_users.push(owner),
+        "--only-group" => a.owner_filter.only_groups.push(owner),
+        "--exclude-user" => a.owner_filter.exclude_users.push(owner),
+        _ => a.owner_filter.exclude_groups.push(owner),
+    }
+    Ok(Parsed::Next)
+}
 
-    // Shared exclusion state: dirs stored as "/dirname", files as "filename",
-    // wildcard dir patterns as "~/pattern", wildcard file patterns as "~pattern"
-    let exclusions: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
-
-    // ── Conflict handling ──────────────────────────────────────────
-    let conflict_label = Label::new(Some("If file already exists:"));
-    conflict_label.set_halign(Align::Start);
-    root.append(&conflict_label);
 
-    let conflict_row = GtkBox::new(Orientation::Horizontal, 12);
-    let chk_skip = CheckButton::with_label("Skip");
-    chk_skip.set_active(true);
-    let chk_overwrite = CheckButton::with_label("Overwrite");
-    chk_overwrite.set_group(Some(&chk_skip));
-    let chk_rename = CheckButton::with_label("Auto-rename");
-    chk_rename.set_group(Some(&chk_skip));
-    conflict_row.append(&chk_skip);
-    conflict_row.append(&chk_overwrite);
-    conflict_row.append(&chk_rename);
-    root.append(&conflict_row);
+/// Options are defined in [`cli::OPTIONS`]; `--generate-man` prints the
+/// full reference, including the JSON output schema.
+fn run_cli(args: &[String]) -> i32 {
+    let settings = config::load();
+    let auto_thresholds = settings.auto_thresholds();
+    let mut a = CliArgs::new(&settings);
+    if let Some(dir) = settings.ssh_control_dir {
+        ssh::set_control_dir(dir);
+    }
+    timeutil::set_timestamps(settings.timestamps);
+
+    // A profile's choices come first; the options given override them.
+    let mut profile_source: Option<String> = None;
+    if let Some(name) = cli::value_of(args, "--profile") {
+        let profile = match profiles::load(name) {
+            Ok(profile) => profile,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let saved = profiles::list();
+                if saved.is_empty() {
+                    eprintln!("--profile: no profile named '{}'; none are saved", name);
+                } else {
+                    eprintln!("--profile: no profile named '{}'; saved profiles: {}", name, saved.join(", "));
+                }
+                return 1;
+            }
+            Err(e) => {
+                eprintln!("--profile {}: {}", name, e);
+                return 1;
+            }
+        };
+        profile_source = profile.source;
+        a.dst = profile.destination;
+        a.do_move = profile.do_move.unwrap_or(a.do_move);
+        a.transfer_mode = profile.transfer_mode.unwrap_or(a.transfer_mode);
+        a.method_choice = profile.method.unwrap_or(a.method_choice);
+        a.conflict_mode = profile.conflict;
+        a.strip_spaces = profile.strip_spaces.unwrap_or(a.strip_spaces);
+        if let Some(list) = profile.patterns {
+            a.patterns = list;
+            a.profile_patterns = true;
+        }
+    }
 
-    let chk_strip_spaces = CheckButton::with_label("Remove spaces from filenames");
-    chk_strip_spaces.set_active(false);
-    root.append(&chk_strip_spaces);
+    let mut i = 0;
+    while i < args.len() {
+        let Some(opt) = cli::find(&args[i]) else {
+            eprintln!("Unknown option: {}", args[i]);
+            return 1;
+        };
+        if opt.takes_value() && i + 1 >= args.len() {
+            eprintln!("{} requires a value", opt.name);
+            return 1;
+        }
+        let value = if opt.takes_value() {
+            i += 1;
+            args[i].as_str()
+        } else {
+            ""
+        };
+        let Some(handle) = cli_handler(opt.name) else {
+            eprintln!("Unknown option: {}", opt.name);
+            return 1;
+        };
+        match handle(&mut a, opt.name, value) {
+            Ok(Parsed::Next) => {}
+            Ok(Parsed::Exit(code)) => return code,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        }
+        i += 1;
+    }
+    let CliArgs {
+        src,
+        dst,
+        mut do_move,
+        move_strategy,
+        conflict_mode,
+        strip_spaces,
+        rename_rules,
+        rename_preview,
+        transfer_mode,
+        keep_parents,
+        patterns,
+        src_files,
+        src_files_from,
+        failed_files_out,
+        detail_report,
+        null_delimited,
+        events,
+        stall_after,
+        move_fallback_copy,
+        recheck_conflicts,
+        recheck_rename,
+        coalesce_errors,
+        list_only,
+        dry_run,
+        fail_if_empty,
+        retain,
+        retain_dry_run,
+        retain_to_trash,
+        on_unwritable,
+        stale_artifacts,
+        on_quota,
+        on_cancel_during_verify,
+        method_choice,
+        duplicate_window,
+        opts,
+        scan_limit_action,
+        refuse_duplicate_runs,
+        case_insensitive_paths,
+        source_config,
+        hash_cache,
+        paranoid,
+        owner_filter,
+        verify_sample,
+        verify_sample_min_size,
+        verify_sample_seed,
+        ..
+    } = a;
+    let owner_filter_active = !owner_filter.is_empty();
+    owners::set(owner_filter);
+    let recheck = match (recheck_conflicts, recheck_rename) {
+        (true, _) => Recheck::Everything,
+        (false, true) => Recheck::RenameTargets,
+        (false, false) => Recheck::Nothing,
+    };
 
-    root.append(&Separator::new(Orientation::Horizontal));
+    let dst = match dst {
+        Some(d) => d,
+        // Nothing is written when only listing.
+        None if list_only || rename_preview => String::new(),
+        None => {
+            eprintln!("--dst is required");
+            return 1;
+        }
+    };
 
-    // ── Progress area ─────────────────────────────────────────────────
-    let progress_bar = ProgressBar::new();
-    progress_bar.set_show_text(true);
-    progress_bar.set_text(Some("Ready"));
-    root.append(&progress_bar);
+    // A `{date}` in the last folder name makes each run a new dated folder.
+    let dated = retention::split_dest(&dst).map(|(base, template)| (base.to_string(), template));
+    if retain.is_some() && dated.is_none() {
+        eprintln!("--retain needs a destination whose last folder name contains {{date}}, e.g. /backups/weekly-{{date}}");
+        return 1;
+    }
+    let dst = match &dated {
+        Some((base, template)) => format!("{}/{}", base, template.expand(Date::today())),
+        None => dst,
+    };
 
-    let status_label = Label::new(Some(""));
-    status_label.set_halign(Align::Start);
-    status_label.set_wrap(true);
-    root.append(&status_label);
+    if let Some(e) = verify::move_error(opts.verify, do_move) {
+        eprintln!("--verify none cannot be used with --move: {}", e.to_lowercase());
+        return 1;
+    }
+    if opts.verify == VerifyMode::None && verify_sample.is_some() {
+        eprintln!("--verify-sample needs a verification other than --verify none");
+        return 1;
+    }
+    match verify_sample {
+        // A move deletes each source once its copy checks out; with a
+        // sample, most copies would never be checked.
+        Some(_) if do_move => {
+            eprintln!("--verify-sample cannot be used with --move: the sources of unverified files would be deleted");
+            return 1;
+        }
+        Some(_) if paranoid => {
+            eprintln!("--verify-sample and --paranoid cannot be used together");
+            return 1;
+        }
+        Some(percent) => sample::enable(sample::SampleSpec {
+            percent,
+            min_size: verify_sample_min_size,
+            seed: verify_sample_seed.unwrap_or_else(sample::fresh_seed),
+        }),
+        None if verify_sample_min_size.is_some() || verify_sample_seed.is_some() => {
+            eprintln!("--verify-sample-min-size and --verify-sample-seed need --verify-sample");
+            return 1;
+        }
+        None => {}
+    }
 
-    // ── Start button ──────────────────────────────────────────────────
-    let btn_start = Button::with_label("Transfer");
-    btn_start.add_css_class("suggested-action");
-    root.append(&btn_start);
+    // Build source selection
+    let source_sel = if let Some(list) = src_files_from {
+        match read_failed_list(&list)
+            .map_err(|e| format!("Cannot read {}: {}", list.display(), e))
+            .and_then(selection_from_list)
+        {
+            Ok(sel) => sel,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        }
+    } else if let Some(files) = src_files {
+        SourceSelection::Files(files)
+    } else if let Some(s) = src {
+        let (host, path) = parse_destination(&s);
+        match host {
+            Some(h) => SourceSelection::Remote(h, path),
+            None => SourceSelection::Directory(PathBuf::from(path)),
+        }
+    } else if let Some(s) = profile_source {
+        // As typed in the window, where a local file is a one-file source.
+        match parse_destination(&s) {
+            (Some(h), path) => SourceSelection::Remote(h, path),
+            (None, path) if Path::new(&path).is_file() => SourceSelection::Files(vec![PathBuf::from(path)]),
+            (None, path) => SourceSelection::Directory(PathBuf::from(path)),
+        }
+    } else {
+        eprintln!("--src, --src-files, --src-files-from or a --profile with a source is required");
+        return 1;
+    };
 
-    // ── Cancel button (hidden until a transfer is running) ────────────
-    let btn_cancel = Button::with_label("Cancel");
-    btn_cancel.add_css_class("destructive-action");
-    btn_cancel.set_visible(false);
-    root.append(&btn_cancel);
+    // A folder source's own `.kosmokopy.toml` fills in what no flag chose.
+    let mut chosen = sourceconf::Chosen {
+        patterns,
+        conflict: conflict_mode,
+        rename_rules: (!rename_rules.is_empty()).then_some(rename_rules),
+        strip_spaces,
+    };
+    let source_note = match &source_sel {
+        SourceSelection::Directory(root) if source_config => sourceconf::merge(root, &mut chosen),
+        _ => None,
+    };
+    if let Some(note) = &source_note {
+        eprintln!("{}", note);
+    }
+    let sourceconf::Chosen { patterns, conflict, rename_rules, strip_spaces } = chosen;
+    let conflict_mode = conflict.unwrap_or(ConflictMode::Skip);
+    let rename_rules = rename_rules.unwrap_or_default();
 
-    window.set_child(Some(&root));
+    if list_only {
+        return list_sources(&source_sel, &patterns, opts.symlinks, null_delimited, fail_if_empty);
+    }
 
-    // ── Shared source-selection state ─────────────────────────────────
-    let source_selection = Rc::new(RefCell::new(SourceSelection::None));
+    // --strip-spaces is the first rule of the pipeline.
+    let mut renamer = Renamer::new(strip_spaces.then(Rule::strip_spaces).into_iter().chain(rename_rules).collect());
+    // A single file sent to a path that names a file lands at exactly that path.
+    if let Some(e) = local_destination_error(&source_sel, &dst).filter(|_| !rename_preview) {
+        println!("{{\"status\":\"error\",\"message\":{}}}", json_string(&e));
+        return 1;
+    }
+    if let Some(e) = source_overlap_error(&source_sel, &dst, case_insensitive_paths).filter(|_| !rename_preview) {
+        println!("{{\"status\":\"error\",\"message\":{}}}", json_string(&e));
+        return 1;
+    }
+    if let Some(e) = tar_method_error(method_choice, &source_sel, &dst, conflict_mode).filter(|_| !rename_preview) {
+        println!("{{\"status\":\"error\",\"message\":{}}}", json_string(&format!("{}.", e)));
+        return 1;
+    }
+    if let Some(e) = symlinks::move_error(opts.symlinks, do_move).filter(|_| !rename_preview) {
+        println!("{{\"status\":\"error\",\"message\":{}}}", json_string(&format!("{}.", e)));
+        return 1;
+    }
+    let dst = match file_target(&source_sel, &dst) {
+        Some((dir, name)) => {
+            debug_log(&format!("{} names the destination file", dst));
+            if let Some(note) = replace_note(&dir, &name, conflict_mode).filter(|_| !rename_preview) {
+                eprintln!("{}.", note);
+            }
+            renamer = renamer.with_target(name);
+            dir
+        }
+        None => dst,
+    };
+    let renamer = Arc::new(renamer);
+    if rename_preview {
+        return preview_renames(&source_sel, &patterns, opts.symlinks, &renamer);
+    }
+    if dry_run {
+        return print_dry_run(&source_sel, &dst, &patterns, &opts, &renamer, transfer_mode, keep_parents, conflict_mode, do_move);
+    }
 
-    // ── Browse Folder button ──────────────────────────────────────────
-    {
-        let win_clone = window.clone();
-        let src_entry_c = src_entry.clone();
-        let source_sel = source_selection.clone();
-        btn_browse_folder.connect_clicked(move |_| {
-            let dialog = FileDialog::builder()
-                .title("Select source folder")
-                .modal(true)
-                .build();
-            let src_entry_c2 = src_entry_c.clone();
-            let source_sel2 = source_sel.clone();
-            dialog.select_folder(
-                Some(&win_clone),
-                gtk4::gio::Cancellable::NONE,
-                move |result| {
-                    if let Ok(file) = result {
-                        if let Some(path) = file.path() {
-                            src_entry_c2.set_text(&path.to_string_lossy());
-                            *source_sel2.borrow_mut() = SourceSelection::Directory(path);
-                        }
-                    }
-                },
+    // Moving off a read-only source would copy everything and then fail
+    // every deletion, so decide up front.
+    let mut extras = CliExtras {
+        coalesce_after: coalesce_errors.then_some(DEFAULT_COALESCE_AFTER),
+        source_config: source_note,
+        ..CliExtras::default()
+    };
+    if do_move && source_is_read_only(&source_sel) {
+        if !move_fallback_copy {
+            println!(
+                "{{\"status\":\"error\",\"message\":{}}}",
+                json_string("Source is read-only, so files cannot be moved. Use --move-fallback copy to copy them instead.")
             );
-        });
+            return 1;
+        }
+        eprintln!("Source is read-only — copying instead of moving.");
+        do_move = false;
+        extras.move_downgraded = true;
     }
 
-    // ── Browse Files button ───────────────────────────────────────────
-    {
-        let win_clone = window.clone();
-        let src_entry_c = src_entry.clone();
-        let source_sel = source_selection.clone();
-        btn_browse_files.connect_clicked(move |_| {
-            let dialog = FileDialog::builder()
-                .title("Select files")
-                .modal(true)
-                .build();
-            let src_entry_c2 = src_entry_c.clone();
-            let source_sel2 = source_sel.clone();
-            dialog.open_multiple(
-                Some(&win_clone),
-                gtk4::gio::Cancellable::NONE,
-                move |result| {
-                    if let Ok(files) = result {
-                        let mut paths = Vec::new();
-                        for i in 0..files.n_items() {
-                            if let Some(obj) = files.item(i) {
-                                if let Ok(gfile) = obj.downcast::<gtk4::gio::File>() {
-                                    if let Some(p) = gfile.path() {
-                                        paths.push(p);
-                                    }
-                                }
-                            }
-                        }
-                        if !paths.is_empty() {
-                            let display = if paths.len() == 1 {
-                                paths[0].to_string_lossy().to_string()
-                            } else {
-                                format!("{} files selected", paths.len())
-                            };
-                            src_entry_c2.set_text(&display);
-                            *source_sel2.borrow_mut() = SourceSelection::Files(paths);
-                        }
-                    }
-                },
+    // The same transfer finished a short while ago: most likely started
+    // twice by mistake.
+    let fingerprint = transfer_options(
+        &source_sel, &dst, do_move, move_strategy, conflict_mode, &renamer, transfer_mode, keep_parents,
+        method_choice, &patterns,
+    )
+    .fingerprint();
+    if let Some(previous) = history::recent_duplicate(&history::load(), &fingerprint, history::now(), duplicate_window) {
+        let warning = describe_duplicate(previous);
+        if refuse_duplicate_runs {
+            println!(
+                "{{\"status\":\"error\",\"kind\":\"duplicate-run\",\"message\":{}}}",
+                json_string(&format!("{}; not starting it again (--refuse-duplicate-runs).", warning))
             );
-        });
+            return 1;
+        }
+        warn(events, &warning);
+        extras.warnings.push(warning);
     }
 
-    // ── Destination browse ────────────────────────────────────────────
+    let (tx, rx) = mpsc::channel::<WorkerMsg>();
+    let cancel_flag = Arc::new(CancelFlag::default());
+
+    // Handle Ctrl+C gracefully in CLI mode.  A worker waiting on a child
+    // command would not see the flag until it exits, so the child is asked
+    // to stop too; a second Ctrl+C gives up on the worker (see below).
+    // With `--on-cancel-during-verify keep`, a Ctrl+C while a file is being
+    // verified only skips the remaining verification, leaving the check
+    // under way to finish.
+    let interrupts = Arc::new(AtomicUsize::new(0));
+    let verifying = Arc::new(AtomicBool::new(false));
     {
-        let win_clone = window.clone();
-        let dst_entry_c = dst_entry.clone();
-        dst_row.1.connect_clicked(move |_| {
-            pick_folder(&win_clone, dst_entry_c.clone());
+        let cancel_flag_c = cancel_flag.clone();
+        let interrupts_c = interrupts.clone();
+        let verifying_c = verifying.clone();
+        let _ = ctrlc::set_handler(move || {
+            let intent = on_cancel_during_verify.intent(verifying_c.load(Ordering::SeqCst), cancel_flag_c.intent());
+            cancel_flag_c.request(intent);
+            if intent == CancelIntent::SkipVerify {
+                eprintln!("\nSkipping the remaining verification (Ctrl+C again to cancel)…");
+                return;
+            }
+            if interrupts_c.fetch_add(1, Ordering::SeqCst) == 0 {
+                eprintln!("\nCancelling…");
+            } else {
+                eprintln!(
+                    "\nCancelling (giving up in {} seconds)…",
+                    FORCED_CANCEL_AFTER.as_secs()
+                );
+            }
+            activity::terminate_children();
         });
     }
 
-    // ── Browse Remote — Source ─────────────────────────────────────────
-    {
-        let win_clone = window.clone();
-        let src_entry_c = src_entry.clone();
-        let source_sel = source_selection.clone();
-        btn_browse_remote_src.connect_clicked(move |_| {
-            let current = src_entry_c.text().to_string();
-            let src_entry_c2 = src_entry_c.clone();
-            let source_sel2 = source_sel.clone();
-            show_remote_browser(&win_clone, &current, false, move |selected| {
-                src_entry_c2.set_text(&selected);
-                // Parse as Remote source
-                if let Some(pos) = selected.find(':') {
-                    let host = selected[..pos].to_string();
-                    let path = selected[pos + 1..].to_string();
-                    *source_sel2.borrow_mut() = SourceSelection::Remote(host, path);
-                }
-            });
-        });
+    let src_is_remote = source_sel.remote_parts().is_some();
+    let (dst_host, dest_path) = parse_destination(&dst);
+    if do_move && dst_host.is_none() && is_cross_fs_move(&source_sel, &dest_path) {
+        eprintln!("{}.", CROSS_FS_MOVE_NOTE);
+    }
+    if dst_host.is_none() {
+        if let Some(warning) = overlap_warning(&source_sel, &dest_path) {
+            warn(events, &warning);
+        }
+    }
+    // Only the local copier compares both sides itself; rsync and the
+    // remote workers verify with checksums computed on each end.  Moves
+    // delete their sources, so they never go on the cache's word.
+    let local_copy = !do_move && !src_is_remote && dst_host.is_none() && method_choice != MethodChoice::Rsync;
+    if hash_cache.is_some() && !local_copy {
+        warn(events, "--hash-cache only applies to local copies with the standard method; ignoring it");
     }
+    let hash_cache = hash_cache.filter(|_| local_copy).map(|path| HashCache::load(&path, !paranoid));
 
-    // ── Browse Remote — Destination ───────────────────────────────────
-    {
-        let win_clone = window.clone();
-        let dst_entry_c = dst_entry.clone();
-        btn_browse_remote_dst.connect_clicked(move |_| {
-            let current = dst_entry_c.text().to_string();
-            let dst_entry_c2 = dst_entry_c.clone();
-            show_remote_browser(&win_clone, &current, true, move |selected| {
-                dst_entry_c2.set_text(&selected);
-            });
-        });
+    quota::reset();
+    if let Some(path) = &detail_report {
+        if let Err(e) = filereport::open(path) {
+            eprintln!("Could not write the detail report {}: {}", path.display(), e);
+            return 1;
+        }
     }
 
-    // ── Exclusion buttons ─────────────────────────────────────────────
-    {
-        let win = window.clone();
-        let source_sel = source_selection.clone();
-        let excls = exclusions.clone();
-        let view = excl_view.clone();
-        btn_excl_dirs.connect_clicked(move |_| {
-            let src = source_sel.borrow().clone();
-            let initial = match &src {
-                SourceSelection::Directory(p) => Some(p.clone()),
-                _ => None,
-            };
-            let dialog = FileDialog::builder()
-                .title("Select directory to exclude")
-                .modal(true)
-                .build();
-            if let Some(ref dir) = initial {
-                dialog.set_initial_folder(Some(&gtk4::gio::File::for_path(dir)));
-            }
-            let excls2 = excls.clone();
-            let view2 = view.clone();
-            dialog.select_folder(Some(&win), gtk4::gio::Cancellable::NONE, move |result| {
-                if let Ok(file) = result {
-                    if let Some(path) = file.path() {
-                        let dir_name = path
-                            .file_name()
-                            .map(|n| n.to_string_lossy().to_string())
-                            .unwrap_or_default();
-                        let entry = format!("/{}", dir_name);
-                        let mut list = excls2.borrow_mut();
-                        if !list.contains(&entry) {
-                            list.push(entry);
-                        }
-                        refresh_exclusion_view(&view2, &list);
+    // Run the worker on its own thread so progress can be drawn while it
+    // works; the JSON result is still printed once it finishes.
+    let worker_cancel = cancel_flag.clone();
+    let worker_renamer = renamer.clone();
+    let worker = thread::spawn(move || {
+        if let Some(outcome) = priority::lower_this_thread(opts.low_priority) {
+            debug_log(&outcome.describe());
+        }
+        let transfer_method = method_choice
+            .fixed()
+            .unwrap_or_else(|| auto_method(&source_sel, dst_host.as_deref(), auto_thresholds, &tx));
+        // A cancel while Auto probed the hosts ends the run here.
+        if worker_cancel.stopping() {
+            let _ = tx.send(cancelled_before_transfer(Details::skipped(), 0, 0));
+            return;
+        }
+        match (src_is_remote, dst_host, transfer_method) {
+            // The tar method only uploads; anything else is refused before
+            // the run starts (see `tar_method_error`).
+            (true, Some(dhost), TransferMethod::Standard | TransferMethod::Tar) => {
+                if let Some((shost, spath, sfiles)) = source_sel.remote_parts() {
+                    run_remote_to_remote_worker(
+                        shost, spath, sfiles, &dhost, &dest_path, do_move, move_strategy, conflict_mode, recheck,
+                        &worker_renamer, transfer_mode, keep_parents, &patterns, &opts, worker_cancel, tx,
+                    );
+                }
+            }
+            (true, Some(dhost), TransferMethod::Rsync) => {
+                if let Some((shost, spath, sfiles)) = source_sel.remote_parts() {
+                    run_remote_to_remote_rsync_worker(
+                        shost, spath, sfiles, &dhost, &dest_path, do_move, move_strategy, conflict_mode, recheck,
+                        &worker_renamer, transfer_mode, keep_parents, &patterns, &opts, worker_cancel, tx,
+                    );
+                }
+            }
+            (true, None, method) => {
+                if let Some((shost, spath, sfiles)) = source_sel.remote_parts() {
+                    run_remote_to_local_worker(
+                        shost, spath, sfiles, &dest_path, do_move, move_strategy, conflict_mode,
+                        &worker_renamer, transfer_mode, keep_parents, &patterns, method, &opts, worker_cancel, tx,
+                    );
+                }
+            }
+            (false, Some(host), method @ (TransferMethod::Standard | TransferMethod::Tar)) => run_remote_worker(
+                source_sel, &host, &dest_path, do_move, move_strategy, conflict_mode, recheck,
+                &worker_renamer, transfer_mode, keep_parents, &patterns, method, &opts, worker_cancel, tx,
+            ),
+            (false, Some(host), TransferMethod::Rsync) => run_remote_rsync_worker(
+                source_sel, &host, &dest_path, do_move, move_strategy, conflict_mode, recheck,
+                &worker_renamer, transfer_mode, keep_parents, &patterns, &opts, worker_cancel, tx,
+            ),
+            (false, None, TransferMethod::Rsync) => run_local_rsync_worker(
+                source_sel, dest_path, do_move, move_strategy, conflict_mode,
+                &worker_renamer, transfer_mode, keep_parents, &patterns, &opts, worker_cancel, tx,
+            ),
+            (false, None, TransferMethod::Standard | TransferMethod::Tar) => run_worker(
+                source_sel, dest_path, do_move, move_strategy, conflict_mode,
+                &worker_renamer, transfer_mode, keep_parents, &patterns, hash_cache, &opts, worker_cancel, tx,
+            ),
+        }
+    });
+
+    // Collect results from the worker.  Progress is only drawn when stderr
+    // is a terminal (and NDJSON events weren't asked for), so scripted
+    // callers just see the JSON on stdout.
+    let show_progress = !events && std::io::stderr().is_terminal();
+    let mut model = ProgressModel::default();
+    let mut throughput = Throughput::default();
+    let mut stall = StallWatch::new(stall_after);
+    let started = std::time::Instant::now();
+    let mut last_file: Option<String> = None;
+    let mut forced_at: Option<std::time::Instant> = None;
+    // Set when the error that follows has a specific cause.
+    let mut error_kind: Option<&str> = None;
+    let mut shortfall: Option<space::Shortfall> = None;
+    let save_failed = |failed: &[OsString]| {
+        if let Some(path) = &failed_files_out {
+            if let Err(e) = write_failed_list(path, failed, null_delimited) {
+                eprintln!("Could not write failed-files list {}: {}", path.display(), e);
+            }
+        }
+    };
+    loop {
+        // After a second Ctrl+C the worker gets a few seconds to report;
+        // its result is what counts, this is only the fallback.
+        if interrupts.load(Ordering::SeqCst) >= 2 {
+            let since = *forced_at.get_or_insert_with(std::time::Instant::now);
+            if since.elapsed() >= FORCED_CANCEL_AFTER {
+                if show_progress { eprintln!(); }
+                println!("{}", forced_cancel_json(model.done(), model.total(), last_file.as_deref(), model.phase()));
+                return 3;
+            }
+        }
+        // Wake up periodically so a worker blocked in a child command can
+        // still be reported as stalled.
+        let msg = match rx.recv_timeout(std::time::Duration::from_millis(250)) {
+            Ok(msg) => msg,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let current = activity::global().current();
+                if let (Some(warning), Some(act)) = (stall.check(current.as_ref()), &current) {
+                    if show_progress { eprintln!(); }
+                    if events {
+                        activity::event("stall", &format!("\"phase\":\"{}\",\"activity\":{},\"seconds\":{},\"message\":{}",
+                            model.phase().name(),
+                            json_string(&act.text),
+                            act.started.elapsed().as_secs(),
+                            json_string(&warning)
+                        ));
+                    } else {
+                        eprintln!("Warning: {}", warning);
                     }
                 }
-            });
-        });
+                continue;
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+        match msg {
+            WorkerMsg::Finished(mut report) => {
+                if show_progress { eprintln!(); }
+                save_failed(&report.failed);
+                extras.groups = Some(std::mem::take(&mut report.groups));
+                let copied = report.copied;
+                // Old dated folders are only pruned after a clean run.
+                if let (Some(keep), Some((base, template)), true) = (retain, &dated, report.errors.is_empty()) {
+                    extras.pruned = Some(prune_dated(base, template, keep, retain_dry_run, retain_to_trash, &mut report.errors));
+                    extras.prune_dry_run = retain_dry_run;
+                }
+                let renames = renamer.log();
+                if renames.count > 0 {
+                    debug_log(&renames.describe());
+                }
+                extras.renames = Some(renames);
+                extras.folders = Some(folders::take());
+                extras.source_dirs_removed = emptied::take();
+                extras.warnings.extend(preserve::take());
+                extras.warnings.extend(collisions::take());
+                extras.timing = Some(timing::take());
+                extras.quota = quota::take();
+                extras.verify_sample = sample::report().map(|report| (report, copied));
+                extras.low_priority = priority::report();
+                extras.filtered_by_owner = owner_filter_active.then(owners::filtered);
+                extras.in_excluded_dirs = patterns::in_excluded_dirs();
+                extras.scan_limit = scanguard::report();
+                extras.detail_report = close_detail_report(detail_report.as_deref(), extras.verify_sample.as_ref(), &mut extras.warnings);
+                if let Err(e) = history::record(&fingerprint, copied) {
+                    debug_log(&format!("could not record run history: {}", e));
+                }
+                report.flush();
+                extras.summary = Some(RunSummary {
+                    ending: Ending::Finished,
+                    moved: do_move,
+                    copied,
+                    bytes: transferred_bytes(&model),
+                    elapsed: Some(started.elapsed()),
+                    skipped: report.skipped.len(),
+                    errors: report.errors.len(),
+                    excluded_files: report.excluded_files,
+                    excluded_dirs: report.excluded_dirs,
+                    in_excluded_dirs: patterns::in_excluded_dirs(),
+                    move_downgraded: extras.move_downgraded,
+                    unverified: extras.unverified.len(),
+                    notes: summary_notes(
+                        extras.auto_method.as_ref(),
+                        extras.relay.as_ref(),
+                        extras.scan_limit.as_ref(),
+                        extras.move_stats,
+                        extras.deletion,
+                        extras.source_dirs_removed,
+                        extras.renames.as_ref(),
+                        extras.folders.as_ref(),
+                        extras.timing.as_ref(),
+                        extras.quota.as_ref(),
+                        extras.verify_sample.as_ref(),
+                        extras.stale.as_ref(),
+                    ),
+                    warnings: extras.warnings.clone(),
+                });
+                return cli_output_json("finished", &report, &extras);
+            }
+            WorkerMsg::Cancelled(mut report) => {
+                if show_progress { eprintln!(); }
+                save_failed(&report.failed);
+                let copied = report.copied;
+                extras.renames = Some(renamer.log());
+                extras.folders = Some(folders::take());
+                extras.warnings.extend(preserve::take());
+                extras.warnings.extend(collisions::take());
+                extras.timing = Some(timing::take());
+                extras.quota = quota::take();
+                extras.verify_sample = sample::report().map(|report| (report, copied));
+                extras.low_priority = priority::report();
+                extras.filtered_by_owner = owner_filter_active.then(owners::filtered);
+                extras.in_excluded_dirs = patterns::in_excluded_dirs();
+                extras.scan_limit = scanguard::report();
+                extras.detail_report = close_detail_report(detail_report.as_deref(), extras.verify_sample.as_ref(), &mut extras.warnings);
+                extras.stage = Some(model.phase());
+                report.flush();
+                extras.summary = Some(RunSummary {
+                    ending: Ending::Cancelled(model.phase()),
+                    moved: do_move,
+                    copied,
+                    bytes: transferred_bytes(&model),
+                    elapsed: Some(started.elapsed()),
+                    skipped: report.skipped.len(),
+                    errors: report.errors.len(),
+                    excluded_files: report.excluded_files,
+                    excluded_dirs: report.excluded_dirs,
+                    in_excluded_dirs: patterns::in_excluded_dirs(),
+                    move_downgraded: extras.move_downgraded,
+                    unverified: extras.unverified.len(),
+                    notes: summary_notes(
+                        extras.auto_method.as_ref(),
+                        extras.relay.as_ref(),
+                        extras.scan_limit.as_ref(),
+                        extras.move_stats,
+                        extras.deletion,
+                        extras.source_dirs_removed,
+                        extras.renames.as_ref(),
+                        extras.folders.as_ref(),
+                        extras.timing.as_ref(),
+                        extras.quota.as_ref(),
+                        extras.verify_sample.as_ref(),
+                        extras.stale.as_ref(),
+                    ),
+                    warnings: extras.warnings.clone(),
+                });
+                return cli_output_json("cancelled", &report, &extras);
+            }
+            WorkerMsg::Error(e) => {
+                // What the report holds so far stays on disk.
+                let _ = filereport::close(None);
+                let kind = error_kind.map(|k| format!(",\"kind\":{}", json_string(k))).unwrap_or_default();
+                let space = shortfall
+                    .as_ref()
+                    .map(|s| format!(",\"needed_bytes\":{},\"free_bytes\":{}", s.needed, s.free))
+                    .unwrap_or_default();
+                println!("{{\"status\":\"error\"{},\"message\":{}{}}}", kind, json_string(&e), space);
+                return 1;
+            }
+            WorkerMsg::ScanProgress { files_found, bytes_found } => {
+                stall.note_progress();
+                if events {
+                    activity::event("scan", &format!("\"files_found\":{},\"bytes_found\":{}",
+                        files_found, bytes_found
+                    ));
+                } else if show_progress {
+                    eprint!("\r{}   ", scanguard::describe_found(files_found, bytes_found));
+                    let _ = std::io::stderr().flush();
+                }
+                continue;
+            }
+            WorkerMsg::Plan { sizes } => model = ProgressModel::new(sizes),
+            WorkerMsg::Progress { done, total, file } => {
+                stall.note_progress();
+                model.file_done(done, total);
+                throughput.sample(started.elapsed(), &model);
+                last_file = Some(file.clone());
+                if events {
+                    let act = activity::global().current().map(|a| a.text).unwrap_or_default();
+                    // null when the run sent no file sizes.
+                    let (bytes, bytes_total) = if model.is_size_aware() {
+                        (model.bytes_done().to_string(), model.bytes_total().to_string())
+                    } else {
+                        ("null".to_string(), "null".to_string())
+                    };
+                    // The speed over the last ten seconds, in bytes when the
+                    // sizes are known and in files otherwise; null until
+                    // there is one.
+                    let (bytes_per_sec, files_per_sec) = match throughput.rate(&model) {
+                        Some(progress::Rate::BytesPerSec(rate)) => (format!("{:.0}", rate), "null".to_string()),
+                        Some(progress::Rate::FilesPerSec(rate)) => ("null".to_string(), format!("{:.2}", rate)),
+                        None => ("null".to_string(), "null".to_string()),
+                    };
+                    let eta_secs = throughput.left(&model).map(|d| d.as_secs().to_string()).unwrap_or_else(|| "null".to_string());
+                    activity::event("progress", &format!("\"phase\":\"{}\",\"done\":{},\"total\":{},\"bytes_done\":{},\"bytes_total\":{},\"bytes_per_sec\":{},\"files_per_sec\":{},\"eta_secs\":{},\"file\":{},\"activity\":{}",
+                        model.phase().name(),
+                        done,
+                        total,
+                        bytes,
+                        bytes_total,
+                        bytes_per_sec,
+                        files_per_sec,
+                        eta_secs,
+                        json_string(&file),
+                        json_string(&act)
+                    ));
+                }
+            }
+            WorkerMsg::FileBytes { bytes } => {
+                stall.note_progress();
+                model.in_flight(bytes);
+                throughput.sample(started.elapsed(), &model);
+            }
+            WorkerMsg::Phase(phase) => {
+                model.set_phase(phase);
+            }
+            WorkerMsg::Unverified(files) => {
+                filereport::unverified(&files);
+                extras.unverified = files;
+            }
+            WorkerMsg::MoveStats(st) => {
+                debug_log(&format!("move breakdown: {}", st.describe()));
+                extras.move_stats = Some(st);
+            }
+            WorkerMsg::DeletePhase { total } => {
+                stall.note_progress();
+                model = ProgressModel::default();
+                model.set_phase(Phase::Finalizing);
+                throughput = Throughput::default();
+                if events {
+                    activity::event("phase", &format!("\"phase\":\"delete\",\"total\":{}", total));
+                } else if show_progress {
+                    eprintln!("\nEvery file copied and verified; deleting {} source file(s)", total);
+                }
+            }
+            WorkerMsg::Deletion(deletion) => {
+                debug_log(&deletion.describe());
+                extras.deletion = Some(deletion);
+            }
+            WorkerMsg::RsyncPeers { versions, warnings } => {
+                for warning in &warnings {
+                    if show_progress { eprintln!(); }
+                    warn(events, warning);
+                }
+                extras.rsync_versions = versions;
+                extras.warnings.extend(warnings);
+            }
+            WorkerMsg::AutoMethod(decision) => {
+                if show_progress {
+                    eprintln!("{}", decision.describe());
+                }
+                extras.auto_method = Some(decision);
+            }
+            WorkerMsg::Relay(decision) => {
+                extras.relay = Some(decision);
+            }
+            WorkerMsg::ClockSkew { host, seconds, warning } => {
+                if let Some(warning) = warning {
+                    if show_progress { eprintln!(); }
+                    warn(events, &warning);
+                    extras.warnings.push(warning);
+                }
+                extras.clock_skew.push((host, seconds));
+            }
+            WorkerMsg::Unwritable { dirs, reply } => {
+                if on_unwritable != OnUnwritable::Abort {
+                    if show_progress { eprintln!(); }
+                    let warning = unwritable_warning(&dirs, on_unwritable);
+                    warn(events, &warning);
+                }
+                let _ = reply.send(on_unwritable);
+                extras.unwritable = dirs;
+            }
+            WorkerMsg::StaleArtifacts { reply, .. } => {
+                if stale_artifacts.is_none() {
+                    error_kind = Some("stale-artifacts");
+                }
+                let _ = reply.send(stale_artifacts);
+            }
+            WorkerMsg::Stale(decision) => {
+                if show_progress { eprintln!(); }
+                if events {
+                    activity::event("stale_artifacts", &stale_event_json(&decision));
+                } else {
+                    eprintln!("Warning: {}", decision.describe());
+                }
+                extras.warnings.extend(decision.failed.iter().map(|e| format!("Could not remove {}", e)));
+                extras.stale = Some(decision);
+            }
+            WorkerMsg::QuotaExceeded { message, reply } => {
+                if show_progress { eprintln!(); }
+                let warning = format!("{}; --on-quota {}", message, on_quota.name());
+                warn(events, &warning);
+                let _ = reply.send(on_quota);
+            }
+            WorkerMsg::ScanLimit { trip, reply } => {
+                if show_progress { eprintln!(); }
+                let go_on = cli_scan_answer(scan_limit_action, &trip);
+                if !go_on {
+                    error_kind = Some("scan-limit");
+                }
+                let _ = reply.send(go_on);
+            }
+            WorkerMsg::HashCache(cache) => {
+                for warning in cache.reset.iter().chain(&cache.save_error) {
+                    if show_progress { eprintln!(); }
+                    warn(events, warning);
+                }
+                extras.warnings.extend(cache.reset.iter().chain(&cache.save_error).cloned());
+                extras.hash_cache = Some(cache);
+            }
+            // Nobody can check a fingerprint here; --accept-new-hostkeys
+            // is the non-interactive answer.
+            WorkerMsg::HostKey { problem, reply, .. } => {
+                error_kind = Some(problem.kind());
+                let _ = reply.send(false);
+            }
+            WorkerMsg::ShortOfSpace(short) => {
+                error_kind = Some("no-space");
+                shortfall = Some(short);
+            }
+            // Only the result dialog verifies a run again.
+            WorkerMsg::Reverified(_) => {}
+        }
+        verifying.store(model.phase() == Phase::Verifying, Ordering::SeqCst);
+        if show_progress {
+            let speed = throughput.rate(&model).map(|r| format!(", {}", r.describe())).unwrap_or_default();
+            let eta = throughput
+                .left(&model)
+                .or_else(|| model.eta(started.elapsed()))
+                .map(|d| format!("{}, {} left", speed, format_eta(d)))
+                .unwrap_or(speed);
+            let bytes = if model.bytes_total() > 0 {
+                format!(
+                    ", {} of {}",
+                    format_bytes(model.bytes_done()),
+                    format_bytes(model.bytes_total())
+                )
+            } else {
+                String::new()
+            };
+            let phase = match model.phase() {
+                Phase::Preflight | Phase::Verifying | Phase::Finalizing => format!(" {}", model.phase().name()),
+                Phase::Scanning | Phase::Transferring => String::new(),
+            };
+            eprint!(
+                "\r{:5.1}% ({}/{}{}){}{}   ",
+                model.bar_fraction() * 100.0,
+                model.done(),
+                model.total(),
+                bytes,
+                eta,
+                phase
+            );
+            let _ = std::io::stderr().flush();
+        }
+    }
+
+    if worker.join().is_err() {
+        eprintln!("Worker thread panicked");
+        return 1;
+    }
+    eprintln!("Worker channel closed without result");
+    1
+}
+
+/// How long a second Ctrl+C waits for the worker's own result before
+/// `--cli` prints a snapshot and exits.
+const FORCED_CANCEL_AFTER: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The result printed when the worker never answered a second Ctrl+C:
+/// only what its progress messages said, since its own counts are lost.
+fn forced_cancel_json(done: usize, total: usize, last_file: Option<&str>, stage: Phase) -> String {
+    let file = last_file.map(json_string).unwrap_or_else(|| "null".to_string());
+    let activity = activity::global()
+        .current()
+        .map(|a| json_string(&a.text))
+        .unwrap_or_else(|| "null".to_string());
+    format!(
+        "{{\"status\":\"cancelled-forced\",\"stage\":\"{}\",\"done\":{},\"total\":{},\"file\":{},\"activity\":{},\"message\":{}}}",
+        stage.name(),
+        done,
+        total,
+        file,
+        activity,
+        json_string("The transfer did not stop in time; counts are from its last progress report and may be incomplete.")
+    )
+}
+
+// ── Messages from worker thread to UI ──────────────────────────────────
+
+enum WorkerMsg {
+    /// The files, and their bytes, the scan has found so far; sent every
+    /// quarter of a second while it goes, and once when it is done.
+    ScanProgress {
+        files_found: usize,
+        bytes_found: u64,
+    },
+    /// Sizes of the files about to be transferred, in processing order.
+    /// `None` marks a file whose size could not be determined.
+    Plan {
+        sizes: Vec<Option<u64>>,
+    },
+    Progress {
+        done: usize,
+        total: usize,
+        file: String,
+    },
+    /// Bytes copied so far of the file currently in flight.
+    FileBytes {
+        bytes: u64,
+    },
+    /// The run is verifying the file just copied, back to transferring,
+    /// or finalizing once every file has been handled.
+    Phase(Phase),
+    /// How a local move's files were moved, sent just before the result.
+    MoveStats(MoveStats),
+    /// A two-phase move has copied everything and is deleting `total` sources.
+    DeletePhase {
+        total: usize,
+    },
+    /// What a two-phase move did with its sources, sent just before the result.
+    Deletion(Deletion),
+    /// Files transferred after the user chose to skip the remaining
+    /// verification, sent just before the result.
+    Unverified(Vec<String>),
+    /// The rsync found on each end (`this machine: rsync 3.2.7, protocol
+    /// 31`) and any downgrade to warn about, sent before an rsync transfer.
+    RsyncPeers {
+        versions: Vec<String>,
+        warnings: Vec<String>,
+    },
+    /// The method Auto chose for a remote run, sent before it starts.
+    AutoMethod(automethod::Decision),
+    /// How a run between two hosts sent its files, sent before its report.
+    Relay(relay::Decision),
+    /// How far `host`'s clock is ahead of this machine's (negative when
+    /// behind), measured when it was first reached, with the warning to
+    /// show if that is too far.
+    ClockSkew {
+        host: String,
+        seconds: i64,
+        warning: Option<String>,
+    },
+    /// Existing destination folders that cannot be written to.  The worker
+    /// waits for the answer on `reply`; dropping it aborts the run.
+    Unwritable {
+        dirs: Vec<UnwritableDir>,
+        reply: mpsc::Sender<OnUnwritable>,
+    },
+    /// Leftovers of an earlier run found at the destination `at`.  The
+    /// worker waits on `reply` for what to do; `None` (or dropping it)
+    /// copies nothing.
+    StaleArtifacts {
+        found: stale::Found,
+        at: String,
+        reply: mpsc::Sender<Option<stale::OnStale>>,
+    },
+    /// What was done about those leftovers.
+    Stale(stale::Decision),
+    /// A file failed for lack of quota on the destination, the first time
+    /// (or the first since the user chose to retry).  The worker waits on
+    /// `reply`; dropping it cancels the run.
+    QuotaExceeded {
+        message: String,
+        reply: mpsc::Sender<quota::OnQuota>,
+    },
+    /// How `--hash-cache` did, sent just before the result.
+    HashCache(CacheStats),
+    /// What verifying a finished run again found; the last message of
+    /// `run_reverify_worker`.
+    Reverified(reverify::Outcome),
+    /// The scan passed a scan limit.  The worker waits on `reply` for
+    /// whether to go on; dropping it stops the scan.
+    ScanLimit {
+        trip: scanguard::Trip,
+        reply: mpsc::Sender<bool>,
+    },
+    /// The destination hasn't room for the plan; sent just before the
+    /// error saying so.
+    ShortOfSpace(space::Shortfall),
+    /// ssh refused `host`'s key.  The worker waits on `reply` for whether
+    /// the user accepted `keys` (found for an unknown host, and added to
+    /// known_hosts by the UI); the error follows otherwise.
+    HostKey {
+        host: String,
+        problem: ssh::HostKeyProblem,
+        keys: Vec<ssh::HostKey>,
+        reply: mpsc::Sender<bool>,
+    },
+    /// What the run did; the last message of a transfer.
+    Finished(TransferReport),
+    /// What the run did before it was cancelled.
+    Cancelled(TransferReport),
+    Error(String),
+}
+
+// ── UI construction ────────────────────────────────────────────────────
+
+/// `start_simple` (from `--simple`) opens in simple mode regardless of the
+/// saved view.
+fn build_ui(app: &Application, start_simple: bool) {
+    let window = ApplicationWindow::builder()
+        .application(app)
+        .title("Kosmokopy")
+        .default_width(560)
+        .default_height(520)
+        .resizable(true)
+        .build();
+
+    let root = GtkBox::new(Orientation::Vertical, 12);
+    root.set_margin_top(16);
+    root.set_margin_bottom(16);
+    root.set_margin_start(16);
+    root.set_margin_end(16);
+
+    // ── Source selection ───────────────────────────────────────────────
+    let src_heading = Label::new(Some("Source:"));
+    src_heading.set_halign(Align::Start);
+    root.append(&src_heading);
+
+    let src_row = GtkBox::new(Orientation::Horizontal, 8);
+    let src_entry = Entry::new();
+    src_entry.set_hexpand(true);
+    src_entry.set_placeholder_text(Some("Local path or host:/remote/path"));
+
+    let btn_browse_folder = Button::with_label("Browse Folder…");
+    let btn_browse_files = Button::with_label("Browse Files…");
+    let btn_browse_remote_src = Button::with_label("Browse Remote…");
+    let btn_select_items = Button::with_label("Select items…");
+    btn_select_items.set_tooltip_text(Some("Scan the source folder and untick individual folders or files"));
+
+    src_row.append(&src_entry);
+    src_row.append(&btn_browse_folder);
+    src_row.append(&btn_browse_files);
+    src_row.append(&btn_browse_remote_src);
+    src_row.append(&btn_select_items);
+    root.append(&src_row);
+
+
+
+    // ── Destination directory ─────────────────────────────────────────
+    let dst_row = dir_row_editable("Destination Directory:");
+    let dst_entry: Entry = dst_row.2.clone();
+    let btn_browse_remote_dst = Button::with_label("Browse Remote…");
+    dst_row.0.append(&btn_browse_remote_dst);
+    root.append(&dst_row.0);
+    // What a run would have to create at the destination, with a button
+    // to create it straight away.
+    let dst_hint_row = GtkBox::new(Orientation::Horizontal, 8);
+    let dst_hint = Label::new(None);
+    dst_hint.set_halign(Align::Start);
+    dst_hint.set_hexpand(true);
+    dst_hint.set_wrap(true);
+    dst_hint.add_css_class("dim-label");
+    let btn_create_dst = Button::with_label("Create Now");
+    dst_hint_row.append(&dst_hint);
+    dst_hint_row.append(&btn_create_dst);
+    dst_hint_row.set_visible(false);
+    root.append(&dst_hint_row);
+
+    // ── Copy / Move toggle ────────────────────────────────────────────
+    let mode_box = GtkBox::new(Orientation::Horizontal, 12);
+    let chk_copy = CheckButton::with_label("Copy");
+    let chk_move = CheckButton::with_label("Move");
+    chk_move.set_group(Some(&chk_copy));
+    chk_copy.set_active(true);
+    mode_box.append(&chk_copy);
+    mode_box.append(&chk_move);
+    // Index 1 is `MoveStrategy::AllThenDelete`.
+    let move_strategy_dropdown = DropDown::from_strings(&[
+        "Delete each source once copied",
+        "Delete sources after everything is copied",
+    ]);
+    move_strategy_dropdown.set_tooltip_text(Some(
+        "Copying everything first means a cancelled or failed move leaves every source in place",
+    ));
+    move_strategy_dropdown.set_sensitive(false);
+    let chk_remove_source_root = CheckButton::with_label("Remove the emptied source folder too");
+    chk_remove_source_root.set_tooltip_text(Some(
+        "After a move with no errors, folders left empty are removed; this lets the source folder itself go as well",
+    ));
+    chk_remove_source_root.set_sensitive(false);
+    chk_move.connect_toggled({
+        let dropdown = move_strategy_dropdown.clone();
+        let remove_root = chk_remove_source_root.clone();
+        move |chk| {
+            dropdown.set_sensitive(chk.is_active());
+            remove_root.set_sensitive(chk.is_active());
+        }
+    });
+    mode_box.append(&move_strategy_dropdown);
+    mode_box.append(&chk_remove_source_root);
+    root.append(&mode_box);
+
+    // ── Transfer mode: Files only / Files with parent / Folders and files
+    let transfer_box = GtkBox::new(Orientation::Horizontal, 12);
+    let chk_files_only = CheckButton::with_label("Files only");
+    let chk_files_parent = CheckButton::with_label("Files with parent folder");
+    let chk_folders_files = CheckButton::with_label("Folders and files");
+    chk_files_parent.set_group(Some(&chk_files_only));
+    chk_folders_files.set_group(Some(&chk_files_only));
+    chk_files_only.set_active(true);
+    transfer_box.append(&chk_files_only);
+    transfer_box.append(&chk_files_parent);
+    transfer_box.append(&chk_folders_files);
+    root.append(&transfer_box);
+
+    // Only used when the source is a list of individual files
+    let keep_parents_box = GtkBox::new(Orientation::Horizontal, 8);
+    let chk_keep_parents = CheckButton::with_label("Preserve original folder names for selected files:");
+    let spin_keep_parents = SpinButton::with_range(1.0, 9.0, 1.0);
+    spin_keep_parents.set_value(1.0);
+    spin_keep_parents.set_sensitive(false);
+    spin_keep_parents.set_tooltip_text(Some("How many parent folder names to recreate above each file"));
+    chk_keep_parents.connect_toggled({
+        let spin = spin_keep_parents.clone();
+        move |chk| spin.set_sensitive(chk.is_active())
+    });
+    keep_parents_box.append(&chk_keep_parents);
+    keep_parents_box.append(&spin_keep_parents);
+    root.append(&keep_parents_box);
+
+    // ── Transfer method ──────────────────────────────────────────────
+    let method_box = GtkBox::new(Orientation::Horizontal, 12);
+    let method_label = Label::new(Some("Transfer method:"));
+    method_label.set_halign(Align::Start);
+    let chk_standard = CheckButton::with_label("Standard (cp/scp)");
+    let chk_rsync = CheckButton::with_label("rsync");
+    chk_rsync.set_group(Some(&chk_standard));
+    let chk_tar = CheckButton::with_label("tar");
+    chk_tar.set_group(Some(&chk_standard));
+    chk_tar.set_tooltip_text(Some(TAR_METHOD_TOOLTIP));
+    let chk_auto = CheckButton::with_label("Auto");
+    chk_auto.set_group(Some(&chk_standard));
+    chk_auto.set_tooltip_text(Some(AUTO_METHOD_TOOLTIP));
+    chk_standard.set_active(true);
+    method_box.append(&method_label);
+    method_box.append(&chk_standard);
+    method_box.append(&chk_rsync);
+    method_box.append(&chk_tar);
+    method_box.append(&chk_auto);
+    // How many files the standard method has in flight at once
+    let parallel_label = Label::new(Some("Files at once:"));
+    let spin_parallel = SpinButton::with_range(1.0, parallel::MAX_WORKERS as f64, 1.0);
+    spin_parallel.set_value(parallel::DEFAULT_WORKERS as f64);
+    spin_parallel.set_tooltip_text(Some("How many files the standard method copies side by side; 1 copies them one at a time"));
+    method_box.append(&parallel_label);
+    method_box.append(&spin_parallel);
+    // How often a remote transfer sends a failed file again
+    let retries_label = Label::new(Some("Retries per file:"));
+    let spin_retries = SpinButton::with_range(0.0, retry::MAX_RETRIES as f64, 1.0);
+    spin_retries.set_tooltip_text(Some(
+        "How many more times a remote transfer sends a file whose copy failed or didn't match, waiting 1s, 2s, 4s… in between",
+    ));
+    method_box.append(&retries_label);
+    method_box.append(&spin_retries);
+    root.append(&method_box);
+
+    let options_sep = Separator::new(Orientation::Horizontal);
+    root.append(&options_sep);
+
+    // ── Quick filter (media categories) ───────────────────────────────
+    let quick_row = GtkBox::new(Orientation::Horizontal, 8);
+    let quick_label = Label::new(Some("Quick filter:"));
+    quick_label.set_halign(Align::Start);
+    quick_row.append(&quick_label);
+    let quick_toggles: Vec<(ToggleButton, &'static str)> = MEDIA_CATEGORIES
+        .iter()
+        .map(|(name, _)| {
+            let toggle = ToggleButton::with_label(name);
+            quick_row.append(&toggle);
+            (toggle, *name)
+        })
+        .collect();
+    root.append(&quick_row);
+
+    // ── Exclusions ────────────────────────────────────────────────────
+    let excl_heading = Label::new(Some("Exclusions:"));
+    excl_heading.set_halign(Align::Start);
+    root.append(&excl_heading);
+
+    let excl_btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    let btn_excl_dirs = Button::with_label("Exclude Directories…");
+    let btn_excl_files = Button::with_label("Exclude Files…");
+    let btn_excl_clear = Button::with_label("Clear");
+    let btn_excl_load = Button::with_label("Load Patterns…");
+    btn_excl_load.set_tooltip_text(Some("Add the patterns in a file, one per line, like --exclude-from"));
+    let btn_excl_save = Button::with_label("Save Patterns…");
+    btn_excl_save.set_tooltip_text(Some("Write the exclusion list to a file Load Patterns and --exclude-from read"));
+    excl_btn_row.append(&btn_excl_dirs);
+    excl_btn_row.append(&btn_excl_files);
+    excl_btn_row.append(&btn_excl_clear);
+    excl_btn_row.append(&btn_excl_load);
+    excl_btn_row.append(&btn_excl_save);
+    root.append(&excl_btn_row);
+
+    // Manual pattern entry row
+    let pattern_row = GtkBox::new(Orientation::Horizontal, 8);
+    let pattern_entry = Entry::new();
+    pattern_entry.set_hexpand(true);
+    pattern_entry.set_placeholder_text(Some("Pattern (e.g. *.jpg, /tmp*, test_*)"));
+    let btn_add_file_pattern = Button::with_label("+ File Pattern");
+    let btn_add_dir_pattern = Button::with_label("+ Dir Pattern");
+    let btn_add_include_pattern = Button::with_label("+ Include Only");
+    pattern_row.append(&pattern_entry);
+    pattern_row.append(&btn_add_file_pattern);
+    pattern_row.append(&btn_add_dir_pattern);
+    pattern_row.append(&btn_add_include_pattern);
+    root.append(&pattern_row);
+
+    // Why the typed pattern was refused; hidden until one is.
+    let pattern_error = Label::new(None);
+    pattern_error.set_halign(Align::Start);
+    pattern_error.set_wrap(true);
+    pattern_error.add_css_class("error");
+    pattern_error.set_visible(false);
+    root.append(&pattern_error);
+    {
+        let pattern_error = pattern_error.clone();
+        pattern_entry.connect_changed(move |_| pattern_error.set_visible(false));
     }
 
+    // One row per exclusion, each with a button removing it; a double
+    // click takes the pattern back into the entry above.
+    let excl_view = ListBox::new();
+    excl_view.set_selection_mode(SelectionMode::None);
+    excl_view.set_activate_on_single_click(false);
+    excl_view.update_property(&[Property::Label("Exclusions")]);
+
+    let excl_scroll = ScrolledWindow::builder()
+        .child(&excl_view)
+        .min_content_height(80)
+        .vexpand(true)
+        .build();
+    root.append(&excl_scroll);
+
+    // Shared exclusion state: dirs stored as "/dirname", files as "filename",
+    // wildcard dir patterns as "~/pattern", wildcard file patterns as "~pattern",
+    // include-only file patterns as "+pattern", path patterns as "^pattern"
+    let exclusions: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
     {
-        let win = window.clone();
-        let source_sel = source_selection.clone();
+        // The rows' remove buttons name their row's index.
+        let remove = gtk4::gio::SimpleAction::new("remove", Some(glib::VariantTy::UINT32));
         let excls = exclusions.clone();
         let view = excl_view.clone();
-        btn_excl_files.connect_clicked(move |_| {
-            let src = source_sel.borrow().clone();
-            let initial = match &src {
-                SourceSelection::Directory(p) => Some(p.clone()),
-                _ => None,
-            };
+        remove.connect_activate(move |_, index| {
+            let Some(i) = index.and_then(|v| v.get::<u32>()) else { return };
+            let mut list = excls.borrow_mut();
+            if (i as usize) < list.len() {
+                list.remove(i as usize);
+                refresh_exclusion_view(&view, &list);
+            }
+        });
+        let actions = gtk4::gio::SimpleActionGroup::new();
+        actions.add_action(&remove);
+        excl_view.insert_action_group("exclusions", Some(&actions));
+    }
+    {
+        let excls = exclusions.clone();
+        let entry = pattern_entry.clone();
+        excl_view.connect_row_activated(move |view, row| {
+            let mut list = excls.borrow_mut();
+            let i = row.index() as usize;
+            if i < list.len() {
+                let pattern = list.remove(i);
+                entry.set_text(PatternKind::decode(&pattern).1);
+                entry.grab_focus();
+                refresh_exclusion_view(view, &list);
+            }
+        });
+    }
+
+    // ── Conflict handling ──────────────────────────────────────────
+    let conflict_label = Label::new(Some("If file already exists:"));
+    conflict_label.set_halign(Align::Start);
+    root.append(&conflict_label);
+
+    let conflict_row = GtkBox::new(Orientation::Horizontal, 12);
+    let chk_skip = CheckButton::with_label("Skip");
+    chk_skip.set_active(true);
+    let chk_overwrite = CheckButton::with_label("Overwrite");
+    chk_overwrite.set_group(Some(&chk_skip));
+    let chk_rename = CheckButton::with_label("Auto-rename");
+    chk_rename.set_group(Some(&chk_skip));
+    let chk_newer = CheckButton::with_label("Newest wins");
+    chk_newer.set_group(Some(&chk_skip));
+    chk_newer.set_tooltip_text(Some("Replace an existing file only when the source was modified more recently"));
+    conflict_row.append(&chk_skip);
+    conflict_row.append(&chk_overwrite);
+    conflict_row.append(&chk_rename);
+    conflict_row.append(&chk_newer);
+    root.append(&conflict_row);
+
+    // Indices follow `collisions::OnCollision`: Rename, Abort.
+    let collision_row = GtkBox::new(Orientation::Horizontal, 8);
+    let collision_label = Label::new(Some("Files of this run with the same name:"));
+    let collision_dropdown = DropDown::from_strings(&["Rename apart (report (from b).pdf)", "Stop before copying"]);
+    collision_dropdown.set_tooltip_text(Some(
+        "When two source files would land on one name, such as a/report.pdf and b/report.pdf with Files only, or two names Remove spaces makes one",
+    ));
+    collision_row.append(&collision_label);
+    collision_row.append(&collision_dropdown);
+    root.append(&collision_row);
+
+    let chk_strip_spaces = CheckButton::with_label("Remove spaces from filenames");
+    chk_strip_spaces.set_active(false);
+    root.append(&chk_strip_spaces);
+
+    // Saved rename rules run after "Remove spaces"; the box is ticked
+    // whenever there are some, so they never apply unseen.
+    let rename_row = GtkBox::new(Orientation::Horizontal, 8);
+    let saved_rules = rename::load().len();
+    let chk_rename_rules = CheckButton::with_label(&rename_rules_label(saved_rules));
+    chk_rename_rules.set_active(saved_rules > 0);
+    chk_rename_rules.set_sensitive(saved_rules > 0);
+    let btn_rename_rules = Button::with_label("Edit Rename Rules…");
+    rename_row.append(&chk_rename_rules);
+    rename_row.append(&btn_rename_rules);
+    root.append(&rename_row);
+
+    let chk_preserve = CheckButton::with_label("Preserve timestamps & permissions");
+    chk_preserve.set_tooltip_text(Some(
+        "Give copies their sources' modification and access times, and the folders made for them their sources' times and permissions (local copies with the standard method)",
+    ));
+    root.append(&chk_preserve);
+
+    // Indices follow `symlinks::Policy`: Skip, Follow, Preserve.
+    let symlinks_row = GtkBox::new(Orientation::Horizontal, 8);
+    let symlinks_label = Label::new(Some("Symbolic links:"));
+    let symlinks_dropdown = DropDown::from_strings(&[
+        "Skip them",
+        "Follow (copy what they point to)",
+        "Preserve (recreate the links)",
+    ]);
+    symlinks_dropdown.set_tooltip_text(Some(
+        "What to do with links inside a local source folder; skipped links are listed with the skipped files",
+    ));
+    symlinks_row.append(&symlinks_label);
+    symlinks_row.append(&symlinks_dropdown);
+    root.append(&symlinks_row);
+
+    // Indices follow `VerifyMode`: Full, Hash, QuickSizeMtime, None.
+    let verify_row = GtkBox::new(Orientation::Horizontal, 8);
+    let verify_label = Label::new(Some("Verification:"));
+    let verify_dropdown = DropDown::from_strings(&[
+        "Full (compare byte for byte)",
+        "Hash (compare SHA-256)",
+        "Quick (size and time only)",
+        "None (trust the copy)",
+    ]);
+    verify_dropdown.set_tooltip_text(Some(
+        "How each local copy is checked against its source; a move can't skip it, and remote transfers are always checked by hash",
+    ));
+    verify_row.append(&verify_label);
+    verify_row.append(&verify_dropdown);
+    // Indices follow `HashAlgo`: Sha256, Blake3, Xxh128.
+    let hash_label = Label::new(Some("Hash:"));
+    let hash_dropdown = DropDown::from_strings(&["SHA-256", "BLAKE3", "XXH128"]);
+    hash_dropdown.set_tooltip_text(Some(
+        "How copies on another host are hashed to verify them; a host without b3sum or xxhsum uses SHA-256",
+    ));
+    verify_row.append(&hash_label);
+    verify_row.append(&hash_dropdown);
+    root.append(&verify_row);
+
+    // Indices follow `relay::RelayChoice::parse`: auto, direct, scp3, staged.
+    let relay_row = GtkBox::new(Orientation::Horizontal, 8);
+    let relay_label = Label::new(Some("Between two hosts:"));
+    let relay_dropdown = DropDown::from_strings(&[
+        "Quickest that works",
+        "Source host sends directly",
+        "Through this machine's memory (scp -3)",
+        "Staged on this machine's disk",
+    ]);
+    relay_dropdown.set_tooltip_text(Some(
+        "How files go from one remote host to another; the quickest that works tries the source host, then scp -3, then a local staging folder",
+    ));
+    relay_row.append(&relay_label);
+    relay_row.append(&relay_dropdown);
+    root.append(&relay_row);
+
+    // Indices follow `throttle::Compress::parse`: auto, always, never.
+    let throttle_row = GtkBox::new(Orientation::Horizontal, 8);
+    let bwlimit_label = Label::new(Some("Bandwidth limit (KB/s):"));
+    let spin_bwlimit = SpinButton::with_range(0.0, throttle::MAX_LIMIT as f64, 100.0);
+    spin_bwlimit.set_tooltip_text(Some(
+        "Keep the transfer to about this many KB/s, all files together, with rsync, scp and the standard method alike; 0 for no limit. Names scp can't carry, sent through cat over ssh, and moves within one host aren't limited",
+    ));
+    let compress_label = Label::new(Some("Compress (-z):"));
+    let compress_dropdown = DropDown::from_strings(&["To or from a host", "Always", "Never"]);
+    compress_dropdown.set_tooltip_text(Some(
+        "When rsync compresses what it sends; on this machine alone it only costs CPU",
+    ));
+    throttle_row.append(&bwlimit_label);
+    throttle_row.append(&spin_bwlimit);
+    throttle_row.append(&compress_label);
+    throttle_row.append(&compress_dropdown);
+    root.append(&throttle_row);
+
+    let chk_low_priority = CheckButton::with_label("Run at low priority");
+    chk_low_priority.set_tooltip_text(Some("Leave the disk and CPU to other programs while copying"));
+    root.append(&chk_low_priority);
+
+    let chk_space_anyway = CheckButton::with_label("Transfer anyway if the destination seems too full");
+    chk_space_anyway.set_tooltip_text(Some(
+        "Start even when the destination's free space looks smaller than the files to be transferred",
+    ));
+    root.append(&chk_space_anyway);
+
+    let chk_print_commands = CheckButton::with_label("List the external commands run");
+    chk_print_commands.set_tooltip_text(Some(
+        "Show every ssh, scp and rsync command in the result, numbered in the order they ran, quoted as for a shell",
+    ));
+    root.append(&chk_print_commands);
+
+    let progress_sep = Separator::new(Orientation::Horizontal);
+    root.append(&progress_sep);
+
+    // ── Progress area ─────────────────────────────────────────────────
+    let progress_bar = ProgressBar::new();
+    progress_bar.set_show_text(true);
+    progress_bar.set_ellipsize(EllipsizeMode::Middle);
+    progress_bar.set_text(Some("Ready"));
+    progress_bar.update_property(&[Property::Label("Transfer progress")]);
+    install_phase_css(&window);
+    root.append(&progress_bar);
+
+    // Speed and time left, while files are on their way
+    let speed_label = Label::new(None);
+    speed_label.set_halign(Align::Start);
+    speed_label.add_css_class("dim-label");
+    speed_label.set_visible(false);
+    root.append(&speed_label);
+
+    let status_label = Label::new(Some(""));
+    status_label.set_halign(Align::Start);
+    status_label.set_wrap(true);
+    status_label.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+    status_label.set_ellipsize(EllipsizeMode::Middle);
+    status_label.set_lines(3);
+    root.append(&status_label);
+
+    // ── Duplicate run notice (hidden until Transfer repeats a recent run) ─
+    let duplicate_bar = GtkBox::new(Orientation::Horizontal, 8);
+    duplicate_bar.add_css_class("card");
+    duplicate_bar.set_visible(false);
+    let duplicate_label = Label::new(None);
+    duplicate_label.set_halign(Align::Start);
+    duplicate_label.set_hexpand(true);
+    duplicate_label.set_wrap(true);
+    duplicate_label.set_margin_start(8);
+    duplicate_bar.append(&duplicate_label);
+    let btn_duplicate_cancel = Button::with_label("Cancel");
+    btn_duplicate_cancel.set_valign(Align::Center);
+    duplicate_bar.append(&btn_duplicate_cancel);
+    let btn_duplicate_continue = Button::with_label("Continue");
+    btn_duplicate_continue.add_css_class("suggested-action");
+    btn_duplicate_continue.set_valign(Align::Center);
+    btn_duplicate_continue.set_margin_end(8);
+    duplicate_bar.append(&btn_duplicate_continue);
+    root.append(&duplicate_bar);
+
+    // ── Start button ──────────────────────────────────────────────────
+    let btn_start = Button::with_label("Transfer");
+    btn_start.add_css_class("suggested-action");
+    root.append(&btn_start);
+
+    // ── Pause and Cancel buttons (hidden until a transfer is running) ─
+    let run_row = GtkBox::new(Orientation::Horizontal, 12);
+    run_row.set_homogeneous(true);
+    let btn_pause = Button::with_label(PAUSE_LABEL);
+    btn_pause.set_tooltip_text(Some("Stop before the next file until resumed; the file in progress finishes first, and with rsync the whole call of files under way"));
+    btn_pause.set_hexpand(true);
+    btn_pause.set_visible(false);
+    run_row.append(&btn_pause);
+    let btn_cancel = Button::with_label("Cancel");
+    btn_cancel.add_css_class("destructive-action");
+    btn_cancel.set_hexpand(true);
+    btn_cancel.set_visible(false);
+    run_row.append(&btn_cancel);
+    // The row takes no space between runs.
+    btn_cancel.bind_property("visible", &run_row, "visible").sync_create().build();
+    root.append(&run_row);
+
+    // ── Skip verification button (shown once files are being verified) ─
+    let btn_skip_verify = Button::with_label(SKIP_VERIFY_LABEL);
+    btn_skip_verify.set_tooltip_text(Some(
+        "Transfer the remaining files without verifying them. They are listed as unverified, and a move keeps their sources.",
+    ));
+    btn_skip_verify.set_visible(false);
+    root.append(&btn_skip_verify);
+
+    window.set_child(Some(&root));
+
+    // ── Simple mode toggle ────────────────────────────────────────────
+    // Simple mode only hides the advanced rows; the widgets (and whatever
+    // they hold) stay in place, so switching views mid-setup loses nothing
+    // and the transfer reads the same state either way.
+    let header = HeaderBar::new();
+    let simple_box = GtkBox::new(Orientation::Horizontal, 6);
+    let simple_switch = Switch::new();
+    simple_switch.set_valign(Align::Center);
+    simple_switch.set_tooltip_text(Some("Show only the source, destination and Copy/Move"));
+    simple_box.append(&Label::new(Some("Simple")));
+    simple_box.append(&simple_switch);
+    header.pack_end(&simple_box);
+    window.set_titlebar(Some(&header));
+
+    let advanced: Vec<gtk4::Widget> = vec![
+        move_strategy_dropdown.clone().upcast(),
+        chk_remove_source_root.clone().upcast(),
+        transfer_box.clone().upcast(),
+        keep_parents_box.clone().upcast(),
+        method_box.clone().upcast(),
+        options_sep.upcast(),
+        quick_row.clone().upcast(),
+        excl_heading.clone().upcast(),
+        excl_btn_row.clone().upcast(),
+        pattern_row.clone().upcast(),
+        excl_scroll.clone().upcast(),
+        conflict_label.clone().upcast(),
+        conflict_row.clone().upcast(),
+        collision_row.clone().upcast(),
+        chk_strip_spaces.clone().upcast(),
+        rename_row.clone().upcast(),
+        chk_preserve.clone().upcast(),
+        symlinks_row.clone().upcast(),
+        verify_row.clone().upcast(),
+        relay_row.clone().upcast(),
+        throttle_row.clone().upcast(),
+        chk_low_priority.clone().upcast(),
+        chk_space_anyway.clone().upcast(),
+        chk_print_commands.clone().upcast(),
+        progress_sep.upcast(),
+    ];
+    // Set the starting view before connecting, so `--simple` isn't saved.
+    let saved = config::load();
+    if start_simple || saved.simple_mode {
+        simple_switch.set_active(true);
+        for w in &advanced {
+            w.set_visible(false);
+        }
+    }
+    simple_switch.connect_active_notify(move |sw| {
+        let simple = sw.is_active();
+        for w in &advanced {
+            w.set_visible(!simple);
+        }
+        let mut config = config::load();
+        if config.simple_mode != simple {
+            config.simple_mode = simple;
+            if let Err(e) = config::save(&config) {
+                debug_log(&format!("could not save view: {}", e));
+            }
+        }
+    });
+
+    // ── Saved settings / first-run setup ──────────────────────────────
+    let method_buttons = [chk_standard.clone(), chk_rsync.clone(), chk_auto.clone(), chk_tar.clone()];
+    select_method(&method_buttons, saved.method);
+    if config::is_first_run() {
+        let page = setup_page(&window, &root, &method_buttons);
+        window.set_child(Some(&page));
+    }
+
+    // ── Shared source-selection state ─────────────────────────────────
+    let source_selection = Rc::new(RefCell::new(SourceSelection::None));
+
+    // ── Browse Folder button ──────────────────────────────────────────
+    {
+        let win_clone = window.clone();
+        let src_entry_c = src_entry.clone();
+        let source_sel = source_selection.clone();
+        btn_browse_folder.connect_clicked(move |_| {
             let dialog = FileDialog::builder()
-                .title("Select files to exclude")
+                .title("Select source folder")
                 .modal(true)
                 .build();
-            if let Some(ref dir) = initial {
-                dialog.set_initial_folder(Some(&gtk4::gio::File::for_path(dir)));
-            }
-            let excls2 = excls.clone();
-            let view2 = view.clone();
-            dialog.open_multiple(Some(&win), gtk4::gio::Cancellable::NONE, move |result| {
-                if let Ok(files) = result {
-                    let mut list = excls2.borrow_mut();
-                    for i in 0..files.n_items() {
-                        if let Some(obj) = files.item(i) {
-                            if let Ok(gfile) = obj.downcast::<gtk4::gio::File>() {
-                                if let Some(p) = gfile.path() {
-                                    let fname = p
-                                        .file_name()
-                                        .map(|n| n.to_string_lossy().to_string())
-                                        .unwrap_or_default();
-                                    if !list.contains(&fname) {
-                                        list.push(fname);
+            let src_entry_c2 = src_entry_c.clone();
+            let source_sel2 = source_sel.clone();
+            dialog.select_folder(
+                Some(&win_clone),
+                gtk4::gio::Cancellable::NONE,
+                move |result| {
+                    if let Ok(file) = result {
+                        if let Some(path) = file.path() {
+                            src_entry_c2.set_text(&path.to_string_lossy());
+                            *source_sel2.borrow_mut() = SourceSelection::Directory(path);
+                        }
+                    }
+                },
+            );
+        });
+    }
+
+    // ── Browse Files button ───────────────────────────────────────────
+    {
+        let win_clone = window.clone();
+        let src_entry_c = src_entry.clone();
+        let source_sel = source_selection.clone();
+        btn_browse_files.connect_clicked(move |_| {
+            let dialog = FileDialog::builder()
+                .title("Select files")
+                .modal(true)
+                .build();
+            let src_entry_c2 = src_entry_c.clone();
+            let source_sel2 = source_sel.clone();
+            dialog.open_multiple(
+                Some(&win_clone),
+                gtk4::gio::Cancellable::NONE,
+                move |result| {
+                    if let Ok(files) = result {
+                        let mut paths = Vec::new();
+                        for i in 0..files.n_items() {
+                            if let Some(obj) = files.item(i) {
+                                if let Ok(gfile) = obj.downcast::<gtk4::gio::File>() {
+                                    if let Some(p) = gfile.path() {
+                                        paths.push(p);
                                     }
                                 }
                             }
                         }
+                        if !paths.is_empty() {
+                            let sel = SourceSelection::Files(paths);
+                            src_entry_c2.set_text(&selection_label(&sel));
+                            *source_sel2.borrow_mut() = sel;
+                        }
+                    }
+                },
+            );
+        });
+    }
+
+    // ── Drops onto the window and the source entry ────────────────────
+    // Files and folders dropped anywhere but the destination entry become
+    // the source, and a dropped failed-files list the selection it names.
+    for widget in [src_entry.clone().upcast::<gtk4::Widget>(), root.clone().upcast()] {
+        let src_entry_c = src_entry.clone();
+        let source_sel = source_selection.clone();
+        let status_label_c = status_label.clone();
+        let drop_target = file_drop_target();
+        drop_target.connect_drop(move |_, value, _, _| {
+            let sel = match dropped_paths(value).as_slice() {
+                [path] if looks_like_failed_list(path) => read_failed_list(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(selection_from_list)
+                    .map_err(|e| format!("Cannot load file list: {}", e)),
+                paths => selection_from_drop(paths.to_vec()),
+            };
+            match sel {
+                Ok(sel) => {
+                    src_entry_c.set_text(&selection_label(&sel));
+                    *source_sel.borrow_mut() = sel;
+                    true
+                }
+                Err(e) => {
+                    status_label_c.set_text(&e);
+                    false
+                }
+            }
+        });
+        widget.add_controller(drop_target);
+    }
+
+    // ── Select items… ─────────────────────────────────────────────────
+    // Scans the local source folder on a worker thread, then shows it as a
+    // tree.  The ticked files become a DirectoryItems selection.
+    {
+        let win_clone = window.clone();
+        let src_entry_c = src_entry.clone();
+        let source_sel = source_selection.clone();
+        let status_label_c = status_label.clone();
+        let excls = exclusions.clone();
+        let view = excl_view.clone();
+        let quick_toggles = quick_toggles.clone();
+        let symlinks_dropdown = symlinks_dropdown.clone();
+        btn_select_items.connect_clicked(move |btn| {
+            let text = src_entry_c.text().to_string().trim().to_string();
+            let browsed = source_sel.borrow().clone();
+            let root = match &browsed {
+                SourceSelection::Directory(p) | SourceSelection::DirectoryItems(p, _)
+                    if text.is_empty() || text == selection_label(&browsed) =>
+                {
+                    Some(p.clone())
+                }
+                _ if parse_destination(&text).0.is_none() && Path::new(&text).is_dir() => {
+                    Some(PathBuf::from(&text))
+                }
+                _ => None,
+            };
+            let Some(root) = root.filter(|r| r.is_dir()) else {
+                status_label_c.set_text("Select items… works with a local source folder.");
+                return;
+            };
+
+            let patterns = active_patterns(&excls.borrow(), &quick_toggles);
+            let links = selected_links(&symlinks_dropdown);
+            btn.set_sensitive(false);
+            status_label_c.set_text(&format!("Scanning {}…", root.display()));
+            let (tx, rx) = mpsc::channel::<Result<ItemTree, String>>();
+            let scan_root = root.clone();
+            thread::spawn(move || {
+                let dir = SourceSelection::Directory(scan_root.clone());
+                let tree = collect_files(&dir, &[], links, &mut ScanGuard::unlimited()).and_then(|(all, _, _)| {
+                    let (kept, _, _) = collect_files(&dir, &patterns, links, &mut ScanGuard::unlimited())?;
+                    Ok(ItemTree::build(&scan_root, &all, &kept.into_iter().collect()))
+                });
+                let _ = tx.send(tree);
+            });
+
+            let btn = btn.clone();
+            let win = win_clone.clone();
+            let src_entry_c = src_entry_c.clone();
+            let source_sel = source_sel.clone();
+            let status_label_c = status_label_c.clone();
+            let excls = excls.clone();
+            let view = view.clone();
+            glib::timeout_add_local(std::time::Duration::from_millis(50), move || {
+                let tree = match rx.try_recv() {
+                    Err(mpsc::TryRecvError::Empty) => return glib::ControlFlow::Continue,
+                    Err(mpsc::TryRecvError::Disconnected) => Err("Scan stopped unexpectedly.".to_string()),
+                    Ok(tree) => tree,
+                };
+                btn.set_sensitive(true);
+                let tree = match tree {
+                    Ok(tree) => tree,
+                    Err(e) => {
+                        status_label_c.set_text(&e);
+                        return glib::ControlFlow::Break;
+                    }
+                };
+                status_label_c.set_text(&format!("{} items scanned", tree.node_count()));
+
+                let on_apply = {
+                    let root = root.clone();
+                    let src_entry_c = src_entry_c.clone();
+                    let source_sel = source_sel.clone();
+                    move |files: Vec<PathBuf>| {
+                        let sel = SourceSelection::DirectoryItems(root.clone(), files);
+                        src_entry_c.set_text(&selection_label(&sel));
+                        *source_sel.borrow_mut() = sel;
+                    }
+                };
+                // Unticked items saved as exclusions apply to the plain folder.
+                let on_exclude = {
+                    let root = root.clone();
+                    let src_entry_c = src_entry_c.clone();
+                    let source_sel = source_sel.clone();
+                    let excls = excls.clone();
+                    let view = view.clone();
+                    move |patterns: Vec<String>| {
+                        let mut list = excls.borrow_mut();
+                        for pattern in patterns {
+                            if !list.contains(&pattern) {
+                                list.push(pattern);
+                            }
+                        }
+                        refresh_exclusion_view(&view, &list);
+                        let sel = SourceSelection::Directory(root.clone());
+                        src_entry_c.set_text(&selection_label(&sel));
+                        *source_sel.borrow_mut() = sel;
+                    }
+                };
+                show_item_picker(&win, tree, on_apply, on_exclude);
+                glib::ControlFlow::Break
+            });
+        });
+    }
+
+    // ── Destination browse ────────────────────────────────────────────
+    {
+        let win_clone = window.clone();
+        let dst_entry_c = dst_entry.clone();
+        dst_row.1.connect_clicked(move |_| {
+            pick_folder(&win_clone, dst_entry_c.clone());
+        });
+    }
+
+    // ── Drop onto the destination entry ───────────────────────────────
+    {
+        let dst_entry_c = dst_entry.clone();
+        let status_label_c = status_label.clone();
+        let drop_target = file_drop_target();
+        drop_target.connect_drop(move |_, value, _, _| match dropped_paths(value).as_slice() {
+            [path] => {
+                dst_entry_c.set_text(&path.to_string_lossy());
+                true
+            }
+            [] => false,
+            _ => {
+                status_label_c.set_text("Drop a single folder to use as the destination.");
+                false
+            }
+        });
+        dst_entry.add_controller(drop_target);
+    }
+
+    // ── Browse Remote — Source ─────────────────────────────────────────
+    {
+        let win_clone = window.clone();
+        let src_entry_c = src_entry.clone();
+        let source_sel = source_selection.clone();
+        btn_browse_remote_src.connect_clicked(move |_| {
+            let current = src_entry_c.text().to_string();
+            let src_entry_c2 = src_entry_c.clone();
+            let source_sel2 = source_sel.clone();
+            show_remote_browser(&win_clone, &current, false, move |selected| {
+                src_entry_c2.set_text(&selected);
+                // Parse as Remote source
+                if let Some(pos) = selected.find(':') {
+                    let host = selected[..pos].to_string();
+                    let path = selected[pos + 1..].to_string();
+                    *source_sel2.borrow_mut() = SourceSelection::Remote(host, path);
+                }
+            });
+        });
+    }
+
+    // ── Browse Remote — Destination ───────────────────────────────────
+    {
+        let win_clone = window.clone();
+        let dst_entry_c = dst_entry.clone();
+        btn_browse_remote_dst.connect_clicked(move |_| {
+            let current = dst_entry_c.text().to_string();
+            let dst_entry_c2 = dst_entry_c.clone();
+            show_remote_browser(&win_clone, &current, true, move |selected| {
+                dst_entry_c2.set_text(&selected);
+            });
+        });
+    }
+
+    // ── Destination hint ──────────────────────────────────────────────
+    {
+        let source_sel = source_selection.clone();
+        let hint_row = dst_hint_row.clone();
+        let hint = dst_hint.clone();
+        let btn_create = btn_create_dst.clone();
+        dst_entry.connect_changed(move |entry| {
+            show_destination_hint(&entry.text(), &source_sel.borrow(), &hint_row, &hint, &btn_create);
+        });
+    }
+    {
+        let dst_entry_c = dst_entry.clone();
+        let hint = dst_hint.clone();
+        btn_create_dst.connect_clicked(move |btn| {
+            let (host, path) = parse_destination(dst_entry_c.text().trim());
+            hint.remove_css_class("error");
+            let Some(host) = host else {
+                match folders::create_early(Path::new(&path)) {
+                    Ok(made) => {
+                        hint.set_text(&format!("Created {} folder(s)", made));
+                        btn.set_visible(false);
+                    }
+                    Err(e) => {
+                        hint.set_text(&format!("Could not create {}: {}", path, e));
+                        hint.add_css_class("error");
+                    }
+                }
+                return;
+            };
+            btn.set_sensitive(false);
+            hint.set_text(&format!("Creating {} on {}…", path, host));
+            let (tx, rx) = mpsc::channel();
+            thread::spawn(move || {
+                let _ = tx.send(create_remote_early(&host, &path));
+            });
+            let replies = relay_to_main_loop(rx);
+            let hint = hint.clone();
+            let btn = btn.clone();
+            glib::spawn_future_local(async move {
+                if let Ok(result) = replies.recv().await {
+                    btn.set_sensitive(true);
+                    match result {
+                        Ok(done) => {
+                            hint.set_text(&done);
+                            btn.set_visible(false);
+                        }
+                        Err(e) => {
+                            hint.set_text(&e);
+                            hint.add_css_class("error");
+                        }
+                    }
+                }
+            });
+        });
+    }
+
+    // ── Exclusion buttons ─────────────────────────────────────────────
+    {
+        let win = window.clone();
+        let source_sel = source_selection.clone();
+        let excls = exclusions.clone();
+        let view = excl_view.clone();
+        btn_excl_dirs.connect_clicked(move |_| {
+            let src = source_sel.borrow().clone();
+            let initial = match &src {
+                SourceSelection::Directory(p) | SourceSelection::DirectoryItems(p, _) => Some(p.clone()),
+                _ => None,
+            };
+            let dialog = FileDialog::builder()
+                .title("Select directory to exclude")
+                .modal(true)
+                .build();
+            if let Some(ref dir) = initial {
+                dialog.set_initial_folder(Some(&gtk4::gio::File::for_path(dir)));
+            }
+            let excls2 = excls.clone();
+            let view2 = view.clone();
+            dialog.select_folder(Some(&win), gtk4::gio::Cancellable::NONE, move |result| {
+                if let Ok(file) = result {
+                    if let Some(path) = file.path() {
+                        let dir_name = path
+                            .file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        let entry = format!("/{}", dir_name);
+                        let mut list = excls2.borrow_mut();
+                        if !list.contains(&entry) {
+                            list.push(entry);
+                        }
+                        refresh_exclusion_view(&view2, &list);
+                    }
+                }
+            });
+        });
+    }
+
+    {
+        let win = window.clone();
+        let source_sel = source_selection.clone();
+        let excls = exclusions.clone();
+        let view = excl_view.clone();
+        btn_excl_files.connect_clicked(move |_| {
+            let src = source_sel.borrow().clone();
+            let initial = match &src {
+                SourceSelection::Directory(p) | SourceSelection::DirectoryItems(p, _) => Some(p.clone()),
+                _ => None,
+            };
+            let dialog = FileDialog::builder()
+                .title("Select files to exclude")
+                .modal(true)
+                .build();
+            if let Some(ref dir) = initial {
+                dialog.set_initial_folder(Some(&gtk4::gio::File::for_path(dir)));
+            }
+            let excls2 = excls.clone();
+            let view2 = view.clone();
+            dialog.open_multiple(Some(&win), gtk4::gio::Cancellable::NONE, move |result| {
+                if let Ok(files) = result {
+                    let mut list = excls2.borrow_mut();
+                    for i in 0..files.n_items() {
+                        if let Some(obj) = files.item(i) {
+                            if let Ok(gfile) = obj.downcast::<gtk4::gio::File>() {
+                                if let Some(p) = gfile.path() {
+                                    let fname = p
+                                        .file_name()
+                                        .map(|n| n.to_string_lossy().to_string())
+                                        .unwrap_or_default();
+                                    if !list.contains(&fname) {
+                                        list.push(fname);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    refresh_exclusion_view(&view2, &list);
+                }
+            });
+        });
+    }
+
+    {
+        let excls = exclusions.clone();
+        let view = excl_view.clone();
+        btn_excl_clear.connect_clicked(move |_| {
+            excls.borrow_mut().clear();
+            refresh_exclusion_view(&view, &[]);
+        });
+    }
+
+    // ── Pattern files ─────────────────────────────────────────────────
+    {
+        let win = window.clone();
+        let excls = exclusions.clone();
+        let view = excl_view.clone();
+        let error = pattern_error.clone();
+        btn_excl_load.connect_clicked(move |_| {
+            let dialog = FileDialog::builder()
+                .title("Load exclusion patterns")
+                .modal(true)
+                .build();
+            let excls = excls.clone();
+            let view = view.clone();
+            let error = error.clone();
+            dialog.open(Some(&win), gtk4::gio::Cancellable::NONE, move |result| {
+                let Some(path) = result.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                match patterns::read_file(&path) {
+                    Ok(from_file) => {
+                        let mut list = excls.borrow_mut();
+                        for pattern in from_file {
+                            patterns::add_unique(&mut list, pattern);
+                        }
+                        refresh_exclusion_view(&view, &list);
+                        error.set_visible(false);
+                    }
+                    Err(e) => {
+                        error.set_text(&format!("Could not load {}.", e));
+                        error.set_visible(true);
+                    }
+                }
+            });
+        });
+    }
+
+    {
+        let win = window.clone();
+        let excls = exclusions.clone();
+        let error = pattern_error.clone();
+        btn_excl_save.connect_clicked(move |_| {
+            let dialog = FileDialog::builder()
+                .title("Save exclusion patterns")
+                .modal(true)
+                .initial_name("exclusions.txt")
+                .build();
+            let excls = excls.clone();
+            let error = error.clone();
+            dialog.save(Some(&win), gtk4::gio::Cancellable::NONE, move |result| {
+                let Some(path) = result.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                if let Err(e) = fs::write(&path, patterns::file_text(&excls.borrow())) {
+                    error.set_text(&format!("Could not save {}: {}.", path.display(), e));
+                    error.set_visible(true);
+                }
+            });
+        });
+    }
+
+    // ── Manual pattern buttons ────────────────────────────────────────
+    {
+        let excls = exclusions.clone();
+        let view = excl_view.clone();
+        let entry = pattern_entry.clone();
+        let error = pattern_error.clone();
+        btn_add_file_pattern.connect_clicked(move |_| {
+            add_typed_pattern(PatternKind::FileWildcard, &entry, &error, &excls, &view);
+        });
+    }
+
+    {
+        let excls = exclusions.clone();
+        let view = excl_view.clone();
+        let entry = pattern_entry.clone();
+        let error = pattern_error.clone();
+        btn_add_dir_pattern.connect_clicked(move |_| {
+            add_typed_pattern(PatternKind::DirWildcard, &entry, &error, &excls, &view);
+        });
+    }
+
+    {
+        let excls = exclusions.clone();
+        let view = excl_view.clone();
+        let entry = pattern_entry.clone();
+        let error = pattern_error.clone();
+        btn_add_include_pattern.connect_clicked(move |_| {
+            add_typed_pattern(PatternKind::Include, &entry, &error, &excls, &view);
+        });
+    }
+
+    // ── Quick filter toggles ──────────────────────────────────────────
+    for (toggle, _) in &quick_toggles {
+        let status_label = status_label.clone();
+        let quick_toggles = quick_toggles.clone();
+        toggle.connect_toggled(move |_| {
+            status_label.set_text(&quick_filter_status(&quick_toggles));
+        });
+    }
+
+    // ── Rename rules editor ───────────────────────────────────────────
+    btn_rename_rules.connect_clicked({
+        let window = window.clone();
+        let source_selection = source_selection.clone();
+        let src_entry = src_entry.clone();
+        let chk_strip_spaces = chk_strip_spaces.clone();
+        let chk_rename_rules = chk_rename_rules.clone();
+        let exclusions = exclusions.clone();
+        let quick_toggles = quick_toggles.clone();
+        let symlinks_dropdown = symlinks_dropdown.clone();
+        move |_| {
+            // Read the source the way the start button does.
+            let typed = src_entry.text().trim().to_string();
+            let browsed = source_selection.borrow().clone();
+            let source = if !typed.is_empty() && typed != selection_label(&browsed) {
+                match parse_destination(&typed) {
+                    (Some(host), path) => SourceSelection::Remote(host, path),
+                    (None, path) if Path::new(&path).is_file() => SourceSelection::Files(vec![PathBuf::from(path)]),
+                    (None, path) => SourceSelection::Directory(PathBuf::from(path)),
+                }
+            } else {
+                browsed
+            };
+            let patterns = active_patterns(&exclusions.borrow(), &quick_toggles);
+            let chk_rename_rules = chk_rename_rules.clone();
+            show_rename_rules_dialog(&window, source, patterns, selected_links(&symlinks_dropdown), chk_strip_spaces.is_active(), move |count| {
+                chk_rename_rules.set_label(Some(&rename_rules_label(count)));
+                chk_rename_rules.set_sensitive(count > 0);
+                chk_rename_rules.set_active(count > 0);
+            });
+        }
+    });
+
+    // ── Start button logic ────────────────────────────────────────────
+    let running = Rc::new(RefCell::new(false));
+    // Set when the user chose "Copy instead" for a read-only move source,
+    // so the restarted transfer can say so in its summary.
+    let move_downgrade_pending = Rc::new(Cell::new(false));
+    // The failed files of the last run and its destination, set by the
+    // result dialog's "Retry failed" for the restarted transfer to use.
+    let retry_pending: Rc<RefCell<Option<(SourceSelection, String)>>> = Rc::new(RefCell::new(None));
+    // The fingerprint of a run the duplicate notice is asking about, and
+    // of one the user chose to start anyway.
+    let duplicate_pending: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    let duplicate_confirmed: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    {
+        let duplicate_bar = duplicate_bar.clone();
+        let duplicate_pending = duplicate_pending.clone();
+        let retry_pending = retry_pending.clone();
+        btn_duplicate_cancel.connect_clicked(move |_| {
+            duplicate_pending.borrow_mut().take();
+            retry_pending.borrow_mut().take();
+            duplicate_bar.set_visible(false);
+        });
+    }
+    {
+        let duplicate_bar = duplicate_bar.clone();
+        let duplicate_pending = duplicate_pending.clone();
+        let duplicate_confirmed = duplicate_confirmed.clone();
+        let btn_start = btn_start.clone();
+        btn_duplicate_continue.connect_clicked(move |_| {
+            duplicate_bar.set_visible(false);
+            *duplicate_confirmed.borrow_mut() = duplicate_pending.borrow_mut().take();
+            btn_start.emit_clicked();
+        });
+    }
+
+    // What the last transfer and the saved profiles are read from and
+    // put back into.
+    let state_widgets = StateWidgets {
+        source_selection: source_selection.clone(),
+        src_entry: src_entry.clone(),
+        dst_entry: dst_entry.clone(),
+        chk_copy: chk_copy.clone(),
+        chk_move: chk_move.clone(),
+        mode_buttons: [chk_folders_files.clone(), chk_files_parent.clone(), chk_files_only.clone()],
+        method_buttons: method_buttons.clone(),
+        conflict_buttons: [chk_skip.clone(), chk_overwrite.clone(), chk_rename.clone(), chk_newer.clone()],
+        chk_strip_spaces: chk_strip_spaces.clone(),
+        exclusions: exclusions.clone(),
+        excl_view: excl_view.clone(),
+    };
+
+    btn_start.connect_clicked({
+        let state_widgets = state_widgets.clone();
+        let source_selection = source_selection.clone();
+        let src_entry = src_entry.clone();
+        let dst_entry = dst_entry.clone();
+        let chk_copy = chk_copy.clone();
+        let chk_move = chk_move.clone();
+        let move_strategy_dropdown = move_strategy_dropdown.clone();
+        let chk_remove_source_root = chk_remove_source_root.clone();
+        let move_downgrade_pending = move_downgrade_pending.clone();
+        let retry_pending = retry_pending.clone();
+        let duplicate_bar = duplicate_bar.clone();
+        let duplicate_label = duplicate_label.clone();
+        let duplicate_pending = duplicate_pending.clone();
+        let duplicate_confirmed = duplicate_confirmed.clone();
+        let chk_files_parent = chk_files_parent.clone();
+        let chk_folders_files = chk_folders_files.clone();
+        let chk_keep_parents = chk_keep_parents.clone();
+        let spin_keep_parents = spin_keep_parents.clone();
+        let spin_parallel = spin_parallel.clone();
+        let spin_retries = spin_retries.clone();
+        let chk_overwrite = chk_overwrite.clone();
+        let chk_rename = chk_rename.clone();
+        let chk_newer = chk_newer.clone();
+        let chk_strip_spaces = chk_strip_spaces.clone();
+        let chk_rename_rules = chk_rename_rules.clone();
+        let chk_preserve = chk_preserve.clone();
+        let symlinks_dropdown = symlinks_dropdown.clone();
+        let verify_dropdown = verify_dropdown.clone();
+        let hash_dropdown = hash_dropdown.clone();
+        let relay_dropdown = relay_dropdown.clone();
+        let spin_bwlimit = spin_bwlimit.clone();
+        let compress_dropdown = compress_dropdown.clone();
+        let collision_dropdown = collision_dropdown.clone();
+        let chk_low_priority = chk_low_priority.clone();
+        let chk_space_anyway = chk_space_anyway.clone();
+        let chk_print_commands = chk_print_commands.clone();
+        let chk_rsync = chk_rsync.clone();
+        let chk_tar = chk_tar.clone();
+        let chk_auto = chk_auto.clone();
+        let exclusions = exclusions.clone();
+        let quick_toggles = quick_toggles.clone();
+        let progress_bar = progress_bar.clone();
+        let speed_label = speed_label.clone();
+        let status_label = status_label.clone();
+        let btn_start = btn_start.clone();
+        let btn_pause = btn_pause.clone();
+        let btn_cancel = btn_cancel.clone();
+        let btn_skip_verify = btn_skip_verify.clone();
+        let running = running.clone();
+        let simple_switch = simple_switch.clone();
+        let window = window.clone();
+
+        move |_| {
+            if *running.borrow() {
+                return;
+            }
+
+            // "Retry failed" restarts this handler with the last run's
+            // failed files and destination in place of the entries.
+            let retried = retry_pending.borrow_mut().take();
+            let (source_sel, dst) = match retried.clone() {
+                Some(run) => run,
+                None => {
+                    let src_text = src_entry.text().to_string().trim().to_string();
+
+                    // Determine source: if the entry contains typed text, parse it;
+                    // otherwise fall back to the source_selection set by browse
+                    // buttons (whose label the entry may still be showing).
+                    let browsed = source_selection.borrow().clone();
+                    let source_sel = if !src_text.is_empty() && src_text != selection_label(&browsed) {
+                        let (host, path) = parse_destination(&src_text);
+                        match host {
+                            Some(h) => SourceSelection::Remote(h, path),
+                            None => {
+                                // Local path — could be a file or directory
+                                let p = PathBuf::from(&path);
+                                if p.is_file() {
+                                    SourceSelection::Files(vec![p])
+                                } else {
+                                    SourceSelection::Directory(p)
+                                }
+                            }
+                        }
+                    } else {
+                        browsed
+                    };
+                    (source_sel, dst_entry.text().to_string())
+                }
+            };
+
+            if let SourceSelection::None = source_sel {
+                status_label.set_text("Please select a source (folder, files, or remote).");
+                return;
+            }
+
+            if dst.is_empty() {
+                status_label.set_text("Please select or type a destination directory.");
+                return;
+            }
+            if let Some(e) = source_overlap_error(&source_sel, &dst, config::load().case_insensitive_paths) {
+                status_label.set_text(&format!("{}.", e));
+                return;
+            }
+            if let Some(e) = local_destination_error(&source_sel, &dst) {
+                status_label.set_text(&format!("{}.", e));
+                return;
+            }
+
+            let do_move = chk_move.is_active();
+            let move_downgraded = move_downgrade_pending.replace(false);
+            if do_move && source_is_read_only(&source_sel) {
+                let chk_copy = chk_copy.clone();
+                let move_downgrade_pending = move_downgrade_pending.clone();
+                let retry_pending = retry_pending.clone();
+                let retried = retried.clone();
+                let btn_start = btn_start.clone();
+                confirm_copy_instead(&window, move || {
+                    chk_copy.set_active(true);
+                    move_downgrade_pending.set(true);
+                    *retry_pending.borrow_mut() = retried.clone();
+                    btn_start.emit_clicked();
+                });
+                return;
+            }
+            let move_strategy = if move_strategy_dropdown.selected() == 1 {
+                MoveStrategy::AllThenDelete
+            } else {
+                MoveStrategy::PerFile
+            };
+            // Skip, the default, leaves the choice to a source config.
+            let conflict_choice = if chk_overwrite.is_active() {
+                Some(ConflictMode::Overwrite)
+            } else if chk_rename.is_active() {
+                Some(ConflictMode::Rename)
+            } else if chk_newer.is_active() {
+                Some(ConflictMode::Newer)
+            } else {
+                None
+            };
+            // Re-testing every remote destination before upload is CLI-only
+            // (--recheck-conflicts); the GUI re-tests the names Rename picks.
+            let recheck = Recheck::RenameTargets;
+            let mut chosen = sourceconf::Chosen {
+                patterns: active_patterns(&exclusions.borrow(), &quick_toggles),
+                conflict: conflict_choice,
+                rename_rules: chk_rename_rules.is_active().then(rename::load),
+                strip_spaces: chk_strip_spaces.is_active(),
+            };
+            let source_note = match &source_sel {
+                // A retry's files keep the folder's own defaults.
+                SourceSelection::Directory(root) | SourceSelection::DirectoryItems(root, _) if config::load().source_config => {
+                    sourceconf::merge(root, &mut chosen)
+                }
+                _ => None,
+            };
+            let sourceconf::Chosen { patterns, conflict, rename_rules, strip_spaces } = chosen;
+            let conflict_mode = conflict.unwrap_or(ConflictMode::Skip);
+            let mut rules = Vec::new();
+            if strip_spaces {
+                rules.push(Rule::strip_spaces());
+            }
+            rules.extend(rename_rules.unwrap_or_default());
+            let mut renamer = Renamer::new(rules);
+            // What "Retry failed" starts from when this run ends.
+            let retry_base = (source_sel.clone(), dst.clone());
+            // A single file sent to a path that names a file lands at
+            // exactly that path; the status line says so.
+            let mut target_note = None;
+            let dst = match file_target(&source_sel, &dst) {
+                Some((dir, name)) => {
+                    target_note = Some(replace_note(&dir, &name, conflict_mode).unwrap_or_else(|| {
+                        format!("{} to the file {}", if do_move { "Moving" } else { "Copying" }, dst.trim())
+                    }));
+                    renamer = renamer.with_target(name);
+                    dir
+                }
+                None => dst,
+            };
+            let renamer = Arc::new(renamer);
+            let transfer_mode = if chk_folders_files.is_active() {
+                TransferMode::FoldersAndFiles
+            } else if chk_files_parent.is_active() {
+                TransferMode::FilesWithParent
+            } else {
+                TransferMode::FilesOnly
+            };
+            let keep_parents = if chk_keep_parents.is_active() {
+                spin_keep_parents.value_as_int().max(0) as usize
+            } else {
+                0
+            };
+            let method_choice = if chk_rsync.is_active() {
+                MethodChoice::Rsync
+            } else if chk_tar.is_active() {
+                MethodChoice::Tar
+            } else if chk_auto.is_active() {
+                MethodChoice::Auto
+            } else {
+                MethodChoice::Standard
+            };
+            if let Some(e) = tar_method_error(method_choice, &source_sel, &dst, conflict_mode) {
+                status_label.set_text(&format!("{}.", e));
+                return;
+            }
+            let links = selected_links(&symlinks_dropdown);
+            if let Some(e) = symlinks::move_error(links, do_move) {
+                status_label.set_text(&format!("{}.", e));
+                return;
+            }
+            let checks = match verify_dropdown.selected() {
+                1 => VerifyMode::Hash,
+                2 => VerifyMode::QuickSizeMtime,
+                3 => VerifyMode::None,
+                _ => VerifyMode::Full,
+            };
+            if let Some(e) = verify::move_error(checks, do_move) {
+                status_label.set_text(&format!("{}.", e));
+                return;
+            }
+
+            // Ask before repeating a run that finished a short while ago;
+            // Continue restarts this handler with the run confirmed.
+            let fingerprint = transfer_options(
+                &source_sel, &dst, do_move, move_strategy, conflict_mode, &renamer, transfer_mode, keep_parents,
+                method_choice, &patterns,
+            )
+            .fingerprint();
+            let confirmed = duplicate_confirmed.borrow_mut().take().as_deref() == Some(fingerprint.as_str());
+            if !confirmed {
+                let window_hours = config::load().duplicate_window_hours;
+                if let Some(previous) = history::recent_duplicate(&history::load(), &fingerprint, history::now(), window_hours) {
+                    duplicate_label.set_text(&format!("{} — continue anyway?", describe_duplicate(previous)));
+                    duplicate_bar.set_visible(true);
+                    *duplicate_pending.borrow_mut() = Some(fingerprint);
+                    move_downgrade_pending.set(move_downgraded);
+                    *retry_pending.borrow_mut() = retried;
+                    return;
+                }
+            }
+            duplicate_bar.set_visible(false);
+            duplicate_pending.borrow_mut().take();
+
+            *running.borrow_mut() = true;
+            // Views are not switched while the poller updates the widgets.
+            simple_switch.set_sensitive(false);
+            btn_start.set_sensitive(false);
+            btn_pause.set_visible(true);
+            btn_cancel.set_visible(true);
+            // Transfer loses focus as it goes insensitive; keep it on a
+            // button that still does something.
+            btn_cancel.grab_focus();
+            progress_bar.update_state(&[State::Busy(true)]);
+            progress_bar.set_fraction(0.0);
+            progress_bar.set_text(Some("Scanning…"));
+            let mut status = quick_filter_status(&quick_toggles);
+            for note in [&target_note, &source_note].into_iter().flatten() {
+                if !status.is_empty() {
+                    status.push_str(" · ");
+                }
+                status.push_str(note);
+            }
+            if do_move && parse_destination(&dst).0.is_none() && is_cross_fs_move(&source_sel, &dst) {
+                if !status.is_empty() {
+                    status.push_str(" · ");
+                }
+                status.push_str(CROSS_FS_MOVE_NOTE);
+            }
+            if parse_destination(&dst).0.is_none() {
+                if let Some(warning) = overlap_warning(&source_sel, &dst) {
+                    if !status.is_empty() {
+                        status.push_str(" · ");
+                    }
+                    status.push_str(&warning);
+                }
+            }
+            status_label.set_text(&status);
+
+            // Cancel flag shared between UI and worker thread
+            let cancel_flag = Arc::new(CancelFlag::default());
+
+            // Time spent paused counts towards neither the elapsed time
+            // nor the estimate
+            let clock = Rc::new(RefCell::new(RunClock::start()));
+            let stall = Rc::new(RefCell::new(StallWatch::new(DEFAULT_STALL_AFTER)));
+
+            // Wire Cancel button
+            {
+                let cancel_flag_c = cancel_flag.clone();
+                let btn_cancel_c = btn_cancel.clone();
+                let btn_pause_c = btn_pause.clone();
+                let btn_skip_verify_c = btn_skip_verify.clone();
+                btn_cancel_c.connect_clicked(move |btn| {
+                    cancel_flag_c.request(CancelIntent::Stop);
+                    btn.set_sensitive(false);
+                    btn.set_label("Cancelling…");
+                    announce(btn, "Cancelling…");
+                    btn_pause_c.set_sensitive(false);
+                    btn_skip_verify_c.set_visible(false);
+                });
+            }
+
+            // Wire Pause button; unlike the others it flips the widgets
+            // back and forth, so it is disconnected when the run ends.
+            let mut pause_handler = {
+                let cancel_flag_c = cancel_flag.clone();
+                let progress_bar_c = progress_bar.clone();
+                let stall_c = stall.clone();
+                let clock_c = clock.clone();
+                Some(btn_pause.connect_clicked(move |btn| {
+                    let pausing = !cancel_flag_c.paused();
+                    cancel_flag_c.set_paused(pausing);
+                    if pausing {
+                        clock_c.borrow_mut().pause();
+                        btn.set_label("Resume");
+                        progress_bar_c.set_text(Some(PAUSED_TEXT));
+                        announce(btn, PAUSED_TEXT);
+                    } else {
+                        clock_c.borrow_mut().resume();
+                        // The wait was asked for, not a stall.
+                        stall_c.borrow_mut().note_progress();
+                        btn.set_label(PAUSE_LABEL);
+                        progress_bar_c.set_text(Some("Resuming…"));
+                        announce(btn, "Resumed");
+                    }
+                }))
+            };
+
+            // Wire Skip Remaining Verification button
+            {
+                let cancel_flag_c = cancel_flag.clone();
+                btn_skip_verify.connect_clicked(move |btn| {
+                    cancel_flag_c.request(CancelIntent::SkipVerify);
+                    btn.set_sensitive(false);
+                    btn.set_label("Skipping verification…");
+                    announce(btn, "Skipping verification…");
+                });
+            }
+
+            // Channel for worker → UI communication
+            let (tx, rx) = mpsc::channel::<WorkerMsg>();
+            activity::global().clear();
+            folders::reset();
+            folders::adopt_early(&dst);
+            emptied::reset();
+            timing::reset();
+            clock::reset();
+            collisions::reset();
+            hashalgo::reset();
+            quota::reset();
+            preserve::reset();
+            owners::reset();
+            patterns::reset_counts();
+            scanguard::reset();
+            // A copy's result dialog can verify it again; a move leaves
+            // nothing to compare against.
+            if do_move {
+                reverify::take();
+            } else {
+                reverify::start();
+            }
+            filereport::keep(details::limit());
+            let settings = config::load();
+            if settings.remember_state {
+                if let Err(e) = state::save(&state_widgets.read()) {
+                    debug_log(&format!("could not remember the window: {}", e));
+                }
+            }
+            let auto_thresholds = settings.auto_thresholds();
+
+            // Spawn worker thread
+            let group_root = source_sel.group_root();
+            let dst_clone = dst.clone();
+            let cancel_flag_w = cancel_flag.clone();
+            let renamer_w = renamer.clone();
+            let opts = RunOptions {
+                workers: spin_parallel.value_as_int().max(1) as usize,
+                throttle: Throttle {
+                    limit: spin_bwlimit.value_as_int().max(0) as u64,
+                    compress: match compress_dropdown.selected() {
+                        1 => Compress::Always,
+                        2 => Compress::Never,
+                        _ => Compress::Auto,
+                    },
+                },
+                verify: checks,
+                hash_algo: match hash_dropdown.selected() {
+                    1 => HashAlgo::Blake3,
+                    2 => HashAlgo::Xxh128,
+                    _ => HashAlgo::Sha256,
+                },
+                on_collision: match collision_dropdown.selected() {
+                    1 => OnCollision::Abort,
+                    _ => OnCollision::Rename,
+                },
+                relay: match relay_dropdown.selected() {
+                    1 => RelayChoice::Only(Relay::Direct),
+                    2 => RelayChoice::Only(Relay::Scp3),
+                    3 => RelayChoice::Only(Relay::Staged),
+                    _ => RelayChoice::Auto,
+                },
+                preserve: chk_preserve.is_active(),
+                low_priority: chk_low_priority.is_active(),
+                ignore_free_space: chk_space_anyway.is_active(),
+                retries: spin_retries.value_as_int().max(0) as u32,
+                symlinks: links,
+                remove_source_root: chk_remove_source_root.is_active(),
+                scan_limits: scanguard::Limits { files: settings.scan_limit_files, secs: settings.scan_limit_secs },
+            };
+            activity::print_commands(chk_print_commands.is_active());
+            // "Verify now" hashes with what the run chose.
+            let hash_algo = opts.hash_algo;
+            thread::spawn(move || {
+                if let Some(outcome) = priority::lower_this_thread(opts.low_priority) {
+                    debug_log(&outcome.describe());
+                }
+                let (dst_host, dest_path) = parse_destination(&dst_clone);
+                let src_is_remote = source_sel.remote_parts().is_some();
+                let transfer_method = method_choice
+                    .fixed()
+                    .unwrap_or_else(|| auto_method(&source_sel, dst_host.as_deref(), auto_thresholds, &tx));
+                // A cancel while Auto probed the hosts ends the run here.
+                if cancel_flag_w.stopping() {
+                    let _ = tx.send(cancelled_before_transfer(Details::skipped(), 0, 0));
+                    return;
+                }
+                match (src_is_remote, dst_host, transfer_method) {
+                    // Remote source → remote destination
+                    (true, Some(dhost), TransferMethod::Standard | TransferMethod::Tar) => {
+                        if let Some((shost, spath, sfiles)) = source_sel.remote_parts() {
+                            run_remote_to_remote_worker(
+                                shost, spath, sfiles, &dhost, &dest_path, do_move, move_strategy, conflict_mode, recheck,
+                                &renamer_w, transfer_mode, keep_parents, &patterns, &opts, cancel_flag_w, tx,
+                            );
+                        }
+                    }
+                    (true, Some(dhost), TransferMethod::Rsync) => {
+                        if let Some((shost, spath, sfiles)) = source_sel.remote_parts() {
+                            run_remote_to_remote_rsync_worker(
+                                shost, spath, sfiles, &dhost, &dest_path, do_move, move_strategy, conflict_mode, recheck,
+                                &renamer_w, transfer_mode, keep_parents, &patterns, &opts, cancel_flag_w, tx,
+                            );
+                        }
+                    }
+                    // Remote source → local destination
+                    (true, None, transfer_method) => {
+                        if let Some((shost, spath, sfiles)) = source_sel.remote_parts() {
+                            run_remote_to_local_worker(
+                                shost, spath, sfiles, &dest_path, do_move, move_strategy, conflict_mode,
+                                &renamer_w, transfer_mode, keep_parents, &patterns, transfer_method, &opts, cancel_flag_w, tx,
+                            );
+                        }
+                    }
+                    // Local source → remote destination
+                    (false, Some(host), method @ (TransferMethod::Standard | TransferMethod::Tar)) => run_remote_worker(
+                        source_sel, &host, &dest_path, do_move, move_strategy, conflict_mode, recheck,
+                        &renamer_w, transfer_mode, keep_parents, &patterns, method, &opts, cancel_flag_w, tx,
+                    ),
+                    (false, Some(host), TransferMethod::Rsync) => run_remote_rsync_worker(
+                        source_sel, &host, &dest_path, do_move, move_strategy, conflict_mode, recheck,
+                        &renamer_w, transfer_mode, keep_parents, &patterns, &opts, cancel_flag_w, tx,
+                    ),
+                    // Local source → local destination
+                    (false, None, TransferMethod::Rsync) => run_local_rsync_worker(
+                        source_sel, dest_path, do_move, move_strategy, conflict_mode,
+                        &renamer_w, transfer_mode, keep_parents, &patterns, &opts, cancel_flag_w, tx,
+                    ),
+                    (false, None, TransferMethod::Standard | TransferMethod::Tar) => run_worker(
+                        source_sel, dest_path, do_move, move_strategy, conflict_mode,
+                        &renamer_w, transfer_mode, keep_parents, &patterns, None, &opts, cancel_flag_w, tx,
+                    ),
+                }
+            });
+
+            // Follow the run on the glib main loop
+            let progress_bar_c = progress_bar.clone();
+            let speed_label_c = speed_label.clone();
+            let status_label_c = status_label.clone();
+            let btn_start_c = btn_start.clone();
+            let btn_pause_c = btn_pause.clone();
+            let btn_cancel_c = btn_cancel.clone();
+            let btn_skip_verify_c = btn_skip_verify.clone();
+            let window_c = window.clone();
+            let running_c = running.clone();
+            let simple_switch_c = simple_switch.clone();
+            let retry_pending_c = retry_pending.clone();
+            let mut model = ProgressModel::default();
+            let mut throughput = Throughput::default();
+            let mut announcer = Announcer::default();
+            let mut move_stats: Option<MoveStats> = None;
+            let mut method_decision: Option<automethod::Decision> = None;
+            let mut relay_decision: Option<relay::Decision> = None;
+            let mut stale_decision: Option<stale::Decision> = None;
+            // `group › file` of the last file processed, kept so the bar's
+            // percentage can be redrawn as a large file's bytes arrive.
+            let mut file_label = String::new();
+            let mut warnings: Vec<String> = Vec::new();
+            let mut deletion: Option<Deletion> = None;
+            let mut unverified: Vec<String> = Vec::new();
+
+            let messages = relay_to_main_loop(rx);
+
+            // Show what the worker is doing, and warn (without stopping
+            // anything) if it has made no progress for a long time.  The
+            // tick stops once the handler below has dropped the messages.
+            let run_messages = messages.downgrade();
+            let progress_bar_t = progress_bar.clone();
+            let status_label_t = status_label.clone();
+            let stall_t = stall.clone();
+            let cancel_flag_t = cancel_flag.clone();
+            glib::timeout_add_local(ACTIVITY_TICK, move || {
+                if run_messages.upgrade().is_none() {
+                    return glib::ControlFlow::Break;
+                }
+                let current = activity::global().current();
+                progress_bar_t.set_tooltip_text(current.as_ref().map(|a| a.text.as_str()));
+                let shown = current.map(|a| Activity { text: shorten_paths(&a.text), ..a });
+                // A paused run is waiting on purpose.
+                if cancel_flag_t.paused() {
+                    stall_t.borrow_mut().note_progress();
+                } else if let Some(warning) = stall_t.borrow_mut().check(shown.as_ref()) {
+                    status_label_t.set_text(&format!("Warning: {}", warning));
+                }
+                glib::ControlFlow::Continue
+            });
+
+            // Handle the worker's messages as they arrive; whatever is
+            // already queued is handled in the same wakeup.
+            glib::spawn_future_local(async move {
+                while let Ok(first) = messages.recv().await {
+                    let queued = std::iter::from_fn(|| messages.try_recv().ok());
+                    for msg in std::iter::once(first).chain(queued) {
+                        match msg {
+                            WorkerMsg::ScanProgress { files_found, bytes_found } => {
+                                stall.borrow_mut().note_progress();
+                                progress_bar_c.set_text(Some(&scanguard::describe_found(files_found, bytes_found)));
+                            }
+                            WorkerMsg::Plan { sizes } => {
+                                model = ProgressModel::new(sizes);
+                            }
+                            WorkerMsg::MoveStats(st) => {
+                                move_stats = Some(st);
+                            }
+                            WorkerMsg::DeletePhase { total } => {
+                                btn_skip_verify_c.set_visible(false);
+                                model = ProgressModel::default();
+                                model.set_phase(Phase::Finalizing);
+                                throughput = Throughput::default();
+                                speed_label_c.set_visible(false);
+                                show_phase(&progress_bar_c, Phase::Finalizing);
+                                announce_progress(&progress_bar_c, &mut announcer, &model);
+                                progress_bar_c.set_fraction(0.0);
+                                status_label_c.set_text(&format!(
+                                    "Every file copied and verified — deleting {} source file(s)…",
+                                    total
+                                ));
+                            }
+                            WorkerMsg::Deletion(d) => {
+                                deletion = Some(d);
+                            }
+                            WorkerMsg::Unverified(files) => {
+                                filereport::unverified(&files);
+                                unverified = files;
+                            }
+                            WorkerMsg::AutoMethod(decision) => {
+                                status_label_c.set_text(&decision.describe());
+                                method_decision = Some(decision);
+                            }
+                            WorkerMsg::Relay(decision) => {
+                                relay_decision = Some(decision);
+                            }
+                            WorkerMsg::RsyncPeers { warnings: w, .. } => {
+                                if let Some(first) = w.first() {
+                                    status_label_c.set_text(&format!("Warning: {}", first));
+                                }
+                                warnings.extend(w);
+                            }
+                            WorkerMsg::ClockSkew { warning: Some(w), .. } => {
+                                status_label_c.set_text(&format!("Warning: {}", w));
+                                warnings.push(w);
+                            }
+                            WorkerMsg::ClockSkew { .. } => {}
+                            WorkerMsg::Unwritable { dirs, reply } => {
+                                status_label_c.set_text("Some destination folders are not writable");
+                                ask_on_unwritable(&window_c, &dirs, move |choice| {
+                                    let _ = reply.send(choice);
+                                });
+                            }
+                            WorkerMsg::StaleArtifacts { found, at, reply } => {
+                                status_label_c.set_text("An earlier run left files at the destination");
+                                ask_on_stale(&window_c, &found, &at, move |choice| {
+                                    let _ = reply.send(choice);
+                                });
+                            }
+                            WorkerMsg::Stale(decision) => {
+                                warnings.extend(decision.failed.iter().map(|e| format!("Could not remove {}", e)));
+                                stale_decision = Some(decision);
+                            }
+                            WorkerMsg::QuotaExceeded { message, reply } => {
+                                status_label_c.set_text("Destination quota exceeded");
+                                ask_on_quota(&window_c, &message, move |choice| {
+                                    let _ = reply.send(choice);
+                                });
+                            }
+                            WorkerMsg::ScanLimit { trip, reply } => {
+                                status_label_c.set_text("The scan has passed its limit");
+                                ask_scan_limit(&window_c, &trip, move |go_on| {
+                                    let _ = reply.send(go_on);
+                                });
+                            }
+                            // Only `--cli` runs with a hash cache, only the
+                            // result dialog verifies a run again, and the
+                            // error after a shortfall says it.
+                            WorkerMsg::HashCache(_) | WorkerMsg::Reverified(_) | WorkerMsg::ShortOfSpace(_) => {}
+                            WorkerMsg::HostKey { host, problem, keys, reply } => {
+                                // A changed key, or one that couldn't be fetched,
+                                // is only explained by the error that follows.
+                                if problem != ssh::HostKeyProblem::Unknown || keys.is_empty() {
+                                    let _ = reply.send(false);
+                                    continue;
+                                }
+                                status_label_c.set_text(&format!("'{}' is not a known host yet", host));
+                                let window = window_c.clone();
+                                let (host_c, keys_c) = (host.clone(), keys.clone());
+                                ask_host_key(&window_c, &host_c, &keys_c, move |accepted| {
+                                    let added = accepted
+                                        && match known_hosts_file(&host)
+                                            .ok_or_else(|| "no home directory".to_string())
+                                            .and_then(|path| ssh::append_known_hosts(&path, &keys).map_err(|e| e.to_string()))
+                                        {
+                                            Ok(()) => true,
+                                            Err(e) => {
+                                                let msg = format!("Could not add the keys of '{}' to known_hosts: {}", host, e);
+                                                show_result_dialog(&window, "Error", &format!("<b>{}</b>", glib::markup_escape_text(&msg)), &[], &[], &[], Followups::none());
+                                                false
+                                            }
+                                        };
+                                    let _ = reply.send(added);
+                                });
+                            }
+                            WorkerMsg::FileBytes { bytes } => {
+                                stall.borrow_mut().note_progress();
+                                model.in_flight(bytes);
+                                throughput.sample(clock.borrow().elapsed(), &model);
+                                show_speed(&speed_label_c, &throughput, &model);
+                                show_phase(&progress_bar_c, model.phase());
+                                announce_progress(&progress_bar_c, &mut announcer, &model);
+                                progress_bar_c.set_fraction(model.bar_fraction());
+                                if model.phase_text().is_none() && !cancel_flag.paused() {
+                                    let left = throughput.left(&model).or_else(|| model.eta(clock.borrow().elapsed()));
+                                    progress_bar_c.set_text(Some(&model.transfer_text(&file_label, left)));
+                                }
+                            }
+                            WorkerMsg::Phase(phase) => {
+                                // Offered from the first check until the run
+                                // finalizes, so it doesn't flicker between files.
+                                match phase {
+                                    Phase::Verifying if !btn_skip_verify_c.is_visible() && btn_cancel_c.is_sensitive() => {
+                                        btn_skip_verify_c.set_visible(true)
+                                    }
+                                    Phase::Finalizing => btn_skip_verify_c.set_visible(false),
+                                    _ => {}
+                                }
+                                if model.set_phase(phase) {
+                                    show_phase(&progress_bar_c, phase);
+                                    announce_progress(&progress_bar_c, &mut announcer, &model);
+                                    if let Some(text) = model.phase_text().filter(|_| !cancel_flag.paused()) {
+                                        progress_bar_c.set_text(Some(&text));
+                                    }
+                                }
+                            }
+                            WorkerMsg::Progress { done, total, file } => {
+                                stall.borrow_mut().note_progress();
+                                model.file_done(done, total);
+                                throughput.sample(clock.borrow().elapsed(), &model);
+                                show_speed(&speed_label_c, &throughput, &model);
+                                show_phase(&progress_bar_c, model.phase());
+                                announce_progress(&progress_bar_c, &mut announcer, &model);
+                                progress_bar_c.set_fraction(model.bar_fraction());
+                                let filename = Path::new(&file)
+                                    .file_name()
+                                    .map(|n| n.to_string_lossy().to_string())
+                                    .unwrap_or_else(|| file.clone());
+                                let filename = ellipsize_middle(&filename, MAX_NAME_LEN);
+                                let group = stats::group_name(group_root.as_deref(), &file);
+                                file_label = format!("{} › {}", group, filename);
+                                // Deleting sources reads as finalizing, not as
+                                // another transfer.
+                                let text = match model.phase_text() {
+                                    // Until the worker resumes, the file just
+                                    // done is the last one.
+                                    _ if cancel_flag.paused() => PAUSED_TEXT.to_string(),
+                                    Some(phase) => format!("{} — {}", phase, filename),
+                                    None => model.transfer_text(
+                                        &file_label,
+                                        throughput.left(&model).or_else(|| model.eta(clock.borrow().elapsed())),
+                                    ),
+                                };
+                                progress_bar_c.set_text(Some(&text));
+                            }
+                            WorkerMsg::Finished(mut run) => {
+                                run.flush();
+                                let TransferReport { copied, ref skipped, excluded_files, excluded_dirs, ref errors, ref failed, ref groups } =
+                                    run;
+                                if let Err(e) = history::record(&fingerprint, copied) {
+                                    debug_log(&format!("could not record run history: {}", e));
+                                }
+                                show_phase(&progress_bar_c, Phase::Transferring);
+                                progress_bar_c.set_fraction(1.0);
+                                warnings.extend(preserve::take());
+                                warnings.extend(collisions::take());
+                                let summary = RunSummary {
+                                    ending: Ending::Finished,
+                                    moved: do_move,
+                                    copied,
+                                    bytes: transferred_bytes(&model),
+                                    elapsed: Some(clock.borrow().elapsed()),
+                                    skipped: skipped.len(),
+                                    errors: errors.len(),
+                                    excluded_files,
+                                    excluded_dirs,
+                                    in_excluded_dirs: patterns::in_excluded_dirs(),
+                                    move_downgraded,
+                                    unverified: unverified.len(),
+                                    notes: summary_notes(
+                                        method_decision.as_ref(),
+                                        relay_decision.as_ref(),
+                                        scanguard::report().as_ref(),
+                                        move_stats,
+                                        deletion,
+                                        emptied::take(),
+                                        Some(&renamer.log()),
+                                        Some(&folders::take()),
+                                        Some(&timing::take()),
+                                        quota::take().as_ref(),
+                                        None,
+                                        stale_decision.as_ref(),
+                                    ),
+                                    warnings: warnings.clone(),
+                                };
+                                progress_bar_c.set_text(Some("Complete"));
+                                status_label_c.set_text(&summary.to_human());
+                                btn_start_c.set_sensitive(true);
+                                if let Some(handler) = pause_handler.take() {
+                                    btn_pause_c.disconnect(handler);
+                                }
+                                btn_pause_c.set_visible(false);
+                                btn_pause_c.set_sensitive(true);
+                                btn_pause_c.set_label(PAUSE_LABEL);
+                                btn_cancel_c.set_visible(false);
+                                btn_cancel_c.set_sensitive(true);
+                                btn_cancel_c.set_label("Cancel");
+                                btn_skip_verify_c.set_visible(false);
+                                btn_skip_verify_c.set_sensitive(true);
+                                btn_skip_verify_c.set_label(SKIP_VERIFY_LABEL);
+                                *running_c.borrow_mut() = false;
+                                simple_switch_c.set_sensitive(true);
+                                progress_bar_c.set_tooltip_text(None);
+                                speed_label_c.set_visible(false);
+                                run_ended(&progress_bar_c, &btn_start_c, &summary.headline());
+
+                                let title = if errors.is_empty() && skipped.is_empty() && warnings.is_empty() && unverified.is_empty()
+                                {
+                                    "Complete"
+                                } else if errors.is_empty() && skipped.is_empty() && warnings.is_empty() {
+                                    "Completed without full verification"
+                                } else if errors.is_empty() && skipped.is_empty() {
+                                    "Completed with warnings"
+                                } else if !errors.is_empty() {
+                                    "Completed with errors"
+                                } else {
+                                    "Completed with skipped files"
+                                };
+
+                                let all_notes = result_notes(&run, &unverified);
+                                let record = reverify::take();
+                                let report = filereport::take();
+                                let verify_now = if do_move {
+                                    VerifyNow::Disabled("A move's sources are gone, so there is nothing to compare against")
+                                } else if record.is_empty() {
+                                    VerifyNow::Hidden
+                                } else {
+                                    VerifyNow::Offer(record, hash_algo)
+                                };
+                                show_result_dialog(&window_c, title, &summary.to_markup(), groups, &all_notes, failed, Followups {
+                                    verify_now,
+                                    report,
+                                    commands: activity::take_printed(),
+                                    retry: retry_followup(&retry_pending_c, &btn_start_c, &retry_base, failed),
+                                });
+
+                                return;
+                            }
+                            WorkerMsg::Error(e) => {
+                                show_phase(&progress_bar_c, Phase::Transferring);
+                                progress_bar_c.set_fraction(0.0);
+                                progress_bar_c.set_text(Some("Error"));
+                                status_label_c.set_text(&e);
+                                btn_start_c.set_sensitive(true);
+                                if let Some(handler) = pause_handler.take() {
+                                    btn_pause_c.disconnect(handler);
+                                }
+                                btn_pause_c.set_visible(false);
+                                btn_pause_c.set_sensitive(true);
+                                btn_pause_c.set_label(PAUSE_LABEL);
+                                btn_cancel_c.set_visible(false);
+                                btn_cancel_c.set_sensitive(true);
+                                btn_cancel_c.set_label("Cancel");
+                                btn_skip_verify_c.set_visible(false);
+                                btn_skip_verify_c.set_sensitive(true);
+                                btn_skip_verify_c.set_label(SKIP_VERIFY_LABEL);
+                                *running_c.borrow_mut() = false;
+                                simple_switch_c.set_sensitive(true);
+                                progress_bar_c.set_tooltip_text(None);
+                                speed_label_c.set_visible(false);
+                                run_ended(&progress_bar_c, &btn_start_c, &e);
+
+                                filereport::take();
+                                show_result_dialog(&window_c, "Error", &format!("<b>{}</b>", glib::markup_escape_text(&e)), &[], &[], &[], Followups::none());
+
+                                return;
+                            }
+                            WorkerMsg::Cancelled(mut run) => {
+                                run.flush();
+                                let TransferReport { copied, ref skipped, excluded_files, excluded_dirs, ref errors, ref failed, .. } = run;
+                                warnings.extend(preserve::take());
+                                warnings.extend(collisions::take());
+                                let summary = RunSummary {
+                                    ending: Ending::Cancelled(model.phase()),
+                                    moved: do_move,
+                                    copied,
+                                    bytes: transferred_bytes(&model),
+                                    elapsed: Some(clock.borrow().elapsed()),
+                                    skipped: skipped.len(),
+                                    errors: errors.len(),
+                                    excluded_files,
+                                    excluded_dirs,
+                                    in_excluded_dirs: patterns::in_excluded_dirs(),
+                                    move_downgraded,
+                                    unverified: unverified.len(),
+                                    notes: summary_notes(
+                                        method_decision.as_ref(),
+                                        relay_decision.as_ref(),
+                                        scanguard::report().as_ref(),
+                                        move_stats,
+                                        deletion,
+                                        emptied::take(),
+                                        Some(&renamer.log()),
+                                        Some(&folders::take()),
+                                        Some(&timing::take()),
+                                        quota::take().as_ref(),
+                                        None,
+                                        stale_decision.as_ref(),
+                                    ),
+                                    warnings: warnings.clone(),
+                                };
+                                show_phase(&progress_bar_c, Phase::Transferring);
+                                progress_bar_c.set_text(Some("Cancelled"));
+                                status_label_c.set_text(&summary.to_human());
+                                btn_start_c.set_sensitive(true);
+                                if let Some(handler) = pause_handler.take() {
+                                    btn_pause_c.disconnect(handler);
+                                }
+                                btn_pause_c.set_visible(false);
+                                btn_pause_c.set_sensitive(true);
+                                btn_pause_c.set_label(PAUSE_LABEL);
+                                btn_cancel_c.set_visible(false);
+                                btn_cancel_c.set_sensitive(true);
+                                btn_cancel_c.set_label("Cancel");
+                                btn_skip_verify_c.set_visible(false);
+                                btn_skip_verify_c.set_sensitive(true);
+                                btn_skip_verify_c.set_label(SKIP_VERIFY_LABEL);
+                                *running_c.borrow_mut() = false;
+                                simple_switch_c.set_sensitive(true);
+                                progress_bar_c.set_tooltip_text(None);
+                                speed_label_c.set_visible(false);
+                                run_ended(&progress_bar_c, &btn_start_c, &summary.headline());
+
+                                let all_notes = result_notes(&run, &unverified);
+                                show_result_dialog(&window_c, "Cancelled", &summary.to_markup(), &[], &all_notes, failed, Followups {
+                                    verify_now: VerifyNow::Hidden,
+                                    report: filereport::take(),
+                                    commands: activity::take_printed(),
+                                    retry: retry_followup(&retry_pending_c, &btn_start_c, &retry_base, failed),
+                                });
+
+                                return;
+                            }
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    // ── Profiles ──────────────────────────────────────────────────────
+    // Picking a profile puts its choices into the window; Save Profile
+    // stores the window's current choices under a name.
+    let profile_names = gtk4::StringList::new(&[NO_PROFILE]);
+    let profile_dropdown = DropDown::new(Some(profile_names.clone()), gtk4::Expression::NONE);
+    profile_dropdown.set_tooltip_text(Some("Put a saved profile's source, destination and options into the window"));
+    let btn_save_profile = Button::with_label("Save Profile…");
+    btn_save_profile.set_tooltip_text(Some("Save the source, destination and options under a name"));
+    let btn_rename_profile = Button::with_label("Rename…");
+    let btn_delete_profile = Button::with_label("Delete");
+    btn_rename_profile.set_sensitive(false);
+    btn_delete_profile.set_sensitive(false);
+    let profile_box = GtkBox::new(Orientation::Horizontal, 6);
+    profile_box.append(&profile_dropdown);
+    profile_box.append(&btn_save_profile);
+    profile_box.append(&btn_rename_profile);
+    profile_box.append(&btn_delete_profile);
+    header.pack_start(&profile_box);
+
+    // Set while the list is rebuilt, so reselecting doesn't reapply.
+    let filling_profiles = Rc::new(Cell::new(false));
+    let fill_profiles = Rc::new({
+        let profile_names = profile_names.clone();
+        let profile_dropdown = profile_dropdown.clone();
+        let filling_profiles = filling_profiles.clone();
+        move |select: Option<&str>| {
+            filling_profiles.set(true);
+            let names = profiles::list();
+            let mut items = vec![NO_PROFILE];
+            items.extend(names.iter().map(String::as_str));
+            profile_names.splice(0, profile_names.n_items(), &items);
+            let index = select.and_then(|name| names.iter().position(|n| n == name)).map_or(0, |i| i + 1);
+            profile_dropdown.set_selected(index as u32);
+            filling_profiles.set(false);
+        }
+    });
+    fill_profiles(None);
+
+    profile_dropdown.connect_selected_notify({
+        let state_widgets = state_widgets.clone();
+        let btn_rename_profile = btn_rename_profile.clone();
+        let btn_delete_profile = btn_delete_profile.clone();
+        let status_label = status_label.clone();
+        move |dropdown| {
+            let name = selected_profile(dropdown);
+            btn_rename_profile.set_sensitive(name.is_some());
+            btn_delete_profile.set_sensitive(name.is_some());
+            if filling_profiles.get() {
+                return;
+            }
+            let Some(name) = name else { return };
+            match profiles::load(&name) {
+                Ok(profile) => {
+                    state_widgets.apply(&profile);
+                    status_label.set_text(&format!("Profile '{}' loaded.", name));
+                }
+                Err(e) => status_label.set_text(&format!("Could not load profile '{}': {}", name, e)),
+            }
+        }
+    });
+
+    btn_save_profile.connect_clicked({
+        let state_widgets = state_widgets.clone();
+        let window = window.clone();
+        let profile_dropdown = profile_dropdown.clone();
+        let fill_profiles = fill_profiles.clone();
+        let status_label = status_label.clone();
+        move |_| {
+            let current = state_widgets.read();
+            let fill_profiles = fill_profiles.clone();
+            let status_label = status_label.clone();
+            let initial = selected_profile(&profile_dropdown).unwrap_or_default();
+            ask_profile_name(&window, "Save Profile", &initial, "Save", move |name| {
+                profiles::save(name, &current)?;
+                fill_profiles(Some(name));
+                status_label.set_text(&format!("Profile '{}' saved.", name));
+                Ok(())
+            });
+        }
+    });
+
+    btn_rename_profile.connect_clicked({
+        let window = window.clone();
+        let profile_dropdown = profile_dropdown.clone();
+        let fill_profiles = fill_profiles.clone();
+        let status_label = status_label.clone();
+        move |_| {
+            let Some(old) = selected_profile(&profile_dropdown) else { return };
+            let fill_profiles = fill_profiles.clone();
+            let status_label = status_label.clone();
+            let title = format!("Rename Profile '{}'", old);
+            ask_profile_name(&window, &title, &old.clone(), "Rename", move |name| {
+                if name != old {
+                    profiles::rename(&old, name)?;
+                }
+                fill_profiles(Some(name));
+                status_label.set_text(&format!("Profile '{}' renamed to '{}'.", old, name));
+                Ok(())
+            });
+        }
+    });
+
+    btn_delete_profile.connect_clicked({
+        let status_label = status_label.clone();
+        move |_| {
+            let Some(name) = selected_profile(&profile_dropdown) else { return };
+            match profiles::delete(&name) {
+                Ok(()) => {
+                    fill_profiles(None);
+                    status_label.set_text(&format!("Profile '{}' deleted.", name));
+                }
+                Err(e) => status_label.set_text(&format!("Could not delete profile '{}': {}", name, e)),
+            }
+        }
+    });
+
+    // ── The last transfer's choices ───────────────────────────────────
+    if saved.remember_state {
+        state_widgets.apply(&state::load());
+    }
+
+    window.present();
+}
+
+/// The widgets a `WindowState` is read from and put back into: the last
+/// transfer's choices and the saved profiles.
+#[derive(Clone)]
+struct StateWidgets {
+    source_selection: Rc<RefCell<SourceSelection>>,
+    src_entry: Entry,
+    dst_entry: Entry,
+    chk_copy: CheckButton,
+    chk_move: CheckButton,
+    /// Folders and files, files with parent, files only.
+    mode_buttons: [CheckButton; 3],
+    /// Standard, rsync, Auto.
+    /// Standard, rsync, Auto and tar.
+    method_buttons: [CheckButton; 4],
+    /// Skip, overwrite, rename, newer.
+    conflict_buttons: [CheckButton; 4],
+    chk_strip_spaces: CheckButton,
+    exclusions: Rc<RefCell<Vec<String>>>,
+    excl_view: ListBox,
+}
+
+impl StateWidgets {
+    fn read(&self) -> state::WindowState {
+        // Typed text wins over a browsed selection, as when starting.
+        let typed = self.src_entry.text().trim().to_string();
+        let browsed = self.source_selection.borrow();
+        let source = if !typed.is_empty() && typed != selection_label(&browsed) {
+            Some(typed)
+        } else {
+            remembered_source(&browsed)
+        };
+        let [folders, parent, _] = &self.mode_buttons;
+        let [_, rsync, auto, tar] = &self.method_buttons;
+        let [_, overwrite, rename, newer] = &self.conflict_buttons;
+        state::WindowState {
+            source,
+            destination: Some(self.dst_entry.text().to_string()),
+            do_move: Some(self.chk_move.is_active()),
+            transfer_mode: Some(if folders.is_active() {
+                TransferMode::FoldersAndFiles
+            } else if parent.is_active() {
+                TransferMode::FilesWithParent
+            } else {
+                TransferMode::FilesOnly
+            }),
+            method: Some(if rsync.is_active() {
+                MethodChoice::Rsync
+            } else if tar.is_active() {
+                MethodChoice::Tar
+            } else if auto.is_active() {
+                MethodChoice::Auto
+            } else {
+                MethodChoice::Standard
+            }),
+            conflict: Some(if overwrite.is_active() {
+                ConflictMode::Overwrite
+            } else if rename.is_active() {
+                ConflictMode::Rename
+            } else if newer.is_active() {
+                ConflictMode::Newer
+            } else {
+                ConflictMode::Skip
+            }),
+            strip_spaces: Some(self.chk_strip_spaces.is_active()),
+            patterns: Some(self.exclusions.borrow().clone()),
+        }
+    }
+
+    /// Put `state`'s choices into the window, leaving alone what it lacks.
+    fn apply(&self, state: &state::WindowState) {
+        if let Some(source) = &state.source {
+            self.src_entry.set_text(source);
+        }
+        if let Some(destination) = &state.destination {
+            self.dst_entry.set_text(destination);
+        }
+        if let Some(do_move) = state.do_move {
+            if do_move { &self.chk_move } else { &self.chk_copy }.set_active(true);
+        }
+        if let Some(mode) = state.transfer_mode {
+            let [folders, parent, files] = &self.mode_buttons;
+            match mode {
+                TransferMode::FoldersAndFiles => folders,
+                TransferMode::FilesWithParent => parent,
+                TransferMode::FilesOnly => files,
+            }
+            .set_active(true);
+        }
+        if let Some(method) = state.method {
+            select_method(&self.method_buttons, method);
+        }
+        if let Some(conflict) = state.conflict {
+            let [skip, overwrite, rename, newer] = &self.conflict_buttons;
+            match conflict {
+                ConflictMode::Skip => skip,
+                ConflictMode::Overwrite => overwrite,
+                ConflictMode::Rename => rename,
+                ConflictMode::Newer => newer,
+            }
+            .set_active(true);
+        }
+        if let Some(strip_spaces) = state.strip_spaces {
+            self.chk_strip_spaces.set_active(strip_spaces);
+        }
+        if let Some(patterns) = &state.patterns {
+            refresh_exclusion_view(&self.excl_view, patterns);
+            *self.exclusions.borrow_mut() = patterns.clone();
+        }
+    }
+}
+
+/// The symbolic-link policy picked in `dropdown`.
+fn selected_links(dropdown: &DropDown) -> symlinks::Policy {
+    match dropdown.selected() {
+        1 => symlinks::Policy::Follow,
+        2 => symlinks::Policy::Preserve,
+        _ => symlinks::Policy::Skip,
+    }
+}
+
+/// The profile dropdown's first entry, standing for none.
+const NO_PROFILE: &str = "No profile";
+
+/// The profile picked in `dropdown`, if any.
+fn selected_profile(dropdown: &DropDown) -> Option<String> {
+    dropdown
+        .selected_item()
+        .and_then(|item| item.downcast::<gtk4::StringObject>().ok())
+        .map(|item| item.string().to_string())
+        .filter(|_| dropdown.selected() > 0)
+}
+
+/// Ask for a profile name, starting from `initial`.  `on_name` runs when
+/// the button is pressed on a usable name; the dialog stays open with the
+/// error shown if it fails.
+fn ask_profile_name(
+    parent: &ApplicationWindow,
+    title: &str,
+    initial: &str,
+    action: &str,
+    on_name: impl Fn(&str) -> std::io::Result<()> + 'static,
+) {
+    let dialog = Window::builder()
+        .title(title)
+        .modal(true)
+        .transient_for(parent)
+        .default_width(360)
+        .resizable(false)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let entry = Entry::new();
+    entry.set_text(initial);
+    entry.set_placeholder_text(Some("e.g. photos-to-nas"));
+    entry.set_activates_default(true);
+    vbox.append(&entry);
+    let error = Label::new(None);
+    error.set_halign(Align::Start);
+    error.set_wrap(true);
+    error.add_css_class("error");
+    error.set_visible(false);
+    vbox.append(&error);
+
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    btn_row.set_halign(Align::End);
+    let btn_cancel = Button::with_label("Cancel");
+    let btn_ok = Button::with_label(action);
+    btn_ok.add_css_class("suggested-action");
+    btn_row.append(&btn_cancel);
+    btn_row.append(&btn_ok);
+    vbox.append(&btn_row);
+
+    {
+        let dialog_ref = dialog.clone();
+        btn_cancel.connect_clicked(move |_| dialog_ref.close());
+    }
+    {
+        let dialog_ref = dialog.clone();
+        let entry = entry.clone();
+        btn_ok.connect_clicked(move |_| {
+            let name = entry.text().to_string();
+            let result = match profiles::name_error(&name) {
+                Some(reason) => Err(reason.to_string()),
+                None => on_name(&name).map_err(|e| e.to_string()),
+            };
+            match result {
+                Ok(()) => dialog_ref.close(),
+                Err(e) => {
+                    error.set_text(&e);
+                    error.set_visible(true);
+                }
+            }
+        });
+    }
+
+    dialog.set_child(Some(&vbox));
+    dialog.set_default_widget(Some(&btn_ok));
+    dialog.present();
+}
+
+/// The source as `state.toml` keeps it: what would be typed to choose it
+/// again, or `None` for a selection that has no such text.
+fn remembered_source(source: &SourceSelection) -> Option<String> {
+    match source {
+        SourceSelection::Directory(_) | SourceSelection::Remote(_, _) => Some(selection_label(source)),
+        SourceSelection::Files(paths) if paths.len() == 1 => Some(selection_label(source)),
+        _ => None,
+    }
+}
+
+// ── Helper: worker messages on the main loop ──────────────────────────
+
+/// How often a run's current activity is shown and checked for stalls.
+const ACTIVITY_TICK: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Hand the messages a worker sends on `rx` to the glib main loop, which
+/// then wakes only when one arrives.  The workers keep their plain
+/// `mpsc::Sender`, as the CLI waits on them with a blocking loop; a relay
+/// thread forwards each message and stops once either side is gone.
+fn relay_to_main_loop<T: Send + 'static>(rx: mpsc::Receiver<T>) -> async_channel::Receiver<T> {
+    let (tx, messages) = async_channel::unbounded();
+    thread::spawn(move || {
+        for msg in rx {
+            if tx.send_blocking(msg).is_err() {
+                break;
+            }
+        }
+    });
+    messages
+}
+
+// ── Helper: first-run setup page ──────────────────────────────────────
+
+const AUTO_METHOD_TOOLTIP: &str =
+    "rsync for more than a couple of hundred files or 500 MB to or from another host, when it is installed on both; cp/scp otherwise";
+
+const TAR_METHOD_TOOLTIP: &str =
+    "One tar stream over ssh for uploads of many small files; skip or overwrite only";
+
+/// Tick the radio button for `choice` among the standard, rsync, Auto and
+/// (when there is one) tar `buttons`.
+fn select_method(buttons: &[CheckButton], choice: MethodChoice) {
+    match choice {
+        MethodChoice::Standard => buttons[0].set_active(true),
+        MethodChoice::Rsync => buttons[1].set_active(true),
+        MethodChoice::Auto => buttons[2].set_active(true),
+        MethodChoice::Tar => match buttons.get(3) {
+            Some(tar) => tar.set_active(true),
+            None => buttons[0].set_active(true),
+        },
+    }
+}
+
+/// The one-time setup page shown in place of the main view on first launch.
+/// "Continue" writes the initial settings, ticks the chosen method among the
+/// main view's `method_buttons` and swaps `main_view` back in.
+fn setup_page(window: &ApplicationWindow, main_view: &GtkBox, method_buttons: &[CheckButton; 4]) -> GtkBox {
+    let defaults = config::Config::default();
+    let page = GtkBox::new(Orientation::Vertical, 12);
+    page.set_margin_top(24);
+    page.set_margin_bottom(24);
+    page.set_margin_start(24);
+    page.set_margin_end(24);
+
+    let heading = Label::new(Some("Welcome to Kosmokopy"));
+    heading.add_css_class("title-2");
+    heading.set_halign(Align::Start);
+    page.append(&heading);
+
+    let intro = Label::new(Some("A few defaults before you start. You can change them later."));
+    intro.set_halign(Align::Start);
+    intro.set_wrap(true);
+    page.append(&intro);
+
+    let method_box = GtkBox::new(Orientation::Horizontal, 12);
+    let method_label = Label::new(Some("Default transfer method:"));
+    let chk_standard = CheckButton::with_label("Standard (cp/scp)");
+    let chk_default_rsync = CheckButton::with_label("rsync");
+    chk_default_rsync.set_group(Some(&chk_standard));
+    let chk_default_auto = CheckButton::with_label("Auto");
+    chk_default_auto.set_group(Some(&chk_standard));
+    chk_default_auto.set_tooltip_text(Some(AUTO_METHOD_TOOLTIP));
+    let default_buttons = [chk_standard.clone(), chk_default_rsync.clone(), chk_default_auto.clone()];
+    select_method(&default_buttons, defaults.method);
+    method_box.append(&method_label);
+    method_box.append(&chk_standard);
+    method_box.append(&chk_default_rsync);
+    method_box.append(&chk_default_auto);
+    page.append(&method_box);
+
+    let chk_remember = CheckButton::with_label("Remember the last source, destination and options");
+    chk_remember.set_active(defaults.remember_state);
+    page.append(&chk_remember);
+
+    let chk_notify = CheckButton::with_label("Show a notification when a transfer finishes");
+    chk_notify.set_active(defaults.notifications);
+    page.append(&chk_notify);
+
+    let status = Label::new(None);
+    status.set_halign(Align::Start);
+    status.set_wrap(true);
+    page.append(&status);
+
+    let btn_continue = Button::with_label("Continue");
+    btn_continue.add_css_class("suggested-action");
+    btn_continue.set_halign(Align::End);
+    btn_continue.set_vexpand(true);
+    btn_continue.set_valign(Align::End);
+    page.append(&btn_continue);
+
+    let window = window.clone();
+    let main_view = main_view.clone();
+    let method_buttons = method_buttons.clone();
+    btn_continue.connect_clicked(move |_| {
+        let saved = config::load();
+        let method = if chk_default_rsync.is_active() {
+            MethodChoice::Rsync
+        } else if chk_default_auto.is_active() {
+            MethodChoice::Auto
+        } else {
+            MethodChoice::Standard
+        };
+        let chosen = config::Config {
+            method,
+            remember_state: chk_remember.is_active(),
+            notifications: chk_notify.is_active(),
+            // The header-bar switch may already have been used.
+            simple_mode: saved.simple_mode,
+            // Only ever set by editing settings.conf.
+            duplicate_window_hours: saved.duplicate_window_hours,
+            auto_rsync_files: saved.auto_rsync_files,
+            auto_rsync_bytes: saved.auto_rsync_bytes,
+            scan_limit_files: saved.scan_limit_files,
+            scan_limit_secs: saved.scan_limit_secs,
+            case_insensitive_paths: saved.case_insensitive_paths,
+            ssh_control_dir: saved.ssh_control_dir,
+            source_config: saved.source_config,
+            timestamps: saved.timestamps,
+        };
+        if let Err(e) = config::save(&chosen) {
+            status.set_text(&format!("Could not save settings: {}", e));
+            return;
+        }
+        select_method(&method_buttons, chosen.method);
+        window.set_child(Some(&main_view));
+    });
+    page
+}
+
+// ── Helper: rename rules editor ───────────────────────────────────────
+
+/// How many renames the editor's preview lists before summing up the rest.
+const RENAME_PREVIEW_LIMIT: usize = 200;
+
+fn rename_rules_label(count: usize) -> String {
+    format!("Apply rename rules ({})", count)
+}
+
+/// Edit the saved rename rules: add, remove and reorder them, and preview
+/// what they (after "Remove spaces", when `strip_spaces`) do to the files
+/// of `source`, scanned with `links`.  `on_saved` gets the number of
+/// rules saved.
+fn show_rename_rules_dialog(
+    parent: &ApplicationWindow,
+    source: SourceSelection,
+    patterns: Vec<String>,
+    links: symlinks::Policy,
+    strip_spaces: bool,
+    on_saved: impl Fn(usize) + 'static,
+) {
+    let dialog = Window::builder()
+        .title("Rename Rules")
+        .modal(true)
+        .transient_for(parent)
+        .default_width(560)
+        .default_height(520)
+        .resizable(true)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 8);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let intro = Label::new(Some(
+        "Rules run in order on every name below the destination folder, before existing files are checked.",
+    ));
+    intro.set_halign(Align::Start);
+    intro.set_wrap(true);
+    vbox.append(&intro);
+
+    let rules = Rc::new(RefCell::new(rename::load()));
+    let listbox = ListBox::new();
+    listbox.set_selection_mode(SelectionMode::Single);
+    fill_rule_list(&listbox, &rules.borrow(), None);
+    let list_scroll = ScrolledWindow::builder()
+        .child(&listbox)
+        .min_content_height(120)
+        .vexpand(true)
+        .build();
+    vbox.append(&list_scroll);
+
+    // Reorder and remove the selected rule.
+    let edit_row = GtkBox::new(Orientation::Horizontal, 8);
+    let btn_up = Button::with_label("Move Up");
+    let btn_down = Button::with_label("Move Down");
+    let btn_remove = Button::with_label("Remove");
+    edit_row.append(&btn_up);
+    edit_row.append(&btn_down);
+    edit_row.append(&btn_remove);
+    vbox.append(&edit_row);
+    for (button, step) in [(&btn_up, -1isize), (&btn_down, 1)] {
+        let listbox = listbox.clone();
+        let rules = rules.clone();
+        button.connect_clicked(move |_| {
+            let Some(i) = listbox.selected_row().map(|r| r.index() as usize) else { return };
+            let mut list = rules.borrow_mut();
+            let Some(j) = i.checked_add_signed(step).filter(|j| *j < list.len()) else { return };
+            list.swap(i, j);
+            fill_rule_list(&listbox, &list, Some(j));
+        });
+    }
+    {
+        let listbox = listbox.clone();
+        let rules = rules.clone();
+        btn_remove.connect_clicked(move |_| {
+            let Some(i) = listbox.selected_row().map(|r| r.index() as usize) else { return };
+            let mut list = rules.borrow_mut();
+            list.remove(i);
+            let next = (!list.is_empty()).then(|| i.min(list.len() - 1));
+            fill_rule_list(&listbox, &list, next);
+        });
+    }
+
+    // Add a rule written as `kind/argument/…`.
+    let add_row = GtkBox::new(Orientation::Horizontal, 8);
+    let entry = Entry::new();
+    entry.set_placeholder_text(Some("e.g. replace-chars/#&/_"));
+    entry.set_hexpand(true);
+    let btn_add = Button::with_label("Add");
+    add_row.append(&entry);
+    add_row.append(&btn_add);
+    vbox.append(&add_row);
+    let error = Label::new(None);
+    error.add_css_class("error");
+    error.set_halign(Align::Start);
+    error.set_wrap(true);
+    error.set_visible(false);
+    vbox.append(&error);
+    let help = Label::new(Some(
+        "replace-chars/#&/_ · case-fold-extension · regex/^IMG_(\\d+)/photo-$1 · date-prefix/_ (modification date)",
+    ));
+    help.add_css_class("dim-label");
+    help.set_halign(Align::Start);
+    help.set_wrap(true);
+    vbox.append(&help);
+    {
+        let listbox = listbox.clone();
+        let rules = rules.clone();
+        let entry = entry.clone();
+        let error = error.clone();
+        btn_add.connect_clicked(move |_| {
+            if entry.text().trim().is_empty() {
+                return;
+            }
+            match Rule::parse(entry.text().trim()) {
+                Ok(rule) => {
+                    let mut list = rules.borrow_mut();
+                    list.push(rule);
+                    fill_rule_list(&listbox, &list, Some(list.len() - 1));
+                    entry.set_text("");
+                    error.set_visible(false);
+                }
+                Err(e) => {
+                    error.set_text(&format!("{}.", e));
+                    error.set_visible(true);
+                }
+            }
+        });
+    }
+    {
+        let btn_add = btn_add.clone();
+        entry.connect_activate(move |_| btn_add.emit_clicked());
+    }
+
+    // Preview what the rules would rename.
+    let btn_preview = Button::with_label("Preview");
+    btn_preview.set_halign(Align::Start);
+    vbox.append(&btn_preview);
+    let preview_view = TextView::new();
+    preview_view.set_editable(false);
+    preview_view.set_monospace(true);
+    let preview_scroll = ScrolledWindow::builder()
+        .child(&preview_view)
+        .min_content_height(120)
+        .vexpand(true)
+        .build();
+    vbox.append(&preview_scroll);
+    {
+        let rules = rules.clone();
+        let preview_view = preview_view.clone();
+        btn_preview.connect_clicked(move |_| {
+            let pipeline: Vec<Rule> = strip_spaces.then(Rule::strip_spaces).into_iter().chain(rules.borrow().iter().cloned()).collect();
+            // Listing a remote source would block the window on ssh.
+            let text = match &source {
+                SourceSelection::None => "Choose a source to preview its files.".to_string(),
+                SourceSelection::Remote(..) | SourceSelection::RemoteFiles(..) | SourceSelection::RemoteItems(..) => {
+                    "Preview is only available for local sources; use --cli --rename-preview for remote ones.".to_string()
+                }
+                _ => match rename_preview(&source, &patterns, links, &Renamer::new(pipeline)) {
+                    Ok((renamed, total)) => {
+                        let mut lines: Vec<String> = renamed
+                            .iter()
+                            .take(RENAME_PREVIEW_LIMIT)
+                            .map(|(before, after)| format!("{} → {}", before, after))
+                            .collect();
+                        if renamed.len() > RENAME_PREVIEW_LIMIT {
+                            lines.push(format!("…and {} more", renamed.len() - RENAME_PREVIEW_LIMIT));
+                        }
+                        lines.push(format!("{} of {} file(s) would be renamed.", renamed.len(), total));
+                        lines.join("\n")
+                    }
+                    Err(e) => e,
+                },
+            };
+            preview_view.buffer().set_text(&text);
+        });
+    }
+
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    btn_row.set_halign(Align::End);
+
+    let btn_cancel = Button::with_label("Cancel");
+    let dialog_ref = dialog.clone();
+    btn_cancel.connect_clicked(move |_| {
+        dialog_ref.close();
+    });
+    btn_row.append(&btn_cancel);
+
+    let btn_save = Button::with_label("Save");
+    btn_save.add_css_class("suggested-action");
+    let dialog_ref = dialog.clone();
+    btn_save.connect_clicked(move |_| {
+        let list = rules.borrow();
+        match rename::save(&list) {
+            Ok(()) => {
+                on_saved(list.len());
+                dialog_ref.close();
+            }
+            Err(e) => {
+                error.set_text(&format!("Could not save the rules: {}.", e));
+                error.set_visible(true);
+            }
+        }
+    });
+    btn_row.append(&btn_save);
+    vbox.append(&btn_row);
+
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+}
+
+/// Show `rules` in `listbox`, one row each, selecting row `selected`.
+fn fill_rule_list(listbox: &ListBox, rules: &[Rule], selected: Option<usize>) {
+    while let Some(child) = listbox.first_child() {
+        listbox.remove(&child);
+    }
+    for rule in rules {
+        let label = Label::new(Some(&rule.to_string()));
+        label.set_halign(Align::Start);
+        label.set_margin_top(4);
+        label.set_margin_bottom(4);
+        label.set_margin_start(8);
+        listbox.append(&label);
+    }
+    if let Some(i) = selected {
+        listbox.select_row(listbox.row_at_index(i as i32).as_ref());
+    }
+}
+
+// ── Helper: duplicate runs ─────────────────────────────────────────────
+
+/// What `history` fingerprints to recognise a run started twice.
+fn transfer_options(
+    source_sel: &SourceSelection,
+    dst: &str,
+    do_move: bool,
+    move_strategy: MoveStrategy,
+    conflict_mode: ConflictMode,
+    renamer: &Renamer,
+    transfer_mode: TransferMode,
+    keep_parents: usize,
+    method_choice: MethodChoice,
+    patterns: &[String],
+) -> TransferOptions {
+    let sources = match source_sel {
+        SourceSelection::None => Vec::new(),
+        SourceSelection::Directory(d) => vec![shown(d)],
+        SourceSelection::DirectoryItems(d, items) => std::iter::once(d).chain(items).map(shown).collect(),
+        SourceSelection::Files(files) => files.iter().map(shown).collect(),
+        SourceSelection::Remote(host, path) => vec![format!("{}:{}", host, path)],
+        SourceSelection::RemoteFiles(host, files) => files.iter().map(|f| format!("{}:{}", host, shown(f))).collect(),
+        SourceSelection::RemoteItems(host, path, files) => std::iter::once(format!("{}:{}", host, path))
+            .chain(files.iter().map(|f| format!("{}:{}", host, shown(f))))
+            .collect(),
+    };
+    TransferOptions {
+        sources,
+        destination: dst.to_string(),
+        do_move,
+        move_strategy: match move_strategy {
+            MoveStrategy::PerFile => "per-file",
+            MoveStrategy::AllThenDelete => "all-then-delete",
+        },
+        conflict: match conflict_mode {
+            ConflictMode::Skip => "skip",
+            ConflictMode::Overwrite => "overwrite",
+            ConflictMode::Rename => "rename",
+            ConflictMode::Newer => "newer",
+        },
+        mode: match transfer_mode {
+            TransferMode::FilesOnly => "files",
+            TransferMode::FilesWithParent => "parent",
+            TransferMode::FoldersAndFiles => "folders",
+        },
+        method: method_choice.as_str(),
+        rename_rules: renamer.rules().iter().map(|r| r.to_string()).collect(),
+        file_name: renamer.target().map(String::from),
+        keep_parents,
+        patterns: patterns.to_vec(),
+    }
+}
+
+/// e.g. "You completed an identical transfer at 18:42 UTC (12,040 files
+/// copied)", with the weekday added when it was not today, and without
+/// "UTC" under `--timestamps local`.
+fn describe_duplicate(previous: &history::Record) -> String {
+    let at = timeutil::civil(Timestamp { secs: previous.finished as i64, nanos: 0 });
+    let day = if at.date == timeutil::civil(Timestamp::now()).date { String::new() } else { format!("{} ", at.weekday()) };
+    let zone = if timeutil::timestamps() == Timestamps::Utc { " UTC" } else { "" };
+    format!(
+        "You completed an identical transfer at {}{:02}:{:02}{} ({} {} copied)",
+        day,
+        at.hour,
+        at.minute,
+        zone,
+        coalesce::group_thousands(previous.copied),
+        if previous.copied == 1 { "file" } else { "files" }
+    )
+}
+
+// ── Helper: read-only source prompt ───────────────────────────────────
+
+/// Ask whether a move from a read-only source should go ahead as a copy.
+/// `on_copy` runs only if the user agrees.
+fn confirm_copy_instead(parent: &ApplicationWindow, on_copy: impl Fn() + 'static) {
+    let dialog = Window::builder()
+        .title("Source is read-only")
+        .modal(true)
+        .transient_for(parent)
+        .default_width(420)
+        .resizable(false)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let label = Label::new(Some(
+        "Source is read-only — files can be copied but not removed afterwards.\n\nContinue as Copy instead?",
+    ));
+    label.set_halign(Align::Start);
+    label.set_wrap(true);
+    vbox.append(&label);
+
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    btn_row.set_halign(Align::End);
+
+    let btn_cancel = Button::with_label("Cancel");
+    let dialog_ref = dialog.clone();
+    btn_cancel.connect_clicked(move |_| {
+        dialog_ref.close();
+    });
+    btn_row.append(&btn_cancel);
+
+    let btn_copy = Button::with_label("Copy Instead");
+    btn_copy.add_css_class("suggested-action");
+    let dialog_ref = dialog.clone();
+    btn_copy.connect_clicked(move |_| {
+        dialog_ref.close();
+        on_copy();
+    });
+    btn_row.append(&btn_copy);
+    vbox.append(&btn_row);
+
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+}
+
+// ── Helper: unwritable destination prompt ─────────────────────────────
+
+/// Unwritable folders listed in the prompt before the rest are counted.
+const UNWRITABLE_SHOWN: usize = 10;
+
+/// Ask what to do about destination folders that cannot be written to.
+/// `on_choice` runs once; closing the dialog counts as Abort.
+fn ask_on_unwritable(parent: &ApplicationWindow, dirs: &[UnwritableDir], on_choice: impl Fn(OnUnwritable) + 'static) {
+    let dialog = Window::builder()
+        .title("Destination folders not writable")
+        .modal(true)
+        .transient_for(parent)
+        .default_width(480)
+        .resizable(false)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let files: usize = dirs.iter().map(|d| d.files).sum();
+    let mut text = format!(
+        "{} file(s) would be copied into folders you cannot write to:\n",
+        files
+    );
+    for d in dirs.iter().take(UNWRITABLE_SHOWN) {
+        text.push_str(&format!("\n{}", d.describe()));
+    }
+    if dirs.len() > UNWRITABLE_SHOWN {
+        text.push_str(&format!("\n…and {} more", dirs.len() - UNWRITABLE_SHOWN));
+    }
+    text.push_str("\n\nNothing has been copied yet.");
+    let label = Label::new(Some(&text));
+    label.set_halign(Align::Start);
+    label.set_wrap(true);
+    label.set_selectable(true);
+    vbox.append(&label);
+
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    btn_row.set_halign(Align::End);
+
+    let on_choice = Rc::new(on_choice);
+    let answered = Rc::new(Cell::new(false));
+    let answer = move |choice: OnUnwritable| {
+        if !answered.replace(true) {
+            on_choice(choice);
+        }
+    };
+
+    for (name, choice, tooltip) in [
+        ("Abort", OnUnwritable::Abort, "Copy nothing"),
+        ("Skip These Folders", OnUnwritable::Skip, "Copy everything else and list these files as skipped"),
+        ("Continue Anyway", OnUnwritable::Continue, "Copy everything; these files will fail one by one"),
+    ] {
+        let btn = Button::with_label(name);
+        btn.set_tooltip_text(Some(tooltip));
+        if choice == OnUnwritable::Skip {
+            btn.add_css_class("suggested-action");
+        }
+        let dialog_ref = dialog.clone();
+        let answer = answer.clone();
+        btn.connect_clicked(move |_| {
+            answer(choice);
+            dialog_ref.close();
+        });
+        btn_row.append(&btn);
+    }
+    vbox.append(&btn_row);
+
+    dialog.connect_close_request(move |_| {
+        answer(OnUnwritable::Abort);
+        glib::Propagation::Proceed
+    });
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+}
+
+// ── Helper: stale leftovers prompt ────────────────────────────────────
+
+/// Leftovers listed in the prompt before the rest are counted.
+const STALE_SHOWN: usize = 10;
+
+/// Ask what to do about leftovers of an earlier run found at `at`.
+/// `on_choice` runs once; closing the dialog counts as Cancel (`None`).
+fn ask_on_stale(parent: &ApplicationWindow, found: &stale::Found, at: &str, on_choice: impl Fn(Option<stale::OnStale>) + 'static) {
+    let dialog = Window::builder()
+        .title("Leftovers of an earlier run")
+        .modal(true)
+        .transient_for(parent)
+        .default_width(480)
+        .resizable(false)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let mut text = format!("An earlier run left {} at {}:\n", found.describe(), at);
+    let paths: Vec<&PathBuf> = found.paths().collect();
+    for path in paths.iter().take(STALE_SHOWN) {
+        text.push_str(&format!("\n{}", shown(path)));
+    }
+    if paths.len() > STALE_SHOWN {
+        text.push_str(&format!("\n…and {} more", paths.len() - STALE_SHOWN));
+    }
+    text.push_str("\n\nNothing has been copied yet.");
+    let label = Label::new(Some(&text));
+    label.set_halign(Align::Start);
+    label.set_wrap(true);
+    label.set_selectable(true);
+    vbox.append(&label);
+
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    btn_row.set_halign(Align::End);
+
+    let on_choice = Rc::new(on_choice);
+    let answered = Rc::new(Cell::new(false));
+    let answer = move |choice: Option<stale::OnStale>| {
+        if !answered.replace(true) {
+            on_choice(choice);
+        }
+    };
+
+    let mut buttons = vec![("Cancel", None, "Copy nothing")];
+    if found.can_resume() {
+        buttons.push(("Resume", Some(stale::OnStale::Resume), "Continue each partial copy where it stopped"));
+    }
+    buttons.push(("Ignore", Some(stale::OnStale::Ignore), "Leave them; partial copies are started over"));
+    buttons.push(("Clean Up", Some(stale::OnStale::Clean), "Delete them, then copy"));
+    for (name, choice, tooltip) in buttons {
+        let btn = Button::with_label(name);
+        btn.set_tooltip_text(Some(tooltip));
+        if choice == Some(stale::OnStale::Clean) {
+            btn.add_css_class("suggested-action");
+        }
+        let dialog_ref = dialog.clone();
+        let answer = answer.clone();
+        btn.connect_clicked(move |_| {
+            answer(choice);
+            dialog_ref.close();
+        });
+        btn_row.append(&btn);
+    }
+    vbox.append(&btn_row);
+
+    dialog.connect_close_request(move |_| {
+        answer(None);
+        glib::Propagation::Proceed
+    });
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+}
+
+// ── Helper: destination quota prompt ──────────────────────────────────
+
+/// Say the destination quota is full and ask whether to retry once space
+/// has been freed, skip the remaining files or cancel.  `on_choice` runs
+/// once; closing the dialog counts as Cancel.
+fn ask_on_quota(parent: &ApplicationWindow, message: &str, on_choice: impl Fn(quota::OnQuota) + 'static) {
+    let dialog = Window::builder()
+        .title("Destination quota exceeded")
+        .modal(true)
+        .transient_for(parent)
+        .default_width(480)
+        .resizable(false)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let text = format!(
+        "Destination quota exceeded — the destination may have free space, but your quota on it is full.\n\n{}\n\n\
+         The transfer is paused. Free some space and retry, skip the remaining files, or cancel.",
+        message
+    );
+    let label = Label::new(Some(&text));
+    label.set_halign(Align::Start);
+    label.set_wrap(true);
+    label.set_selectable(true);
+    vbox.append(&label);
+
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    btn_row.set_halign(Align::End);
+
+    let on_choice = Rc::new(on_choice);
+    let answered = Rc::new(Cell::new(false));
+    let answer = move |choice: quota::OnQuota| {
+        if !answered.replace(true) {
+            on_choice(choice);
+        }
+    };
+
+    for (name, choice, tooltip) in [
+        ("Cancel", quota::OnQuota::Cancel, "Stop the transfer"),
+        ("Skip Remaining", quota::OnQuota::Skip, "Transfer nothing more and list the remaining files as skipped"),
+        ("Retry", quota::OnQuota::Retry, "Carry on after freeing space; files that already failed stay in the error list"),
+    ] {
+        let btn = Button::with_label(name);
+        btn.set_tooltip_text(Some(tooltip));
+        if choice == quota::OnQuota::Retry {
+            btn.add_css_class("suggested-action");
+        }
+        let dialog_ref = dialog.clone();
+        let answer = answer.clone();
+        btn.connect_clicked(move |_| {
+            answer(choice);
+            dialog_ref.close();
+        });
+        btn_row.append(&btn);
+    }
+    vbox.append(&btn_row);
+
+    dialog.connect_close_request(move |_| {
+        answer(quota::OnQuota::Cancel);
+        glib::Propagation::Proceed
+    });
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+}
+
+// ── Helper: scan limit prompt ─────────────────────────────────────────
+
+/// Say how far a long scan has got and ask whether to keep going.
+/// `on_choice` runs once; closing the dialog counts as Stop.
+fn ask_scan_limit(parent: &ApplicationWindow, trip: &scanguard::Trip, on_choice: impl Fn(bool) + 'static) {
+    let dialog = Window::builder()
+        .title("Scan is taking a long time")
+        .modal(true)
+        .transient_for(parent)
+        .default_width(480)
+        .resizable(false)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let text = format!(
+        "So far {}.\n\nIf the source is not the folder you meant, or contains a mount that loops back on itself, stop now. Nothing has been copied yet.",
+        trip.describe()
+    );
+    let label = Label::new(Some(&text));
+    label.set_halign(Align::Start);
+    label.set_wrap(true);
+    label.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+    label.set_selectable(true);
+    vbox.append(&label);
+
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    btn_row.set_halign(Align::End);
+
+    let on_choice = Rc::new(on_choice);
+    let answered = Rc::new(Cell::new(false));
+    let answer = move |go_on: bool| {
+        if !answered.replace(true) {
+            on_choice(go_on);
+        }
+    };
+
+    for (name, go_on) in [("Stop", false), ("Keep Scanning", true)] {
+        let btn = Button::with_label(name);
+        if !go_on {
+            btn.add_css_class("suggested-action");
+        }
+        let dialog_ref = dialog.clone();
+        let answer = answer.clone();
+        btn.connect_clicked(move |_| {
+            answer(go_on);
+            dialog_ref.close();
+        });
+        btn_row.append(&btn);
+    }
+    vbox.append(&btn_row);
+
+    dialog.connect_close_request(move |_| {
+        answer(false);
+        glib::Propagation::Proceed
+    });
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+}
+
+// ── Helper: unknown host key prompt ───────────────────────────────────
+
+/// Show the fingerprints of a host ssh doesn't know yet and ask whether to
+/// trust it.  `on_choice` runs once; closing the dialog counts as Cancel.
+fn ask_host_key(parent: &ApplicationWindow, host: &str, keys: &[ssh::HostKey], on_choice: impl Fn(bool) + 'static) {
+    let dialog = Window::builder()
+        .title("Unknown host")
+        .modal(true)
+        .transient_for(parent)
+        .default_width(520)
+        .resizable(false)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let mut text = format!(
+        "'{}' has not been connected to before, so its identity cannot be confirmed. \
+         It offers these keys:\n",
+        host
+    );
+    for key in keys {
+        text.push_str(&format!("\n{}", key.fingerprint));
+    }
+    text.push_str(
+        "\n\nOnly continue if they match the fingerprints the host's owner gave you. \
+         Accepting adds them to your known_hosts file.",
+    );
+    let label = Label::new(Some(&text));
+    label.set_halign(Align::Start);
+    label.set_wrap(true);
+    label.set_selectable(true);
+    vbox.append(&label);
+
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    btn_row.set_halign(Align::End);
+
+    let answered = Rc::new(Cell::new(false));
+    let answer = Rc::new(move |accepted: bool| {
+        if !answered.replace(true) {
+            on_choice(accepted);
+        }
+    });
+
+    for (name, accepted) in [("Cancel", false), ("Accept and Continue", true)] {
+        let btn = Button::with_label(name);
+        if accepted {
+            btn.add_css_class("suggested-action");
+        }
+        let dialog_ref = dialog.clone();
+        let answer = answer.clone();
+        btn.connect_clicked(move |_| {
+            answer(accepted);
+            dialog_ref.close();
+        });
+        btn_row.append(&btn);
+    }
+    vbox.append(&btn_row);
+
+    dialog.connect_close_request(move |_| {
+        answer(false);
+        glib::Propagation::Proceed
+    });
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+}
+
+/// The known_hosts file ssh reads for `host`.
+fn known_hosts_file(host: &str) -> Option<PathBuf> {
+    ssh_target(host)
+        .and_then(|t| t.known_hosts)
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".ssh/known_hosts")))
+}
+
+// ── Helper: per-item source picker ─────────────────────────────────────
+
+/// A list model of tree node ids.
+fn node_id_model(ids: &[usize]) -> gtk4::gio::ListStore {
+    let store = gtk4::gio::ListStore::new::<glib::BoxedAnyObject>();
+    let items: Vec<glib::BoxedAnyObject> = ids.iter().map(|&id| glib::BoxedAnyObject::new(id)).collect();
+    store.extend_from_slice(&items);
+    store
+}
+
+fn node_id(obj: Option<glib::Object>) -> Option<usize> {
+    obj.and_downcast::<glib::BoxedAnyObject>().map(|o| *o.borrow::<usize>())
+}
+
+/// Show a check state on a checkbox; mixed directories show the
+/// inconsistent mark and tick their whole subtree when clicked.
+fn show_check(check: &CheckButton, state: Check) {
+    check.set_active(state == Check::Checked);
+    check.set_inconsistent(state == Check::Mixed);
+}
+
+/// Show the scanned source folder as a tree with a checkbox per directory
+/// and file.  "Use Selection" passes the ticked files to `on_apply`; "Add
+/// Unchecked as Exclusions" passes patterns for the unticked items to
+/// `on_exclude`.
+///
+/// A directory's children are only turned into a list model when its row
+/// is expanded, so very large trees open immediately.
+fn show_item_picker(
+    parent: &ApplicationWindow,
+    tree: ItemTree,
+    on_apply: impl Fn(Vec<PathBuf>) + 'static,
+    on_exclude: impl Fn(Vec<String>) + 'static,
+) {
+    let tree = Rc::new(RefCell::new(tree));
+    let dialog = Window::builder()
+        .title("Select items")
+        .modal(true)
+        .transient_for(parent)
+        .default_width(520)
+        .default_height(480)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let summary = Label::new(None);
+    summary.set_halign(Align::Start);
+    vbox.append(&summary);
+
+    let tree_model = {
+        let tree = tree.clone();
+        gtk4::TreeListModel::new(node_id_model(&[ItemTree::ROOT]), false, false, move |obj| {
+            let id = node_id(Some(obj.clone()))?;
+            let tree = tree.borrow();
+            let node = tree.node(id);
+            node.is_dir.then(|| node_id_model(&node.children).upcast::<gtk4::gio::ListModel>())
+        })
+    };
+
+    // Checkboxes of the rows currently on screen, by node id.
+    let bound: Rc<RefCell<HashMap<CheckButton, usize>>> = Rc::new(RefCell::new(HashMap::new()));
+    // Set while checkboxes are updated from the tree, so `toggled` ignores it.
+    let updating = Rc::new(Cell::new(false));
+
+    let refresh = {
+        let tree = tree.clone();
+        let bound = bound.clone();
+        let updating = updating.clone();
+        let summary = summary.clone();
+        Rc::new(move || {
+            let tree = tree.borrow();
+            updating.set(true);
+            for (check, &id) in bound.borrow().iter() {
+                show_check(check, tree.state(id));
+            }
+            updating.set(false);
+            let (files, ticked) = tree.counts();
+            summary.set_text(&format!("{} of {} files selected", ticked, files));
+        })
+    };
+    refresh();
+
+    let factory = gtk4::SignalListItemFactory::new();
+    factory.connect_setup({
+        let tree = tree.clone();
+        let bound = bound.clone();
+        let updating = updating.clone();
+        let refresh = refresh.clone();
+        move |_, item| {
+            let Some(item) = item.downcast_ref::<gtk4::ListItem>() else { return };
+            let check = CheckButton::new();
+            let label = Label::new(None);
+            label.set_halign(Align::Start);
+            label.set_ellipsize(EllipsizeMode::Middle);
+            let row = GtkBox::new(Orientation::Horizontal, 6);
+            row.append(&check);
+            row.append(&label);
+            let expander = gtk4::TreeExpander::new();
+            expander.set_child(Some(&row));
+            item.set_child(Some(&expander));
+
+            let tree = tree.clone();
+            let bound = bound.clone();
+            let updating = updating.clone();
+            let refresh = refresh.clone();
+            check.connect_toggled(move |check| {
+                if updating.get() {
+                    return;
+                }
+                let Some(&id) = bound.borrow().get(check) else { return };
+                tree.borrow_mut().set_checked(id, check.is_active());
+                refresh();
+            });
+        }
+    });
+    factory.connect_bind({
+        let tree = tree.clone();
+        let bound = bound.clone();
+        let updating = updating.clone();
+        move |_, item| {
+            let Some(item) = item.downcast_ref::<gtk4::ListItem>() else { return };
+            let Some(row) = item.item().and_downcast::<gtk4::TreeListRow>() else { return };
+            let Some(id) = node_id(row.item()) else { return };
+            let Some(expander) = item.child().and_downcast::<gtk4::TreeExpander>() else { return };
+            expander.set_list_row(Some(&row));
+            let Some(row_box) = expander.child() else { return };
+            let Some(check) = row_box.first_child().and_downcast::<CheckButton>() else { return };
+            let Some(label) = check.next_sibling().and_downcast::<Label>() else { return };
+
+            let tree = tree.borrow();
+            let node = tree.node(id);
+            if node.is_dir {
+                label.set_text(&format!("{}/", node.name));
+            } else {
+                label.set_text(&node.name);
+            }
+            bound.borrow_mut().insert(check.clone(), id);
+            updating.set(true);
+            show_check(&check, tree.state(id));
+            updating.set(false);
+        }
+    });
+    factory.connect_unbind({
+        let bound = bound.clone();
+        move |_, item| {
+            let Some(item) = item.downcast_ref::<gtk4::ListItem>() else { return };
+            let Some(expander) = item.child().and_downcast::<gtk4::TreeExpander>() else { return };
+            if let Some(check) = expander.child().and_then(|b| b.first_child()).and_downcast::<CheckButton>() {
+                bound.borrow_mut().remove(&check);
+            }
+            expander.set_list_row(None);
+        }
+    });
+
+    let list_view = gtk4::ListView::new(Some(gtk4::NoSelection::new(Some(tree_model.clone()))), Some(factory));
+    if let Some(root_row) = tree_model.row(0) {
+        root_row.set_expanded(true);
+    }
+    let scroll = ScrolledWindow::builder()
+        .child(&list_view)
+        .hscrollbar_policy(PolicyType::Never)
+        .vexpand(true)
+        .build();
+    vbox.append(&scroll);
+
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+
+    let btn_exclude = Button::with_label("Add Unchecked as Exclusions");
+    btn_exclude.set_tooltip_text(Some(
+        "Save the unticked folders and files as exclusion patterns instead of a one-off selection",
+    ));
+    btn_exclude.set_hexpand(true);
+    btn_exclude.set_halign(Align::Start);
+    let dialog_ref = dialog.clone();
+    let tree_c = tree.clone();
+    btn_exclude.connect_clicked(move |_| {
+        on_exclude(tree_c.borrow().exclusion_patterns());
+        dialog_ref.close();
+    });
+    btn_row.append(&btn_exclude);
+
+    let btn_cancel = Button::with_label("Cancel");
+    let dialog_ref = dialog.clone();
+    btn_cancel.connect_clicked(move |_| {
+        dialog_ref.close();
+    });
+    btn_row.append(&btn_cancel);
+
+    let btn_apply = Button::with_label("Use Selection");
+    btn_apply.add_css_class("suggested-action");
+    let dialog_ref = dialog.clone();
+    let summary_c = summary.clone();
+    btn_apply.connect_clicked(move |_| {
+        let files = tree.borrow().selected_files();
+        if files.is_empty() {
+            summary_c.set_text("Nothing is selected.");
+            return;
+        }
+        on_apply(files);
+        dialog_ref.close();
+    });
+    btn_row.append(&btn_apply);
+    vbox.append(&btn_row);
+
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+}
+
+// ── SSH password prompt (askpass helper) ───────────────────────────────
+
+/// Show ssh's `prompt` in a small password dialog and print the answer to
+/// stdout.  Returns the exit status ssh expects: 0 with an answer, 1 when
+/// the prompt was cancelled.
+fn run_askpass(prompt: &str) -> i32 {
+    if gtk4::init().is_err() {
+        return 1;
+    }
+    let main_loop = glib::MainLoop::new(None, false);
+    let answer: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+
+    let dialog = Window::builder()
+        .title("Kosmokopy — SSH authentication")
+        .modal(true)
+        .default_width(420)
+        .resizable(false)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    let label = Label::new(Some(prompt.trim()));
+    label.set_halign(Align::Start);
+    label.set_wrap(true);
+    vbox.append(&label);
+
+    let entry = PasswordEntry::new();
+    entry.set_show_peek_icon(true);
+    vbox.append(&entry);
+
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    btn_row.set_halign(Align::End);
+
+    let btn_cancel = Button::with_label("Cancel");
+    let dialog_ref = dialog.clone();
+    btn_cancel.connect_clicked(move |_| {
+        dialog_ref.close();
+    });
+    btn_row.append(&btn_cancel);
+
+    let btn_ok = Button::with_label("OK");
+    btn_ok.add_css_class("suggested-action");
+    let submit = {
+        let dialog = dialog.clone();
+        let entry = entry.clone();
+        let answer = answer.clone();
+        move || {
+            *answer.borrow_mut() = Some(entry.text().to_string());
+            dialog.close();
+        }
+    };
+    btn_ok.connect_clicked({
+        let submit = submit.clone();
+        move |_| submit()
+    });
+    entry.connect_activate(move |_| submit());
+    btn_row.append(&btn_ok);
+    vbox.append(&btn_row);
+
+    dialog.connect_close_request({
+        let main_loop = main_loop.clone();
+        move |_| {
+            main_loop.quit();
+            glib::Propagation::Proceed
+        }
+    });
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+    entry.grab_focus();
+    main_loop.run();
+
+    let answer = answer.borrow_mut().take();
+    match answer {
+        Some(text) => {
+            println!("{}", text);
+            0
+        }
+        None => 1,
+    }
+}
+
+// ── Helper: directory chooser row (editable) ──────────────────────────
+
+fn dir_row_editable(label_text: &str) -> (GtkBox, Button, Entry) {
+    let row = GtkBox::new(Orientation::Horizontal, 8);
+    let label = Label::new(Some(label_text));
+    label.set_width_chars(20);
+    label.set_xalign(0.0);
+
+    let entry = Entry::new();
+    entry.set_hexpand(true);
+    entry.set_placeholder_text(Some("Local path or host:/remote/path"));
+
+    let btn = Button::with_label("Browse…");
+
+    row.append(&label);
+    row.append(&entry);
+    row.append(&btn);
+
+    (row, btn, entry)
+}
+
+// ── Helper: result dialog with scrollable error list ───────────────────
+
+/// The result dialog's offer to verify the run again.
+enum VerifyNow {
+    /// Not offered: nothing was copied, or the run didn't finish.
+    Hidden,
+    /// Shown greyed out, with the reason as its tooltip.
+    Disabled(&'static str),
+    /// Offered for the run's copies, hashed with the algorithm it chose.
+    Offer(Vec<reverify::Copied>, HashAlgo),
+}
+
+/// The result dialog's list: skipped files grouped by what kept them
+/// from being sent, then the unverified ones, then the errors.
+fn result_notes(report: &TransferReport, unverified: &[String]) -> Vec<String> {
+    let mut notes = Vec::new();
+    // A cut-short list says how many there were and where the rest went.
+    if report.skipped.is_truncated() {
+        notes.push(report.skipped.heading("Skipped"));
+    }
+    // Counted in full, though only the first entries are kept.
+    let tally = report.skipped.tally();
+    let counts = [tally.identical, tally.conflicts, tally.skipped];
+    for ((label, entries), count) in report.skipped_by_outcome().into_iter().zip(counts) {
+        if !entries.is_empty() {
+            notes.push(format!("{} ({}):", label, count));
+            notes.extend(entries.into_iter().map(str::to_string));
+        }
+    }
+    if !unverified.is_empty() {
+        notes.push(format!("Unverified ({}):", unverified.len()));
+        notes.extend(unverified.iter().cloned());
+    }
+    if !report.errors.is_empty() {
+        notes.push(report.errors.heading("Errors"));
+        notes.extend(coalesce::coalesce(report.errors.shown(), DEFAULT_COALESCE_AFTER).into_iter().map(|e| e.message));
+    }
+    notes
+}
+
+/// What the result dialog can do with the finished run.
+struct Followups {
+    verify_now: VerifyNow,
+    /// The run's per-file records, for "Save detailed report…".
+    report: filereport::Kept,
+    /// The external commands run, when they were asked for.
+    commands: Vec<String>,
+    /// Starts a run of just the failed files, for "Retry failed".
+    retry: Option<Box<dyn Fn()>>,
+}
+
+impl Followups {
+    fn none() -> Followups {
+        Followups { verify_now: VerifyNow::Hidden, report: filereport::Kept::default(), commands: Vec::new(), retry: None }
+    }
+}
+
+fn show_result_dialog(
+    parent: &ApplicationWindow,
+    title: &str,
+    summary_markup: &str,
+    groups: &[GroupStats],
+    errors: &[String],
+    failed: &[OsString],
+    followups: Followups,
+) {
+    let Followups { verify_now, report, commands, retry } = followups;
+    let dialog = Window::builder()
+        .title(title)
+        .modal(true)
+        .transient_for(parent)
+        .default_width(500)
+        .default_height(if errors.is_empty() && commands.is_empty() && groups.len() < 2 { 150 } else { 400 })
+        .resizable(true)
+        .accessible_role(gtk4::AccessibleRole::Dialog)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(16);
+    vbox.set_margin_bottom(16);
+    vbox.set_margin_start(16);
+    vbox.set_margin_end(16);
+
+    // Summary label (large, headline in bold)
+    let summary_label = Label::new(None);
+    summary_label.set_halign(Align::Start);
+    summary_label.set_wrap(true);
+    summary_label.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+    summary_label.set_markup(&format!("<big>{}</big>", summary_markup));
+    // Focusable, so a screen reader starts on the outcome rather than
+    // on whichever button GTK picks.
+    summary_label.set_selectable(true);
+    summary_label.set_focusable(true);
+    dialog.update_relation(&[Relation::DescribedBy(&[summary_label.upcast_ref()])]);
+    vbox.append(&summary_label);
+
+    // Per-folder table, when the source had more than one top-level folder
+    if groups.len() > 1 {
+        let grid = Grid::new();
+        grid.set_column_spacing(16);
+        grid.set_row_spacing(2);
+        let headings = ["Folder", "Copied", "Skipped", "Errors", "Size"];
+        for (col, heading) in headings.iter().enumerate() {
+            let label = Label::new(None);
+            label.set_markup(&format!("<b>{}</b>", heading));
+            label.set_halign(if col == 0 { Align::Start } else { Align::End });
+            grid.attach(&label, col as i32, 0, 1, 1);
+        }
+        for (row, g) in groups.iter().enumerate() {
+            let cells = [
+                ellipsize_middle(&g.name, MAX_NAME_LEN),
+                g.copied.to_string(),
+                g.skipped.to_string(),
+                g.errors.to_string(),
+                format_bytes(g.bytes),
+            ];
+            for (col, text) in cells.iter().enumerate() {
+                let label = Label::new(Some(text));
+                label.set_halign(if col == 0 { Align::Start } else { Align::End });
+                if col == 3 && g.errors > 0 {
+                    label.add_css_class("error");
+                }
+                grid.attach(&label, col as i32, row as i32 + 1, 1, 1);
+            }
+        }
+        let grid_scroll = ScrolledWindow::builder()
+            .child(&grid)
+            .hscrollbar_policy(PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(200)
+            .build();
+        vbox.append(&grid_scroll);
+    }
+
+    // Scrollable error list
+    if !errors.is_empty() {
+        let error_heading = Label::builder().accessible_role(gtk4::AccessibleRole::Heading).build();
+        error_heading.set_halign(Align::Start);
+        error_heading.set_markup(&format!("<b>{} error(s):</b>", errors.len()));
+        vbox.append(&error_heading);
+
+        let error_text = errors
+            .iter()
+            .enumerate()
+            .map(|(i, e)| format!("{}. {}", i + 1, e))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let error_view = TextView::new();
+        error_view.set_editable(false);
+        error_view.set_cursor_visible(false);
+        error_view.set_wrap_mode(WrapMode::WordChar);
+        error_view.set_monospace(true);
+        error_view.buffer().set_text(&error_text);
+        error_view.update_relation(&[Relation::LabelledBy(&[error_heading.upcast_ref()])]);
+
+        let scroll = ScrolledWindow::builder()
+            .child(&error_view)
+            .min_content_height(150)
+            .vexpand(true)
+            .build();
+        vbox.append(&scroll);
+    }
+
+    // The external commands run, as the CLI's --print-commands prints them
+    if !commands.is_empty() {
+        let commands_heading = Label::builder().accessible_role(gtk4::AccessibleRole::Heading).build();
+        commands_heading.set_halign(Align::Start);
+        commands_heading.set_markup(&format!("<b>{} command(s) run:</b>", commands.len()));
+        vbox.append(&commands_heading);
+
+        let commands_view = TextView::new();
+        commands_view.set_editable(false);
+        commands_view.set_cursor_visible(false);
+        commands_view.set_wrap_mode(WrapMode::WordChar);
+        commands_view.set_monospace(true);
+        commands_view.buffer().set_text(&commands.join("\n"));
+        commands_view.update_relation(&[Relation::LabelledBy(&[commands_heading.upcast_ref()])]);
+
+        let scroll = ScrolledWindow::builder()
+            .child(&commands_view)
+            .min_content_height(150)
+            .vexpand(true)
+            .build();
+        vbox.append(&scroll);
+    }
+
+    // Where "Verify now" shows its progress and outcome
+    let verify_area = GtkBox::new(Orientation::Vertical, 8);
+    vbox.append(&verify_area);
+
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    btn_row.set_halign(Align::End);
+
+    match verify_now {
+        VerifyNow::Hidden => {}
+        VerifyNow::Disabled(reason) => {
+            let btn_verify = Button::with_label("Verify now");
+            btn_verify.set_sensitive(false);
+            btn_verify.set_tooltip_text(Some(reason));
+            btn_row.append(&btn_verify);
+        }
+        VerifyNow::Offer(record, algo) => btn_row.append(&verify_now_button(&dialog, &verify_area, record, algo)),
+    }
+
+    // Retry failed button — a new run of just those files, same settings
+    if let Some(retry) = retry {
+        let btn_retry = Button::with_label("Retry failed");
+        btn_retry.set_tooltip_text(Some(&format!("Transfer the {} failed file(s) again, to the same places", failed.len())));
+        let dialog_ref = dialog.clone();
+        btn_retry.connect_clicked(move |_| {
+            dialog_ref.close();
+            retry();
+        });
+        btn_row.append(&btn_retry);
+    }
+
+    // Save failed list button — same format as the CLI's --failed-files-out
+    if !failed.is_empty() {
+        let btn_save = Button::with_label("Save failed list…");
+        let dialog_ref = dialog.clone();
+        let failed = failed.to_vec();
+        btn_save.connect_clicked(move |_| {
+            let file_dialog = FileDialog::builder()
+                .title("Save failed list")
+                .modal(true)
+                .initial_name(format!("failed.{}", FAILED_LIST_EXT))
+                .build();
+            let failed = failed.clone();
+            file_dialog.save(Some(&dialog_ref), gtk4::gio::Cancellable::NONE, move |result| {
+                if let Ok(file) = result {
+                    if let Some(path) = file.path() {
+                        if let Err(e) = write_failed_list(&path, &failed, false) {
+                            eprintln!("Could not write failed-files list {}: {}", path.display(), e);
+                        }
+                    }
+                }
+            });
+        });
+        btn_row.append(&btn_save);
+    }
+
+    // Save detailed report button — what the CLI's --detail-report streams
+    if !report.records.is_empty() {
+        let btn_report = Button::with_label("Save detailed report…");
+        btn_report.set_tooltip_text(Some("One row per file: copied, renamed, skipped, excluded or failed, and why"));
+        let dialog_ref = dialog.clone();
+        let report = Rc::new(report);
+        btn_report.connect_clicked(move |_| {
+            let file_dialog = FileDialog::builder()
+                .title("Save detailed report")
+                .modal(true)
+                .initial_name("report.csv")
+                .build();
+            let report = report.clone();
+            file_dialog.save(Some(&dialog_ref), gtk4::gio::Cancellable::NONE, move |result| {
+                if let Ok(file) = result {
+                    if let Some(path) = file.path() {
+                        if let Err(e) = report.write(&path) {
+                            eprintln!("Could not write detailed report {}: {}", path.display(), e);
+                        }
+                    }
+                }
+            });
+        });
+        btn_row.append(&btn_report);
+    }
+
+    // OK button
+    let btn_ok = Button::with_label("OK");
+    btn_ok.add_css_class("suggested-action");
+    let dialog_ref = dialog.clone();
+    btn_ok.connect_clicked(move |_| {
+        dialog_ref.close();
+    });
+    btn_row.append(&btn_ok);
+    vbox.append(&btn_row);
+
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+    summary_label.grab_focus();
+    summary_label.select_region(0, 0);
+}
+
+/// "Retry failed" for a run of `run` (its source and destination): hands
+/// the failed files to the Transfer button, which starts them with the
+/// window's settings, still those of the run behind the modal dialog.
+fn retry_followup(
+    pending: &Rc<RefCell<Option<(SourceSelection, String)>>>,
+    btn_start: &Button,
+    run: &(SourceSelection, String),
+    failed: &[OsString],
+) -> Option<Box<dyn Fn()>> {
+    let retry = retry_run(&run.0, &run.1, failed)?;
+    let pending = pending.clone();
+    let btn_start = btn_start.clone();
+    Some(Box::new(move || {
+        *pending.borrow_mut() = Some(retry.clone());
+        btn_start.emit_clicked();
+    }))
+}
+
+/// "Verify now": hash the run's copies again with `algo` in
+/// `run_reverify_worker`, showing its progress and then what it found in
+/// `area`.  Closing `dialog` stops it.
+fn verify_now_button(dialog: &Window, area: &GtkBox, record: Vec<reverify::Copied>, algo: HashAlgo) -> Button {
+    let btn = Button::with_label("Verify now");
+    btn.set_tooltip_text(Some("Hash the copied files again at both ends and compare them"));
+    let cancel_flag = Arc::new(CancelFlag::default());
+    {
+        let cancel_flag = cancel_flag.clone();
+        dialog.connect_close_request(move |_| {
+            cancel_flag.request(CancelIntent::Stop);
+            glib::Propagation::Proceed
+        });
+    }
+    let area = area.clone();
+    btn.connect_clicked(move |btn| {
+        btn.set_sensitive(false);
+        btn.set_label("Verifying…");
+        while let Some(child) = area.first_child() {
+            area.remove(&child);
+        }
+        let bar = ProgressBar::new();
+        bar.set_show_text(true);
+        area.append(&bar);
+
+        let (tx, rx) = mpsc::channel::<WorkerMsg>();
+        let record = record.clone();
+        let cancel_flag_w = cancel_flag.clone();
+        thread::spawn(move || run_reverify_worker(record, algo, cancel_flag_w, tx));
+
+        let area = area.clone();
+        let btn = btn.clone();
+        let mut model = ProgressModel::default();
+        glib::timeout_add_local(std::time::Duration::from_millis(50), move || {
+            while let Ok(msg) = rx.try_recv() {
+                match msg {
+                    WorkerMsg::Plan { sizes } => model = ProgressModel::new(sizes),
+                    WorkerMsg::Phase(phase) => {
+                        model.set_phase(phase);
+                        show_phase(&bar, phase);
+                    }
+                    WorkerMsg::Progress { done, total, file } => {
+                        model.file_done(done, total);
+                        bar.set_fraction(model.bar_fraction());
+                        bar.set_text(Some(&format!("{}/{} — {}", done, total, ellipsize_middle(&file, MAX_NAME_LEN))));
+                    }
+                    WorkerMsg::Reverified(outcome) => {
+                        area.remove(&bar);
+                        let label = Label::new(None);
+                        label.set_halign(Align::Start);
+                        label.set_wrap(true);
+                        label.set_markup(&format!("<b>{}</b>", glib::markup_escape_text(&outcome.describe())));
+                        if !outcome.is_clean() {
+                            label.add_css_class("error");
+                        }
+                        area.append(&label);
+                        let problems = outcome.problems();
+                        if !problems.is_empty() {
+                            let view = TextView::new();
+                            view.set_editable(false);
+                            view.set_cursor_visible(false);
+                            view.set_wrap_mode(WrapMode::WordChar);
+                            view.set_monospace(true);
+                            view.buffer().set_text(&problems.join("\n"));
+                            let scroll = ScrolledWindow::builder().child(&view).min_content_height(100).vexpand(true).build();
+                            area.append(&scroll);
+                        }
+                        btn.set_label("Verify again");
+                        btn.set_sensitive(true);
+                        return glib::ControlFlow::Break;
+                    }
+                    _ => {}
+                }
+            }
+            glib::ControlFlow::Continue
+        });
+    });
+    btn
+}
+
+// ── Remote file browser ────────────────────────────────────────────────
+
+/// Entry in a remote directory listing.
+#[derive(Clone)]
+struct RemoteEntry {
+    name: String,
+    is_dir: bool,
+}
+
+/// Resolve the SSH user's home directory on the remote host.
+fn resolve_remote_home(host: &str) -> Result<String, String> {
+    let ctl = ssh::ctl_args(host);
+    let out = Command::new("ssh")
+        .args(&ctl)
+        .arg(host)
+        .arg("echo $HOME")
+        .printed()
+        .output()
+        .map_err(|e| format!("SSH failed: {}", e))?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(ssh::known_error(host, &stderr)
+            .unwrap_or_else(|| "Could not resolve home directory".to_string()));
+    }
+    let home = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    if home.is_empty() {
+        Err("Home directory is empty".to_string())
+    } else {
+        Ok(home)
+    }
+}
+
+/// List the contents of a remote directory via SSH.
+/// Returns a sorted vec of `RemoteEntry` (directories first, then files).
+fn list_remote_dir(host: &str, path: &str) -> Result<Vec<RemoteEntry>, String> {
+    let ctl = ssh::ctl_args(host);
+    let clean_path = if path == "/" { "/".to_string() } else { path.trim_end_matches('/').to_string() };
+    let cmd = format!(
+        "command ls -1apL {} 2>/dev/null",
+        shell_quote(&clean_path),
+    );
+    let out = Command::new("ssh")
+        .args(&ctl)
+        .arg(host)
+        .arg(&cmd)
+        .printed()
+        .output()
+        .map_err(|e| format!("SSH failed: {}", e))?;
+
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(ssh::known_error(host, &stderr)
+            .unwrap_or_else(|| format!("Cannot list '{}': {}", path, stderr.trim())));
+    }
+
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+
+    for line in String::from_utf8_lossy(&out.stdout).lines() {
+        let line = line.trim();
+        if line.is_empty() || line == "./" {
+            continue;
+        }
+        if line == "../" {
+            continue; // Handled by the Up button
+        }
+        if line.ends_with('/') {
+            dirs.push(RemoteEntry {
+                name: line.trim_end_matches('/').to_string(),
+                is_dir: true,
+            });
+        } else {
+            files.push(RemoteEntry {
+                name: line.to_string(),
+                is_dir: false,
+            });
+        }
+    }
+
+    dirs.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    files.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    dirs.append(&mut files);
+    Ok(dirs)
+}
+
+/// Open a remote file browser dialog.
+/// When the user selects a path, `on_select` is called with `"host:/selected/path"`.
+fn show_remote_browser(
+    parent: &ApplicationWindow,
+    initial_text: &str,
+    select_dirs_only: bool,
+    on_select: impl Fn(String) + 'static,
+) {
+    // Parse any existing host:/path from the entry text
+    let (initial_host, initial_path) = if let Some(pos) = initial_text.find(':') {
+        let h = &initial_text[..pos];
+        let p = &initial_text[pos + 1..];
+        if !h.is_empty() && !h.contains('/') && !p.is_empty() {
+            (h.to_string(), p.to_string())
+        } else {
+            (String::new(), "~".to_string())
+        }
+    } else {
+        (String::new(), "~".to_string())
+    };
+
+    let dialog = Window::builder()
+        .title("Browse Remote Location")
+        .modal(true)
+        .transient_for(parent)
+        .default_width(520)
+        .default_height(480)
+        .resizable(true)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 8);
+    vbox.set_margin_top(12);
+    vbox.set_margin_bottom(12);
+    vbox.set_margin_start(12);
+    vbox.set_margin_end(12);
+
+    // ── Host row ──────────────────────────────────────────────────────
+    let host_row = GtkBox::new(Orientation::Horizontal, 6);
+    let host_label = Label::new(Some("Host:"));
+    host_label.set_width_chars(6);
+    host_label.set_xalign(0.0);
+    let host_entry = Entry::new();
+    host_entry.set_hexpand(true);
+    host_entry.set_placeholder_text(Some("user@hostname"));
+    host_entry.set_text(&initial_host);
+    let btn_connect = Button::with_label("Connect");
+    btn_connect.add_css_class("suggested-action");
+    host_row.append(&host_label);
+    host_row.append(&host_entry);
+    host_row.append(&btn_connect);
+    vbox.append(&host_row);
+
+    // ── Path row ──────────────────────────────────────────────────────
+    let path_row = GtkBox::new(Orientation::Horizontal, 6);
+    let path_label = Label::new(Some("Path:"));
+    path_label.set_width_chars(6);
+    path_label.set_xalign(0.0);
+    let path_entry = Entry::new();
+    path_entry.set_hexpand(true);
+    path_entry.set_text(&initial_path);
+    let btn_up = Button::with_label("↑ Up");
+    let btn_go = Button::with_label("Go");
+    path_row.append(&path_label);
+    path_row.append(&path_entry);
+    path_row.append(&btn_up);
+    path_row.append(&btn_go);
+    vbox.append(&path_row);
+
+    // ── Status label ──────────────────────────────────────────────────
+    let status = Label::new(Some("Enter a host and click Connect."));
+    status.set_halign(Align::Start);
+    status.add_css_class("dim-label");
+    vbox.append(&status);
+
+    // ── File list ─────────────────────────────────────────────────────
+    let listbox = ListBox::new();
+    listbox.set_selection_mode(SelectionMode::Single);
+    let scroll = ScrolledWindow::builder()
+        .hscrollbar_policy(PolicyType::Never)
+        .vscrollbar_policy(PolicyType::Automatic)
+        .vexpand(true)
+        .child(&listbox)
+        .build();
+    vbox.append(&scroll);
+
+    // ── Action buttons ────────────────────────────────────────────────
+    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
+    btn_row.set_halign(Align::End);
+    let btn_cancel = Button::with_label("Cancel");
+    let btn_select = Button::with_label("Select");
+    btn_select.add_css_class("suggested-action");
+    btn_select.set_sensitive(false);
+    btn_row.append(&btn_cancel);
+    btn_row.append(&btn_select);
+    vbox.append(&btn_row);
+
+    dialog.set_child(Some(&vbox));
+
+    // ── Shared state ──────────────────────────────────────────────────
+    // Entries is the listing for the current directory.
+    let entries: Rc<RefCell<Vec<RemoteEntry>>> = Rc::new(RefCell::new(Vec::new()));
+    // selected_path will hold the path the user ultimately wants.
+    let selected_path: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+
+    // ── Populate the listbox from a listing ───────────────────────────
+    let populate: Rc<dyn Fn(String, String)> = Rc::new({
+        let listbox = listbox.clone();
+        let entries = entries.clone();
+        let status = status.clone();
+        let btn_select = btn_select.clone();
+        let path_entry = path_entry.clone();
+        let selected_path = selected_path.clone();
+        move |host: String, path: String| {
+            // Clear existing rows
+            while let Some(row) = listbox.row_at_index(0) {
+                listbox.remove(&row);
+            }
+            entries.borrow_mut().clear();
+            btn_select.set_sensitive(false);
+            *selected_path.borrow_mut() = None;
+
+            status.set_text("Loading…");
+
+            // Spawn thread for SSH listing, poll result on main loop
+            // The channel now also returns the (possibly resolved) path so
+            // we can update the path bar when "~" is expanded.
+            let (tx, rx) = mpsc::channel::<(String, Result<Vec<RemoteEntry>, String>)>();
+            let host_c = host.clone();
+            let path_c = path.clone();
+            thread::spawn(move || {
+                // Resolve "~" or paths starting with "~/" to an absolute path.
+                let resolved = if path_c == "~" || path_c.starts_with("~/") {
+                    match resolve_remote_home(&host_c) {
+                        Ok(home) => {
+                            if path_c == "~" {
+                                home
+                            } else {
+                                format!("{}/{}", home, &path_c[2..])
+                            }
+                        }
+                        Err(e) => {
+                            let _ = tx.send((path_c, Err(e)));
+                            return;
+                        }
+                    }
+                } else {
+                    path_c
+                };
+                let result = list_remote_dir(&host_c, &resolved);
+                let _ = tx.send((resolved, result));
+            });
+
+            let listbox_c = listbox.clone();
+            let entries_c = entries.clone();
+            let status_c = status.clone();
+            let btn_select_c = btn_select.clone();
+            let path_entry_c = path_entry.clone();
+            let selected_path_c = selected_path.clone();
+            glib::timeout_add_local(std::time::Duration::from_millis(50), move || {
+                match rx.try_recv() {
+                    Ok((resolved_path, result)) => {
+                        // Update the path bar to the resolved absolute path
+                        path_entry_c.set_text(&resolved_path);
+                        match result {
+                            Ok(listing) => {
+                                let count = listing.len();
+                                for entry in &listing {
+                                    let row_box = GtkBox::new(Orientation::Horizontal, 8);
+                                    row_box.set_margin_top(2);
+                                    row_box.set_margin_bottom(2);
+                                    row_box.set_margin_start(6);
+                                    row_box.set_margin_end(6);
+
+                                    let icon = Label::new(Some(
+                                        if entry.is_dir { "📁" } else { "📄" },
+                                    ));
+                                    let name_label = Label::new(Some(&entry.name));
+                                    name_label.set_halign(Align::Start);
+                                    name_label.set_hexpand(true);
+                                    name_label.set_ellipsize(EllipsizeMode::Middle);
+
+                                    if entry.is_dir {
+                                        name_label.set_markup(&format!(
+                                            "<b>{}</b>",
+                                            glib::markup_escape_text(&entry.name)
+                                        ));
+                                    }
+
+                                    row_box.append(&icon);
+                                    row_box.append(&name_label);
+                                    listbox_c.append(&row_box);
+                                }
+                                *entries_c.borrow_mut() = listing;
+
+                                let dir_count =
+                                    entries_c.borrow().iter().filter(|e| e.is_dir).count();
+                                let file_count = count - dir_count;
+                                status_c.set_text(&format!(
+                                    "{} — {} folder(s), {} file(s)",
+                                    path_entry_c.text(),
+                                    dir_count,
+                                    file_count,
+                                ));
+
+                                // Always allow selecting the current directory
+                                // (for source browse this selects the folder as source;
+                                //  for destination browse this selects the folder as target).
+                                btn_select_c.set_sensitive(true);
+                                *selected_path_c.borrow_mut() =
+                                    Some(path_entry_c.text().to_string());
+                            }
+                            Err(e) => {
+                                status_c.set_text(&format!("Error: {}", e));
+                            }
+                        }
+                        glib::ControlFlow::Break
+                    }
+                    Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        status_c.set_text("Error: listing thread terminated unexpectedly");
+                        glib::ControlFlow::Break
+                    }
+                }
+            });
+        }
+    });
+
+    // ── Connect button ────────────────────────────────────────────────
+    {
+        let populate = populate.clone();
+        let host_entry = host_entry.clone();
+        let path_entry = path_entry.clone();
+        btn_connect.connect_clicked(move |_| {
+            let host = host_entry.text().to_string().trim().to_string();
+            if host.is_empty() {
+                return;
+            }
+            let path = path_entry.text().to_string().trim().to_string();
+            let path = if path.is_empty() { "~".to_string() } else { path };
+            populate(host, path);
+        });
+    }
+
+    // ── Go button (navigate to typed path) ────────────────────────────
+    {
+        let populate = populate.clone();
+        let host_entry = host_entry.clone();
+        let path_entry = path_entry.clone();
+        btn_go.connect_clicked(move |_| {
+            let host = host_entry.text().to_string().trim().to_string();
+            if host.is_empty() {
+                return;
+            }
+            let path = path_entry.text().to_string().trim().to_string();
+            let path = if path.is_empty() { "~".to_string() } else { path };
+            populate(host, path);
+        });
+    }
+
+    // ── Up button ─────────────────────────────────────────────────────
+    {
+        let populate = populate.clone();
+        let host_entry = host_entry.clone();
+        let path_entry = path_entry.clone();
+        btn_up.connect_clicked(move |_| {
+            let host = host_entry.text().to_string().trim().to_string();
+            if host.is_empty() {
+                return;
+            }
+            let current = path_entry.text().to_string();
+            let parent = Path::new(&current)
+                .parent()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_else(|| "/".to_string());
+            let parent = if parent.is_empty() { "/".to_string() } else { parent };
+            path_entry.set_text(&parent);
+            populate(host, parent);
+        });
+    }
+
+    // ── Double-click on a row → navigate into directory ───────────────
+    {
+        let populate = populate.clone();
+        let host_entry = host_entry.clone();
+        let path_entry = path_entry.clone();
+        let entries = entries.clone();
+        let selected_path = selected_path.clone();
+        let btn_select = btn_select.clone();
+        listbox.connect_row_activated(move |_, row| {
+            let idx = row.index() as usize;
+            // Clone the entry data we need, then drop the borrow BEFORE
+            // calling populate (which takes a mutable borrow on entries).
+            let entry_snapshot = {
+                let ents = entries.borrow();
+                ents.get(idx).cloned()
+            };
+            if let Some(entry) = entry_snapshot {
+                if entry.is_dir {
+                    let host = host_entry.text().to_string().trim().to_string();
+                    let current = path_entry.text().to_string();
+                    let new_path = format!(
+                        "{}/{}",
+                        current.trim_end_matches('/'),
+                        entry.name
+                    );
+                    path_entry.set_text(&new_path);
+                    populate(host, new_path);
+                } else if !select_dirs_only {
+                    // File selected — set and enable
+                    let current = path_entry.text().to_string();
+                    let file_path = format!(
+                        "{}/{}",
+                        current.trim_end_matches('/'),
+                        entry.name,
+                    );
+                    *selected_path.borrow_mut() = Some(file_path);
+                    btn_select.set_sensitive(true);
+                }
+            }
+        });
+    }
+
+    // ── Single click on a row → highlight / update selected_path ──────
+    {
+        let entries = entries.clone();
+        let path_entry = path_entry.clone();
+        let selected_path = selected_path.clone();
+        let btn_select = btn_select.clone();
+        listbox.connect_row_selected(move |_, row| {
+            if let Some(row) = row {
+                let idx = row.index() as usize;
+                let entry_snapshot = {
+                    let ents = entries.borrow();
+                    ents.get(idx).cloned()
+                };
+                if let Some(entry) = entry_snapshot {
+                    let current = path_entry.text().to_string();
+                    if entry.is_dir {
+                        let dir_path = format!(
+                            "{}/{}",
+                            current.trim_end_matches('/'),
+                            entry.name,
+                        );
+                        *selected_path.borrow_mut() = Some(dir_path);
+                        btn_select.set_sensitive(true);
+                    } else if !select_dirs_only {
+                        let file_path = format!(
+                            "{}/{}",
+                            current.trim_end_matches('/'),
+                            entry.name,
+                        );
+                        *selected_path.borrow_mut() = Some(file_path);
+                        btn_select.set_sensitive(true);
+                    }
+                }
+            }
+        });
+    }
+
+    // ── Cancel button ─────────────────────────────────────────────────
+    {
+        let dialog = dialog.clone();
+        btn_cancel.connect_clicked(move |_| {
+            dialog.close();
+        });
+    }
+
+    // ── Select button ─────────────────────────────────────────────────
+    {
+        let dialog = dialog.clone();
+        let host_entry = host_entry.clone();
+        let path_entry = path_entry.clone();
+        let selected_path = selected_path.clone();
+        btn_select.connect_clicked(move |_| {
+            let host = host_entry.text().to_string().trim().to_string();
+            let path = selected_path
+                .borrow()
+                .clone()
+                .unwrap_or_else(|| path_entry.text().to_string());
+            if !host.is_empty() && !path.is_empty() {
+                on_select(format!("{}:{}", host, path));
+            }
+            dialog.close();
+        });
+    }
+
+    // ── Enter key in path entry → Go ──────────────────────────────────
+    {
+        let populate = populate.clone();
+        let host_entry = host_entry.clone();
+        let path_entry_c = path_entry.clone();
+        path_entry.connect_activate(move |_| {
+            let host = host_entry.text().to_string().trim().to_string();
+            if host.is_empty() {
+                return;
+            }
+            let path = path_entry_c.text().to_string().trim().to_string();
+            let path = if path.is_empty() { "/".to_string() } else { path };
+            populate(host, path);
+        });
+    }
+
+    // ── Enter key in host entry → Connect ─────────────────────────────
+    {
+        let populate = populate.clone();
+        let host_entry_c = host_entry.clone();
+        let path_entry = path_entry.clone();
+        host_entry.connect_activate(move |_| {
+            let host = host_entry_c.text().to_string().trim().to_string();
+            if host.is_empty() {
+                return;
+            }
+            let path = path_entry.text().to_string().trim().to_string();
+            let path = if path.is_empty() { "/".to_string() } else { path };
+            populate(host, path);
+        });
+    }
+
+    // Auto-connect if host was pre-filled
+    if !initial_host.is_empty() {
+        let populate = populate.clone();
+        let host = initial_host.clone();
+        let path = initial_path.clone();
+        glib::idle_add_local_once(move || {
+            populate(host, path);
+        });
+    }
+
+    dialog.present();
+}
+
+// ── Helper: shorten paths in activity text ────────────────────────────
+
+/// `text` with every word that looks like a path cut to [`MAX_NAME_LEN`]
+/// characters, so one long file name can't stretch the status line.
+fn shorten_paths(text: &str) -> String {
+    text.split(' ')
+        .map(|word| {
+            if word.contains('/') {
+                ellipsize_middle(word, MAX_NAME_LEN)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// ── Helper: progress bar phases ───────────────────────────────────────
+
+const SKIP_VERIFY_LABEL: &str = "Skip Remaining Verification";
+
+const PAUSE_LABEL: &str = "Pause";
+
+/// What the bar reads while a run is paused.
+const PAUSED_TEXT: &str = "Paused";
+
+/// Verifying and finalizing tint the bar so the phase shows at a glance.
+const PHASE_CSS: &str = "\
+progressbar.verifying > trough > progress { background-color: @success_color; }
+progressbar.finalizing > trough > progress { background-color: alpha(@success_color, 0.6); }
+";
+
+fn install_phase_css(window: &ApplicationWindow) {
+    let provider = gtk4::CssProvider::new();
+    provider.load_from_data(PHASE_CSS);
+    gtk4::style_context_add_provider_for_display(
+        &WidgetExt::display(window),
+        &provider,
+        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+}
+
+/// Have a screen reader read out `text` now, as well as showing it.
+#[cfg(feature = "gtk_v4_14")]
+fn announce(widget: &impl IsA<gtk4::Accessible>, text: &str) {
+    widget.announce(text, gtk4::AccessibleAnnouncementPriority::Medium);
+}
+
+/// GTK before 4.14 cannot announce; the bar's description still changes
+/// and the result dialog's summary is focused.
+#[cfg(not(feature = "gtk_v4_14"))]
+fn announce(_widget: &impl IsA<gtk4::Accessible>, _text: &str) {}
+
+/// Describe and announce the progress in `model` when `announcer` thinks it
+/// worth hearing; every other change is only drawn.
+fn announce_progress(bar: &ProgressBar, announcer: &mut Announcer, model: &ProgressModel) {
+    if let Some(text) = announcer.update(model) {
+        bar.update_property(&[Property::Description(&text)]);
+        announce(bar, &text);
+    }
+}
+
+/// Tell screen readers the run is over and how it went, and give focus
+/// back to Transfer for when the result dialog closes.
+fn run_ended(bar: &ProgressBar, btn_start: &Button, outcome: &str) {
+    bar.update_state(&[State::Busy(false)]);
+    bar.update_property(&[Property::Description(outcome)]);
+    announce(bar, outcome);
+    btn_start.grab_focus();
+}
+
+/// Give `bar` the CSS class of `phase`, dropping the other phases' classes.
+/// The speed and time left under the bar, once there is a speed to show.
+fn show_speed(label: &Label, throughput: &Throughput, model: &ProgressModel) {
+    match throughput.describe(model) {
+        Some(text) => {
+            label.set_text(&text);
+            label.set_visible(true);
+        }
+        None => label.set_visible(false),
+    }
+}
+
+fn show_phase(bar: &ProgressBar, phase: Phase) {
+    for p in [Phase::Verifying, Phase::Finalizing] {
+        if p == phase {
+            bar.add_css_class(p.name());
+        } else {
+            bar.remove_css_class(p.name());
+        }
+    }
+}
+
+// ── Helper: open folder picker ─────────────────────────────────────────
+
+fn pick_folder(window: &ApplicationWindow, target_entry: Entry) {
+    let dialog = FileDialog::builder()
+        .title("Select folder")
+        .modal(true)
+        .build();
+
+    dialog.select_folder(Some(window), gtk4::gio::Cancellable::NONE, move |result| {
+        if let Ok(file) = result {
+            if let Some(path) = file.path() {
+                target_entry.set_text(&path.to_string_lossy());
+            }
+        }
+    });
+}
+
+// ── Helper: destination hint ───────────────────────────────────────────
+
+/// Show under the destination entry what a run into `dst` would have to
+/// create, with the button that creates it now; hide the row otherwise.
+fn show_destination_hint(dst: &str, source: &SourceSelection, row: &GtkBox, label: &Label, button: &Button) {
+    let (host, path) = parse_destination(dst.trim());
+    let hint = folders::Hint::of(host.as_deref(), &path, file_target(source, dst).is_some());
+    label.remove_css_class("error");
+    match hint.describe() {
+        Some(text) => {
+            label.set_text(&text);
+            button.set_label(if host.is_some() { "Create Remote Folder Now" } else { "Create Now" });
+            button.set_visible(true);
+            row.set_visible(true);
+        }
+        None => row.set_visible(false),
+    }
+}
+
+/// Create `path` on `host`, and any folders above it, with the same
+/// `mkdir -p` script a run uses, ahead of the run.  What to show.
+fn create_remote_early(host: &str, path: &str) -> Result<String, String> {
+    let targets = HashSet::from([PathBuf::from(path.trim_end_matches('/'))]);
+    let out = Command::new("ssh")
+        .args(ssh::ctl_args(host))
+        .arg(host)
+        .arg(folders::mkdir_script(&targets, |p| quote_path(p)))
+        .printed()
+        .output()
+        .map_err(|e| format!("SSH failed: {}", e))?;
+    let made = folders::note_early_remote(host, &targets, &out.stdout);
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(ssh::known_error(host, &stderr)
+            .unwrap_or_else(|| format!("Could not create {} on {}: {}", path, host, stderr.trim())));
+    }
+    Ok(match made {
+        0 => format!("{} already exists on {}", path, host),
+        made => format!("Created {} folder(s) on {}", made, host),
+    })
+}
+
+/// `rmdir` each of `paths` on `host`, in order.  Folders with anything in
+/// them stay.
+fn remove_remote_folders(host: &str, paths: &[String]) {
+    let quoted: Vec<String> = paths.iter().map(|p| shell_quote(p)).collect();
+    let _ = Command::new("ssh")
+        .args(ssh::ctl_args(host))
+        .arg(host)
+        .arg(format!("rmdir -- {} 2>/dev/null", quoted.join(" ")))
+        .printed()
+        .output();
+}
+
+// ── Helper: add a typed pattern ────────────────────────────────────────
+
+/// Add the entry's text as a `kind` pattern, or show why it was refused
+/// in `error`.  An empty entry is ignored.
+fn add_typed_pattern(kind: PatternKind, entry: &Entry, error: &Label, exclusions: &RefCell<Vec<String>>, view: &ListBox) {
+    if entry.text().trim().is_empty() {
+        return;
+    }
+    match patterns::normalize(kind, &entry.text()) {
+        Ok(pattern) => {
+            let mut list = exclusions.borrow_mut();
+            patterns::add_unique(&mut list, pattern);
+            refresh_exclusion_view(view, &list);
+            entry.set_text("");
+        }
+        Err(e) => {
+            error.set_text(&format!("{}.", e));
+            error.set_visible(true);
+        }
+    }
+}
+
+// ── Helper: refresh the exclusion display ──────────────────────────────
+
+fn refresh_exclusion_view(view: &ListBox, items: &[String]) {
+    while let Some(child) = view.first_child() {
+        view.remove(&child);
+    }
+    for (i, item) in items.iter().enumerate() {
+        let row = GtkBox::new(Orientation::Horizontal, 8);
+        let label = Label::new(Some(&exclusion_label(item)));
+        label.set_halign(Align::Start);
+        label.set_hexpand(true);
+        label.set_wrap(true);
+        label.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+        label.add_css_class("monospace");
+        label.set_margin_start(8);
+        let remove = Button::with_label("×");
+        remove.add_css_class("flat");
+        remove.set_tooltip_text(Some("Remove this exclusion"));
+        remove.update_property(&[Property::Label(&format!("Remove {}", exclusion_label(item)))]);
+        remove.set_action_name(Some("exclusions.remove"));
+        remove.set_action_target_value(Some(&(i as u32).to_variant()));
+        row.append(&label);
+        row.append(&remove);
+        view.append(&row);
+    }
+}
+
+/// How the exclusion list shows the stored pattern `item`.
+fn exclusion_label(item: &str) -> String {
+    if let Some(pat) = item.strip_prefix('+') {
+        // Include-only file pattern
+        format!("{} (include only)", pat)
+    } else if let Some(pat) = item.strip_prefix('^') {
+        // Pattern for the path below the source
+        format!("/{} (path pattern)", pat)
+    } else if item.starts_with("~/") {
+        // Wildcard directory pattern
+        format!("{}/ (dir pattern)", &item[1..])
+    } else if item.starts_with('~') {
+        // Wildcard file pattern
+        format!("{} (file pattern)", &item[1..])
+    } else if item.starts_with('/') {
+        format!("{}/ (recursive)", item)
+    } else {
+        item.to_string()
+    }
+}
+
+// ── Quick filter categories ────────────────────────────────────────────
+
+/// Media categories offered as one-click include filters, with the file
+/// extensions each one covers.
+const MEDIA_CATEGORIES: &[(&str, &[&str])] = &[
+    ("Photos", &["jpg", "jpeg", "png", "heic", "raw", "cr2", "nef", "dng"]),
+    ("Videos", &["mp4", "mov", "avi", "mkv", "mts"]),
+    ("Music", &["mp3", "flac", "m4a", "ogg"]),
+    ("Documents", &["pdf", "docx", "odt", "txt", "xlsx"]),
+];
+
+/// Translate a category name into include-only patterns ("+*.ext").
+fn category_include_patterns(name: &str) -> Vec<String> {
+    MEDIA_CATEGORIES
+        .iter()
+        .filter(|(cat, _)| *cat == name)
+        .flat_map(|(_, exts)| exts.iter().map(|ext| format!("+*.{}", ext)))
+        .collect()
+}
+
+/// The exclusion list plus the include patterns of the active quick filters.
+fn active_patterns(exclusions: &[String], toggles: &[(ToggleButton, &'static str)]) -> Vec<String> {
+    let mut patterns = exclusions.to_vec();
+    for (toggle, name) in toggles {
+        if toggle.is_active() {
+            for pattern in category_include_patterns(name) {
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            }
+        }
+    }
+    patterns
+}
+
+/// Status text describing the active quick filters, or empty if none.
+fn quick_filter_status(toggles: &[(ToggleButton, &'static str)]) -> String {
+    let active: Vec<&str> = toggles
+        .iter()
+        .filter(|(t, _)| t.is_active())
+        .map(|(_, name)| *name)
+        .collect();
+    if active.is_empty() {
+        String::new()
+    } else {
+        format!("Filtering: {}", active.join(", "))
+    }
+}
+
+// ── Destination parsing ─────────────────────────────────────────────────
+
+/// Parse "host:/path", "user@host:/path" or "ssh://user@host:port/path"
+/// → (Some(host), path), where the host is `Remote::target`.  Plain paths
+/// → (None, path).  A port is handed to the ssh module, which adds it to
+/// every command run against that host.
+fn parse_destination(dst: &str) -> (Option<String>, String) {
+    match remote::Remote::parse(dst) {
+        Some(remote) => {
+            let host = remote.target();
+            if let Some(port) = remote.port {
+                ssh::set_port(&host, port);
+            }
+            (Some(host), remote.path)
+        }
+        None => (None, dst.to_string()),
+    }
+}
+
+/// The folder and file name a single-file run writes to when `dst` names
+/// the file itself rather than a folder to put it in.
+///
+/// `dst` stays a folder, as for any other run, when it ends in `/`, is an
+/// existing folder, is remote, or its parent folder doesn't exist (the
+/// folder is then created).  Otherwise the one picked file is copied to
+/// exactly `dst`, which may be an existing file.
+fn file_target(source: &SourceSelection, dst: &str) -> Option<(String, String)> {
+    let single = match source {
+        SourceSelection::Files(files) => files.len() == 1,
+        SourceSelection::RemoteFiles(_, files) => files.len() == 1,
+        _ => false,
+    };
+    let dst = dst.trim();
+    if !single || dst.ends_with('/') || parse_destination(dst).0.is_some() {
+        return None;
+    }
+    let path = Path::new(dst);
+    if path.is_dir() {
+        return None;
+    }
+    let name = path.file_name()?.to_str()?;
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    dir.is_dir().then(|| (dir.display().to_string(), name.to_string()))
+}
+
+/// Why the local `dst` can't be used: it is an existing file, and the
+/// run needs a folder to copy into.  A single file sent to a path naming
+/// it (see `file_target`) is the exception.
+fn local_destination_error(source: &SourceSelection, dst: &str) -> Option<String> {
+    let dst = dst.trim();
+    if parse_destination(dst).0.is_some() || file_target(source, dst).is_some() {
+        return None;
+    }
+    // `file/` can't be stat'ed at all, so look at `file`.
+    let path = Path::new(dst.trim_end_matches('/')).to_path_buf();
+    let meta = fs::metadata(&path).ok().filter(|_| !path.as_os_str().is_empty())?;
+    (!meta.is_dir()).then(|| dest_is_file_error(&path.display().to_string()))
+}
+
+/// Why the tar method, when chosen, can't run this transfer: it only
+/// uploads local files, and only skips or overwrites what is there.
+fn tar_method_error(method: MethodChoice, source: &SourceSelection, dst: &str, conflict_mode: ConflictMode) -> Option<&'static str> {
+    if method != MethodChoice::Tar {
+        None
+    } else if source.remote_parts().is_some() || parse_destination(dst.trim()).0.is_none() {
+        Some("The tar method only uploads local files to another host (user@host:/path); choose another method")
+    } else if !matches!(conflict_mode, ConflictMode::Skip | ConflictMode::Overwrite) {
+        Some("The tar method can only skip or overwrite existing files; choose one of those, or another method")
+    } else {
+        None
+    }
+}
+
+fn dest_is_file_error(dst: &str) -> String {
+    format!("Destination {} is a file, not a folder; choose a folder to copy into", dst)
+}
+
+/// `Will replace the existing file …` when a single file sent to `dir/name`
+/// overwrites what is there.
+fn replace_note(dir: &str, name: &str, conflict_mode: ConflictMode) -> Option<String> {
+    let path = Path::new(dir).join(name);
+    (conflict_mode == ConflictMode::Overwrite && path.is_file())
+        .then(|| format!("Will replace the existing file {}", path.display()))
+}
+
+/// Shell-escape a string with single quotes (for ssh remote commands).
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// `shell_quote` for a path, keeping bytes that aren't UTF-8 as they are;
+/// the remote shell takes them as they come.
+fn quote_path(path: impl AsRef<OsStr>) -> OsString {
+    let mut out = vec![b'\''];
+    for &b in path.as_ref().as_bytes() {
+        match b {
+            b'\'' => out.extend_from_slice(b"'\\''"),
+            b => out.push(b),
+        }
+    }
+    out.push(b'\'');
+    OsString::from_vec(out)
+}
+
+/// A remote command: `template` with each `{}` replaced by the next of
+/// `paths`, shell-quoted with `quote_path`.
+fn path_command(template: &str, paths: &[&OsStr]) -> OsString {
+    let mut out = OsString::new();
+    let mut paths = paths.iter();
+    let mut parts = template.split("{}").peekable();
+    while let Some(part) = parts.next() {
+        out.push(part);
+        if parts.peek().is_some() {
+            out.push(quote_path(paths.next().expect("a path for each {}")));
+        }
+    }
+    out
+}
+
+/// An scp or rsync `host:path` argument with the path's bytes kept.
+fn remote_arg(host: &str, path: impl AsRef<OsStr>) -> OsString {
+    let mut out = OsString::from(format!("{}:", host));
+    out.push(path);
+    out
+}
+
+/// Escape a remote path for rsync's `host:path` syntax.
+///
+/// rsync passes the path portion of `host:path` through the remote shell,
+/// so characters like spaces, parentheses, and other shell metacharacters
+/// must be backslash-escaped.  This avoids needing `--protect-args` which
+/// is not supported by macOS's bundled openrsync.
+fn rsync_escape_remote(path: impl AsRef<OsStr>) -> OsString {
+    let path = path.as_ref().as_bytes();
+    let mut out = Vec::with_capacity(path.len() + 16);
+    for &b in path {
+        match b {
+            b' ' | b'(' | b')' | b'\'' | b'"' | b'&' | b';' | b'|' | b'$' | b'`'
+            | b'!' | b'#' | b'*' | b'?' | b'[' | b']' | b'{' | b'}' | b'\\' | b'<' | b'>' | b'~' | b'\t' => {
+                out.push(b'\\');
+                out.push(b);
+            }
+            // A backslash before a newline would join the lines instead.
+            b'\n' => out.extend_from_slice(b"'\n'"),
+            _ => out.push(b),
+        }
+    }
+    OsString::from_vec(out)
+}
+
+/// Whether `path` exists on the remote host.
+fn remote_path_exists(host: &str, ctl: &[String], path: &Path) -> bool {
+    let check = Command::new("ssh")
+        .args(ctl)
+        .arg(host)
+        .arg(path_command("test -e {}", &[path.as_os_str()]))
+        .tracked_status();
+    matches!(check, Ok(s) if s.success())
+}
+
+/// Why a per-file scp or rsync run failed, as `: <reason>` to end its
+/// error entry: the line of stderr that explains it, or why it could not
+/// be started.  Empty when stderr has nothing useful.  The full stderr
+/// goes to the debug log.
+fn failure_reason(subject: &str, result: &std::io::Result<std::process::Output>) -> String {
+    match result {
+        Ok(out) => {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            if !stderr.trim().is_empty() {
+                debug_log(&format!("stderr for {}:\n{}", subject, stderr.trim_end()));
+            }
+            ssh::stderr_summary(&stderr).map(|line| format!(": {}", line)).unwrap_or_default()
+        }
+        Err(e) => format!(": {}", e),
+    }
+}
+
+/// Remove what an scp stopped by `--file-timeout` left at `path` on
+/// `host`; rsync cleans up after itself.
+fn remove_remote_partial(host: &str, ctl: &[String], path: &Path) {
+    let _ = Command::new("ssh")
+        .args(ctl)
+        .arg(host)
+        .arg(path_command("rm -f {}", &[path.as_os_str()]))
+        .tracked_status();
+}
+
+/// Check that the remote destination `base` is a folder or not there yet,
+/// sending the error and returning `false` if it is a file.  A probe that
+/// fails for another reason is left to the transfer.
+fn check_remote_destination(host: &str, ctl: &[String], base: &str, tx: &mpsc::Sender<WorkerMsg>) -> bool {
+    let path = shell_quote(base.trim_end_matches('/'));
+    let probe = Command::new("ssh")
+        .args(ctl)
+        .arg(host)
+        .arg(format!("test -d {0} || {{ test -f {0} && echo file; }} || true", path))
+        .tracked_output();
+    match probe {
+        Ok(out) if out.status.success() && String::from_utf8_lossy(&out.stdout).trim() == "file" => {
+            let _ = tx.send(WorkerMsg::Error(dest_is_file_error(&format!("{}:{}", host, base))));
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Check that `host` answers over ssh before a transfer, sending the
+/// reason and returning `false` if it doesn't.  When ssh does not know the
+/// host's key yet, the UI is shown its fingerprints first; once they are
+/// accepted (and added to known_hosts) the check is tried again.
+fn check_ssh_host(host: &str, ctl: &[String], tx: &mpsc::Sender<WorkerMsg>) -> bool {
+    let mut asked = false;
+    loop {
+        let before = clock::now();
+        let check = Command::new("ssh")
+            .args(ctl)
+            .args([host, "date +%s"])
+            .tracked_output();
+        let stderr = match check {
+            Ok(o) if o.status.success() => {
+                return check_clock_skew(host, before, clock::now(), &String::from_utf8_lossy(&o.stdout), tx)
+            }
+            Ok(o) => String::from_utf8_lossy(&o.stderr).to_string(),
+            Err(e) => {
+                let _ = tx.send(WorkerMsg::Error(format!("Could not run ssh: {}", e)));
+                return false;
+            }
+        };
+        if let Some(problem) = ssh::host_key_problem(&stderr) {
+            // A changed key is only ever reported, so don't look it up.
+            let keys = match problem {
+                ssh::HostKeyProblem::Unknown if !asked => scan_host_keys(host),
+                _ => Vec::new(),
+            };
+            let (reply_tx, reply_rx) = mpsc::channel();
+            let _ = tx.send(WorkerMsg::HostKey { host: host.to_string(), problem, keys, reply: reply_tx });
+            if reply_rx.recv() == Ok(true) && !asked {
+                asked = true;
+                continue;
+            }
+        }
+        let _ = tx.send(WorkerMsg::Error(ssh::connection_error(host, &stderr)));
+        return false;
+    }
+}
+
+/// Report how far `host`'s clock (`date +%s` printed `remote`) is off,
+/// returning `false` after sending the error if `--max-clock-skew` is
+/// exceeded.  A host whose answer isn't a time is let through.
+fn check_clock_skew(host: &str, before: f64, after: f64, remote: &str, tx: &mpsc::Sender<WorkerMsg>) -> bool {
+    let Some(seconds) = clock::measure(before, after, remote) else {
+        debug_log(&format!("{}: no clock reading in {:?}", host, remote.trim()));
+        return true;
+    };
+    debug_log(&format!("{}: clock skew {}s", host, seconds));
+    let warning = match clock::assess(host, seconds) {
+        clock::Verdict::Refuse(e) => {
+            let _ = tx.send(WorkerMsg::Error(e));
+            return false;
+        }
+        clock::Verdict::Warn(w) => Some(w),
+        clock::Verdict::Fine => None,
+    };
+    clock::record(host, seconds);
+    let _ = tx.send(WorkerMsg::ClockSkew { host: host.to_string(), seconds, warning });
+    true
+}
+
+/// The keys `host` offers with their fingerprints, for the user to check
+/// before trusting it; empty if they could not be fetched.
+fn scan_host_keys(host: &str) -> Vec<ssh::HostKey> {
+    let Some(target) = ssh_target(host) else { return Vec::new() };
+    let scan = Command::new("ssh-keyscan")
+        .args(["-T", "10", "-p", &target.port, &target.hostname])
+        .tracked_output();
+    let keys = match scan {
+        Ok(o) if o.status.success() => String::from_utf8_lossy(&o.stdout).to_string(),
+        _ => return Vec::new(),
+    };
+    let fingerprints = Command::new("ssh-keygen")
+        .args(["-l", "-f", "-"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .printed()
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(keys.as_bytes())?;
+            }
+            child.wait_with_output()
+        });
+    match fingerprints {
+        Ok(o) => ssh::pair_fingerprints(&keys, &String::from_utf8_lossy(&o.stdout)),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Find `name` on PATH for `--capabilities`, with its version where it
+/// reports one: rsync's from `--version`, OpenSSH's from `ssh -V` (on
+/// stderr).  scp has no version option.  A program that is missing or
+/// fails to run is reported without a version rather than as an error.
+fn probe_tool(name: &'static str) -> cli::Tool {
+    let path = priority::which(name, std::env::var_os("PATH"));
+    let version = path.as_ref().and_then(|path| match name {
+        "rsync" => {
+            let out = Command::new(path).arg("--version").output().ok()?;
+            rsync::parse_version(&String::from_utf8_lossy(&out.stdout)).map(|info| info.version)
+        }
+        "ssh" => {
+            let out = Command::new(path).arg("-V").output().ok()?;
+            let text = String::from_utf8_lossy(&out.stderr);
+            text.lines().next().map(|line| line.trim().to_string()).filter(|line| !line.is_empty())
+        }
+        _ => None,
+    });
+    cli::Tool { name, path: path.map(|p| p.to_string_lossy().to_string()), version }
+}
+
+/// Where ssh connects for `host` after applying the user's ssh config.
+fn ssh_target(host: &str) -> Option<ssh::Target> {
+    let out = Command::new("ssh").args(ssh::host_args(host)).args(["-G", host]).tracked_output().ok()?;
+    ssh::parse_target(&String::from_utf8_lossy(&out.stdout))
+}
+
+/// Whether `a` and `b` reach one account on one machine, as typed or as
+/// the ssh config resolves them.
+fn same_account(a: &str, b: &str) -> bool {
+    a == b || matches!((ssh_target(a), ssh_target(b)), (Some(a), Some(b)) if ssh::same_account(&a, &b))
+}
+
+/// Run `rsync --version` here and on each of `hosts` before an rsync
+/// transfer, sending what was found and any downgrade warnings to the UI.
+/// A missing rsync on either end is sent as an error and returns `false`;
+/// a host whose check fails for another reason is left to the transfer.
+fn check_rsync_peers(hosts: &[&str], tx: &mpsc::Sender<WorkerMsg>) -> bool {
+    let mut peers = Vec::new();
+    match Command::new("rsync").arg("--version").printed().output() {
+        Ok(o) if o.status.success() => {
+            if let Some(info) = rsync::parse_version(&String::from_utf8_lossy(&o.stdout)) {
+                peers.push(("this machine".to_string(), info));
+            }
+        }
+        _ => {
+            let _ = tx.send(WorkerMsg::Error(
+                "rsync is not installed or not found in PATH".to_string(),
+            ));
+            return false;
+        }
+    }
+    for host in hosts {
+        let out = Command::new("ssh")
+            .args(ssh::ctl_args(host))
+            .arg(host)
+            .arg("rsync --version")
+            .tracked_output();
+        match out {
+            Ok(o) if o.status.success() => {
+                match rsync::parse_version(&String::from_utf8_lossy(&o.stdout)) {
+                    Some(info) => peers.push((format!("'{}'", host), info)),
+                    None => debug_log(&format!("unrecognised rsync --version output on {}", host)),
+                }
+            }
+            Ok(o) if rsync::is_missing(o.status.code(), &String::from_utf8_lossy(&o.stderr)) => {
+                let _ = tx.send(WorkerMsg::Error(format!(
+                    "rsync is not installed on '{}'. Install it there or use the standard method.",
+                    host
+                )));
+                return false;
+            }
+            Ok(o) => debug_log(&format!(
+                "rsync --version failed on {}: {}",
+                host,
+                String::from_utf8_lossy(&o.stderr).trim()
+            )),
+            Err(e) => debug_log(&format!("could not run ssh to check rsync on {}: {}", host, e)),
+        }
+    }
+    let versions: Vec<String> = peers
+        .iter()
+        .map(|(place, info)| format!("{}: {}", place, info.describe()))
+        .collect();
+    for v in &versions {
+        debug_log(&format!("rsync on {}", v));
+    }
+    let warnings = rsync::downgrade_warnings(&peers);
+    let _ = tx.send(WorkerMsg::RsyncPeers { versions, warnings });
+    true
+}
+
+/// Settle the Auto method for a run from `source` to `dst_host` (`None`
+/// for a local destination), sending the decision to the UI.  Local runs
+/// always use the standard method.  The source is measured before any
+/// exclusions, and a host that cannot be probed counts as having no rsync,
+/// leaving a connection error to the worker.
+fn auto_method(
+    source: &SourceSelection,
+    dst_host: Option<&str>,
+    thresholds: automethod::Thresholds,
+    tx: &mpsc::Sender<WorkerMsg>,
+) -> TransferMethod {
+    let remote_src = source.remote_parts();
+    if remote_src.is_none() && dst_host.is_none() {
+        return TransferMethod::Standard;
+    }
+    activity::set("choosing the transfer method");
+    let mut installed = priority::which("rsync", std::env::var_os("PATH")).is_some();
+    let shape = match remote_src {
+        Some((host, base, files)) => {
+            // A list of files is counted as it is; only a folder is measured.
+            let probe = remote_probe(host, Some(base).filter(|_| files.is_empty()));
+            installed &= probe.is_some_and(|(_, _, rsync)| rsync);
+            let (files, bytes) = match probe {
+                Some((found, bytes, _)) if files.is_empty() => (found, bytes),
+                _ => (files.len(), None),
+            };
+            automethod::PlanShape { files, bytes, remote: true }
+        }
+        None => {
+            let (files, bytes) = local_shape(source);
+            automethod::PlanShape { files, bytes: Some(bytes), remote: true }
+        }
+    };
+    if let Some(host) = dst_host {
+        installed &= remote_probe(host, None).is_some_and(|(_, _, rsync)| rsync);
+    }
+    let decision = automethod::decide(&shape, installed, thresholds);
+    debug_log(&decision.describe());
+    let method = decision.method;
+    let _ = tx.send(WorkerMsg::AutoMethod(decision));
+    method
+}
+
+/// The files under a local source and their total size, for Auto.
+fn local_shape(source: &SourceSelection) -> (usize, u64) {
+    let roots: Vec<&Path> = match source {
+        SourceSelection::Directory(dir) => vec![dir.as_path()],
+        SourceSelection::DirectoryItems(_, items) | SourceSelection::Files(items) => {
+            items.iter().map(PathBuf::as_path).collect()
+        }
+        _ => Vec::new(),
+    };
+    roots
+        .iter()
+        .flat_map(|root| WalkDir::new(root).into_iter().filter_map(Result::ok))
+        .filter(|entry| entry.file_type().is_file())
+        .fold((0, 0), |(files, bytes), entry| (files + 1, bytes + entry.metadata().map_or(0, |m| m.len())))
+}
+
+/// Count and size the files under `base` on `host` (nothing without a
+/// base) and check for rsync there; see `automethod::parse_probe`.
+fn remote_probe(host: &str, base: Option<&str>) -> Option<(usize, Option<u64>, bool)> {
+    const RSYNC: &str = "command -v rsync >/dev/null 2>&1 && echo rsync";
+    let script = match base {
+        Some(base) => format!(
+            "find {0} -type f 2>/dev/null | wc -l; du -sk {0} 2>/dev/null || echo; {1}",
+            shell_quote(base),
+            RSYNC
+        ),
+        None => format!("echo 0; echo; {}", RSYNC),
+    };
+    let out = Command::new("ssh").args(ssh::ctl_args(host)).arg(host).arg(script).tracked_output().ok()?;
+    automethod::parse_probe(&String::from_utf8_lossy(&out.stdout))
+}
+
+/// `path` on `host` with symlinks resolved, or `None` if that fails.
+fn remote_canonical(host: &str, ctl: &[String], path: &Path) -> Option<String> {
+    let out = Command::new("ssh")
+        .args(ctl)
+        .arg(host)
+        .arg(path_command("readlink -f {}", &[path.as_os_str()]))
+        .tracked_output()
+        .ok()?;
+    let resolved = escape_non_utf8(out.stdout.trim_ascii()).0;
+    (out.status.success() && !resolved.is_empty()).then_some(resolved)
+}
+
+/// Whether copying the (resolved) remote source folder `src_root` into
+/// `dst` would write its files back onto themselves.
+fn lands_on_source(src_root: &str, dst: &str, transfer_mode: TransferMode) -> bool {
+    let src_root = src_root.trim_end_matches('/');
+    let dst = dst.trim_end_matches('/');
+    let parent = src_root.rsplit_once('/').map_or("", |(p, _)| p);
+    dst == src_root || (transfer_mode == TransferMode::FoldersAndFiles && dst == parent)
+}
+
+/// Error for a remote-to-remote transfer whose destination is the source
+/// itself.  Inodes can't be compared over ssh, so this only catches paths
+/// that resolve to the same place, and is only asked when both hosts are
+/// one account on one host (see `same_account`).
+fn remote_same_location_error(
+    src_host: &str,
+    src_base: &str,
+    src_files: &[PathBuf],
+    dst_host: &str,
+    dst_base: &str,
+    transfer_mode: TransferMode,
+    ctl: &[String],
+) -> Option<String> {
+    let src_root = if src_base.is_empty() { src_files.first()?.parent()? } else { Path::new(src_base) };
+    let mode = if src_base.is_empty() { TransferMode::FilesOnly } else { transfer_mode };
+    let src_real = remote_canonical(src_host, ctl, src_root)?;
+    let dst_real = remote_canonical(dst_host, ctl, Path::new(dst_base))?;
+    if lands_on_source(&src_real, &dst_real, mode) {
+        return Some(format!(
+            "Source and destination are the same location on '{}' ({}), so files would overwrite themselves.",
+            src_host, src_real
+        ));
+    }
+    // A folder is listed in full, so one below it would be listed too.
+    (!src_base.is_empty() && lies_below(&dst_real, &src_real)).then(|| {
+        format!(
+            "Destination {} is inside the source folder {} on '{}', so the run would copy its own copies.",
+            dst_real, src_real, src_host
+        )
+    })
+}
+
+/// Whether the remote path `inner` is below the folder `outer`, both
+/// resolved.
+fn lies_below(inner: &str, outer: &str) -> bool {
+    let outer = outer.trim_end_matches('/');
+    inner.trim_end_matches('/').strip_prefix(outer).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// What is re-tested on the destination host just before an upload.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Recheck {
+    /// Trust the files looked up before the run (`--no-recheck-rename`).
+    Nothing,
+    /// Test the name Rename mode picked, so a file written there since the
+    /// lookup is not overwritten.  The default.
+    RenameTargets,
+    /// Test every target too (`--recheck-conflicts`), for destinations
+    /// other processes may be writing to.
+    Everything,
+}
+
+/// Modification times at most this far apart count as the same for Newest
+/// wins: FAT keeps them to 2 s and `scp -p` to whole seconds.
+const NEWER_TOLERANCE: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Whether Newest wins replaces a destination file modified at `dest` with
+/// a source modified at `source`.  A time that couldn't be read keeps the
+/// destination.  The tolerance widens by the skew between the run's
+/// clocks, so a host whose clock runs ahead doesn't win on that alone.
+fn source_is_newer(source: Option<Timestamp>, dest: Option<Timestamp>) -> bool {
+    newer_allowing(source, dest, clock::allowance())
+}
+
+/// `source_is_newer` with clocks `skew` apart, noting a source kept only
+/// because of the skew for `kept_reason`.
+fn newer_allowing(source: Option<Timestamp>, dest: Option<Timestamp>, skew: std::time::Duration) -> bool {
+    let (Some(source), Some(dest)) = (source, dest) else {
+        clock::note_kept_for_skew(false);
+        return false;
+    };
+    let newer = source.is_newer(dest, NEWER_TOLERANCE + skew);
+    clock::note_kept_for_skew(!newer && source.is_newer(dest, NEWER_TOLERANCE));
+    newer
+}
+
+/// Why a file was skipped because its remote destination exists.
+fn kept_reason(conflict_mode: ConflictMode) -> &'static str {
+    match conflict_mode {
+        ConflictMode::Newer if clock::kept_for_skew() => WITHIN_SKEW_NOTE,
+        ConflictMode::Newer => DEST_NEWER_NOTE,
+        _ => "already exists at destination",
+    }
+}
+
+/// Where a planned upload should go given the destination files known so
+/// far, or `None` to skip it.
+///
+/// Workers keep `existing` live by inserting every uploaded path, and a
+/// name Rename mode picks goes in straight away, so two sources mapping to
+/// the same destination are caught like any other conflict.  `exists`
+/// tests a path on the destination host; which paths it is asked about
+/// depends on `recheck`.  In Newest wins, `newer` says whether the source
+/// is newer than the existing file at a path.
+fn resolve_remote_conflict<'a>(
+    target: &'a Path,
+    existing: &mut HashSet<PathBuf>,
+    conflict_mode: ConflictMode,
+    recheck: Recheck,
+    newer: impl FnOnce(&Path) -> bool,
+    mut exists: impl FnMut(&Path) -> bool,
+) -> Option<std::borrow::Cow<'a, Path>> {
+    if conflict_mode == ConflictMode::Overwrite {
+        return Some(std::borrow::Cow::Borrowed(target));
+    }
+    if recheck == Recheck::Everything && !existing.contains(target) && exists(target) {
+        existing.insert(target.to_path_buf());
+    }
+    if !existing.contains(target) {
+        return Some(std::borrow::Cow::Borrowed(target));
+    }
+    match conflict_mode {
+        ConflictMode::Skip => None,
+        ConflictMode::Rename => {
+            let mut candidate = destpath::unique_remote(target, existing);
+            while recheck != Recheck::Nothing && exists(&candidate) {
+                debug_log(&format!("rename target {} appeared since the lookup; picking another", shown(&candidate)));
+                existing.insert(candidate);
+                candidate = destpath::unique_remote(target, existing);
+            }
+            existing.insert(candidate.clone());
+            Some(std::borrow::Cow::Owned(candidate))
+        }
+        ConflictMode::Newer => newer(target).then_some(std::borrow::Cow::Borrowed(target)),
+        ConflictMode::Overwrite => unreachable!(),
+    }
+}
+
+// ── Existing destination files ─────────────────────────────────────────
+
+/// Plans with at most this many targets probe each target directly;
+/// larger plans list the target directories instead.
+const PROBE_MAX_TARGETS: usize = 256;
+
+/// Paths per remote shell command, to stay well under ARG_MAX.
+const LOOKUP_CHUNK: usize = 200;
+
+/// How the existing destination files were looked up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ExistingLookup {
+    /// `[ -f … ]` on each target (plus the hits' directories when renaming).
+    Probe,
+    /// `find <dir> -maxdepth 1 -type f` on each target directory.
+    ListDirs,
+}
+
+/// Modification times of remote files, from the lookups that print them.
+type RemoteTimes = HashMap<PathBuf, Timestamp>;
+
+/// `find` output of each file's path and modification time, one record
+/// per file ending in a NUL, as every lookup here prints its paths.
+const MTIME_PRINTF: &str = "-printf '%p\\t%T@\\0'";
+
+/// Fetch the existing files on the destination that conflict decisions can
+/// depend on, without listing the whole destination tree.
+///
+/// Skip only needs to know whether each target exists.  Rename also needs
+/// the target's siblings so `name_N.ext` candidates can be checked, which
+/// is why hits (and targets the plan names twice) are followed up with a
+/// listing of their directories.  Newest wins has the same commands print
+/// each file's modification time too, returned as the `RemoteTimes`.  The
+/// result answers `contains` and `find_unique_remote_path_from_set` exactly
+/// as a full `find <base> -type f` listing would for these targets.
+///
+/// `run` executes a shell command on the destination host and returns its
+/// stdout, or `None` if it could not be run.
+fn existing_remote_files(
+    targets: &[&Path],
+    conflict_mode: ConflictMode,
+    mut run: impl FnMut(&OsStr) -> Option<Vec<u8>>,
+) -> (HashSet<PathBuf>, RemoteTimes, ExistingLookup) {
+    let with_times = conflict_mode == ConflictMode::Newer;
+    let mut existing = HashSet::new();
+    let mut times = RemoteTimes::new();
+    let path = |bytes: &[u8]| PathBuf::from(OsStr::from_bytes(bytes));
+    let mut collect = |script: OsString, existing: &mut HashSet<PathBuf>| {
+        let Some(out) = run(&script) else { return };
+        for line in out.split(|&b| b == 0).filter(|l| !l.is_empty()) {
+            let timed = line.iter().rposition(|&b| b == b'\t').and_then(|tab| {
+                let time = Timestamp::parse_epoch(std::str::from_utf8(&line[tab + 1..]).ok()?)?;
+                Some((&line[..tab], time))
+            });
+            match timed.filter(|_| with_times) {
+                Some((name, time)) => {
+                    times.insert(path(name), time);
+                    existing.insert(path(name));
+                }
+                None => {
+                    existing.insert(path(line));
+                }
+            }
+        }
+    };
+
+    if targets.len() > PROBE_MAX_TARGETS {
+        for script in list_dirs_scripts(parent_dirs(targets.iter().copied()), with_times) {
+            collect(script, &mut existing);
+        }
+        return (existing, times, ExistingLookup::ListDirs);
+    }
+
+    for chunk in targets.chunks(LOOKUP_CHUNK) {
+        let mut script = OsString::from(if with_times { "find " } else { "for f in " });
+        script.push(quoted_list(chunk));
+        script.push(match with_times {
+            true => format!(" -maxdepth 0 -type f {} 2>/dev/null; true", MTIME_PRINTF),
+            false => "; do [ -f \"$f\" ] && printf '%s\\0' \"$f\"; done; true".to_string(),
+        });
+        collect(script, &mut existing);
+    }
+    // A target named twice in the plan conflicts with itself once the first
+    // copy lands, so renaming needs its siblings too.
+    let mut seen = HashSet::new();
+    let duplicates: Vec<&Path> = targets.iter().copied().filter(|t| !seen.insert(*t)).collect();
+    if conflict_mode == ConflictMode::Rename && (!existing.is_empty() || !duplicates.is_empty()) {
+        let dirs = parent_dirs(existing.iter().map(PathBuf::as_path).chain(duplicates));
+        for script in list_dirs_scripts(dirs, false) {
+            collect(script, &mut existing);
+        }
+    }
+    (existing, times, ExistingLookup::Probe)
+}
+
+/// Distinct parent directories of `paths`.
+fn parent_dirs<'a>(paths: impl Iterator<Item = &'a Path>) -> HashSet<PathBuf> {
+    paths.filter_map(Path::parent).map(Path::to_path_buf).collect()
+}
+
+/// `paths` shell-quoted with `quote_path`, separated by spaces.
+fn quoted_list(paths: &[impl AsRef<OsStr>]) -> OsString {
+    let mut out = OsString::new();
+    for (i, path) in paths.iter().enumerate() {
+        if i > 0 {
+            out.push(" ");
+        }
+        out.push(quote_path(path));
+    }
+    out
+}
+
+/// Chunked `find -maxdepth 1` commands listing the files directly in `dirs`,
+/// with their modification times if `with_times`.
+fn list_dirs_scripts(dirs: HashSet<PathBuf>, with_times: bool) -> Vec<OsString> {
+    let mut dirs: Vec<PathBuf> = dirs.into_iter().collect();
+    dirs.sort();
+    let printf = if with_times { MTIME_PRINTF } else { "-print0" };
+    dirs.chunks(LOOKUP_CHUNK)
+        .map(|chunk| {
+            let mut script = OsString::from("find ");
+            script.push(quoted_list(chunk));
+            script.push(format!(" -maxdepth 1 -type f {} 2>/dev/null; true", printf));
+            script
+        })
+        .collect()
+}
+
+/// Look up existing destination files over SSH for the given targets, with
+/// their modification times in Newest wins.
+fn existing_remote_files_ssh(
+    host: &str,
+    ctl: &[String],
+    targets: &[&Path],
+    conflict_mode: ConflictMode,
+) -> (HashSet<PathBuf>, RemoteTimes) {
+    let (existing, times, lookup) = existing_remote_files(targets, conflict_mode, |script| {
+        Command::new("ssh").args(ctl).arg(host).arg(script).tracked_output().ok().map(|o| o.stdout)
+    });
+    debug_log(&format!(
+        "existing-file lookup on {}: {:?} for {} target(s), {} hit(s)",
+        host,
+        lookup,
+        targets.len(),
+        existing.len()
+    ));
+    (existing, times)
+}
+
+/// Modification times of the remote source files `paths`, for Newest wins
+/// to compare with their destinations.  Looked up like existing targets.
+fn remote_source_times(host: &str, ctl: &[String], paths: &[&Path]) -> RemoteTimes {
+    existing_remote_files_ssh(host, ctl, paths, ConflictMode::Newer).1
+}
+
+/// A local file's modification time, for the date-prefix rename rule.
+fn modified(path: &Path) -> Option<std::time::SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// ── Failed-files lists ─────────────────────────────────────────────────
+
+/// Source paths of the files named in `errors`, for the failed-files list.
+///
+/// Worker error messages start with `"<source path>: "`, the path as
+/// `shown` writes it, so each message is matched against the known sources
+/// at every `": "` boundary.  The paths come back with their own bytes;
+/// remote sources in `host:/path` form.
+fn failed_sources(
+    sources: impl Iterator<Item = PathBuf>,
+    errors: &[String],
+    host: Option<&str>,
+) -> Vec<OsString> {
+    let known: HashMap<String, PathBuf> =
+        sources.map(|source| (escape_non_utf8(source.as_os_str().as_bytes()).0, source)).collect();
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut failed = Vec::new();
+    for e in errors {
+        for (idx, _) in e.match_indices(": ") {
+            let candidate = &e[..idx];
+            if let Some(source) = known.get(candidate) {
+                if seen.insert(candidate) {
+                    failed.push(match host {
+                        Some(h) => ssh::location(h, source),
+                        None => source.clone().into_os_string(),
+                    });
+                }
+                break;
+            }
+        }
+    }
+    failed
+}
+
+/// File extension used for saved failed-files lists.
+const FAILED_LIST_EXT: &str = "kosmokopy-failed";
+
+/// Write a failed-files list, one entry per line or NUL-terminated.  Each
+/// entry is written as its bytes, UTF-8 or not.
+fn write_failed_list(path: &Path, entries: &[OsString], nul: bool) -> std::io::Result<()> {
+    let sep = if nul { b'\0' } else { b'\n' };
+    let mut out = Vec::new();
+    for entry in entries {
+        out.extend_from_slice(entry.as_bytes());
+        out.push(sep);
+    }
+    fs::write(path, out)
+}
+
+/// Read a failed-files list.  NUL-delimited lists are detected automatically.
+fn read_failed_list(path: &Path) -> std::io::Result<Vec<OsString>> {
+    let content = fs::read(path)?;
+    let sep = if content.contains(&0) { b'\0' } else { b'\n' };
+    Ok(content
+        .split(|&b| b == sep)
+        .map(|l| l.strip_suffix(b"\r").unwrap_or(l))
+        .filter(|l| !l.is_empty())
+        .map(|l| OsStr::from_bytes(l).to_os_string())
+        .collect())
+}
+
+/// Whether `path` looks like a failed-files list: either it has the
+/// `.kosmokopy-failed` extension, or it is a small text file whose every
+/// entry is an absolute local path or a `host:/path`.
+fn looks_like_failed_list(path: &Path) -> bool {
+    if path.extension().map(|e| e == FAILED_LIST_EXT).unwrap_or(false) {
+        return true;
+    }
+    match fs::metadata(path) {
+        Ok(m) if m.is_file() && m.len() > 0 && m.len() <= 16 * 1024 * 1024 => {}
+        _ => return false,
+    }
+    match read_failed_list(path) {
+        Ok(entries) => {
+            !entries.is_empty()
+                && entries.iter().all(|e| {
+                    let (host, p) = parse_list_entry(e);
+                    p.has_root() && (host.is_some() || p.is_absolute())
+                })
+        }
+        Err(_) => false,
+    }
+}
+
+/// The host a failed-files list entry names, if any, and its path with the
+/// entry's bytes kept.  The entry is parsed as text with a `?` standing in
+/// for each byte that isn't UTF-8, so the text is as long as the entry and
+/// the path is the same-length tail of the raw bytes.
+fn parse_list_entry(entry: &OsStr) -> (Option<String>, PathBuf) {
+    let bytes = entry.as_bytes();
+    let text: String = bytes
+        .utf8_chunks()
+        .flat_map(|c| c.valid().chars().chain(c.invalid().iter().map(|_| '?')))
+        .collect();
+    let (host, path) = parse_destination(&text);
+    let tail = &bytes[bytes.len().saturating_sub(path.len())..];
+    (host, PathBuf::from(OsStr::from_bytes(tail)))
+}
+
+/// Rebuild a source selection from failed-files list entries: local paths
+/// become a `Files` selection, `host:/path` entries a `RemoteFiles` one.
+fn selection_from_list(entries: Vec<OsString>) -> Result<SourceSelection, String> {
+    if entries.is_empty() {
+        return Err("The file list is empty.".to_string());
+    }
+    let parsed: Vec<(Option<String>, PathBuf)> =
+        entries.iter().map(|e| parse_list_entry(e)).collect();
+    let first_host = parsed[0].0.clone();
+    match first_host {
+        None => {
+            if parsed.iter().any(|(h, _)| h.is_some()) {
+                return Err("The file list mixes local and remote entries.".to_string());
+            }
+            Ok(SourceSelection::Files(parsed.into_iter().map(|(_, p)| p).collect()))
+        }
+        Some(host) => {
+            if parsed.iter().any(|(h, _)| h.as_deref() != Some(host.as_str())) {
+                return Err("The file list must name a single remote host.".to_string());
+            }
+            Ok(SourceSelection::RemoteFiles(host, parsed.into_iter().map(|(_, p)| p).collect()))
+        }
+    }
+}
+
+/// The source and destination that retry just the `failed` files of a run
+/// of `source` to `dst`, or `None` when nothing failed.  Files of a folder
+/// keep the folder as their root, so each lands where the first run would
+/// have put it; a lone failed file of several goes into the destination
+/// folder rather than becoming the target file.
+fn retry_run(source: &SourceSelection, dst: &str, failed: &[OsString]) -> Option<(SourceSelection, String)> {
+    if failed.is_empty() {
+        return None;
+    }
+    let local = || failed.iter().map(PathBuf::from).collect::<Vec<_>>();
+    let remote = || failed.iter().map(|f| parse_list_entry(f).1).collect::<Vec<_>>();
+    let retry = match source {
+        SourceSelection::None => return None,
+        SourceSelection::Directory(root) | SourceSelection::DirectoryItems(root, _) => {
+            SourceSelection::DirectoryItems(root.clone(), local())
+        }
+        SourceSelection::Files(_) => SourceSelection::Files(local()),
+        SourceSelection::Remote(host, base) | SourceSelection::RemoteItems(host, base, _) => {
+            SourceSelection::RemoteItems(host.clone(), base.clone(), remote())
+        }
+        SourceSelection::RemoteFiles(host, _) => SourceSelection::RemoteFiles(host.clone(), remote()),
+    };
+    let dst = if file_target(source, dst).is_none() && file_target(&retry, dst).is_some() {
+        format!("{}/", dst.trim())
+    } else {
+        dst.to_string()
+    };
+    Some((retry, dst))
+}
+
+/// Text shown in the source entry for a selection made through a dialog or
+/// a dropped list rather than typed in.
+fn selection_label(sel: &SourceSelection) -> String {
+    match sel {
+        SourceSelection::None => String::new(),
+        SourceSelection::Directory(p) => p.to_string_lossy().to_string(),
+        SourceSelection::DirectoryItems(p, files) => {
+            format!("{} ({} files selected)", p.to_string_lossy(), files.len())
+        }
+        SourceSelection::Files(paths) if paths.len() == 1 => paths[0].to_string_lossy().to_string(),
+        SourceSelection::Files(paths) => format!("{} files selected", paths.len()),
+        SourceSelection::Remote(h, p) => format!("{}:{}", h, p),
+        SourceSelection::RemoteFiles(h, files) => format!("{} files selected on {}", files.len(), h),
+        SourceSelection::RemoteItems(h, p, files) => format!("{}:{} ({} files selected)", h, p, files.len()),
+    }
+}
+
+// ── Dropped files ──────────────────────────────────────────────────────
+
+/// A drop target for files from a file manager, offered as a file list, a
+/// single file or `text/uri-list` text.
+fn file_drop_target() -> gtk4::DropTarget {
+    let target = gtk4::DropTarget::new(glib::Type::INVALID, gtk4::gdk::DragAction::COPY);
+    target.set_types(&[
+        gtk4::gdk::FileList::static_type(),
+        gtk4::gio::File::static_type(),
+        String::static_type(),
+    ]);
+    target
+}
+
+/// The local paths in a value dropped on a `file_drop_target`.
+fn dropped_paths(value: &glib::Value) -> Vec<PathBuf> {
+    if let Ok(list) = value.get::<gtk4::gdk::FileList>() {
+        list.files().iter().filter_map(|f| f.path()).collect()
+    } else if let Ok(file) = value.get::<gtk4::gio::File>() {
+        file.path().into_iter().collect()
+    } else if let Ok(text) = value.get::<String>() {
+        paths_from_uri_list(&text)
+    } else {
+        Vec::new()
+    }
+}
+
+/// The local paths in `text/uri-list` text, one per line: `file://` URIs
+/// with `%20` and the like decoded, or plain absolute paths.  Comments and
+/// URIs of other schemes or hosts are left out.
+fn paths_from_uri_list(text: &str) -> Vec<PathBuf> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| match l.strip_prefix("file://") {
+            Some(rest) => file_uri_path(rest),
+            None => Path::new(l).is_absolute().then(|| PathBuf::from(l)),
+        })
+        .collect()
+}
+
+/// The path of a `file://` URI from what follows `file://`: an empty host
+/// or `localhost`, then the path with its bytes percent-encoded.
+fn file_uri_path(rest: &str) -> Option<PathBuf> {
+    let (host, path) = rest.split_at(rest.find('/')?);
+    if !matches!(host, "" | "localhost") {
+        return None;
+    }
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes.get(i + 1..i + 3) {
+            Some(hex) if bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit) => {
+                u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+            }
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    Some(PathBuf::from(std::ffi::OsString::from_vec(decoded)))
+}
+
+/// The source selection for files and folders dropped on the window: one
+/// folder becomes a folder source and any number of files a file
+/// selection.  A folder dropped along with anything else is refused, as
+/// copying it as loose files would lose its structure.
+fn selection_from_drop(paths: Vec<PathBuf>) -> Result<SourceSelection, String> {
+    match paths.as_slice() {
+        [] => Err("Only local files and folders can be dropped here.".to_string()),
+        [one] if one.is_dir() => Ok(SourceSelection::Directory(one.clone())),
+        _ => match paths.iter().find(|p| p.is_dir()) {
+            Some(dir) => Err(format!(
+                "Drop either one folder or any number of files: {} is a folder",
+                dir.display()
+            )),
+            None => Ok(SourceSelection::Files(paths)),
+        },
+    }
+}
+
+// ── Wildcard pattern matching ──────────────────────────────────────────
+
+/// Match a name against a pattern that may contain `*` (any chars) and `?`
+/// (single char) wildcards.  Matching is case-insensitive and only ever
+/// applied to a single path component (file or directory name).
+fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.to_lowercase().chars().collect();
+    let n: Vec<char> = name.to_lowercase().chars().collect();
+    wildcard_match_inner(&p, &n)
+}
+
+fn wildcard_match_inner(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            // '*' matches zero or more characters
+            wildcard_match_inner(&pattern[1..], name)
+                || (!name.is_empty() && wildcard_match_inner(pattern, &name[1..]))
+        }
+        (Some('?'), Some(_)) => wildcard_match_inner(&pattern[1..], &name[1..]),
+        (Some(pc), Some(nc)) if *pc == *nc => wildcard_match_inner(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+/// Match `rel_path`, a path below the source root, against a path pattern
+/// (`^pattern` without its prefix): each component is matched like a name,
+/// and a `**` component matches any number of them, none included.
+fn path_pattern_matches(pattern: &str, rel_path: &str) -> bool {
+    let p: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+    let r: Vec<&str> = rel_path.split('/').filter(|c| !c.is_empty()).collect();
+    path_match_inner(&p, &r)
+}
+
+fn path_match_inner(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(&"**"), _) => {
+            path_match_inner(&pattern[1..], path) || (!path.is_empty() && path_match_inner(pattern, &path[1..]))
+        }
+        (Some(pc), Some(nc)) => wildcard_matches(pc, nc) && path_match_inner(&pattern[1..], &path[1..]),
+        _ => false,
+    }
+}
+
+/// Whether a file name passes the include-only patterns.  With no include
+/// patterns every file passes; otherwise the union of the patterns applies.
+fn is_included(include_patterns: &[String], name: &str) -> bool {
+    include_patterns.is_empty() || include_patterns.iter().any(|pat| wildcard_matches(pat, name))
+}
+
+// ── File collection (shared by local & remote workers) ─────────────────
+
+/// The files a run takes from a local `source`, meeting links as `links`
+/// says.  `guard` watches a directory walk, which stops with its message
+/// if it says so.
+fn collect_files(
+    source: &SourceSelection,
+    patterns: &[String],
+    links: symlinks::Policy,
+    guard: &mut ScanGuard,
+) -> Result<(Vec<PathBuf>, usize, usize), String> {
+    symlinks::start_scan();
+    match source {
+        SourceSelection::None => Err("No source selected.".to_string()),
+        SourceSelection::Remote(..) | SourceSelection::RemoteFiles(..) | SourceSelection::RemoteItems(..) => {
+            Err("Remote source uses its own file listing.".to_string())
+        }
+        SourceSelection::Files(paths) => Ok((paths.clone(), 0, 0)),
+        // The ticked items replace the pattern results.
+        SourceSelection::DirectoryItems(_, files) => Ok((files.clone(), 0, 0)),
+        SourceSelection::Directory(src_dir) => {
+            activity::set(&format!("scanning {}", src_dir.display()));
+            // Exact directory exclusions: "/dirname"
+            let excluded_dirs: HashSet<String> = patterns
+                .iter()
+                .filter(|p| p.starts_with('/') && !p.starts_with("~/"))
+                .map(|p| p.trim_start_matches('/').to_string())
+                .collect();
+            // Exact file exclusions: "filename"
+            let excluded_files: HashSet<String> = patterns
+                .iter()
+                .filter(|p| !p.starts_with('/') && !p.starts_with('~') && !p.starts_with('+') && !p.starts_with('^'))
+                .cloned()
+                .collect();
+            // Wildcard directory patterns: "~/pattern" → "pattern"
+            let wildcard_dirs: Vec<String> = patterns
+                .iter()
+                .filter(|p| p.starts_with("~/"))
+                .map(|p| p[2..].to_string())
+                .collect();
+            // Wildcard file patterns: "~pattern" (but not "~/...")
+            let wildcard_files: Vec<String> = patterns
+                .iter()
+                .filter(|p| p.starts_with('~') && !p.starts_with("~/"))
+                .map(|p| p[1..].to_string())
+                .collect();
+            // Include-only file patterns: "+pattern"
+            let include_files: Vec<String> = patterns
+                .iter()
+                .filter_map(|p| p.strip_prefix('+').map(|p| p.to_string()))
+                .collect();
+            // Path patterns: "^pattern", against the path below the source
+            let path_patterns: Vec<String> = patterns
+                .iter()
+                .filter_map(|p| p.strip_prefix('^').map(|p| p.to_string()))
+                .collect();
+            let path_match = |path: &Path| {
+                let rel = path.strip_prefix(src_dir.as_path()).unwrap_or(path).to_string_lossy();
+                path_patterns.iter().find(|pat| path_pattern_matches(pat, &rel)).cloned()
+            };
+
+            let owner_filter = owners::current();
+
+            let src_dir = src_dir.clone();
+            let mut collected = Vec::new();
+            let mut excluded_file_count = 0usize;
+            let mut owner_filtered = 0usize;
+            // Each pruned folder counts once, however many files it held:
+            // it is never read, so they are not counted anywhere.
+            let excluded_dir_count = Cell::new(0usize);
+            let walk = WalkDir::new(&src_dir).follow_links(links == symlinks::Policy::Follow);
+            for entry in walk.into_iter().filter_entry(|e| {
+                if e.path() == src_dir.as_path() {
+                    return true;
+                }
+                if e.file_type().is_dir() {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    if excluded_dirs.contains(&name) {
+                        excluded_dir_count.set(excluded_dir_count.get() + 1);
+                        filereport::excluded(e.path(), &format!("folder excluded by /{}", name));
+                        return false;
+                    }
+                    if let Some(pat) = wildcard_dirs.iter().find(|pat| wildcard_matches(pat, &name)) {
+                        excluded_dir_count.set(excluded_dir_count.get() + 1);
+                        filereport::excluded(e.path(), &format!("folder excluded by ~/{}", pat));
+                        return false;
+                    }
+                    if let Some(pat) = path_match(e.path()) {
+                        excluded_dir_count.set(excluded_dir_count.get() + 1);
+                        filereport::excluded(e.path(), &format!("folder excluded by ^{}", pat));
+                        return false;
+                    }
+                    return true;
+                }
+                true
+            }) {
+                let e = match entry {
+                    Ok(e) => e,
+                    // Followed, a link pointing nowhere or to a folder above
+                    // it is an error of the walk.
+                    Err(err) => {
+                        if let Some(path) = err.path().filter(|p| symlinks::is_link(p)) {
+                            symlinks::note_left_out(path, if err.loop_ancestor().is_some() { symlinks::LOOP } else { symlinks::BROKEN });
+                        }
+                        continue;
+                    }
+                };
+                // Unfollowed, a link is taken like a file and then left out
+                // or kept as a link.
+                let is_link = e.file_type().is_symlink();
+                guard.visit(e.path(), e.depth(), e.file_type().is_file() || is_link).map_err(|stopped| stopped.message())?;
+                if e.file_type().is_file() || is_link {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    // Which pattern left the file out, for the detail report.
+                    let exclusion = if excluded_files.contains(&name) {
+                        Some(format!("excluded by {}", name))
+                    } else if let Some(pat) = wildcard_files.iter().find(|pat| wildcard_matches(pat, &name)) {
+                        Some(format!("excluded by ~{}", pat))
+                    } else if let Some(pat) = path_match(e.path()) {
+                        Some(format!("excluded by ^{}", pat))
+                    } else if !is_included(&include_files, &name) {
+                        Some("matched no include pattern".to_string())
+                    } else {
+                        None
+                    };
+                    if let Some(reason) = exclusion {
+                        excluded_file_count += 1;
+                        filereport::excluded(e.path(), &reason);
+                    } else if is_link && links == symlinks::Policy::Skip {
+                        symlinks::note_left_out(e.path(), symlinks::SKIPPED);
+                    } else if !owner_filter.is_empty() && !local_owner_kept(&owner_filter, &e) {
+                        owner_filtered += 1;
+                    } else {
+                        guard.found(e.metadata().map(|m| m.len()).unwrap_or(0));
+                        collected.push(e.into_path());
                     }
-                    refresh_exclusion_view(&view2, &list);
                 }
-            });
-        });
+            }
+            guard.flush();
+            owners::note_filtered(owner_filtered);
+            Ok((collected, excluded_file_count, excluded_dir_count.get()))
+        }
     }
+}
 
-    {
-        let excls = exclusions.clone();
-        let view = excl_view.clone();
-        btn_excl_clear.connect_clicked(move |_| {
-            excls.borrow_mut().clear();
-            view.buffer().set_text("");
-        });
+/// The skipped list a worker starts from: the links its scan left out
+/// (see `symlinks`).
+fn links_left_out() -> Details {
+    let mut skipped = Details::skipped();
+    for entry in symlinks::take() {
+        skipped.push(entry);
     }
+    skipped
+}
 
-    // ── Manual pattern buttons ────────────────────────────────────────
-    {
-        let excls = exclusions.clone();
-        let view = excl_view.clone();
-        let entry = pattern_entry.clone();
-        btn_add_file_pattern.connect_clicked(move |_| {
-            let text = entry.text().to_string().trim().to_string();
-            if text.is_empty() {
-                return;
-            }
-            // File wildcard pattern stored as "~pattern"
-            let pattern = format!("~{}", text);
-            let mut list = excls.borrow_mut();
-            if !list.contains(&pattern) {
-                list.push(pattern);
-            }
-            refresh_exclusion_view(&view, &list);
-            entry.set_text("");
-        });
+/// The guard for a worker's scan: it stops when the run is cancelled,
+/// asks through `tx` whether to go past one of `limits`, and sends what
+/// it has found as it goes.
+fn worker_scan_guard<'a>(limits: scanguard::Limits, cancel_flag: &'a CancelFlag, tx: &'a mpsc::Sender<WorkerMsg>) -> ScanGuard<'a> {
+    ScanGuard::with_limits(
+        limits,
+        move || cancel_flag.stopping(),
+        move |trip| {
+            let (reply_tx, reply_rx) = mpsc::channel();
+            let _ = tx.send(WorkerMsg::ScanLimit { trip: trip.clone(), reply: reply_tx });
+            let go_on = reply_rx.recv().unwrap_or(false);
+            debug_log(&format!("scan limit: {}; {}", trip.describe(), if go_on { "continuing" } else { "stopping" }));
+            go_on
+        },
+    )
+    .with_progress(move |files_found, bytes_found| {
+        let _ = tx.send(WorkerMsg::ScanProgress { files_found, bytes_found });
+    })
+}
+
+/// What a worker whose scan or remote listing failed sends: a cancelled
+/// run still ends as cancelled, having done nothing.
+fn scan_failure(error: String, cancel_flag: &CancelFlag) -> WorkerMsg {
+    if !cancel_flag.stopping() {
+        return WorkerMsg::Error(error);
     }
+    cancelled_before_transfer(Details::skipped(), 0, 0)
+}
 
-    {
-        let excls = exclusions.clone();
-        let view = excl_view.clone();
-        let entry = pattern_entry.clone();
-        btn_add_dir_pattern.connect_clicked(move |_| {
-            let text = entry.text().to_string().trim().to_string();
-            if text.is_empty() {
-                return;
-            }
-            // Dir wildcard pattern stored as "~/pattern"
-            let pattern = format!("~/{}", text);
-            let mut list = excls.borrow_mut();
-            if !list.contains(&pattern) {
-                list.push(pattern);
-            }
-            refresh_exclusion_view(&view, &list);
-            entry.set_text("");
-        });
+/// A run cancelled before its first file: nothing copied, with what the
+/// scan and preflight got through.  The UI names the phase it stopped in.
+fn cancelled_before_transfer(skipped: Details, excluded_files: usize, excluded_dirs: usize) -> WorkerMsg {
+    WorkerMsg::Cancelled(TransferReport::nothing_sent(skipped, excluded_files, excluded_dirs))
+}
+
+/// Called between the preflight and the first file: if the run has been
+/// cancelled, send its result and return `true` for the worker to stop.
+fn stopped_before_transfer(
+    cancel_flag: &CancelFlag,
+    skipped: &mut Details,
+    excluded_files: usize,
+    excluded_dirs: usize,
+    tx: &mpsc::Sender<WorkerMsg>,
+) -> bool {
+    if !cancel_flag.stopping() {
+        return false;
     }
+    let skipped = std::mem::replace(skipped, Details::skipped());
+    let _ = tx.send(cancelled_before_transfer(skipped, excluded_files, excluded_dirs));
+    true
+}
 
-    // ── Start button logic ────────────────────────────────────────────
-    let running = Rc::new(RefCell::new(false));
+/// Whether `--only-user` and the like keep the scanned file `entry`; one
+/// that can't be stat'ed is kept, for the copy to report.
+fn local_owner_kept(filter: &owners::OwnerFilter, entry: &walkdir::DirEntry) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    entry.metadata().map_or(true, |m| filter.keeps(m.uid(), m.gid(), None, None))
+}
 
-    btn_start.connect_clicked({
-        let source_selection = source_selection.clone();
-        let src_entry = src_entry.clone();
-        let dst_entry = dst_entry.clone();
-        let chk_move = chk_move.clone();
-        let chk_folders_files = chk_folders_files.clone();
-        let chk_overwrite = chk_overwrite.clone();
-        let chk_rename = chk_rename.clone();
-        let chk_strip_spaces = chk_strip_spaces.clone();
-        let chk_rsync = chk_rsync.clone();
-        let exclusions = exclusions.clone();
-        let progress_bar = progress_bar.clone();
-        let status_label = status_label.clone();
-        let btn_start = btn_start.clone();
-        let btn_cancel = btn_cancel.clone();
-        let running = running.clone();
-        let window = window.clone();
+// ── Planning one local file ────────────────────────────────────────────
+
+/// What a local run does with one source file, decided before anything is
+/// written.  `run_worker` acts on it and `--dry-run` prints it.
+#[derive(Debug)]
+enum LocalAction {
+    /// Nothing is there yet.
+    Copy(PathBuf),
+    /// A different version is there and is replaced.
+    Overwrite(PathBuf),
+    /// A different version is there, so the file takes the next free name.
+    Rename(PathBuf),
+    /// The same contents are already there.
+    Identical(PathBuf),
+    /// A different version is there and is kept.
+    SkipExisting(PathBuf),
+    /// A different version is there, not older than the source, and is
+    /// kept by Newest wins.
+    SkipNewer(PathBuf),
+    /// The destination is the source file itself.
+    SameFile(PathBuf),
+    /// The file has no place under the destination.
+    Unplaced(destpath::Unplaced),
+    /// What is there could not be compared with the source.
+    CompareFailed(PathBuf, std::io::Error),
+}
 
-        move |_| {
-            if *running.borrow() {
-                return;
+impl LocalAction {
+    /// The `action` of a `--dry-run` entry; an identical file is a move's
+    /// source to delete.
+    fn name(&self, do_move: bool) -> &'static str {
+        match self {
+            LocalAction::Copy(_) => "copy",
+            LocalAction::Overwrite(_) => "overwrite",
+            LocalAction::Rename(_) => "rename",
+            LocalAction::Identical(_) if do_move => "delete-source",
+            LocalAction::Identical(_) => "skip-identical",
+            LocalAction::SkipExisting(_) => "skip-existing",
+            LocalAction::SkipNewer(_) => "skip-newer",
+            LocalAction::SameFile(_) => "skip-same-file",
+            LocalAction::Unplaced(_) => "skip-unplaced",
+            LocalAction::CompareFailed(..) => "error",
+        }
+    }
+
+    fn dest(&self) -> Option<&Path> {
+        match self {
+            LocalAction::Copy(dest)
+            | LocalAction::Overwrite(dest)
+            | LocalAction::Rename(dest)
+            | LocalAction::Identical(dest)
+            | LocalAction::SkipExisting(dest)
+            | LocalAction::SkipNewer(dest)
+            | LocalAction::SameFile(dest)
+            | LocalAction::CompareFailed(dest, _) => Some(dest),
+            LocalAction::Unplaced(_) => None,
+        }
+    }
+}
+
+/// Where `input.file` goes, renamed below the destination folder, and
+/// what happens to whatever is there, a link kept as a link when `links`
+/// says so.  `taken` says whether a path is in use and `compare` whether
+/// the source matches it, so a plan can count the files it has already
+/// placed.
+fn plan_local_file(
+    input: &PlanInput,
+    renamer: &Renamer,
+    conflict_mode: ConflictMode,
+    links: symlinks::Policy,
+    taken: impl Fn(&Path) -> bool,
+    compare: impl FnOnce(&Path, &Path) -> std::io::Result<bool>,
+) -> LocalAction {
+    let file = input.file;
+    let dest = match destpath::build_destination(input, |rel| renamer.rename_path(rel, modified(file))) {
+        Ok(dest) => dest.into_local(),
+        Err(why) => return LocalAction::Unplaced(why),
+    };
+    // A preserved link goes across as a link: a link already there is
+    // taken even when it points nowhere, and where it points is no concern.
+    let link = symlinks::preserved(links, file);
+    if !(taken(&dest) || link && symlinks::is_link(&dest)) {
+        return LocalAction::Copy(dest);
+    }
+    // Through a symlinked or bind-mounted destination, or a hard link,
+    // writing the destination would truncate the source.
+    if !link && same_file(file, &dest) {
+        return LocalAction::SameFile(dest);
+    }
+    match compare(file, &dest) {
+        Ok(true) => LocalAction::Identical(dest),
+        Ok(false) => match conflict_mode {
+            ConflictMode::Skip => LocalAction::SkipExisting(dest),
+            ConflictMode::Rename => LocalAction::Rename(destpath::unique_local_where(&dest, taken)),
+            ConflictMode::Overwrite => LocalAction::Overwrite(dest),
+            ConflictMode::Newer if source_is_newer(modified(file).map(Timestamp::of), modified(&dest).map(Timestamp::of)) => {
+                LocalAction::Overwrite(dest)
             }
+            ConflictMode::Newer => LocalAction::SkipNewer(dest),
+        },
+        Err(e) => LocalAction::CompareFailed(dest, e),
+    }
+}
 
-            let src_text = src_entry.text().to_string().trim().to_string();
-            let dst = dst_entry.text().to_string();
+/// Where `input.file` goes before anything there is taken into account,
+/// or `None` if it has no place.  The rename is not recorded: the file is
+/// planned again when its turn comes.
+fn planned_local_dest(input: &PlanInput, renamer: &Renamer) -> Option<PathBuf> {
+    let file = input.file;
+    destpath::build_destination(input, |rel| renamer.apply_path(rel, modified(file))).ok().map(|dest| dest.into_local())
+}
 
-            // Determine source: if the entry contains text, parse it;
-            // otherwise fall back to the source_selection set by browse buttons.
-            let source_sel = if !src_text.is_empty() {
-                let (host, path) = parse_destination(&src_text);
-                match host {
-                    Some(h) => SourceSelection::Remote(h, path),
-                    None => {
-                        // Local path — could be a file or directory
-                        let p = PathBuf::from(&path);
-                        if p.is_file() {
-                            SourceSelection::Files(vec![p])
-                        } else {
-                            SourceSelection::Directory(p)
-                        }
-                    }
-                }
-            } else {
-                source_selection.borrow().clone()
-            };
+// ── Worker thread (local) ──────────────────────────────────────────────
 
-            match &source_sel {
-                SourceSelection::None => {
-                    status_label.set_text("Please select a source (folder, files, or remote).");
-                    return;
-                }
-                SourceSelection::Directory(p) if p.to_string_lossy() == dst => {
-                    status_label.set_text("Source and destination must be different.");
-                    return;
-                }
-                _ => {}
-            }
+/// How one file of a standard local run went on a thread of the pool.
+struct LocalDone {
+    /// The file's place in the plan.
+    index: usize,
+    dest: PathBuf,
+    /// Ok(false): transferred, but left unverified at the user's request.
+    result: std::io::Result<bool>,
+    /// How a move got there.
+    moved: Option<MoveMethod>,
+    /// Read-only sources kept (see `remove_moved_source`).
+    ro_kept: usize,
+    /// A verification for the hash cache to remember.
+    matched: Option<hashcache::Matched>,
+}
 
-            if dst.is_empty() {
-                status_label.set_text("Please select or type a destination directory.");
-                return;
-            }
+fn run_worker(
+    source: SourceSelection,
+    dst: String,
+    do_move: bool,
+    move_strategy: MoveStrategy,
+    conflict_mode: ConflictMode,
+    renamer: &Renamer,
+    transfer_mode: TransferMode,
+    keep_parents: usize,
+    patterns: &[String],
+    mut hash_cache: Option<HashCache>,
+    opts: &RunOptions,
+    cancel_flag: Arc<CancelFlag>,
+    tx: mpsc::Sender<WorkerMsg>,
+) {
+    let dst_path = PathBuf::from(&dst);
 
-            let do_move = chk_move.is_active();
-            let conflict_mode = if chk_overwrite.is_active() {
-                ConflictMode::Overwrite
-            } else if chk_rename.is_active() {
-                ConflictMode::Rename
-            } else {
-                ConflictMode::Skip
-            };
-            let strip_spaces = chk_strip_spaces.is_active();
-            let transfer_mode = if chk_folders_files.is_active() {
-                TransferMode::FoldersAndFiles
-            } else {
-                TransferMode::FilesOnly
-            };
-            let transfer_method = if chk_rsync.is_active() {
-                TransferMethod::Rsync
-            } else {
-                TransferMethod::Standard
-            };
+    // Create destination directory if it doesn't exist
+    if !dst_path.exists() {
+        if let Err(e) = folders::create_all(&dst_path) {
+            let _ = tx.send(WorkerMsg::Error(format!(
+                "Failed to create destination directory: {}",
+                e
+            )));
+            return;
+        }
+    }
+    if !settle_stale_artifacts(&dst_path, &tx) {
+        return;
+    }
 
-            let patterns: Vec<String> = exclusions.borrow().clone();
+    // Collect the files to process
+    let (files, excluded_files, excluded_dirs) = match collect_files(&source, patterns, opts.symlinks, &mut worker_scan_guard(opts.scan_limits, &cancel_flag, &tx)) {
+        Ok(v) => v,
+        Err(e) => {
+            let _ = tx.send(scan_failure(e, &cancel_flag));
+            return;
+        }
+    };
+    // The links the scan left out lead the skipped list.
+    let mut skipped = links_left_out();
+    if cancel_flag.stopping() {
+        let _ = tx.send(cancelled_before_transfer(skipped, excluded_files, excluded_dirs));
+        return;
+    }
 
-            *running.borrow_mut() = true;
-            btn_start.set_sensitive(false);
-            btn_cancel.set_visible(true);
-            progress_bar.set_fraction(0.0);
-            progress_bar.set_text(Some("Scanning…"));
-            status_label.set_text("");
+    let total = files.len();
+    if total == 0 {
+        let _ = tx.send(WorkerMsg::Finished(TransferReport::nothing_sent(skipped, excluded_files, excluded_dirs)));
+        return;
+    }
 
-            // Cancel flag shared between UI and worker thread
-            let cancel_flag = Arc::new(AtomicBool::new(false));
+    // Determine the source directory (only relevant for "Folders and files" mode)
+    let src_dir = match &source {
+        SourceSelection::Directory(d) | SourceSelection::DirectoryItems(d, _) => Some(d.clone()),
+        _ => None,
+    };
+    let parent_levels = parent_levels(transfer_mode, src_dir.is_none(), keep_parents);
+    if let Err(e) = settle_collisions(
+        files.iter().map(|file| PlanInput { file, src_dir: src_dir.as_deref(), transfer_mode, parent_levels, dest: Dest::Local(&dst_path) }),
+        renamer,
+        false,
+        opts.on_collision,
+    ) {
+        let _ = tx.send(WorkerMsg::Error(e));
+        return;
+    }
 
-            // Wire Cancel button
-            {
-                let cancel_flag_c = cancel_flag.clone();
-                let btn_cancel_c = btn_cancel.clone();
-                btn_cancel_c.connect_clicked(move |btn| {
-                    cancel_flag_c.store(true, Ordering::SeqCst);
-                    btn.set_sensitive(false);
-                    btn.set_label("Cancelling…");
-                });
-            }
+    let Some(files) = preflight_local_tree(files, src_dir.as_deref(), transfer_mode, &dst_path, renamer, &mut skipped, &tx)
+    else {
+        return;
+    };
+    if stopped_before_transfer(&cancel_flag, &mut skipped, excluded_files, excluded_dirs, &tx) {
+        return;
+    }
+    let total = files.len();
 
-            // Channel for worker → UI communication
-            let (tx, rx) = mpsc::channel::<WorkerMsg>();
+    let sizes: Vec<Option<u64>> = files.iter().map(|f| fs::metadata(f).ok().map(|m| m.len())).collect();
+    // A move within one filesystem renames, needing no room.
+    let renames = do_move && source_root(&source).and_then(|r| same_device(&r, &dst_path)) == Some(true);
+    if !renames && !room_for_plan(&sizes, opts.ignore_free_space, || space::local_free(&dst_path), &tx) {
+        return;
+    }
+    let mut stats = RunStats::new(
+        source.group_root().as_deref(),
+        files.iter().map(shown).zip(sizes.iter().copied()),
+    );
+    send_plan(&tx, sizes);
 
-            // Spawn worker thread
-            let dst_clone = dst.clone();
-            let cancel_flag_w = cancel_flag.clone();
-            thread::spawn(move || {
-                let (dst_host, dest_path) = parse_destination(&dst_clone);
-                let src_is_remote = matches!(&source_sel, SourceSelection::Remote(_, _));
-                match (src_is_remote, dst_host, transfer_method) {
-                    // Remote source → remote destination
-                    (true, Some(dhost), TransferMethod::Standard) => {
-                        if let SourceSelection::Remote(shost, spath) = &source_sel {
-                            run_remote_to_remote_worker(
-                                shost, &spath, &dhost, &dest_path, do_move, conflict_mode,
-                                strip_spaces, transfer_mode, &patterns, cancel_flag_w, tx,
-                            );
-                        }
+    let mut copied = 0usize;
+    let mut errors = Details::errors();
+    let mut ro_kept = 0usize;
+    let mut move_stats = MoveStats::default();
+    // Renames only work within one filesystem; go straight to copying
+    // when the source and destination are known to be on different devices.
+    let try_rename = source_root(&source).and_then(|r| same_device(&r, &dst_path)) != Some(false);
+    // A two-phase move copies every file and deletes the sources at the end.
+    let defer = do_move && move_strategy == MoveStrategy::AllThenDelete;
+    let mut deferred: Vec<PathBuf> = Vec::new();
+    let mut unverified: Vec<String> = Vec::new();
+    // With --preserve, the folders made for the files, given their
+    // sources' times once everything is in.
+    let mut new_dirs: Vec<preserve::NewDir> = Vec::new();
+
+    let front = parallel::Front::new(total);
+    let progress = |i: usize| {
+        let _ = tx.send(WorkerMsg::Progress { done: front.end(i), total, file: files[i].to_string_lossy().to_string() });
+    };
+    let caching = hash_cache.is_some();
+    // The copy or move of one file and its verification, on a thread of
+    // the pool
+    let transfer = |(i, dest_file): (usize, PathBuf)| {
+        let file_path = &files[i];
+        deadline::begin(i);
+        // The bar shows the bytes of the first file still to end.
+        let on_bytes = |b| {
+            if front.is_first(i) {
+                let _ = tx.send(WorkerMsg::FileBytes { bytes: b });
+            }
+        };
+        let (mut ro_kept, mut moved, mut matched) = (0, None, None);
+        if symlinks::preserved(opts.symlinks, file_path) {
+            // The link itself goes across: no bytes to copy or compare.
+            let result = match do_move && !defer {
+                true => move_link(file_path, &dest_file, try_rename, &mut ro_kept).map(|method| {
+                    moved = Some(method);
+                    true
+                }),
+                false => symlinks::recreate(file_path, &dest_file).map(|()| true),
+            };
+            return LocalDone { index: i, dest: dest_file, result, moved, ro_kept, matched };
+        }
+        let result = if do_move && !defer {
+            // Rename where possible, otherwise copy + verify + delete original
+            move_file(file_path, &dest_file, try_rename, &mut ro_kept, opts, on_bytes, || start_verify(&cancel_flag, &tx)).map(
+                |method| {
+                    debug_log(&format!("moved {} ({:?})", shown(file_path), method));
+                    moved = Some(method);
+                    method != MoveMethod::Unverified
+                },
+            )
+        } else {
+            // Copy + verify
+            match copy_file_chunked(file_path, &dest_file, opts, on_bytes) {
+                Ok(_) => match verify_unless_skipped(&cancel_flag, &tx, i, || match caching {
+                    true => hashcache::compare_in_full(file_path, &dest_file).map(|(same, m)| {
+                        matched = m;
+                        same
+                    }),
+                    false => verify_copy(file_path, &dest_file, opts),
+                }) {
+                    None => Ok(false),
+                    Some(Ok(true)) => Ok(true),
+                    Some(Ok(false)) => {
+                        let _ = fs::remove_file(&dest_file);
+                        Err(std::io::Error::new(
+                            std::io::ErrorKind::Other,
+                            "integrity check failed — copy removed",
+                        ))
                     }
-                    (true, Some(dhost), TransferMethod::Rsync) => {
-                        if let SourceSelection::Remote(shost, spath) = &source_sel {
-                            run_remote_to_remote_rsync_worker(
-                                shost, &spath, &dhost, &dest_path, do_move, conflict_mode,
-                                strip_spaces, transfer_mode, &patterns, cancel_flag_w, tx,
-                            );
+                    Some(Err(e)) => Err(std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        format!("verification error: {}", e),
+                    )),
+                },
+                Err(e) => Err(e),
+            }
+        };
+        LocalDone { index: i, dest: dest_file, result, moved, ro_kept, matched }
+    };
+
+    // Destinations being written now
+    let mut in_flight: HashSet<PathBuf> = HashSet::new();
+    let mut stopped = false;
+    parallel::run(opts.workers, transfer, |lanes| {
+        let mut items = files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| {
+                // Where the file goes, and what to do about what is there
+                let input = PlanInput {
+                    file,
+                    src_dir: src_dir.as_deref(),
+                    transfer_mode,
+                    parent_levels,
+                    dest: Dest::Local(&dst_path),
+                };
+                let planned = planned_local_dest(&input, renamer);
+                (i, input, planned)
+            })
+            .peekable();
+        loop {
+            // Take back a finished file when every lane is busy, when the
+            // next file is bound for a path still being written, or when
+            // there is nothing more to start.
+            let next = items.peek().filter(|_| !stopped);
+            if lanes.is_full() || next.is_none_or(|(_, _, planned)| planned.as_ref().is_some_and(|p| in_flight.contains(p)))
+            {
+                let Some(done) = lanes.wait() else {
+                    break;
+                };
+                in_flight.remove(&done.dest);
+                let file_path = &files[done.index];
+                ro_kept += done.ro_kept;
+                if let Some(method) = done.moved {
+                    move_stats.record(method);
+                }
+                if let (Some(cache), Some(matched)) = (hash_cache.as_mut(), done.matched) {
+                    cache.remember(matched);
+                }
+                match done.result {
+                    Ok(true) => {
+                        copied += 1;
+                        stats.copied(&shown(file_path));
+                        note_copied(reverify::Location::Local(file_path.clone()), reverify::Location::Local(done.dest), opts);
+                        if defer {
+                            deferred.push(file_path.clone());
                         }
                     }
-                    // Remote source → local destination
-                    (true, None, transfer_method) => {
-                        if let SourceSelection::Remote(shost, spath) = &source_sel {
-                            run_remote_to_local_worker(
-                                shost, &spath, &dest_path, do_move, conflict_mode,
-                                strip_spaces, transfer_mode, &patterns, transfer_method, cancel_flag_w, tx,
-                            );
+                    Ok(false) => unverified.push(shown(file_path)),
+                    Err(e) => errors.push(format!("{}: {}", shown(file_path), e)),
+                }
+                progress(done.index);
+                continue;
+            }
+            let Some((i, input, _)) = items.next() else {
+                break;
+            };
+            let file_path = &files[i];
+            if before_file(i, &tx, &cancel_flag) {
+                skipped.push(format!("{}: {}", shown(file_path), quota::SKIPPED));
+                front.end(i);
+                continue;
+            }
+            if cancel_flag.stopping() {
+                // Let the files in flight finish, then stop
+                stopped = true;
+                continue;
+            }
+            let compare = |src: &Path, dest: &Path| match hash_cache.as_mut() {
+                _ if symlinks::preserved(opts.symlinks, src) => symlinks::same_target(src, dest),
+                Some(cache) => cache.files_identical(src, dest),
+                None => files_are_identical(src, dest),
+            };
+            let taken = |p: &Path| p.exists() || in_flight.contains(p);
+            let dest_file = match plan_local_file(&input, renamer, conflict_mode, opts.symlinks, taken, compare) {
+                LocalAction::Copy(dest) | LocalAction::Overwrite(dest) | LocalAction::Rename(dest) => dest,
+                LocalAction::Unplaced(why) => {
+                    skipped.push(format!("{}: {}", shown(file_path), why.reason()));
+                    front.end(i);
+                    continue;
+                }
+                LocalAction::SameFile(_) => {
+                    skipped.push(format!("{}: {}", shown(file_path), SAME_FILE_NOTE));
+                    progress(i);
+                    continue;
+                }
+                LocalAction::Identical(dest_file) => {
+                    // Destination is already identical — no copy needed
+                    if defer {
+                        deferred.push(file_path.clone());
+                        copied += 1;
+                        stats.copied(&shown(file_path));
+                        filereport::copied(&reverify::Location::Local(file_path.clone()), &reverify::Location::Local(dest_file), opts);
+                        move_stats.identical += 1;
+                    } else if do_move {
+                        // Just delete the source
+                        if let Err(e) = remove_moved_source(file_path, &mut ro_kept) {
+                            errors.push(format!("{}: identical at destination but failed to delete source: {}", shown(file_path), e));
+                        } else {
+                            copied += 1;
+                            stats.copied(&shown(file_path));
+                            filereport::copied(&reverify::Location::Local(file_path.clone()), &reverify::Location::Local(dest_file), opts);
+                            move_stats.identical += 1;
                         }
+                    } else {
+                        skipped.push(format!("{}: identical at destination", shown(file_path)));
                     }
-                    // Local source → remote destination
-                    (false, Some(host), TransferMethod::Standard) => run_remote_worker(
-                        source_sel, &host, &dest_path, do_move, conflict_mode,
-                        strip_spaces, transfer_mode, &patterns, cancel_flag_w, tx,
-                    ),
-                    (false, Some(host), TransferMethod::Rsync) => run_remote_rsync_worker(
-                        source_sel, &host, &dest_path, do_move, conflict_mode,
-                        strip_spaces, transfer_mode, &patterns, cancel_flag_w, tx,
-                    ),
-                    // Local source → local destination
-                    (false, None, TransferMethod::Rsync) => run_local_rsync_worker(
-                        source_sel, dest_path, do_move, conflict_mode,
-                        strip_spaces, transfer_mode, &patterns, cancel_flag_w, tx,
-                    ),
-                    (false, None, TransferMethod::Standard) => run_worker(
-                        source_sel, dest_path, do_move, conflict_mode,
-                        strip_spaces, transfer_mode, &patterns, cancel_flag_w, tx,
-                    ),
+                    progress(i);
+                    continue;
                 }
-            });
-
-            // Poll for messages on the glib main loop
-            let progress_bar_c = progress_bar.clone();
-            let status_label_c = status_label.clone();
-            let btn_start_c = btn_start.clone();
-            let btn_cancel_c = btn_cancel.clone();
-            let window_c = window.clone();
-            let running_c = running.clone();
-
-            glib::timeout_add_local(std::time::Duration::from_millis(50), move || {
-                while let Ok(msg) = rx.try_recv() {
-                    match msg {
-                        WorkerMsg::Progress { done, total, file } => {
-                            let frac = if total > 0 {
-                                done as f64 / total as f64
-                            } else {
-                                0.0
-                            };
-                            progress_bar_c.set_fraction(frac);
-                            let filename = Path::new(&file)
-                                .file_name()
-                                .map(|n| n.to_string_lossy().to_string())
-                                .unwrap_or(file);
-                            progress_bar_c
-                                .set_text(Some(&format!("{}/{} — {}", done, total, filename)));
-                        }
-                        WorkerMsg::Finished {
-                            copied,
-                            skipped,
-                            excluded_files,
-                            excluded_dirs,
-                            errors,
-                        } => {
-                            progress_bar_c.set_fraction(1.0);
-                            let verb = if do_move { "Moved" } else { "Copied" };
-                            let mut excl_parts = Vec::new();
-                            if excluded_files > 0 {
-                                excl_parts.push(format!("{} file(s)", excluded_files));
-                            }
-                            if excluded_dirs > 0 {
-                                excl_parts.push(format!("{} dir(s)", excluded_dirs));
-                            }
-                            let excl_str = if excl_parts.is_empty() {
-                                "0".to_string()
-                            } else {
-                                excl_parts.join(", ")
-                            };
-                            let summary = format!(
-                                "{} {} file(s), {} skipped, {} excluded.",
-                                verb, copied, skipped.len(), excl_str
-                            );
-                            progress_bar_c.set_text(Some("Complete"));
-                            status_label_c.set_text(&summary);
-                            btn_start_c.set_sensitive(true);
-                            btn_cancel_c.set_visible(false);
-                            btn_cancel_c.set_sensitive(true);
-                            btn_cancel_c.set_label("Cancel");
-                            *running_c.borrow_mut() = false;
-
-                            let title = if errors.is_empty() && skipped.is_empty() {
-                                "Complete"
-                            } else if !errors.is_empty() {
-                                "Completed with errors"
-                            } else {
-                                "Completed with skipped files"
-                            };
-
-                            // Combine skipped and errors for the dialog
-                            let mut all_notes = Vec::new();
-                            if !skipped.is_empty() {
-                                all_notes.push(format!("Skipped ({}):", skipped.len()));
-                                all_notes.extend(skipped);
-                            }
-                            if !errors.is_empty() {
-                                all_notes.push(format!("Errors ({}):", errors.len()));
-                                all_notes.extend(errors);
-                            }
-                            show_result_dialog(&window_c, title, &summary, &all_notes);
-
-                            return glib::ControlFlow::Break;
-                        }
-                        WorkerMsg::Error(e) => {
-                            progress_bar_c.set_fraction(0.0);
-                            progress_bar_c.set_text(Some("Error"));
-                            status_label_c.set_text(&e);
-                            btn_start_c.set_sensitive(true);
-                            btn_cancel_c.set_visible(false);
-                            btn_cancel_c.set_sensitive(true);
-                            btn_cancel_c.set_label("Cancel");
-                            *running_c.borrow_mut() = false;
-
-                            show_result_dialog(&window_c, "Error", &e, &[]);
-
-                            return glib::ControlFlow::Break;
-                        }
-                        WorkerMsg::Cancelled {
-                            copied,
-                            skipped,
-                            excluded_files,
-                            excluded_dirs,
-                            errors,
-                        } => {
-                            let verb = if do_move { "Moved" } else { "Copied" };
-                            let mut excl_parts = Vec::new();
-                            if excluded_files > 0 {
-                                excl_parts.push(format!("{} file(s)", excluded_files));
-                            }
-                            if excluded_dirs > 0 {
-                                excl_parts.push(format!("{} dir(s)", excluded_dirs));
-                            }
-                            let excl_str = if excl_parts.is_empty() {
-                                "0".to_string()
-                            } else {
-                                excl_parts.join(", ")
-                            };
-                            let summary = format!(
-                                "Cancelled. {} {} file(s) before stopping, {} skipped, {} excluded.",
-                                verb, copied, skipped.len(), excl_str
-                            );
-                            progress_bar_c.set_text(Some("Cancelled"));
-                            status_label_c.set_text(&summary);
-                            btn_start_c.set_sensitive(true);
-                            btn_cancel_c.set_visible(false);
-                            btn_cancel_c.set_sensitive(true);
-                            btn_cancel_c.set_label("Cancel");
-                            *running_c.borrow_mut() = false;
-
-                            let mut all_notes = Vec::new();
-                            if !skipped.is_empty() {
-                                all_notes.push(format!("Skipped ({}):", skipped.len()));
-                                all_notes.extend(skipped);
-                            }
-                            if !errors.is_empty() {
-                                all_notes.push(format!("Errors ({}):", errors.len()));
-                                all_notes.extend(errors);
-                            }
-                            show_result_dialog(&window_c, "Cancelled", &summary, &all_notes);
+                LocalAction::SkipExisting(_) => {
+                    skipped.push(format!("{}: different version exists at destination", shown(file_path)));
+                    progress(i);
+                    continue;
+                }
+                LocalAction::SkipNewer(_) => {
+                    skipped.push(format!("{}: {}", shown(file_path), DEST_NEWER_NOTE));
+                    progress(i);
+                    continue;
+                }
+                LocalAction::CompareFailed(_, e) => {
+                    errors.push(format!("{}: could not compare with destination: {}", shown(file_path), e));
+                    progress(i);
+                    continue;
+                }
+            };
 
-                            return glib::ControlFlow::Break;
-                        }
-                    }
+            // Create parent directory in destination
+            if let Some(parent) = dest_file.parent() {
+                let made = if opts.preserve { preserve::new_dirs(file_path, &dest_file) } else { Vec::new() };
+                if let Err(e) = folders::create_all(parent) {
+                    errors.push(format!("{}: {}", shown(file_path), e));
+                    front.end(i);
+                    continue;
                 }
-                glib::ControlFlow::Continue
-            });
+                new_dirs.extend(made);
+            }
+            in_flight.insert(dest_file.clone());
+            lanes.start((i, dest_file));
         }
     });
 
-    window.present();
-}
-
-// ── Helper: directory chooser row (editable) ──────────────────────────
-
-fn dir_row_editable(label_text: &str) -> (GtkBox, Button, Entry) {
-    let row = GtkBox::new(Orientation::Horizontal, 8);
-    let label = Label::new(Some(label_text));
-    label.set_width_chars(20);
-    label.set_xalign(0.0);
+    if stopped {
+        preserve::apply_to_dirs(&new_dirs, folders::mode().is_some());
+        push_read_only_summary(&mut errors, ro_kept);
+        if do_move {
+            let _ = tx.send(WorkerMsg::MoveStats(move_stats));
+        }
+        if defer {
+            let _ = tx.send(WorkerMsg::Deletion(Deletion::Skipped));
+        }
+        if let Some(cache) = hash_cache.take() {
+            let _ = tx.send(WorkerMsg::HashCache(cache.save()));
+        }
+        if !unverified.is_empty() {
+            let _ = tx.send(WorkerMsg::Unverified(unverified));
+        }
+        let _ = tx.send(WorkerMsg::Cancelled(TransferReport {
+            copied,
+            skipped,
+            excluded_files,
+            excluded_dirs,
+            failed: failed_sources(files.iter().cloned(), errors.shown(), None),
+            errors,
+            groups: Vec::new(),
+        }));
+        return;
+    }
 
-    let entry = Entry::new();
-    entry.set_hexpand(true);
-    entry.set_placeholder_text(Some("Local path or host:/remote/path"));
+    send_phase(&tx, Phase::Finalizing);
+    preserve::apply_to_dirs(&new_dirs, folders::mode().is_some());
+    let cancelled = defer
+        && delete_deferred_sources(&deferred, unverified.len(), None, &[], &mut errors, &mut ro_kept, &cancel_flag, &tx);
+    push_read_only_summary(&mut errors, ro_kept);
+    if !cancelled {
+        tidy_moved_source(&source, do_move, opts.remove_source_root, &files, &errors);
+    }
+    if do_move {
+        let _ = tx.send(WorkerMsg::MoveStats(move_stats));
+    }
+    if let Some(cache) = hash_cache {
+        let _ = tx.send(WorkerMsg::HashCache(cache.save()));
+    }
+    let groups = stats.finish(skipped.shown(), errors.shown());
+    let failed = failed_sources(files.iter().cloned(), errors.shown(), None);
+    if !unverified.is_empty() {
+        let _ = tx.send(WorkerMsg::Unverified(unverified));
+    }
+    let report = TransferReport { copied, skipped, excluded_files, excluded_dirs, errors, failed, groups };
+    let _ = tx.send(if cancelled { WorkerMsg::Cancelled(report) } else { WorkerMsg::Finished(report) });
+}
 
-    let btn = Button::with_label("Browse…");
+// ── Worker thread (local via rsync) ────────────────────────────────────
 
-    row.append(&label);
-    row.append(&entry);
-    row.append(&btn);
+/// How rsync dealt with one file of a call.
+enum RsyncSent {
+    /// Reported sent: its copy is verified like any other.
+    Reported,
+    /// Not reported by a call that succeeded: unchanged, or kept by
+    /// `rsync_conflict_args`, so a copy that differs is left alone.
+    Quiet,
+    /// Not named by a call that failed, but something is there: a copy
+    /// that matches counts, anything else is this failure.
+    Unclear(String),
+    Failed(String),
+}
 
-    (row, btn, entry)
+/// What rsync is told about a file already at its destination.  The plan
+/// has decided for every file; this holds to it for one written since.
+fn rsync_conflict_args(conflict_mode: ConflictMode) -> &'static [&'static str] {
+    match conflict_mode {
+        ConflictMode::Skip => &["--ignore-existing"],
+        ConflictMode::Newer => &["--update"],
+        ConflictMode::Overwrite | ConflictMode::Rename => &[],
+    }
 }
 
-// ── Helper: result dialog with scrollable error list ───────────────────
+/// `dir` as an rsync argument naming its contents.
+fn rsync_dir_arg(dir: &Path) -> std::ffi::OsString {
+    let mut arg = dir.as_os_str().to_os_string();
+    arg.push("/");
+    arg
+}
 
-fn show_result_dialog(parent: &ApplicationWindow, title: &str, summary: &str, errors: &[String]) {
-    let dialog = Window::builder()
-        .title(title)
-        .modal(true)
-        .transient_for(parent)
-        .default_width(500)
-        .default_height(if errors.is_empty() { 150 } else { 400 })
-        .resizable(true)
-        .build();
+/// Before each rsync call of a planned chunk: wait out a pause, which a
+/// call under way can't honour, and say whether the run is to stop.
+fn stop_before_call(cancel_flag: &CancelFlag) -> bool {
+    cancel_flag.wait_while_paused();
+    cancel_flag.stopping()
+}
 
-    let vbox = GtkBox::new(Orientation::Vertical, 12);
-    vbox.set_margin_top(16);
-    vbox.set_margin_bottom(16);
-    vbox.set_margin_start(16);
-    vbox.set_margin_end(16);
+/// Run `rsync`, given its transfer options, over `batch` into `dest` (the
+/// folder as rsync is to name it).  Returns how the call ended and the
+/// files it reported sent, each passed to `progress` as it comes.
+fn send_rsync_batch(
+    rsync: &mut Command,
+    batch: &rsync::Batch,
+    conflict_mode: ConflictMode,
+    dest: std::ffi::OsString,
+    progress: impl Fn(usize),
+) -> (std::io::Result<std::process::Output>, HashSet<usize>) {
+    let mut sent = HashSet::new();
+    let list = match rsync::FileList::write(batch) {
+        Ok(list) => list,
+        Err(e) => return (Err(e), sent),
+    };
+    let out = rsync
+        .args(rsync_conflict_args(conflict_mode))
+        .args(list.args())
+        .arg(rsync_dir_arg(&batch.from))
+        .arg(dest)
+        .tracked_lines(|line| {
+            if let Some(i) = list.sent(line) {
+                sent.insert(i);
+                progress(i);
+            }
+        });
+    (out, sent)
+}
 
-    // Summary label (large & bold)
-    let summary_label = Label::new(None);
-    summary_label.set_halign(Align::Start);
-    summary_label.set_wrap(true);
-    summary_label.set_markup(&format!("<big><b>{}</b></big>", glib::markup_escape_text(summary)));
-    vbox.append(&summary_label);
+fn run_local_rsync_worker(
+    source: SourceSelection,
+    dst: String,
+    do_move: bool,
+    move_strategy: MoveStrategy,
+    conflict_mode: ConflictMode,
+    renamer: &Renamer,
+    transfer_mode: TransferMode,
+    keep_parents: usize,
+    patterns: &[String],
+    opts: &RunOptions,
+    cancel_flag: Arc<CancelFlag>,
+    tx: mpsc::Sender<WorkerMsg>,
+) {
+    let dst_path = PathBuf::from(&dst);
 
-    // Scrollable error list
-    if !errors.is_empty() {
-        let error_heading = Label::new(None);
-        error_heading.set_halign(Align::Start);
-        error_heading.set_markup(&format!("<b>{} error(s):</b>", errors.len()));
-        vbox.append(&error_heading);
+    // Check that rsync is available
+    if !check_rsync_peers(&[], &tx) {
+        return;
+    }
 
-        let error_text = errors
-            .iter()
-            .enumerate()
-            .map(|(i, e)| format!("{}. {}", i + 1, e))
-            .collect::<Vec<_>>()
-            .join("\n");
+    // Create destination directory if it doesn't exist
+    if !dst_path.exists() {
+        if let Err(e) = folders::create_all(&dst_path) {
+            let _ = tx.send(WorkerMsg::Error(format!(
+                "Failed to create destination directory: {}",
+                e
+            )));
+            return;
+        }
+    }
+    if !settle_stale_artifacts(&dst_path, &tx) {
+        return;
+    }
 
-        let error_view = TextView::new();
-        error_view.set_editable(false);
-        error_view.set_cursor_visible(false);
-        error_view.set_wrap_mode(WrapMode::WordChar);
-        error_view.set_monospace(true);
-        error_view.buffer().set_text(&error_text);
+    // Collect the files to process
+    let (files, excluded_files, excluded_dirs) = match collect_files(&source, patterns, opts.symlinks, &mut worker_scan_guard(opts.scan_limits, &cancel_flag, &tx)) {
+        Ok(v) => v,
+        Err(e) => {
+            let _ = tx.send(scan_failure(e, &cancel_flag));
+            return;
+        }
+    };
+    // The links the scan left out lead the skipped list.
+    let mut skipped = links_left_out();
+    if cancel_flag.stopping() {
+        let _ = tx.send(cancelled_before_transfer(skipped, excluded_files, excluded_dirs));
+        return;
+    }
 
-        let scroll = ScrolledWindow::builder()
-            .child(&error_view)
-            .min_content_height(150)
-            .vexpand(true)
-            .build();
-        vbox.append(&scroll);
+    let total = files.len();
+    if total == 0 {
+        let _ = tx.send(WorkerMsg::Finished(TransferReport::nothing_sent(skipped, excluded_files, excluded_dirs)));
+        return;
     }
 
-    // OK button
-    let btn_ok = Button::with_label("OK");
-    btn_ok.add_css_class("suggested-action");
-    btn_ok.set_halign(Align::End);
-    let dialog_ref = dialog.clone();
-    btn_ok.connect_clicked(move |_| {
-        dialog_ref.close();
-    });
-    vbox.append(&btn_ok);
+    let src_dir = match &source {
+        SourceSelection::Directory(d) | SourceSelection::DirectoryItems(d, _) => Some(d.clone()),
+        _ => None,
+    };
+    let parent_levels = parent_levels(transfer_mode, src_dir.is_none(), keep_parents);
+    if let Err(e) = settle_collisions(
+        files.iter().map(|file| PlanInput { file, src_dir: src_dir.as_deref(), transfer_mode, parent_levels, dest: Dest::Local(&dst_path) }),
+        renamer,
+        false,
+        opts.on_collision,
+    ) {
+        let _ = tx.send(WorkerMsg::Error(e));
+        return;
+    }
 
-    dialog.set_child(Some(&vbox));
-    dialog.present();
-}
+    let Some(files) = preflight_local_tree(files, src_dir.as_deref(), transfer_mode, &dst_path, renamer, &mut skipped, &tx)
+    else {
+        return;
+    };
+    if stopped_before_transfer(&cancel_flag, &mut skipped, excluded_files, excluded_dirs, &tx) {
+        return;
+    }
+    let total = files.len();
 
-// ── Remote file browser ────────────────────────────────────────────────
+    let sizes: Vec<Option<u64>> = files.iter().map(|f| fs::metadata(f).ok().map(|m| m.len())).collect();
+    if !room_for_plan(&sizes, opts.ignore_free_space, || space::local_free(&dst_path), &tx) {
+        return;
+    }
+    let mut stats = RunStats::new(
+        source.group_root().as_deref(),
+        files.iter().map(shown).zip(sizes.iter().copied()),
+    );
+    send_plan(&tx, sizes);
 
-/// Entry in a remote directory listing.
-#[derive(Clone)]
-struct RemoteEntry {
-    name: String,
-    is_dir: bool,
-}
+    let mut copied = 0usize;
+    let mut errors = Details::errors();
+    let mut ro_kept = 0usize;
+    let mut move_stats = MoveStats::default();
+    // Renames only work within one filesystem; go straight to copying
+    // when the source and destination are known to be on different devices.
+    let try_rename = source_root(&source).and_then(|r| same_device(&r, &dst_path)) != Some(false);
+    let defer = do_move && move_strategy == MoveStrategy::AllThenDelete;
+    let mut deferred: Vec<PathBuf> = Vec::new();
+    let mut unverified: Vec<String> = Vec::new();
+    let mut stopped = false;
+
+    let front = parallel::Front::new(total);
+    let progress = |i: usize| {
+        let _ = tx.send(WorkerMsg::Progress { done: front.end(i), total, file: files[i].to_string_lossy().to_string() });
+    };
+    let mut plan = files.iter().enumerate().peekable();
+    while plan.peek().is_some() && !stopped {
+        // Plan files until one is bound for a path already waiting to be
+        // written, then send those waiting.
+        let mut sending: Vec<(usize, PathBuf)> = Vec::new();
+        let mut bound: HashSet<PathBuf> = HashSet::new();
+        while let Some(&(i, file_path)) = plan.peek() {
+            // Where the file goes, and what to do about what is there
+            let input = PlanInput {
+                file: file_path,
+                src_dir: src_dir.as_deref(),
+                transfer_mode,
+                parent_levels,
+                dest: Dest::Local(&dst_path),
+            };
+            if planned_local_dest(&input, renamer).is_some_and(|p| bound.contains(&p)) {
+                break;
+            }
+            plan.next();
+            if before_file(i, &tx, &cancel_flag) {
+                skipped.push(format!("{}: {}", shown(file_path), quota::SKIPPED));
+                front.end(i);
+                continue;
+            }
+            if cancel_flag.stopping() {
+                stopped = true;
+                break;
+            }
+            let dest_file = match plan_local_file(&input, renamer, conflict_mode, opts.symlinks, |p| p.exists(), |a, b| copies_match(a, b, opts.symlinks)) {
+                LocalAction::Copy(dest) | LocalAction::Overwrite(dest) | LocalAction::Rename(dest) => dest,
+                LocalAction::Unplaced(why) => {
+                    skipped.push(format!("{}: {}", shown(file_path), why.reason()));
+                    front.end(i);
+                    continue;
+                }
+                LocalAction::SameFile(_) => {
+                    skipped.push(format!("{}: {}", shown(file_path), SAME_FILE_NOTE));
+                    progress(i);
+                    continue;
+                }
+                LocalAction::Identical(dest_file) => {
+                    if defer {
+                        deferred.push(file_path.clone());
+                        copied += 1;
+                        stats.copied(&shown(file_path));
+                        filereport::copied(&reverify::Location::Local(file_path.clone()), &reverify::Location::Local(dest_file), opts);
+                        move_stats.identical += 1;
+                    } else if do_move {
+                        if let Err(e) = remove_moved_source(file_path, &mut ro_kept) {
+                            errors.push(format!(
+                                "{}: identical at destination but failed to delete source: {}",
+                                shown(file_path),
+                                e
+                            ));
+                        } else {
+                            copied += 1;
+                            stats.copied(&shown(file_path));
+                            filereport::copied(&reverify::Location::Local(file_path.clone()), &reverify::Location::Local(dest_file), opts);
+                            move_stats.identical += 1;
+                        }
+                    } else {
+                        skipped.push(format!("{}: identical at destination", shown(file_path)));
+                    }
+                    progress(i);
+                    continue;
+                }
+                LocalAction::SkipExisting(_) => {
+                    skipped.push(format!(
+                        "{}: different version exists at destination",
+                        shown(file_path)
+                    ));
+                    progress(i);
+                    continue;
+                }
+                LocalAction::SkipNewer(_) => {
+                    skipped.push(format!("{}: {}", shown(file_path), DEST_NEWER_NOTE));
+                    progress(i);
+                    continue;
+                }
+                LocalAction::CompareFailed(_, e) => {
+                    errors.push(format!(
+                        "{}: could not compare with destination: {}",
+                        shown(file_path),
+                        e
+                    ));
+                    progress(i);
+                    continue;
+                }
+            };
 
-/// Resolve the SSH user's home directory on the remote host.
-fn resolve_remote_home(host: &str) -> Result<String, String> {
-    let ctl = [
-        "-o", "ControlMaster=auto",
-        "-o", "ControlPath=/tmp/kosmokopy_ssh_%h_%p_%r",
-        "-o", "ControlPersist=60",
-        "-o", "ConnectTimeout=10",
-    ];
-    let out = Command::new("ssh")
-        .args(&ctl)
-        .arg(host)
-        .arg("echo $HOME")
-        .output()
-        .map_err(|e| format!("SSH failed: {}", e))?;
-    if !out.status.success() {
-        return Err("Could not resolve home directory".to_string());
-    }
-    let home = String::from_utf8_lossy(&out.stdout).trim().to_string();
-    if home.is_empty() {
-        Err("Home directory is empty".to_string())
-    } else {
-        Ok(home)
-    }
-}
+            // Create parent directory
+            if let Some(parent) = dest_file.parent() {
+                if let Err(e) = folders::create_all(parent) {
+                    errors.push(format!("{}: {}", shown(file_path), e));
+                    front.end(i);
+                    continue;
+                }
+            }
 
-/// List the contents of a remote directory via SSH.
-/// Returns a sorted vec of `RemoteEntry` (directories first, then files).
-fn list_remote_dir(host: &str, path: &str) -> Result<Vec<RemoteEntry>, String> {
-    let ctl = [
-        "-o", "ControlMaster=auto",
-        "-o", "ControlPath=/tmp/kosmokopy_ssh_%h_%p_%r",
-        "-o", "ControlPersist=60",
-        "-o", "ConnectTimeout=10",
-    ];
-    let clean_path = if path == "/" { "/".to_string() } else { path.trim_end_matches('/').to_string() };
-    let cmd = format!(
-        "command ls -1apL {} 2>/dev/null",
-        shell_quote(&clean_path),
-    );
-    let out = Command::new("ssh")
-        .args(&ctl)
-        .arg(host)
-        .arg(&cmd)
-        .output()
-        .map_err(|e| format!("SSH failed: {}", e))?;
+            // For move on the same filesystem, try rename first (atomic, no copy needed)
+            if do_move && !defer && try_rename {
+                if let Ok(()) = fs::rename(file_path, &dest_file) {
+                    copied += 1;
+                    stats.copied(&shown(file_path));
+                    filereport::copied(&reverify::Location::Local(file_path.clone()), &reverify::Location::Local(dest_file.clone()), opts);
+                    move_stats.record(MoveMethod::Renamed);
+                    debug_log(&format!("moved {} (renamed)", shown(file_path)));
+                    progress(i);
+                    continue;
+                }
+                // rename failed (cross-device) — fall through to rsync
+            }
+            bound.insert(dest_file.clone());
+            sending.push((i, dest_file));
+        }
 
-    if !out.status.success() {
-        let stderr = String::from_utf8_lossy(&out.stderr);
-        return Err(format!("Cannot list '{}': {}", path, stderr.trim()));
+        // Transfer via rsync with checksum verification: one call for each
+        // folder the files share, one each for the rest (see `rsync::Batch`)
+        let mut batches = Vec::new();
+        let mut singles = Vec::new();
+        for (i, dest_file) in &sending {
+            if !rsync::add(&mut batches, *i, &files[*i], dest_file) {
+                singles.push((*i, dest_file.clone()));
+            }
+        }
+        let mut results: Vec<(usize, RsyncSent)> = Vec::new();
+        for batch in &batches {
+            if stopped || stop_before_call(&cancel_flag) {
+                stopped = true;
+                break;
+            }
+            let (out, sent) = send_rsync_batch(
+                priority::command("rsync", opts.low_priority).args(["-a", "--checksum"]).args(opts.throttle.rsync_args(false)).args(symlinks::rsync_args(opts.symlinks)),
+                batch,
+                conflict_mode,
+                rsync_dir_arg(&batch.to),
+                progress,
+            );
+            let reason = failure_reason(&format!("rsync of {} files into {}", batch.files.len(), shown(&batch.to)), &out);
+            for (i, tail) in &batch.files {
+                let outcome = match &out {
+                    _ if sent.contains(i) => RsyncSent::Reported,
+                    Ok(o) if o.status.success() => RsyncSent::Quiet,
+                    _ if cancel_flag.stopping() => {
+                        stopped = true;
+                        continue;
+                    }
+                    Ok(o) => match batch.error_about(&String::from_utf8_lossy(&o.stderr), tail) {
+                        Some(line) => RsyncSent::Failed(format!("rsync failed (exit code {}): {}", o.status.code().unwrap_or(-1), line)),
+                        None => {
+                            let why = format!("rsync failed (exit code {}){}", o.status.code().unwrap_or(-1), reason);
+                            match batch.to.join(tail).exists() {
+                                true => RsyncSent::Unclear(why),
+                                false => RsyncSent::Failed(why),
+                            }
+                        }
+                    },
+                    Err(e) => RsyncSent::Failed(e.to_string()),
+                };
+                results.push((*i, outcome));
+            }
+        }
+        for (i, dest_file) in &singles {
+            if stopped || stop_before_call(&cancel_flag) {
+                stopped = true;
+                break;
+            }
+            let file_path = &files[*i];
+            let rsync_result = priority::command("rsync", opts.low_priority)
+                .args(["-a", "--checksum"])
+                .args(opts.throttle.rsync_args(false))
+                .args(symlinks::rsync_args(opts.symlinks))
+                .args(rsync_conflict_args(conflict_mode))
+                .arg(file_path)
+                .arg(dest_file)
+                .tracked_stderr();
+            let outcome = match &rsync_result {
+                Ok(out) if out.status.success() => RsyncSent::Reported,
+                // The cancel stopped this rsync; that is not a failed file
+                Ok(_) if cancel_flag.stopping() => {
+                    stopped = true;
+                    break;
+                }
+                Ok(out) => {
+                    let path = shown(file_path);
+                    RsyncSent::Failed(format!(
+                        "rsync failed (exit code {}){}",
+                        out.status.code().unwrap_or(-1),
+                        failure_reason(&path, &rsync_result)
+                    ))
+                }
+                Err(e) => RsyncSent::Failed(e.to_string()),
+            };
+            results.push((*i, outcome));
+        }
+        results.sort_by_key(|(i, _)| *i);
+
+        let dests: HashMap<usize, PathBuf> = sending.into_iter().collect();
+        for (i, outcome) in results {
+            let file_path = &files[i];
+            let dest_file = &dests[&i];
+            let (quiet, failed) = match outcome {
+                RsyncSent::Reported => (false, None),
+                RsyncSent::Quiet => (true, None),
+                RsyncSent::Unclear(why) => (true, Some(why)),
+                RsyncSent::Failed(why) => {
+                    errors.push(format!("{}: {}", shown(file_path), why));
+                    progress(i);
+                    continue;
+                }
+            };
+            // rsync --checksum verifies during transfer; also check the
+            // copy the run's way (--verify) for defense in depth
+            match verify_unless_skipped(&cancel_flag, &tx, i, || verify_copy(file_path, dest_file, opts)) {
+                None => unverified.push(shown(file_path)),
+                Some(Ok(true)) => {
+                    copied += 1;
+                    stats.copied(&shown(file_path));
+                    note_copied(reverify::Location::Local(file_path.clone()), reverify::Location::Local(dest_file.clone()), opts);
+                    if defer {
+                        deferred.push(file_path.clone());
+                    } else if do_move {
+                        if let Err(e) = remove_moved_source(file_path, &mut ro_kept) {
+                            errors.push(format!(
+                                "{}: transferred and verified but failed to delete source: {}",
+                                shown(file_path),
+                                e
+                            ));
+                        } else {
+                            let bytes = fs::metadata(dest_file).map(|m| m.len()).unwrap_or(0);
+                            move_stats.record(MoveMethod::Copied(bytes));
+                            debug_log(&format!("moved {} (copied)", shown(file_path)));
+                        }
+                    }
+                }
+                Some(Ok(false) | Err(_)) if failed.is_some() => {
+                    errors.push(format!("{}: {}", shown(file_path), failed.unwrap_or_default()));
+                }
+                // rsync left a file written there since the plan alone
+                Some(Ok(false)) if quiet => skipped.push(format!(
+                    "{}: {}",
+                    shown(file_path),
+                    match conflict_mode {
+                        ConflictMode::Newer => DEST_NEWER_NOTE,
+                        _ => "different version exists at destination",
+                    }
+                )),
+                Some(Ok(false)) => {
+                    let _ = fs::remove_file(dest_file);
+                    errors.push(format!(
+                        "{}: integrity check failed — byte comparison mismatch (original retained, copy removed)",
+                        shown(file_path)
+                    ));
+                }
+                Some(Err(e)) => {
+                    if do_move {
+                        errors.push(format!(
+                            "{}: transferred but verification failed: {} (original retained)",
+                            shown(file_path),
+                            e
+                        ));
+                    } else {
+                        errors.push(format!(
+                            "{}: transferred but could not verify: {}",
+                            shown(file_path),
+                            e
+                        ));
+                    }
+                }
+            }
+            progress(i);
+        }
     }
 
-    let mut dirs = Vec::new();
-    let mut files = Vec::new();
-
-    for line in String::from_utf8_lossy(&out.stdout).lines() {
-        let line = line.trim();
-        if line.is_empty() || line == "./" {
-            continue;
+    if stopped {
+        push_read_only_summary(&mut errors, ro_kept);
+        if do_move {
+            let _ = tx.send(WorkerMsg::MoveStats(move_stats));
         }
-        if line == "../" {
-            continue; // Handled by the Up button
+        if defer {
+            let _ = tx.send(WorkerMsg::Deletion(Deletion::Skipped));
         }
-        if line.ends_with('/') {
-            dirs.push(RemoteEntry {
-                name: line.trim_end_matches('/').to_string(),
-                is_dir: true,
-            });
-        } else {
-            files.push(RemoteEntry {
-                name: line.to_string(),
-                is_dir: false,
-            });
+        if !unverified.is_empty() {
+            let _ = tx.send(WorkerMsg::Unverified(unverified));
         }
+        let _ = tx.send(WorkerMsg::Cancelled(TransferReport {
+            copied,
+            skipped,
+            excluded_files,
+            excluded_dirs,
+            failed: failed_sources(files.iter().cloned(), errors.shown(), None),
+            errors,
+            groups: Vec::new(),
+        }));
+        return;
     }
 
-    dirs.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-    files.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-    dirs.append(&mut files);
-    Ok(dirs)
+    send_phase(&tx, Phase::Finalizing);
+    let cancelled = defer
+        && delete_deferred_sources(&deferred, unverified.len(), None, &[], &mut errors, &mut ro_kept, &cancel_flag, &tx);
+    push_read_only_summary(&mut errors, ro_kept);
+    if !cancelled {
+        tidy_moved_source(&source, do_move, opts.remove_source_root, &files, &errors);
+    }
+    if do_move {
+        let _ = tx.send(WorkerMsg::MoveStats(move_stats));
+    }
+    let groups = stats.finish(skipped.shown(), errors.shown());
+    let failed = failed_sources(files.iter().cloned(), errors.shown(), None);
+    if !unverified.is_empty() {
+        let _ = tx.send(WorkerMsg::Unverified(unverified));
+    }
+    let report = TransferReport { copied, skipped, excluded_files, excluded_dirs, errors, failed, groups };
+    let _ = tx.send(if cancelled { WorkerMsg::Cancelled(report) } else { WorkerMsg::Finished(report) });
 }
 
-/// Open a remote file browser dialog.
-/// When the user selects a path, `on_select` is called with `"host:/selected/path"`.
-fn show_remote_browser(
-    parent: &ApplicationWindow,
-    initial_text: &str,
-    select_dirs_only: bool,
-    on_select: impl Fn(String) + 'static,
-) {
-    // Parse any existing host:/path from the entry text
-    let (initial_host, initial_path) = if let Some(pos) = initial_text.find(':') {
-        let h = &initial_text[..pos];
-        let p = &initial_text[pos + 1..];
-        if !h.is_empty() && !h.contains('/') && !p.is_empty() {
-            (h.to_string(), p.to_string())
-        } else {
-            (String::new(), "~".to_string())
-        }
-    } else {
-        (String::new(), "~".to_string())
-    };
+// ── Worker thread (remote via ssh/scp) ─────────────────────────────────
 
-    let dialog = Window::builder()
-        .title("Browse Remote Location")
-        .modal(true)
-        .transient_for(parent)
-        .default_width(520)
-        .default_height(480)
-        .resizable(true)
-        .build();
+fn run_remote_worker(
+    source: SourceSelection,
+    host: &str,
+    remote_base: &str,
+    do_move: bool,
+    move_strategy: MoveStrategy,
+    conflict_mode: ConflictMode,
+    recheck: Recheck,
+    renamer: &Renamer,
+    transfer_mode: TransferMode,
+    keep_parents: usize,
+    patterns: &[String],
+    method: TransferMethod,
+    opts: &RunOptions,
+    cancel_flag: Arc<CancelFlag>,
+    tx: mpsc::Sender<WorkerMsg>,
+) {
+    // SSH control-socket args — reuses a single TCP connection for all calls
+    let ctl = ssh::ctl_args(host);
 
-    let vbox = GtkBox::new(Orientation::Vertical, 8);
-    vbox.set_margin_top(12);
-    vbox.set_margin_bottom(12);
-    vbox.set_margin_start(12);
-    vbox.set_margin_end(12);
+    // Quick connectivity check
+    if !check_ssh_host(host, &ctl, &tx) || !check_remote_destination(host, &ctl, remote_base, &tx) {
+        return;
+    }
+    if !settle_remote_stale_artifacts(host, &ctl, remote_base, &tx) {
+        return;
+    }
 
-    // ── Host row ──────────────────────────────────────────────────────
-    let host_row = GtkBox::new(Orientation::Horizontal, 6);
-    let host_label = Label::new(Some("Host:"));
-    host_label.set_width_chars(6);
-    host_label.set_xalign(0.0);
-    let host_entry = Entry::new();
-    host_entry.set_hexpand(true);
-    host_entry.set_placeholder_text(Some("user@hostname"));
-    host_entry.set_text(&initial_host);
-    let btn_connect = Button::with_label("Connect");
-    btn_connect.add_css_class("suggested-action");
-    host_row.append(&host_label);
-    host_row.append(&host_entry);
-    host_row.append(&btn_connect);
-    vbox.append(&host_row);
+    // Collect files locally
+    let (files, excluded_files, excluded_dirs) = match collect_files(&source, patterns, opts.symlinks, &mut worker_scan_guard(opts.scan_limits, &cancel_flag, &tx)) {
+        Ok(v) => v,
+        Err(e) => {
+            let _ = tx.send(scan_failure(e, &cancel_flag));
+            return;
+        }
+    };
+    // The links the scan left out lead the skipped list.
+    let mut early_skipped = links_left_out();
+    if cancel_flag.stopping() {
+        let _ = tx.send(cancelled_before_transfer(early_skipped, excluded_files, excluded_dirs));
+        return;
+    }
 
-    // ── Path row ──────────────────────────────────────────────────────
-    let path_row = GtkBox::new(Orientation::Horizontal, 6);
-    let path_label = Label::new(Some("Path:"));
-    path_label.set_width_chars(6);
-    path_label.set_xalign(0.0);
-    let path_entry = Entry::new();
-    path_entry.set_hexpand(true);
-    path_entry.set_text(&initial_path);
-    let btn_up = Button::with_label("↑ Up");
-    let btn_go = Button::with_label("Go");
-    path_row.append(&path_label);
-    path_row.append(&path_entry);
-    path_row.append(&btn_up);
-    path_row.append(&btn_go);
-    vbox.append(&path_row);
+    let total = files.len();
+    if total == 0 {
+        let _ = tx.send(WorkerMsg::Finished(TransferReport::nothing_sent(early_skipped, excluded_files, excluded_dirs)));
+        return;
+    }
 
-    // ── Status label ──────────────────────────────────────────────────
-    let status = Label::new(Some("Enter a host and click Connect."));
-    status.set_halign(Align::Start);
-    status.add_css_class("dim-label");
-    vbox.append(&status);
+    let src_dir = match &source {
+        SourceSelection::Directory(d) | SourceSelection::DirectoryItems(d, _) => Some(d.clone()),
+        _ => None,
+    };
+    let parent_levels = parent_levels(transfer_mode, src_dir.is_none(), keep_parents);
+    if let Err(e) = settle_collisions(
+        files.iter().map(|file| PlanInput { file, src_dir: src_dir.as_deref(), transfer_mode, parent_levels, dest: Dest::Remote(remote_base) }),
+        renamer,
+        false,
+        opts.on_collision,
+    ) {
+        let _ = tx.send(WorkerMsg::Error(e));
+        return;
+    }
 
-    // ── File list ─────────────────────────────────────────────────────
-    let listbox = ListBox::new();
-    listbox.set_selection_mode(SelectionMode::Single);
-    let scroll = ScrolledWindow::builder()
-        .hscrollbar_policy(PolicyType::Never)
-        .vscrollbar_policy(PolicyType::Automatic)
-        .vexpand(true)
-        .child(&listbox)
-        .build();
-    vbox.append(&scroll);
+    // Build list of (local_path, remote_path) pairs
+    let remote_base = remote_base.trim_end_matches('/');
+    let mut transfers: Vec<(PathBuf, PathBuf)> = Vec::new();
 
-    // ── Action buttons ────────────────────────────────────────────────
-    let btn_row = GtkBox::new(Orientation::Horizontal, 8);
-    btn_row.set_halign(Align::End);
-    let btn_cancel = Button::with_label("Cancel");
-    let btn_select = Button::with_label("Select");
-    btn_select.add_css_class("suggested-action");
-    btn_select.set_sensitive(false);
-    btn_row.append(&btn_cancel);
-    btn_row.append(&btn_select);
-    vbox.append(&btn_row);
+    for file_path in &files {
+        let placed = destpath::build_destination(
+            &PlanInput {
+                file: file_path,
+                src_dir: src_dir.as_deref(),
+                transfer_mode,
+                parent_levels,
+                dest: Dest::Remote(remote_base),
+            },
+            |rel| renamer.rename_path(rel, modified(file_path)),
+        );
+        let remote_file = match placed {
+            Ok(dest) => dest.into_remote(),
+            Err(why) => {
+                early_skipped.push(format!("{}: {}", shown(file_path), why.reason()));
+                continue;
+            }
+        };
+        transfers.push((file_path.clone(), remote_file));
+    }
 
-    dialog.set_child(Some(&vbox));
+    // Check the existing folders the upload will write into before
+    // creating any.
+    let Some(transfers) = preflight_remote(
+        transfers, host, &ctl, |t| &t.1, |t| shown(&t.0), &mut early_skipped, &tx,
+    ) else {
+        return;
+    };
+    if stopped_before_transfer(&cancel_flag, &mut early_skipped, excluded_files, excluded_dirs, &tx) {
+        return;
+    }
+    let sizes: Vec<Option<u64>> = transfers.iter().map(|(l, _)| fs::metadata(l).ok().map(|m| m.len())).collect();
+    if !room_for_plan(&sizes, opts.ignore_free_space, || remote_free(host, &ctl, remote_base), &tx) {
+        return;
+    }
+    let remote_dirs = remote_target_dirs(remote_base, transfers.iter().map(|t| t.1.as_path()));
 
-    // ── Shared state ──────────────────────────────────────────────────
-    // Entries is the listing for the current directory.
-    let entries: Rc<RefCell<Vec<RemoteEntry>>> = Rc::new(RefCell::new(Vec::new()));
-    // selected_path will hold the path the user ultimately wants.
-    let selected_path: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    // Create all remote directories in one SSH call
+    let mkdir_result = Command::new("ssh")
+        .args(&ctl)
+        .arg(host)
+        .arg(folders::mkdir_script(&remote_dirs, |p| quote_path(p)))
+        .tracked_output();
+    if let Ok(o) = &mkdir_result {
+        folders::note_remote(host, &remote_dirs, &o.stdout);
+        if !o.status.success() {
+            let msg = String::from_utf8_lossy(&o.stderr);
+            let _ = tx.send(WorkerMsg::Error(format!(
+                "Failed to create remote directories: {}", msg.trim()
+            )));
+            return;
+        }
+    }
 
-    // ── Populate the listbox from a listing ───────────────────────────
-    let populate: Rc<dyn Fn(String, String)> = Rc::new({
-        let listbox = listbox.clone();
-        let entries = entries.clone();
-        let status = status.clone();
-        let btn_select = btn_select.clone();
-        let path_entry = path_entry.clone();
-        let selected_path = selected_path.clone();
-        move |host: String, path: String| {
-            // Clear existing rows
-            while let Some(row) = listbox.row_at_index(0) {
-                listbox.remove(&row);
-            }
-            entries.borrow_mut().clear();
-            btn_select.set_sensitive(false);
-            *selected_path.borrow_mut() = None;
+    // If not overwriting, look up which planned destinations already exist
+    let (mut existing, mut dest_times) = if conflict_mode != ConflictMode::Overwrite {
+        let targets: Vec<&Path> = transfers.iter().map(|t| t.1.as_path()).collect();
+        existing_remote_files_ssh(host, &ctl, &targets, conflict_mode)
+    } else {
+        (HashSet::new(), RemoteTimes::new())
+    };
 
-            status.set_text("Loading…");
+    send_plan(&tx, sizes);
+    let mut stats = RunStats::new(
+        source.group_root().as_deref(),
+        files.iter().map(|f| (shown(f), fs::metadata(f).ok().map(|m| m.len()))),
+    );
 
-            // Spawn thread for SSH listing, poll result on main loop
-            // The channel now also returns the (possibly resolved) path so
-            // we can update the path bar when "~" is expanded.
-            let (tx, rx) = mpsc::channel::<(String, Result<Vec<RemoteEntry>, String>)>();
-            let host_c = host.clone();
-            let path_c = path.clone();
-            thread::spawn(move || {
-                // Resolve "~" or paths starting with "~/" to an absolute path.
-                let resolved = if path_c == "~" || path_c.starts_with("~/") {
-                    match resolve_remote_home(&host_c) {
-                        Ok(home) => {
-                            if path_c == "~" {
-                                home
-                            } else {
-                                format!("{}/{}", home, &path_c[2..])
-                            }
-                        }
+    let total_transfers = transfers.len();
+    let mut skipped = early_skipped;
+    let defer = do_move && move_strategy == MoveStrategy::AllThenDelete;
+    let mut batch = UploadBatch::new(host, &ctl, opts, method, do_move, defer);
+
+    let front = parallel::Front::new(total_transfers);
+    let progress = |i: usize| {
+        let _ = tx.send(WorkerMsg::Progress {
+            done: front.end(i),
+            total: total_transfers,
+            file: shown(&transfers[i].0),
+        });
+    };
+    // The scp of one file, on a thread of the pool
+    let upload = |(i, remote, claimed): (usize, PathBuf, bool)| {
+        let local = &transfers[i].0;
+        deadline::begin(i);
+        if symlinks::preserved(opts.symlinks, local) {
+            return (i, remote.clone(), claimed, make_remote_link(host, &ctl, local, &remote));
+        }
+        let sent = retry::attempt(opts.retries, &cancel_flag, || scp_upload(host, &ctl, local, &remote, opts.workers, opts));
+        (i, remote, claimed, sent)
+    };
+
+    let mut stopped = false;
+    if method == TransferMethod::Tar {
+        stopped = send_tar_stream(
+            host, &ctl, remote_base, &transfers, &mut existing, conflict_mode, &mut batch, &mut stats, &mut skipped,
+            progress, opts, &cancel_flag, &tx,
+        );
+    } else {
+        // Destinations being written now
+        let mut in_flight: HashSet<PathBuf> = HashSet::new();
+        parallel::run(opts.workers, upload, |lanes| {
+            let mut items = transfers.iter().enumerate().peekable();
+            loop {
+                let next = items.peek().filter(|_| !stopped);
+                // A copy still to be verified is checked before another file
+                // is written over it.
+                if next.is_some_and(|(_, (_, remote))| batch.is_waiting(remote)) {
+                    batch.verify(&mut stats, &cancel_flag, &tx);
+                    continue;
+                }
+                // Take back a finished file when every lane is busy, when the
+                // next file is bound for a path still being written, or when
+                // there is nothing more to start.
+                if lanes.is_full() || next.is_none_or(|(_, (_, remote))| in_flight.contains(remote)) {
+                    let Some((i, remote, claimed, sent)) = lanes.wait() else {
+                        break;
+                    };
+                    in_flight.remove(&remote);
+                    match sent {
+                        Ok(()) => batch.add(i, transfers[i].0.clone(), remote),
                         Err(e) => {
-                            let _ = tx.send((path_c, Err(e)));
-                            return;
+                            // Nothing was written, so the name is free again
+                            if claimed {
+                                existing.remove(&remote);
+                            }
+                            batch.errors.push(e);
                         }
                     }
-                } else {
-                    path_c
+                    progress(i);
+                    if batch.is_full() {
+                        batch.verify(&mut stats, &cancel_flag, &tx);
+                    }
+                    continue;
+                }
+                let Some((i, (local, remote))) = items.next() else {
+                    break;
                 };
-                let result = list_remote_dir(&host_c, &resolved);
-                let _ = tx.send((resolved, result));
-            });
+                if before_file(i, &tx, &cancel_flag) {
+                    skipped.push(format!("{}: {}", shown(local), quota::SKIPPED));
+                    front.end(i);
+                    continue;
+                }
+                if cancel_flag.stopping() {
+                    // Let the files in flight finish, then stop
+                    stopped = true;
+                    continue;
+                }
+                // Handle conflict if the destination already exists
+                // A path's time is taken with its first decision; a later source
+                // for the same path finds none and leaves the earlier upload alone.
+                let newer = |p: &Path| source_is_newer(modified(local).map(Timestamp::of), dest_times.remove(p));
+                let Some(remote) = resolve_remote_conflict(remote, &mut existing, conflict_mode, recheck, newer, |p| {
+                    remote_path_exists(host, &ctl, p)
+                }) else {
+                    skipped.push(format!("{}: {}", shown(local), kept_reason(conflict_mode)));
+                    progress(i);
+                    continue;
+                };
+                let remote = remote.into_owned();
+                // Later entries mapping to the same path now see it as taken
+                let claimed = existing.insert(remote.clone());
+                in_flight.insert(remote.clone());
+                lanes.start((i, remote, claimed));
+            }
+        });
+    }
+    // The last copies, also those sent before a cancel
+    batch.verify(&mut stats, &cancel_flag, &tx);
+    let UploadBatch { copied, mut errors, mut ro_kept, deferred, unverified, .. } = batch;
+
+    if stopped {
+        push_read_only_summary(&mut errors, ro_kept);
+        if defer {
+            let _ = tx.send(WorkerMsg::Deletion(Deletion::Skipped));
+        }
+        if !unverified.is_empty() {
+            let _ = tx.send(WorkerMsg::Unverified(unverified));
+        }
+        let _ = tx.send(WorkerMsg::Cancelled(TransferReport {
+            copied,
+            skipped,
+            excluded_files,
+            excluded_dirs,
+            failed: failed_sources(files.iter().cloned(), errors.shown(), None),
+            errors,
+            groups: Vec::new(),
+        }));
+        return;
+    }
 
-            let listbox_c = listbox.clone();
-            let entries_c = entries.clone();
-            let status_c = status.clone();
-            let btn_select_c = btn_select.clone();
-            let path_entry_c = path_entry.clone();
-            let selected_path_c = selected_path.clone();
-            glib::timeout_add_local(std::time::Duration::from_millis(50), move || {
-                match rx.try_recv() {
-                    Ok((resolved_path, result)) => {
-                        // Update the path bar to the resolved absolute path
-                        path_entry_c.set_text(&resolved_path);
-                        match result {
-                            Ok(listing) => {
-                                let count = listing.len();
-                                for entry in &listing {
-                                    let row_box = GtkBox::new(Orientation::Horizontal, 8);
-                                    row_box.set_margin_top(2);
-                                    row_box.set_margin_bottom(2);
-                                    row_box.set_margin_start(6);
-                                    row_box.set_margin_end(6);
+    send_phase(&tx, Phase::Finalizing);
+    let cancelled = defer
+        && delete_deferred_sources(&deferred, unverified.len(), None, &[], &mut errors, &mut ro_kept, &cancel_flag, &tx);
+    push_read_only_summary(&mut errors, ro_kept);
+    if !cancelled {
+        tidy_moved_source(&source, do_move, opts.remove_source_root, &files, &errors);
+    }
+    let groups = stats.finish(skipped.shown(), errors.shown());
+    let failed = failed_sources(files.iter().cloned(), errors.shown(), None);
+    if !unverified.is_empty() {
+        let _ = tx.send(WorkerMsg::Unverified(unverified));
+    }
+    let report = TransferReport { copied, skipped, excluded_files, excluded_dirs, errors, failed, groups };
+    let _ = tx.send(if cancelled { WorkerMsg::Cancelled(report) } else { WorkerMsg::Finished(report) });
+}
 
-                                    let icon = Label::new(Some(
-                                        if entry.is_dir { "📁" } else { "📄" },
-                                    ));
-                                    let name_label = Label::new(Some(&entry.name));
-                                    name_label.set_halign(Align::Start);
-                                    name_label.set_hexpand(true);
+/// Send `local` to `remote` on `host` with one rsync call over `ssh_cmd`,
+/// leaving a file already there as `conflict_mode` says.  The error says
+/// why, without the file's name.
+fn rsync_upload(ssh_cmd: &str, host: &str, local: &Path, remote: &Path, conflict_mode: ConflictMode, opts: &RunOptions) -> Result<(), String> {
+    let rsync_result = priority::command("rsync", opts.low_priority)
+        .args(["-a", "--checksum"])
+        .args(opts.throttle.rsync_args(true))
+        .args(symlinks::rsync_args(opts.symlinks))
+        .args(rsync_conflict_args(conflict_mode))
+        .arg("-e")
+        .arg(ssh_cmd)
+        .arg(local)
+        .arg(remote_arg(host, rsync_escape_remote(remote)))
+        .tracked_stderr();
+    match &rsync_result {
+        Ok(out) if out.status.success() => Ok(()),
+        Ok(out) => Err(format!(
+            "rsync failed (exit code {}){}",
+            out.status.code().unwrap_or(-1),
+            failure_reason(&shown(local), &rsync_result)
+        )),
+        Err(e) => Err(e.to_string()),
+    }
+}
 
-                                    if entry.is_dir {
-                                        name_label.set_markup(&format!(
-                                            "<b>{}</b>",
-                                            glib::markup_escape_text(&entry.name)
-                                        ));
-                                    }
+/// Send `local` to `remote` on `host`: with scp, or, for a name scp would
+/// expand or can't carry (see `ssh::scp_literal`), as ssh's stdin to
+/// `cat`.  `lanes` uploads run side by side and share the bandwidth limit.
+fn scp_send(host: &str, ctl: &[String], local: &Path, remote: &Path, lanes: usize, opts: &RunOptions) -> std::io::Result<std::process::Output> {
+    if ssh::scp_literal(remote) {
+        return priority::command("scp", opts.low_priority)
+            .args(ctl)
+            .arg("-q")
+            .args(opts.throttle.scp_args(lanes))
+            .arg(local)
+            .arg(remote_arg(host, remote))
+            .tracked_stderr();
+    }
+    priority::command("ssh", opts.low_priority)
+        .args(ctl)
+        .arg(host)
+        .arg(path_command("cat > {}", &[remote.as_os_str()]))
+        .stdin(fs::File::open(local)?)
+        .tracked_stderr()
+}
 
-                                    row_box.append(&icon);
-                                    row_box.append(&name_label);
-                                    listbox_c.append(&row_box);
-                                }
-                                *entries_c.borrow_mut() = listing;
+/// Fetch `remote` on `host` to `local`, as `scp_send` sends it.  A failed
+/// `cat` leaves no local file behind.
+fn scp_fetch(host: &str, ctl: &[String], remote: &Path, local: &Path, opts: &RunOptions) -> std::io::Result<std::process::Output> {
+    if ssh::scp_literal(remote) {
+        return priority::command("scp", opts.low_priority)
+            .args(ctl)
+            .arg("-q")
+            .args(opts.throttle.scp_args(1))
+            .arg(remote_arg(host, remote))
+            .arg(local)
+            .tracked_stderr();
+    }
+    let fetched = priority::command("ssh", opts.low_priority)
+        .args(ctl)
+        .arg(host)
+        .arg(path_command("cat -- {}", &[remote.as_os_str()]))
+        .tracked_into(fs::File::create(local)?);
+    if !matches!(&fetched, Ok(out) if out.status.success()) {
+        let _ = fs::remove_file(local);
+    }
+    fetched
+}
 
-                                let dir_count =
-                                    entries_c.borrow().iter().filter(|e| e.is_dir).count();
-                                let file_count = count - dir_count;
-                                status_c.set_text(&format!(
-                                    "{} — {} folder(s), {} file(s)",
-                                    path_entry_c.text(),
-                                    dir_count,
-                                    file_count,
-                                ));
+/// Send `local` to `remote` on `host` with one scp call, one of `lanes`.
+fn scp_upload(host: &str, ctl: &[String], local: &Path, remote: &Path, lanes: usize, opts: &RunOptions) -> Result<(), String> {
+    let scp_result = scp_send(host, ctl, local, remote, lanes, opts);
+    match &scp_result {
+        Ok(out) if out.status.success() => Ok(()),
+        Ok(out) => {
+            let path = shown(local);
+            Err(format!(
+                "{}: scp failed (exit code {}){}",
+                path,
+                out.status.code().unwrap_or(-1),
+                failure_reason(&path, &scp_result)
+            ))
+        }
+        Err(e) => {
+            if deadline::is_timeout(e) {
+                remove_remote_partial(host, ctl, remote);
+            }
+            Err(format!("{}: {}", shown(local), e))
+        }
+    }
+}
 
-                                // Always allow selecting the current directory
-                                // (for source browse this selects the folder as source;
-                                //  for destination browse this selects the folder as target).
-                                btn_select_c.set_sensitive(true);
-                                *selected_path_c.borrow_mut() =
-                                    Some(path_entry_c.text().to_string());
-                            }
-                            Err(e) => {
-                                status_c.set_text(&format!("Error: {}", e));
-                            }
-                        }
-                        glib::ControlFlow::Break
-                    }
-                    Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
-                    Err(mpsc::TryRecvError::Disconnected) => {
-                        status_c.set_text("Error: listing thread terminated unexpectedly");
-                        glib::ControlFlow::Break
-                    }
+/// Make `remote` on `host` a link pointing where the local link `local`
+/// does (`--symlinks preserve`).
+fn make_remote_link(host: &str, ctl: &[String], local: &Path, remote: &Path) -> Result<(), String> {
+    let target = fs::read_link(local).map_err(|e| format!("{}: {}", shown(local), e))?;
+    let made = Command::new("ssh").args(ctl).arg(host).arg(symlinks::remote_script(&target, remote, |p| quote_path(p))).tracked_stderr();
+    match made {
+        Ok(out) if out.status.success() => Ok(()),
+        Ok(out) => Err(format!(
+            "{}: could not make the link on {}: {}",
+            shown(local),
+            host,
+            String::from_utf8_lossy(&out.stderr).trim()
+        )),
+        Err(e) => Err(format!("{}: {}", shown(local), e)),
+    }
+}
+
+/// Upload `transfers` as one tar stream unpacked under `remote_base` on
+/// `host`, in place of an scp per file (the tar method).  A destination in
+/// `existing` is skipped rather than written, and so is a second file
+/// bound for the same path.  Each file sent goes to `batch`, verified once
+/// the stream has closed.  Returns whether a cancel cut the stream short;
+/// the archive is still ended cleanly, so what was sent stays.
+fn send_tar_stream(
+    host: &str,
+    ctl: &[String],
+    remote_base: &str,
+    transfers: &[(PathBuf, PathBuf)],
+    existing: &mut HashSet<PathBuf>,
+    conflict_mode: ConflictMode,
+    batch: &mut UploadBatch,
+    stats: &mut RunStats,
+    skipped: &mut Details,
+    progress: impl Fn(usize),
+    opts: &RunOptions,
+    cancel_flag: &CancelFlag,
+    tx: &mpsc::Sender<WorkerMsg>,
+) -> bool {
+    let mut sent: Vec<usize> = Vec::new();
+    let mut stopped = false;
+    let unpack = format!("tar -xf - -C {}", shell_quote(if remote_base.is_empty() { "/" } else { remote_base }));
+    let streamed = Command::new("ssh").args(ctl).arg(host).arg(unpack).tracked_feed(|stdin| {
+        let mut out = std::io::BufWriter::new(stdin);
+        for (i, (local, remote)) in transfers.iter().enumerate() {
+            if before_file(i, tx, cancel_flag) {
+                skipped.push(format!("{}: {}", shown(local), quota::SKIPPED));
+                progress(i);
+                continue;
+            }
+            if cancel_flag.stopping() {
+                stopped = true;
+                break;
+            }
+            if !existing.insert(remote.clone()) {
+                let reason = match conflict_mode {
+                    ConflictMode::Overwrite => "another file in this run goes to the same path",
+                    _ => kept_reason(conflict_mode),
+                };
+                skipped.push(format!("{}: {}", shown(local), reason));
+                progress(i);
+                continue;
+            }
+            let name = remote.strip_prefix(remote_base).unwrap_or(remote);
+            activity::set(&format!("sending {}", shown(local)));
+            // The outer error is reading the source; an inner one is the far
+            // end no longer reading, and its own message says why.
+            let written = match symlinks::preserved(opts.symlinks, local) {
+                true => fs::read_link(local).map(|target| tarstream::append_link(&mut out, name, &target)),
+                false => fs::File::open(local).map(|file| tarstream::append_file(&mut out, name, file, opts.throttle).map(drop)),
+            };
+            match written {
+                Ok(written) => written?,
+                Err(e) => {
+                    existing.remove(remote);
+                    batch.errors.push(format!("{}: {}", shown(local), e));
+                    progress(i);
+                    continue;
                 }
-            });
+            }
+            sent.push(i);
+            progress(i);
         }
+        tarstream::finish(&mut out)
     });
+    match streamed {
+        _ if cancel_flag.stopping() => stopped = true,
+        Ok((fed, out)) if !out.status.success() => {
+            let why = String::from_utf8_lossy(&out.stderr);
+            let why = match why.trim() {
+                "" => fed.err().map_or_else(String::new, |e| e.to_string()),
+                why => why.to_string(),
+            };
+            batch.errors.push(format!("tar on {} failed (exit code {}): {}", host, out.status.code().unwrap_or(-1), why));
+        }
+        Ok((Err(e), _)) | Err(e) => batch.errors.push(format!("tar stream to {}: {}", host, e)),
+        Ok((Ok(()), _)) => {}
+    }
+    for i in sent {
+        let (local, remote) = &transfers[i];
+        batch.add(i, local.clone(), remote.clone());
+        if batch.is_full() {
+            batch.verify(stats, cancel_flag, tx);
+        }
+    }
+    stopped
+}
 
-    // ── Connect button ────────────────────────────────────────────────
-    {
-        let populate = populate.clone();
-        let host_entry = host_entry.clone();
-        let path_entry = path_entry.clone();
-        btn_connect.connect_clicked(move |_| {
-            let host = host_entry.text().to_string().trim().to_string();
-            if host.is_empty() {
-                return;
+// ── Chunked local copy ─────────────────────────────────────────────────
+
+/// Copy `src` to `dst` in 1 MiB chunks, calling `on_progress` with the
+/// running byte count after each chunk.  Under the bandwidth limit of
+/// `opts` the chunks are smaller and each waits its turn (see
+/// `throttle`).  Permissions are carried over the same way `fs::copy`
+/// does, and with `--preserve` the access and modification times too.
+///
+/// The bytes go to `<dst>.kosmokopy-partial`, renamed to `dst` once
+/// complete, so a run that dies leaves no half-copy under the real name
+/// (see `stale`).  With `--stale-artifacts resume` a partial copy left
+/// found there is continued when it matches the start of `src`.
+fn copy_file_chunked(
+    src: &Path,
+    dst: &Path,
+    opts: &RunOptions,
+    mut on_progress: impl FnMut(u64),
+) -> std::io::Result<u64> {
+    activity::set(&format!("copying {}", shown(src)));
+    let mut reader = fs::File::open(src)?;
+    let meta = reader.metadata()?;
+    let partial = stale::partial_path(dst);
+    let resumed = match stale::resumes(&partial) {
+        true => resume_partial(&mut reader, &partial, meta.len()),
+        false => None,
+    };
+    let (mut writer, mut copied) = match resumed {
+        Some(resumed) => resumed,
+        None => (fs::File::create(&partial)?, 0),
+    };
+    if copied > 0 {
+        debug_log(&format!("resuming {} at {} bytes", shown(dst), copied));
+        on_progress(copied);
+    }
+    let mut buf = vec![0u8; opts.throttle.chunk(1024 * 1024)];
+    let result = (|| {
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            writer.write_all(&buf[..n])?;
+            opts.throttle.spend(n);
+            copied += n as u64;
+            on_progress(copied);
+        }
+        if opts.preserve {
+            if let Err(e) = preserve::times_of(&meta).and_then(|times| writer.set_times(times)) {
+                preserve::note_failure(dst, &e);
             }
-            let path = path_entry.text().to_string().trim().to_string();
-            let path = if path.is_empty() { "~".to_string() } else { path };
-            populate(host, path);
-        });
+        }
+        writer.set_permissions(meta.permissions())?;
+        fs::rename(&partial, dst)
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    Ok(copied)
+}
+
+/// Open the partial copy at `partial` to be continued, with `reader`
+/// moved past the bytes it already holds.  `None` (and `reader` back at
+/// the start) when there is none, or it is longer than the source or
+/// differs from its start, so it must be written afresh.
+fn resume_partial(reader: &mut fs::File, partial: &Path, src_len: u64) -> Option<(fs::File, u64)> {
+    use std::io::{Seek, SeekFrom};
+    let mut file = fs::OpenOptions::new().read(true).append(true).open(partial).ok()?;
+    let have = file.metadata().ok()?.len();
+    let mut matches = have <= src_len && have > 0;
+    let (mut ours, mut theirs) = (vec![0u8; 64 * 1024], vec![0u8; 64 * 1024]);
+    let mut left = have;
+    while matches && left > 0 {
+        let n = left.min(ours.len() as u64) as usize;
+        matches = file.read_exact(&mut ours[..n]).is_ok()
+            && reader.read_exact(&mut theirs[..n]).is_ok()
+            && ours[..n] == theirs[..n];
+        left -= n as u64;
+    }
+    if !matches {
+        let _ = reader.seek(SeekFrom::Start(0));
+        return None;
+    }
+    Some((file, have))
+}
+
+// ── Read-only sources ──────────────────────────────────────────────────
+
+/// Overrides read-only source detection: `1` reports the source as
+/// read-only, `0` as writable.  Used by the test suite where a read-only
+/// mount can't be created.
+const READ_ONLY_SOURCE_ENV: &str = "KOSMOKOPY_TEST_READ_ONLY_SOURCE";
+
+/// Whether a local source lives on a read-only filesystem, in which case a
+/// move can copy but never delete the originals.
+///
+/// Probes by creating (and removing) a scratch file next to the first
+/// source file, leaving the folder's times as they were.  Remote sources
+/// are never reported as read-only.
+fn source_is_read_only(source: &SourceSelection) -> bool {
+    match std::env::var(READ_ONLY_SOURCE_ENV).as_deref() {
+        Ok("1") => return true,
+        Ok("0") => return false,
+        _ => {}
+    }
+    match source_root(source) {
+        Some(dir) => dir_is_read_only(&dir),
+        None => false,
+    }
+}
+
+/// The directory a local source lives in: the source folder itself, or
+/// the parent of the (first) selected file.
+fn source_root(source: &SourceSelection) -> Option<PathBuf> {
+    match source {
+        SourceSelection::Directory(p) if p.is_file() => p.parent().map(Path::to_path_buf),
+        SourceSelection::Directory(p) | SourceSelection::DirectoryItems(p, _) => Some(p.clone()),
+        SourceSelection::Files(paths) => paths.first().and_then(|p| p.parent()).map(Path::to_path_buf),
+        _ => None,
     }
+}
 
-    // ── Go button (navigate to typed path) ────────────────────────────
-    {
-        let populate = populate.clone();
-        let host_entry = host_entry.clone();
-        let path_entry = path_entry.clone();
-        btn_go.connect_clicked(move |_| {
-            let host = host_entry.text().to_string().trim().to_string();
-            if host.is_empty() {
-                return;
+fn dir_is_read_only(dir: &Path) -> bool {
+    let probe = dir.join(format!("{}{}", stale::PROBE_PREFIX, std::process::id()));
+    let times = fs::metadata(dir).and_then(|m| preserve::times_of(&m));
+    match fs::OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            // The probe moved the folder's modification time on; put it
+            // back, for `--preserve` and for anyone looking.
+            if let Ok(times) = times {
+                let _ = fs::File::open(dir).and_then(|d| d.set_times(times));
             }
-            let path = path_entry.text().to_string().trim().to_string();
-            let path = if path.is_empty() { "~".to_string() } else { path };
-            populate(host, path);
-        });
+            false
+        }
+        Err(e) => e.kind() == std::io::ErrorKind::ReadOnlyFilesystem,
     }
+}
 
-    // ── Up button ─────────────────────────────────────────────────────
-    {
-        let populate = populate.clone();
-        let host_entry = host_entry.clone();
-        let path_entry = path_entry.clone();
-        btn_up.connect_clicked(move |_| {
-            let host = host_entry.text().to_string().trim().to_string();
-            if host.is_empty() {
-                return;
-            }
-            let current = path_entry.text().to_string();
-            let parent = Path::new(&current)
-                .parent()
-                .map(|p| p.to_string_lossy().to_string())
-                .unwrap_or_else(|| "/".to_string());
-            let parent = if parent.is_empty() { "/".to_string() } else { parent };
-            path_entry.set_text(&parent);
-            populate(host, parent);
-        });
+/// Delete the source of a moved file.  If the source filesystem has become
+/// read-only the file is left in place and counted in `ro_kept`, to be
+/// reported once by `push_read_only_summary` instead of once per file.
+fn remove_moved_source(path: &Path, ro_kept: &mut usize) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::ReadOnlyFilesystem => {
+            *ro_kept += 1;
+            Ok(())
+        }
+        other => other,
     }
+}
 
-    // ── Double-click on a row → navigate into directory ───────────────
-    {
-        let populate = populate.clone();
-        let host_entry = host_entry.clone();
-        let path_entry = path_entry.clone();
-        let entries = entries.clone();
-        let selected_path = selected_path.clone();
-        let btn_select = btn_select.clone();
-        listbox.connect_row_activated(move |_, row| {
-            let idx = row.index() as usize;
-            // Clone the entry data we need, then drop the borrow BEFORE
-            // calling populate (which takes a mutable borrow on entries).
-            let entry_snapshot = {
-                let ents = entries.borrow();
-                ents.get(idx).cloned()
-            };
-            if let Some(entry) = entry_snapshot {
-                if entry.is_dir {
-                    let host = host_entry.text().to_string().trim().to_string();
-                    let current = path_entry.text().to_string();
-                    let new_path = format!(
-                        "{}/{}",
-                        current.trim_end_matches('/'),
-                        entry.name
-                    );
-                    path_entry.set_text(&new_path);
-                    populate(host, new_path);
-                } else if !select_dirs_only {
-                    // File selected — set and enable
-                    let current = path_entry.text().to_string();
-                    let file_path = format!(
-                        "{}/{}",
-                        current.trim_end_matches('/'),
-                        entry.name,
-                    );
-                    *selected_path.borrow_mut() = Some(file_path);
-                    btn_select.set_sensitive(true);
-                }
-            }
-        });
+/// After a move out of a source folder that ended with no errors, remove
+/// the folders it emptied, and with `remove_root` the folder itself.
+fn tidy_moved_source(source: &SourceSelection, do_move: bool, remove_root: bool, files: &[PathBuf], errors: &Details) {
+    if let (true, SourceSelection::Directory(root)) = (do_move && errors.is_empty(), source) {
+        emptied::tidy_local(root, remove_root, files);
     }
+}
 
-    // ── Single click on a row → highlight / update selected_path ──────
-    {
-        let entries = entries.clone();
-        let path_entry = path_entry.clone();
-        let selected_path = selected_path.clone();
-        let btn_select = btn_select.clone();
-        listbox.connect_row_selected(move |_, row| {
-            if let Some(row) = row {
-                let idx = row.index() as usize;
-                let entry_snapshot = {
-                    let ents = entries.borrow();
-                    ents.get(idx).cloned()
-                };
-                if let Some(entry) = entry_snapshot {
-                    let current = path_entry.text().to_string();
-                    if entry.is_dir {
-                        let dir_path = format!(
-                            "{}/{}",
-                            current.trim_end_matches('/'),
-                            entry.name,
-                        );
-                        *selected_path.borrow_mut() = Some(dir_path);
-                        btn_select.set_sensitive(true);
-                    } else if !select_dirs_only {
-                        let file_path = format!(
-                            "{}/{}",
-                            current.trim_end_matches('/'),
-                            entry.name,
-                        );
-                        *selected_path.borrow_mut() = Some(file_path);
-                        btn_select.set_sensitive(true);
+/// `tidy_moved_source` for a folder on `host`, in batches over one
+/// connection.
+fn tidy_moved_remote_source(host: &str, ctl: &[String], base: &str, files: &[PathBuf], do_move: bool, remove_root: bool, errors: &Details) {
+    if !do_move || base.is_empty() || !errors.is_empty() {
+        return;
+    }
+    let dirs = emptied::candidates(Path::new(base), Some(host), remove_root, files.iter().map(PathBuf::as_path));
+    for chunk in dirs.chunks(LOOKUP_CHUNK) {
+        let result = Command::new("ssh")
+            .args(ctl)
+            .arg(host)
+            .arg(emptied::remote_script(chunk, |d| quote_path(d)))
+            .tracked_output();
+        match result {
+            Ok(o) => emptied::note_removed(o.stdout.iter().filter(|&&b| b == b'\n').count()),
+            Err(e) => debug_log(&format!("could not remove emptied source folders on {}: {}", host, e)),
+        }
+    }
+}
+
+/// Add the single summary line for sources kept because they were read-only.
+fn push_read_only_summary(errors: &mut Details, ro_kept: usize) {
+    if ro_kept > 0 {
+        errors.push(format!(
+            "Source is read-only: {} file(s) were copied but could not be removed from the source",
+            ro_kept
+        ));
+    }
+}
+
+// ── Before each file ───────────────────────────────────────────────────
+
+/// Called before the file at `index` in the plan: wait out a pause; note
+/// it as the file being worked on, for `--file-timeout`; once a quota
+/// error is in, ask the UI what to do about it (requesting a stop if told
+/// to cancel); then say whether this file is to be skipped for the quota.
+fn before_file(index: usize, tx: &mpsc::Sender<WorkerMsg>, cancel_flag: &CancelFlag) -> bool {
+    cancel_flag.wait_while_paused();
+    deadline::begin(index);
+    if let Some(message) = quota::unanswered() {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        let _ = tx.send(WorkerMsg::QuotaExceeded { message, reply: reply_tx });
+        let choice = reply_rx.recv().unwrap_or(quota::OnQuota::Cancel);
+        quota::answer(choice);
+        if choice == quota::OnQuota::Cancel {
+            cancel_flag.request(CancelIntent::Stop);
+        }
+    }
+    quota::skip()
+}
+
+// ── Unwritable destination folders ─────────────────────────────────────
+
+/// The warning for going ahead despite unwritable folders.
+fn unwritable_warning(dirs: &[UnwritableDir], choice: OnUnwritable) -> String {
+    let list: Vec<String> = dirs.iter().map(UnwritableDir::describe).collect();
+    match choice {
+        OnUnwritable::Skip => format!("skipping files bound for unwritable destination folders: {}", list.join(", ")),
+        _ => format!("destination folders are not writable, so their files will fail: {}", list.join(", ")),
+    }
+}
+
+/// Check the destination folders of `plan` (`dirs[i]` is where item `i`
+/// lands) and, if any cannot be written to, ask the UI what to do.
+/// Returns the items to transfer, adding any left out to `skipped`, or
+/// `None` when the run is aborted (the error has been sent).
+fn preflight_plan<T>(
+    plan: Vec<T>,
+    dirs: &[String],
+    verdicts: impl FnOnce(&[String]) -> HashMap<String, String>,
+    source_of: impl Fn(&T) -> String,
+    skipped: &mut Details,
+    tx: &mpsc::Sender<WorkerMsg>,
+) -> Option<Vec<T>> {
+    send_phase(tx, Phase::Preflight);
+    activity::set("checking destination folders");
+    let check = preflight::check(dirs, verdicts);
+    if check.unwritable.is_empty() {
+        return Some(plan);
+    }
+    let (reply_tx, reply_rx) = mpsc::channel();
+    let _ = tx.send(WorkerMsg::Unwritable { dirs: check.unwritable.clone(), reply: reply_tx });
+    match reply_rx.recv().unwrap_or(OnUnwritable::Abort) {
+        OnUnwritable::Abort => {
+            let list: Vec<String> = check.unwritable.iter().map(UnwritableDir::describe).collect();
+            let _ = tx.send(WorkerMsg::Error(format!(
+                "Nothing was copied: these destination folders are not writable: {}",
+                list.join(", ")
+            )));
+            None
+        }
+        OnUnwritable::Continue => Some(plan),
+        OnUnwritable::Skip => Some(
+            plan.into_iter()
+                .enumerate()
+                .filter_map(|(i, item)| match check.blocked.get(&i) {
+                    Some(dir) => {
+                        skipped.push(format!("{}: destination folder {} is not writable", source_of(&item), dir));
+                        None
                     }
-                }
-            }
-        });
+                    None => Some(item),
+                })
+                .collect(),
+        ),
     }
+}
 
-    // ── Cancel button ─────────────────────────────────────────────────
-    {
-        let dialog = dialog.clone();
-        btn_cancel.connect_clicked(move |_| {
-            dialog.close();
-        });
+/// Verdicts for folders on `host`, checked in batches over one connection.
+/// `dirs` are the folders as shown, `paths` their bytes by that text.
+fn remote_unwritable_verdicts(
+    host: &str,
+    ctl: &[String],
+    dirs: &[String],
+    paths: &HashMap<String, PathBuf>,
+) -> HashMap<String, String> {
+    let mut out = HashMap::new();
+    for chunk in dirs.chunks(LOOKUP_CHUNK) {
+        let quoted: Vec<OsString> = chunk.iter().filter_map(|d| paths.get(d)).map(quote_path).collect();
+        let result = Command::new("ssh")
+            .args(ctl)
+            .arg(host)
+            .arg(preflight::remote_script(&quoted))
+            .tracked_output();
+        match result {
+            Ok(o) => out.extend(
+                preflight::parse_remote_verdicts(&o.stdout).into_iter().map(|(dir, blame)| (shown(dir), shown(blame))),
+            ),
+            Err(e) => debug_log(&format!("could not check destination folders on {}: {}", host, e)),
+        }
     }
+    out
+}
 
-    // ── Select button ─────────────────────────────────────────────────
-    {
-        let dialog = dialog.clone();
-        let host_entry = host_entry.clone();
-        let path_entry = path_entry.clone();
-        let selected_path = selected_path.clone();
-        btn_select.connect_clicked(move |_| {
-            let host = host_entry.text().to_string().trim().to_string();
-            let path = selected_path
-                .borrow()
-                .clone()
-                .unwrap_or_else(|| path_entry.text().to_string());
-            if !host.is_empty() && !path.is_empty() {
-                on_select(format!("{}:{}", host, path));
+/// `preflight_plan` for an upload to `host`; `target_of` gives each
+/// item's remote destination path.
+fn preflight_remote<T>(
+    plan: Vec<T>,
+    host: &str,
+    ctl: &[String],
+    target_of: impl Fn(&T) -> &Path,
+    source_of: impl Fn(&T) -> String,
+    skipped: &mut Details,
+    tx: &mpsc::Sender<WorkerMsg>,
+) -> Option<Vec<T>> {
+    let parents: Vec<&Path> = plan.iter().map(|t| target_of(t).parent().unwrap_or(Path::new(""))).collect();
+    let dirs: Vec<String> = parents.iter().map(shown).collect();
+    let paths: HashMap<String, PathBuf> = dirs.iter().cloned().zip(parents.iter().map(|p| p.to_path_buf())).collect();
+    preflight_plan(plan, &dirs, |d| remote_unwritable_verdicts(host, ctl, d, &paths), source_of, skipped, tx)
+}
+
+/// `base` plus the folders holding `targets`, for one `mkdir -p`.
+fn remote_target_dirs<'a>(base: &str, targets: impl Iterator<Item = &'a Path>) -> HashSet<PathBuf> {
+    let mut dirs = parent_dirs(targets);
+    dirs.insert(PathBuf::from(base));
+    dirs
+}
+
+/// `preflight_plan` for a local "Folders and files" copy of a folder;
+/// other copies write into the destination itself or folders named after
+/// single parents, which the run creates, so they are not checked.
+fn preflight_local_tree(
+    files: Vec<PathBuf>,
+    src_dir: Option<&Path>,
+    transfer_mode: TransferMode,
+    dst_path: &Path,
+    renamer: &Renamer,
+    skipped: &mut Details,
+    tx: &mpsc::Sender<WorkerMsg>,
+) -> Option<Vec<PathBuf>> {
+    let Some(src_dir) = src_dir.filter(|_| transfer_mode == TransferMode::FoldersAndFiles) else {
+        return Some(files);
+    };
+    let dirs: Vec<String> = files
+        .iter()
+        .map(|f| local_tree_dest_dir(dst_path, src_dir, f, renamer).unwrap_or_default())
+        .collect();
+    preflight_plan(files, &dirs, preflight::local_verdicts, |f| shown(f), skipped, tx)
+}
+
+/// The folder a "Folders and files" copy from `src_dir` puts `file` in
+/// under `dst_path`, as the local workers build it.
+fn local_tree_dest_dir(dst_path: &Path, src_dir: &Path, file: &Path, renamer: &Renamer) -> Option<String> {
+    let input = PlanInput {
+        file,
+        src_dir: Some(src_dir),
+        transfer_mode: TransferMode::FoldersAndFiles,
+        parent_levels: 0,
+        dest: Dest::Local(dst_path),
+    };
+    let dest = destpath::build_destination(&input, |rel| renamer.apply_path(rel, modified(file))).ok()?;
+    dest.into_local().parent().map(shown)
+}
+
+// ── Leftovers of an earlier run ────────────────────────────────────────
+
+/// Look for what an earlier run left in the local destination `dst_path`
+/// and settle what to do with it (see `settle_stale`).
+fn settle_stale_artifacts(dst_path: &Path, tx: &mpsc::Sender<WorkerMsg>) -> bool {
+    stale::set_resumable(&[]);
+    activity::set("looking for leftovers of an earlier run");
+    let found = stale::find_local(dst_path);
+    settle_stale(found, shown(dst_path), tx, |paths| {
+        paths.iter().filter_map(|p| fs::remove_file(p).err().map(|e| (p.to_path_buf(), e))).collect()
+    })
+}
+
+/// `settle_stale_artifacts` for the folder `base` on `host`.
+fn settle_remote_stale_artifacts(host: &str, ctl: &[String], base: &str, tx: &mpsc::Sender<WorkerMsg>) -> bool {
+    stale::set_resumable(&[]);
+    activity::set(&format!("looking for leftovers of an earlier run on {}", host));
+    let listing = Command::new("ssh")
+        .args(ctl)
+        .arg(host)
+        .arg(stale::find_script(OsStr::new(base), |p| quote_path(p)))
+        .tracked_output();
+    let found = listing.map(|o| stale::found_remote(&o.stdout)).unwrap_or_default();
+    settle_stale(found, format!("{}:{}", host, base), tx, |paths| {
+        let mut script = OsString::from("rm -f -- ");
+        script.push(quoted_list(paths));
+        let why = match Command::new("ssh").args(ctl).arg(host).arg(script).tracked_output() {
+            Ok(o) if o.status.success() => return Vec::new(),
+            Ok(o) => String::from_utf8_lossy(&o.stderr).trim().to_string(),
+            Err(e) => e.to_string(),
+        };
+        paths.iter().map(|p| (p.to_path_buf(), std::io::Error::other(why.clone()))).collect()
+    })
+}
+
+/// Ask what to do about the leftovers `found` at `at`, deleting with
+/// `remove` when cleaning, and send the decision; or send the error and
+/// return `false` when the run is to copy nothing.
+fn settle_stale(
+    found: stale::Found,
+    at: String,
+    tx: &mpsc::Sender<WorkerMsg>,
+    remove: impl FnMut(&[&Path]) -> Vec<(PathBuf, std::io::Error)>,
+) -> bool {
+    if found.is_empty() {
+        return true;
+    }
+    let (reply_tx, reply_rx) = mpsc::channel();
+    let _ = tx.send(WorkerMsg::StaleArtifacts { found: found.clone(), at: at.clone(), reply: reply_tx });
+    let Some(mut choice) = reply_rx.recv().ok().flatten() else {
+        let _ = tx.send(WorkerMsg::Error(format!(
+            "Nothing was copied: {} left by an earlier run at {}; choose whether to resume, clean or ignore them (--stale-artifacts).",
+            found.describe(),
+            at
+        )));
+        return false;
+    };
+    if choice == stale::OnStale::Resume && !found.resumable {
+        choice = stale::OnStale::Ignore;
+    }
+    let decision = stale::Decision::apply(&found, choice, remove);
+    debug_log(&decision.describe());
+    if choice == stale::OnStale::Resume {
+        stale::set_resumable(&found.partials);
+    }
+    let _ = tx.send(WorkerMsg::Stale(decision));
+    true
+}
+
+// ── Free space at the destination ──────────────────────────────────────
+
+/// Whether the destination has room for files of `sizes`, by `free`
+/// (its free bytes, `None` when they couldn't be learnt), or the run is
+/// to go ahead `anyway`.  Sends the shortfall and the error otherwise.
+fn room_for_plan(sizes: &[Option<u64>], anyway: bool, free: impl FnOnce() -> Option<u64>, tx: &mpsc::Sender<WorkerMsg>) -> bool {
+    if anyway {
+        return true;
+    }
+    activity::set("checking free space at the destination");
+    let Some(short) = free().and_then(|free| space::shortfall(sizes, free)) else {
+        return true;
+    };
+    let _ = tx.send(WorkerMsg::ShortOfSpace(short.clone()));
+    let _ = tx.send(WorkerMsg::Error(format!(
+        "{}. Nothing was copied; tick Transfer anyway (--ignore-free-space) to try regardless.",
+        short.describe()
+    )));
+    false
+}
+
+/// The free bytes at the folder `dir` on `host`, or the deepest of its
+/// ancestors that exists.
+fn remote_free(host: &str, ctl: &[String], dir: &str) -> Option<u64> {
+    let result = Command::new("ssh").args(ctl).arg(host).arg(space::remote_command(&shell_quote(dir))).tracked_output();
+    match result {
+        Ok(o) if o.status.success() => space::parse_df(&String::from_utf8_lossy(&o.stdout)),
+        Ok(o) => {
+            debug_log(&format!("could not check free space on {}: {}", host, String::from_utf8_lossy(&o.stderr).trim()));
+            None
+        }
+        Err(e) => {
+            debug_log(&format!("could not check free space on {}: {}", host, e));
+            None
+        }
+    }
+}
+
+/// Whether the folders `source` and `dest` on `host` are on one
+/// filesystem (see `samehost::devices`); `None` when that can't be told.
+fn remote_same_device(host: &str, ctl: &[String], source: &Path, dest: &Path) -> Option<bool> {
+    let script = samehost::devices(source.as_os_str(), dest.as_os_str(), |p| quote_path(p));
+    let out = Command::new("ssh").args(ctl).arg(host).arg(script).tracked_output().ok()?;
+    samehost::same_device(&String::from_utf8_lossy(&out.stdout))
+}
+
+// ── Clashes within the batch ───────────────────────────────────────────
+
+/// Settle the files of `inputs` bound for the same path below the
+/// destination (see `collisions`), placed with the rename rules as the
+/// run will place them; `remote_source` files have no modification time
+/// to hand.  The error lists the clashes when `choice` stops the run.
+fn settle_collisions<'a>(
+    inputs: impl Iterator<Item = PlanInput<'a>>,
+    renamer: &Renamer,
+    remote_source: bool,
+    choice: OnCollision,
+) -> Result<(), String> {
+    let mut flat = false;
+    let placed: Vec<collisions::Placed> = inputs
+        .filter_map(|input| {
+            flat = input.transfer_mode == TransferMode::FilesOnly && input.src_dir.is_some();
+            let when = if remote_source { None } else { modified(input.file) };
+            let named = destpath::renamed_relative(&input, Path::to_path_buf).ok()?;
+            let target = renamer.apply_path(&named, when);
+            Some(collisions::Placed { source: input.file.to_path_buf(), named, target })
+        })
+        .collect();
+    collisions::settle(&placed, flat, choice).map(drop).map_err(|clashes| {
+        let list: Vec<String> = clashes.iter().map(collisions::Collision::describe).collect();
+        format!("Nothing was copied: these files would land on the same name: {}", list.join("; "))
+    })
+}
+
+// ── Two-phase moves ────────────────────────────────────────────────────
+
+/// What a two-phase move did with its sources, sent just before the result.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Deletion {
+    /// The copy had errors or was cancelled, so no source was deleted.
+    Skipped,
+    /// Every copy verified and the sources were deleted; `failed` of them
+    /// could not be and are listed as errors.
+    Ran { deleted: usize, failed: usize },
+    /// Cancelled while deleting: the copy is complete, `kept` sources remain.
+    Stopped { deleted: usize, kept: usize },
+    /// Verification was skipped for `files` copies, so no source was deleted.
+    Unverified { files: usize },
+}
+
+impl Deletion {
+    fn describe(&self) -> String {
+        match *self {
+            Deletion::Skipped => "Sources were not deleted because the copy did not complete".to_string(),
+            Deletion::Ran { deleted, failed: 0 } => {
+                format!("Every file copied and verified, then {} source(s) deleted", deleted)
             }
-            dialog.close();
-        });
+            Deletion::Ran { deleted, failed } => format!(
+                "Every file copied and verified, then {} source(s) deleted ({} could not be)",
+                deleted, failed
+            ),
+            Deletion::Stopped { deleted, kept } => format!(
+                "Deletion cancelled after {} source(s); the copy is complete and {} source(s) remain",
+                deleted, kept
+            ),
+            Deletion::Unverified { files } => format!(
+                "Sources were not deleted because {} file(s) were copied without verification",
+                files
+            ),
+        }
     }
 
-    // ── Enter key in path entry → Go ──────────────────────────────────
-    {
-        let populate = populate.clone();
-        let host_entry = host_entry.clone();
-        let path_entry_c = path_entry.clone();
-        path_entry.connect_activate(move |_| {
-            let host = host_entry.text().to_string().trim().to_string();
-            if host.is_empty() {
-                return;
+    fn to_json(self) -> String {
+        match self {
+            Deletion::Skipped => "{\"ran\":false}".to_string(),
+            Deletion::Ran { deleted, failed } => {
+                format!("{{\"ran\":true,\"deleted\":{},\"failed\":{}}}", deleted, failed)
             }
-            let path = path_entry_c.text().to_string().trim().to_string();
-            let path = if path.is_empty() { "/".to_string() } else { path };
-            populate(host, path);
-        });
+            Deletion::Stopped { deleted, kept } => {
+                format!("{{\"ran\":true,\"cancelled\":true,\"deleted\":{},\"kept\":{}}}", deleted, kept)
+            }
+            Deletion::Unverified { files } => format!("{{\"ran\":false,\"unverified\":{}}}", files),
+        }
     }
+}
 
-    // ── Enter key in host entry → Connect ─────────────────────────────
-    {
-        let populate = populate.clone();
-        let host_entry_c = host_entry.clone();
-        let path_entry = path_entry.clone();
-        host_entry.connect_activate(move |_| {
-            let host = host_entry_c.text().to_string().trim().to_string();
-            if host.is_empty() {
-                return;
+/// The deletion phase of a two-phase move.  Once every file has been copied
+/// and verified (`errors` is still empty and no file was left `unverified`),
+/// delete `sources` — local paths, or paths on `host`.  Sends `WorkerMsg::Deletion` with the outcome and
+/// returns `true` if it was cancelled part way.
+fn delete_deferred_sources(
+    sources: &[PathBuf],
+    unverified: usize,
+    host: Option<&str>,
+    ctl: &[String],
+    errors: &mut Details,
+    ro_kept: &mut usize,
+    cancel_flag: &CancelFlag,
+    tx: &mpsc::Sender<WorkerMsg>,
+) -> bool {
+    if !errors.is_empty() {
+        let _ = tx.send(WorkerMsg::Deletion(Deletion::Skipped));
+        return false;
+    }
+    if unverified > 0 {
+        let _ = tx.send(WorkerMsg::Deletion(Deletion::Unverified { files: unverified }));
+        return false;
+    }
+    let total = sources.len();
+    let _ = tx.send(WorkerMsg::DeletePhase { total });
+    let mut deleted = 0usize;
+    let mut failed = 0usize;
+    for (i, src) in sources.iter().enumerate() {
+        if cancel_flag.stopping() {
+            let _ = tx.send(WorkerMsg::Deletion(Deletion::Stopped { deleted, kept: total - i }));
+            return true;
+        }
+        let result = match host {
+            None => {
+                let kept_before = *ro_kept;
+                remove_moved_source(src, ro_kept)
+                    .map(|()| *ro_kept == kept_before)
+                    .map_err(|e| e.to_string())
             }
-            let path = path_entry.text().to_string().trim().to_string();
-            let path = if path.is_empty() { "/".to_string() } else { path };
-            populate(host, path);
+            Some(host) => {
+                let rm = Command::new("ssh")
+                    .args(ctl)
+                    .arg(host)
+                    .arg(path_command("rm -f {}", &[src.as_os_str()]))
+                    .tracked_status();
+                match rm {
+                    Ok(s) if s.success() => Ok(true),
+                    Ok(s) => Err(format!("rm exited with {}", s)),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+        };
+        match result {
+            Ok(true) => deleted += 1,
+            Ok(false) => {}
+            Err(e) => {
+                failed += 1;
+                errors.push(format!("{}: copied and verified but failed to delete source: {}", shown(src), e));
+            }
+        }
+        let _ = tx.send(WorkerMsg::Progress {
+            done: i + 1,
+            total,
+            file: shown(src),
         });
     }
+    let _ = tx.send(WorkerMsg::Deletion(Deletion::Ran { deleted, failed }));
+    false
+}
 
-    // Auto-connect if host was pre-filled
-    if !initial_host.is_empty() {
-        let populate = populate.clone();
-        let host = initial_host.clone();
-        let path = initial_path.clone();
-        glib::idle_add_local_once(move || {
-            populate(host, path);
-        });
+// ── Skipping verification ──────────────────────────────────────────────
+
+/// Whether to verify the file just copied: not once the user has asked to
+/// skip the remaining verification.  Announces the Verifying phase if so.
+fn start_verify(cancel_flag: &CancelFlag, tx: &mpsc::Sender<WorkerMsg>) -> bool {
+    if cancel_flag.skipping_verify() {
+        return false;
     }
+    send_phase(tx, Phase::Verifying);
+    true
+}
 
-    dialog.present();
+/// Run `verify` on the file just copied, the one at `index` in the plan,
+/// or `None` if verification is being skipped.  Workers list a skipped
+/// file as unverified: it is not counted as copied, and a move keeps its
+/// source.  A file `--verify-sample` left out passes without a check.
+fn verify_unless_skipped<E>(
+    cancel_flag: &CancelFlag,
+    tx: &mpsc::Sender<WorkerMsg>,
+    index: usize,
+    verify: impl FnOnce() -> Result<bool, E>,
+) -> Option<Result<bool, E>> {
+    if !sample::includes(index) {
+        return Some(Ok(true));
+    }
+    let result = start_verify(cancel_flag, tx).then(verify);
+    if result.is_some() {
+        sample::note_verified(index);
+    }
+    result
 }
 
-// ── Helper: open folder picker ─────────────────────────────────────────
+/// Send the plan, picking the files a `--verify-sample` run verifies and
+/// noting their sizes for `--file-timeout`.
+fn send_plan(tx: &mpsc::Sender<WorkerMsg>, sizes: Vec<Option<u64>>) {
+    sample::plan(&sizes);
+    deadline::plan(&sizes);
+    activity::set_stage(Phase::Transferring);
+    let _ = tx.send(WorkerMsg::Plan { sizes });
+}
 
-fn pick_folder(window: &ApplicationWindow, target_entry: Entry) {
-    let dialog = FileDialog::builder()
-        .title("Select folder")
-        .modal(true)
-        .build();
+/// Tell the UI the run has reached `phase`; commands printed with
+/// `--print-commands` carry it from now on.
+fn send_phase(tx: &mpsc::Sender<WorkerMsg>, phase: Phase) {
+    activity::set_stage(phase);
+    let _ = tx.send(WorkerMsg::Phase(phase));
+}
 
-    dialog.select_folder(Some(window), gtk4::gio::Cancellable::NONE, move |result| {
-        if let Ok(file) = result {
-            if let Some(path) = file.path() {
-                target_entry.set_text(&path.to_string_lossy());
+// ── Local moves ────────────────────────────────────────────────────────
+
+/// How a moved file reached its destination.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MoveMethod {
+    /// Same filesystem: renamed in place.
+    Renamed,
+    /// Across filesystems: this many bytes copied, verified, then deleted.
+    Copied(u64),
+    /// Across filesystems: copied, but verification was skipped, so the
+    /// source was kept.
+    Unverified,
+}
+
+/// Breakdown of a local move by mechanism, reported with the summary.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct MoveStats {
+    renamed: usize,
+    copied: usize,
+    copied_bytes: u64,
+    /// Already identical at the destination; only the source was deleted.
+    identical: usize,
+}
+
+impl MoveStats {
+    fn record(&mut self, method: MoveMethod) {
+        match method {
+            MoveMethod::Renamed => self.renamed += 1,
+            MoveMethod::Copied(bytes) => {
+                self.copied += 1;
+                self.copied_bytes += bytes;
             }
+            MoveMethod::Unverified => {}
         }
-    });
+    }
+
+    /// e.g. `11950 renamed, 450 copied across devices (9.8 GiB)`.
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.renamed > 0 {
+            parts.push(format!("{} renamed", self.renamed));
+        }
+        if self.copied > 0 {
+            parts.push(format!(
+                "{} copied across devices ({})",
+                self.copied,
+                format_bytes(self.copied_bytes)
+            ));
+        }
+        if self.identical > 0 {
+            parts.push(format!("{} already at destination", self.identical));
+        }
+        parts.join(", ")
+    }
 }
 
-// ── Helper: refresh the exclusion display ──────────────────────────────
+/// Whether `a` and `b` are on the same filesystem.  Paths that don't exist
+/// yet are judged by their nearest existing ancestor; `None` if unknown.
+fn same_device(a: &Path, b: &Path) -> Option<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let dev = |p: &Path| p.ancestors().find_map(|a| fs::metadata(a).ok()).map(|m| m.dev());
+    Some(dev(a)? == dev(b)?)
+}
 
-fn refresh_exclusion_view(view: &TextView, items: &[String]) {
-    let display: Vec<String> = items
-        .iter()
-        .map(|item| {
-            if item.starts_with("~/") {
-                // Wildcard directory pattern
-                format!("{}/ (dir pattern)", &item[1..])
-            } else if item.starts_with('~') {
-                // Wildcard file pattern
-                format!("{} (file pattern)", &item[1..])
-            } else if item.starts_with('/') {
-                format!("{}/ (recursive)", item)
-            } else {
-                item.clone()
-            }
-        })
-        .collect();
-    view.buffer().set_text(&display.join("\n"));
+/// `(st_dev, st_ino)` of what `path` points to, following symlinks.
+fn file_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
 }
 
-// ── Destination parsing ─────────────────────────────────────────────────
+/// Whether `a` and `b` are the same object on disk: one path, a symlink to
+/// the other, a hard link, or a file seen through a bind mount.
+fn same_file(a: &Path, b: &Path) -> bool {
+    file_id(a).is_some_and(|id| file_id(b) == Some(id))
+}
 
-/// Parse "host:/path" → (Some(host), path).  Plain paths → (None, path).
-fn parse_destination(dst: &str) -> (Option<String>, String) {
-    if let Some(pos) = dst.find(':') {
-        let host = &dst[..pos];
-        let path = &dst[pos + 1..];
-        // Only treat as remote if host has no slashes and path is non-empty
-        if !host.is_empty() && !host.contains('/') && !path.is_empty() {
-            return (Some(host.to_string()), path.to_string());
-        }
-    }
-    (None, dst.to_string())
+/// Whether `inner` is `outer` or lies below it once symlinks are resolved.
+/// Ancestors are compared by inode, so bind mounts of `outer` count too.
+/// A path that doesn't exist yet is judged by its nearest existing ancestor.
+fn is_within(inner: &Path, outer: &Path) -> bool {
+    let Some(outer_id) = file_id(outer) else { return false };
+    inner
+        .ancestors()
+        .find_map(|a| a.canonicalize().ok())
+        .is_some_and(|real| real.ancestors().any(|a| file_id(a) == Some(outer_id)))
 }
 
-/// Shell-escape a string with single quotes (for ssh remote commands).
-fn shell_quote(s: &str) -> String {
-    format!("'{}'", s.replace('\'', "'\\''"))
+/// A typed local folder as a run would reach it: through any symlinks
+/// when it exists, otherwise made absolute, and without trailing slashes.
+fn resolved_folder(path: &Path) -> PathBuf {
+    let resolved = path.canonicalize().or_else(|_| std::path::absolute(path)).unwrap_or_else(|_| path.to_path_buf());
+    resolved.components().collect()
 }
 
-/// Escape a remote path for rsync's `host:path` syntax.
-///
-/// rsync passes the path portion of `host:path` through the remote shell,
-/// so characters like spaces, parentheses, and other shell metacharacters
-/// must be backslash-escaped.  This avoids needing `--protect-args` which
-/// is not supported by macOS's bundled openrsync.
-fn rsync_escape_remote(path: &str) -> String {
-    let mut out = String::with_capacity(path.len() + 16);
-    for ch in path.chars() {
-        match ch {
-            ' ' | '(' | ')' | '\'' | '"' | '&' | ';' | '|' | '$' | '`'
-            | '!' | '#' | '*' | '?' | '[' | ']' | '{' | '}' | '\\' => {
-                out.push('\\');
-                out.push(ch);
-            }
-            _ => out.push(ch),
-        }
+/// Why a local source folder and destination can't be used together: they
+/// are the same folder, however they were typed, or the destination lies
+/// inside the source, where the scan would find the run's own copies.  A
+/// trailing slash, a relative path or a symlink doesn't hide it, nor, with
+/// `case_insensitive`, does a difference in case alone.
+fn source_overlap_error(source: &SourceSelection, dst: &str, case_insensitive: bool) -> Option<String> {
+    let (SourceSelection::Directory(src) | SourceSelection::DirectoryItems(src, _)) = source else { return None };
+    let dst = dst.trim();
+    if src.is_file() || dst.is_empty() || parse_destination(dst).0.is_some() {
+        return None;
     }
-    out
+    let (from, to) = (resolved_folder(src), resolved_folder(Path::new(dst)));
+    let same = if case_insensitive {
+        from.to_string_lossy().to_lowercase() == to.to_string_lossy().to_lowercase()
+    } else {
+        from == to
+    };
+    if same {
+        return Some(format!(
+            "Source {} and destination {} are the same folder, {}; choose a different destination",
+            shown(src),
+            dst,
+            shown(from)
+        ));
+    }
+    is_within(&to, &from).then(|| {
+        format!(
+            "Destination {} is inside the source folder {}, so the run would copy its own copies; choose a destination outside it",
+            dst,
+            shown(src)
+        )
+    })
 }
 
-/// Find a unique local path by appending "_1", "_2", etc. before the extension.
-fn find_unique_local_path(original: &Path) -> PathBuf {
-    let parent = original.parent().unwrap_or_else(|| Path::new("."));
-    let stem = original.file_stem().unwrap_or_default().to_string_lossy().to_string();
-    let ext = original.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
-    let mut n = 1u32;
-    loop {
-        let candidate = parent.join(format!("{}_{}{}", stem, n, ext));
-        if !candidate.exists() {
-            return candidate;
-        }
-        n += 1;
+/// Warning for a local destination that overlaps the source folder once
+/// symlinks and bind mounts are resolved, so a transfer could read and
+/// write the same files.
+fn overlap_warning(source: &SourceSelection, dest: &str) -> Option<String> {
+    let root = source_root(source)?;
+    let dest = Path::new(dest);
+    if is_within(dest, &root) {
+        Some(format!("Destination {} is inside the source folder {}", shown(dest), shown(root)))
+    } else if is_within(&root, dest) {
+        Some(format!("Source folder {} is inside the destination {}", shown(root), shown(dest)))
+    } else {
+        None
     }
 }
 
-/// Find a unique remote path by appending "_1", "_2", etc. before the extension.
-/// Checks existence via SSH.
-#[allow(dead_code)]
-fn find_unique_remote_path(
-    original: &str,
-    host: &str,
-    ctl: &[&str],
-) -> String {
-    let path = Path::new(original);
-    let parent = path.parent().unwrap_or_else(|| Path::new(".")).to_string_lossy().to_string();
-    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
-    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
-    let mut n = 1u32;
-    loop {
-        let candidate = format!("{}/{}_{}{}", parent, stem, n, ext);
-        let check = Command::new("ssh")
-            .args(ctl)
-            .arg(host)
-            .arg(format!("test -e {}", shell_quote(&candidate)))
-            .status();
-        match check {
-            Ok(s) if s.success() => {
-                // exists, try next number
-                n += 1;
-            }
-            _ => return candidate,
-        }
-    }
+/// Skip reason for a file whose destination is the source itself.
+const SAME_FILE_NOTE: &str = "source and destination are the same file";
+
+/// Skip reason for a file Newest wins keeps the destination's version of.
+const DEST_NEWER_NOTE: &str = "destination is newer";
+
+/// Skip reason for a file that looks newer than the destination's only by
+/// less than the skew between the two hosts' clocks.
+const WITHIN_SKEW_NOTE: &str = "source is newer only within the clock skew between the hosts";
+
+/// Status text warning that a local move will copy rather than rename.
+const CROSS_FS_MOVE_NOTE: &str = "Cross-filesystem move — files will be copied and verified";
+
+/// Whether moving `source` to the local `dest` is known to cross filesystems.
+fn is_cross_fs_move(source: &SourceSelection, dest: &str) -> bool {
+    source_root(source).and_then(|r| same_device(&r, Path::new(dest))) == Some(false)
 }
 
-/// Find a unique remote path using the pre-fetched set of existing files.
-fn find_unique_remote_path_from_set(
-    original: &str,
-    existing: &HashSet<String>,
-) -> String {
-    let path = Path::new(original);
-    let parent = path.parent().unwrap_or_else(|| Path::new(".")).to_string_lossy().to_string();
-    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
-    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
-    let mut n = 1u32;
-    loop {
-        let candidate = format!("{}/{}_{}{}", parent, stem, n, ext);
-        if !existing.contains(&candidate) {
-            return candidate;
-        }
-        n += 1;
+/// Move the link at `src` to `dst` as a link (`--symlinks preserve`):
+/// renamed where possible, otherwise made again and the original removed.
+fn move_link(src: &Path, dst: &Path, try_rename: bool, ro_kept: &mut usize) -> std::io::Result<MoveMethod> {
+    if try_rename && fs::rename(src, dst).is_ok() {
+        return Ok(MoveMethod::Renamed);
     }
+    symlinks::recreate(src, dst)?;
+    remove_moved_source(src, ro_kept)?;
+    Ok(MoveMethod::Copied(0))
 }
 
-/// Strip spaces from path components beyond the base destination directory.
-fn strip_spaces_from_path(base: &Path, full: &Path) -> PathBuf {
-    match full.strip_prefix(base) {
-        Ok(rel) => {
-            let cleaned: PathBuf = rel
-                .components()
-                .map(|c| {
-                    let s = c.as_os_str().to_string_lossy();
-                    std::ffi::OsString::from(s.replace(' ', ""))
-                })
-                .collect();
-            base.join(cleaned)
+/// Move one file.  When `try_rename` is set an instant rename is tried
+/// first; otherwise (or if the rename fails, e.g. across devices) the file
+/// is copied and checked as `opts` says, and the source deleted.
+/// `verify` is asked once the copy is done; if it says no, the copy is
+/// kept unverified and so is the source.
+fn move_file(
+    src: &Path,
+    dst: &Path,
+    try_rename: bool,
+    ro_kept: &mut usize,
+    opts: &RunOptions,
+    on_progress: impl FnMut(u64),
+    verify: impl FnOnce() -> bool,
+) -> std::io::Result<MoveMethod> {
+    if try_rename && fs::rename(src, dst).is_ok() {
+        return Ok(MoveMethod::Renamed);
+    }
+    let bytes = copy_file_chunked(src, dst, opts, on_progress)?;
+    if !verify() {
+        return Ok(MoveMethod::Unverified);
+    }
+    match verify_copy(src, dst, opts) {
+        Ok(true) => remove_moved_source(src, ro_kept).map(|_| MoveMethod::Copied(bytes)),
+        Ok(false) => {
+            let _ = fs::remove_file(dst);
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "integrity check failed — original retained",
+            ))
         }
-        Err(_) => full.to_path_buf(),
+        Err(e) => Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("verification error (original retained): {}", e),
+        )),
     }
 }
 
-// ── Wildcard pattern matching ──────────────────────────────────────────
+// ── Byte-by-byte file comparison ───────────────────────────────────────
 
-/// Match a name against a pattern that may contain `*` (any chars) and `?`
-/// (single char) wildcards.  Matching is case-insensitive and only ever
-/// applied to a single path component (file or directory name).
-fn wildcard_matches(pattern: &str, name: &str) -> bool {
-    let p: Vec<char> = pattern.to_lowercase().chars().collect();
-    let n: Vec<char> = name.to_lowercase().chars().collect();
-    wildcard_match_inner(&p, &n)
+/// Whether the local copy `b` matches `a`: byte for byte, or, for a link
+/// kept as a link by `links`, by where it points.
+fn copies_match(a: &Path, b: &Path, links: symlinks::Policy) -> std::io::Result<bool> {
+    match symlinks::preserved(links, a) {
+        true => symlinks::same_target(a, b),
+        false => files_are_identical(a, b),
+    }
 }
 
-fn wildcard_match_inner(pattern: &[char], name: &[char]) -> bool {
-    match (pattern.first(), name.first()) {
-        (None, None) => true,
-        (Some('*'), _) => {
-            // '*' matches zero or more characters
-            wildcard_match_inner(&pattern[1..], name)
-                || (!name.is_empty() && wildcard_match_inner(pattern, &name[1..]))
+/// Whether the local copy `b` checks out against `a` under the run's
+/// verification (`--verify`); a link kept as a link by where it points.
+fn verify_copy(a: &Path, b: &Path, opts: &RunOptions) -> std::io::Result<bool> {
+    if symlinks::preserved(opts.symlinks, a) {
+        return symlinks::same_target(a, b);
+    }
+    match opts.verify {
+        VerifyMode::Full if filereport::recording() => {
+            // The detail report keeps the digest, taken while comparing.
+            let hash = hashcache::compare_and_hash(a, b)?;
+            if let Some(hash) = &hash {
+                filereport::hashed(&reverify::Location::Local(a.to_path_buf()), "sha256", &hashcache::hex(hash));
+            }
+            Ok(hash.is_some())
         }
-        (Some('?'), Some(_)) => wildcard_match_inner(&pattern[1..], &name[1..]),
-        (Some(pc), Some(nc)) if *pc == *nc => wildcard_match_inner(&pattern[1..], &name[1..]),
-        _ => false,
+        VerifyMode::Full => files_are_identical(a, b),
+        VerifyMode::Hash => {
+            let algo = opts.hash_algo;
+            let hash = hash_local(a, algo)?;
+            let same = hash == hash_local(b, algo)?;
+            if same {
+                filereport::hashed(&reverify::Location::Local(a.to_path_buf()), algo.name(), &hash);
+            }
+            Ok(same)
+        }
+        VerifyMode::QuickSizeMtime => verify::same_size_and_time(a, b, opts.preserve),
+        VerifyMode::None => Ok(true),
     }
 }
 
-// ── File collection (shared by local & remote workers) ─────────────────
+fn files_are_identical(a: &Path, b: &Path) -> std::io::Result<bool> {
+    let meta_a = fs::metadata(a)?;
+    let meta_b = fs::metadata(b)?;
+    if meta_a.len() != meta_b.len() {
+        return Ok(false);
+    }
 
-fn collect_files(
-    source: &SourceSelection,
-    patterns: &[String],
-) -> Result<(Vec<PathBuf>, usize, usize), String> {
-    match source {
-        SourceSelection::None => Err("No source selected.".to_string()),
-        SourceSelection::Remote(_, _) => Err("Remote source uses its own file listing.".to_string()),
-        SourceSelection::Files(paths) => Ok((paths.clone(), 0, 0)),
-        SourceSelection::Directory(src_dir) => {
-            // Exact directory exclusions: "/dirname"
-            let excluded_dirs: HashSet<String> = patterns
-                .iter()
-                .filter(|p| p.starts_with('/') && !p.starts_with("~/"))
-                .map(|p| p.trim_start_matches('/').to_string())
-                .collect();
-            // Exact file exclusions: "filename"
-            let excluded_files: HashSet<String> = patterns
-                .iter()
-                .filter(|p| !p.starts_with('/') && !p.starts_with('~'))
-                .cloned()
-                .collect();
-            // Wildcard directory patterns: "~/pattern" → "pattern"
-            let wildcard_dirs: Vec<String> = patterns
-                .iter()
-                .filter(|p| p.starts_with("~/"))
-                .map(|p| p[2..].to_string())
-                .collect();
-            // Wildcard file patterns: "~pattern" (but not "~/...")
-            let wildcard_files: Vec<String> = patterns
-                .iter()
-                .filter(|p| p.starts_with('~') && !p.starts_with("~/"))
-                .map(|p| p[1..].to_string())
-                .collect();
+    activity::set(&format!("comparing {} with {}", shown(a), shown(b)));
+    let mut fa = fs::File::open(a)?;
+    let mut fb = fs::File::open(b)?;
+    let mut buf_a = [0u8; 8192];
+    let mut buf_b = [0u8; 8192];
 
-            let src_dir = src_dir.clone();
-            let mut collected = Vec::new();
-            let mut excluded_file_count = 0usize;
-            let excluded_dir_count = Cell::new(0usize);
-            for entry in WalkDir::new(&src_dir).into_iter().filter_entry(|e| {
-                if e.path() == src_dir.as_path() {
-                    return true;
-                }
-                if e.file_type().is_dir() {
-                    let name = e.file_name().to_string_lossy().to_string();
-                    if excluded_dirs.contains(&name) {
-                        excluded_dir_count.set(excluded_dir_count.get() + 1);
-                        return false;
-                    }
-                    if wildcard_dirs.iter().any(|pat| wildcard_matches(pat, &name)) {
-                        excluded_dir_count.set(excluded_dir_count.get() + 1);
-                        return false;
-                    }
-                    return true;
-                }
-                true
-            }) {
-                match entry {
-                    Ok(e) if e.file_type().is_file() => {
-                        let name = e.file_name().to_string_lossy().to_string();
-                        if excluded_files.contains(&name)
-                            || wildcard_files.iter().any(|pat| wildcard_matches(pat, &name))
-                        {
-                            excluded_file_count += 1;
-                        } else {
-                            collected.push(e.into_path());
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            Ok((collected, excluded_file_count, excluded_dir_count.get()))
+    loop {
+        let n_a = fa.read(&mut buf_a)?;
+        let n_b = fb.read(&mut buf_b)?;
+        if n_a != n_b || buf_a[..n_a] != buf_b[..n_b] {
+            return Ok(false);
+        }
+        if n_a == 0 {
+            return Ok(true);
         }
     }
 }
 
-// ── Worker thread (local) ──────────────────────────────────────────────
+// ── Remote file listing ────────────────────────────────────────────────
 
-fn run_worker(
-    source: SourceSelection,
-    dst: String,
-    do_move: bool,
-    conflict_mode: ConflictMode,
-    strip_spaces: bool,
-    transfer_mode: TransferMode,
+/// List the files of a remote source: either the explicit `src_files`
+/// (e.g. loaded from a failed-files list) or everything under `remote_base`.
+fn collect_remote_source(
+    host: &str,
+    ctl: &[String],
+    remote_base: &str,
+    src_files: &[PathBuf],
     patterns: &[String],
-    cancel_flag: Arc<AtomicBool>,
-    tx: mpsc::Sender<WorkerMsg>,
-) {
-    let dst_path = PathBuf::from(&dst);
-
-    // Create destination directory if it doesn't exist
-    if !dst_path.exists() {
-        if let Err(e) = fs::create_dir_all(&dst_path) {
-            let _ = tx.send(WorkerMsg::Error(format!(
-                "Failed to create destination directory: {}",
-                e
-            )));
-            return;
-        }
+    guard: &mut ScanGuard,
+) -> Result<(RemoteListing, usize, usize), String> {
+    if src_files.is_empty() {
+        collect_remote_files(host, ctl, remote_base, patterns, guard)
+    } else {
+        Ok((src_files.iter().map(|f| (f.clone(), None)).collect(), 0, 0))
     }
+}
 
-    // Collect the files to process
-    let (files, excluded_files, excluded_dirs) = match collect_files(&source, patterns) {
-        Ok(v) => v,
-        Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(e));
-            return;
-        }
+/// Remote files as `(path, size)` pairs; `None` when the size is unknown.
+/// Paths keep the bytes the host listed, UTF-8 or not.
+type RemoteListing = Vec<(PathBuf, Option<u64>)>;
+
+/// List files on a remote host under `remote_base`, applying exclusion
+/// patterns and the owner filter.
+/// Returns (Vec<(remote_path, size)>, excluded_file_count, excluded_dir_count).
+///
+/// Each record of the listing is `<size> <uid>:<gid>:<user>:<group>\t<path>`
+/// ending in a NUL, so a name holding a newline is listed whole.
+/// The fields come from GNU `stat -c` or BSD `stat -f`; if neither works
+/// they are left empty and the file's size is reported as unknown.  With
+/// an owner filter, `find` leaves out the other files itself and prints
+/// `#owner <n>` records for each batch of them.  A `find` that rejects the tests
+/// (e.g. a user unknown on that host) is run again without them, and the
+/// listing's owner fields are checked instead.  The files listed so far,
+/// before any are left out, go to `guard` as the records arrive.
+fn collect_remote_files(
+    host: &str,
+    ctl: &[String],
+    remote_base: &str,
+    patterns: &[String],
+    guard: &mut ScanGuard,
+) -> Result<(RemoteListing, usize, usize), String> {
+    const LIST: &str = "for f; do m=$(stat -c \"%s %u:%g:%U:%G\" \"$f\" 2>/dev/null || stat -f \"%z %u:%g:%Su:%Sg\" \"$f\" 2>/dev/null); printf \"%s\\t%s\\000\" \"$m\" \"$f\"; done";
+    let filter = owners::current();
+    let mut list = |tests: &str| {
+        let select = match tests {
+            "" => format!("-exec sh -c '{}' sh {{}} +", LIST),
+            tests => format!("\\( {} -exec sh -c '{}' sh {{}} + -o -exec sh -c 'printf \"#owner %s\\000\" $#' sh {{}} + \\)", tests, LIST),
+        };
+        let mut stdout = Vec::new();
+        let out = Command::new("ssh")
+            .args(ctl)
+            .arg(host)
+            .arg(format!("find {} -type f {} 2>/dev/null", shell_quote(remote_base), select))
+            .tracked_records(0, |record| {
+                if !record.starts_with(b"#") {
+                    let size = record.split(|&b| b == b' ').next().and_then(|s| std::str::from_utf8(s).ok()?.parse().ok());
+                    guard.found(size.unwrap_or(0));
+                }
+                stdout.extend_from_slice(record);
+                stdout.push(0);
+            })
+            .map_err(|e| format!("Failed to list remote files: {}", e))?;
+        guard.flush();
+        Ok::<_, String>(std::process::Output { stdout, ..out })
     };
+    let tests = filter.find_tests(shell_quote);
+    let mut out = list(&tests)?;
+    if !tests.is_empty() && !out.status.success() && out.stdout.is_empty() {
+        debug_log(&format!("{}: find rejected the owner tests; filtering the listing instead", host));
+        out = list("")?;
+    }
 
-    let total = files.len();
-    if total == 0 {
-        let _ = tx.send(WorkerMsg::Finished {
-            copied: 0,
-            skipped: vec![],
-            excluded_files,
-            excluded_dirs,
-            errors: vec![],
-        });
-        return;
+    if !out.status.success() {
+        return Err(format!(
+            "Failed to list remote files: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
     }
 
-    // Determine the source directory (only relevant for "Folders and files" mode)
-    let src_dir = match &source {
-        SourceSelection::Directory(d) => Some(d.clone()),
-        _ => None,
-    };
+    let (collected, excluded_files, excluded_dirs, in_excluded_dirs, owner_filtered) =
+        parse_remote_listing(&out.stdout, remote_base, patterns, &filter);
+    owners::note_filtered(owner_filtered);
+    patterns::note_in_excluded_dirs(in_excluded_dirs);
+    Ok((collected, excluded_files, excluded_dirs))
+}
 
-    let mut copied = 0usize;
-    let mut skipped: Vec<String> = Vec::new();
-    let mut errors: Vec<String> = Vec::new();
+/// The files of a remote `find` listing (see `collect_remote_files`) that
+/// the patterns and `filter` keep, with the files excluded by pattern, the
+/// folders excluded (each counted once by its path, the outermost where
+/// they nest), the files under those folders, and the files left out by
+/// owner.  Patterns are matched against the names as text; the paths
+/// kept are the listed bytes.
+fn parse_remote_listing(
+    stdout: &[u8],
+    remote_base: &str,
+    patterns: &[String],
+    filter: &owners::OwnerFilter,
+) -> (RemoteListing, usize, usize, usize, usize) {
+    // Parse exclusion patterns
+    let excluded_dirs: HashSet<String> = patterns
+        .iter()
+        .filter(|p| p.starts_with('/') && !p.starts_with("~/"))
+        .map(|p| p.trim_start_matches('/').to_string())
+        .collect();
+    let excluded_files: HashSet<String> = patterns
+        .iter()
+        .filter(|p| !p.starts_with('/') && !p.starts_with('~') && !p.starts_with('+') && !p.starts_with('^'))
+        .cloned()
+        .collect();
+    let wildcard_dirs: Vec<String> = patterns
+        .iter()
+        .filter(|p| p.starts_with("~/"))
+        .map(|p| p[2..].to_string())
+        .collect();
+    let wildcard_files: Vec<String> = patterns
+        .iter()
+        .filter(|p| p.starts_with('~') && !p.starts_with("~/"))
+        .map(|p| p[1..].to_string())
+        .collect();
+    let include_files: Vec<String> = patterns
+        .iter()
+        .filter_map(|p| p.strip_prefix('+').map(|p| p.to_string()))
+        .collect();
+    let path_patterns: Vec<String> = patterns
+        .iter()
+        .filter_map(|p| p.strip_prefix('^').map(|p| p.to_string()))
+        .collect();
+    let path_matches = |rel: &str| path_patterns.iter().any(|pat| path_pattern_matches(pat, rel));
 
-    for (i, file_path) in files.iter().enumerate() {
-        if cancel_flag.load(Ordering::SeqCst) {
-            let _ = tx.send(WorkerMsg::Cancelled {
-                copied,
-                skipped,
-                excluded_files,
-                excluded_dirs,
-                errors,
-            });
-            return;
+    let remote_base_slash = format!("{}/", remote_base.trim_end_matches('/'));
+    let remote_base_slash = remote_base_slash.as_bytes();
+    let mut collected = Vec::new();
+    let mut excluded_file_count = 0usize;
+    let mut excluded_dir_paths: HashSet<String> = HashSet::new();
+    let mut in_excluded_dirs = 0usize;
+    let mut owner_filtered = 0usize;
+
+    // Paths are kept as listed: spaces and newlines at either end belong
+    // to the name.
+    for record in stdout.split(|&b| b == 0) {
+        if let Some(count) = record.strip_prefix(b"#owner ") {
+            owner_filtered += String::from_utf8_lossy(count).trim().parse::<usize>().unwrap_or(0);
+            continue;
         }
-        // Build destination path based on source type and transfer mode
-        let dest_file = match (&src_dir, transfer_mode) {
-            // Directory source + "Folders and files": preserve directory structure
-            (Some(sd), TransferMode::FoldersAndFiles) => match file_path.strip_prefix(sd) {
-                Ok(rel) => {
-                    let root = sd.file_name().unwrap_or(sd.as_os_str());
-                    dst_path.join(root).join(rel)
-                }
-                Err(_) => {
-                    skipped.push(format!("{}: outside source directory", file_path.display()));
-                    continue;
-                }
-            },
-            // Directory source + "Files only": flat copy (just the filename)
-            // Individual files: always flat copy
-            _ => {
-                let fname = match file_path.file_name() {
-                    Some(f) => f,
-                    None => {
-                        skipped.push(format!("{}: no filename", file_path.display()));
-                        continue;
-                    }
-                };
-                dst_path.join(fname)
-            }
+        let (meta, line) = match record.iter().position(|&b| b == b'\t') {
+            Some(tab) => (String::from_utf8_lossy(&record[..tab]), &record[tab + 1..]),
+            None => (Default::default(), record),
         };
+        let mut fields = meta.split_whitespace();
+        let (size, owner) = (fields.next().and_then(|sz| sz.parse::<u64>().ok()), fields.next());
+        if line.is_empty() {
+            continue;
+        }
 
-        // Strip spaces from the destination path components if requested
-        let mut dest_file = if strip_spaces {
-            strip_spaces_from_path(&dst_path, &dest_file)
+        // Get relative path from remote_base
+        let path = Path::new(OsStr::from_bytes(line));
+        let rel = if let Some(stripped) = line.strip_prefix(remote_base_slash) {
+            stripped
+        } else if line == remote_base.as_bytes() {
+            // The remote path is a single file, not a directory.
+            // Use just the filename as the relative path.
+            match path.file_name() {
+                Some(name) => name.as_bytes(),
+                None => continue,
+            }
         } else {
-            dest_file
+            continue;
         };
+        let rel = String::from_utf8_lossy(rel);
+        let rel = rel.as_ref();
 
-        // Create parent directory in destination
-        if let Some(parent) = dest_file.parent() {
-            if let Err(e) = fs::create_dir_all(parent) {
-                errors.push(format!("{}: {}", file_path.display(), e));
-                continue;
-            }
+        // Check directory exclusions against each path component
+        let parts: Vec<&str> = rel.split('/').collect();
+        let filename = parts.last().unwrap_or(&"");
+
+        // Check dir exclusions (all components except the filename); the
+        // first match is the folder a local scan would have pruned.
+        let dirs = &parts[..parts.len().saturating_sub(1)];
+        let excluded_at = dirs.iter().enumerate().position(|(depth, part)| {
+            excluded_dirs.contains(*part)
+                || wildcard_dirs.iter().any(|pat| wildcard_matches(pat, part))
+                || path_matches(&dirs[..=depth].join("/"))
+        });
+        if let Some(depth) = excluded_at {
+            excluded_dir_paths.insert(dirs[..=depth].join("/"));
+            in_excluded_dirs += 1;
+            continue;
         }
 
-        // Check if destination already exists
-        if dest_file.exists() {
-            match files_are_identical(file_path, &dest_file) {
-                Ok(true) => {
-                    // Destination is already identical — no copy needed
-                    if do_move {
-                        // Just delete the source
-                        if let Err(e) = fs::remove_file(file_path) {
-                            errors.push(format!("{}: identical at destination but failed to delete source: {}", file_path.display(), e));
-                        } else {
-                            copied += 1;
-                        }
-                    } else {
-                        skipped.push(format!("{}: identical at destination", file_path.display()));
-                    }
-                    let _ = tx.send(WorkerMsg::Progress {
-                        done: i + 1,
-                        total,
-                        file: file_path.to_string_lossy().to_string(),
-                    });
-                    continue;
-                }
-                Ok(false) => {
-                    match conflict_mode {
-                        ConflictMode::Skip => {
-                            skipped.push(format!("{}: different version exists at destination", file_path.display()));
-                            let _ = tx.send(WorkerMsg::Progress {
-                                done: i + 1,
-                                total,
-                                file: file_path.to_string_lossy().to_string(),
-                            });
-                            continue;
-                        }
-                        ConflictMode::Rename => {
-                            dest_file = find_unique_local_path(&dest_file);
-                        }
-                        ConflictMode::Overwrite => {
-                            // fall through to overwrite
-                        }
-                    }
-                }
-                Err(e) => {
-                    errors.push(format!("{}: could not compare with destination: {}", file_path.display(), e));
-                    let _ = tx.send(WorkerMsg::Progress {
-                        done: i + 1,
-                        total,
-                        file: file_path.to_string_lossy().to_string(),
-                    });
-                    continue;
-                }
-            }
+        // Check file exclusions
+        if excluded_files.contains(*filename)
+            || wildcard_files.iter().any(|pat| wildcard_matches(pat, filename))
+            || path_matches(rel)
+            || !is_included(&include_files, filename)
+        {
+            excluded_file_count += 1;
+            continue;
         }
 
-        let result = if do_move {
-            // Try rename first (instant pointer change on same filesystem)
-            match fs::rename(file_path, &dest_file) {
-                Ok(()) => Ok(()),
-                Err(_) => {
-                    // Cross-device: copy + verify + delete original
-                    match fs::copy(file_path, &dest_file) {
-                        Ok(_) => match files_are_identical(file_path, &dest_file) {
-                            Ok(true) => fs::remove_file(file_path),
-                            Ok(false) => {
-                                let _ = fs::remove_file(&dest_file);
-                                Err(std::io::Error::new(
-                                    std::io::ErrorKind::Other,
-                                    "integrity check failed — original retained",
-                                ))
-                            }
-                            Err(e) => Err(std::io::Error::new(
-                                std::io::ErrorKind::Other,
-                                format!("verification error (original retained): {}", e),
-                            )),
-                        },
-                        Err(e) => Err(e),
-                    }
-                }
-            }
-        } else {
-            // Copy + verify
-            match fs::copy(file_path, &dest_file) {
-                Ok(_) => match files_are_identical(file_path, &dest_file) {
-                    Ok(true) => Ok(()),
-                    Ok(false) => {
-                        let _ = fs::remove_file(&dest_file);
-                        Err(std::io::Error::new(
-                            std::io::ErrorKind::Other,
-                            "integrity check failed — copy removed",
-                        ))
-                    }
-                    Err(e) => Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("verification error: {}", e),
-                    )),
-                },
-                Err(e) => Err(e),
+        if let Some((uid, gid, user, group)) = owner.and_then(parse_owner_field) {
+            if !filter.keeps(uid, gid, Some(user), Some(group)) {
+                owner_filtered += 1;
+                continue;
             }
-        };
-
-        match result {
-            Ok(()) => copied += 1,
-            Err(e) => errors.push(format!("{}: {}", file_path.display(), e)),
         }
 
-        let _ = tx.send(WorkerMsg::Progress {
-            done: i + 1,
-            total,
-            file: file_path.to_string_lossy().to_string(),
-        });
+        collected.push((path.to_path_buf(), size));
     }
 
-    let _ = tx.send(WorkerMsg::Finished {
-        copied,
-        skipped,
-        excluded_files,
-        excluded_dirs,
-        errors,
-    });
+    (collected, excluded_file_count, excluded_dir_paths.len(), in_excluded_dirs, owner_filtered)
 }
 
-// ── Worker thread (local via rsync) ────────────────────────────────────
+/// `uid:gid:user:group` from a listing line.
+fn parse_owner_field(field: &str) -> Option<(u32, u32, &str, &str)> {
+    let mut parts = field.splitn(4, ':');
+    let uid = parts.next()?.parse().ok()?;
+    let gid = parts.next()?.parse().ok()?;
+    Some((uid, gid, parts.next()?, parts.next()?))
+}
 
-fn run_local_rsync_worker(
-    source: SourceSelection,
-    dst: String,
+// ── Worker thread (remote source → local destination) ──────────────────
+
+fn run_remote_to_local_worker(
+    src_host: &str,
+    src_remote_base: &str,
+    src_files: &[PathBuf],
+    local_dst: &str,
     do_move: bool,
+    move_strategy: MoveStrategy,
     conflict_mode: ConflictMode,
-    strip_spaces: bool,
+    renamer: &Renamer,
     transfer_mode: TransferMode,
+    keep_parents: usize,
     patterns: &[String],
-    cancel_flag: Arc<AtomicBool>,
+    transfer_method: TransferMethod,
+    opts: &RunOptions,
+    cancel_flag: Arc<CancelFlag>,
     tx: mpsc::Sender<WorkerMsg>,
 ) {
-    let dst_path = PathBuf::from(&dst);
+    let ctl = ssh::ctl_args(src_host);
 
-    // Check that rsync is available
-    match Command::new("rsync").arg("--version").output() {
-        Ok(o) if o.status.success() => {}
-        _ => {
-            let _ = tx.send(WorkerMsg::Error(
-                "rsync is not installed or not found in PATH".to_string(),
-            ));
-            return;
-        }
+    // Connectivity check to source
+    if !check_ssh_host(src_host, &ctl, &tx) {
+        return;
     }
 
-    // Create destination directory if it doesn't exist
-    if !dst_path.exists() {
-        if let Err(e) = fs::create_dir_all(&dst_path) {
-            let _ = tx.send(WorkerMsg::Error(format!(
-                "Failed to create destination directory: {}",
-                e
-            )));
-            return;
-        }
+    if transfer_method == TransferMethod::Rsync && !check_rsync_peers(&[src_host], &tx) {
+        return;
     }
 
-    // Collect the files to process
-    let (files, excluded_files, excluded_dirs) = match collect_files(&source, patterns) {
+    // List remote source files
+    let (listing, excluded_files, excluded_dirs) = match collect_remote_source(src_host, &ctl, src_remote_base, src_files, patterns, &mut worker_scan_guard(opts.scan_limits, &cancel_flag, &tx)) {
         Ok(v) => v,
         Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(e));
+            let _ = tx.send(scan_failure(e, &cancel_flag));
             return;
         }
     };
+    if cancel_flag.stopping() {
+        let _ = tx.send(cancelled_before_transfer(Details::skipped(), excluded_files, excluded_dirs));
+        return;
+    }
+    let (remote_files, remote_sizes): (Vec<PathBuf>, Vec<Option<u64>>) = listing.into_iter().unzip();
 
-    let total = files.len();
+    let total = remote_files.len();
     if total == 0 {
-        let _ = tx.send(WorkerMsg::Finished {
-            copied: 0,
-            skipped: vec![],
-            excluded_files,
-            excluded_dirs,
-            errors: vec![],
-        });
+        let _ = tx.send(WorkerMsg::Finished(TransferReport::nothing_sent(Details::skipped(), excluded_files, excluded_dirs)));
         return;
     }
 
-    let src_dir = match &source {
-        SourceSelection::Directory(d) => Some(d.clone()),
-        _ => None,
+    let dst_path = PathBuf::from(local_dst);
+    if !dst_path.exists() {
+        if let Err(e) = folders::create_all(&dst_path) {
+            let _ = tx.send(WorkerMsg::Error(format!(
+                "Failed to create destination directory: {}", e
+            )));
+            return;
+        }
+    }
+    if !settle_stale_artifacts(&dst_path, &tx) {
+        return;
+    }
+
+    let src_base = src_remote_base.trim_end_matches('/');
+    let src_folder = (!src_remote_base.is_empty()).then_some(Path::new(src_base));
+    let parent_levels = parent_levels(transfer_mode, src_remote_base.is_empty(), keep_parents);
+    if let Err(e) = settle_collisions(
+        remote_files.iter().map(|f| PlanInput::listed(f, src_folder, transfer_mode, parent_levels, Dest::Local(&dst_path))),
+        renamer,
+        true,
+        opts.on_collision,
+    ) {
+        let _ = tx.send(WorkerMsg::Error(e));
+        return;
+    }
+    let ssh_cmd = ssh::rsync_shell(src_host);
+
+    // Check the existing folders the copy will write into before starting.
+    let mut skipped = Details::skipped();
+    let (remote_files, remote_sizes): (Vec<PathBuf>, Vec<Option<u64>>) =
+        if transfer_mode == TransferMode::FoldersAndFiles && !src_remote_base.is_empty() {
+            let dirs: Vec<String> = remote_files
+                .iter()
+                .map(|f| {
+                    let input = PlanInput::listed(f, src_folder, transfer_mode, parent_levels, Dest::Local(&dst_path));
+                    // Remote sources have no modification date to hand.
+                    let dest = destpath::build_destination(&input, |rel| renamer.apply_path(rel, None));
+                    dest.ok().and_then(|d| d.into_local().parent().map(shown)).unwrap_or_default()
+                })
+                .collect();
+            let plan: Vec<(PathBuf, Option<u64>)> = remote_files.into_iter().zip(remote_sizes).collect();
+            match preflight_plan(plan, &dirs, preflight::local_verdicts, |(f, _)| shown(f), &mut skipped, &tx) {
+                Some(plan) => plan.into_iter().unzip(),
+                None => return,
+            }
+        } else {
+            (remote_files, remote_sizes)
+        };
+    if stopped_before_transfer(&cancel_flag, &mut skipped, excluded_files, excluded_dirs, &tx) {
+        return;
+    }
+    if !room_for_plan(&remote_sizes, opts.ignore_free_space, || space::local_free(&dst_path), &tx) {
+        return;
+    }
+    let total = remote_files.len();
+    // Newest wins compares each source's time with its destination's.
+    let source_times = match conflict_mode {
+        ConflictMode::Newer => {
+            let sources: Vec<&Path> = remote_files.iter().map(PathBuf::as_path).collect();
+            remote_source_times(src_host, &ctl, &sources)
+        }
+        _ => RemoteTimes::new(),
     };
 
+    let mut stats = RunStats::new(
+        (!src_remote_base.is_empty()).then_some(src_remote_base),
+        remote_files.iter().map(shown).zip(remote_sizes.iter().copied()),
+    );
+    send_plan(&tx, remote_sizes);
+
     let mut copied = 0usize;
-    let mut skipped: Vec<String> = Vec::new();
-    let mut errors: Vec<String> = Vec::new();
+    let mut errors = Details::errors();
+    let defer = do_move && move_strategy == MoveStrategy::AllThenDelete;
+    let mut deferred: Vec<PathBuf> = Vec::new();
+    let mut unverified: Vec<String> = Vec::new();
 
-    for (i, file_path) in files.iter().enumerate() {
-        if cancel_flag.load(Ordering::SeqCst) {
-            let _ = tx.send(WorkerMsg::Cancelled {
+    for (i, remote_file) in remote_files.iter().enumerate() {
+        if before_file(i, &tx, &cancel_flag) {
+            skipped.push(format!("{}: {}", shown(remote_file), quota::SKIPPED));
+            continue;
+        }
+        if cancel_flag.stopping() {
+            if defer {
+                let _ = tx.send(WorkerMsg::Deletion(Deletion::Skipped));
+            }
+            if !unverified.is_empty() {
+                let _ = tx.send(WorkerMsg::Unverified(unverified));
+            }
+            let _ = tx.send(WorkerMsg::Cancelled(TransferReport {
                 copied,
                 skipped,
                 excluded_files,
                 excluded_dirs,
+                failed: failed_sources(remote_files.iter().cloned(), errors.shown(), Some(src_host)),
                 errors,
-            });
+                groups: Vec::new(),
+            }));
             return;
         }
-        // Build destination path
-        let dest_file = match (&src_dir, transfer_mode) {
-            (Some(sd), TransferMode::FoldersAndFiles) => match file_path.strip_prefix(sd) {
-                Ok(rel) => {
-                    let root = sd.file_name().unwrap_or(sd.as_os_str());
-                    dst_path.join(root).join(rel)
-                }
-                Err(_) => {
-                    skipped.push(format!("{}: outside source directory", file_path.display()));
-                    continue;
-                }
-            },
-            _ => {
-                let fname = match file_path.file_name() {
-                    Some(f) => f,
-                    None => {
-                        skipped.push(format!("{}: no filename", file_path.display()));
-                        continue;
-                    }
-                };
-                dst_path.join(fname)
-            }
-        };
-
-        // Strip spaces if requested
-        let mut dest_file = if strip_spaces {
-            strip_spaces_from_path(&dst_path, &dest_file)
-        } else {
-            dest_file
-        };
-
-        // Create parent directory
-        if let Some(parent) = dest_file.parent() {
-            if let Err(e) = fs::create_dir_all(parent) {
-                errors.push(format!("{}: {}", file_path.display(), e));
+        let placed = destpath::build_destination(
+            &PlanInput::listed(remote_file, src_folder, transfer_mode, parent_levels, Dest::Local(&dst_path)),
+            |rel| renamer.rename_path(rel, None),
+        );
+        let mut local_dest = match placed {
+            Ok(dest) => dest.into_local(),
+            Err(why) => {
+                skipped.push(format!("{}: {}", shown(remote_file), why.reason()));
                 continue;
             }
-        }
-
-        // Check if destination already exists
-        if dest_file.exists() {
-            match files_are_identical(file_path, &dest_file) {
-                Ok(true) => {
-                    if do_move {
-                        if let Err(e) = fs::remove_file(file_path) {
-                            errors.push(format!(
-                                "{}: identical at destination but failed to delete source: {}",
-                                file_path.display(),
-                                e
-                            ));
-                        } else {
-                            copied += 1;
-                        }
-                    } else {
-                        skipped.push(format!("{}: identical at destination", file_path.display()));
-                    }
-                    let _ = tx.send(WorkerMsg::Progress {
-                        done: i + 1,
-                        total,
-                        file: file_path.to_string_lossy().to_string(),
-                    });
-                    continue;
-                }
-                Ok(false) => {
-                    match conflict_mode {
-                        ConflictMode::Skip => {
-                            skipped.push(format!(
-                                "{}: different version exists at destination",
-                                file_path.display()
-                            ));
-                            let _ = tx.send(WorkerMsg::Progress {
-                                done: i + 1,
-                                total,
-                                file: file_path.to_string_lossy().to_string(),
-                            });
-                            continue;
-                        }
-                        ConflictMode::Rename => {
-                            dest_file = find_unique_local_path(&dest_file);
-                        }
-                        ConflictMode::Overwrite => {
-                            // fall through to overwrite
-                        }
-                    }
-                }
-                Err(e) => {
-                    errors.push(format!(
-                        "{}: could not compare with destination: {}",
-                        file_path.display(),
-                        e
-                    ));
+        };
+
+        // Create parent directory
+        if let Some(parent) = local_dest.parent() {
+            if let Err(e) = folders::create_all(parent) {
+                errors.push(format!("{}: {}", shown(remote_file), e));
+                continue;
+            }
+        }
+
+        // Check conflict
+        if local_dest.exists() {
+            match conflict_mode {
+                ConflictMode::Newer
+                    if source_is_newer(source_times.get(remote_file).copied(), modified(&local_dest).map(Timestamp::of)) => {}
+                ConflictMode::Skip | ConflictMode::Newer => {
+                    skipped.push(format!("{}: {}", shown(remote_file), kept_reason(conflict_mode)));
                     let _ = tx.send(WorkerMsg::Progress {
                         done: i + 1,
                         total,
-                        file: file_path.to_string_lossy().to_string(),
+                        file: shown(remote_file),
                     });
                     continue;
                 }
+                ConflictMode::Rename => {
+                    local_dest = destpath::unique_local(&local_dest);
+                }
+                ConflictMode::Overwrite => {
+                    // fall through
+                }
             }
         }
 
-        // For move on the same filesystem, try rename first (atomic, no copy needed)
-        if do_move {
-            if let Ok(()) = fs::rename(file_path, &dest_file) {
-                copied += 1;
-                let _ = tx.send(WorkerMsg::Progress {
-                    done: i + 1,
-                    total,
-                    file: file_path.to_string_lossy().to_string(),
-                });
-                continue;
+        // Download from source
+        let download = match transfer_method {
+            TransferMethod::Standard | TransferMethod::Tar => scp_fetch(src_host, &ctl, remote_file, &local_dest, opts),
+            TransferMethod::Rsync => priority::command("rsync", opts.low_priority)
+                .args(["-a", "--checksum"])
+                .args(opts.throttle.rsync_args(true))
+                .arg("-e")
+                .arg(&ssh_cmd)
+                .arg(remote_arg(src_host, rsync_escape_remote(remote_file)))
+                .arg(&local_dest)
+                .tracked_stderr(),
+        };
+
+        if !matches!(&download, Ok(out) if out.status.success()) {
+            if matches!(&download, Err(e) if deadline::is_timeout(e)) {
+                let _ = fs::remove_file(&local_dest);
             }
-            // rename failed (cross-device) — fall through to rsync
+            errors.push(format!(
+                "{}: download from source failed{}",
+                shown(remote_file),
+                failure_reason(&shown(remote_file), &download)
+            ));
+            let _ = tx.send(WorkerMsg::Progress {
+                done: i + 1,
+                total,
+                file: shown(remote_file),
+            });
+            continue;
         }
 
-        // Transfer via rsync with checksum verification
-        let rsync_result = Command::new("rsync")
-            .args(["-a", "--checksum"])
-            .arg(file_path)
-            .arg(&dest_file)
-            .status();
-
-        match rsync_result {
-            Ok(s) if s.success() => {
-                // rsync --checksum verifies during transfer; also do a full
-                // byte-by-byte comparison for defense in depth
-                match files_are_identical(file_path, &dest_file) {
-                    Ok(true) => {
-                        copied += 1;
-                        if do_move {
-                            if let Err(e) = fs::remove_file(file_path) {
-                                errors.push(format!(
-                                    "{}: transferred and verified but failed to delete source: {}",
-                                    file_path.display(),
-                                    e
-                                ));
-                            }
-                        }
-                    }
-                    Ok(false) => {
-                        let _ = fs::remove_file(&dest_file);
+        // Verify download by hash
+        match verify_unless_skipped(&cancel_flag, &tx, i, || verify_remote_hash(&local_dest, src_host, &ctl, remote_file, opts.hash_algo)) {
+            None => unverified.push(shown(remote_file)),
+            Some(Ok(true)) => {
+                copied += 1;
+                stats.copied(&shown(remote_file));
+                note_copied(reverify::Location::remote(src_host, remote_file), reverify::Location::Local(local_dest.clone()), opts);
+                if defer {
+                    deferred.push(remote_file.clone());
+                } else if do_move {
+                    // Delete from source host
+                    let rm_result = Command::new("ssh")
+                        .args(&ctl)
+                        .arg(src_host)
+                        .arg(path_command("rm -f {}", &[remote_file.as_os_str()]))
+                        .tracked_status();
+                    if !matches!(rm_result, Ok(s) if s.success()) {
                         errors.push(format!(
-                            "{}: integrity check failed — byte comparison mismatch (original retained, copy removed)",
-                            file_path.display()
+                            "{}: downloaded and verified but failed to delete from source",
+                            shown(remote_file)
                         ));
                     }
-                    Err(e) => {
-                        if do_move {
-                            errors.push(format!(
-                                "{}: transferred but verification failed: {} (original retained)",
-                                file_path.display(),
-                                e
-                            ));
-                        } else {
-                            errors.push(format!(
-                                "{}: transferred but could not verify: {}",
-                                file_path.display(),
-                                e
-                            ));
-                        }
-                    }
                 }
             }
-            Ok(s) => {
+            Some(Ok(false)) => {
+                let _ = fs::remove_file(&local_dest);
                 errors.push(format!(
-                    "{}: rsync failed (exit code {})",
-                    file_path.display(),
-                    s.code().unwrap_or(-1)
+                    "{}: download integrity check failed — {} (local copy removed)",
+                    shown(remote_file),
+                    hash_mismatch(src_host, &ctl, opts.hash_algo)
                 ));
             }
-            Err(e) => {
-                errors.push(format!("{}: {}", file_path.display(), e));
+            Some(Err(e)) => {
+                if do_move {
+                    errors.push(format!(
+                        "{}: downloaded but verification failed: {} (source retained)",
+                        shown(remote_file), e
+                    ));
+                } else {
+                    errors.push(format!(
+                        "{}: downloaded but could not verify: {}",
+                        shown(remote_file), e
+                    ));
+                }
             }
         }
 
         let _ = tx.send(WorkerMsg::Progress {
             done: i + 1,
             total,
-            file: file_path.to_string_lossy().to_string(),
+            file: shown(remote_file),
         });
     }
 
-    let _ = tx.send(WorkerMsg::Finished {
-        copied,
-        skipped,
-        excluded_files,
-        excluded_dirs,
-        errors,
-    });
+    send_phase(&tx, Phase::Finalizing);
+    let cancelled = defer
+        && delete_deferred_sources(&deferred, unverified.len(), Some(src_host), &ctl, &mut errors, &mut 0, &cancel_flag, &tx);
+    if !cancelled {
+        tidy_moved_remote_source(src_host, &ctl, src_remote_base, &remote_files, do_move, opts.remove_source_root, &errors);
+    }
+    let groups = stats.finish(skipped.shown(), errors.shown());
+    let failed = failed_sources(remote_files.iter().cloned(), errors.shown(), Some(src_host));
+    if !unverified.is_empty() {
+        let _ = tx.send(WorkerMsg::Unverified(unverified));
+    }
+    let report = TransferReport { copied, skipped, excluded_files, excluded_dirs, errors, failed, groups };
+    let _ = tx.send(if cancelled { WorkerMsg::Cancelled(report) } else { WorkerMsg::Finished(report) });
 }
 
-// ── Worker thread (remote via ssh/scp) ─────────────────────────────────
+// ── Worker thread (remote source → remote destination via SCP) ─────────
 
-fn run_remote_worker(
-    source: SourceSelection,
-    host: &str,
-    remote_base: &str,
+fn run_remote_to_remote_worker(
+    src_host: &str,
+    src_remote_base: &str,
+    src_files: &[PathBuf],
+    dst_host: &str,
+    dst_remote_base: &str,
     do_move: bool,
+    move_strategy: MoveStrategy,
     conflict_mode: ConflictMode,
-    strip_spaces: bool,
+    recheck: Recheck,
+    renamer: &Renamer,
     transfer_mode: TransferMode,
+    keep_parents: usize,
     patterns: &[String],
-    cancel_flag: Arc<AtomicBool>,
+    opts: &RunOptions,
+    cancel_flag: Arc<CancelFlag>,
     tx: mpsc::Sender<WorkerMsg>,
 ) {
-    // SSH control-socket args — reuses a single TCP connection for all calls
-    let ctl = ["-o", "ControlMaster=auto",
-               "-o", "ControlPath=/tmp/kosmokopy_ssh_%h_%p_%r",
-               "-o", "ControlPersist=60"];
+    let src_ctl = ssh::ctl_args(src_host);
+    let dst_ctl = ssh::ctl_args(dst_host);
 
-    // Quick connectivity check
-    let check = Command::new("ssh")
-        .args(&ctl)
-        .args([host, "echo ok"])
-        .output();
-    match check {
-        Ok(o) if o.status.success() => {}
-        Ok(o) => {
-            let msg = String::from_utf8_lossy(&o.stderr);
-            let _ = tx.send(WorkerMsg::Error(format!(
-                "SSH connection to '{}' failed: {}", host, msg.trim()
-            )));
-            return;
-        }
-        Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(format!(
-                "Could not run ssh command: {}", e
-            )));
+    // Connectivity check to both hosts
+    for host in [src_host, dst_host] {
+        if !check_ssh_host(host, &ssh::ctl_args(host), &tx) {
             return;
         }
     }
 
-    // Collect files locally
-    let (files, excluded_files, excluded_dirs) = match collect_files(&source, patterns) {
+    // Resolved once: each side may take an `ssh -G`.
+    let one_account = same_account(src_host, dst_host);
+    if let Some(e) = one_account
+        .then(|| remote_same_location_error(src_host, src_remote_base, src_files, dst_host, dst_remote_base, transfer_mode, &src_ctl))
+        .flatten()
+    {
+        let _ = tx.send(WorkerMsg::Error(e));
+        return;
+    }
+    let in_place = do_move && one_account;
+    if !check_remote_destination(dst_host, &dst_ctl, dst_remote_base, &tx) {
+        return;
+    }
+    if !settle_remote_stale_artifacts(dst_host, &dst_ctl, dst_remote_base, &tx) {
+        return;
+    }
+
+    // List remote source files
+    let (listing, excluded_files, excluded_dirs) = match collect_remote_source(src_host, &src_ctl, src_remote_base, src_files, patterns, &mut worker_scan_guard(opts.scan_limits, &cancel_flag, &tx)) {
         Ok(v) => v,
         Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(e));
+            let _ = tx.send(scan_failure(e, &cancel_flag));
             return;
         }
     };
+    if cancel_flag.stopping() {
+        let _ = tx.send(cancelled_before_transfer(Details::skipped(), excluded_files, excluded_dirs));
+        return;
+    }
+    let (remote_files, remote_sizes): (Vec<PathBuf>, Vec<Option<u64>>) = listing.into_iter().unzip();
 
-    let total = files.len();
+    let total = remote_files.len();
     if total == 0 {
-        let _ = tx.send(WorkerMsg::Finished {
-            copied: 0,
-            skipped: vec![],
-            excluded_files,
-            excluded_dirs,
-            errors: vec![],
-        });
+        let _ = tx.send(WorkerMsg::Finished(TransferReport::nothing_sent(Details::skipped(), excluded_files, excluded_dirs)));
         return;
     }
 
-    let src_dir = match &source {
-        SourceSelection::Directory(d) => Some(d.clone()),
-        _ => None,
+    // Create a temp directory for the local staging area; a move within
+    // one account is done on the host and stages nothing.
+    let temp_dir = match (!in_place).then(tempdir_for_relay).transpose() {
+        Ok(d) => d,
+        Err(e) => {
+            let _ = tx.send(WorkerMsg::Error(format!(
+                "Failed to create temp directory: {}", e
+            )));
+            return;
+        }
+    };
+    let remove_temp = || {
+        if let Some(dir) = &temp_dir {
+            let _ = fs::remove_dir_all(dir);
+        }
     };
 
-    // Build list of (local_path, remote_path) pairs
-    let remote_base = remote_base.trim_end_matches('/');
-    let mut transfers: Vec<(PathBuf, String)> = Vec::new();
-    let mut remote_dirs: HashSet<String> = HashSet::new();
-    remote_dirs.insert(remote_base.to_string());
-    let mut early_skipped: Vec<String> = Vec::new();
+    let src_base = src_remote_base.trim_end_matches('/');
+    let src_folder = (!src_remote_base.is_empty()).then_some(Path::new(src_base));
+    let dst_base = dst_remote_base.trim_end_matches('/');
 
-    for file_path in &files {
-        let rel_dest = match (&src_dir, transfer_mode) {
-            (Some(sd), TransferMode::FoldersAndFiles) => match file_path.strip_prefix(sd) {
-                Ok(rel) => {
-                    let root = sd.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
-                    if root.is_empty() { rel.to_string_lossy().to_string() }
-                    else { format!("{}/{}", root, rel.to_string_lossy()) }
-                }
-                Err(_) => {
-                    early_skipped.push(format!(
-                        "{}: outside source directory",
-                        file_path.display()
-                    ));
-                    continue;
-                }
-            },
-            _ => match file_path.file_name() {
-                Some(f) => f.to_string_lossy().to_string(),
-                None => {
-                    early_skipped.push(format!("{}: no filename", file_path.display()));
-                    continue;
-                }
-            },
-        };
-        let remote_file = format!("{}/{}", remote_base, rel_dest);
-        // Strip spaces from the remote path if requested
-        let remote_file = if strip_spaces {
-            remote_file.split('/').map(|c| c.replace(' ', "")).collect::<Vec<_>>().join("/")
-        } else {
-            remote_file
+    // Build destination remote paths and ensure remote dirs
+    let mut transfers: Vec<(PathBuf, PathBuf, PathBuf)> = Vec::new(); // (src_remote, dst_remote, local_temp)
+    let parent_levels = parent_levels(transfer_mode, src_remote_base.is_empty(), keep_parents);
+    if let Err(e) = settle_collisions(
+        remote_files.iter().map(|f| PlanInput::listed(f, src_folder, transfer_mode, parent_levels, Dest::Remote(dst_base))),
+        renamer,
+        true,
+        opts.on_collision,
+    ) {
+        let _ = tx.send(WorkerMsg::Error(e));
+        return;
+    }
+    let mut early_skipped = Details::skipped();
+
+    for remote_file in &remote_files {
+        // For single-file sources, strip_prefix leaves nothing, as the
+        // base is the file itself. Use just the filename so local_temp
+        // stays inside temp_dir.
+        let rel = remote_file
+            .strip_prefix(src_base)
+            .ok()
+            .filter(|rel| !rel.as_os_str().is_empty())
+            .or_else(|| remote_file.file_name().map(Path::new))
+            .unwrap_or(remote_file);
+
+        let placed = destpath::build_destination(
+            &PlanInput::listed(remote_file, src_folder, transfer_mode, parent_levels, Dest::Remote(dst_base)),
+            |rel| renamer.rename_path(rel, None),
+        );
+        let dst_remote = match placed {
+            Ok(dest) => dest.into_remote(),
+            Err(why) => {
+                early_skipped.push(format!("{}: {}", shown(remote_file), why.reason()));
+                continue;
+            }
         };
-        if let Some(parent) = Path::new(&remote_file).parent() {
-            remote_dirs.insert(parent.to_string_lossy().to_string());
-        }
-        transfers.push((file_path.clone(), remote_file));
+
+        // Local temp path preserves structure for staging
+        let local_temp = temp_dir.as_deref().map_or_else(PathBuf::new, |dir| dir.join(rel));
+        transfers.push((remote_file.clone(), dst_remote, local_temp));
     }
 
-    // Create all remote directories in one SSH call
-    let dirs_arg: Vec<String> = remote_dirs.iter().map(|d| shell_quote(d)).collect();
+    // Check the existing folders the upload will write into before
+    // creating any.
+    let Some(transfers) = preflight_remote(
+        transfers, dst_host, &dst_ctl, |t| &t.1, |t| shown(&t.0), &mut early_skipped, &tx,
+    ) else {
+        remove_temp();
+        return;
+    };
+    if stopped_before_transfer(&cancel_flag, &mut early_skipped, excluded_files, excluded_dirs, &tx) {
+        remove_temp();
+        return;
+    }
+    // Only the source-side deletions are deferred; the relay's temp copies
+    // are removed as each upload verifies.
+    let defer = do_move && move_strategy == MoveStrategy::AllThenDelete;
+    // A move within a host renames, needing no room, only between folders
+    // on one filesystem and when the sources aren't held back.
+    let renames = in_place && !defer && {
+        let src_root = src_folder.or_else(|| remote_files.first()?.parent()).unwrap_or(Path::new("/"));
+        remote_same_device(src_host, &src_ctl, src_root, Path::new(dst_base)) == Some(true)
+    };
+    if !renames && !room_for_plan(&remote_sizes, opts.ignore_free_space, || remote_free(dst_host, &dst_ctl, dst_base), &tx) {
+        remove_temp();
+        return;
+    }
+    let dst_remote_dirs = remote_target_dirs(dst_base, transfers.iter().map(|t| t.1.as_path()));
+
+    // Create all destination remote directories
     let mkdir_result = Command::new("ssh")
-        .args(&ctl)
-        .arg(host)
-        .arg(format!("mkdir -p {}", dirs_arg.join(" ")))
-        .output();
+        .args(&dst_ctl)
+        .arg(dst_host)
+        .arg(folders::mkdir_script(&dst_remote_dirs, |p| quote_path(p)))
+        .tracked_output();
     if let Ok(o) = &mkdir_result {
+        folders::note_remote(dst_host, &dst_remote_dirs, &o.stdout);
         if !o.status.success() {
-            let msg = String::from_utf8_lossy(&o.stderr);
             let _ = tx.send(WorkerMsg::Error(format!(
-                "Failed to create remote directories: {}", msg.trim()
+                "Failed to create remote directories on destination: {}",
+                String::from_utf8_lossy(&o.stderr).trim()
             )));
+            remove_temp();
             return;
         }
     }
 
-    // If not overwriting, get list of existing remote files in one SSH call
-    let existing: HashSet<String> = if conflict_mode != ConflictMode::Overwrite {
-        let out = Command::new("ssh")
-            .args(&ctl)
-            .arg(host)
-            .arg(format!("find {} -type f 2>/dev/null", shell_quote(remote_base)))
-            .output();
-        match out {
-            Ok(o) => String::from_utf8_lossy(&o.stdout)
-                .lines()
-                .map(|l| l.to_string())
-                .collect(),
-            Err(_) => HashSet::new(),
-        }
+    // If not overwriting, look up which planned destinations already exist
+    let (mut existing, mut dest_times) = if conflict_mode != ConflictMode::Overwrite {
+        let targets: Vec<&Path> = transfers.iter().map(|t| t.1.as_path()).collect();
+        existing_remote_files_ssh(dst_host, &dst_ctl, &targets, conflict_mode)
     } else {
-        HashSet::new()
+        (HashSet::new(), RemoteTimes::new())
+    };
+    // Newest wins also needs the times of the sources of those files.
+    let source_times = match dest_times.is_empty() {
+        true => RemoteTimes::new(),
+        false => {
+            let sources: Vec<&Path> =
+                transfers.iter().filter(|t| dest_times.contains_key(&t.1)).map(|t| t.0.as_path()).collect();
+            remote_source_times(src_host, &src_ctl, &sources)
+        }
     };
 
+    let mut stats = RunStats::new(
+        (!src_remote_base.is_empty()).then_some(src_remote_base),
+        remote_files.iter().map(shown).zip(remote_sizes.iter().copied()),
+    );
+    send_plan(&tx, remote_sizes);
+
     let total_transfers = transfers.len();
     let mut copied = 0usize;
     let mut skipped = early_skipped;
-    let mut errors: Vec<String> = Vec::new();
+    let mut errors = Details::errors();
+    let mut deferred: Vec<PathBuf> = Vec::new();
+    let mut unverified: Vec<String> = Vec::new();
+
+    // A move within one account on one host is done there (see
+    // `samehost`), batch by batch; the relay loop below then has nothing
+    // left to send.
+    let transfers = if !in_place {
+        transfers
+    } else {
+        let mut done = 0usize;
+        for batch in transfers.chunks(samehost::BATCH) {
+            cancel_flag.wait_while_paused();
+            if cancel_flag.stopping() {
+                if defer {
+                    let _ = tx.send(WorkerMsg::Deletion(Deletion::Skipped));
+                }
+                let _ = tx.send(WorkerMsg::Cancelled(TransferReport {
+                    copied,
+                    skipped,
+                    excluded_files,
+                    excluded_dirs,
+                    failed: failed_sources(remote_files.iter().cloned(), errors.shown(), Some(src_host)),
+                    errors,
+                    groups: Vec::new(),
+                }));
+                remove_temp();
+                return;
+            }
+            let mut pairs: Vec<(PathBuf, PathBuf)> = Vec::new();
+            for (src_remote, dst_remote, _) in batch {
+                let newer = |p: &Path| source_is_newer(source_times.get(src_remote).copied(), dest_times.remove(p));
+                match resolve_remote_conflict(dst_remote, &mut existing, conflict_mode, recheck, newer, |p| {
+                    remote_path_exists(dst_host, &dst_ctl, p)
+                }) {
+                    Some(target) => pairs.push((src_remote.clone(), target.into_owned())),
+                    None => {
+                        skipped.push(format!("{}: {}", shown(src_remote), kept_reason(conflict_mode)));
+                        done += 1;
+                        let _ = tx.send(WorkerMsg::Progress { done, total: total_transfers, file: shown(src_remote) });
+                    }
+                }
+            }
+            if pairs.is_empty() {
+                continue;
+            }
+            let mut ended = 0usize;
+            let moved = Command::new("ssh")
+                .args(&src_ctl)
+                .arg(src_host)
+                .arg(samehost::script(&pairs, defer, |p| quote_path(p)))
+                .tracked_records(0, |record| {
+                    let Some((src_remote, dst_remote)) = pairs.get(ended) else { return };
+                    ended += 1;
+                    match samehost::Moved::parse(record) {
+                        outcome @ (samehost::Moved::Renamed | samehost::Moved::Copied | samehost::Moved::Undeleted(_)) => {
+                            copied += 1;
+                            stats.copied(&shown(src_remote));
+                            note_copied(reverify::Location::remote(src_host, src_remote), reverify::Location::remote(dst_host, dst_remote), opts);
+                            existing.insert(dst_remote.clone());
+                            match outcome {
+                                samehost::Moved::Copied if defer => deferred.push(src_remote.clone()),
+                                samehost::Moved::Undeleted(why) => errors.push(format!(
+                                    "{}: transferred and verified but failed to delete from source: {}",
+                                    shown(src_remote), why
+                                )),
+                                _ => {}
+                            }
+                        }
+                        samehost::Moved::Mismatch => errors.push(format!(
+                            "{}: copy on the host did not match its source (source retained, dest copy removed)",
+                            shown(src_remote)
+                        )),
+                        samehost::Moved::Failed(why) => errors.push(format!("{}: move on the host failed: {}", shown(src_remote), why)),
+                    }
+                    done += 1;
+                    let _ = tx.send(WorkerMsg::Progress { done, total: total_transfers, file: shown(src_remote) });
+                });
+            // Files the script never got to, e.g. when the connection dropped.
+            for (src_remote, _) in &pairs[ended..] {
+                errors.push(format!("{}: move on the host failed{}", shown(src_remote), failure_reason(&shown(src_remote), &moved)));
+                done += 1;
+                let _ = tx.send(WorkerMsg::Progress { done, total: total_transfers, file: shown(src_remote) });
+            }
+        }
+        Vec::new()
+    };
+
+    // Through the staging folder: download, check against the source,
+    // upload, check against the local copy.
+    let staged = |i: usize, src_remote: &Path, dst_remote: &Path, local_temp: &Path| -> Result<Option<Result<(), String>>, String> {
+        // Create local temp parent dir
+        if let Some(parent) = local_temp.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("{}: temp dir error: {}", shown(src_remote), e))?;
+        }
+
+        // Step 1: Download from source to local temp
+        let fetched = retry::attempt(opts.retries, &cancel_flag, || {
+            let dl_result = scp_fetch(src_host, &src_ctl, src_remote, local_temp, opts);
+            if !matches!(&dl_result, Ok(out) if out.status.success()) {
+                return Err(format!(
+                    "{}: download from source failed{}",
+                    shown(src_remote),
+                    failure_reason(&shown(src_remote), &dl_result)
+                ));
+            }
+            // Verify download
+            match verify_unless_skipped(&cancel_flag, &tx, i, || verify_remote_hash(local_temp, src_host, &src_ctl, src_remote, opts.hash_algo)) {
+                None | Some(Ok(true)) => Ok(Ok(())),
+                Some(Ok(false)) => {
+                    let _ = fs::remove_file(local_temp);
+                    Err(format!(
+                        "{}: download integrity check failed — {}",
+                        shown(src_remote),
+                        hash_mismatch(src_host, &src_ctl, opts.hash_algo)
+                    ))
+                }
+                Some(Err(e)) => {
+                    let _ = fs::remove_file(local_temp);
+                    Ok(Err(format!("{}: download verification error: {}", shown(src_remote), e)))
+                }
+            }
+        });
+        fetched.and_then(|verified| verified)?;
+
+        send_phase(&tx, Phase::Transferring);
+        // Step 2: Upload from local temp to destination
+        retry::attempt(opts.retries, &cancel_flag, || {
+            let ul_result = scp_send(dst_host, &dst_ctl, local_temp, dst_remote, 1, opts);
+            if !matches!(&ul_result, Ok(out) if out.status.success()) {
+                if matches!(&ul_result, Err(e) if deadline::is_timeout(e)) {
+                    remove_remote_partial(dst_host, &dst_ctl, dst_remote);
+                }
+                return Err(format!(
+                    "{}: upload to destination failed{}",
+                    shown(src_remote),
+                    failure_reason(&shown(src_remote), &ul_result)
+                ));
+            }
+            // Verify upload
+            match verify_unless_skipped(&cancel_flag, &tx, i, || verify_remote_hash(local_temp, dst_host, &dst_ctl, dst_remote, opts.hash_algo)) {
+                None => Ok(None),
+                Some(Ok(true)) => Ok(Some(Ok(()))),
+                Some(Ok(false)) => {
+                    // Remove corrupt destination copy
+                    let _ = Command::new("ssh")
+                        .args(&dst_ctl)
+                        .arg(dst_host)
+                        .arg(path_command("rm -f {}", &[dst_remote.as_os_str()]))
+                        .tracked_status();
+                    Err(format!(
+                        "{}: upload integrity check failed — {} (source retained, dest copy removed)",
+                        shown(src_remote),
+                        hash_mismatch(dst_host, &dst_ctl, opts.hash_algo)
+                    ))
+                }
+                Some(Err(e)) => Ok(Some(Err(e))),
+            }
+        })
+    };
+
+    // One attempt the quicker ways: send, then compare the hashes of the
+    // two hosts' files.
+    let between = |way: Relay, i: usize, src_remote: &Path, dst_remote: &Path| -> Result<Option<Result<(), String>>, String> {
+        send_phase(&tx, Phase::Transferring);
+        let target = remote_arg(dst_host, dst_remote);
+        let result = match way {
+            Relay::Direct => {
+                let dst_args: Vec<String> =
+                    opts.throttle.scp_args(1).into_iter().chain(ssh::host_args(dst_host)).map(|a| shell_quote(&a)).collect();
+                priority::command("ssh", opts.low_priority)
+                    .args(&src_ctl)
+                    .arg(src_host)
+                    .arg(relay::direct_command(&quote_path(src_remote), &quote_path(&target), &dst_args))
+                    .tracked_stderr()
+            }
+            _ => priority::command("scp", opts.low_priority)
+                .arg("-3")
+                .args(&src_ctl)
+                .arg("-q")
+                .args(opts.throttle.scp_args(1))
+                .arg(remote_arg(src_host, src_remote))
+                .arg(&target)
+                .tracked_stderr(),
+        };
+        if !matches!(&result, Ok(out) if out.status.success()) {
+            if matches!(&result, Err(e) if deadline::is_timeout(e)) {
+                remove_remote_partial(dst_host, &dst_ctl, dst_remote);
+            }
+            return Err(format!("{}: {} copy failed{}", shown(src_remote), way.name(), failure_reason(&shown(src_remote), &result)));
+        }
+        match verify_unless_skipped(&cancel_flag, &tx, i, || {
+            verify_between_hosts(src_host, &src_ctl, src_remote, dst_host, &dst_ctl, dst_remote, opts.hash_algo)
+        }) {
+            None => Ok(None),
+            Some(Ok(true)) => Ok(Some(Ok(()))),
+            Some(Ok(false)) => {
+                let _ = Command::new("ssh")
+                    .args(&dst_ctl)
+                    .arg(dst_host)
+                    .arg(path_command("rm -f {}", &[dst_remote.as_os_str()]))
+                    .tracked_status();
+                Err(format!(
+                    "{}: integrity check failed — {} hash mismatch (source retained, dest copy removed)",
+                    shown(src_remote),
+                    pair_algo(src_host, &src_ctl, dst_host, &dst_ctl, opts.hash_algo).name()
+                ))
+            }
+            Some(Err(e)) => Ok(Some(Err(e))),
+        }
+    };
+
+    // The ways still to try, the first being tried or settled on.
+    let order = opts.relay.order();
+    let scp3_usable = ssh::host_args(src_host).is_empty() && ssh::host_args(dst_host).is_empty();
+    let mut ways: Vec<Relay> = order.iter().copied().filter(|&way| way != Relay::Scp3 || scp3_usable).collect();
+    let mut fallbacks: Vec<String> = Vec::new();
+    if ways.len() < order.len() {
+        fallbacks.push("scp3: the hosts need options of their own (a port or an IPv6 address)".to_string());
+    }
+    if ways.is_empty() {
+        ways.push(Relay::Staged);
+    }
+    let mut settled = ways.len() == 1;
+    let mut staged_for_names = 0usize;
+    let mut relayed = false;
 
-    for (i, (local, remote)) in transfers.iter().enumerate() {
-        if cancel_flag.load(Ordering::SeqCst) {
-            let _ = tx.send(WorkerMsg::Cancelled {
+    for (i, (src_remote, dst_remote, local_temp)) in transfers.iter().enumerate() {
+        if before_file(i, &tx, &cancel_flag) {
+            skipped.push(format!("{}: {}", shown(src_remote), quota::SKIPPED));
+            continue;
+        }
+        if cancel_flag.stopping() {
+            if defer {
+                let _ = tx.send(WorkerMsg::Deletion(Deletion::Skipped));
+            }
+            if relayed {
+                let _ = tx.send(WorkerMsg::Relay(relay::Decision { relay: ways[0], fallbacks, staged: staged_for_names }));
+            }
+            if !unverified.is_empty() {
+                let _ = tx.send(WorkerMsg::Unverified(unverified));
+            }
+            let _ = tx.send(WorkerMsg::Cancelled(TransferReport {
                 copied,
                 skipped,
                 excluded_files,
                 excluded_dirs,
+                failed: failed_sources(remote_files.iter().cloned(), errors.shown(), Some(src_host)),
                 errors,
-            });
+                groups: Vec::new(),
+            }));
             return;
-        }
-        // Handle conflict if file exists remotely
-        let remote = if conflict_mode != ConflictMode::Overwrite && existing.contains(remote) {
-            match conflict_mode {
-                ConflictMode::Skip => {
-                    skipped.push(format!(
-                        "{}: already exists at destination",
-                        local.display()
-                    ));
-                    let _ = tx.send(WorkerMsg::Progress {
-                        done: i + 1,
-                        total: total_transfers,
-                        file: local.to_string_lossy().to_string(),
-                    });
-                    continue;
+        }
+        // Handle conflict if the destination already exists
+        let newer = |p: &Path| source_is_newer(source_times.get(src_remote).copied(), dest_times.remove(p));
+        let dst_remote = match resolve_remote_conflict(dst_remote, &mut existing, conflict_mode, recheck, newer, |p| {
+            remote_path_exists(dst_host, &dst_ctl, p)
+        }) {
+            Some(r) => r,
+            None => {
+                skipped.push(format!("{}: {}", shown(src_remote), kept_reason(conflict_mode)));
+                let _ = tx.send(WorkerMsg::Progress {
+                    done: i + 1,
+                    total: total_transfers,
+                    file: shown(src_remote),
+                });
+                continue;
+            }
+        };
+
+        relayed = true;
+        let sent = loop {
+            let way = ways[0];
+            let fits = match way {
+                Relay::Direct => relay::direct_safe(src_remote) && relay::direct_safe(&dst_remote),
+                Relay::Scp3 => ssh::scp_literal(src_remote) && ssh::scp_literal(&dst_remote),
+                Relay::Staged => true,
+            };
+            if way == Relay::Staged || !fits {
+                staged_for_names += usize::from(way != Relay::Staged);
+                break staged(i, src_remote, &dst_remote, local_temp);
+            }
+            if settled {
+                break retry::attempt(opts.retries, &cancel_flag, || between(way, i, src_remote, &dst_remote));
+            }
+            // Not settled yet: a failure moves on to the next way, which
+            // tries this file again.
+            match between(way, i, src_remote, &dst_remote) {
+                Err(e) => {
+                    fallbacks.push(format!("{}: {}", way.name(), e));
+                    ways.remove(0);
+                    settled = ways.len() == 1;
                 }
-                ConflictMode::Rename => {
-                    std::borrow::Cow::Owned(find_unique_remote_path_from_set(remote, &existing))
+                sent => {
+                    settled = true;
+                    break sent;
                 }
-                ConflictMode::Overwrite => unreachable!(),
             }
-        } else {
-            std::borrow::Cow::Borrowed(remote.as_str())
         };
+        let verified = match sent {
+            Ok(verified) => verified,
+            Err(e) => {
+                let _ = fs::remove_file(local_temp);
+                errors.push(e);
+                let _ = tx.send(WorkerMsg::Progress {
+                    done: i + 1,
+                    total: total_transfers,
+                    file: shown(src_remote),
+                });
+                continue;
+            }
+        };
+        // Later entries mapping to the same path now see it as taken
+        existing.insert(dst_remote.to_path_buf());
 
-        // Transfer via scp
-        let scp_result = Command::new("scp")
-            .args(&ctl)
-            .arg("-q")
-            .arg(local)
-            .arg(format!("{}:{}", host, remote))
-            .status();
-
-        match scp_result {
-            Ok(s) if s.success() => {
-                // Verify integrity with SHA-256 hash comparison
-                match verify_remote_hash(local, host, &ctl, &remote) {
-                    Ok(true) => {
-                        copied += 1;
-                        if do_move {
-                            if let Err(e) = fs::remove_file(local) {
-                                errors.push(format!(
-                                    "{}: transferred and verified but failed to delete local: {}",
-                                    local.display(),
-                                    e
-                                ));
-                            }
-                        }
-                    }
-                    Ok(false) => {
-                        // Hash mismatch — remove corrupt remote copy, keep source
-                        let _ = Command::new("ssh")
-                            .args(&ctl)
-                            .arg(host)
-                            .arg(format!("rm -f {}", shell_quote(&remote)))
-                            .status();
+        match verified {
+            None => {
+                let _ = fs::remove_file(local_temp);
+                unverified.push(shown(src_remote));
+            }
+            Some(Ok(())) => {
+                copied += 1;
+                stats.copied(&shown(src_remote));
+                note_copied(reverify::Location::remote(src_host, src_remote), reverify::Location::remote(dst_host, &dst_remote), opts);
+                // Clean up local temp
+                let _ = fs::remove_file(local_temp);
+                if defer {
+                    deferred.push(src_remote.clone());
+                } else if do_move {
+                    let rm_result = Command::new("ssh")
+                        .args(&src_ctl)
+                        .arg(src_host)
+                        .arg(path_command("rm -f {}", &[src_remote.as_os_str()]))
+                        .tracked_status();
+                    if !matches!(rm_result, Ok(s) if s.success()) {
                         errors.push(format!(
-                            "{}: integrity check failed — hash mismatch (original retained, remote copy removed)",
-                            local.display()
+                            "{}: transferred and verified but failed to delete from source",
+                            shown(src_remote)
                         ));
                     }
-                    Err(e) => {
-                        // Cannot verify — keep both, report error
-                        if do_move {
-                            errors.push(format!(
-                                "{}: transferred but verification failed: {} (original retained)",
-                                local.display(),
-                                e
-                            ));
-                        } else {
-                            errors.push(format!(
-                                "{}: transferred but could not verify: {}",
-                                local.display(),
-                                e
-                            ));
-                        }
-                    }
                 }
             }
-            Ok(s) => {
-                errors.push(format!(
-                    "{}: scp failed (exit code {})",
-                    local.display(),
-                    s.code().unwrap_or(-1)
-                ));
-            }
-            Err(e) => {
-                errors.push(format!("{}: {}", local.display(), e));
+            Some(Err(e)) => {
+                let _ = fs::remove_file(local_temp);
+                if do_move {
+                    errors.push(format!(
+                        "{}: uploaded but verification failed: {} (source retained)",
+                        shown(src_remote), e
+                    ));
+                } else {
+                    errors.push(format!(
+                        "{}: uploaded but could not verify: {}",
+                        shown(src_remote), e
+                    ));
+                }
             }
         }
 
         let _ = tx.send(WorkerMsg::Progress {
             done: i + 1,
             total: total_transfers,
-            file: local.to_string_lossy().to_string(),
+            file: shown(src_remote),
         });
     }
 
-    let _ = tx.send(WorkerMsg::Finished {
-        copied,
-        skipped,
-        excluded_files,
-        excluded_dirs,
-        errors,
-    });
-}
-
-// ── Byte-by-byte file comparison ───────────────────────────────────────
-
-fn files_are_identical(a: &Path, b: &Path) -> std::io::Result<bool> {
-    let meta_a = fs::metadata(a)?;
-    let meta_b = fs::metadata(b)?;
-    if meta_a.len() != meta_b.len() {
-        return Ok(false);
-    }
-
-    let mut fa = fs::File::open(a)?;
-    let mut fb = fs::File::open(b)?;
-    let mut buf_a = [0u8; 8192];
-    let mut buf_b = [0u8; 8192];
+    send_phase(&tx, Phase::Finalizing);
+    // Clean up temp directory
+    remove_temp();
 
-    loop {
-        let n_a = fa.read(&mut buf_a)?;
-        let n_b = fb.read(&mut buf_b)?;
-        if n_a != n_b || buf_a[..n_a] != buf_b[..n_b] {
-            return Ok(false);
-        }
-        if n_a == 0 {
-            return Ok(true);
-        }
+    let cancelled = defer
+        && delete_deferred_sources(&deferred, unverified.len(), Some(src_host), &src_ctl, &mut errors, &mut 0, &cancel_flag, &tx);
+    if !cancelled {
+        tidy_moved_remote_source(src_host, &src_ctl, src_remote_base, &remote_files, do_move, opts.remove_source_root, &errors);
     }
-}
-
-// ── Remote file listing ────────────────────────────────────────────────
-
-/// List files on a remote host under `remote_base`, applying exclusion patterns.
-/// Returns (Vec<remote_path>, excluded_count).
-fn collect_remote_files(
-    host: &str,
-    ctl: &[&str],
-    remote_base: &str,
-    patterns: &[String],
-) -> Result<(Vec<String>, usize, usize), String> {
-    let out = Command::new("ssh")
-        .args(ctl)
-        .arg(host)
-        .arg(format!("find {} -type f 2>/dev/null", shell_quote(remote_base)))
-        .output()
-        .map_err(|e| format!("Failed to list remote files: {}", e))?;
-
-    if !out.status.success() {
-        return Err(format!(
-            "Failed to list remote files: {}",
-            String::from_utf8_lossy(&out.stderr).trim()
-        ));
+    let groups = stats.finish(skipped.shown(), errors.shown());
+    let failed = failed_sources(remote_files.iter().cloned(), errors.shown(), Some(src_host));
+    if relayed {
+        let _ = tx.send(WorkerMsg::Relay(relay::Decision { relay: ways[0], fallbacks, staged: staged_for_names }));
     }
-
-    // Parse exclusion patterns
-    let excluded_dirs: HashSet<String> = patterns
-        .iter()
-        .filter(|p| p.starts_with('/') && !p.starts_with("~/"))
-        .map(|p| p.trim_start_matches('/').to_string())
-        .collect();
-    let excluded_files: HashSet<String> = patterns
-        .iter()
-        .filter(|p| !p.starts_with('/') && !p.starts_with('~'))
-        .cloned()
-        .collect();
-    let wildcard_dirs: Vec<String> = patterns
-        .iter()
-        .filter(|p| p.starts_with("~/"))
-        .map(|p| p[2..].to_string())
-        .collect();
-    let wildcard_files: Vec<String> = patterns
-        .iter()
-        .filter(|p| p.starts_with('~') && !p.starts_with("~/"))
-        .map(|p| p[1..].to_string())
-        .collect();
-
-    let remote_base_slash = format!("{}/", remote_base.trim_end_matches('/'));
-    let mut collected = Vec::new();
-    let mut excluded_file_count = 0usize;
-    let mut excluded_dir_names: HashSet<String> = HashSet::new();
-
-    for line in String::from_utf8_lossy(&out.stdout).lines() {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
-
-        // Get relative path from remote_base
-        let rel = if let Some(stripped) = line.strip_prefix(&remote_base_slash) {
-            stripped
-        } else if line == remote_base {
-            // The remote path is a single file, not a directory.
-            // Use just the filename as the relative path.
-            match Path::new(line).file_name() {
-                Some(name) => name.to_str().unwrap_or(line),
-                None => continue,
-            }
-        } else {
-            continue;
-        };
-
-        // Check directory exclusions against each path component
-        let parts: Vec<&str> = rel.split('/').collect();
-        let filename = parts.last().unwrap_or(&"");
-
-        // Check dir exclusions (all components except the filename)
-        let mut dir_excluded = false;
-        for part in &parts[..parts.len().saturating_sub(1)] {
-            if excluded_dirs.contains(*part)
-                || wildcard_dirs.iter().any(|pat| wildcard_matches(pat, part))
-            {
-                dir_excluded = true;
-                excluded_dir_names.insert(part.to_string());
-                break;
-            }
-        }
-        if dir_excluded {
-            continue;
-        }
-
-        // Check file exclusions
-        if excluded_files.contains(*filename)
-            || wildcard_files.iter().any(|pat| wildcard_matches(pat, filename))
-        {
-            excluded_file_count += 1;
-            continue;
-        }
-
-        collected.push(line.to_string());
+    if !unverified.is_empty() {
+        let _ = tx.send(WorkerMsg::Unverified(unverified));
     }
-
-    Ok((collected, excluded_file_count, excluded_dir_names.len()))
+    let report = TransferReport { copied, skipped, excluded_files, excluded_dirs, errors, failed, groups };
+    let _ = tx.send(if cancelled { WorkerMsg::Cancelled(report) } else { WorkerMsg::Finished(report) });
 }
 
-// ── Worker thread (remote source → local destination) ──────────────────
+// ── Worker thread (remote source → remote destination via rsync) ───────
 
-fn run_remote_to_local_worker(
+fn run_remote_to_remote_rsync_worker(
     src_host: &str,
     src_remote_base: &str,
-    local_dst: &str,
+    src_files: &[PathBuf],
+    dst_host: &str,
+    dst_remote_base: &str,
     do_move: bool,
+    move_strategy: MoveStrategy,
     conflict_mode: ConflictMode,
-    strip_spaces: bool,
+    recheck: Recheck,
+    renamer: &Renamer,
     transfer_mode: TransferMode,
+    keep_parents: usize,
     patterns: &[String],
-    transfer_method: TransferMethod,
-    cancel_flag: Arc<AtomicBool>,
+    opts: &RunOptions,
+    cancel_flag: Arc<CancelFlag>,
     tx: mpsc::Sender<WorkerMsg>,
 ) {
-    let ctl = [
-        "-o", "ControlMaster=auto",
-        "-o", "ControlPath=/tmp/kosmokopy_ssh_%h_%p_%r",
-        "-o", "ControlPersist=60",
-    ];
+    let src_ctl = ssh::ctl_args(src_host);
+    let dst_ctl = ssh::ctl_args(dst_host);
+    let src_shell = ssh::rsync_shell(src_host);
+    let dst_shell = ssh::rsync_shell(dst_host);
 
-    // Connectivity check to source
-    let check = Command::new("ssh")
-        .args(&ctl)
-        .args([src_host, "echo ok"])
-        .output();
-    match check {
-        Ok(o) if o.status.success() => {}
-        Ok(o) => {
-            let _ = tx.send(WorkerMsg::Error(format!(
-                "SSH connection to source '{}' failed: {}",
-                src_host,
-                String::from_utf8_lossy(&o.stderr).trim()
-            )));
-            return;
-        }
-        Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(format!("Could not run ssh: {}", e)));
+    // Connectivity check to both hosts
+    for host in [src_host, dst_host] {
+        if !check_ssh_host(host, &ssh::ctl_args(host), &tx) {
             return;
         }
     }
 
+    if let Some(e) = same_account(src_host, dst_host)
+        .then(|| remote_same_location_error(src_host, src_remote_base, src_files, dst_host, dst_remote_base, transfer_mode, &src_ctl))
+        .flatten()
+    {
+        let _ = tx.send(WorkerMsg::Error(e));
+        return;
+    }
+    if !check_remote_destination(dst_host, &dst_ctl, dst_remote_base, &tx) {
+        return;
+    }
+    if !settle_remote_stale_artifacts(dst_host, &dst_ctl, dst_remote_base, &tx) {
+        return;
+    }
+
+    // Check rsync here and on both hosts
+    if !check_rsync_peers(&[src_host, dst_host], &tx) {
+        return;
+    }
+
     // List remote source files
-    let (remote_files, excluded_files, excluded_dirs) = match collect_remote_files(src_host, &ctl, src_remote_base, patterns) {
+    let (listing, excluded_files, excluded_dirs) = match collect_remote_source(src_host, &src_ctl, src_remote_base, src_files, patterns, &mut worker_scan_guard(opts.scan_limits, &cancel_flag, &tx)) {
         Ok(v) => v,
         Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(e));
+            let _ = tx.send(scan_failure(e, &cancel_flag));
             return;
         }
     };
+    if cancel_flag.stopping() {
+        let _ = tx.send(cancelled_before_transfer(Details::skipped(), excluded_files, excluded_dirs));
+        return;
+    }
+    let (remote_files, remote_sizes): (Vec<PathBuf>, Vec<Option<u64>>) = listing.into_iter().unzip();
 
     let total = remote_files.len();
     if total == 0 {
-        let _ = tx.send(WorkerMsg::Finished {
-            copied: 0,
-            skipped: vec![],
-            excluded_files,
-            excluded_dirs,
-            errors: vec![],
-        });
+        let _ = tx.send(WorkerMsg::Finished(TransferReport::nothing_sent(Details::skipped(), excluded_files, excluded_dirs)));
         return;
     }
 
-    let dst_path = PathBuf::from(local_dst);
-    if !dst_path.exists() {
-        if let Err(e) = fs::create_dir_all(&dst_path) {
+    let temp_dir = match tempdir_for_relay() {
+        Ok(d) => d,
+        Err(e) => {
+            let _ = tx.send(WorkerMsg::Error(format!(
+                "Failed to create temp directory: {}", e
+            )));
+            return;
+        }
+    };
+
+    let src_base = src_remote_base.trim_end_matches('/');
+    let src_folder = (!src_remote_base.is_empty()).then_some(Path::new(src_base));
+    let dst_base = dst_remote_base.trim_end_matches('/');
+
+    let mut transfers: Vec<(PathBuf, PathBuf, PathBuf)> = Vec::new();
+    let parent_levels = parent_levels(transfer_mode, src_remote_base.is_empty(), keep_parents);
+    if let Err(e) = settle_collisions(
+        remote_files.iter().map(|f| PlanInput::listed(f, src_folder, transfer_mode, parent_levels, Dest::Remote(dst_base))),
+        renamer,
+        true,
+        opts.on_collision,
+    ) {
+        let _ = tx.send(WorkerMsg::Error(e));
+        return;
+    }
+    let mut early_skipped = Details::skipped();
+
+    for remote_file in &remote_files {
+        // For single-file sources, strip_prefix leaves nothing, as the
+        // base is the file itself. Use just the filename so local_temp
+        // stays inside temp_dir.
+        let rel = remote_file
+            .strip_prefix(src_base)
+            .ok()
+            .filter(|rel| !rel.as_os_str().is_empty())
+            .or_else(|| remote_file.file_name().map(Path::new))
+            .unwrap_or(remote_file);
+
+        let placed = destpath::build_destination(
+            &PlanInput::listed(remote_file, src_folder, transfer_mode, parent_levels, Dest::Remote(dst_base)),
+            |rel| renamer.rename_path(rel, None),
+        );
+        let dst_remote = match placed {
+            Ok(dest) => dest.into_remote(),
+            Err(why) => {
+                early_skipped.push(format!("{}: {}", shown(remote_file), why.reason()));
+                continue;
+            }
+        };
+
+        let local_temp = temp_dir.join(rel);
+        transfers.push((remote_file.clone(), dst_remote, local_temp));
+    }
+
+    // Check the existing folders the upload will write into before
+    // creating any.
+    let Some(transfers) = preflight_remote(
+        transfers, dst_host, &dst_ctl, |t| &t.1, |t| shown(&t.0), &mut early_skipped, &tx,
+    ) else {
+        let _ = fs::remove_dir_all(&temp_dir);
+        return;
+    };
+    if stopped_before_transfer(&cancel_flag, &mut early_skipped, excluded_files, excluded_dirs, &tx) {
+        let _ = fs::remove_dir_all(&temp_dir);
+        return;
+    }
+    if !room_for_plan(&remote_sizes, opts.ignore_free_space, || remote_free(dst_host, &dst_ctl, dst_base), &tx) {
+        let _ = fs::remove_dir_all(&temp_dir);
+        return;
+    }
+    let dst_remote_dirs = remote_target_dirs(dst_base, transfers.iter().map(|t| t.1.as_path()));
+
+    // Create destination remote directories
+    let mkdir_result = Command::new("ssh")
+        .args(&dst_ctl)
+        .arg(dst_host)
+        .arg(folders::mkdir_script(&dst_remote_dirs, |p| quote_path(p)))
+        .tracked_output();
+    if let Ok(o) = &mkdir_result {
+        folders::note_remote(dst_host, &dst_remote_dirs, &o.stdout);
+        if !o.status.success() {
             let _ = tx.send(WorkerMsg::Error(format!(
-                "Failed to create destination directory: {}", e
+                "Failed to create remote directories on destination: {}",
+                String::from_utf8_lossy(&o.stderr).trim()
             )));
+            let _ = fs::remove_dir_all(&temp_dir);
             return;
         }
     }
 
-    let src_base = src_remote_base.trim_end_matches('/');
-    let src_base_slash = format!("{}/", src_base);
-    let src_root_name = Path::new(src_base).file_name()
-        .map(|f| f.to_string_lossy().to_string())
-        .unwrap_or_default();
-    let ssh_cmd = "ssh -o ControlMaster=auto -o ControlPath=/tmp/kosmokopy_ssh_%h_%p_%r -o ControlPersist=60";
+    // If not overwriting, look up which planned destinations already exist
+    let (mut existing, mut dest_times) = if conflict_mode != ConflictMode::Overwrite {
+        let targets: Vec<&Path> = transfers.iter().map(|t| t.1.as_path()).collect();
+        existing_remote_files_ssh(dst_host, &dst_ctl, &targets, conflict_mode)
+    } else {
+        (HashSet::new(), RemoteTimes::new())
+    };
+    // Newest wins also needs the times of the sources of those files.
+    let source_times = match dest_times.is_empty() {
+        true => RemoteTimes::new(),
+        false => {
+            let sources: Vec<&Path> =
+                transfers.iter().filter(|t| dest_times.contains_key(&t.1)).map(|t| t.0.as_path()).collect();
+            remote_source_times(src_host, &src_ctl, &sources)
+        }
+    };
 
+    let mut stats = RunStats::new(
+        (!src_remote_base.is_empty()).then_some(src_remote_base),
+        remote_files.iter().map(shown).zip(remote_sizes.iter().copied()),
+    );
+    send_plan(&tx, remote_sizes);
+
+    let total_transfers = transfers.len();
     let mut copied = 0usize;
-    let mut skipped: Vec<String> = Vec::new();
-    let mut errors: Vec<String> = Vec::new();
+    let mut skipped = early_skipped;
+    let mut errors = Details::errors();
+    // Only the source-side deletions are deferred; the relay's temp copies
+    // are removed as each upload verifies.
+    let defer = do_move && move_strategy == MoveStrategy::AllThenDelete;
+    let mut deferred: Vec<PathBuf> = Vec::new();
+    let mut unverified: Vec<String> = Vec::new();
 
-    for (i, remote_file) in remote_files.iter().enumerate() {
-        if cancel_flag.load(Ordering::SeqCst) {
-            let _ = tx.send(WorkerMsg::Cancelled {
+    for (i, (src_remote, dst_remote, local_temp)) in transfers.iter().enumerate() {
+        if before_file(i, &tx, &cancel_flag) {
+            skipped.push(format!("{}: {}", shown(src_remote), quota::SKIPPED));
+            continue;
+        }
+        if cancel_flag.stopping() {
+            if defer {
+                let _ = tx.send(WorkerMsg::Deletion(Deletion::Skipped));
+            }
+            if !unverified.is_empty() {
+                let _ = tx.send(WorkerMsg::Unverified(unverified));
+            }
+            let _ = tx.send(WorkerMsg::Cancelled(TransferReport {
                 copied,
                 skipped,
                 excluded_files,
                 excluded_dirs,
+                failed: failed_sources(remote_files.iter().cloned(), errors.shown(), Some(src_host)),
                 errors,
-            });
+                groups: Vec::new(),
+            }));
             return;
         }
-        let rel = remote_file
-            .strip_prefix(&src_base_slash)
-            .unwrap_or(remote_file);
-
-        let local_dest = match transfer_mode {
-            TransferMode::FoldersAndFiles => {
-                if src_root_name.is_empty() { dst_path.join(rel) }
-                else { dst_path.join(&src_root_name).join(rel) }
-            }
-            TransferMode::FilesOnly => {
-                let fname = Path::new(rel)
-                    .file_name()
-                    .map(|f| f.to_string_lossy().to_string())
-                    .unwrap_or_else(|| rel.to_string());
-                dst_path.join(fname)
+        let newer = |p: &Path| source_is_newer(source_times.get(src_remote).copied(), dest_times.remove(p));
+        let dst_remote = match resolve_remote_conflict(dst_remote, &mut existing, conflict_mode, recheck, newer, |p| {
+            remote_path_exists(dst_host, &dst_ctl, p)
+        }) {
+            Some(r) => r,
+            None => {
+                skipped.push(format!("{}: {}", shown(src_remote), kept_reason(conflict_mode)));
+                let _ = tx.send(WorkerMsg::Progress {
+                    done: i + 1,
+                    total: total_transfers,
+                    file: shown(src_remote),
+                });
+                continue;
             }
         };
 
-        let mut local_dest = if strip_spaces {
-            strip_spaces_from_path(&dst_path, &local_dest)
-        } else {
-            local_dest
-        };
-
-        // Create parent directory
-        if let Some(parent) = local_dest.parent() {
+        if let Some(parent) = local_temp.parent() {
             if let Err(e) = fs::create_dir_all(parent) {
-                errors.push(format!("{}: {}", remote_file, e));
+                errors.push(format!("{}: temp dir error: {}", shown(src_remote), e));
                 continue;
             }
         }
 
-        // Check conflict
-        if local_dest.exists() {
-            match conflict_mode {
-                ConflictMode::Skip => {
-                    skipped.push(format!("{}: already exists at destination", remote_file));
-                    let _ = tx.send(WorkerMsg::Progress {
-                        done: i + 1,
-                        total,
-                        file: remote_file.clone(),
-                    });
-                    continue;
-                }
-                ConflictMode::Rename => {
-                    local_dest = find_unique_local_path(&local_dest);
+        // Download from source via rsync
+        let fetched = retry::attempt(opts.retries, &cancel_flag, || {
+            let dl_result = priority::command("rsync", opts.low_priority)
+                .args(["-a", "--checksum"])
+                .args(opts.throttle.rsync_args(true))
+                .arg("-e")
+                .arg(&src_shell)
+                .arg(remote_arg(src_host, rsync_escape_remote(src_remote)))
+                .arg(local_temp)
+                .tracked_stderr();
+            if !matches!(&dl_result, Ok(out) if out.status.success()) {
+                return Err(format!(
+                    "{}: rsync download from source failed{}",
+                    shown(src_remote),
+                    failure_reason(&shown(src_remote), &dl_result)
+                ));
+            }
+            // Verify download
+            match verify_unless_skipped(&cancel_flag, &tx, i, || verify_remote_hash(local_temp, src_host, &src_ctl, src_remote, opts.hash_algo)) {
+                None | Some(Ok(true)) => Ok(Ok(())),
+                Some(Ok(false)) => {
+                    let _ = fs::remove_file(local_temp);
+                    Err(format!(
+                        "{}: download integrity check failed — {}",
+                        shown(src_remote),
+                        hash_mismatch(src_host, &src_ctl, opts.hash_algo)
+                    ))
                 }
-                ConflictMode::Overwrite => {
-                    // fall through
+                Some(Err(e)) => {
+                    let _ = fs::remove_file(local_temp);
+                    Ok(Err(format!("{}: download verification error: {}", shown(src_remote), e)))
                 }
             }
-        }
-
-        // Download from source
-        let download_ok = match transfer_method {
-            TransferMethod::Standard => {
-                let result = Command::new("scp")
-                    .args(&ctl)
-                    .arg("-q")
-                    .arg(format!("{}:{}", src_host, remote_file))
-                    .arg(&local_dest)
-                    .status();
-                matches!(result, Ok(s) if s.success())
-            }
-            TransferMethod::Rsync => {
-                let result = Command::new("rsync")
-                    .args(["-az", "--checksum"])
-                    .arg("-e")
-                    .arg(ssh_cmd)
-                    .arg(format!("{}:{}", src_host, rsync_escape_remote(remote_file)))
-                    .arg(&local_dest)
-                    .status();
-                matches!(result, Ok(s) if s.success())
-            }
-        };
-
-        if !download_ok {
-            errors.push(format!("{}: download from source failed", remote_file));
+        });
+        if let Err(e) = fetched.and_then(|verified| verified) {
+            errors.push(e);
             let _ = tx.send(WorkerMsg::Progress {
                 done: i + 1,
-                total,
-                file: remote_file.clone(),
+                total: total_transfers,
+                file: shown(src_remote),
             });
             continue;
         }
 
-        // Verify download with SHA-256
-        match verify_remote_hash(&local_dest, src_host, &ctl, remote_file) {
-            Ok(true) => {
+        send_phase(&tx, Phase::Transferring);
+        // Upload to destination via rsync
+        let sent = retry::attempt(opts.retries, &cancel_flag, || {
+            let ul_result = priority::command("rsync", opts.low_priority)
+                .args(["-a", "--checksum"])
+                .args(opts.throttle.rsync_args(true))
+                .arg("-e")
+                .arg(&dst_shell)
+                .arg(local_temp)
+                .arg(remote_arg(dst_host, rsync_escape_remote(dst_remote.as_os_str())))
+                .tracked_stderr();
+            if !matches!(&ul_result, Ok(out) if out.status.success()) {
+                return Err(format!(
+                    "{}: rsync upload to destination failed{}",
+                    shown(src_remote),
+                    failure_reason(&shown(src_remote), &ul_result)
+                ));
+            }
+            // Verify upload
+            match verify_unless_skipped(&cancel_flag, &tx, i, || verify_remote_hash(local_temp, dst_host, &dst_ctl, &dst_remote, opts.hash_algo)) {
+                None => Ok(None),
+                Some(Ok(true)) => Ok(Some(Ok(()))),
+                Some(Ok(false)) => {
+                    // Remove corrupt destination copy
+                    let _ = Command::new("ssh")
+                        .args(&dst_ctl)
+                        .arg(dst_host)
+                        .arg(path_command("rm -f {}", &[dst_remote.as_os_str()]))
+                        .tracked_status();
+                    Err(format!(
+                        "{}: upload integrity check failed — {} (source retained, dest copy removed)",
+                        shown(src_remote),
+                        hash_mismatch(dst_host, &dst_ctl, opts.hash_algo)
+                    ))
+                }
+                Some(Err(e)) => Ok(Some(Err(e))),
+            }
+        });
+        let verified = match sent {
+            Ok(verified) => verified,
+            Err(e) => {
+                let _ = fs::remove_file(local_temp);
+                errors.push(e);
+                let _ = tx.send(WorkerMsg::Progress {
+                    done: i + 1,
+                    total: total_transfers,
+                    file: shown(src_remote),
+                });
+                continue;
+            }
+        };
+        // Later entries mapping to the same path now see it as taken
+        existing.insert(dst_remote.to_path_buf());
+
+        match verified {
+            None => {
+                let _ = fs::remove_file(local_temp);
+                unverified.push(shown(src_remote));
+            }
+            Some(Ok(())) => {
                 copied += 1;
-                if do_move {
-                    // Delete from source host
+                stats.copied(&shown(src_remote));
+                note_copied(reverify::Location::remote(src_host, src_remote), reverify::Location::remote(dst_host, &dst_remote), opts);
+                let _ = fs::remove_file(local_temp);
+                if defer {
+                    deferred.push(src_remote.clone());
+                } else if do_move {
                     let rm_result = Command::new("ssh")
-                        .args(&ctl)
+                        .args(&src_ctl)
                         .arg(src_host)
-                        .arg(format!("rm -f {}", shell_quote(remote_file)))
-                        .status();
+                        .arg(path_command("rm -f {}", &[src_remote.as_os_str()]))
+                        .tracked_status();
                     if !matches!(rm_result, Ok(s) if s.success()) {
                         errors.push(format!(
-                            "{}: downloaded and verified but failed to delete from source",
-                            remote_file
+                            "{}: transferred and verified but failed to delete from source",
+                            shown(src_remote)
                         ));
                     }
                 }
             }
-            Ok(false) => {
-                let _ = fs::remove_file(&local_dest);
-                errors.push(format!(
-                    "{}: download integrity check failed — hash mismatch (local copy removed)",
-                    remote_file
-                ));
-            }
-            Err(e) => {
+            Some(Err(e)) => {
+                let _ = fs::remove_file(local_temp);
                 if do_move {
                     errors.push(format!(
-                        "{}: downloaded but verification failed: {} (source retained)",
-                        remote_file, e
+                        "{}: uploaded but verification failed: {} (source retained)",
+                        shown(src_remote), e
                     ));
                 } else {
                     errors.push(format!(
-                        "{}: downloaded but could not verify: {}",
-                        remote_file, e
+                        "{}: uploaded but could not verify: {}",
+                        shown(src_remote), e
+                    ));
+                }
+            }
+        }
+
+        let _ = tx.send(WorkerMsg::Progress {
+            done: i + 1,
+            total: total_transfers,
+            file: shown(src_remote),
+        });
+    }
+
+    send_phase(&tx, Phase::Finalizing);
+    let _ = fs::remove_dir_all(&temp_dir);
+
+    let cancelled = defer
+        && delete_deferred_sources(&deferred, unverified.len(), Some(src_host), &src_ctl, &mut errors, &mut 0, &cancel_flag, &tx);
+    if !cancelled {
+        tidy_moved_remote_source(src_host, &src_ctl, src_remote_base, &remote_files, do_move, opts.remove_source_root, &errors);
+    }
+    let groups = stats.finish(skipped.shown(), errors.shown());
+    let failed = failed_sources(remote_files.iter().cloned(), errors.shown(), Some(src_host));
+    if !unverified.is_empty() {
+        let _ = tx.send(WorkerMsg::Unverified(unverified));
+    }
+    let report = TransferReport { copied, skipped, excluded_files, excluded_dirs, errors, failed, groups };
+    let _ = tx.send(if cancelled { WorkerMsg::Cancelled(report) } else { WorkerMsg::Finished(report) });
+}
+
+// ── Dated destination retention ────────────────────────────────────────
+
+/// Delete all but the newest `keep` dated folders in `base` (local or
+/// `host:/path`) named by `template`, or with `to_trash` move them to the
+/// trash (see `trash::script`).  Returns the folders deleted, or with
+/// `dry_run` the ones that would be; failures are added to `errors`.
+fn prune_dated(
+    base: &str,
+    template: &Template,
+    keep: usize,
+    dry_run: bool,
+    to_trash: bool,
+    errors: &mut Details,
+) -> Vec<String> {
+    let (host, base_path) = parse_destination(base);
+    let listing = match &host {
+        Some(h) => list_remote_subdirs(h, &base_path),
+        None => list_local_subdirs(Path::new(&base_path)).map_err(|e| e.to_string()),
+    };
+    let names = match listing {
+        Ok(names) => names,
+        Err(e) => {
+            errors.push(format!("{}: could not list dated folders: {}", base, e));
+            return Vec::new();
+        }
+    };
+
+    let mut pruned = Vec::new();
+    for name in retention::to_prune(&names, template, keep) {
+        let target = format!("{}/{}", base_path.trim_end_matches('/'), name);
+        let shown = match &host {
+            Some(h) => format!("{}:{}", h, target),
+            None => target.clone(),
+        };
+        if !retention::is_safe_target(&base_path, &target, template) {
+            errors.push(format!("{}: not deleted, not a dated folder directly under {}", shown, base_path));
+            continue;
+        }
+        if !dry_run {
+            let script = if to_trash {
+                trash::script(OsStr::new(&target), |p| quote_path(p))
+            } else {
+                OsString::from(format!("rm -rf -- {}", shell_quote(&target)))
+            };
+            let run = |command: &mut Command| {
+                command.tracked_output().map_err(|e| e.to_string()).and_then(|o| {
+                    if o.status.success() {
+                        Ok(())
+                    } else {
+                        Err(String::from_utf8_lossy(&o.stderr).trim().to_string())
+                    }
+                })
+            };
+            let removed = match &host {
+                Some(h) => run(Command::new("ssh").args(ssh::ctl_args(h)).arg(h).arg(script)),
+                None if to_trash => {
+                    activity::set(&format!("moving {} to the trash", target));
+                    run(Command::new("sh").arg("-c").arg(script))
+                }
+                None => {
+                    activity::set(&format!("deleting {}", target));
+                    fs::remove_dir_all(&target).map_err(|e| e.to_string())
+                }
+            };
+            if let Err(e) = removed {
+                let what = if to_trash { "move old dated folder to the trash" } else { "delete old dated folder" };
+                errors.push(format!("{}: could not {}: {}", shown, what, e));
+                continue;
+            }
+        }
+        pruned.push(shown);
+    }
+    pruned
+}
+
+/// Names of the directories directly in `dir`; symlinks are left out.
+fn list_local_subdirs(dir: &Path) -> std::io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            if let Some(name) = entry.file_name().to_str() {
+                names.push(name.to_string());
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Names of the directories directly in `dir` on `host`, listed
+/// NUL-separated so that any name comes through whole.  `find -type d`
+/// leaves out symlinks to directories.
+fn list_remote_subdirs(host: &str, dir: &str) -> Result<Vec<String>, String> {
+    let out = Command::new("ssh")
+        .args(ssh::ctl_args(host))
+        .arg(host)
+        .arg(format!("find {} -mindepth 1 -maxdepth 1 -type d -print0", shell_quote(dir)))
+        .tracked_output()
+        .map_err(|e| e.to_string())?;
+    if !out.status.success() {
+        return Err(String::from_utf8_lossy(&out.stderr).trim().to_string());
+    }
+    Ok(out
+        .stdout
+        .split(|&b| b == 0)
+        .filter_map(|path| path.rsplit(|&b| b == b'/').next())
+        .filter_map(|name| std::str::from_utf8(name).ok())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Create a temporary directory for relay transfers.
+fn tempdir_for_relay() -> std::io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("kosmokopy_relay_{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+// ── Hashing for remote transfer verification ──────────────────────────
+
+/// The hash of a local file with `algo`, as a lowercase hex string.
+fn hash_local(path: &Path, algo: HashAlgo) -> std::io::Result<String> {
+    activity::set(&format!("hashing {}", shown(path)));
+    hashalgo::hash_file(path, algo)
+}
+
+/// The algorithm files on `host` are hashed with this run: `chosen` if
+/// the host has its program, asked once per run, otherwise SHA-256.
+fn remote_algo(host: &str, ctl: &[String], chosen: HashAlgo) -> HashAlgo {
+    hashalgo::for_host(host, chosen, |tool| {
+        Command::new("ssh")
+            .args(ctl)
+            .arg(host)
+            .arg(format!("command -v {} >/dev/null 2>&1", tool))
+            .tracked_status()
+            .is_ok_and(|status| status.success())
+    })
+}
+
+/// `command`, built for a stand-in path, with each stand-in replaced by
+/// `quoted`.  A NUL stands in, as no shell command can hold one.
+fn hash_command(command: impl Fn(&str) -> String, quoted: &OsStr) -> OsString {
+    let mut out = OsString::new();
+    for (i, part) in command("\0").split('\0').enumerate() {
+        if i > 0 {
+            out.push(quoted);
+        }
+        out.push(part);
+    }
+    out
+}
+
+/// The hash of a remote file via SSH, with `algo`.
+fn hash_remote(host: &str, ctl: &[String], remote_path: &Path, algo: HashAlgo) -> Result<String, String> {
+    let output = Command::new("ssh")
+        .args(ctl)
+        .arg(host)
+        .arg(hash_command(|p| algo.remote_command_one(p), &quote_path(remote_path)))
+        .tracked_output()
+        .map_err(|e| format!("Failed to run SSH for hash verification: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Remote {} hash command failed: {}",
+            algo.name(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Every tool prints: <hash>  <filename>, after a backslash when the
+    // name had to be escaped (a newline or backslash in it).
+    let hash = stdout
+        .trim()
+        .split_whitespace()
+        .next()
+        .map(|hash| hash.strip_prefix('\\').unwrap_or(hash))
+        .ok_or_else(|| "Could not parse remote hash output".to_string())?;
+
+    Ok(hash.to_lowercase().to_string())
+}
+
+/// Verify a local file against a remote file by comparing their hashes,
+/// with `chosen` if `host` allows.
+fn verify_remote_hash(
+    local: &Path,
+    host: &str,
+    ctl: &[String],
+    remote: &Path,
+    chosen: HashAlgo,
+) -> Result<bool, String> {
+    let algo = remote_algo(host, ctl, chosen);
+    // Hashing the local side is the local share of a remote run's time.
+    let started = std::time::Instant::now();
+    let local_hash = hash_local(local, algo);
+    timing::note_local(started.elapsed());
+    let local_hash = local_hash.map_err(|e| format!("local {} hash error: {}", algo.name(), e))?;
+    let remote_hash = hash_remote(host, ctl, remote, algo)?;
+    let same = local_hash == remote_hash;
+    if same {
+        note_hash([reverify::Location::Local(local.to_path_buf()), reverify::Location::remote(host, remote)], algo, &local_hash);
+    }
+    Ok(same)
+}
+
+/// Note the digest both ends of a verified copy came to, for the detail
+/// report; its record is written under either end.
+fn note_hash(ends: [reverify::Location; 2], algo: HashAlgo, hash: &str) {
+    for end in &ends {
+        filereport::hashed(end, algo.name(), hash);
+    }
+}
+
+/// `hash mismatch`, naming the algorithm `host` was checked with.
+fn hash_mismatch(host: &str, ctl: &[String], chosen: HashAlgo) -> String {
+    format!("{} hash mismatch", remote_algo(host, ctl, chosen).name())
+}
+
+/// The algorithm two hosts both hash with: `chosen` where each has its
+/// tool, otherwise SHA-256.
+fn pair_algo(src_host: &str, src_ctl: &[String], dst_host: &str, dst_ctl: &[String], chosen: HashAlgo) -> HashAlgo {
+    let algo = remote_algo(src_host, src_ctl, chosen);
+    if remote_algo(dst_host, dst_ctl, chosen) == algo { algo } else { HashAlgo::Sha256 }
+}
+
+/// Verify a copy sent straight between two hosts by comparing the hashes
+/// each host gives, with `chosen` where both allow.
+fn verify_between_hosts(
+    src_host: &str,
+    src_ctl: &[String],
+    src: &Path,
+    dst_host: &str,
+    dst_ctl: &[String],
+    dst: &Path,
+    chosen: HashAlgo,
+) -> Result<bool, String> {
+    let algo = pair_algo(src_host, src_ctl, dst_host, dst_ctl, chosen);
+    let hash = hash_remote(src_host, src_ctl, src, algo)?;
+    let same = hash == hash_remote(dst_host, dst_ctl, dst, algo)?;
+    if same {
+        note_hash([reverify::Location::remote(src_host, src), reverify::Location::remote(dst_host, dst)], algo, &hash);
+    }
+    Ok(same)
+}
+
+/// Hashes of many files on `host` with `algo`, by path, over one
+/// connection per `reverify::HASH_BATCH` of them.  A file that could not
+/// be hashed is left out; only a failed connection is an error.
+fn hash_remote_batch(host: &str, ctl: &[String], paths: &[PathBuf], algo: HashAlgo) -> Result<HashMap<PathBuf, String>, String> {
+    let mut hashes = HashMap::new();
+    for batch in paths.chunks(reverify::HASH_BATCH) {
+        let output = Command::new("ssh")
+            .args(ctl)
+            .arg(host)
+            .arg(hash_command(|p| algo.remote_command(p), &quoted_list(batch)))
+            .tracked_output()
+            .map_err(|e| format!("Failed to run SSH for hash verification: {}", e))?;
+        // The tools exit non-zero when any file is missing; ssh exits 255
+        // when it can't connect.
+        if output.status.code() == Some(255) {
+            return Err(format!("Could not reach {}: {}", host, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        hashes.extend(reverify::parse_hashes(&output.stdout, algo.hex_len()));
+    }
+    Ok(hashes)
+}
+
+// ── Verifying uploads in batches ───────────────────────────────────────
+
+/// Files sent to another host and waiting to be verified, and what the
+/// ones already verified came to.  Hashing each copy over its own ssh
+/// doubles the round trips of a large upload, so the upload workers
+/// gather up to `reverify::HASH_BATCH` copies and hash them with one
+/// `sha256sum` on the host.  A copy the batch could not hash, or every
+/// copy when the call itself fails, is then hashed on its own.
+struct UploadBatch<'a> {
+    host: &'a str,
+    ctl: &'a [String],
+    opts: &'a RunOptions,
+    /// How a copy that doesn't match is sent again.
+    method: TransferMethod,
+    do_move: bool,
+    defer: bool,
+    /// (index in the plan, local file, remote copy)
+    waiting: Vec<(usize, PathBuf, PathBuf)>,
+    copied: usize,
+    errors: Details,
+    ro_kept: usize,
+    deferred: Vec<PathBuf>,
+    unverified: Vec<String>,
+}
+
+impl<'a> UploadBatch<'a> {
+    fn new(host: &'a str, ctl: &'a [String], opts: &'a RunOptions, method: TransferMethod, do_move: bool, defer: bool) -> Self {
+        UploadBatch {
+            host,
+            ctl,
+            opts,
+            method,
+            do_move,
+            defer,
+            waiting: Vec::new(),
+            copied: 0,
+            errors: Details::errors(),
+            ro_kept: 0,
+            deferred: Vec::new(),
+            unverified: Vec::new(),
+        }
+    }
+
+    /// Note that the file at `index` in the plan was sent to `remote`.
+    fn add(&mut self, index: usize, local: PathBuf, remote: PathBuf) {
+        self.waiting.push((index, local, remote));
+    }
+
+    fn is_full(&self) -> bool {
+        self.waiting.len() >= reverify::HASH_BATCH
+    }
+
+    /// Whether the copy at `remote` is still to be verified.  Another file
+    /// written there first would be checked in its place.
+    fn is_waiting(&self, remote: &Path) -> bool {
+        self.waiting.iter().any(|(_, _, r)| r == remote)
+    }
+
+    /// Verify the copies waiting, then count each one, or remove it and
+    /// keep its source when it does not match.
+    fn verify(&mut self, stats: &mut RunStats, cancel_flag: &CancelFlag, tx: &mpsc::Sender<WorkerMsg>) {
+        let waiting = std::mem::take(&mut self.waiting);
+        // A link kept as a link has no contents of its own to hash.
+        let checked: Vec<PathBuf> = waiting
+            .iter()
+            .filter(|(i, local, _)| sample::includes(*i) && !symlinks::preserved(self.opts.symlinks, local))
+            .map(|(_, _, r)| r.clone())
+            .collect();
+        let algo = remote_algo(self.host, self.ctl, self.opts.hash_algo);
+        let hashes = match checked.is_empty() || !start_verify(cancel_flag, tx) {
+            true => None,
+            false => Some(hash_remote_batch(self.host, self.ctl, &checked, algo)),
+        };
+        for (index, local, remote) in waiting {
+            let verified = match &hashes {
+                _ if !sample::includes(index) || symlinks::preserved(self.opts.symlinks, &local) => Some(Ok(true)),
+                None => None,
+                Some(hashes) => {
+                    sample::note_verified(index);
+                    Some(match hashes.as_ref().ok().and_then(|h| h.get(&remote)) {
+                        Some(remote_hash) => {
+                            let started = std::time::Instant::now();
+                            let local_hash = hash_local(&local, algo);
+                            timing::note_local(started.elapsed());
+                            local_hash
+                                .map(|h| h == *remote_hash)
+                                .inspect(|&same| {
+                                    if same {
+                                        let ends = [reverify::Location::Local(local.clone()), reverify::Location::remote(self.host, &remote)];
+                                        note_hash(ends, algo, remote_hash);
+                                    }
+                                })
+                                .map_err(|e| format!("local {} hash error: {}", algo.name(), e))
+                        }
+                        None => verify_remote_hash(&local, self.host, self.ctl, &remote, self.opts.hash_algo),
+                    })
+                }
+            };
+            self.settle(local, &remote, verified, stats, cancel_flag);
+        }
+        if hashes.is_some() {
+            // Files still to be sent are printed as transferring again
+            activity::set_stage(Phase::Transferring);
+        }
+    }
+
+    fn settle(
+        &mut self,
+        local: PathBuf,
+        remote: &Path,
+        verified: Option<Result<bool, String>>,
+        stats: &mut RunStats,
+        cancel_flag: &CancelFlag,
+    ) {
+        match verified {
+            None => self.unverified.push(shown(&local)),
+            Some(Ok(true)) => {
+                self.copied += 1;
+                stats.copied(&shown(&local));
+                note_copied(reverify::Location::Local(local.clone()), reverify::Location::remote(self.host, remote), self.opts);
+                if self.defer {
+                    self.deferred.push(local);
+                } else if self.do_move {
+                    if let Err(e) = remove_moved_source(&local, &mut self.ro_kept) {
+                        self.errors.push(format!(
+                            "{}: transferred and verified but failed to delete local: {}",
+                            shown(&local),
+                            e
+                        ));
+                    }
+                }
+            }
+            Some(Ok(false)) => {
+                // Hash mismatch — remove corrupt remote copy, keep source
+                let remove_copy = || {
+                    let _ = Command::new("ssh")
+                        .args(self.ctl)
+                        .arg(self.host)
+                        .arg(path_command("rm -f {}", &[remote.as_os_str()]))
+                        .tracked_status();
+                };
+                let mismatch = format!(
+                    "{}: integrity check failed — {} (original retained, remote copy removed)",
+                    shown(&local),
+                    hash_mismatch(self.host, self.ctl, self.opts.hash_algo)
+                );
+                let resent = retry::again(self.opts.retries, mismatch.clone(), cancel_flag, || {
+                    remove_copy();
+                    self.resend(&local, remote)?;
+                    match verify_remote_hash(&local, self.host, self.ctl, remote, self.opts.hash_algo) {
+                        Ok(true) => Ok(()),
+                        Ok(false) => Err(mismatch.clone()),
+                        Err(e) => Err(format!("{}: transferred but could not verify: {}", shown(&local), e)),
+                    }
+                });
+                match resent {
+                    Ok(()) => self.settle(local, remote, Some(Ok(true)), stats, cancel_flag),
+                    Err(e) => {
+                        remove_copy();
+                        self.errors.push(e);
+                    }
+                }
+            }
+            Some(Err(e)) => {
+                // Cannot verify — keep both, report error
+                if self.do_move {
+                    self.errors.push(format!(
+                        "{}: transferred but verification failed: {} (original retained)",
+                        shown(&local),
+                        e
                     ));
+                } else {
+                    self.errors.push(format!("{}: transferred but could not verify: {}", shown(&local), e));
                 }
             }
         }
+    }
+}
+
+impl UploadBatch<'_> {
+    /// Send `local` to `remote` again, the way the run sent it first.
+    fn resend(&self, local: &Path, remote: &Path) -> Result<(), String> {
+        match self.method {
+            TransferMethod::Rsync => rsync_upload(&ssh::rsync_shell(self.host), self.host, local, remote, ConflictMode::Overwrite, self.opts)
+                .map_err(|why| format!("{}: {}", shown(local), why)),
+            _ => scp_upload(self.host, self.ctl, local, remote, 1, self.opts),
+        }
+    }
+}
+
+// ── Verifying a finished run again ─────────────────────────────────────
+
+/// Note a file copied and verified by a run with `opts`, for "Verify
+/// now" and the detail report.
+fn note_copied(source: reverify::Location, dest: reverify::Location, opts: &RunOptions) {
+    filereport::copied(&source, &dest, opts);
+    reverify::note(source, dest);
+}
+
+/// Hash each file of `record` again at both ends and compare, for the
+/// result dialog's "Verify now", with `chosen` where every host has it.
+/// Remote files are hashed first, in
+/// batches; local ones are read as they come.  Sends the plan, progress
+/// and finally `WorkerMsg::Reverified`; stops early when cancelled.
+fn run_reverify_worker(record: Vec<reverify::Copied>, chosen: HashAlgo, cancel_flag: Arc<CancelFlag>, tx: mpsc::Sender<WorkerMsg>) {
+    use reverify::Location;
+
+    let total = record.len();
+    let sizes = record
+        .iter()
+        .map(|c| match &c.dest {
+            Location::Local(path) => fs::metadata(path).ok().map(|m| m.len()),
+            Location::Remote { .. } => None,
+        })
+        .collect();
+    let _ = tx.send(WorkerMsg::Plan { sizes });
+    send_phase(&tx, Phase::Verifying);
+
+    let mut by_host: HashMap<&str, Vec<PathBuf>> = HashMap::new();
+    for loc in record.iter().flat_map(|c| [&c.source, &c.dest]) {
+        if let Location::Remote { host, path } = loc {
+            by_host.entry(host.as_str()).or_default().push(path.clone());
+        }
+    }
+    // Both ends of a file need the same algorithm: the chosen one when
+    // every host has it, otherwise SHA-256 throughout.
+    let algo = by_host.keys().fold(chosen, |algo, host| match remote_algo(host, &ssh::ctl_args(host), chosen) {
+        used if used == algo => algo,
+        _ => HashAlgo::Sha256,
+    });
+    let remote: HashMap<&str, Result<HashMap<PathBuf, String>, String>> = by_host
+        .into_iter()
+        .map(|(host, paths)| (host, hash_remote_batch(host, &ssh::ctl_args(host), &paths, algo)))
+        .collect();
+    let hash = |loc: &Location| -> Result<String, String> {
+        match loc {
+            Location::Local(path) => hash_local(path, algo).map_err(|e| e.to_string()),
+            Location::Remote { host, path } => match &remote[host.as_str()] {
+                Ok(hashes) => hashes.get(path).cloned().ok_or_else(|| "could not be hashed".to_string()),
+                Err(e) => Err(e.clone()),
+            },
+        }
+    };
 
+    let mut outcome = reverify::Outcome { total, ..Default::default() };
+    for (i, copied) in record.iter().enumerate() {
+        if cancel_flag.stopping() {
+            outcome.cancelled = true;
+            break;
+        }
+        match (hash(&copied.source), hash(&copied.dest)) {
+            (Ok(a), Ok(b)) if a == b => outcome.matched += 1,
+            (Ok(_), Ok(_)) => outcome.differ.push(copied.dest.describe()),
+            (Err(e), _) => outcome.failed.push(format!("{}: {}", copied.source.describe(), e)),
+            (_, Err(e)) => outcome.failed.push(format!("{}: {}", copied.dest.describe(), e)),
+        }
         let _ = tx.send(WorkerMsg::Progress {
             done: i + 1,
             total,
-            file: remote_file.clone(),
+            file: copied.dest.describe(),
         });
     }
-
-    let _ = tx.send(WorkerMsg::Finished {
-        copied,
-        skipped,
-        excluded_files,
-        excluded_dirs,
-        errors,
-    });
+    let _ = tx.send(WorkerMsg::Reverified(outcome));
 }
 
-// ── Worker thread (remote source → remote destination via SCP) ─────────
+// ── Worker thread (remote via rsync) ───────────────────────────────────
 
-fn run_remote_to_remote_worker(
-    src_host: &str,
-    src_remote_base: &str,
-    dst_host: &str,
-    dst_remote_base: &str,
+fn run_remote_rsync_worker(
+    source: SourceSelection,
+    host: &str,
+    remote_base: &str,
     do_move: bool,
+    move_strategy: MoveStrategy,
     conflict_mode: ConflictMode,
-    strip_spaces: bool,
+    recheck: Recheck,
+    renamer: &Renamer,
     transfer_mode: TransferMode,
+    keep_parents: usize,
     patterns: &[String],
-    cancel_flag: Arc<AtomicBool>,
+    opts: &RunOptions,
+    cancel_flag: Arc<CancelFlag>,
     tx: mpsc::Sender<WorkerMsg>,
 ) {
-    let ctl = [
-        "-o", "ControlMaster=auto",
-        "-o", "ControlPath=/tmp/kosmokopy_ssh_%h_%p_%r",
-        "-o", "ControlPersist=60",
-    ];
+    // SSH options — reused for direct ssh calls and passed to rsync via -e
+    let ctl = ssh::ctl_args(host);
+    let ssh_cmd = ssh::rsync_shell(host);
 
-    // Connectivity check to both hosts
-    for host in [src_host, dst_host] {
-        let check = Command::new("ssh")
-            .args(&ctl)
-            .args([host, "echo ok"])
-            .output();
-        match check {
-            Ok(o) if o.status.success() => {}
-            Ok(o) => {
-                let _ = tx.send(WorkerMsg::Error(format!(
-                    "SSH connection to '{}' failed: {}",
-                    host,
-                    String::from_utf8_lossy(&o.stderr).trim()
-                )));
-                return;
-            }
-            Err(e) => {
-                let _ = tx.send(WorkerMsg::Error(format!("Could not run ssh: {}", e)));
-                return;
-            }
-        }
+    // Quick connectivity check
+    if !check_ssh_host(host, &ctl, &tx) || !check_remote_destination(host, &ctl, remote_base, &tx) {
+        return;
+    }
+    if !settle_remote_stale_artifacts(host, &ctl, remote_base, &tx) {
+        return;
     }
 
-    // List remote source files
-    let (remote_files, excluded_files, excluded_dirs) = match collect_remote_files(src_host, &ctl, src_remote_base, patterns) {
+    // Check rsync here and on the host
+    if !check_rsync_peers(&[host], &tx) {
+        return;
+    }
+
+    // Collect files locally
+    let (files, excluded_files, excluded_dirs) = match collect_files(&source, patterns, opts.symlinks, &mut worker_scan_guard(opts.scan_limits, &cancel_flag, &tx)) {
         Ok(v) => v,
         Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(e));
+            let _ = tx.send(scan_failure(e, &cancel_flag));
             return;
         }
     };
+    // The links the scan left out lead the skipped list.
+    let mut early_skipped = links_left_out();
+    if cancel_flag.stopping() {
+        let _ = tx.send(cancelled_before_transfer(early_skipped, excluded_files, excluded_dirs));
+        return;
+    }
 
-    let total = remote_files.len();
+    let total = files.len();
     if total == 0 {
-        let _ = tx.send(WorkerMsg::Finished {
-            copied: 0,
-            skipped: vec![],
-            excluded_files,
-            excluded_dirs,
-            errors: vec![],
-        });
+        let _ = tx.send(WorkerMsg::Finished(TransferReport::nothing_sent(early_skipped, excluded_files, excluded_dirs)));
         return;
     }
 
-    // Create a temp directory for the local staging area
-    let temp_dir = match tempdir_for_relay() {
-        Ok(d) => d,
-        Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(format!(
-                "Failed to create temp directory: {}", e
-            )));
-            return;
-        }
+    let src_dir = match &source {
+        SourceSelection::Directory(d) | SourceSelection::DirectoryItems(d, _) => Some(d.clone()),
+        _ => None,
     };
+    let parent_levels = parent_levels(transfer_mode, src_dir.is_none(), keep_parents);
+    if let Err(e) = settle_collisions(
+        files.iter().map(|file| PlanInput { file, src_dir: src_dir.as_deref(), transfer_mode, parent_levels, dest: Dest::Remote(remote_base) }),
+        renamer,
+        false,
+        opts.on_collision,
+    ) {
+        let _ = tx.send(WorkerMsg::Error(e));
+        return;
+    }
 
-    let src_base = src_remote_base.trim_end_matches('/');
-    let src_base_slash = format!("{}/", src_base);
-    let src_root_name = Path::new(src_base).file_name()
-        .map(|f| f.to_string_lossy().to_string())
-        .unwrap_or_default();
-    let dst_base = dst_remote_base.trim_end_matches('/');
-
-    // Build destination remote paths and ensure remote dirs
-    let mut transfers: Vec<(String, String, PathBuf)> = Vec::new(); // (src_remote, dst_remote, local_temp)
-    let mut dst_remote_dirs: HashSet<String> = HashSet::new();
-    dst_remote_dirs.insert(dst_base.to_string());
-
-    for remote_file in &remote_files {
-        // For single-file sources, strip_prefix fails because
-        // src_base_slash is "<file>/" which doesn't match. Use just
-        // the filename so local_temp stays inside temp_dir.
-        let is_single_file = remote_file.as_str() == src_base;
-        let rel = remote_file
-            .strip_prefix(&src_base_slash)
-            .unwrap_or_else(|| {
-                Path::new(remote_file.as_str())
-                    .file_name()
-                    .and_then(|f| f.to_str())
-                    .unwrap_or(remote_file.as_str())
-            });
+    // Build list of (local_path, remote_path) pairs
+    let remote_base = remote_base.trim_end_matches('/');
+    let mut transfers: Vec<(PathBuf, PathBuf)> = Vec::new();
 
-        let dst_rel = match transfer_mode {
-            TransferMode::FoldersAndFiles => {
-                if src_root_name.is_empty() || is_single_file { rel.to_string() }
-                else { format!("{}/{}", src_root_name, rel) }
-            }
-            TransferMode::FilesOnly => {
-                Path::new(rel)
-                    .file_name()
-                    .map(|f| f.to_string_lossy().to_string())
-                    .unwrap_or_else(|| rel.to_string())
+    for file_path in &files {
+        let placed = destpath::build_destination(
+            &PlanInput {
+                file: file_path,
+                src_dir: src_dir.as_deref(),
+                transfer_mode,
+                parent_levels,
+                dest: Dest::Remote(remote_base),
+            },
+            |rel| renamer.rename_path(rel, modified(file_path)),
+        );
+        let remote_file = match placed {
+            Ok(dest) => dest.into_remote(),
+            Err(why) => {
+                early_skipped.push(format!("{}: {}", shown(file_path), why.reason()));
+                continue;
             }
         };
+        transfers.push((file_path.clone(), remote_file));
+    }
 
-        let dst_remote = format!("{}/{}", dst_base, dst_rel);
-        let dst_remote = if strip_spaces {
-            dst_remote.split('/').map(|c| c.replace(' ', "")).collect::<Vec<_>>().join("/")
-        } else {
-            dst_remote
-        };
-
-        if let Some(parent) = Path::new(&dst_remote).parent() {
-            dst_remote_dirs.insert(parent.to_string_lossy().to_string());
-        }
-
-        // Local temp path preserves structure for staging
-        let local_temp = temp_dir.join(rel);
-        transfers.push((remote_file.clone(), dst_remote, local_temp));
+    // Check the existing folders the upload will write into before
+    // creating any.
+    let Some(transfers) = preflight_remote(
+        transfers, host, &ctl, |t| &t.1, |t| shown(&t.0), &mut early_skipped, &tx,
+    ) else {
+        return;
+    };
+    if stopped_before_transfer(&cancel_flag, &mut early_skipped, excluded_files, excluded_dirs, &tx) {
+        return;
+    }
+    let sizes: Vec<Option<u64>> = transfers.iter().map(|(l, _)| fs::metadata(l).ok().map(|m| m.len())).collect();
+    if !room_for_plan(&sizes, opts.ignore_free_space, || remote_free(host, &ctl, remote_base), &tx) {
+        return;
     }
+    let remote_dirs = remote_target_dirs(remote_base, transfers.iter().map(|t| t.1.as_path()));
 
-    // Create all destination remote directories
-    let dirs_arg: Vec<String> = dst_remote_dirs.iter().map(|d| shell_quote(d)).collect();
+    // Create all remote directories in one SSH call
     let mkdir_result = Command::new("ssh")
         .args(&ctl)
-        .arg(dst_host)
-        .arg(format!("mkdir -p {}", dirs_arg.join(" ")))
-        .output();
+        .arg(host)
+        .arg(folders::mkdir_script(&remote_dirs, |p| quote_path(p)))
+        .tracked_output();
     if let Ok(o) = &mkdir_result {
+        folders::note_remote(host, &remote_dirs, &o.stdout);
         if !o.status.success() {
+            let msg = String::from_utf8_lossy(&o.stderr);
             let _ = tx.send(WorkerMsg::Error(format!(
-                "Failed to create remote directories on destination: {}",
-                String::from_utf8_lossy(&o.stderr).trim()
+                "Failed to create remote directories: {}",
+                msg.trim()
             )));
-            let _ = fs::remove_dir_all(&temp_dir);
             return;
         }
     }
 
-    // If not overwriting, get existing files on destination
-    let existing: HashSet<String> = if conflict_mode != ConflictMode::Overwrite {
-        let out = Command::new("ssh")
-            .args(&ctl)
-            .arg(dst_host)
-            .arg(format!("find {} -type f 2>/dev/null", shell_quote(dst_base)))
-            .output();
-        match out {
-            Ok(o) => String::from_utf8_lossy(&o.stdout)
-                .lines()
-                .map(|l| l.to_string())
-                .collect(),
-            Err(_) => HashSet::new(),
-        }
+    // If not overwriting, look up which planned destinations already exist
+    let (mut existing, mut dest_times) = if conflict_mode != ConflictMode::Overwrite {
+        let targets: Vec<&Path> = transfers.iter().map(|t| t.1.as_path()).collect();
+        existing_remote_files_ssh(host, &ctl, &targets, conflict_mode)
     } else {
-        HashSet::new()
+        (HashSet::new(), RemoteTimes::new())
     };
 
+    send_plan(&tx, sizes);
+    let mut stats = RunStats::new(
+        source.group_root().as_deref(),
+        files.iter().map(|f| (shown(f), fs::metadata(f).ok().map(|m| m.len()))),
+    );
+
     let total_transfers = transfers.len();
-    let mut copied = 0usize;
-    let mut skipped: Vec<String> = Vec::new();
-    let mut errors: Vec<String> = Vec::new();
+    let mut skipped = early_skipped;
+    let defer = do_move && move_strategy == MoveStrategy::AllThenDelete;
+    let mut batch = UploadBatch::new(host, &ctl, opts, TransferMethod::Rsync, do_move, defer);
 
-    for (i, (src_remote, dst_remote, local_temp)) in transfers.iter().enumerate() {
-        if cancel_flag.load(Ordering::SeqCst) {
-            let _ = tx.send(WorkerMsg::Cancelled {
-                copied,
-                skipped,
-                excluded_files,
-                excluded_dirs,
-                errors,
-            });
-            return;
-        }
-        // Handle conflict if destination exists
-        let dst_remote = if conflict_mode != ConflictMode::Overwrite && existing.contains(dst_remote) {
-            match conflict_mode {
-                ConflictMode::Skip => {
-                    skipped.push(format!("{}: already exists at destination", src_remote));
-                    let _ = tx.send(WorkerMsg::Progress {
-                        done: i + 1,
-                        total: total_transfers,
-                        file: src_remote.clone(),
-                    });
+    let front = parallel::Front::new(total_transfers);
+    let progress = |i: usize| {
+        let _ = tx.send(WorkerMsg::Progress {
+            done: front.end(i),
+            total: total_transfers,
+            file: shown(&transfers[i].0),
+        });
+    };
+    let mut plan = transfers.iter().enumerate();
+    // A file bound for a path still waiting to be written, put off to the
+    // next call so that the copy there is verified first
+    let mut carried: Option<(usize, PathBuf)> = None;
+    let mut stopped = false;
+    loop {
+        let mut sending: Vec<(usize, PathBuf)> = carried.take().into_iter().collect();
+        let mut bound: HashSet<PathBuf> = sending.iter().map(|(_, r)| r.clone()).collect();
+        for (i, (local, remote)) in plan.by_ref() {
+            if before_file(i, &tx, &cancel_flag) {
+                skipped.push(format!("{}: {}", shown(local), quota::SKIPPED));
+                front.end(i);
+                continue;
+            }
+            if cancel_flag.stopping() {
+                stopped = true;
+                break;
+            }
+            // Handle conflict if the destination already exists
+            // A path's time is taken with its first decision; a later source
+            // for the same path finds none and leaves the earlier upload alone.
+            let newer = |p: &Path| source_is_newer(modified(local).map(Timestamp::of), dest_times.remove(p));
+            let remote = match resolve_remote_conflict(remote, &mut existing, conflict_mode, recheck, newer, |p| {
+                remote_path_exists(host, &ctl, p)
+            }) {
+                Some(r) => r.into_owned(),
+                None => {
+                    skipped.push(format!(
+                        "{}: {}",
+                        shown(local),
+                        kept_reason(conflict_mode)
+                    ));
+                    progress(i);
                     continue;
                 }
-                ConflictMode::Rename => {
-                    std::borrow::Cow::Owned(find_unique_remote_path_from_set(dst_remote, &existing))
-                }
-                ConflictMode::Overwrite => unreachable!(),
-            }
-        } else {
-            std::borrow::Cow::Borrowed(dst_remote.as_str())
-        };
-
-        // Create local temp parent dir
-        if let Some(parent) = local_temp.parent() {
-            if let Err(e) = fs::create_dir_all(parent) {
-                errors.push(format!("{}: temp dir error: {}", src_remote, e));
-                continue;
+            };
+            // Later entries mapping to the same path now see it as taken
+            existing.insert(remote.clone());
+            if bound.contains(&remote) {
+                carried = Some((i, remote));
+                break;
             }
+            bound.insert(remote.clone());
+            sending.push((i, remote));
         }
-
-        // Step 1: Download from source to local temp
-        let dl_result = Command::new("scp")
-            .args(&ctl)
-            .arg("-q")
-            .arg(format!("{}:{}", src_host, src_remote))
-            .arg(local_temp)
-            .status();
-        if !matches!(dl_result, Ok(s) if s.success()) {
-            errors.push(format!("{}: download from source failed", src_remote));
-            let _ = tx.send(WorkerMsg::Progress {
-                done: i + 1,
-                total: total_transfers,
-                file: src_remote.clone(),
-            });
-            continue;
+        if stopped || sending.is_empty() {
+            break;
         }
-
-        // Verify download
-        match verify_remote_hash(local_temp, src_host, &ctl, src_remote) {
-            Ok(true) => {}
-            Ok(false) => {
-                let _ = fs::remove_file(local_temp);
-                errors.push(format!(
-                    "{}: download integrity check failed — hash mismatch",
-                    src_remote
-                ));
-                let _ = tx.send(WorkerMsg::Progress {
-                    done: i + 1,
-                    total: total_transfers,
-                    file: src_remote.clone(),
-                });
-                continue;
-            }
-            Err(e) => {
-                let _ = fs::remove_file(local_temp);
-                errors.push(format!(
-                    "{}: download verification error: {}",
-                    src_remote, e
-                ));
-                let _ = tx.send(WorkerMsg::Progress {
-                    done: i + 1,
-                    total: total_transfers,
-                    file: src_remote.clone(),
-                });
-                continue;
-            }
+        // A copy still to be verified is checked before rsync replaces it
+        if sending.iter().any(|(_, remote)| batch.is_waiting(remote)) {
+            batch.verify(&mut stats, &cancel_flag, &tx);
         }
 
-        // Step 2: Upload from local temp to destination
-        let ul_result = Command::new("scp")
-            .args(&ctl)
-            .arg("-q")
-            .arg(local_temp)
-            .arg(format!("{}:{}", dst_host, dst_remote))
-            .status();
-        if !matches!(ul_result, Ok(s) if s.success()) {
-            let _ = fs::remove_file(local_temp);
-            errors.push(format!("{}: upload to destination failed", src_remote));
-            let _ = tx.send(WorkerMsg::Progress {
-                done: i + 1,
-                total: total_transfers,
-                file: src_remote.clone(),
-            });
-            continue;
+        // Transfer via rsync with checksum verification: one call for each
+        // folder the files share, one each for the rest (see `rsync::Batch`)
+        let mut calls = Vec::new();
+        let mut singles = Vec::new();
+        for (i, remote) in &sending {
+            if !rsync::add(&mut calls, *i, &transfers[*i].0, remote) {
+                singles.push((*i, remote.clone()));
+            }
         }
-
-        // Verify upload
-        match verify_remote_hash(local_temp, dst_host, &ctl, &dst_remote) {
-            Ok(true) => {
-                copied += 1;
-                // Clean up local temp
-                let _ = fs::remove_file(local_temp);
-                if do_move {
-                    let rm_result = Command::new("ssh")
-                        .args(&ctl)
-                        .arg(src_host)
-                        .arg(format!("rm -f {}", shell_quote(src_remote)))
-                        .status();
-                    if !matches!(rm_result, Ok(s) if s.success()) {
-                        errors.push(format!(
-                            "{}: transferred and verified but failed to delete from source",
-                            src_remote
-                        ));
+        let mut results: Vec<(usize, RsyncSent)> = Vec::new();
+        for call in &calls {
+            if stopped || stop_before_call(&cancel_flag) {
+                stopped = true;
+                break;
+            }
+            let mut dest = remote_arg(host, rsync_escape_remote(&call.to));
+            dest.push("/");
+            let (out, sent) = send_rsync_batch(
+                priority::command("rsync", opts.low_priority)
+                    .args(["-a", "--checksum"])
+                    .args(opts.throttle.rsync_args(true))
+                    .args(symlinks::rsync_args(opts.symlinks))
+                    .arg("-e")
+                    .arg(&ssh_cmd),
+                call,
+                conflict_mode,
+                dest,
+                progress,
+            );
+            let reason = failure_reason(&format!("rsync of {} files to {}:{}", call.files.len(), host, shown(&call.to)), &out);
+            for (i, tail) in &call.files {
+                let outcome = match &out {
+                    _ if sent.contains(i) => RsyncSent::Reported,
+                    Ok(o) if o.status.success() => RsyncSent::Quiet,
+                    _ if cancel_flag.stopping() => {
+                        stopped = true;
+                        continue;
                     }
-                }
+                    // A copy on the host can't be looked at cheaply, so one
+                    // the call says nothing about counts as failed too.
+                    Ok(o) => RsyncSent::Failed(format!(
+                        "rsync failed (exit code {}){}",
+                        o.status.code().unwrap_or(-1),
+                        call.error_about(&String::from_utf8_lossy(&o.stderr), tail).map_or(reason.clone(), |line| format!(": {}", line))
+                    )),
+                    Err(e) => RsyncSent::Failed(e.to_string()),
+                };
+                results.push((*i, outcome));
             }
-            Ok(false) => {
-                let _ = fs::remove_file(local_temp);
-                // Remove corrupt destination copy
-                let _ = Command::new("ssh")
-                    .args(&ctl)
-                    .arg(dst_host)
-                    .arg(format!("rm -f {}", shell_quote(&dst_remote)))
-                    .status();
-                errors.push(format!(
-                    "{}: upload integrity check failed — hash mismatch (source retained, dest copy removed)",
-                    src_remote
-                ));
+        }
+        for (i, remote) in &singles {
+            if stopped || stop_before_call(&cancel_flag) {
+                stopped = true;
+                break;
             }
-            Err(e) => {
-                let _ = fs::remove_file(local_temp);
-                if do_move {
-                    errors.push(format!(
-                        "{}: uploaded but verification failed: {} (source retained)",
-                        src_remote, e
-                    ));
-                } else {
-                    errors.push(format!(
-                        "{}: uploaded but could not verify: {}",
-                        src_remote, e
-                    ));
+            let outcome = match rsync_upload(&ssh_cmd, host, &transfers[*i].0, remote, conflict_mode, opts) {
+                Ok(()) => RsyncSent::Reported,
+                Err(_) if cancel_flag.stopping() => {
+                    stopped = true;
+                    break;
+                }
+                Err(why) => RsyncSent::Failed(why),
+            };
+            results.push((*i, outcome));
+        }
+        results.sort_by_key(|(i, _)| *i);
+
+        let remotes: HashMap<usize, PathBuf> = sending.into_iter().collect();
+        for (i, outcome) in results {
+            let local = &transfers[i].0;
+            match outcome {
+                // Not sent for the guard: what is there was written since
+                // the lookup, and stays.
+                RsyncSent::Quiet if !rsync_conflict_args(conflict_mode).is_empty() => {
+                    skipped.push(format!("{}: {}", shown(local), kept_reason(conflict_mode)));
+                }
+                // rsync --checksum already verifies integrity during transfer,
+                // but we perform an additional SHA-256 comparison to be safe,
+                // especially before deleting source files in move mode.
+                RsyncSent::Reported | RsyncSent::Quiet | RsyncSent::Unclear(_) => {
+                    batch.add(i, local.clone(), remotes[&i].clone());
+                }
+                RsyncSent::Failed(why) => {
+                    let remote = &remotes[&i];
+                    match retry::again(opts.retries, why, &cancel_flag, || rsync_upload(&ssh_cmd, host, local, remote, conflict_mode, opts)) {
+                        Ok(()) => batch.add(i, local.clone(), remote.clone()),
+                        Err(why) => batch.errors.push(format!("{}: {}", shown(local), why)),
+                    }
                 }
             }
+            progress(i);
+        }
+        if stopped {
+            break;
+        }
+        if batch.is_full() {
+            batch.verify(&mut stats, &cancel_flag, &tx);
+        }
+    }
+    if stopped {
+        batch.verify(&mut stats, &cancel_flag, &tx);
+        let UploadBatch { copied, mut errors, ro_kept, unverified, .. } = batch;
+        push_read_only_summary(&mut errors, ro_kept);
+        if defer {
+            let _ = tx.send(WorkerMsg::Deletion(Deletion::Skipped));
         }
-
-        let _ = tx.send(WorkerMsg::Progress {
-            done: i + 1,
-            total: total_transfers,
-            file: src_remote.clone(),
-        });
+        if !unverified.is_empty() {
+            let _ = tx.send(WorkerMsg::Unverified(unverified));
+        }
+        let _ = tx.send(WorkerMsg::Cancelled(TransferReport {
+            copied,
+            skipped,
+            excluded_files,
+            excluded_dirs,
+            failed: failed_sources(files.iter().cloned(), errors.shown(), None),
+            errors,
+            groups: Vec::new(),
+        }));
+        return;
+    }
+    batch.verify(&mut stats, &cancel_flag, &tx);
+    let UploadBatch { copied, mut errors, mut ro_kept, deferred, unverified, .. } = batch;
+
+    send_phase(&tx, Phase::Finalizing);
+    let cancelled = defer
+        && delete_deferred_sources(&deferred, unverified.len(), None, &[], &mut errors, &mut ro_kept, &cancel_flag, &tx);
+    push_read_only_summary(&mut errors, ro_kept);
+    if !cancelled {
+        tidy_moved_source(&source, do_move, opts.remove_source_root, &files, &errors);
+    }
+    let groups = stats.finish(skipped.shown(), errors.shown());
+    let failed = failed_sources(files.iter().cloned(), errors.shown(), None);
+    if !unverified.is_empty() {
+        let _ = tx.send(WorkerMsg::Unverified(unverified));
     }
+    let report = TransferReport { copied, skipped, excluded_files, excluded_dirs, errors, failed, groups };
+    let _ = tx.send(if cancelled { WorkerMsg::Cancelled(report) } else { WorkerMsg::Finished(report) });
+}
 
-    // Clean up temp directory
-    let _ = fs::remove_dir_all(&temp_dir);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let _ = tx.send(WorkerMsg::Finished {
-        copied,
-        skipped,
-        excluded_files,
-        excluded_dirs,
-        errors,
-    });
-}
+    /// Create an empty scratch directory unique to this test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kosmokopy_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
 
-// ── Worker thread (remote source → remote destination via rsync) ───────
+    fn collected_names(files: &[PathBuf]) -> HashSet<String> {
+        files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
 
-fn run_remote_to_remote_rsync_worker(
-    src_host: &str,
-    src_remote_base: &str,
-    dst_host: &str,
-    dst_remote_base: &str,
-    do_move: bool,
-    conflict_mode: ConflictMode,
-    strip_spaces: bool,
-    transfer_mode: TransferMode,
-    patterns: &[String],
-    cancel_flag: Arc<AtomicBool>,
-    tx: mpsc::Sender<WorkerMsg>,
-) {
-    let ctl = [
-        "-o", "ControlMaster=auto",
-        "-o", "ControlPath=/tmp/kosmokopy_ssh_%h_%p_%r",
-        "-o", "ControlPersist=60",
-    ];
-    let ssh_cmd = "ssh -o ControlMaster=auto -o ControlPath=/tmp/kosmokopy_ssh_%h_%p_%r -o ControlPersist=60";
+    #[test]
+    fn a_copy_is_written_as_a_partial_and_resumed_only_when_it_matches() {
+        let dir = scratch_dir("partial");
+        let (src, dst) = (dir.join("a.txt"), dir.join("b.txt"));
+        fs::write(&src, b"Hello, World!").unwrap();
+        fs::write(stale::partial_path(&dst), b"Hello, ").unwrap();
+        let mut reader = fs::File::open(&src).unwrap();
+        let (_, have) = resume_partial(&mut reader, &stale::partial_path(&dst), 13).unwrap();
+        assert_eq!(have, 7);
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "World!");
+
+        fs::write(stale::partial_path(&dst), b"Howdy, ").unwrap();
+        let mut reader = fs::File::open(&src).unwrap();
+        assert!(resume_partial(&mut reader, &stale::partial_path(&dst), 13).is_none());
+        let mut all = String::new();
+        reader.read_to_string(&mut all).unwrap();
+        assert_eq!(all, "Hello, World!");
+
+        assert_eq!(copy_file_chunked(&src, &dst, &RunOptions::default(), |_| {}).unwrap(), 13);
+        assert_eq!(fs::read(&dst).unwrap(), b"Hello, World!");
+        assert!(!stale::partial_path(&dst).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-    // Connectivity check to both hosts
-    for host in [src_host, dst_host] {
-        let check = Command::new("ssh")
-            .args(&ctl)
-            .args([host, "echo ok"])
-            .output();
-        match check {
-            Ok(o) if o.status.success() => {}
-            Ok(o) => {
-                let _ = tx.send(WorkerMsg::Error(format!(
-                    "SSH connection to '{}' failed: {}",
-                    host,
-                    String::from_utf8_lossy(&o.stderr).trim()
-                )));
-                return;
-            }
-            Err(e) => {
-                let _ = tx.send(WorkerMsg::Error(format!("Could not run ssh: {}", e)));
-                return;
-            }
+    #[test]
+    fn quick_filter_extensions_match_case_insensitively() {
+        let dir = scratch_dir("quick_filter");
+        for name in ["IMG_0001.JPG", "clip.Mov", "song.flac", "notes.md", "raw.CR2"] {
+            fs::write(dir.join(name), b"x").unwrap();
         }
+        let mut patterns = category_include_patterns("Photos");
+        patterns.extend(category_include_patterns("Videos"));
+
+        let (files, excluded_files, _) =
+            collect_files(&SourceSelection::Directory(dir.clone()), &patterns, symlinks::Policy::Skip, &mut ScanGuard::unlimited()).unwrap();
+        let names = collected_names(&files);
+        assert_eq!(
+            names,
+            ["IMG_0001.JPG", "clip.Mov", "raw.CR2"].iter().map(|s| s.to_string()).collect()
+        );
+        assert_eq!(excluded_files, 2);
+        let _ = fs::remove_dir_all(&dir);
     }
 
-    // Check rsync availability
-    match Command::new("rsync").arg("--version").output() {
-        Ok(o) if o.status.success() => {}
-        _ => {
-            let _ = tx.send(WorkerMsg::Error(
-                "rsync is not installed or not found in PATH".to_string(),
-            ));
-            return;
+    #[test]
+    fn result_notes_group_skips_by_outcome() {
+        let mut report = TransferReport::nothing_sent(Details::skipped(), 0, 0);
+        for entry in ["/in/a: symbolic link, skipped", "/in/b: identical at destination", "/in/c: destination is newer"] {
+            report.skipped.push(entry.to_string());
         }
+        report.errors.push("/in/d: copy failed: disk full".to_string());
+        assert_eq!(
+            result_notes(&report, &["/in/e".to_string()]),
+            [
+                "Identical at destination (1):",
+                "/in/b: identical at destination",
+                "Existing file kept (1):",
+                "/in/c: destination is newer",
+                "Skipped (1):",
+                "/in/a: symbolic link, skipped",
+                "Unverified (1):",
+                "/in/e",
+                "Errors (1):",
+                "/in/d: copy failed: disk full",
+            ]
+        );
     }
 
-    // List remote source files
-    let (remote_files, excluded_files, excluded_dirs) = match collect_remote_files(src_host, &ctl, src_remote_base, patterns) {
-        Ok(v) => v,
-        Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(e));
-            return;
+    #[test]
+    fn include_patterns_union_with_manual_pattern_and_respect_exclusions() {
+        let dir = scratch_dir("include_union");
+        for name in ["a.jpg", "b.jpg", "readme.md", "data.bin"] {
+            fs::write(dir.join(name), b"x").unwrap();
         }
-    };
+        let mut patterns = category_include_patterns("Photos");
+        patterns.push("+*.md".to_string());
+        patterns.push("b.jpg".to_string());
+
+        let (files, _, _) =
+            collect_files(&SourceSelection::Directory(dir.clone()), &patterns, symlinks::Policy::Skip, &mut ScanGuard::unlimited()).unwrap();
+        let names = collected_names(&files);
+        assert_eq!(names, ["a.jpg", "readme.md"].iter().map(|s| s.to_string()).collect());
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-    let total = remote_files.len();
-    if total == 0 {
-        let _ = tx.send(WorkerMsg::Finished {
-            copied: 0,
-            skipped: vec![],
-            excluded_files,
-            excluded_dirs,
-            errors: vec![],
-        });
-        return;
+    #[test]
+    fn owner_filters_apply_to_local_and_remote_listings() {
+        use std::os::unix::fs::MetadataExt;
+        let dir = scratch_dir("owner_filter");
+        fs::write(dir.join("mine.txt"), b"x").unwrap();
+        let uid = fs::metadata(dir.join("mine.txt")).unwrap().uid();
+        let entry = WalkDir::new(dir.join("mine.txt")).into_iter().next().unwrap().unwrap();
+        let only = |id| owners::OwnerFilter { only_users: vec![owners::Owner { id, name: None }], ..Default::default() };
+        let exclude = |id| owners::OwnerFilter { exclude_users: vec![owners::Owner { id, name: None }], ..Default::default() };
+        assert!(local_owner_kept(&only(uid), &entry));
+        assert!(!local_owner_kept(&only(uid + 1), &entry));
+        assert!(!local_owner_kept(&exclude(uid), &entry));
+        let _ = fs::remove_dir_all(&dir);
+
+        let listing = "12 1001:2000:ann:design\t/srv/projects/a/plan.pdf\0\
+                       7 1002:3000:bob:ops\t/srv/projects/b/notes.txt\0\
+                       9 1003:2000:cy:design\t/srv/projects/b/logo.svg\0\
+                       #owner 40\0\
+                       \t/srv/projects/c/unknown.bin\0";
+        let design = owners::OwnerFilter {
+            only_groups: vec![owners::Owner { id: 99, name: Some("design".to_string()) }],
+            ..Default::default()
+        };
+        let (kept, excluded, _, _, filtered) = parse_remote_listing(listing.as_bytes(), "/srv/projects", &["~*.svg".to_string()], &design);
+        let paths: Vec<&str> = kept.iter().map(|(p, _)| p.to_str().unwrap()).collect();
+        // Names decide on the remote side; a line without owner fields is kept.
+        assert_eq!(paths, ["/srv/projects/a/plan.pdf", "/srv/projects/c/unknown.bin"]);
+        assert_eq!(kept[0].1, Some(12));
+        assert_eq!((excluded, filtered), (1, 41));
+
+        let (all, _, _, _, none) = parse_remote_listing(listing.as_bytes(), "/srv/projects", &[], &owners::OwnerFilter::default());
+        assert_eq!((all.len(), none), (4, 40));
     }
 
-    let temp_dir = match tempdir_for_relay() {
-        Ok(d) => d,
-        Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(format!(
-                "Failed to create temp directory: {}", e
-            )));
-            return;
-        }
-    };
+    #[test]
+    fn hostile_remote_names_survive_the_listing() {
+        let listing = "3\t/srv/p/two\nlines.txt\0\
+                       4\t/srv/p/ padded \0\
+                       5\t/srv/p/$HOME `id` *.txt\0";
+        let (kept, ..) = parse_remote_listing(listing.as_bytes(), "/srv/p", &[], &owners::OwnerFilter::default());
+        let paths: Vec<&str> = kept.iter().map(|(p, _)| p.to_str().unwrap()).collect();
+        assert_eq!(paths, ["/srv/p/two\nlines.txt", "/srv/p/ padded ", "/srv/p/$HOME `id` *.txt"]);
+        assert_eq!(rsync_escape_remote("/a b\nc~<d>"), "/a\\ b'\n'c\\~\\<d\\>");
+        assert_eq!(rsync_escape_remote(OsStr::from_bytes(b"/x\xff y")).as_bytes(), b"/x\xff\\ y");
+    }
 
-    let src_base = src_remote_base.trim_end_matches('/');
-    let src_base_slash = format!("{}/", src_base);
-    let src_root_name = Path::new(src_base).file_name()
-        .map(|f| f.to_string_lossy().to_string())
-        .unwrap_or_default();
-    let dst_base = dst_remote_base.trim_end_matches('/');
+    #[test]
+    fn excluded_folders_count_once_by_path() {
+        let patterns = ["/cache".to_string(), "~/build*".to_string()];
+        // Two different folders named cache, one cache inside another,
+        // and a cache inside an excluded build folder.
+        let listing = "1\t/srv/p/a/cache/1.tmp\0\
+                       1\t/srv/p/a/cache/2.tmp\0\
+                       1\t/srv/p/b/cache/3.tmp\0\
+                       1\t/srv/p/cache/cache/4.tmp\0\
+                       1\t/srv/p/build-1/cache/5.o\0\
+                       1\t/srv/p/b/keep.txt\0\
+                       1\t/srv/p/a/cache.txt\0";
+        let (kept, files, dirs, hidden, _) =
+            parse_remote_listing(listing.as_bytes(), "/srv/p", &patterns, &owners::OwnerFilter::default());
+        assert_eq!(kept.len(), 2);
+        // a/cache, b/cache, cache and build-1, holding 5 files.
+        assert_eq!((files, dirs, hidden), (0, 4, 5));
+
+        // Locally the same folders are pruned unread, plus an empty one a
+        // listing of files never shows.
+        let dir = scratch_dir("excluded_dirs");
+        for file in ["a/cache/1.tmp", "a/cache/2.tmp", "b/cache/3.tmp", "cache/cache/4.tmp", "build-1/cache/5.o", "b/keep.txt", "a/cache.txt"] {
+            fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+            fs::write(dir.join(file), b"x").unwrap();
+        }
+        fs::create_dir_all(dir.join("c/cache")).unwrap();
+        let (kept, files, dirs) =
+            collect_files(&SourceSelection::Directory(dir.clone()), &patterns, symlinks::Policy::Skip, &mut ScanGuard::unlimited()).unwrap();
+        assert_eq!((kept.len(), files, dirs), (2, 0, 5));
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-    let mut transfers: Vec<(String, String, PathBuf)> = Vec::new();
-    let mut dst_remote_dirs: HashSet<String> = HashSet::new();
-    dst_remote_dirs.insert(dst_base.to_string());
+    #[test]
+    fn path_patterns_match_below_the_source() {
+        // `**` stands for any number of folders, none included.
+        for rel in ["node_modules", "web/node_modules", "a/b/node_modules/x/y.js"] {
+            assert!(path_pattern_matches("**/node_modules/**", rel), "{}", rel);
+        }
+        assert!(!path_pattern_matches("**/node_modules/**", "web/node_modules.txt"));
+        // Anchored at the source root, however it was written.
+        assert!(path_pattern_matches("docs/generated", "docs/generated"));
+        assert!(!path_pattern_matches("docs/generated", "site/docs/generated"));
+        assert!(!path_pattern_matches("docs/generated", "docs/generated/index.html"));
+        // `*` and `?` stay within one name, case-insensitively.
+        assert!(path_pattern_matches("logs/2024-0?/*.LOG", "logs/2024-03/app.log"));
+        assert!(!path_pattern_matches("logs/2024-0?/*.log", "logs/2024-11/app.log"));
+        assert!(!path_pattern_matches("logs/*.log", "logs/old/app.log"));
+    }
 
-    for remote_file in &remote_files {
-        // For single-file sources, strip_prefix fails because
-        // src_base_slash is "<file>/" which doesn't match. Use just
-        // the filename so local_temp stays inside temp_dir.
-        let is_single_file = remote_file.as_str() == src_base;
-        let rel = remote_file
-            .strip_prefix(&src_base_slash)
-            .unwrap_or_else(|| {
-                Path::new(remote_file.as_str())
-                    .file_name()
-                    .and_then(|f| f.to_str())
-                    .unwrap_or(remote_file.as_str())
-            });
+    #[test]
+    fn path_patterns_leave_out_one_folder_of_a_name() {
+        let patterns = ["^docs/generated".to_string(), "^**/node_modules/**".to_string(), "^notes/?.t*".to_string()];
+        let files = ["docs/generated/a.html", "docs/guide.md", "site/generated/b.html", "web/node_modules/x/y.js", "notes/1.txt", "notes/12.txt"];
+        let listing: String = files.iter().map(|f| format!("1\t/srv/p/{}\0", f)).collect();
+        let (kept, excluded, dirs, hidden, _) =
+            parse_remote_listing(listing.as_bytes(), "/srv/p", &patterns, &owners::OwnerFilter::default());
+        let kept: Vec<&Path> = kept.iter().map(|(p, _)| p.strip_prefix("/srv/p").unwrap()).collect();
+        assert_eq!(kept, ["docs/guide.md", "site/generated/b.html", "notes/12.txt"]);
+        // docs/generated and web/node_modules, holding 2 files.
+        assert_eq!((excluded, dirs, hidden), (1, 2, 2));
+
+        let dir = scratch_dir("path_patterns");
+        for file in files {
+            fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+            fs::write(dir.join(file), b"x").unwrap();
+        }
+        let (kept, excluded, dirs) =
+            collect_files(&SourceSelection::Directory(dir.clone()), &patterns, symlinks::Policy::Skip, &mut ScanGuard::unlimited()).unwrap();
+        let mut kept: Vec<String> = kept.iter().map(|p| p.strip_prefix(&dir).unwrap().display().to_string()).collect();
+        kept.sort();
+        assert_eq!(kept, ["docs/guide.md", "notes/12.txt", "site/generated/b.html"]);
+        assert_eq!((excluded, dirs), (1, 2));
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-        let dst_rel = match transfer_mode {
-            TransferMode::FoldersAndFiles => {
-                if src_root_name.is_empty() || is_single_file { rel.to_string() }
-                else { format!("{}/{}", src_root_name, rel) }
-            }
-            TransferMode::FilesOnly => {
-                Path::new(rel)
-                    .file_name()
-                    .map(|f| f.to_string_lossy().to_string())
-                    .unwrap_or_else(|| rel.to_string())
-            }
-        };
+    #[test]
+    fn unknown_category_has_no_patterns() {
+        assert!(category_include_patterns("Spreadsheets").is_empty());
+        assert!(category_include_patterns("Music").contains(&"+*.flac".to_string()));
+    }
 
-        let dst_remote = format!("{}/{}", dst_base, dst_rel);
-        let dst_remote = if strip_spaces {
-            dst_remote.split('/').map(|c| c.replace(' ', "")).collect::<Vec<_>>().join("/")
-        } else {
-            dst_remote
-        };
+    #[test]
+    fn failed_sources_match_error_prefixes() {
+        let sources = vec![PathBuf::from("/data/a: b.txt"), PathBuf::from("/data/c.txt"), PathBuf::from("/data/ok.txt")];
+        let errors = vec![
+            "/data/a: b.txt: Permission denied".to_string(),
+            "/data/c.txt: copy failed: No space left on device".to_string(),
+            "/data/c.txt: verification failed".to_string(),
+            "Could not create /dst: Read-only file system".to_string(),
+        ];
+        assert_eq!(
+            failed_sources(sources.clone().into_iter(), &errors, None),
+            vec![OsString::from("/data/a: b.txt"), OsString::from("/data/c.txt")]
+        );
+        assert_eq!(
+            failed_sources(sources.into_iter(), &errors[1..2], Some("nas")),
+            vec![OsString::from("nas:/data/c.txt")]
+        );
+    }
 
-        if let Some(parent) = Path::new(&dst_remote).parent() {
-            dst_remote_dirs.insert(parent.to_string_lossy().to_string());
+    #[test]
+    fn failed_list_round_trips_to_selection() {
+        let dir = scratch_dir("failed_list");
+        let failed = vec![OsString::from("/data/one.txt"), OsString::from("/data/with space/two.txt"), OsString::from_vec(b"/data/caf\xe9.txt".to_vec())];
+        for nul in [false, true] {
+            let list = dir.join(format!("failed_{}.{}", nul, FAILED_LIST_EXT));
+            write_failed_list(&list, &failed, nul).unwrap();
+            assert!(looks_like_failed_list(&list));
+            let sel = selection_from_list(read_failed_list(&list).unwrap()).unwrap();
+            assert_eq!(
+                sel,
+                SourceSelection::Files(failed.iter().map(PathBuf::from).collect())
+            );
         }
 
-        let local_temp = temp_dir.join(rel);
-        transfers.push((remote_file.clone(), dst_remote, local_temp));
+        // Without the extension, a list is still recognised by its content.
+        let plain = dir.join("failed.txt");
+        write_failed_list(&plain, &failed, false).unwrap();
+        assert!(looks_like_failed_list(&plain));
+        fs::write(dir.join("notes.txt"), "just some notes\n").unwrap();
+        assert!(!looks_like_failed_list(&dir.join("notes.txt")));
+        let _ = fs::remove_dir_all(&dir);
     }
 
-    // Create destination remote directories
-    let dirs_arg: Vec<String> = dst_remote_dirs.iter().map(|d| shell_quote(d)).collect();
-    let mkdir_result = Command::new("ssh")
-        .args(&ctl)
-        .arg(dst_host)
-        .arg(format!("mkdir -p {}", dirs_arg.join(" ")))
-        .output();
-    if let Ok(o) = &mkdir_result {
-        if !o.status.success() {
-            let _ = tx.send(WorkerMsg::Error(format!(
-                "Failed to create remote directories on destination: {}",
-                String::from_utf8_lossy(&o.stderr).trim()
-            )));
-            let _ = fs::remove_dir_all(&temp_dir);
-            return;
+    #[test]
+    fn remote_failed_list_becomes_remote_files() {
+        let entries = vec![OsString::from("nas:/srv/a.bin"), OsString::from("nas:/srv/b c.bin")];
+        let sel = selection_from_list(entries).unwrap();
+        assert_eq!(
+            sel,
+            SourceSelection::RemoteFiles(
+                "nas".to_string(),
+                vec![PathBuf::from("/srv/a.bin"), PathBuf::from("/srv/b c.bin")]
+            )
+        );
+        assert_eq!(selection_label(&sel), "2 files selected on nas");
+
+        assert!(selection_from_list(vec!["nas:/a".into(), "/b".into()]).is_err());
+        assert!(selection_from_list(vec!["nas:/a".into(), "box:/b".into()]).is_err());
+
+        // The path keeps bytes that aren't UTF-8, in either form of address.
+        for entry in [&b"nas:/srv/caf\xe9.bin"[..], b"ssh://nas:2222/srv/caf\xe9.bin"] {
+            let (host, path) = parse_list_entry(OsStr::from_bytes(entry));
+            assert!(host.is_some());
+            assert_eq!(path.as_os_str().as_bytes(), b"/srv/caf\xe9.bin");
         }
+        assert!(selection_from_list(vec![]).is_err());
     }
 
-    let existing: HashSet<String> = if conflict_mode != ConflictMode::Overwrite {
-        let out = Command::new("ssh")
-            .args(&ctl)
-            .arg(dst_host)
-            .arg(format!("find {} -type f 2>/dev/null", shell_quote(dst_base)))
-            .output();
-        match out {
-            Ok(o) => String::from_utf8_lossy(&o.stdout)
-                .lines()
-                .map(|l| l.to_string())
-                .collect(),
-            Err(_) => HashSet::new(),
-        }
-    } else {
-        HashSet::new()
-    };
+    #[test]
+    fn a_retry_keeps_the_first_runs_folder_and_destination() {
+        let root = PathBuf::from("/data/photos");
+        let failed = vec![OsString::from("/data/photos/2024/a.jpg")];
+        assert_eq!(
+            retry_run(&SourceSelection::Directory(root.clone()), "/backup", &failed),
+            Some((SourceSelection::DirectoryItems(root, vec![PathBuf::from("/data/photos/2024/a.jpg")]), "/backup".to_string()))
+        );
+        assert_eq!(
+            retry_run(&SourceSelection::Remote("nas".to_string(), "/srv".to_string()), "/backup", &["nas:/srv/x/b.bin".into()]),
+            Some((
+                SourceSelection::RemoteItems("nas".to_string(), "/srv".to_string(), vec![PathBuf::from("/srv/x/b.bin")]),
+                "/backup".to_string()
+            ))
+        );
+        assert_eq!(retry_run(&SourceSelection::Directory(PathBuf::from("/data")), "/backup", &[]), None);
+
+        // One failed file of two still goes into the destination folder,
+        // even when the first run is what would have created it.
+        let dir = std::env::temp_dir().join(format!("kosmokopy_retry_run_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let two = SourceSelection::Files(vec![dir.join("a.txt"), dir.join("b.txt")]);
+        let dst = dir.join("out").to_string_lossy().to_string();
+        let (retry, retry_dst) = retry_run(&two, &dst, &[dir.join("b.txt").into_os_string()]).unwrap();
+        assert_eq!(retry, SourceSelection::Files(vec![dir.join("b.txt")]));
+        assert_eq!(retry_dst, format!("{}/", dst));
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-    let total_transfers = transfers.len();
-    let mut copied = 0usize;
-    let mut skipped: Vec<String> = Vec::new();
-    let mut errors: Vec<String> = Vec::new();
+    #[test]
+    fn dropped_uris_become_paths() {
+        let text = "# dragged from Files\r\nfile:///home/ann/My%20Photos\r\nfile://localhost/srv/caf%C3%A9.txt\r\n\
+                    file://nas/share/x\r\nsftp://nas/srv/y\r\n/tmp/plain path\r\nfile:///tmp/100%25%2\r\n";
+        assert_eq!(
+            paths_from_uri_list(text),
+            [
+                PathBuf::from("/home/ann/My Photos"),
+                PathBuf::from("/srv/café.txt"),
+                PathBuf::from("/tmp/plain path"),
+                PathBuf::from("/tmp/100%%2"),
+            ]
+        );
+        // Escapes that aren't UTF-8 keep their bytes.
+        assert_eq!(paths_from_uri_list("file:///a%FFb"), [PathBuf::from(std::ffi::OsString::from_vec(b"/a\xFFb".to_vec()))]);
+    }
 
-    for (i, (src_remote, dst_remote, local_temp)) in transfers.iter().enumerate() {
-        if cancel_flag.load(Ordering::SeqCst) {
-            let _ = tx.send(WorkerMsg::Cancelled {
-                copied,
-                skipped,
-                excluded_files,
-                excluded_dirs,
-                errors,
-            });
-            return;
-        }
-        let dst_remote = if conflict_mode != ConflictMode::Overwrite && existing.contains(dst_remote) {
-            match conflict_mode {
-                ConflictMode::Skip => {
-                    skipped.push(format!("{}: already exists at destination", src_remote));
-                    let _ = tx.send(WorkerMsg::Progress {
-                        done: i + 1,
-                        total: total_transfers,
-                        file: src_remote.clone(),
-                    });
-                    continue;
-                }
-                ConflictMode::Rename => {
-                    std::borrow::Cow::Owned(find_unique_remote_path_from_set(dst_remote, &existing))
-                }
-                ConflictMode::Overwrite => unreachable!(),
-            }
-        } else {
-            std::borrow::Cow::Borrowed(dst_remote.as_str())
-        };
+    #[test]
+    fn drops_take_one_folder_or_files() {
+        let dir = scratch_dir("drop");
+        let (folder, a, b) = (dir.join("album"), dir.join("a.jpg"), dir.join("b.jpg"));
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+        assert_eq!(selection_from_drop(vec![folder.clone()]), Ok(SourceSelection::Directory(folder.clone())));
+        assert_eq!(selection_from_drop(vec![a.clone()]), Ok(SourceSelection::Files(vec![a.clone()])));
+        assert_eq!(
+            selection_from_drop(vec![a.clone(), b.clone()]),
+            Ok(SourceSelection::Files(vec![a.clone(), b.clone()]))
+        );
+        let mixed = selection_from_drop(vec![a, folder.clone()]).unwrap_err();
+        assert!(mixed.ends_with(&format!("{} is a folder", folder.display())), "{}", mixed);
+        assert!(selection_from_drop(Vec::new()).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-        if let Some(parent) = local_temp.parent() {
-            if let Err(e) = fs::create_dir_all(parent) {
-                errors.push(format!("{}: temp dir error: {}", src_remote, e));
-                continue;
-            }
+    /// Single-quoted arguments of a script built with `shell_quote`.
+    fn quoted_args(script: &str) -> Vec<String> {
+        let unescaped = script.replace("'\\''", "\u{0}");
+        unescaped
+            .split('\'')
+            .skip(1)
+            .step_by(2)
+            .map(|a| a.replace('\u{0}', "'"))
+            .collect()
+    }
+
+    /// Answer lookup scripts against a fixture destination tree.
+    fn mock_remote<'a>(
+        tree: &'a HashSet<PathBuf>,
+        calls: &'a mut Vec<String>,
+    ) -> impl FnMut(&OsStr) -> Option<Vec<u8>> + 'a {
+        move |script| {
+            let script = script.to_str().unwrap();
+            calls.push(script.to_string());
+            let args = quoted_args(script);
+            let hits: Vec<&PathBuf> = if script.starts_with("for f in") || script.contains("-maxdepth 0") {
+                tree.iter().filter(|f| args.iter().any(|a| Path::new(a) == f.as_path())).collect()
+            } else if script.starts_with("find") {
+                tree.iter().filter(|f| args.iter().any(|d| Path::new(d) == f.parent().unwrap())).collect()
+            } else {
+                panic!("unexpected script: {}", script)
+            };
+            // Every file was last modified at the same time.
+            let time = if script.contains("%T@") { "\t1700000000.5" } else { "" };
+            Some(hits.iter().flat_map(|h| format!("{}{}\0", h.display(), time).into_bytes()).collect())
         }
+    }
 
-        // Download from source via rsync
-        let dl_result = Command::new("rsync")
-            .args(["-az", "--checksum"])
-            .arg("-e")
-            .arg(ssh_cmd)
-            .arg(format!("{}:{}", src_host, rsync_escape_remote(src_remote)))
-            .arg(local_temp)
-            .status();
-        if !matches!(dl_result, Ok(s) if s.success()) {
-            errors.push(format!("{}: rsync download from source failed", src_remote));
-            let _ = tx.send(WorkerMsg::Progress {
-                done: i + 1,
-                total: total_transfers,
-                file: src_remote.clone(),
-            });
-            continue;
+    fn path_set(paths: &[&str]) -> HashSet<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    /// A large destination archive, with the plan touching one corner of it.
+    fn conflict_fixture(plan_len: usize) -> (HashSet<PathBuf>, Vec<PathBuf>) {
+        let mut tree: HashSet<PathBuf> = (0..2000)
+            .map(|i| PathBuf::from(format!("/archive/year{}/photo{}.jpg", i % 20, i)))
+            .collect();
+        for name in ["a.txt", "a_1.txt", "it's here.txt", "b.txt"] {
+            tree.insert(Path::new("/archive/incoming").join(name));
+        }
+        tree.insert(PathBuf::from("/archive/incoming/deep/c.txt"));
+        let mut plan: Vec<PathBuf> = ["a.txt", "it's here.txt", "new.txt", "deep/c.txt", "deep/d.txt"]
+            .iter()
+            .map(|n| Path::new("/archive/incoming").join(n))
+            .collect();
+        let mut i = 0;
+        while plan.len() < plan_len {
+            plan.push(PathBuf::from(format!("/archive/incoming/bulk/f{}.bin", i)));
+            i += 1;
         }
+        (tree, plan)
+    }
 
-        // Verify download
-        match verify_remote_hash(local_temp, src_host, &ctl, src_remote) {
-            Ok(true) => {}
-            Ok(false) => {
-                let _ = fs::remove_file(local_temp);
-                errors.push(format!(
-                    "{}: download integrity check failed — hash mismatch",
-                    src_remote
-                ));
-                let _ = tx.send(WorkerMsg::Progress {
-                    done: i + 1,
-                    total: total_transfers,
-                    file: src_remote.clone(),
-                });
-                continue;
-            }
-            Err(e) => {
-                let _ = fs::remove_file(local_temp);
-                errors.push(format!(
-                    "{}: download verification error: {}",
-                    src_remote, e
-                ));
-                let _ = tx.send(WorkerMsg::Progress {
-                    done: i + 1,
-                    total: total_transfers,
-                    file: src_remote.clone(),
-                });
-                continue;
+    fn assert_same_decisions(
+        tree: &HashSet<PathBuf>,
+        plan: &[PathBuf],
+        scoped: &HashSet<PathBuf>,
+        mode: ConflictMode,
+    ) {
+        for target in plan {
+            assert_eq!(tree.contains(target), scoped.contains(target), "{}", target.display());
+            if mode == ConflictMode::Rename && tree.contains(target) {
+                assert_eq!(
+                    destpath::unique_remote(target, tree),
+                    destpath::unique_remote(target, scoped),
+                    "{}",
+                    target.display()
+                );
             }
         }
+    }
 
-        // Upload to destination via rsync
-        let ul_result = Command::new("rsync")
-            .args(["-az", "--checksum"])
-            .arg("-e")
-            .arg(ssh_cmd)
-            .arg(local_temp)
-            .arg(format!("{}:{}", dst_host, rsync_escape_remote(&dst_remote)))
-            .status();
-        if !matches!(ul_result, Ok(s) if s.success()) {
-            let _ = fs::remove_file(local_temp);
-            errors.push(format!("{}: rsync upload to destination failed", src_remote));
-            let _ = tx.send(WorkerMsg::Progress {
-                done: i + 1,
-                total: total_transfers,
-                file: src_remote.clone(),
-            });
-            continue;
+    #[test]
+    fn small_plan_probes_targets() {
+        let (tree, plan) = conflict_fixture(5);
+        let targets: Vec<&Path> = plan.iter().map(PathBuf::as_path).collect();
+        for mode in [ConflictMode::Skip, ConflictMode::Rename, ConflictMode::Newer] {
+            let mut calls = Vec::new();
+            let (scoped, _, lookup) =
+                existing_remote_files(&targets, mode, mock_remote(&tree, &mut calls));
+            assert_eq!(lookup, ExistingLookup::Probe);
+            assert_same_decisions(&tree, &plan, &scoped, mode);
+            // Nothing outside the touched directories is fetched.
+            assert!(scoped.iter().all(|f| f.starts_with("/archive/incoming/")));
+            let expected_calls = if mode == ConflictMode::Rename { 2 } else { 1 };
+            assert_eq!(calls.len(), expected_calls);
         }
+        assert_eq!(
+            destpath::unique_remote(Path::new("/archive/incoming/a.txt"), &tree),
+            Path::new("/archive/incoming/a_2.txt")
+        );
+    }
 
-        // Verify upload
-        match verify_remote_hash(local_temp, dst_host, &ctl, &dst_remote) {
-            Ok(true) => {
-                copied += 1;
-                let _ = fs::remove_file(local_temp);
-                if do_move {
-                    let rm_result = Command::new("ssh")
-                        .args(&ctl)
-                        .arg(src_host)
-                        .arg(format!("rm -f {}", shell_quote(src_remote)))
-                        .status();
-                    if !matches!(rm_result, Ok(s) if s.success()) {
-                        errors.push(format!(
-                            "{}: transferred and verified but failed to delete from source",
-                            src_remote
-                        ));
-                    }
-                }
-            }
-            Ok(false) => {
-                let _ = fs::remove_file(local_temp);
-                let _ = Command::new("ssh")
-                    .args(&ctl)
-                    .arg(dst_host)
-                    .arg(format!("rm -f {}", shell_quote(&dst_remote)))
-                    .status();
-                errors.push(format!(
-                    "{}: upload integrity check failed — hash mismatch (source retained, dest copy removed)",
-                    src_remote
-                ));
-            }
-            Err(e) => {
-                let _ = fs::remove_file(local_temp);
-                if do_move {
-                    errors.push(format!(
-                        "{}: uploaded but verification failed: {} (source retained)",
-                        src_remote, e
-                    ));
-                } else {
-                    errors.push(format!(
-                        "{}: uploaded but could not verify: {}",
-                        src_remote, e
-                    ));
-                }
-            }
+    #[test]
+    fn large_plan_lists_target_directories() {
+        let (tree, plan) = conflict_fixture(PROBE_MAX_TARGETS + 50);
+        let targets: Vec<&Path> = plan.iter().map(PathBuf::as_path).collect();
+        for mode in [ConflictMode::Skip, ConflictMode::Rename, ConflictMode::Newer] {
+            let mut calls = Vec::new();
+            let (scoped, _, lookup) =
+                existing_remote_files(&targets, mode, mock_remote(&tree, &mut calls));
+            assert_eq!(lookup, ExistingLookup::ListDirs);
+            assert_same_decisions(&tree, &plan, &scoped, mode);
+            assert_eq!(calls.len(), 1);
+            assert!(calls[0].contains("-maxdepth 1"));
+            assert!(scoped.iter().all(|f| f.starts_with("/archive/incoming/")));
         }
+    }
 
-        let _ = tx.send(WorkerMsg::Progress {
-            done: i + 1,
-            total: total_transfers,
-            file: src_remote.clone(),
-        });
+    #[test]
+    fn writable_source_is_not_read_only() {
+        let dir = scratch_dir("ro_probe");
+        fs::write(dir.join("a.txt"), b"x").unwrap();
+        assert!(!dir_is_read_only(&dir));
+        // The probe file is cleaned up again.
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let mut ro_kept = 0;
+        remove_moved_source(&dir.join("a.txt"), &mut ro_kept).unwrap();
+        assert!(!dir.join("a.txt").exists());
+        assert!(remove_moved_source(&dir.join("a.txt"), &mut ro_kept).is_err());
+        assert_eq!(ro_kept, 0);
+        let _ = fs::remove_dir_all(&dir);
     }
 
-    let _ = fs::remove_dir_all(&temp_dir);
+    #[test]
+    fn a_single_file_destination_names_the_file_unless_it_is_a_folder() {
+        let dir = scratch_dir("file_target");
+        let one = SourceSelection::Files(vec![PathBuf::from("/src/a.conf")]);
+        let at = |rel: &str| format!("{}/{}", dir.display(), rel);
+        fs::create_dir(dir.join("existing")).unwrap();
+        fs::write(dir.join("a.conf.old"), "old").unwrap();
+
+        // Existing folder, with or without a slash: into it.
+        assert_eq!(file_target(&one, &at("existing")), None);
+        assert_eq!(file_target(&one, &at("existing/")), None);
+        // Missing, with a slash: a new folder.
+        assert_eq!(file_target(&one, &at("missing/")), None);
+        // Missing, without a slash: the file itself, as is an existing file.
+        assert_eq!(file_target(&one, &at("a.conf.new")), Some((dir.display().to_string(), "a.conf.new".to_string())));
+        assert_eq!(file_target(&one, &at("a.conf.old")), Some((dir.display().to_string(), "a.conf.old".to_string())));
+
+        // A missing parent is created as folders, as before.
+        assert_eq!(file_target(&one, &at("missing/deeper")), None);
+        let two = SourceSelection::Files(vec![PathBuf::from("/src/a"), PathBuf::from("/src/b")]);
+        assert_eq!(file_target(&two, &at("a.conf.new")), None);
+        assert_eq!(file_target(&SourceSelection::Directory(PathBuf::from("/src")), &at("new")), None);
+        assert_eq!(file_target(&one, "nas:/etc/app/a.conf.new"), None);
+
+        // An existing file is only a destination for a single file.
+        let folder = SourceSelection::Directory(PathBuf::from("/src"));
+        let refused = Some(dest_is_file_error(&at("a.conf.old")));
+        assert_eq!(local_destination_error(&one, &at("a.conf.old")), None);
+        assert_eq!(local_destination_error(&one, &at("a.conf.old/")), refused);
+        assert_eq!(local_destination_error(&two, &at("a.conf.old")), refused);
+        assert_eq!(local_destination_error(&folder, &at("a.conf.old")), refused);
+        assert_eq!(local_destination_error(&folder, &at("existing")), None);
+        assert_eq!(local_destination_error(&folder, &at("missing")), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-    let _ = tx.send(WorkerMsg::Finished {
-        copied,
-        skipped,
-        excluded_files,
-        excluded_dirs,
-        errors,
-    });
-}
+    #[test]
+    fn read_only_deletions_collapse_to_one_line() {
+        let mut errors = Details::errors();
+        errors.push("/a: copy failed".to_string());
+        push_read_only_summary(&mut errors, 0);
+        assert_eq!(errors.len(), 1);
+        push_read_only_summary(&mut errors, 42);
+        assert_eq!(errors.len(), 2);
+        assert!(errors.shown()[1].contains("42 file(s)"));
+        // The summary names no source, so it never lands in a failed-files list.
+        let failed = failed_sources(vec![PathBuf::from("/a")].into_iter(), errors.shown(), None);
+        assert_eq!(failed, vec![OsString::from("/a")]);
+    }
+
+    #[test]
+    fn symlinked_destination_is_inside_the_source() {
+        let src_dir = scratch_dir("overlap_src");
+        let dst_dir = scratch_dir("overlap_dst");
+        fs::write(src_dir.join("a.txt"), "a").unwrap();
+        let link = dst_dir.join("link");
+        std::os::unix::fs::symlink(&src_dir, &link).unwrap();
+
+        assert!(is_within(&link.join("not/yet/created"), &src_dir));
+        assert!(!is_within(&dst_dir, &src_dir));
+        assert!(same_file(&src_dir.join("a.txt"), &link.join("a.txt")));
+        let warning = overlap_warning(&SourceSelection::Directory(src_dir.clone()), &link.to_string_lossy());
+        assert!(warning.unwrap().contains("inside the source folder"));
+        assert_eq!(overlap_warning(&SourceSelection::Directory(src_dir.clone()), &dst_dir.to_string_lossy()), None);
+
+        fs::hard_link(src_dir.join("a.txt"), dst_dir.join("b.txt")).unwrap();
+        assert!(same_file(&src_dir.join("a.txt"), &dst_dir.join("b.txt")));
+        assert!(!same_file(&src_dir.join("a.txt"), &dst_dir.join("missing.txt")));
+        let _ = fs::remove_dir_all(&src_dir);
+        let _ = fs::remove_dir_all(&dst_dir);
+    }
+
+    #[test]
+    fn the_same_folder_is_refused_however_it_is_typed() {
+        let src = scratch_dir("same_folder");
+        let other = scratch_dir("same_folder_other");
+        let link = other.join("link");
+        std::os::unix::fs::symlink(&src, &link).unwrap();
+        let source = SourceSelection::Directory(src.clone());
+        let refused = |dst: &str, case_insensitive: bool| source_overlap_error(&source, dst, case_insensitive);
+
+        let typed = src.display().to_string();
+        let error = refused(&format!("{}/", typed), false).unwrap();
+        assert_eq!(
+            error,
+            format!("Source {0} and destination {0}/ are the same folder, {0}; choose a different destination", typed)
+        );
+        let up = "../".repeat(std::env::current_dir().unwrap().components().count());
+        assert!(refused(&format!("{}{}", up, typed.trim_start_matches('/')), false).is_some());
+        assert!(refused(&link.display().to_string(), false).is_some());
+        assert!(refused(&format!("{}/./", typed), false).is_some());
+
+        let shouted = src.with_file_name(src.file_name().unwrap().to_string_lossy().to_uppercase());
+        assert_eq!(refused(&shouted.display().to_string(), false), None);
+        assert!(refused(&shouted.display().to_string(), true).is_some());
+        assert_eq!(refused(&other.display().to_string(), true), None);
+        assert_eq!(refused(&format!("nas:{}", typed), false), None);
+
+        let inside = refused(&format!("{}/backup/", typed), false).unwrap();
+        assert_eq!(
+            inside,
+            format!(
+                "Destination {0}/backup/ is inside the source folder {0}, so the run would copy its own copies; choose a destination outside it",
+                typed
+            )
+        );
+        assert!(refused(&link.join("backup").display().to_string(), false).is_some());
+        assert_eq!(refused(&format!("{}-backup", typed), false), None);
+        let _ = fs::remove_dir_all(&src);
+        let _ = fs::remove_dir_all(&other);
+    }
+
+    #[test]
+    fn long_paths_in_activity_text_are_shortened() {
+        let long = format!("/srv/{}.iso", "ü".repeat(300));
+        let shown = shorten_paths(&format!("scp {} nas:/backup/", long));
+        assert!(shown.starts_with("scp /srv/"));
+        assert!(shown.ends_with(".iso nas:/backup/"));
+        assert_eq!(shown.split(' ').nth(1).unwrap().chars().count(), MAX_NAME_LEN);
+        assert_eq!(shorten_paths("scanning /a/b"), "scanning /a/b");
+    }
+
+    #[test]
+    fn remote_destination_landing_on_the_source() {
+        assert!(lands_on_source("/srv/photos", "/srv/photos/", TransferMode::FilesOnly));
+        assert!(lands_on_source("/srv/photos", "/srv", TransferMode::FoldersAndFiles));
+        assert!(!lands_on_source("/srv/photos", "/srv", TransferMode::FilesOnly));
+        assert!(!lands_on_source("/srv/photos", "/backup", TransferMode::FoldersAndFiles));
+        assert!(lies_below("/srv/photos/backup/", "/srv/photos"));
+        assert!(lies_below("/srv", "/"));
+        assert!(!lies_below("/srv/photos-backup", "/srv/photos"));
+        assert!(!lies_below("/srv/photos", "/srv/photos/"));
+    }
+
+    #[test]
+    fn move_renames_on_same_device() {
+        let src_dir = scratch_dir("move_src_rename");
+        let dst_dir = scratch_dir("move_dst_rename");
+        assert_eq!(same_device(&src_dir, &dst_dir.join("not/yet/created")), Some(true));
+        fs::write(src_dir.join("a.bin"), vec![7u8; 4096]).unwrap();
+
+        let mut ro_kept = 0;
+        let mut progress = Vec::new();
+        let method = move_file(&src_dir.join("a.bin"), &dst_dir.join("a.bin"), true, &mut ro_kept, &RunOptions::default(), |b| progress.push(b), || true)
+            .unwrap();
+        assert_eq!(method, MoveMethod::Renamed);
+        assert!(progress.is_empty());
+        assert!(!src_dir.join("a.bin").exists());
+        assert_eq!(fs::read(dst_dir.join("a.bin")).unwrap().len(), 4096);
+        let _ = fs::remove_dir_all(&src_dir);
+        let _ = fs::remove_dir_all(&dst_dir);
+    }
+
+    #[test]
+    fn move_copies_when_devices_differ() {
+        // Two tempdirs share a device, so inject the cross-device verdict.
+        let src_dir = scratch_dir("move_src_copy");
+        let dst_dir = scratch_dir("move_dst_copy");
+        fs::write(src_dir.join("a.bin"), vec![7u8; 4096]).unwrap();
+        fs::write(src_dir.join("b.bin"), b"tiny").unwrap();
+
+        let mut stats = MoveStats::default();
+        let mut ro_kept = 0;
+        for name in ["a.bin", "b.bin"] {
+            let method =
+                move_file(&src_dir.join(name), &dst_dir.join(name), false, &mut ro_kept, &RunOptions::default(), |_| {}, || true).unwrap();
+            stats.record(method);
+            assert!(!src_dir.join(name).exists());
+        }
+        assert_eq!(fs::read(dst_dir.join("b.bin")).unwrap(), b"tiny");
+        assert_eq!(
+            stats,
+            MoveStats { renamed: 0, copied: 2, copied_bytes: 4100, identical: 0 }
+        );
+        stats.record(MoveMethod::Renamed);
+        stats.identical = 1;
+        assert_eq!(
+            stats.describe(),
+            "1 renamed, 2 copied across devices (4.0 KiB), 1 already at destination"
+        );
+        let _ = fs::remove_dir_all(&src_dir);
+        let _ = fs::remove_dir_all(&dst_dir);
+    }
+
+    #[test]
+    fn move_keeps_the_source_when_verification_is_skipped() {
+        let src_dir = scratch_dir("move_src_unverified");
+        let dst_dir = scratch_dir("move_dst_unverified");
+        fs::write(src_dir.join("a.bin"), vec![7u8; 4096]).unwrap();
+
+        let mut ro_kept = 0;
+        let method =
+            move_file(&src_dir.join("a.bin"), &dst_dir.join("a.bin"), false, &mut ro_kept, &RunOptions::default(), |_| {}, || false).unwrap();
+        assert_eq!(method, MoveMethod::Unverified);
+        assert!(src_dir.join("a.bin").exists());
+        assert_eq!(fs::read(dst_dir.join("a.bin")).unwrap().len(), 4096);
+        let mut stats = MoveStats::default();
+        stats.record(method);
+        assert_eq!(stats, MoveStats::default());
+        let _ = fs::remove_dir_all(&src_dir);
+        let _ = fs::remove_dir_all(&dst_dir);
+    }
+
+    /// Destinations chosen for each plan entry, mirroring the worker loop:
+    /// every upload is added to the live set.
+    fn plan_decisions(tree: &HashSet<PathBuf>, plan: &[&str], mode: ConflictMode) -> Vec<Option<PathBuf>> {
+        let plan: Vec<&Path> = plan.iter().map(Path::new).collect();
+        let mut calls = Vec::new();
+        let (mut existing, _, _) = if mode == ConflictMode::Overwrite {
+            (HashSet::new(), RemoteTimes::new(), ExistingLookup::Probe)
+        } else {
+            existing_remote_files(&plan, mode, mock_remote(tree, &mut calls))
+        };
+        plan.iter()
+            .map(|t| {
+                let dest = resolve_remote_conflict(t, &mut existing, mode, Recheck::Nothing, |_| false, |_| false).map(|d| d.into_owned());
+                if let Some(d) = &dest {
+                    existing.insert(d.clone());
+                }
+                dest
+            })
+            .collect()
+    }
 
-/// Create a temporary directory for relay transfers.
-fn tempdir_for_relay() -> std::io::Result<PathBuf> {
-    let dir = std::env::temp_dir().join(format!("kosmokopy_relay_{}", std::process::id()));
-    fs::create_dir_all(&dir)?;
-    Ok(dir)
-}
+    #[test]
+    fn duplicate_destinations_in_plan_do_not_overwrite() {
+        let tree = path_set(&["/dst/new_1.txt", "/dst/other.txt"]);
+        let plan = ["/dst/new.txt", "/dst/new.txt", "/dst/new_2.txt", "/dst/fresh.txt"];
+        let some = |s: &str| Some(PathBuf::from(s));
+
+        assert_eq!(
+            plan_decisions(&tree, &plan, ConflictMode::Rename),
+            vec![some("/dst/new.txt"), some("/dst/new_2.txt"), some("/dst/new_2_1.txt"), some("/dst/fresh.txt")]
+        );
+        assert_eq!(
+            plan_decisions(&tree, &plan, ConflictMode::Skip),
+            vec![some("/dst/new.txt"), None, some("/dst/new_2.txt"), some("/dst/fresh.txt")]
+        );
+        assert_eq!(
+            plan_decisions(&tree, &plan, ConflictMode::Overwrite),
+            plan.iter().map(|p| some(p)).collect::<Vec<_>>()
+        );
+    }
 
-// ── SHA-256 hashing for remote transfer verification ───────────────────
+    #[test]
+    fn rename_targets_are_retested_before_upload() {
+        // Another client writes new_1.txt after the lookup: the first test
+        // finds it, so the next free name is used and kept for later files.
+        let mut existing = path_set(&["/dst/new.txt"]);
+        let mut asked = Vec::new();
+        let mut exists = |p: &Path| {
+            asked.push(p.to_path_buf());
+            p == Path::new("/dst/new_1.txt")
+        };
+        let dest = resolve_remote_conflict(Path::new("/dst/new.txt"), &mut existing, ConflictMode::Rename, Recheck::RenameTargets, |_| false, &mut exists);
+        assert_eq!(dest.as_deref(), Some(Path::new("/dst/new_2.txt")));
+        assert_eq!(asked, [Path::new("/dst/new_1.txt"), Path::new("/dst/new_2.txt")]);
+        assert!(existing.contains(Path::new("/dst/new_1.txt")) && existing.contains(Path::new("/dst/new_2.txt")));
+        let again = resolve_remote_conflict(Path::new("/dst/new.txt"), &mut existing, ConflictMode::Rename, Recheck::Nothing, |_| false, |_| true);
+        assert_eq!(again.as_deref(), Some(Path::new("/dst/new_3.txt")));
+
+        // Only --recheck-conflicts tests targets that were not conflicts,
+        // and Skip never asks about rename candidates.
+        let mut calls = 0;
+        let fresh = resolve_remote_conflict(Path::new("/dst/fresh.txt"), &mut existing, ConflictMode::Rename, Recheck::RenameTargets, |_| false, |_| {
+            calls += 1;
+            true
+        });
+        assert_eq!((fresh.as_deref(), calls), (Some(Path::new("/dst/fresh.txt")), 0));
+        let taken = resolve_remote_conflict(Path::new("/dst/late.txt"), &mut existing, ConflictMode::Skip, Recheck::Everything, |_| false, |_| true);
+        assert_eq!(taken, None);
+    }
 
-/// Compute SHA-256 hash of a local file, returned as a lowercase hex string.
-fn compute_sha256_local(path: &Path) -> std::io::Result<String> {
-    let mut file = fs::File::open(path)?;
-    let mut hasher = Sha256::new();
-    let mut buf = [0u8; 8192];
-    loop {
-        let n = file.read(&mut buf)?;
-        if n == 0 {
-            break;
-        }
-        hasher.update(&buf[..n]);
+    #[test]
+    fn newest_wins_compares_times_from_the_lookup() {
+        let tree = path_set(&["/dst/old.txt", "/dst/same.txt"]);
+        let plan = ["/dst/old.txt", "/dst/same.txt", "/dst/fresh.txt", "/dst/old.txt"].map(Path::new);
+        let mut calls = Vec::new();
+        let (mut existing, mut times, _) = existing_remote_files(&plan, ConflictMode::Newer, mock_remote(&tree, &mut calls));
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].starts_with("find ") && calls[0].contains("%T@"));
+        assert_eq!(times.get(Path::new("/dst/old.txt")), Some(&Timestamp { secs: 1_700_000_000, nanos: 500_000_000 }));
+
+        // old.txt's source is 2.5 s newer, same.txt's only 0.5 s.
+        let source = |t: &Path| Timestamp { secs: if t == Path::new("/dst/old.txt") { 1_700_000_003 } else { 1_700_000_001 }, nanos: 0 };
+        let some = |s: &str| Some(PathBuf::from(s));
+        let decisions: Vec<Option<PathBuf>> = plan
+            .iter()
+            .map(|t| {
+                let newer = |p: &Path| source_is_newer(Some(source(t)), times.remove(p));
+                let dest = resolve_remote_conflict(t, &mut existing, ConflictMode::Newer, Recheck::Nothing, newer, |_| false)
+                    .map(|d| d.into_owned());
+                if let Some(d) = &dest {
+                    existing.insert(d.clone());
+                }
+                dest
+            })
+            .collect();
+        // The second source for old.txt leaves the first one's upload alone.
+        assert_eq!(decisions, vec![some("/dst/old.txt"), None, some("/dst/fresh.txt"), None]);
+        assert!(!source_is_newer(None, Some(source(Path::new("/dst/old.txt")))));
     }
-    Ok(format!("{:x}", hasher.finalize()))
-}
 
-/// Compute SHA-256 hash of a remote file via SSH.
-/// Tries sha256sum first, then falls back to shasum -a 256.
-fn compute_sha256_remote(host: &str, ctl: &[&str], remote_path: &str) -> Result<String, String> {
-    let cmd = format!(
-        "sha256sum {} 2>/dev/null || shasum -a 256 {} 2>/dev/null",
-        shell_quote(remote_path),
-        shell_quote(remote_path)
-    );
-    let output = Command::new("ssh")
-        .args(ctl)
-        .arg(host)
-        .arg(&cmd)
-        .output()
-        .map_err(|e| format!("Failed to run SSH for hash verification: {}", e))?;
+    #[test]
+    fn clock_skew_widens_newest_wins() {
+        let dest = Timestamp { secs: 1_700_000_000, nanos: 0 };
+        let source = |ahead: i64| Some(Timestamp { secs: dest.secs + ahead, nanos: 0 });
+        let skew = std::time::Duration::from_secs;
+        // In step, a minute newer wins.
+        assert!(newer_allowing(source(60), Some(dest), skew(0)));
+        assert!(!clock::kept_for_skew());
+        // A host 90 s off: a minute is within the skew, and the skip says so.
+        assert!(!newer_allowing(source(60), Some(dest), skew(90)));
+        assert_eq!(kept_reason(ConflictMode::Newer), WITHIN_SKEW_NOTE);
+        assert_eq!(kept_reason(ConflictMode::Newer), DEST_NEWER_NOTE);
+        // Beyond skew and tolerance together it wins again.
+        assert!(!newer_allowing(source(92), Some(dest), skew(90)));
+        assert!(newer_allowing(source(93), Some(dest), skew(90)));
+        // A source no newer at all is kept whatever the skew.
+        assert!(!newer_allowing(source(1), Some(dest), skew(90)));
+        assert_eq!(kept_reason(ConflictMode::Newer), DEST_NEWER_NOTE);
+        assert!(!newer_allowing(None, Some(dest), skew(90)));
+        assert!(!clock::kept_for_skew());
+    }
 
-    if !output.status.success() {
-        return Err(format!(
-            "Remote hash command failed: {}",
-            String::from_utf8_lossy(&output.stderr).trim()
-        ));
+    #[test]
+    fn lookup_chunks_long_target_lists() {
+        let tree = HashSet::new();
+        let plan: Vec<PathBuf> = (0..PROBE_MAX_TARGETS).map(|i| PathBuf::from(format!("/d/f{}", i))).collect();
+        let targets: Vec<&Path> = plan.iter().map(PathBuf::as_path).collect();
+        let mut calls = Vec::new();
+        let (scoped, _, _) =
+            existing_remote_files(&targets, ConflictMode::Skip, mock_remote(&tree, &mut calls));
+        assert!(scoped.is_empty());
+        assert_eq!(calls.len(), PROBE_MAX_TARGETS.div_ceil(LOOKUP_CHUNK));
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    // Both sha256sum and shasum output: <hash>  <filename>
-    let hash = stdout
-        .trim()
-        .split_whitespace()
-        .next()
-        .ok_or_else(|| "Could not parse remote hash output".to_string())?;
+    #[test]
+    fn cli_parser_handles_exactly_the_defined_options() {
+        // Every option in cli::OPTIONS has one handler and every handler an
+        // option, so the man page and completions stay complete.
+        let handled: Vec<&str> = CLI_HANDLERS.iter().map(|(name, _)| *name).collect();
+        let unique: HashSet<&str> = handled.iter().copied().collect();
+        assert_eq!(unique.len(), handled.len(), "an option has two handlers");
+        let defined: HashSet<&str> = cli::OPTIONS.iter().map(|o| o.name).collect();
+        assert_eq!(unique, defined);
+    }
 
-    Ok(hash.to_lowercase().to_string())
-}
+    #[test]
+    fn cli_handlers_report_bad_values() {
+        let mut a = CliArgs::new(&config::Config::default());
+        let handle = cli_handler("--retries").unwrap();
+        assert!(matches!(handle(&mut a, "--retries", "3"), Ok(Parsed::Next)));
+        let err = handle(&mut a, "--retries", "many").err().unwrap();
+        assert!(err.starts_with("--retries expects"), "{}", err);
+        assert!(cli_handler("--no-such-option").is_none());
+    }
 
-/// Verify a local file against a remote file by comparing SHA-256 hashes.
-fn verify_remote_hash(
-    local: &Path,
-    host: &str,
-    ctl: &[&str],
-    remote: &str,
-) -> Result<bool, String> {
-    let local_hash =
-        compute_sha256_local(local).map_err(|e| format!("local hash error: {}", e))?;
-    let remote_hash = compute_sha256_remote(host, ctl, remote)?;
-    Ok(local_hash == remote_hash)
-}
+    #[test]
+    fn cli_include_replaces_the_profile_patterns() {
+        let mut a = CliArgs::new(&config::Config::default());
+        a.patterns = vec!["*.tmp".to_string()];
+        a.profile_patterns = true;
+        let handle = cli_handler("--exclude").unwrap();
+        assert!(matches!(handle(&mut a, "--exclude", "*.bak"), Ok(Parsed::Next)));
+        assert!(matches!(handle(&mut a, "--exclude", "*.log"), Ok(Parsed::Next)));
+        assert_eq!(a.patterns.len(), 2);
+        assert!(!a.patterns.iter().any(|p| p.contains("tmp")));
+    }
 
-// ── Worker thread (remote via rsync) ───────────────────────────────────
+    // ── Local workers end to end ──────────────────────────────────────
+
+    /// Deterministic pseudo-random bytes (xorshift), so fixture files are
+    /// incompressible without a random-number dependency.
+    fn noise(seed: u64, len: usize) -> Vec<u8> {
+        let mut x = seed | 1;
+        (0..len)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                x as u8
+            })
+            .collect()
+    }
 
-fn run_remote_rsync_worker(
-    source: SourceSelection,
-    host: &str,
-    remote_base: &str,
-    do_move: bool,
-    conflict_mode: ConflictMode,
-    strip_spaces: bool,
-    transfer_mode: TransferMode,
-    patterns: &[String],
-    cancel_flag: Arc<AtomicBool>,
-    tx: mpsc::Sender<WorkerMsg>,
-) {
-    // SSH options — reused for direct ssh calls and passed to rsync via -e
-    let ctl = [
-        "-o", "ControlMaster=auto",
-        "-o", "ControlPath=/tmp/kosmokopy_ssh_%h_%p_%r",
-        "-o", "ControlPersist=60",
-    ];
-    let ssh_cmd = "ssh -o ControlMaster=auto -o ControlPath=/tmp/kosmokopy_ssh_%h_%p_%r -o ControlPersist=60";
+    /// SHA-256 of every regular file under `root`, by path relative to it.
+    /// Symlinks are not followed, as the workers don't copy them.
+    fn tree_hashes(root: &Path) -> std::collections::BTreeMap<String, String> {
+        WalkDir::new(root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+            .map(|e| {
+                let rel = e.path().strip_prefix(root).unwrap().to_string_lossy().to_string();
+                let hash = hashalgo::hash_file(e.path(), HashAlgo::Sha256).unwrap();
+                (rel, hash)
+            })
+            .collect()
+    }
 
-    // Quick connectivity check
-    let check = Command::new("ssh")
-        .args(&ctl)
-        .args([host, "echo ok"])
-        .output();
-    match check {
-        Ok(o) if o.status.success() => {}
-        Ok(o) => {
-            let msg = String::from_utf8_lossy(&o.stderr);
-            let _ = tx.send(WorkerMsg::Error(format!(
-                "SSH connection to '{}' failed: {}",
-                host,
-                msg.trim()
-            )));
-            return;
-        }
-        Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(format!(
-                "Could not run ssh command: {}",
-                e
-            )));
-            return;
+    /// A source tree with the names and layouts transfers must keep:
+    /// nesting, spaces, unicode, dotfiles, an empty file, a few larger
+    /// random files, and a symlink (skipped by default).  File names are
+    /// unique so the tree can also be flattened.
+    fn fixture_tree(name: &str) -> PathBuf {
+        let root = scratch_dir(name).join("library");
+        for dir in ["docs/drafts", "Photos 2024/Day One", "música/ñandú", ".config/app", "empty"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
         }
+        fs::write(root.join("readme.txt"), "top level\n").unwrap();
+        fs::write(root.join(".hidden"), "dotfile\n").unwrap();
+        fs::write(root.join("docs/report final.md"), "# Report\n").unwrap();
+        fs::write(root.join("docs/drafts/v1.txt"), "").unwrap();
+        fs::write(root.join("Photos 2024/Day One/IMG 0001.raw"), noise(1, 300_000)).unwrap();
+        fs::write(root.join("Photos 2024/Day One/IMG 0002.raw"), noise(2, 70_001)).unwrap();
+        fs::write(root.join("música/ñandú/canción.flac"), noise(3, 150_000)).unwrap();
+        fs::write(root.join(".config/app/settings.json"), "{}").unwrap();
+        std::os::unix::fs::symlink("../readme.txt", root.join("docs/link to readme")).unwrap();
+        root
     }
 
-    // Check that rsync is available locally
-    match Command::new("rsync").arg("--version").output() {
-        Ok(o) if o.status.success() => {}
-        _ => {
-            let _ = tx.send(WorkerMsg::Error(
-                "rsync is not installed or not found in PATH".to_string(),
-            ));
-            return;
+    struct Outcome {
+        copied: usize,
+        skipped: Details,
+        excluded_files: usize,
+        errors: Details,
+        messages: Vec<WorkerMsg>,
+    }
+
+    /// Run the standard local worker on a whole folder, as the GUI and
+    /// `--cli` do, and collect its messages.
+    fn run_local(src: &Path, dst: &Path, do_move: bool, strategy: MoveStrategy, mode: TransferMode, options: LocalRun) -> Outcome {
+        let (tx, rx) = mpsc::channel();
+        let cancel_flag = Arc::new(CancelFlag::default());
+        cancel_flag.request(options.intent);
+        run_worker(
+            SourceSelection::Directory(src.to_path_buf()),
+            dst.display().to_string(),
+            do_move,
+            strategy,
+            options.conflict,
+            &Renamer::new(options.rules),
+            mode,
+            0,
+            &options.patterns,
+            options.hash_cache,
+            &RunOptions::default(),
+            cancel_flag,
+            tx,
+        );
+        let mut messages: Vec<WorkerMsg> = rx.try_iter().collect();
+        match messages.pop() {
+            Some(WorkerMsg::Finished(TransferReport { copied, skipped, excluded_files, errors, .. })) => {
+                Outcome { copied, skipped, excluded_files, errors, messages }
+            }
+            Some(WorkerMsg::Error(e)) => panic!("worker failed: {}", e),
+            _ => panic!("worker did not finish"),
         }
     }
 
-    // Collect files locally
-    let (files, excluded_files, excluded_dirs) = match collect_files(&source, patterns) {
-        Ok(v) => v,
-        Err(e) => {
-            let _ = tx.send(WorkerMsg::Error(e));
-            return;
+    struct LocalRun {
+        conflict: ConflictMode,
+        rules: Vec<Rule>,
+        patterns: Vec<String>,
+        hash_cache: Option<HashCache>,
+        /// Requested before the run starts.
+        intent: CancelIntent,
+    }
+
+    impl Default for LocalRun {
+        fn default() -> Self {
+            LocalRun {
+                conflict: ConflictMode::Skip,
+                rules: Vec::new(),
+                patterns: Vec::new(),
+                hash_cache: None,
+                intent: CancelIntent::Run,
+            }
         }
-    };
+    }
 
-    let total = files.len();
-    if total == 0 {
-        let _ = tx.send(WorkerMsg::Finished {
-            copied: 0,
-            skipped: vec![],
-            excluded_files,
-            excluded_dirs,
-            errors: vec![],
-        });
-        return;
+    fn copy(src: &Path, dst: &Path) -> Outcome {
+        run_local(src, dst, false, MoveStrategy::PerFile, TransferMode::FoldersAndFiles, LocalRun::default())
     }
 
-    let src_dir = match &source {
-        SourceSelection::Directory(d) => Some(d.clone()),
-        _ => None,
-    };
+    #[test]
+    fn copy_reproduces_the_fixture_tree() {
+        let src = fixture_tree("e2e_copy");
+        let dst = src.with_file_name("dest");
+        let expected = tree_hashes(&src);
+        assert_eq!(expected.len(), 8);
+
+        let out = copy(&src, &dst);
+        assert_eq!((out.copied, out.errors.len()), (8, 0));
+        assert_eq!(out.skipped.iter().collect::<Vec<_>>(), [&format!("{}: {}", src.join("docs/link to readme").display(), symlinks::SKIPPED)]);
+        assert_eq!(tree_hashes(&dst.join("library")), expected);
+        assert!(fs::symlink_metadata(dst.join("library/docs/link to readme")).is_err());
+        // Progress reached the end and the plan covered every file.
+        let plan_len = out.messages.iter().find_map(|m| match m {
+            WorkerMsg::Plan { sizes } => Some(sizes.len()),
+            _ => None,
+        });
+        assert_eq!(plan_len, Some(8));
+        assert!(out.messages.iter().any(|m| matches!(m, WorkerMsg::Progress { done: 8, total: 8, .. })));
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
 
-    // Build list of (local_path, remote_path) pairs
-    let remote_base = remote_base.trim_end_matches('/');
-    let mut transfers: Vec<(PathBuf, String)> = Vec::new();
-    let mut remote_dirs: HashSet<String> = HashSet::new();
-    remote_dirs.insert(remote_base.to_string());
-    let mut early_skipped: Vec<String> = Vec::new();
+    #[test]
+    fn a_finished_copy_can_be_verified_again() {
+        let src = fixture_tree("e2e_reverify");
+        let dst = src.with_file_name("dest");
+        reverify::start();
+        let out = copy(&src, &dst);
+        assert_eq!(out.copied, 8);
+        // Other tests' runs may be noted too.
+        let record: Vec<reverify::Copied> = reverify::take()
+            .into_iter()
+            .filter(|c| matches!(&c.dest, reverify::Location::Local(p) if p.starts_with(&dst)))
+            .collect();
+        assert_eq!(record.len(), 8);
+
+        fs::write(dst.join("library/readme.txt"), "changed since\n").unwrap();
+        fs::remove_file(dst.join("library/.hidden")).unwrap();
+        let (tx, rx) = mpsc::channel();
+        run_reverify_worker(record.clone(), HashAlgo::Sha256, Arc::new(CancelFlag::default()), tx);
+        let messages: Vec<WorkerMsg> = rx.try_iter().collect();
+        assert!(messages.iter().any(|m| matches!(m, WorkerMsg::Progress { done: 8, total: 8, .. })));
+        let Some(WorkerMsg::Reverified(outcome)) = messages.last() else {
+            panic!("the verification sent no outcome");
+        };
+        assert_eq!((outcome.total, outcome.matched), (8, 6));
+        assert_eq!(outcome.differ, [dst.join("library/readme.txt").display().to_string()]);
+        assert!(outcome.failed[0].starts_with(&format!("{}: ", dst.join("library/.hidden").display())));
+
+        // Closing the dialog stops it before the first file.
+        let (tx, rx) = mpsc::channel();
+        let cancel_flag = Arc::new(CancelFlag::default());
+        cancel_flag.request(CancelIntent::Stop);
+        run_reverify_worker(record, HashAlgo::Sha256, cancel_flag, tx);
+        match rx.try_iter().last() {
+            Some(WorkerMsg::Reverified(outcome)) => assert!(outcome.cancelled && outcome.matched == 0),
+            _ => panic!("the verification sent no outcome"),
+        }
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
 
-    for file_path in &files {
-        let rel_dest = match (&src_dir, transfer_mode) {
-            (Some(sd), TransferMode::FoldersAndFiles) => match file_path.strip_prefix(sd) {
-                Ok(rel) => {
-                    let root = sd.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
-                    if root.is_empty() { rel.to_string_lossy().to_string() }
-                    else { format!("{}/{}", root, rel.to_string_lossy()) }
-                }
-                Err(_) => {
-                    early_skipped.push(format!(
-                        "{}: outside source directory",
-                        file_path.display()
-                    ));
-                    continue;
+    #[test]
+    fn each_copy_is_verified_before_its_progress_and_finalizing_comes_last() {
+        let src = fixture_tree("e2e_phases");
+        let dst = src.with_file_name("dest");
+        let out = copy(&src, &dst);
+        let mut model = ProgressModel::default();
+        let mut seen = vec![Phase::Scanning];
+        // Files in flight side by side may each start verifying in turn.
+        let mut verifications = 0;
+        for m in &out.messages {
+            match m {
+                WorkerMsg::Plan { sizes } => model = ProgressModel::new(sizes.clone()),
+                WorkerMsg::FileBytes { bytes } => model.in_flight(*bytes),
+                WorkerMsg::Phase(phase) => {
+                    verifications += usize::from(*phase == Phase::Verifying);
+                    let repeated = *phase == Phase::Verifying && model.phase() == *phase;
+                    assert!(model.set_phase(*phase) || repeated, "{:?} after {:?}", phase, model.phase());
                 }
-            },
-            _ => match file_path.file_name() {
-                Some(f) => f.to_string_lossy().to_string(),
-                None => {
-                    early_skipped.push(format!("{}: no filename", file_path.display()));
-                    continue;
+                WorkerMsg::Progress { done, total, .. } => {
+                    assert!(verifications >= *done, "file {} was not verified", done);
+                    model.file_done(*done, *total);
                 }
-            },
-        };
-        let remote_file = format!("{}/{}", remote_base, rel_dest);
-        let remote_file = if strip_spaces {
-            remote_file
-                .split('/')
-                .map(|c| c.replace(' ', ""))
-                .collect::<Vec<_>>()
-                .join("/")
-        } else {
-            remote_file
-        };
-        if let Some(parent) = Path::new(&remote_file).parent() {
-            remote_dirs.insert(parent.to_string_lossy().to_string());
+                _ => {}
+            }
+            if seen.last() != Some(&model.phase()) {
+                seen.push(model.phase());
+            }
         }
-        transfers.push((file_path.clone(), remote_file));
+        assert_eq!(seen[1..3], [Phase::Preflight, Phase::Transferring]);
+        assert_eq!(seen.last(), Some(&Phase::Finalizing));
+        assert_eq!(verifications, 8);
+        let _ = fs::remove_dir_all(src.parent().unwrap());
     }
 
-    // Create all remote directories in one SSH call
-    let dirs_arg: Vec<String> = remote_dirs.iter().map(|d| shell_quote(d)).collect();
-    let mkdir_result = Command::new("ssh")
-        .args(&ctl)
-        .arg(host)
-        .arg(format!("mkdir -p {}", dirs_arg.join(" ")))
-        .output();
-    if let Ok(o) = &mkdir_result {
-        if !o.status.success() {
-            let msg = String::from_utf8_lossy(&o.stderr);
-            let _ = tx.send(WorkerMsg::Error(format!(
-                "Failed to create remote directories: {}",
-                msg.trim()
-            )));
-            return;
+    #[test]
+    fn a_cancel_before_the_first_file_ends_as_cancelled_while_scanning() {
+        let src = fixture_tree("e2e_cancel_scan");
+        let dst = src.with_file_name("dest");
+        let (tx, rx) = mpsc::channel();
+        let cancel_flag = Arc::new(CancelFlag::default());
+        cancel_flag.request(CancelIntent::Stop);
+        run_worker(
+            SourceSelection::Directory(src.clone()),
+            dst.display().to_string(),
+            false,
+            MoveStrategy::PerFile,
+            ConflictMode::Skip,
+            &Renamer::new(Vec::new()),
+            TransferMode::FoldersAndFiles,
+            0,
+            &[],
+            None,
+            &RunOptions::default(),
+            cancel_flag,
+            tx,
+        );
+        let mut model = ProgressModel::default();
+        let mut ending = None;
+        for m in rx.try_iter() {
+            match m {
+                WorkerMsg::Plan { .. } => panic!("a plan was sent after the cancel"),
+                WorkerMsg::Phase(phase) => {
+                    model.set_phase(phase);
+                }
+                WorkerMsg::Cancelled(report) => ending = Some(report.copied),
+                _ => {}
+            }
         }
+        assert_eq!(ending, Some(0));
+        assert_eq!(model.phase(), Phase::Scanning);
+        assert!(!dst.join("library").exists());
+        let _ = fs::remove_dir_all(src.parent().unwrap());
     }
 
-    // If not overwriting, get list of existing remote files in one SSH call
-    let existing: HashSet<String> = if conflict_mode != ConflictMode::Overwrite {
-        let out = Command::new("ssh")
-            .args(&ctl)
-            .arg(host)
-            .arg(format!(
-                "find {} -type f 2>/dev/null",
-                shell_quote(remote_base)
-            ))
-            .output();
-        match out {
-            Ok(o) => String::from_utf8_lossy(&o.stdout)
-                .lines()
-                .map(|l| l.to_string())
-                .collect(),
-            Err(_) => HashSet::new(),
+    fn unverified(out: &Outcome) -> Vec<String> {
+        out.messages
+            .iter()
+            .find_map(|m| match m {
+                WorkerMsg::Unverified(files) => Some(files.clone()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn skipped_verification_lists_copies_as_unverified() {
+        let src = fixture_tree("e2e_skip_verify");
+        let dst = src.with_file_name("dest");
+        let expected = tree_hashes(&src);
+        let options = LocalRun { intent: CancelIntent::SkipVerify, ..LocalRun::default() };
+
+        let out = run_local(&src, &dst, false, MoveStrategy::PerFile, TransferMode::FoldersAndFiles, options);
+        assert_eq!((out.copied, out.errors.len()), (0, 0));
+        assert_eq!(unverified(&out).len(), 8);
+        assert_eq!(tree_hashes(&dst.join("library")), expected);
+        assert!(!out.messages.iter().any(|m| matches!(m, WorkerMsg::Phase(Phase::Verifying))));
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
+
+    #[test]
+    fn two_phase_move_never_deletes_unverified_sources() {
+        let src = fixture_tree("e2e_skip_verify_move");
+        let dst = src.with_file_name("dest");
+        let expected = tree_hashes(&src);
+        let options = LocalRun { intent: CancelIntent::SkipVerify, ..LocalRun::default() };
+
+        let out = run_local(&src, &dst, true, MoveStrategy::AllThenDelete, TransferMode::FoldersAndFiles, options);
+        assert_eq!((out.copied, out.errors.len()), (0, 0));
+        assert_eq!(unverified(&out).len(), 8);
+        assert!(out.messages.iter().any(|m| matches!(m, WorkerMsg::Deletion(Deletion::Unverified { files: 8 }))));
+        assert!(!out.messages.iter().any(|m| matches!(m, WorkerMsg::DeletePhase { .. })));
+        assert_eq!(tree_hashes(&src), expected);
+        assert_eq!(tree_hashes(&dst.join("library")), expected);
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
+
+    #[test]
+    fn copying_again_finds_every_file_identical() {
+        let src = fixture_tree("e2e_recopy");
+        let dst = src.with_file_name("dest");
+        copy(&src, &dst);
+
+        let again = copy(&src, &dst);
+        assert_eq!((again.copied, again.errors.len()), (0, 0));
+        assert_eq!(again.skipped.len(), 9);
+        let identical = again.skipped.iter().filter(|s| s.ends_with(": identical at destination")).count();
+        assert_eq!(identical, 8, "{:?}", again.skipped);
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
+
+    #[test]
+    fn move_empties_the_source_and_completes_the_destination() {
+        for strategy in [MoveStrategy::PerFile, MoveStrategy::AllThenDelete] {
+            let src = fixture_tree("e2e_move");
+            let dst = src.with_file_name("dest");
+            let expected = tree_hashes(&src);
+
+            let out = run_local(&src, &dst, true, strategy, TransferMode::FoldersAndFiles, LocalRun::default());
+            assert_eq!((out.copied, out.errors.len()), (8, 0));
+            assert!(tree_hashes(&src).is_empty());
+            assert_eq!(tree_hashes(&dst.join("library")), expected);
+            let _ = fs::remove_dir_all(src.parent().unwrap());
         }
-    } else {
-        HashSet::new()
-    };
+    }
 
-    let total_transfers = transfers.len();
-    let mut copied = 0usize;
-    let mut skipped = early_skipped;
-    let mut errors: Vec<String> = Vec::new();
+    #[test]
+    fn files_only_flattens_the_tree() {
+        let src = fixture_tree("e2e_flat");
+        let dst = src.with_file_name("dest");
+        let out = run_local(&src, &dst, false, MoveStrategy::PerFile, TransferMode::FilesOnly, LocalRun::default());
+        assert_eq!(out.copied, 8);
+        let flat: Vec<String> = tree_hashes(&dst).into_keys().collect();
+        assert_eq!(
+            flat,
+            [".hidden", "IMG 0001.raw", "IMG 0002.raw", "canción.flac", "readme.txt", "report final.md", "settings.json", "v1.txt"]
+        );
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
 
-    for (i, (local, remote)) in transfers.iter().enumerate() {
-        if cancel_flag.load(Ordering::SeqCst) {
-            let _ = tx.send(WorkerMsg::Cancelled {
-                copied,
-                skipped,
-                excluded_files,
-                excluded_dirs,
-                errors,
-            });
-            return;
+    #[test]
+    fn strip_spaces_renames_below_the_destination_only() {
+        let src = fixture_tree("e2e_strip");
+        let dst = src.with_file_name("my dest");
+        let options = LocalRun { rules: vec![Rule::strip_spaces()], ..LocalRun::default() };
+        let out = run_local(&src, &dst, false, MoveStrategy::PerFile, TransferMode::FoldersAndFiles, options);
+        assert_eq!(out.copied, 8);
+        let copied = tree_hashes(&dst.join("library"));
+        assert!(copied.contains_key("Photos2024/DayOne/IMG0001.raw"));
+        assert!(copied.contains_key("docs/reportfinal.md"));
+        assert!(copied.keys().all(|k| !k.contains(' ')));
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
+
+    #[test]
+    fn existing_files_are_found_under_their_renamed_names() {
+        let src = fixture_tree("e2e_rename_rules");
+        let dst = src.with_file_name("dest");
+        let rules = || vec![Rule::parse("regex/^IMG (\\d+)/photo-$1").unwrap(), Rule::CaseFoldExtension];
+        let first = run_local(&src, &dst, false, MoveStrategy::PerFile, TransferMode::FoldersAndFiles, LocalRun { rules: rules(), ..LocalRun::default() });
+        assert_eq!(first.copied, 8);
+        let copied = tree_hashes(&dst.join("library"));
+        assert!(copied.contains_key("Photos 2024/Day One/photo-0001.raw"));
+        assert!(!copied.keys().any(|k| k.contains("IMG")));
+        // The second run checks for the renamed files, not the originals.
+        let second = run_local(&src, &dst, false, MoveStrategy::PerFile, TransferMode::FoldersAndFiles, LocalRun { rules: rules(), ..LocalRun::default() });
+        assert_eq!(second.copied, 0);
+        assert!(second.skipped.iter().any(|s| s.ends_with("IMG 0001.raw: identical at destination")));
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
+
+    #[test]
+    fn exclusions_leave_files_and_folders_out() {
+        let src = fixture_tree("e2e_exclude");
+        let dst = src.with_file_name("dest");
+        let options = LocalRun { patterns: vec!["/.config".to_string(), "~*.raw".to_string()], ..LocalRun::default() };
+        let out = run_local(&src, &dst, false, MoveStrategy::PerFile, TransferMode::FoldersAndFiles, options);
+        assert_eq!((out.copied, out.excluded_files), (5, 2));
+        let copied = tree_hashes(&dst.join("library"));
+        assert!(!copied.keys().any(|k| k.starts_with(".config") || k.ends_with(".raw")));
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
+
+    #[test]
+    fn changed_destination_files_follow_the_conflict_mode() {
+        let src = fixture_tree("e2e_conflict");
+        let dst = src.with_file_name("dest");
+        copy(&src, &dst);
+        let changed = dst.join("library/docs/report final.md");
+        fs::write(&changed, "edited at the destination\n").unwrap();
+
+        let skip = copy(&src, &dst);
+        assert_eq!(skip.copied, 0);
+        assert!(skip.skipped.iter().any(|s| s.ends_with("report final.md: different version exists at destination")));
+
+        let options = LocalRun { conflict: ConflictMode::Rename, ..LocalRun::default() };
+        let rename = run_local(&src, &dst, false, MoveStrategy::PerFile, TransferMode::FoldersAndFiles, options);
+        assert_eq!(rename.copied, 1);
+        assert_eq!(fs::read_to_string(&changed).unwrap(), "edited at the destination\n");
+        assert_eq!(fs::read_to_string(dst.join("library/docs/report final_1.md")).unwrap(), "# Report\n");
+
+        let options = LocalRun { conflict: ConflictMode::Overwrite, ..LocalRun::default() };
+        let overwrite = run_local(&src, &dst, false, MoveStrategy::PerFile, TransferMode::FoldersAndFiles, options);
+        assert_eq!(overwrite.copied, 1);
+        assert_eq!(fs::read_to_string(&changed).unwrap(), "# Report\n");
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
+
+    #[test]
+    fn planned_files_follow_the_conflict_mode() {
+        let dir = scratch_dir("plan_local_file");
+        let (src, dst) = (dir.join("src"), dir.join("dst"));
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&dst).unwrap();
+        for (name, text) in [("new.txt", "a"), ("same.txt", "b"), ("changed.txt", "c")] {
+            fs::write(src.join(name), text).unwrap();
         }
-        // Handle conflict if file exists remotely
-        let remote = if conflict_mode != ConflictMode::Overwrite && existing.contains(remote) {
-            match conflict_mode {
-                ConflictMode::Skip => {
-                    skipped.push(format!(
-                        "{}: already exists at destination",
-                        local.display()
-                    ));
-                    let _ = tx.send(WorkerMsg::Progress {
-                        done: i + 1,
-                        total: total_transfers,
-                        file: local.to_string_lossy().to_string(),
-                    });
-                    continue;
-                }
-                ConflictMode::Rename => {
-                    std::borrow::Cow::Owned(find_unique_remote_path_from_set(remote, &existing))
-                }
-                ConflictMode::Overwrite => unreachable!(),
-            }
-        } else {
-            std::borrow::Cow::Borrowed(remote.as_str())
+        fs::write(dst.join("same.txt"), "b").unwrap();
+        fs::write(dst.join("changed.txt"), "old").unwrap();
+        let renamer = Renamer::new(Vec::new());
+        let on_disk = |p: &Path| p.exists();
+        let plan = |name: &str, conflict_mode, taken: &dyn Fn(&Path) -> bool| {
+            let file = src.join(name);
+            let input = PlanInput {
+                file: &file,
+                src_dir: Some(&src),
+                transfer_mode: TransferMode::FilesOnly,
+                parent_levels: 0,
+                dest: Dest::Local(&dst),
+            };
+            let action = plan_local_file(&input, &renamer, conflict_mode, symlinks::Policy::Skip, taken, files_are_identical);
+            (action.name(false), action.dest().map(Path::to_path_buf))
         };
 
-        // Transfer via rsync with checksum verification
-        let rsync_result = Command::new("rsync")
-            .args(["-az", "--checksum"])
-            .arg("-e")
-            .arg(ssh_cmd)
-            .arg(local)
-            .arg(format!("{}:{}", host, rsync_escape_remote(&remote)))
-            .status();
+        assert_eq!(plan("new.txt", ConflictMode::Skip, &on_disk), ("copy", Some(dst.join("new.txt"))));
+        assert_eq!(plan("same.txt", ConflictMode::Overwrite, &on_disk), ("skip-identical", Some(dst.join("same.txt"))));
+        assert_eq!(plan("changed.txt", ConflictMode::Skip, &on_disk), ("skip-existing", Some(dst.join("changed.txt"))));
+        assert_eq!(plan("changed.txt", ConflictMode::Overwrite, &on_disk), ("overwrite", Some(dst.join("changed.txt"))));
+        assert_eq!(plan("changed.txt", ConflictMode::Rename, &on_disk), ("rename", Some(dst.join("changed_1.txt"))));
+        // A name the plan has already given out counts as taken.
+        let planned = |p: &Path| p.exists() || p == dst.join("changed_1.txt");
+        assert_eq!(plan("changed.txt", ConflictMode::Rename, &planned), ("rename", Some(dst.join("changed_2.txt"))));
+        // Newest wins goes by the modification times, give or take 2 s.
+        let at = |path: PathBuf, secs| {
+            let file = fs::File::options().write(true).open(path).unwrap();
+            file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs)).unwrap();
+        };
+        at(src.join("changed.txt"), 1_700_000_003);
+        at(dst.join("changed.txt"), 1_700_000_000);
+        assert_eq!(plan("changed.txt", ConflictMode::Newer, &on_disk), ("overwrite", Some(dst.join("changed.txt"))));
+        at(dst.join("changed.txt"), 1_700_000_001);
+        assert_eq!(plan("changed.txt", ConflictMode::Newer, &on_disk), ("skip-newer", Some(dst.join("changed.txt"))));
+        assert_eq!(plan("same.txt", ConflictMode::Newer, &on_disk), ("skip-identical", Some(dst.join("same.txt"))));
+        // Nothing was written.
+        assert!(!dst.join("new.txt").exists() && !dst.join("changed_1.txt").exists());
+
+        let identical = LocalAction::Identical(dst.join("same.txt"));
+        assert_eq!((identical.name(false), identical.name(true)), ("skip-identical", "delete-source"));
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-        match rsync_result {
-            Ok(s) if s.success() => {
-                // rsync --checksum already verifies integrity during transfer,
-                // but we perform an additional SHA-256 comparison to be safe,
-                // especially before deleting source files in move mode.
-                match verify_remote_hash(local, host, &ctl, &remote) {
-                    Ok(true) => {
-                        copied += 1;
-                        if do_move {
-                            if let Err(e) = fs::remove_file(local) {
-                                errors.push(format!(
-                                    "{}: transferred and verified but failed to delete local: {}",
-                                    local.display(),
-                                    e
-                                ));
-                            }
-                        }
-                    }
-                    Ok(false) => {
-                        // Hash mismatch — remove corrupt remote copy, keep source
-                        let _ = Command::new("ssh")
-                            .args(&ctl)
-                            .arg(host)
-                            .arg(format!("rm -f {}", shell_quote(&remote)))
-                            .status();
-                        errors.push(format!(
-                            "{}: integrity check failed — hash mismatch (original retained, remote copy removed)",
-                            local.display()
-                        ));
-                    }
-                    Err(e) => {
-                        // Cannot verify — keep both, report error
-                        if do_move {
-                            errors.push(format!(
-                                "{}: transferred but verification failed: {} (original retained)",
-                                local.display(),
-                                e
-                            ));
-                        } else {
-                            errors.push(format!(
-                                "{}: transferred but could not verify: {}",
-                                local.display(),
-                                e
-                            ));
-                        }
-                    }
-                }
-            }
-            Ok(s) => {
-                errors.push(format!(
-                    "{}: rsync failed (exit code {})",
-                    local.display(),
-                    s.code().unwrap_or(-1)
-                ));
-            }
-            Err(e) => {
-                errors.push(format!("{}: {}", local.display(), e));
+    #[test]
+    fn warm_hash_cache_skips_every_file() {
+        let src = fixture_tree("e2e_hash_cache");
+        let dst = src.with_file_name("dest");
+        let cache_path = src.with_file_name("hashes");
+        let cached = || LocalRun { hash_cache: Some(HashCache::load(&cache_path, true)), ..LocalRun::default() };
+        let stats_of = |out: &Outcome| {
+            out.messages.iter().find_map(|m| match m {
+                WorkerMsg::HashCache(cache) => Some((cache.hits, cache.misses)),
+                _ => None,
+            })
+        };
+
+        let cold = run_local(&src, &dst, false, MoveStrategy::PerFile, TransferMode::FoldersAndFiles, cached());
+        assert_eq!((cold.copied, stats_of(&cold)), (8, Some((0, 0))));
+        let warm = run_local(&src, &dst, false, MoveStrategy::PerFile, TransferMode::FoldersAndFiles, cached());
+        assert_eq!((warm.copied, warm.skipped.len(), stats_of(&warm)), (0, 9, Some((8, 0))));
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
+
+    // ── Remote workers through a stand-in host ─────────────────────────
+
+    /// Puts a stand-in `ssh` and `scp` first on PATH, once for the whole
+    /// test binary: `ssh` runs its command here and `scp` copies here with
+    /// the `host:` prefix dropped, so the remote workers run end to end
+    /// through TrackActivity without a host.  scp loses the connection for
+    /// any file named `unreachable*`.
+    fn stand_in_host() {
+        static READY: std::sync::OnceLock<()> = std::sync::OnceLock::new();
+        READY.get_or_init(|| {
+            use std::os::unix::fs::PermissionsExt;
+            let bin = scratch_dir("stand_in_host");
+            let scripts = [
+                ("ssh", "for a; do cmd=\"$a\"; done\nexec sh -c \"$cmd\"\n"),
+                (
+                    "scp",
+                    "for a; do src=\"$dst\"; dst=\"$a\"; done\n\
+                     case \"$dst\" in */unreachable*) echo 'lost connection' >&2; exit 1;; esac\n\
+                     cp \"${src#*:}\" \"${dst#*:}\"\n",
+                ),
+            ];
+            for (name, script) in scripts {
+                fs::write(bin.join(name), format!("#!/bin/sh\n{}", script)).unwrap();
+                fs::set_permissions(bin.join(name), fs::Permissions::from_mode(0o755)).unwrap();
             }
+            let path = std::env::var_os("PATH").unwrap_or_default();
+            let mut dirs = vec![bin];
+            dirs.extend(std::env::split_paths(&path));
+            std::env::set_var("PATH", std::env::join_paths(dirs).unwrap());
+        });
+    }
+
+    /// The report a worker finished with.
+    fn finished(rx: mpsc::Receiver<WorkerMsg>) -> TransferReport {
+        match rx.try_iter().last() {
+            Some(WorkerMsg::Finished(report)) => report,
+            Some(WorkerMsg::Error(e)) => panic!("worker failed: {}", e),
+            _ => panic!("worker did not finish"),
         }
+    }
 
-        let _ = tx.send(WorkerMsg::Progress {
-            done: i + 1,
-            total: total_transfers,
-            file: local.to_string_lossy().to_string(),
-        });
+    fn upload(src: &Path, dst: &Path, do_move: bool) -> TransferReport {
+        stand_in_host();
+        let (tx, rx) = mpsc::channel();
+        run_remote_worker(
+            SourceSelection::Directory(src.to_path_buf()),
+            "fakehost",
+            &dst.display().to_string(),
+            do_move,
+            MoveStrategy::PerFile,
+            ConflictMode::Skip,
+            Recheck::RenameTargets,
+            &Renamer::new(Vec::new()),
+            TransferMode::FoldersAndFiles,
+            0,
+            &[],
+            TransferMethod::Standard,
+            &RunOptions::default(),
+            Arc::new(CancelFlag::default()),
+            tx,
+        );
+        finished(rx)
     }
 
-    let _ = tx.send(WorkerMsg::Finished {
-        copied,
-        skipped,
-        excluded_files,
-        excluded_dirs,
-        errors,
-    });
+    #[test]
+    fn an_upload_through_the_stand_in_host_reproduces_the_fixture_tree() {
+        let src = fixture_tree("e2e_upload");
+        let dst = src.with_file_name("dest");
+        fs::create_dir(&dst).unwrap();
+        let expected = tree_hashes(&src);
+        let report = upload(&src, &dst, false);
+        assert_eq!((report.copied, report.errors.len()), (8, 0), "{:?}", report.errors);
+        assert_eq!(tree_hashes(&dst.join("library")), expected);
+        // Sending it again finds every file there already.
+        let again = upload(&src, &dst, false);
+        assert_eq!((again.copied, again.skipped.len()), (0, 9));
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
+
+    #[test]
+    fn a_moved_upload_keeps_the_source_of_a_file_that_did_not_arrive() {
+        let src = scratch_dir("e2e_upload_lost").join("outbox");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("sent.txt"), "sent\n").unwrap();
+        fs::write(src.join("unreachable.txt"), "lost\n").unwrap();
+        let dst = src.with_file_name("dest");
+        fs::create_dir(&dst).unwrap();
+        let report = upload(&src, &dst, true);
+        assert_eq!((report.copied, report.errors.len()), (1, 1));
+        assert!(report.errors.iter().any(|e| e.contains("unreachable.txt")), "{:?}", report.errors);
+        assert_eq!(fs::read_to_string(dst.join("outbox/sent.txt")).unwrap(), "sent\n");
+        assert!(!src.join("sent.txt").exists());
+        assert!(src.join("unreachable.txt").exists());
+        assert!(!dst.join("outbox/unreachable.txt").exists());
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
+
+    #[test]
+    fn a_download_through_the_stand_in_host_reproduces_the_fixture_tree() {
+        stand_in_host();
+        let src = fixture_tree("e2e_download");
+        let dst = src.with_file_name("dest");
+        let expected = tree_hashes(&src);
+        let (tx, rx) = mpsc::channel();
+        run_remote_to_local_worker(
+            "fakehost",
+            &src.display().to_string(),
+            &[],
+            &dst.display().to_string(),
+            false,
+            MoveStrategy::PerFile,
+            ConflictMode::Skip,
+            &Renamer::new(Vec::new()),
+            TransferMode::FoldersAndFiles,
+            0,
+            &[],
+            TransferMethod::Standard,
+            &RunOptions::default(),
+            Arc::new(CancelFlag::default()),
+            tx,
+        );
+        let report = finished(rx);
+        assert_eq!((report.copied, report.errors.len()), (8, 0), "{:?}", report.errors);
+        assert_eq!(tree_hashes(&dst.join("library")), expected);
+        let _ = fs::remove_dir_all(src.parent().unwrap());
+    }
+
+    #[test]
+    fn a_burst_of_worker_messages_reaches_the_main_loop_in_order() {
+        const BURST: usize = 100_000;
+        let (tx, rx) = mpsc::channel();
+        let messages = relay_to_main_loop(rx);
+        thread::spawn(move || {
+            for done in 1..=BURST {
+                let _ = tx.send(WorkerMsg::Progress { done, total: BURST, file: String::new() });
+            }
+        });
+        let started = std::time::Instant::now();
+        let last = glib::MainContext::new().block_on(async move {
+            let mut last = 0;
+            while let Ok(first) = messages.recv().await {
+                let queued = std::iter::from_fn(|| messages.try_recv().ok());
+                for msg in std::iter::once(first).chain(queued) {
+                    let WorkerMsg::Progress { done, .. } = msg else {
+                        panic!("only progress was sent");
+                    };
+                    assert_eq!(done, last + 1);
+                    last = done;
+                }
+            }
+            last
+        });
+        assert_eq!(last, BURST);
+        // Nothing waits on a timer between messages.
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    }
 }
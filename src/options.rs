@@ -0,0 +1,72 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use crate::collisions::OnCollision;
+use crate::hashalgo::HashAlgo;
+use crate::parallel;
+use crate::relay::RelayChoice;
+use crate::scanguard;
+use crate::symlinks;
+use crate::throttle::Throttle;
+use crate::verify::VerifyMode;
+
+// ── Options of one run ─────────────────────────────────────────────────
+//
+// The choices a transfer is started with, read from the window's widgets
+// when Start is pressed or from the CLI's flags, and handed to the worker
+// with the run.  Each run gets its own copy, so nothing one run chose is
+// left behind for the next and there is nothing to put back between runs.
+// What a run finds out as it goes (the folders it made, what each host
+// hashes with, the counts it reports) is kept by the modules themselves
+// and forgotten when the next run starts.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunOptions {
+    /// Files in flight at once, within 1 and [`parallel::MAX_WORKERS`].
+    pub workers: usize,
+    /// `--bwlimit` and `--compress`.
+    pub throttle: Throttle,
+    /// How local copies are checked.
+    pub verify: VerifyMode,
+    /// What copies are hashed with, where hosts allow.
+    pub hash_algo: HashAlgo,
+    /// What happens to files of one batch bound for the same path.
+    pub on_collision: OnCollision,
+    /// How files go between two hosts.
+    pub relay: RelayChoice,
+    /// Keep times and permissions.
+    pub preserve: bool,
+    /// Run at nice 19 and in the idle IO class (see `priority`).
+    pub low_priority: bool,
+    /// Transfer whatever the destination's free space.
+    pub ignore_free_space: bool,
+    /// Times a failed file is sent again.
+    pub retries: u32,
+    /// What scans do with the links they meet.
+    pub symlinks: symlinks::Policy,
+    /// Whether a move may remove the emptied source folder itself.
+    pub remove_source_root: bool,
+    /// When a scan stops to ask whether to go on.
+    pub scan_limits: scanguard::Limits,
+}
+
+impl Default for RunOptions {
+    fn default() -> RunOptions {
+        RunOptions {
+            workers: parallel::DEFAULT_WORKERS,
+            throttle: Throttle::default(),
+            verify: VerifyMode::default(),
+            hash_algo: HashAlgo::default(),
+            on_collision: OnCollision::default(),
+            relay: RelayChoice::default(),
+            preserve: false,
+            low_priority: false,
+            ignore_free_space: false,
+            retries: 0,
+            symlinks: symlinks::Policy::default(),
+            remove_source_root: false,
+            scan_limits: scanguard::Limits::DEFAULT,
+        }
+    }
+}
@@ -0,0 +1,241 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::fs;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::activity::TrackActivity;
+
+// ── Owner filters ──────────────────────────────────────────────────────
+//
+// A backup of a shared tree such as `/srv/projects` often wants only one
+// team's files.  `--only-user`/`--only-group` keep the files owned by any
+// of the users or groups given, and `--exclude-user`/`--exclude-group`
+// leave theirs out.  Names are looked up once, before the run, so a typo
+// is an error rather than an empty copy.
+//
+// A local scan compares numeric ids.  On a remote host `find` does the
+// filtering where it can, and the owner fields of the listing are checked
+// too, by name where a name was given, since ids differ between machines.
+// Individually picked files are never filtered.
+//
+// Files left out by owner are counted apart from pattern exclusions.  Like
+// the folders a run creates, the count is process-wide.
+
+/// A user or group from the command line: its id here, and its name when
+/// it was given by name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Owner {
+    pub id: u32,
+    pub name: Option<String>,
+}
+
+impl Owner {
+    fn matches(&self, id: u32, name: Option<&str>) -> bool {
+        match (&self.name, name) {
+            (Some(wanted), Some(name)) => wanted == name,
+            _ => self.id == id,
+        }
+    }
+
+    /// How `find -user`/`-group` names it.
+    fn find_arg(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.id.to_string())
+    }
+}
+
+/// The owner rules of a run; empty keeps every file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OwnerFilter {
+    pub only_users: Vec<Owner>,
+    pub only_groups: Vec<Owner>,
+    pub exclude_users: Vec<Owner>,
+    pub exclude_groups: Vec<Owner>,
+}
+
+impl OwnerFilter {
+    pub fn is_empty(&self) -> bool {
+        self.only_users.is_empty()
+            && self.only_groups.is_empty()
+            && self.exclude_users.is_empty()
+            && self.exclude_groups.is_empty()
+    }
+
+    /// Whether a file owned by `uid`/`gid` is kept; `user` and `group` are
+    /// their names where the listing has them.
+    pub fn keeps(&self, uid: u32, gid: u32, user: Option<&str>, group: Option<&str>) -> bool {
+        let any = |owners: &[Owner], id: u32, name: Option<&str>| owners.iter().any(|o| o.matches(id, name));
+        (self.only_users.is_empty() || any(&self.only_users, uid, user))
+            && (self.only_groups.is_empty() || any(&self.only_groups, gid, group))
+            && !any(&self.exclude_users, uid, user)
+            && !any(&self.exclude_groups, gid, group)
+    }
+
+    /// `find` tests selecting the files kept, e.g.
+    /// `\( -user 'ann' -o -user '1002' \) ! -group 'scratch'`; empty when
+    /// every file is.  `quote` is the shell quoting the workers use.
+    pub fn find_tests(&self, quote: impl Fn(&str) -> String) -> String {
+        let mut tests = Vec::new();
+        let mut one_of = |owners: &[Owner], test: &str| {
+            let alternatives: Vec<String> =
+                owners.iter().map(|o| format!("{} {}", test, quote(&o.find_arg()))).collect();
+            match alternatives.len() {
+                0 => {}
+                1 => tests.push(alternatives[0].clone()),
+                _ => tests.push(format!("\\( {} \\)", alternatives.join(" -o "))),
+            }
+        };
+        one_of(&self.only_users, "-user");
+        one_of(&self.only_groups, "-group");
+        for owner in &self.exclude_users {
+            tests.push(format!("! -user {}", quote(&owner.find_arg())));
+        }
+        for owner in &self.exclude_groups {
+            tests.push(format!("! -group {}", quote(&owner.find_arg())));
+        }
+        tests.join(" ")
+    }
+}
+
+static FILTER: Mutex<OwnerFilter> = Mutex::new(OwnerFilter {
+    only_users: Vec::new(),
+    only_groups: Vec::new(),
+    exclude_users: Vec::new(),
+    exclude_groups: Vec::new(),
+});
+
+static FILTERED: AtomicUsize = AtomicUsize::new(0);
+
+/// Filter the files of every scan from now on.
+pub fn set(filter: OwnerFilter) {
+    if let Ok(mut current) = FILTER.lock() {
+        *current = filter;
+    }
+}
+
+/// The rules scans apply now.
+pub fn current() -> OwnerFilter {
+    FILTER.lock().map(|f| f.clone()).unwrap_or_default()
+}
+
+/// Count `count` more files left out by owner.
+pub fn note_filtered(count: usize) {
+    FILTERED.fetch_add(count, Ordering::SeqCst);
+}
+
+/// Files left out by owner since the last `reset`.
+pub fn filtered() -> usize {
+    FILTERED.load(Ordering::SeqCst)
+}
+
+/// Forget the last run's count, e.g. when a new transfer starts.
+pub fn reset() {
+    FILTERED.store(0, Ordering::SeqCst);
+}
+
+/// A user given by name or uid.
+pub fn resolve_user(text: &str) -> Result<Owner, String> {
+    resolve(text, "passwd", "user")
+}
+
+/// A group given by name or gid.
+pub fn resolve_group(text: &str) -> Result<Owner, String> {
+    resolve(text, "group", "group")
+}
+
+fn resolve(text: &str, database: &str, what: &str) -> Result<Owner, String> {
+    let text = text.trim();
+    if let Ok(id) = text.parse::<u32>() {
+        return Ok(Owner { id, name: None });
+    }
+    lookup(database, text)
+        .map(|id| Owner { id, name: Some(text.to_string()) })
+        .ok_or_else(|| format!("No {} named '{}' on this machine", what, text))
+}
+
+/// The id of `name` in `database` (`passwd` or `group`): from `getent`,
+/// which sees directory services too, else from the file in /etc.
+fn lookup(database: &str, name: &str) -> Option<u32> {
+    if name.is_empty() || name.contains(':') {
+        return None;
+    }
+    let from_getent = Command::new("getent")
+        .args([database, name])
+        .printed()
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| id_in(&String::from_utf8_lossy(&o.stdout), name));
+    from_getent.or_else(|| id_in(&fs::read_to_string(format!("/etc/{}", database)).ok()?, name))
+}
+
+/// The id (third field) of `name` in `passwd`/`group`-format text.
+fn id_in(text: &str, name: &str) -> Option<u32> {
+    text.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        (fields.next()? == name).then_some(())?;
+        fields.nth(1)?.trim().parse().ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn by_name(id: u32, name: &str) -> Owner {
+        Owner { id, name: Some(name.to_string()) }
+    }
+
+    fn by_id(id: u32) -> Owner {
+        Owner { id, name: None }
+    }
+
+    #[test]
+    fn only_and_exclude_rules_combine() {
+        let filter = OwnerFilter {
+            only_groups: vec![by_name(2000, "design"), by_id(2001)],
+            exclude_users: vec![by_name(1002, "bob")],
+            ..OwnerFilter::default()
+        };
+        assert!(filter.keeps(1001, 2000, None, None));
+        assert!(filter.keeps(1001, 2001, None, None));
+        assert!(!filter.keeps(1001, 3000, None, None));
+        assert!(!filter.keeps(1002, 2000, None, None));
+        // Names win where the listing has them: ids differ between hosts.
+        assert!(filter.keeps(1001, 5000, Some("ann"), Some("design")));
+        assert!(!filter.keeps(1001, 2000, Some("ann"), Some("ops")));
+        assert!(!filter.keeps(7, 5000, Some("bob"), Some("design")));
+        assert!(OwnerFilter::default().keeps(0, 0, None, None));
+        assert!(OwnerFilter::default().is_empty());
+    }
+
+    #[test]
+    fn find_tests_follow_the_rules() {
+        let quote = |s: &str| format!("'{}'", s);
+        let filter = OwnerFilter {
+            only_users: vec![by_name(1001, "ann"), by_id(1002)],
+            only_groups: vec![by_name(2000, "design")],
+            exclude_groups: vec![by_id(3000)],
+            ..OwnerFilter::default()
+        };
+        assert_eq!(
+            filter.find_tests(quote),
+            "\\( -user 'ann' -o -user '1002' \\) -group 'design' ! -group '3000'"
+        );
+        assert_eq!(OwnerFilter::default().find_tests(quote), "");
+    }
+
+    #[test]
+    fn names_come_from_the_databases() {
+        let passwd = "root:x:0:0:root:/root:/bin/bash\nann:x:1001:1001::/home/ann:/bin/sh\n";
+        assert_eq!(id_in(passwd, "ann"), Some(1001));
+        assert_eq!(id_in(passwd, "an"), None);
+        assert_eq!(id_in("design:x:2000:ann,bob\n", "design"), Some(2000));
+        assert_eq!(resolve_user("1234"), Ok(by_id(1234)));
+        assert_eq!(resolve_user("root").map(|o| o.id), Ok(0));
+        assert!(resolve_group("no-such-group-kosmokopy").unwrap_err().contains("No group named"));
+    }
+}
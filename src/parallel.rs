@@ -0,0 +1,178 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+// ── Parallel transfers ─────────────────────────────────────────────────
+//
+// scp starts a process and an ssh round trip for every file, and a local
+// copy of many small files waits on the disk for each in turn, so the
+// standard workers keep several files in flight: four by default, or as
+// many as `--parallel N` or the "Files at once" spin button say (1 goes
+// one at a time).
+//
+// Only the copy of a file and its verification run on the pool's
+// threads.  The worker thread that owns the run still takes the files in
+// plan order, decides each conflict and keeps the counts and lists,
+// taking back each file's outcome as it ends, so the totals come out as
+// they would one at a time and no two files are given the same "(1)"
+// name.  A file bound for a path that a file in flight is writing waits
+// for that one to end first.
+
+/// Files in flight at once unless told otherwise.
+pub const DEFAULT_WORKERS: usize = 4;
+/// The most files in flight `--parallel` allows.
+pub const MAX_WORKERS: usize = 32;
+
+/// The pool as the run's own thread sees it: files handed out, and their
+/// outcomes coming back in the order they end.
+pub struct Lanes<J, R> {
+    jobs: mpsc::Sender<J>,
+    results: mpsc::Receiver<R>,
+    capacity: usize,
+    busy: usize,
+}
+
+impl<J, R> Lanes<J, R> {
+    /// Whether every thread has a file.
+    pub fn is_full(&self) -> bool {
+        self.busy >= self.capacity
+    }
+
+    /// Hand `job` to the next free thread.
+    pub fn start(&mut self, job: J) {
+        if self.jobs.send(job).is_ok() {
+            self.busy += 1;
+        }
+    }
+
+    /// The outcome of the next job to end, waiting for it; `None` when
+    /// none is in flight.
+    pub fn wait(&mut self) -> Option<R> {
+        if self.busy == 0 {
+            return None;
+        }
+        let result = self.results.recv().ok()?;
+        self.busy -= 1;
+        Some(result)
+    }
+}
+
+/// Run `coordinate` on this thread with lanes that run `work` on up to
+/// `workers` threads of their own.  Jobs still in flight when it returns
+/// are finished before this does.
+pub fn run<J: Send, R: Send, T>(
+    workers: usize,
+    work: impl Fn(J) -> R + Sync,
+    coordinate: impl FnOnce(&mut Lanes<J, R>) -> T,
+) -> T {
+    let workers = workers.max(1);
+    let (job_tx, job_rx) = mpsc::channel::<J>();
+    let (result_tx, results) = mpsc::channel::<R>();
+    let job_rx = Mutex::new(job_rx);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let (job_rx, work, result_tx) = (&job_rx, &work, result_tx.clone());
+            scope.spawn(move || loop {
+                // The lock is held only while waiting for the next job.
+                let job = match job_rx.lock() {
+                    Ok(rx) => rx.recv(),
+                    Err(_) => return,
+                };
+                let Ok(job) = job else { return };
+                if result_tx.send(work(job)).is_err() {
+                    return;
+                }
+            });
+        }
+        drop(result_tx);
+        let mut lanes = Lanes { jobs: job_tx, results, capacity: workers, busy: 0 };
+        coordinate(&mut lanes)
+        // Dropping the lanes closes the job channel, and the threads end.
+    })
+}
+
+/// How far through the plan a run is when files end out of order.  The
+/// progress bar counts the files before the first one still to end, so it
+/// never claims bytes that are not in; the bytes it shows for the file in
+/// flight come from that first one.
+#[derive(Debug)]
+pub struct Front {
+    ended: Mutex<Vec<bool>>,
+    count: AtomicUsize,
+}
+
+impl Front {
+    pub fn new(total: usize) -> Front {
+        Front { ended: Mutex::new(vec![false; total]), count: AtomicUsize::new(0) }
+    }
+
+    /// Note that the file at `index` in the plan has ended; how many files
+    /// from the start of the plan have.
+    pub fn end(&self, index: usize) -> usize {
+        let mut ended = self.ended.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(slot) = ended.get_mut(index) {
+            *slot = true;
+        }
+        let mut count = self.count.load(Ordering::SeqCst);
+        while ended.get(count) == Some(&true) {
+            count += 1;
+        }
+        self.count.store(count, Ordering::SeqCst);
+        count
+    }
+
+    /// Whether the file at `index` is the first still to end.
+    pub fn is_first(&self, index: usize) -> bool {
+        self.count.load(Ordering::SeqCst) == index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn lanes_run_jobs_on_the_pool_and_give_every_outcome_back() {
+        let mut seen: Vec<(usize, usize)> = run(
+            3,
+            |n: usize| {
+                // Later jobs end first.
+                thread::sleep(Duration::from_millis(5 * (8 - n) as u64));
+                (n, n * n)
+            },
+            |lanes| {
+                let mut seen = Vec::new();
+                for n in 0..8 {
+                    if lanes.is_full() {
+                        seen.extend(lanes.wait());
+                    }
+                    lanes.start(n);
+                }
+                while let Some(outcome) = lanes.wait() {
+                    seen.push(outcome);
+                }
+                seen
+            },
+        );
+        seen.sort();
+        assert_eq!(seen, (0..8).map(|n| (n, n * n)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn the_front_waits_for_the_first_file() {
+        let front = Front::new(4);
+        assert!(front.is_first(0));
+        assert_eq!(front.end(2), 0);
+        assert_eq!(front.end(1), 0);
+        assert!(front.is_first(0));
+        assert_eq!(front.end(0), 3);
+        assert!(front.is_first(3));
+        assert_eq!(front.end(3), 4);
+        assert_eq!(front.end(9), 4);
+    }
+}
@@ -0,0 +1,139 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// ── Where Kosmokopy keeps its files ────────────────────────────────────
+//
+// Everything Kosmokopy writes outside a transfer (settings, remembered
+// state, caches, logs) lives in one of the XDG base directories below.
+// Nothing else should build such paths itself: with `KOSMOKOPY_HOME` set,
+// all four directories move under that one folder, which is how the tests
+// keep the real home directory untouched.
+
+/// Overrides every directory with a subfolder of its value.
+pub const HOME_ENV: &str = "KOSMOKOPY_HOME";
+
+const APP_DIR: &str = "kosmokopy";
+
+/// One of the XDG base directories.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dir {
+    /// Settings the user chose (`$XDG_CONFIG_HOME/kosmokopy`).
+    Config,
+    /// State kept between runs, e.g. history and logs (`$XDG_STATE_HOME`).
+    State,
+    /// Data that can be rebuilt at any time (`$XDG_CACHE_HOME`).
+    Cache,
+    /// Files the user would want to keep (`$XDG_DATA_HOME`).
+    Data,
+}
+
+impl Dir {
+    pub const ALL: [Dir; 4] = [Dir::Config, Dir::State, Dir::Cache, Dir::Data];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Dir::Config => "config",
+            Dir::State => "state",
+            Dir::Cache => "cache",
+            Dir::Data => "data",
+        }
+    }
+
+    fn xdg_var(self) -> &'static str {
+        match self {
+            Dir::Config => "XDG_CONFIG_HOME",
+            Dir::State => "XDG_STATE_HOME",
+            Dir::Cache => "XDG_CACHE_HOME",
+            Dir::Data => "XDG_DATA_HOME",
+        }
+    }
+
+    /// The spec's default, relative to `$HOME`.
+    fn home_default(self) -> &'static str {
+        match self {
+            Dir::Config => ".config",
+            Dir::State => ".local/state",
+            Dir::Cache => ".cache",
+            Dir::Data => ".local/share",
+        }
+    }
+}
+
+/// Resolve `dir` using `var` to read the environment.  `None` when neither
+/// the override, the XDG variable nor `$HOME` gives an absolute path.
+fn resolve(dir: Dir, var: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    // Relative values are ignored, as the XDG spec asks.
+    let absolute = |name: &str| var(name).map(PathBuf::from).filter(|p| p.is_absolute());
+    if let Some(home) = absolute(HOME_ENV) {
+        return Some(home.join(dir.name()));
+    }
+    if let Some(base) = absolute(dir.xdg_var()) {
+        return Some(base.join(APP_DIR));
+    }
+    absolute("HOME").map(|home| home.join(dir.home_default()).join(APP_DIR))
+}
+
+/// Where `dir` is, without creating it.
+pub fn dir(dir: Dir) -> Option<PathBuf> {
+    resolve(dir, |name| std::env::var_os(name))
+}
+
+/// Where `dir` is, creating it (and its parents) if missing.
+pub fn ensure(dir: Dir) -> io::Result<PathBuf> {
+    let path = self::dir(dir).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no {} directory: set HOME or {}", dir.name(), HOME_ENV),
+        )
+    })?;
+    fs::create_dir_all(&path)?;
+    Ok(path)
+}
+
+/// The path of `name` inside `dir`, creating `dir` if missing.
+pub fn file(dir: Dir, name: &str) -> io::Result<PathBuf> {
+    Ok(ensure(dir)?.join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        let map: HashMap<&str, &str> = vars.iter().copied().collect();
+        move |name| map.get(name).map(OsString::from)
+    }
+
+    #[test]
+    fn home_defaults_follow_the_spec() {
+        let vars = [("HOME", "/home/ann")];
+        assert_eq!(resolve(Dir::Config, env(&vars)), Some("/home/ann/.config/kosmokopy".into()));
+        assert_eq!(resolve(Dir::State, env(&vars)), Some("/home/ann/.local/state/kosmokopy".into()));
+        assert_eq!(resolve(Dir::Cache, env(&vars)), Some("/home/ann/.cache/kosmokopy".into()));
+        assert_eq!(resolve(Dir::Data, env(&vars)), Some("/home/ann/.local/share/kosmokopy".into()));
+    }
+
+    #[test]
+    fn xdg_variables_win_over_home_and_relative_ones_are_ignored() {
+        let vars = [("HOME", "/home/ann"), ("XDG_CONFIG_HOME", "/cfg"), ("XDG_CACHE_HOME", "rel")];
+        assert_eq!(resolve(Dir::Config, env(&vars)), Some("/cfg/kosmokopy".into()));
+        assert_eq!(resolve(Dir::Cache, env(&vars)), Some("/home/ann/.cache/kosmokopy".into()));
+        assert_eq!(resolve(Dir::Data, env(&[])), None);
+    }
+
+    #[test]
+    fn override_moves_every_directory() {
+        let vars = [("HOME", "/home/ann"), ("XDG_STATE_HOME", "/st"), (HOME_ENV, "/tmp/kk")];
+        for d in Dir::ALL {
+            let path = resolve(d, env(&vars)).unwrap();
+            assert_eq!(path, PathBuf::from("/tmp/kk").join(d.name()));
+        }
+    }
+}
@@ -0,0 +1,336 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+// ── Checking exclusion patterns as they are entered ────────────────────
+//
+// Most exclusions match one folder or file name anywhere in the source, so
+// a pattern that can never match a name (a trailing slash, a leading `./`)
+// used to sit in the list doing nothing.  Patterns from the GUI and from
+// `--exclude`/`--include` are tidied here before they are stored, and the
+// ones that cannot be tidied are refused with a reason.
+//
+// An exclusion with a slash inside, such as `docs/generated/*`, is a path
+// pattern instead: it is matched against the path below the source root,
+// so it can leave out one `generated` folder without every other one.  A
+// leading slash only says so again; `**` stands for any number of folders.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// What a stored pattern matches; its prefix in the pattern list.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PatternKind {
+    /// `/name`: every folder with exactly this name.
+    Dir,
+    /// `name`: every file with exactly this name.
+    File,
+    /// `~/pattern`: folders whose name matches the wildcard.
+    DirWildcard,
+    /// `~pattern`: files whose name matches the wildcard.
+    FileWildcard,
+    /// `+pattern`: only files whose name matches are transferred.
+    Include,
+    /// `^pattern`: folders and files whose path below the source matches.
+    Path,
+}
+
+impl PatternKind {
+    /// Split a stored (or `--exclude`) pattern into its kind and text.
+    pub fn decode(pattern: &str) -> (PatternKind, &str) {
+        if let Some(rest) = pattern.strip_prefix('+') {
+            (PatternKind::Include, rest)
+        } else if let Some(rest) = pattern.strip_prefix('^') {
+            (PatternKind::Path, rest)
+        } else if let Some(rest) = pattern.strip_prefix("~/") {
+            (PatternKind::DirWildcard, rest)
+        } else if let Some(rest) = pattern.strip_prefix('~') {
+            (PatternKind::FileWildcard, rest)
+        } else if let Some(rest) = pattern.strip_prefix('/') {
+            (PatternKind::Dir, rest)
+        } else {
+            (PatternKind::File, pattern)
+        }
+    }
+
+    fn encode(self, name: &str) -> String {
+        match self {
+            PatternKind::Dir => format!("/{}", name),
+            PatternKind::File => name.to_string(),
+            PatternKind::DirWildcard => format!("~/{}", name),
+            PatternKind::FileWildcard => format!("~{}", name),
+            PatternKind::Include => format!("+{}", name),
+            PatternKind::Path => format!("^{}", name),
+        }
+    }
+
+    /// The same kind for folders: a trailing slash asks for a folder.
+    fn as_dir(self) -> Option<PatternKind> {
+        match self {
+            PatternKind::Dir | PatternKind::File => Some(PatternKind::Dir),
+            PatternKind::DirWildcard | PatternKind::FileWildcard => Some(PatternKind::DirWildcard),
+            PatternKind::Path => Some(PatternKind::Path),
+            PatternKind::Include => None,
+        }
+    }
+}
+
+/// Tidy `text` typed as a pattern of `kind` into its stored form, or say
+/// why it cannot match anything.
+pub fn normalize(kind: PatternKind, text: &str) -> Result<String, String> {
+    tidy(kind, text.trim(), text.trim())
+}
+
+/// Tidy a stored-form pattern, such as the value of `--exclude`.
+pub fn normalize_stored(pattern: &str) -> Result<String, String> {
+    let (kind, text) = PatternKind::decode(pattern.trim());
+    tidy(kind, text, pattern.trim())
+}
+
+/// `typed` is the pattern as the user wrote it, for messages.
+fn tidy(kind: PatternKind, text: &str, typed: &str) -> Result<String, String> {
+    let mut name = text;
+    while let Some(rest) = name.strip_prefix("./") {
+        name = rest;
+    }
+    let mut kind = kind;
+    if name.len() > 1 && name.ends_with('/') {
+        name = name.trim_end_matches('/');
+        kind = kind
+            .as_dir()
+            .ok_or_else(|| format!("'{}' names a folder, but include patterns match file names", typed))?;
+    }
+    // A lone leading slash is harmless ("/tmp*"); more make it a path.
+    if !name.trim_start_matches('/').contains('/') {
+        name = name.trim_start_matches('/');
+    }
+    if name.is_empty() || name == "." {
+        return Err(if typed.is_empty() {
+            "Pattern is empty".to_string()
+        } else {
+            format!("'{}' does not name a folder or file", typed)
+        });
+    }
+    if name.contains('/') || kind == PatternKind::Path {
+        return tidy_path(kind, name, typed);
+    }
+    Ok(kind.encode(name))
+}
+
+/// Tidy a path pattern: `name` has a slash inside, or came as `^…`.
+fn tidy_path(kind: PatternKind, name: &str, typed: &str) -> Result<String, String> {
+    if kind == PatternKind::Include {
+        let last = name.rsplit('/').next().unwrap_or(name);
+        return Err(format!(
+            "'{}' is a path, but include patterns match a file name anywhere in the source; use '{}' instead",
+            typed, last
+        ));
+    }
+    if name.starts_with('~') {
+        return Err(format!("'{}' is a path in your home folder, but path patterns are relative to the source", typed));
+    }
+    let parts: Vec<&str> = name.split('/').filter(|part| !part.is_empty() && *part != ".").collect();
+    if parts.contains(&"..") {
+        return Err(format!("'{}' goes up with '..', but path patterns are relative to the source", typed));
+    }
+    if parts.is_empty() {
+        return Err(format!("'{}' does not name a folder or file", typed));
+    }
+    Ok(PatternKind::Path.encode(&parts.join("/")))
+}
+
+/// Add `pattern` to `list` unless it is already there; returns whether it
+/// was added.
+pub fn add_unique(list: &mut Vec<String>, pattern: String) -> bool {
+    if list.contains(&pattern) {
+        return false;
+    }
+    list.push(pattern);
+    true
+}
+
+// ── Pattern files ──────────────────────────────────────────────────────
+//
+// A long list of junk patterns lives better in a file than retyped in the
+// window: `--exclude-from` and the window's Load Patterns read one, Save
+// Patterns writes the list out.  Each line holds one pattern written as
+// for `--exclude`, prefix and all, except that a bare name with `*` or `?`
+// in it is taken as the wildcard it looks like (`*.tmp` as `~*.tmp`), as
+// junk lists are written; blank lines and lines starting with `#` are
+// skipped.  Every pattern is tidied as if typed, and one that can't match
+// refuses the whole file, naming its line.
+
+/// What a saved pattern file starts with, so it explains itself.
+const FILE_HEADER: &str = "\
+# Kosmokopy exclusion patterns: one per line, blank lines and # comments skipped.
+#   name       files with exactly this name; *.tmp is taken as ~*.tmp
+#   /name      folders with exactly this name
+#   ~pattern   files whose name matches the wildcard (* and ?)
+#   ~/pattern  folders whose name matches the wildcard
+#   a/b/*      a path below the source (** for any folders)
+#   +pattern   only transfer files whose name matches
+";
+
+/// The patterns in `text`, the contents of a pattern file, tidied and
+/// without repeats, or why line so-and-so can't be used.
+pub fn parse_file(text: &str) -> Result<Vec<String>, String> {
+    let mut patterns = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let checked = match PatternKind::decode(line) {
+            (PatternKind::File, name) if name.contains(['*', '?']) && !name.contains('/') => {
+                normalize(PatternKind::FileWildcard, name)
+            }
+            _ => normalize_stored(line),
+        };
+        let pattern = checked.map_err(|e| format!("line {}: {}", n + 1, e))?;
+        add_unique(&mut patterns, pattern);
+    }
+    Ok(patterns)
+}
+
+/// Read the pattern file at `path`.
+pub fn read_file(path: &Path) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse_file(&text).map_err(|e| format!("{} {}", path.display(), e))
+}
+
+/// `patterns` as a pattern file that `read_file` gives back.
+pub fn file_text(patterns: &[String]) -> String {
+    let mut text = FILE_HEADER.to_string();
+    for pattern in patterns {
+        text.push_str(pattern);
+        text.push('\n');
+    }
+    text
+}
+
+// ── What folder exclusions hid ─────────────────────────────────────────
+//
+// `excluded_dirs` counts each excluded folder once, by its path below the
+// source: two folders both called `cache` count as two, and a folder
+// inside one already excluded is not counted again.  A local scan prunes
+// an excluded folder without reading it, so what it held is not known and
+// only the folder is counted.  A remote listing names every file before
+// the patterns are applied, so there the files under excluded folders are
+// counted too (and only folders holding at least one file are seen).
+
+static IN_EXCLUDED_DIRS: Mutex<Option<usize>> = Mutex::new(None);
+
+/// Record files a remote listing left out because a folder above them was
+/// excluded.
+pub fn note_in_excluded_dirs(count: usize) {
+    if let Ok(mut total) = IN_EXCLUDED_DIRS.lock() {
+        *total = Some(total.unwrap_or(0) + count);
+    }
+}
+
+/// Files left out under excluded folders since the last `reset_counts`,
+/// or `None` when no remote listing counted them.
+pub fn in_excluded_dirs() -> Option<usize> {
+    IN_EXCLUDED_DIRS.lock().ok().and_then(|total| *total)
+}
+
+/// Forget the last run's count, e.g. when a new transfer starts.
+pub fn reset_counts() {
+    if let Ok(mut total) = IN_EXCLUDED_DIRS.lock() {
+        *total = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_names_are_kept() {
+        assert_eq!(normalize(PatternKind::Dir, "target"), Ok("/target".to_string()));
+        assert_eq!(normalize(PatternKind::File, "notes.txt"), Ok("notes.txt".to_string()));
+        assert_eq!(normalize(PatternKind::DirWildcard, "tmp*"), Ok("~/tmp*".to_string()));
+        assert_eq!(normalize(PatternKind::FileWildcard, "*.log"), Ok("~*.log".to_string()));
+        assert_eq!(normalize(PatternKind::Include, "*.jpg"), Ok("+*.jpg".to_string()));
+    }
+
+    #[test]
+    fn whitespace_dot_slash_and_trailing_slashes_are_stripped() {
+        assert_eq!(normalize(PatternKind::FileWildcard, "  *.log \n"), Ok("~*.log".to_string()));
+        assert_eq!(normalize(PatternKind::Dir, "./build"), Ok("/build".to_string()));
+        assert_eq!(normalize(PatternKind::Dir, "././build//"), Ok("/build".to_string()));
+        assert_eq!(normalize(PatternKind::DirWildcard, "/tmp*"), Ok("~/tmp*".to_string()));
+        assert_eq!(normalize(PatternKind::DirWildcard, "cache*/"), Ok("~/cache*".to_string()));
+    }
+
+    #[test]
+    fn trailing_slash_makes_a_folder_pattern() {
+        assert_eq!(normalize(PatternKind::File, "node_modules/"), Ok("/node_modules".to_string()));
+        assert_eq!(normalize(PatternKind::FileWildcard, "build-*/"), Ok("~/build-*".to_string()));
+        assert!(normalize(PatternKind::Include, "photos/").unwrap_err().contains("include patterns match file names"));
+    }
+
+    #[test]
+    fn empty_patterns_are_refused() {
+        assert_eq!(normalize(PatternKind::File, ""), Err("Pattern is empty".to_string()));
+        assert_eq!(normalize(PatternKind::Dir, "   "), Err("Pattern is empty".to_string()));
+        assert!(normalize(PatternKind::Dir, "/").is_err());
+        assert!(normalize(PatternKind::Dir, "./").is_err());
+        assert!(normalize(PatternKind::File, ".").is_err());
+    }
+
+    #[test]
+    fn paths_become_path_patterns() {
+        assert_eq!(normalize(PatternKind::FileWildcard, "docs/generated/*"), Ok("^docs/generated/*".to_string()));
+        assert_eq!(normalize(PatternKind::DirWildcard, "/docs//generated/"), Ok("^docs/generated".to_string()));
+        assert_eq!(normalize(PatternKind::Dir, "./src/./vendor"), Ok("^src/vendor".to_string()));
+        assert_eq!(normalize(PatternKind::File, "**/node_modules/**"), Ok("^**/node_modules/**".to_string()));
+        assert_eq!(normalize(PatternKind::Path, "build"), Ok("^build".to_string()));
+    }
+
+    #[test]
+    fn path_patterns_that_cannot_match_are_refused() {
+        let err = normalize(PatternKind::Include, "logs/*.log").unwrap_err();
+        assert!(err.starts_with("'logs/*.log' is a path"), "{}", err);
+        assert!(err.ends_with("use '*.log' instead"));
+        assert!(normalize(PatternKind::Dir, "~/Documents").unwrap_err().contains("home folder"));
+        assert!(normalize(PatternKind::File, "docs/../secret").unwrap_err().contains("'..'"));
+        assert!(normalize(PatternKind::Path, "/").is_err());
+    }
+
+    #[test]
+    fn stored_patterns_from_the_command_line() {
+        assert_eq!(normalize_stored("/target/"), Ok("/target".to_string()));
+        assert_eq!(normalize_stored("build/"), Ok("/build".to_string()));
+        assert_eq!(normalize_stored("~/cache*"), Ok("~/cache*".to_string()));
+        assert_eq!(normalize_stored("~*.tmp"), Ok("~*.tmp".to_string()));
+        assert_eq!(normalize_stored("+*.png"), Ok("+*.png".to_string()));
+        assert_eq!(normalize_stored("/docs/*.md"), Ok("^docs/*.md".to_string()));
+        assert_eq!(normalize_stored("^docs/*.md"), Ok("^docs/*.md".to_string()));
+        assert_eq!(normalize_stored("^docs"), Ok("^docs".to_string()));
+        assert!(normalize_stored("+photos/*.jpg").is_err());
+        assert!(normalize_stored("").is_err());
+    }
+
+    #[test]
+    fn duplicates_merge_after_normalizing() {
+        let mut list = Vec::new();
+        for typed in ["target", "target/", "./target", "/target"] {
+            let pattern = normalize(PatternKind::Dir, typed).unwrap();
+            add_unique(&mut list, pattern);
+        }
+        assert_eq!(list, ["/target"]);
+        assert!(!add_unique(&mut list, "/target".to_string()));
+        assert!(add_unique(&mut list, "target".to_string()));
+    }
+
+    #[test]
+    fn pattern_files_round_trip() {
+        let text = "# junk\n.DS_Store\n\n  *.tmp  \nnode_modules/\n~/cache*\ndocs/drafts/*\n+*.jpg\n.DS_Store\n";
+        let patterns = parse_file(text).unwrap();
+        assert_eq!(patterns, [".DS_Store", "~*.tmp", "/node_modules", "~/cache*", "^docs/drafts/*", "+*.jpg"]);
+        assert_eq!(parse_file(&file_text(&patterns)).unwrap(), patterns);
+        assert_eq!(parse_file("a\n# b\n../up/x\n").unwrap_err(), "line 3: '../up/x' goes up with '..', but path patterns are relative to the source");
+    }
+}
@@ -0,0 +1,235 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+// ── Unwritable destination folders ─────────────────────────────────────
+//
+// A destination folder the user cannot write to (a subtree owned by root,
+// say) used to surface only when the copy reached it, possibly hours in.
+// Once a run's plan is known, the existing folders that will receive files
+// are checked first, so the run can stop, leave those subtrees out, or go
+// ahead anyway.  Folders the run will create are not checked themselves:
+// whether they can be created depends on the deepest one that exists.
+
+/// What to do about unwritable destination folders.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnUnwritable {
+    /// Copy nothing.
+    Abort,
+    /// Leave out the files bound for them, reporting them as skipped.
+    Skip,
+    /// Copy anyway; those files fail one by one.
+    Continue,
+}
+
+impl OnUnwritable {
+    pub fn parse(word: &str) -> Option<OnUnwritable> {
+        match word {
+            "abort" => Some(OnUnwritable::Abort),
+            "skip" => Some(OnUnwritable::Skip),
+            "continue" => Some(OnUnwritable::Continue),
+            _ => None,
+        }
+    }
+}
+
+/// An existing destination folder that cannot be written to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnwritableDir {
+    pub dir: String,
+    /// Planned files that would land in it or in folders created below it.
+    pub files: usize,
+}
+
+impl UnwritableDir {
+    /// e.g. `/backup/photos/raw (12 files)`.
+    pub fn describe(&self) -> String {
+        format!("{} ({} file{})", self.dir, self.files, if self.files == 1 { "" } else { "s" })
+    }
+}
+
+/// Result of a preflight: the unwritable folders, sorted, and for each
+/// planned file bound for one of them, its index and that folder.
+#[derive(Debug, Default, PartialEq)]
+pub struct Preflight {
+    pub unwritable: Vec<UnwritableDir>,
+    pub blocked: HashMap<usize, String>,
+}
+
+/// Check the destination folder of each planned file.  `verdicts` is
+/// asked once with the distinct folders and returns, for each one that
+/// cannot be written, the existing folder to blame (the folder itself, or
+/// the deepest ancestor that exists when the run would create it).
+pub fn check(file_dirs: &[String], verdicts: impl FnOnce(&[String]) -> HashMap<String, String>) -> Preflight {
+    let mut distinct: Vec<String> = file_dirs.iter().cloned().collect::<HashSet<_>>().into_iter().collect();
+    distinct.sort();
+    let verdicts = verdicts(&distinct);
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut blocked = HashMap::new();
+    for (i, dir) in file_dirs.iter().enumerate() {
+        if let Some(blame) = verdicts.get(dir) {
+            *counts.entry(blame.clone()).or_insert(0) += 1;
+            blocked.insert(i, blame.clone());
+        }
+    }
+    Preflight {
+        unwritable: counts.into_iter().map(|(dir, files)| UnwritableDir { dir, files }).collect(),
+        blocked,
+    }
+}
+
+/// The deepest of `dir` and its ancestors that exists.
+pub fn deepest_existing(dir: &Path) -> Option<&Path> {
+    dir.ancestors().find(|d| d.is_dir())
+}
+
+/// Whether files can be created in the local folder `dir`.
+///
+/// Probes by creating (and removing) a scratch file, as the read-only
+/// source check does, so ACLs and read-only mounts count as well as
+/// ownership and mode bits.
+pub fn local_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!("{}{}", crate::stale::PROBE_PREFIX, std::process::id()));
+    match std::fs::OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            true
+        }
+        // Someone else's leftover probe still proves the folder writable.
+        Err(e) => e.kind() == std::io::ErrorKind::AlreadyExists,
+    }
+}
+
+/// Verdicts for local folders.
+pub fn local_verdicts(dirs: &[String]) -> HashMap<String, String> {
+    let mut blame_writable: HashMap<&Path, bool> = HashMap::new();
+    let mut out = HashMap::new();
+    for dir in dirs {
+        let Some(existing) = deepest_existing(Path::new(dir)) else { continue };
+        let writable = *blame_writable.entry(existing).or_insert_with(|| local_writable(existing));
+        if !writable {
+            out.insert(dir.clone(), existing.display().to_string());
+        }
+    }
+    out
+}
+
+/// Shell loop printing `<dir>\t<existing folder>\0` for each of the
+/// quoted `dirs` whose deepest existing folder is not writable.
+pub fn remote_script(quoted_dirs: &[OsString]) -> OsString {
+    let mut script = OsString::from("for d in");
+    for dir in quoted_dirs {
+        script.push(" ");
+        script.push(dir);
+    }
+    script.push(
+        "; do p=\"$d\"; while [ ! -d \"$p\" ]; do p=$(dirname \"$p\"); done; \
+         [ -w \"$p\" ] || printf '%s\\t%s\\0' \"$d\" \"$p\"; done; true",
+    );
+    script
+}
+
+/// Parse the output of `remote_script`, keeping the paths' bytes.
+pub fn parse_remote_verdicts(output: &[u8]) -> HashMap<PathBuf, PathBuf> {
+    output
+        .split(|&b| b == 0)
+        .filter_map(|record| {
+            let tab = record.iter().rposition(|&b| b == b'\t')?;
+            let path = |bytes| PathBuf::from(OsStr::from_bytes(bytes));
+            Some((path(&record[..tab]), path(&record[tab + 1..])))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dirs(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn counts_files_per_blamed_folder() {
+        let plan = dirs(&[
+            "/dst/photos",
+            "/dst/photos/raw",
+            "/dst/photos/raw",
+            "/dst/photos/raw/2024",
+            "/dst/docs",
+        ]);
+        let mut asked = Vec::new();
+        let result = check(&plan, |distinct| {
+            asked = distinct.to_vec();
+            // raw exists but is root-owned; raw/2024 would be created in it.
+            HashMap::from([
+                ("/dst/photos/raw".to_string(), "/dst/photos/raw".to_string()),
+                ("/dst/photos/raw/2024".to_string(), "/dst/photos/raw".to_string()),
+            ])
+        });
+        assert_eq!(asked, ["/dst/docs", "/dst/photos", "/dst/photos/raw", "/dst/photos/raw/2024"]);
+        assert_eq!(result.unwritable, [UnwritableDir { dir: "/dst/photos/raw".to_string(), files: 3 }]);
+        let mut blocked: Vec<usize> = result.blocked.keys().copied().collect();
+        blocked.sort();
+        assert_eq!(blocked, [1, 2, 3]);
+        assert_eq!(result.blocked[&3], "/dst/photos/raw");
+        assert_eq!(result.unwritable[0].describe(), "/dst/photos/raw (3 files)");
+    }
+
+    #[test]
+    fn all_writable_is_empty() {
+        let result = check(&dirs(&["/a", "/b"]), |_| HashMap::new());
+        assert_eq!(result, Preflight::default());
+    }
+
+    #[test]
+    fn policies_parse() {
+        assert_eq!(OnUnwritable::parse("abort"), Some(OnUnwritable::Abort));
+        assert_eq!(OnUnwritable::parse("skip"), Some(OnUnwritable::Skip));
+        assert_eq!(OnUnwritable::parse("continue"), Some(OnUnwritable::Continue));
+        assert_eq!(OnUnwritable::parse("ignore"), None);
+    }
+
+    #[test]
+    fn remote_output_is_parsed() {
+        let out = b"/dst/a b/c\t/dst/a b\0/dst/x\t/dst/x\0/dst/caf\xe9\t/dst\0";
+        let verdicts = parse_remote_verdicts(out);
+        assert_eq!(verdicts[Path::new("/dst/a b/c")], Path::new("/dst/a b"));
+        assert_eq!(verdicts[Path::new("/dst/x")], Path::new("/dst/x"));
+        assert_eq!(verdicts[Path::new(OsStr::from_bytes(b"/dst/caf\xe9"))], Path::new("/dst"));
+        assert_eq!(verdicts.len(), 3);
+        assert!(remote_script(&["'/dst/x'".into()]).to_str().unwrap().starts_with("for d in '/dst/x'; do"));
+    }
+
+    #[test]
+    fn local_folders_are_probed() {
+        use std::os::unix::fs::PermissionsExt;
+        let base = std::env::temp_dir().join(format!("kosmokopy-preflight-{}", std::process::id()));
+        let locked = base.join("locked");
+        std::fs::create_dir_all(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+        let open = base.display().to_string();
+        let new_below_open = base.join("new/deeper").display().to_string();
+        let inside = locked.display().to_string();
+        let new_below_locked = locked.join("new").display().to_string();
+        let verdicts = local_verdicts(&[open, new_below_open, inside.clone(), new_below_locked.clone()]);
+        // Permission bits don't stop root, so only check them as a user.
+        if !local_writable(&locked) {
+            assert_eq!(verdicts.len(), 2);
+            assert_eq!(verdicts[&inside], inside);
+            assert_eq!(verdicts[&new_below_locked], inside);
+        } else {
+            assert!(verdicts.is_empty());
+        }
+        assert_eq!(deepest_existing(&base.join("new/deeper")), Some(base.as_path()));
+
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::fs::{self, FileTimes};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// ── Preserving times and permissions ───────────────────────────────────
+//
+// A copy is a new file, so it carries the time it was written, and the
+// folders made for it the time they were made.  With "Preserve timestamps
+// & permissions" (`--preserve`) the standard local worker gives each file
+// it copies the access and modification times of its source, including
+// the copy a move falls back to across filesystems, and each folder it
+// creates the times and mode of the source folder it mirrors.  Copies
+// already keep their mode bits, and a move that renames keeps everything.
+//
+// Writing a file into a folder moves the folder's modification time on,
+// so folders are done once the run's files are in, deepest first.  When
+// `--chmod-dirs` is given it still decides their mode.
+//
+// Times or modes that cannot be set (a filesystem without them, a folder
+// owned by someone else) cost the file nothing: they are counted and
+// reported as one warning.  Like the folders a run creates, the record is
+// process-wide.
+
+/// The times to give a copy of a file or folder with metadata `meta`.
+pub fn times_of(meta: &fs::Metadata) -> io::Result<FileTimes> {
+    Ok(FileTimes::new().set_accessed(meta.accessed()?).set_modified(meta.modified()?))
+}
+
+/// What could not be kept since the last `reset`.
+#[derive(Debug, Default)]
+struct Failures {
+    count: usize,
+    first: Option<String>,
+}
+
+static FAILURES: Mutex<Failures> = Mutex::new(Failures { count: 0, first: None });
+
+/// Note that the times or mode of `path` could not be set.
+pub fn note_failure(path: &Path, e: &io::Error) {
+    let mut failures = FAILURES.lock().unwrap_or_else(|e| e.into_inner());
+    failures.count += 1;
+    failures.first.get_or_insert_with(|| format!("{}: {}", path.display(), e));
+}
+
+/// Forget the failures of the last run, e.g. when a new transfer starts.
+pub fn reset() {
+    *FAILURES.lock().unwrap_or_else(|e| e.into_inner()) = Failures::default();
+}
+
+/// The warning for what could not be kept since the last `reset`, e.g.
+/// `could not keep the times or permissions of 3 item(s), first
+/// /dst/a.txt: Operation not permitted`, leaving the record empty.
+pub fn take() -> Option<String> {
+    let failures = std::mem::take(&mut *FAILURES.lock().unwrap_or_else(|e| e.into_inner()));
+    describe(failures.count, failures.first.as_deref())
+}
+
+fn describe(count: usize, first: Option<&str>) -> Option<String> {
+    match (count, first) {
+        (0, _) | (_, None) => None,
+        (1, Some(first)) => Some(format!("could not keep the times or permissions of {}", first)),
+        (count, Some(first)) => {
+            Some(format!("could not keep the times or permissions of {} item(s), first {}", count, first))
+        }
+    }
+}
+
+/// A folder the run made, with what to give it once its files are in.
+#[derive(Debug)]
+pub struct NewDir {
+    dest: PathBuf,
+    times: FileTimes,
+    perms: fs::Permissions,
+}
+
+/// The folders above `dest_file` that do not exist yet, each paired with
+/// the folder above `src_file` at the same depth, deepest first.
+fn missing_dirs(src_file: &Path, dest_file: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let (Some(src), Some(dest)) = (src_file.parent(), dest_file.parent()) else {
+        return Vec::new();
+    };
+    src.ancestors()
+        .zip(dest.ancestors())
+        .take_while(|(_, d)| !d.as_os_str().is_empty() && !d.exists())
+        .map(|(s, d)| (s.to_path_buf(), d.to_path_buf()))
+        .collect()
+}
+
+/// The folders above `dest_file` about to be made for it, with the times
+/// and mode of the source folders they mirror.  These are read now, before
+/// a move deletes anything from them.
+pub fn new_dirs(src_file: &Path, dest_file: &Path) -> Vec<NewDir> {
+    missing_dirs(src_file, dest_file)
+        .into_iter()
+        .filter_map(|(src, dest)| {
+            let meta = fs::metadata(&src).and_then(|m| Ok((times_of(&m)?, m.permissions())));
+            match meta {
+                Ok((times, perms)) => Some(NewDir { dest, times, perms }),
+                Err(e) => {
+                    note_failure(&dest, &e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Give the folders the run made the times of their sources, and their
+/// mode too unless `keep_mode`, deepest first.
+pub fn apply_to_dirs(dirs: &[NewDir], keep_mode: bool) {
+    let mut dirs: Vec<&NewDir> = dirs.iter().collect();
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.dest.components().count()));
+    for dir in dirs {
+        let applied = fs::File::open(&dir.dest).and_then(|f| f.set_times(dir.times)).and_then(|_| match keep_mode {
+            true => Ok(()),
+            false => fs::set_permissions(&dir.dest, dir.perms.clone()),
+        });
+        if let Err(e) = applied {
+            note_failure(&dir.dest, &e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn created_folders_mirror_their_sources() {
+        let root = std::env::temp_dir().join(format!("kosmokopy_preserve_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src/photos/2025")).unwrap();
+        fs::create_dir_all(root.join("dst")).unwrap();
+        let src_file = root.join("src/photos/2025/a.jpg");
+        let dest_file = root.join("dst/photos/2025/a.jpg");
+
+        // Only the folders still to be made are paired, deepest first.
+        let dirs = missing_dirs(&src_file, &dest_file);
+        assert_eq!(
+            dirs,
+            vec![
+                (root.join("src/photos/2025"), root.join("dst/photos/2025")),
+                (root.join("src/photos"), root.join("dst/photos")),
+            ]
+        );
+
+        let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for (src, _) in &dirs {
+            fs::File::open(src).unwrap().set_times(FileTimes::new().set_modified(then)).unwrap();
+        }
+        fs::set_permissions(root.join("src/photos"), fs::Permissions::from_mode(0o750)).unwrap();
+
+        let made = new_dirs(&src_file, &dest_file);
+        assert_eq!(made.len(), 2);
+        fs::create_dir_all(root.join("dst/photos/2025")).unwrap();
+        fs::write(&dest_file, b"jpg").unwrap();
+        apply_to_dirs(&made, false);
+        let meta = |d: &str| fs::metadata(root.join(d)).unwrap();
+        assert_eq!(meta("dst/photos").modified().unwrap(), then);
+        assert_eq!(meta("dst/photos/2025").modified().unwrap(), then);
+        assert_eq!(meta("dst/photos").mode() & 0o7777, 0o750);
+        assert!(missing_dirs(&src_file, &dest_file).is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn failures_make_one_warning() {
+        assert_eq!(describe(0, None), None);
+        assert_eq!(
+            describe(1, Some("/dst/a: Operation not permitted")).as_deref(),
+            Some("could not keep the times or permissions of /dst/a: Operation not permitted")
+        );
+        assert_eq!(
+            describe(3, Some("/dst/a: Operation not permitted")).as_deref(),
+            Some("could not keep the times or permissions of 3 item(s), first /dst/a: Operation not permitted")
+        );
+    }
+}
@@ -0,0 +1,194 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::ffi::OsString;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+use crate::activity::TrackActivity;
+
+// ── Low priority ───────────────────────────────────────────────────────
+//
+// A big local copy competes with the desktop for the disk at normal
+// priority.  With "Run at low priority" (`--low-priority`) the worker
+// thread gives itself nice 19 and, on Linux, the idle IO scheduling
+// class, and rsync and scp are started under `ionice -c3 nice -n19`.
+// Nice values and IO priorities belong to a thread on Linux, so the GTK
+// main thread (or the CLI's progress loop) keeps its normal priority.
+//
+// The worker changes its own settings through `renice` and `ionice -p`
+// on its thread id, so no system calls are made directly.  Whatever is
+// missing is skipped without complaint; what was applied is recorded for
+// the debug log and the CLI result.
+
+/// What lowering the worker's priority achieved.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Outcome {
+    /// The worker thread runs at nice 19.
+    pub nice: bool,
+    /// The worker thread is in the idle IO class.
+    pub io_idle: bool,
+    /// What rsync and scp are started under; empty when neither `ionice`
+    /// nor `nice` was found.
+    pub child_prefix: Vec<&'static str>,
+}
+
+impl Outcome {
+    /// `low priority: nice 19 applied, idle IO unavailable, children
+    /// under nice -n19`.
+    pub fn describe(&self) -> String {
+        let state = |applied: bool| if applied { "applied" } else { "unavailable" };
+        let children = match self.child_prefix.is_empty() {
+            true => "children unchanged".to_string(),
+            false => format!("children under {}", self.child_prefix.join(" ")),
+        };
+        format!("low priority: nice 19 {}, idle IO {}, {}", state(self.nice), state(self.io_idle), children)
+    }
+}
+
+static OUTCOME: Mutex<Option<Outcome>> = Mutex::new(None);
+
+/// Lower the calling thread's priority if `low` (the run asked for it),
+/// returning what was applied.  Called first thing on the worker thread.
+pub fn lower_this_thread(low: bool) -> Option<Outcome> {
+    let outcome = low.then(|| {
+        let tid = thread_id();
+        let run = |program: &str, args: &[&str]| {
+            tid.is_some_and(|tid| {
+                Command::new(program)
+                    .args(args)
+                    .arg(tid.to_string())
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .printed()
+                    .status()
+                    .is_ok_and(|s| s.success())
+            })
+        };
+        Outcome {
+            nice: run("renice", &["-n", "19", "-p"]),
+            io_idle: run("ionice", &["-c", "3", "-p"]),
+            child_prefix: child_prefix().to_vec(),
+        }
+    });
+    if let Ok(mut last) = OUTCOME.lock() {
+        *last = outcome.clone();
+    }
+    outcome
+}
+
+/// What the last run's `lower_this_thread` applied, if it ran at low
+/// priority.
+pub fn report() -> Option<Outcome> {
+    OUTCOME.lock().ok()?.clone()
+}
+
+/// `Command::new(program)`, started under `ionice`/`nice` when `low`.
+pub fn command(program: &str, low: bool) -> Command {
+    match low {
+        true => build(child_prefix(), program),
+        false => Command::new(program),
+    }
+}
+
+fn child_prefix() -> &'static [&'static str] {
+    static PREFIX: OnceLock<Vec<&'static str>> = OnceLock::new();
+    PREFIX.get_or_init(|| {
+        let path = std::env::var_os("PATH");
+        prefix(on_path("ionice", path.clone()), on_path("nice", path))
+    })
+}
+
+/// The wrapper for the tools that exist.
+fn prefix(ionice: bool, nice: bool) -> Vec<&'static str> {
+    let mut prefix = Vec::new();
+    if ionice {
+        prefix.extend(["ionice", "-c3"]);
+    }
+    if nice {
+        prefix.extend(["nice", "-n19"]);
+    }
+    prefix
+}
+
+fn build(prefix: &[&str], program: &str) -> Command {
+    match prefix.split_first() {
+        Some((first, rest)) => {
+            let mut cmd = Command::new(first);
+            cmd.args(rest).arg(program);
+            cmd
+        }
+        None => Command::new(program),
+    }
+}
+
+/// Whether an executable `name` is in one of the `path` folders.
+fn on_path(name: &str, path: Option<OsString>) -> bool {
+    which(name, path).is_some()
+}
+
+/// The first executable `name` in the `path` folders, as a shell would
+/// find it.
+pub fn which(name: &str, path: Option<OsString>) -> Option<PathBuf> {
+    std::env::split_paths(&path?).map(|dir| dir.join(name)).find(|file| {
+        fs::metadata(file).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    })
+}
+
+/// The kernel's id for the calling thread, from `/proc/thread-self`
+/// (`<pid>/task/<tid>`); `None` off Linux.
+fn thread_id() -> Option<u32> {
+    let link = fs::read_link("/proc/thread-self").ok()?;
+    link.file_name()?.to_str()?.parse().ok().filter(|_| link.starts_with(Path::new(&std::process::id().to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(cmd: &Command) -> Vec<String> {
+        std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|a| a.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn children_are_prefixed_with_the_tools_found() {
+        assert_eq!(argv(&build(&prefix(true, true), "rsync")), ["ionice", "-c3", "nice", "-n19", "rsync"]);
+        assert_eq!(argv(&build(&prefix(false, true), "scp")), ["nice", "-n19", "scp"]);
+        assert_eq!(argv(&build(&prefix(true, false), "scp")), ["ionice", "-c3", "scp"]);
+        assert_eq!(argv(&build(&prefix(false, false), "rsync")), ["rsync"]);
+    }
+
+    #[test]
+    fn a_missing_ionice_falls_back_to_nice() {
+        let dir = std::env::temp_dir().join(format!("kosmokopy_priority_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("nice"), "#!/bin/sh\nexec \"$@\"\n").unwrap();
+        fs::set_permissions(dir.join("nice"), fs::Permissions::from_mode(0o755)).unwrap();
+        // Not executable, so not a usable ionice either.
+        fs::write(dir.join("ionice"), "").unwrap();
+
+        let path = Some(dir.clone().into_os_string());
+        assert!(on_path("nice", path.clone()));
+        assert!(!on_path("ionice", path.clone()));
+        assert!(!on_path("nice", None));
+        let found = prefix(on_path("ionice", path.clone()), on_path("nice", path));
+        assert_eq!(argv(&build(&found, "rsync")), ["nice", "-n19", "rsync"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn outcomes_read_plainly() {
+        let outcome = Outcome { nice: true, io_idle: false, child_prefix: vec!["nice", "-n19"] };
+        assert_eq!(outcome.describe(), "low priority: nice 19 applied, idle IO unavailable, children under nice -n19");
+        assert_eq!(Outcome::default().describe(), "low priority: nice 19 unavailable, idle IO unavailable, children unchanged");
+    }
+}
@@ -0,0 +1,105 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::paths::{self, Dir};
+use crate::state::WindowState;
+
+// ── Named transfer profiles ────────────────────────────────────────────
+//
+// A profile is the window's choices saved under a name, such as
+// "photos-to-nas", so a job run every week is one pick from a list (or
+// `--cli --profile photos-to-nas`) instead of a dozen clicks.  Each one is
+// a file in the `profiles` folder of the config directory, written in the
+// same form as `state.toml`; the name is the file name without `.toml`.
+
+const DIR_NAME: &str = "profiles";
+const EXTENSION: &str = "toml";
+
+/// Why `name` cannot name a profile, or `None` when it can.
+pub fn name_error(name: &str) -> Option<&'static str> {
+    if name.trim().is_empty() {
+        Some("a profile needs a name")
+    } else if name != name.trim() {
+        Some("a profile name cannot start or end with a space")
+    } else if name.contains('/') {
+        Some("a profile name cannot contain '/'")
+    } else if name.starts_with('.') {
+        Some("a profile name cannot start with '.'")
+    } else if name.chars().any(char::is_control) {
+        Some("a profile name cannot contain control characters")
+    } else {
+        None
+    }
+}
+
+fn dir() -> Option<PathBuf> {
+    paths::dir(Dir::Config).map(|d| d.join(DIR_NAME))
+}
+
+fn path(name: &str) -> io::Result<PathBuf> {
+    if let Some(reason) = name_error(name) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, reason));
+    }
+    let dir = paths::ensure(Dir::Config)?.join(DIR_NAME);
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("{}.{}", name, EXTENSION)))
+}
+
+/// The saved profiles' names, sorted; empty when there are none.
+pub fn list() -> Vec<String> {
+    let Some(entries) = dir().and_then(|d| fs::read_dir(d).ok()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|e| {
+            let path = e.path();
+            let name = path.file_stem()?.to_str()?.to_string();
+            (path.extension()? == EXTENSION && name_error(&name).is_none()).then_some(name)
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// The profile called `name`.  Unlike `state::load`, a missing profile is
+/// an error: it was asked for by name.
+pub fn load(name: &str) -> io::Result<WindowState> {
+    fs::read_to_string(path(name)?).map(|text| WindowState::parse(&text))
+}
+
+pub fn save(name: &str, profile: &WindowState) -> io::Result<()> {
+    fs::write(path(name)?, profile.to_text())
+}
+
+pub fn delete(name: &str) -> io::Result<()> {
+    fs::remove_file(path(name)?)
+}
+
+/// Rename a profile, refusing to replace another one.
+pub fn rename(from: &str, to: &str) -> io::Result<()> {
+    let target = path(to)?;
+    if target.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("a profile named '{}' already exists", to)));
+    }
+    fs::rename(path(from)?, target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_must_make_plain_file_names() {
+        assert_eq!(name_error("photos-to-nas"), None);
+        assert_eq!(name_error("Weekly backup (NAS)"), None);
+        for bad in ["", "  ", " padded", "a/b", "..", ".hidden", "tab\there"] {
+            assert!(name_error(bad).is_some(), "{:?}", bad);
+        }
+    }
+}
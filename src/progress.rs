@@ -0,0 +1,737 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// ── Phases of a run ────────────────────────────────────────────────────
+//
+// Each file is checked straight after it is copied, so a run goes back
+// and forth between transferring and verifying, one file at a time, and a
+// large file could sit at a full bar while it was hashed.  Tracking the
+// phase lets the bar say what it is waiting for, and holding it short of
+// full until the result arrives keeps a run that is still deleting
+// sources or tidying up from reading as done.
+
+/// What a run is doing, in the order a run goes through them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// Listing the files to transfer; no plan yet.
+    #[default]
+    Scanning,
+    /// Checking the destination folders the plan will write into.
+    Preflight,
+    /// Copying files.
+    Transferring,
+    /// Checking the file just copied against its source.
+    Verifying,
+    /// Deleting the sources of a move, removing staging folders and
+    /// tallying results.
+    Finalizing,
+}
+
+impl Phase {
+    /// Name used in `--events` output and as the progress bar's CSS class.
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Scanning => "scanning",
+            Phase::Preflight => "preflight",
+            Phase::Transferring => "transferring",
+            Phase::Verifying => "verifying",
+            Phase::Finalizing => "finalizing",
+        }
+    }
+
+    /// Whether a run can go from `self` to `next`: forwards, or back from
+    /// verifying one file to transferring the next.  Finalizing is last.
+    pub fn may_become(self, next: Phase) -> bool {
+        next > self || (self == Phase::Verifying && next == Phase::Transferring)
+    }
+}
+
+/// Fullest the bar is drawn before the run's result arrives.
+pub const HELD_FRACTION: f64 = 0.99;
+
+// ── Size-aware progress model ──────────────────────────────────────────
+
+/// Overall progress of a transfer, weighted by file size.
+///
+/// Workers send the per-file sizes once collection is complete (in the
+/// order the files will be processed), followed by a `done` count after
+/// each file and optional in-flight byte counts while a file is being
+/// copied.  Files whose size is known share their portion of the bar in
+/// proportion to their bytes; files with an unknown size fall back to an
+/// equal count-based share, so a single huge file no longer sits behind
+/// thousands of tiny ones at "95%".
+#[derive(Clone, Debug, Default)]
+pub struct ProgressModel {
+    /// Fraction of the whole transfer each file is worth (sums to 1.0).
+    weights: Vec<f64>,
+    /// Cumulative weight of files `0..i`, with one extra trailing entry.
+    prefix: Vec<f64>,
+    sizes: Vec<Option<u64>>,
+    /// Prefix sums of known sizes, used for byte counts.
+    byte_prefix: Vec<u64>,
+    total: usize,
+    done: usize,
+    in_flight: u64,
+    phase: Phase,
+}
+
+impl ProgressModel {
+    /// Build a model from the sizes of the files about to be transferred.
+    pub fn new(sizes: Vec<Option<u64>>) -> Self {
+        let n = sizes.len();
+        let known_count = sizes.iter().filter(|s| s.is_some()).count();
+        let known_bytes: u64 = sizes.iter().flatten().sum();
+
+        let weights: Vec<f64> = sizes
+            .iter()
+            .map(|s| match s {
+                // Known sizes split the known files' count share by bytes.
+                // If every known file is empty, weight them by count instead.
+                Some(sz) if known_bytes > 0 => {
+                    (known_count as f64 / n as f64) * (*sz as f64 / known_bytes as f64)
+                }
+                _ => 1.0 / n as f64,
+            })
+            .collect();
+
+        let mut prefix = Vec::with_capacity(n + 1);
+        let mut acc = 0.0;
+        prefix.push(acc);
+        for w in &weights {
+            acc += w;
+            prefix.push(acc);
+        }
+
+        let mut byte_prefix = Vec::with_capacity(n + 1);
+        let mut bytes = 0u64;
+        byte_prefix.push(bytes);
+        for s in &sizes {
+            bytes += s.unwrap_or(0);
+            byte_prefix.push(bytes);
+        }
+
+        ProgressModel {
+            weights,
+            prefix,
+            sizes,
+            byte_prefix,
+            total: n,
+            done: 0,
+            in_flight: 0,
+            phase: Phase::Transferring,
+        }
+    }
+
+    /// Record that `done` of `total` files have been processed.
+    ///
+    /// If no sizes were supplied (or they don't match `total`), the model
+    /// falls back to a plain file-count fraction.  Counts never go
+    /// backwards, and the in-flight byte count is reset for the next file.
+    /// A file that was being verified is finished, so the run is back to
+    /// transferring.
+    pub fn file_done(&mut self, done: usize, total: usize) {
+        if self.sizes.len() != total {
+            let phase = self.phase;
+            *self = ProgressModel::default();
+            self.total = total;
+            self.phase = phase;
+        }
+        if done >= self.done {
+            self.done = done.min(self.total);
+            self.in_flight = 0;
+        }
+        self.set_phase(Phase::Transferring);
+    }
+
+    /// Record the number of bytes copied so far of the file currently in flight.
+    pub fn in_flight(&mut self, bytes: u64) {
+        self.in_flight = bytes;
+        self.set_phase(Phase::Transferring);
+    }
+
+    /// The phase the run is in.
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Move the run to `phase` if it can get there from the current one;
+    /// returns whether it did.  A late message from an earlier phase is
+    /// ignored rather than sending the bar backwards.
+    pub fn set_phase(&mut self, phase: Phase) -> bool {
+        if !self.phase.may_become(phase) {
+            return false;
+        }
+        self.phase = phase;
+        true
+    }
+
+    /// What the bar shows for the current phase: `Verifying… 84/120` while
+    /// a file is checked, `Finalizing…` at the end; `None` while scanning
+    /// or transferring, which show their own detail.
+    pub fn phase_text(&self) -> Option<String> {
+        match self.phase {
+            Phase::Scanning | Phase::Transferring => None,
+            Phase::Preflight => Some("Checking destination folders…".to_string()),
+            Phase::Verifying => Some(format!("Verifying… {}/{}", (self.done + 1).min(self.total), self.total)),
+            Phase::Finalizing => Some("Finalizing…".to_string()),
+        }
+    }
+
+    /// What the bar shows while transferring: `42% — file 4 of 10 — label
+    /// (3:12 left)`.  The percentage follows `fraction()`, so it moves with
+    /// the bytes of a large file rather than waiting for it to finish.
+    pub fn transfer_text(&self, label: &str, eta: Option<Duration>) -> String {
+        // Floored, so a run never reads 100% before its last byte is in.
+        let percent = (self.fraction() * 100.0).floor() as u32;
+        let eta = eta.map(|d| format!(" ({} left)", format_eta(d))).unwrap_or_default();
+        format!("{}% — file {} of {} — {}{}", percent, self.done, self.total, label, eta)
+    }
+
+    /// The fraction to draw: `fraction()`, held short of full until the
+    /// run's result arrives.
+    pub fn bar_fraction(&self) -> f64 {
+        self.fraction().min(HELD_FRACTION)
+    }
+
+    /// Whether per-file sizes are driving the fraction.
+    pub fn is_size_aware(&self) -> bool {
+        !self.weights.is_empty()
+    }
+
+    /// Files processed so far.
+    pub fn done(&self) -> usize {
+        self.done
+    }
+
+    /// Total number of files in the transfer.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Overall fraction complete, in `0.0..=1.0`.
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        if !self.is_size_aware() {
+            return self.done as f64 / self.total as f64;
+        }
+        let mut frac = self.prefix[self.done];
+        if let (Some(w), Some(Some(sz))) = (self.weights.get(self.done), self.sizes.get(self.done)) {
+            if *sz > 0 {
+                frac += w * (self.in_flight.min(*sz) as f64 / *sz as f64);
+            }
+        }
+        frac.clamp(0.0, 1.0)
+    }
+
+    /// Bytes completed so far among files with a known size, including the
+    /// in-flight file.
+    pub fn bytes_done(&self) -> u64 {
+        match self.byte_prefix.get(self.done) {
+            Some(b) => {
+                let partial = match self.sizes.get(self.done) {
+                    Some(Some(sz)) => self.in_flight.min(*sz),
+                    _ => 0,
+                };
+                b + partial
+            }
+            None => 0,
+        }
+    }
+
+    /// Total bytes across files with a known size.
+    pub fn bytes_total(&self) -> u64 {
+        self.byte_prefix.last().copied().unwrap_or(0)
+    }
+
+    /// Estimated time remaining given the time elapsed so far.
+    ///
+    /// Because the fraction is byte-weighted, this extrapolates from the
+    /// byte rate rather than the file rate.  Returns `None` until enough
+    /// progress has been made for a meaningful estimate.
+    pub fn eta(&self, elapsed: Duration) -> Option<Duration> {
+        let frac = self.fraction();
+        if frac <= 0.0 || elapsed.is_zero() {
+            return None;
+        }
+        if frac >= 1.0 {
+            return Some(Duration::ZERO);
+        }
+        let remaining = elapsed.as_secs_f64() * (1.0 - frac) / frac;
+        Some(Duration::from_secs_f64(remaining))
+    }
+}
+
+// ── Speed and time left ────────────────────────────────────────────────
+//
+// `ProgressModel::eta` extrapolates from the whole run so far, so a slow
+// first minute (a cold cache, a disk spinning up) keeps the estimate high
+// long after the run has sped up.  The speed shown under the bar is taken
+// over the last ten seconds instead, and the time left is worked out from
+// it: by bytes when the files' sizes are known, by files when they aren't
+// (e.g. a remote source whose listing gave no sizes).
+
+/// How far back the speed looks.
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Samples closer together than this replace one another.
+const SAMPLE_EVERY: Duration = Duration::from_millis(250);
+
+/// How fast a run is going.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rate {
+    BytesPerSec(f64),
+    /// For runs whose file sizes are unknown.
+    FilesPerSec(f64),
+}
+
+impl Rate {
+    /// `42.0 MiB/s`, or `12.5 files/s`.
+    pub fn describe(self) -> String {
+        match self {
+            Rate::BytesPerSec(rate) => format!("{}/s", format_bytes(rate as u64)),
+            Rate::FilesPerSec(rate) => format!("{:.1} files/s", rate),
+        }
+    }
+}
+
+/// Recent progress samples of one run, as `(working time, bytes done,
+/// files done)`, oldest first.
+#[derive(Clone, Debug, Default)]
+pub struct Throughput {
+    samples: VecDeque<(Duration, u64, usize)>,
+}
+
+impl Throughput {
+    /// Note where `model` is at `at` of the run's working time.
+    pub fn sample(&mut self, at: Duration, model: &ProgressModel) {
+        let sample = (at, model.bytes_done(), model.done());
+        let n = self.samples.len();
+        if n >= 2 && at.saturating_sub(self.samples[n - 2].0) < SAMPLE_EVERY {
+            self.samples[n - 1] = sample;
+        } else {
+            self.samples.push_back(sample);
+        }
+        while self.samples.len() > 2 && at.saturating_sub(self.samples[1].0) >= RATE_WINDOW {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The speed over the window, by bytes when `model` knows the sizes;
+    /// `None` until the samples span a moment.
+    pub fn rate(&self, model: &ProgressModel) -> Option<Rate> {
+        let (first, last) = (self.samples.front()?, self.samples.back()?);
+        let span = last.0.saturating_sub(first.0).as_secs_f64();
+        if span < SAMPLE_EVERY.as_secs_f64() {
+            return None;
+        }
+        Some(if model.bytes_total() > 0 {
+            Rate::BytesPerSec(last.1.saturating_sub(first.1) as f64 / span)
+        } else {
+            Rate::FilesPerSec(last.2.saturating_sub(first.2) as f64 / span)
+        })
+    }
+
+    /// The time left at the current speed; `None` while nothing is moving.
+    pub fn left(&self, model: &ProgressModel) -> Option<Duration> {
+        let remaining = match self.rate(model)? {
+            Rate::BytesPerSec(rate) if rate > 0.0 => model.bytes_total().saturating_sub(model.bytes_done()) as f64 / rate,
+            Rate::FilesPerSec(rate) if rate > 0.0 => model.total().saturating_sub(model.done()) as f64 / rate,
+            _ => return None,
+        };
+        Some(Duration::from_secs_f64(remaining))
+    }
+
+    /// `42.0 MiB/s — about 3 min left`, or just the speed while nothing
+    /// is moving.
+    pub fn describe(&self, model: &ProgressModel) -> Option<String> {
+        let rate = self.rate(model)?.describe();
+        Some(match self.left(model) {
+            Some(left) => format!("{} — {}", rate, about_left(left)),
+            None => rate,
+        })
+    }
+}
+
+/// `about 3 min left`, rounded as a person would say it.
+pub fn about_left(left: Duration) -> String {
+    let mins = (left.as_secs() + 30) / 60;
+    match mins {
+        0 => "less than a minute left".to_string(),
+        1..=59 => format!("about {} min left", mins),
+        _ => format!("about {} h {} min left", mins / 60, mins % 60),
+    }
+}
+
+// ── Time a run has been going ──────────────────────────────────────────
+
+/// The time a run has spent working, which leaves out its pauses so that
+/// neither the elapsed time nor the estimate built on it runs on while the
+/// run waits to be resumed.
+#[derive(Clone, Copy, Debug)]
+pub struct RunClock {
+    started: Instant,
+    paused_at: Option<Instant>,
+    paused_for: Duration,
+}
+
+impl RunClock {
+    pub fn start() -> Self {
+        RunClock { started: Instant::now(), paused_at: None, paused_for: Duration::ZERO }
+    }
+
+    /// Stop counting until `resume`.
+    pub fn pause(&mut self) {
+        self.paused_at.get_or_insert_with(Instant::now);
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(at) = self.paused_at.take() {
+            self.paused_for += at.elapsed();
+        }
+    }
+
+    /// Time spent working, not counting pauses.
+    pub fn elapsed(&self) -> Duration {
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        now.duration_since(self.started).saturating_sub(self.paused_for)
+    }
+}
+
+/// Format a byte count with binary units, e.g. `1.5 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Format an ETA as `h:mm:ss` or `m:ss`.
+pub fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+/// Longest file name shown in the progress bar or an activity line.
+pub const MAX_NAME_LEN: usize = 60;
+
+/// Shorten `name` to at most `max` characters by replacing its middle with
+/// `…`, keeping a short extension intact, e.g. `holiday_vid…_final.mkv`.
+/// Works on characters, so a multibyte sequence is never split.
+pub fn ellipsize_middle(name: &str, max: usize) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    if chars.len() <= max || max < 2 {
+        return name.to_string();
+    }
+    let budget = max - 1;
+    let ext_len = name
+        .rfind('.')
+        .filter(|&dot| dot > 0)
+        .map(|dot| name[dot..].chars().count())
+        .filter(|&n| n <= budget / 3)
+        .unwrap_or(0);
+    let tail = ext_len.max(budget / 3);
+    let head = budget - tail;
+    let mut out: String = chars[..head].iter().collect();
+    out.push('…');
+    out.extend(&chars[chars.len() - tail..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_monotonic(fracs: &[f64]) {
+        for pair in fracs.windows(2) {
+            assert!(pair[1] >= pair[0], "fraction went backwards: {:?}", fracs);
+        }
+        for f in fracs {
+            assert!((0.0..=1.0).contains(f), "fraction out of range: {}", f);
+        }
+    }
+
+    #[test]
+    fn many_small_then_one_huge() {
+        let mut sizes = vec![Some(1_000u64); 1000];
+        sizes.push(Some(4_000_000_000));
+        let mut model = ProgressModel::new(sizes);
+        let total = model.total();
+        let mut fracs = vec![model.fraction()];
+        for done in 1..=1000 {
+            model.file_done(done, total);
+            fracs.push(model.fraction());
+        }
+        // All the small files together are a sliver of the bytes.
+        assert!(model.fraction() < 0.01, "got {}", model.fraction());
+
+        for chunk in 1..=4 {
+            model.in_flight(chunk * 1_000_000_000);
+            fracs.push(model.fraction());
+        }
+        model.file_done(total, total);
+        fracs.push(model.fraction());
+
+        assert_monotonic(&fracs);
+        assert!((model.fraction() - 1.0).abs() < 1e-9);
+        assert_eq!(model.bytes_done(), model.bytes_total());
+    }
+
+    #[test]
+    fn single_file_in_flight() {
+        let mut model = ProgressModel::new(vec![Some(100)]);
+        assert_eq!(model.fraction(), 0.0);
+        model.in_flight(25);
+        assert!((model.fraction() - 0.25).abs() < 1e-9);
+        model.in_flight(250); // over-reporting never exceeds the file's share
+        assert!((model.fraction() - 1.0).abs() < 1e-9);
+        model.file_done(1, 1);
+        assert!((model.fraction() - 1.0).abs() < 1e-9);
+        assert_eq!(model.bytes_done(), 100);
+    }
+
+    #[test]
+    fn transfer_text_shows_bytes_and_files() {
+        let mut model = ProgressModel::new(vec![Some(10), Some(990), Some(0)]);
+        model.file_done(1, 3);
+        assert_eq!(model.transfer_text("docs › a.txt", None), "1% — file 1 of 3 — docs › a.txt");
+        model.in_flight(500);
+        assert_eq!(
+            model.transfer_text("docs › a.txt", Some(Duration::from_secs(192))),
+            "51% — file 1 of 3 — docs › a.txt (3:12 left)"
+        );
+        model.in_flight(989);
+        assert!(model.transfer_text("x", None).starts_with("99% "));
+        model.file_done(3, 3);
+        assert_eq!(model.transfer_text("x", None), "100% — file 3 of 3 — x");
+    }
+
+    #[test]
+    fn unknown_sizes_use_count_share() {
+        let mut model = ProgressModel::new(vec![None, Some(300), None, Some(100)]);
+        let mut fracs = vec![model.fraction()];
+        model.file_done(1, 4);
+        // An unknown-size file is worth one file's share.
+        assert!((model.fraction() - 0.25).abs() < 1e-9);
+        fracs.push(model.fraction());
+        model.file_done(2, 4);
+        // Known files share half the bar by bytes: 300/400 of 0.5.
+        assert!((model.fraction() - 0.625).abs() < 1e-9);
+        fracs.push(model.fraction());
+        model.file_done(3, 4);
+        fracs.push(model.fraction());
+        model.file_done(4, 4);
+        fracs.push(model.fraction());
+        assert_monotonic(&fracs);
+        assert!((model.fraction() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn all_unknown_matches_file_count() {
+        let mut model = ProgressModel::new(vec![None; 4]);
+        model.file_done(3, 4);
+        assert!((model.fraction() - 0.75).abs() < 1e-9);
+        assert_eq!(model.bytes_total(), 0);
+    }
+
+    #[test]
+    fn counts_never_go_backwards() {
+        let mut model = ProgressModel::new(vec![Some(10); 4]);
+        model.file_done(3, 4);
+        model.file_done(2, 4);
+        assert_eq!(model.done(), 3);
+    }
+
+    #[test]
+    fn without_sizes_falls_back_to_count() {
+        let mut model = ProgressModel::default();
+        model.file_done(1, 4);
+        assert!(!model.is_size_aware());
+        assert!((model.fraction() - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pauses_are_left_out_of_the_elapsed_time() {
+        let mut clock = RunClock::start();
+        clock.pause();
+        let at_pause = clock.elapsed();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(clock.elapsed(), at_pause);
+
+        clock.resume();
+        assert!(clock.elapsed() < at_pause + Duration::from_millis(20));
+        // Resuming twice adds nothing.
+        clock.resume();
+        assert!(clock.elapsed() >= at_pause);
+    }
+
+    #[test]
+    fn eta_extrapolates_from_fraction() {
+        let mut model = ProgressModel::new(vec![Some(100), Some(300)]);
+        assert_eq!(model.eta(Duration::from_secs(10)), None);
+        model.file_done(1, 2);
+        // 25% done in 10s → 30s remaining.
+        let eta = model.eta(Duration::from_secs(10)).unwrap();
+        assert_eq!(eta.as_secs(), 30);
+        assert_eq!(format_eta(eta), "0:30");
+        assert_eq!(format_eta(Duration::from_secs(3725)), "1:02:05");
+    }
+
+    #[test]
+    fn phases_run_in_order() {
+        let mut model = ProgressModel::default();
+        assert_eq!(model.phase(), Phase::Scanning);
+        assert!(model.set_phase(Phase::Preflight));
+        assert_eq!(model.phase_text().as_deref(), Some("Checking destination folders…"));
+        model = ProgressModel::new(vec![Some(100), Some(100)]);
+        assert_eq!(model.phase(), Phase::Transferring);
+        let mut seen = vec![model.phase()];
+        for done in 0..2 {
+            model.in_flight(100);
+            seen.push(model.phase());
+            assert!(model.set_phase(Phase::Verifying));
+            seen.push(model.phase());
+            assert_eq!(model.phase_text().unwrap(), format!("Verifying… {}/2", done + 1));
+            model.file_done(done + 1, 2);
+            seen.push(model.phase());
+        }
+        assert!(model.set_phase(Phase::Finalizing));
+        seen.push(model.phase());
+        use Phase::*;
+        assert_eq!(
+            seen,
+            [Transferring, Transferring, Verifying, Transferring, Transferring, Verifying, Transferring, Finalizing]
+        );
+        assert_eq!(model.phase_text().as_deref(), Some("Finalizing…"));
+    }
+
+    #[test]
+    fn phases_never_go_backwards() {
+        use Phase::*;
+        assert!(Scanning.may_become(Transferring));
+        assert!(Scanning.may_become(Preflight));
+        assert!(Preflight.may_become(Transferring));
+        assert!(!Transferring.may_become(Preflight));
+        assert!(Scanning.may_become(Finalizing));
+        assert!(Verifying.may_become(Transferring));
+        assert!(!Transferring.may_become(Scanning));
+        assert!(!Finalizing.may_become(Transferring));
+        assert!(!Finalizing.may_become(Verifying));
+
+        // Deletion progress after Finalizing keeps the phase.
+        let mut model = ProgressModel::default();
+        assert!(model.set_phase(Finalizing));
+        model.file_done(1, 3);
+        model.in_flight(10);
+        assert_eq!(model.phase(), Finalizing);
+        assert!(!model.set_phase(Verifying));
+    }
+
+    #[test]
+    fn bar_is_held_short_of_full_until_the_result() {
+        let mut model = ProgressModel::new(vec![Some(100)]);
+        model.in_flight(100);
+        assert!((model.fraction() - 1.0).abs() < 1e-9);
+        assert_eq!(model.bar_fraction(), HELD_FRACTION);
+        model.set_phase(Phase::Verifying);
+        assert_eq!(model.phase_text().as_deref(), Some("Verifying… 1/1"));
+        model.file_done(1, 1);
+        model.set_phase(Phase::Finalizing);
+        assert!(model.bar_fraction() < 1.0);
+    }
+
+    #[test]
+    fn formats_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+
+    #[test]
+    fn short_names_are_unchanged() {
+        assert_eq!(ellipsize_middle("photo.jpg", MAX_NAME_LEN), "photo.jpg");
+        assert_eq!(ellipsize_middle(&"a".repeat(60), 60), "a".repeat(60));
+    }
+
+    #[test]
+    fn long_names_keep_start_and_extension() {
+        let name = format!("{}.tar.gz", "x".repeat(400));
+        let short = ellipsize_middle(&name, MAX_NAME_LEN);
+        assert_eq!(short.chars().count(), MAX_NAME_LEN);
+        assert!(short.starts_with("xxxx"));
+        assert!(short.ends_with(".gz"));
+        assert!(short.contains('…'));
+        // A "extension" too long to keep is cut like the rest.
+        let dotted = format!("a.{}", "b".repeat(100));
+        assert_eq!(ellipsize_middle(&dotted, 20).chars().count(), 20);
+    }
+
+    #[test]
+    fn speed_follows_the_last_ten_seconds() {
+        let mut model = ProgressModel::new(vec![Some(100 << 20); 100]);
+        let mut speed = Throughput::default();
+        let secs = Duration::from_secs;
+        // A slow first minute: one file in 60 s.
+        speed.sample(secs(0), &model);
+        model.file_done(1, 100);
+        speed.sample(secs(60), &model);
+        // Then one file a second.
+        for done in 2..=20 {
+            model.file_done(done, 100);
+            speed.sample(secs(59 + done as u64), &model);
+        }
+        assert_eq!(speed.rate(&model), Some(Rate::BytesPerSec((100 << 20) as f64)));
+        assert_eq!(speed.left(&model), Some(secs(80)));
+        assert_eq!(speed.describe(&model).unwrap(), "100.0 MiB/s — about 1 min left");
+        // The run's average would still say over five minutes.
+        assert!(model.eta(secs(79)).unwrap() > secs(300));
+    }
+
+    #[test]
+    fn without_sizes_the_speed_is_in_files() {
+        let mut model = ProgressModel::new(vec![None; 6000]);
+        let mut speed = Throughput::default();
+        assert_eq!(speed.describe(&model), None);
+        for done in 0..=10 {
+            model.file_done(done, 6000);
+            speed.sample(Duration::from_secs(done as u64), &model);
+        }
+        assert_eq!(speed.rate(&model), Some(Rate::FilesPerSec(1.0)));
+        assert_eq!(speed.describe(&model).unwrap(), "1.0 files/s — about 1 h 40 min left");
+        assert_eq!(about_left(Duration::from_secs(20)), "less than a minute left");
+        assert_eq!(about_left(Duration::from_secs(170)), "about 3 min left");
+    }
+
+    #[test]
+    fn multibyte_names_are_cut_on_character_boundaries() {
+        for name in [
+            format!("{}.jpg", "日本語".repeat(40)),
+            format!("{}.txt", "é".repeat(300)),
+            "🎞️".repeat(80),
+            format!("<&>{}&amp;.png", "ß".repeat(90)),
+        ] {
+            let short = ellipsize_middle(&name, MAX_NAME_LEN);
+            assert_eq!(short.chars().count(), MAX_NAME_LEN, "{}", name);
+            assert!(name.starts_with(short.split('…').next().unwrap()));
+            assert!(name.ends_with(short.split('…').nth(1).unwrap()));
+        }
+    }
+}
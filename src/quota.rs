@@ -0,0 +1,244 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::sync::Mutex;
+
+use crate::coalesce::group_thousands;
+
+// ── Destination quotas ─────────────────────────────────────────────────
+//
+// On NFS and Samba shares with per-user quotas, writes fail with "Disk
+// quota exceeded" while the filesystem itself has plenty of room, so the
+// free-space check passes and every file after that fails with an OS
+// message that does not say whose space ran out.  Errors that are quota
+// failures, whether a local write, scp or rsync reported them, are
+// reworded to one cause: they fold together like any other failure that
+// hits every remaining file, and the summary can name the condition.
+//
+// At the first one the run pauses: the GUI asks whether to retry once
+// space has been freed, skip the remaining files or cancel; the CLI
+// follows `--on-quota`.
+
+/// The cause a quota error is reworded to.
+pub const CAUSE: &str = "destination quota exceeded (your quota there is full, though the disk may have room)";
+
+/// Why a file was skipped after choosing to skip the remaining files.
+pub const SKIPPED: &str = "not transferred, destination quota exceeded";
+
+/// What to do once the destination quota is exceeded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnQuota {
+    /// Carry on; each file that does not fit fails on its own.
+    Continue,
+    /// Carry on now that space has been freed, asking again if the quota
+    /// is hit again.
+    Retry,
+    /// Transfer nothing more, listing the remaining files as skipped.
+    Skip,
+    /// Stop the run.
+    Cancel,
+}
+
+impl OnQuota {
+    /// `--on-quota`; retrying needs someone to free space, so only the
+    /// GUI offers it.
+    pub fn parse(word: &str) -> Option<OnQuota> {
+        match word {
+            "continue" => Some(OnQuota::Continue),
+            "skip" => Some(OnQuota::Skip),
+            "cancel" => Some(OnQuota::Cancel),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OnQuota::Continue => "continue",
+            OnQuota::Retry => "retry",
+            OnQuota::Skip => "skip",
+            OnQuota::Cancel => "cancel",
+        }
+    }
+}
+
+/// How errors read when a quota stopped the write: the errno text of
+/// EDQUOT (local writes, scp, rsync), NFSv4's and SMB's status names, and
+/// the `over quota` some servers print.  Lower case.
+const SIGNS: [&str; 6] = ["disk quota exceeded", "quota exceeded", "quota_exceeded", "edquot", "nfs4err_dquot", "over quota"];
+
+/// Whether `message` says a write failed for lack of quota.  Only the text
+/// after the last `": "` of each line is looked at, so a file named
+/// `quota exceeded.txt` does not count.
+pub fn is_quota_error(message: &str) -> bool {
+    message.lines().any(|line| {
+        let tail = line.rsplit(": ").next().unwrap_or(line).to_lowercase();
+        SIGNS.iter().any(|sign| tail.contains(sign))
+    })
+}
+
+/// The run's quota errors so far and what was decided about them.
+#[derive(Debug, Default)]
+struct State {
+    failed: usize,
+    skipped: usize,
+    /// A quota error came in since the last answer.
+    pending: bool,
+    choice: Option<OnQuota>,
+    /// The latest quota error, for the prompt.
+    last: String,
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+/// Start watching for quota errors, forgetting the last run's.
+pub fn reset() {
+    if let Ok(mut state) = STATE.lock() {
+        *state = Some(State::default());
+    }
+}
+
+/// `message` reworded to [`CAUSE`] when it is a quota error, keeping the
+/// source path it starts with; otherwise unchanged.
+pub fn reword(message: String) -> String {
+    if !is_quota_error(&message) {
+        return message;
+    }
+    crate::debug_log(&format!("quota error: {}", message));
+    let path = message.split_once(": ").map_or("", |(path, _)| path);
+    let reworded = if path.is_empty() { CAUSE.to_string() } else { format!("{}: {}", path, CAUSE) };
+    if let Ok(mut state) = STATE.lock() {
+        if let Some(state) = state.as_mut() {
+            state.failed += 1;
+            state.pending = matches!(state.choice, None | Some(OnQuota::Retry));
+            state.last = reworded.clone();
+        }
+    }
+    reworded
+}
+
+/// The quota error still waiting for an answer, if any.
+pub fn unanswered() -> Option<String> {
+    let state = STATE.lock().ok()?;
+    state.as_ref().filter(|s| s.pending).map(|s| s.last.clone())
+}
+
+/// Record what was decided about the quota errors so far.
+pub fn answer(choice: OnQuota) {
+    if let Ok(mut state) = STATE.lock() {
+        if let Some(state) = state.as_mut() {
+            state.pending = false;
+            state.choice = Some(choice);
+        }
+    }
+}
+
+/// Whether the next file is to be skipped for the quota, counting it if
+/// so.
+pub fn skip() -> bool {
+    let Ok(mut state) = STATE.lock() else { return false };
+    match state.as_mut() {
+        Some(state) if state.choice == Some(OnQuota::Skip) => {
+            state.skipped += 1;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// What the quota did to a run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    /// Files that failed with a quota error.
+    pub failed: usize,
+    /// Files skipped afterwards.
+    pub skipped: usize,
+    pub choice: Option<OnQuota>,
+}
+
+impl Report {
+    /// `Destination quota exceeded: 3 file(s) failed; the remaining 120
+    /// were skipped.`
+    pub fn describe(&self) -> String {
+        let mut text = format!("Destination quota exceeded: {} file(s) failed", group_thousands(self.failed));
+        match self.choice {
+            Some(OnQuota::Skip) => {
+                text.push_str(&format!("; the remaining {} were skipped", group_thousands(self.skipped)))
+            }
+            Some(OnQuota::Cancel) => text.push_str("; the run was cancelled"),
+            Some(OnQuota::Retry) => text.push_str("; the run was resumed after freeing space"),
+            Some(OnQuota::Continue) | None => {}
+        }
+        text.push('.');
+        text
+    }
+
+    /// `{"failed":3,"skipped":120,"action":"skip"}`.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"failed\":{},\"skipped\":{},\"action\":{}}}",
+            self.failed,
+            self.skipped,
+            self.choice.map_or("null".to_string(), |c| format!("\"{}\"", c.name()))
+        )
+    }
+}
+
+/// What the run's quota errors came to, or `None` when there were none;
+/// watching stops.
+pub fn take() -> Option<Report> {
+    let state = STATE.lock().ok()?.take()?;
+    (state.failed > 0).then_some(Report { failed: state.failed, skipped: state.skipped, choice: state.choice })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// EDQUOT on Linux.
+    const EDQUOT: i32 = 122;
+
+    #[test]
+    fn quota_errors_are_recognised_whoever_reports_them() {
+        let local = format!("/src/a.txt: copy failed: {}", std::io::Error::from_raw_os_error(EDQUOT));
+        for message in [
+            local.as_str(),
+            "/src/a.txt: upload failed: scp: /srv/backup/a.txt: Disk quota exceeded",
+            "/src/a.txt: rsync: [receiver] write failed on \"/srv/backup/a.txt\": Disk quota exceeded (122)",
+            "/src/a.txt: NT_STATUS_QUOTA_EXCEEDED",
+            "/src/a.txt: write error: NFS4ERR_DQUOT",
+            "/src/a.txt: upload failed: ERROR: over quota",
+        ] {
+            assert!(is_quota_error(message), "{}", message);
+        }
+        for message in [
+            format!("/src/a.txt: copy failed: {}", std::io::Error::from_raw_os_error(28)),
+            format!("/src/a.txt: {}", std::io::Error::from_raw_os_error(13)),
+            "/src/quota exceeded.txt: copy failed: Read-only file system (os error 30)".to_string(),
+        ] {
+            assert!(!is_quota_error(&message), "{}", message);
+        }
+    }
+
+    #[test]
+    fn quota_runs_are_counted_and_paused_once() {
+        reset();
+        let scp = reword("/src/a.txt: upload failed: scp: /srv/a.txt: Disk quota exceeded".to_string());
+        let rsync = reword("/src/b.txt: rsync: write failed on \"/srv/b.txt\": Disk quota exceeded (122)".to_string());
+        assert_eq!(scp, format!("/src/a.txt: {}", CAUSE));
+        assert_eq!(crate::coalesce::cause(&scp), crate::coalesce::cause(&rsync));
+        assert_eq!(reword("/src/c.txt: Permission denied".to_string()), "/src/c.txt: Permission denied");
+        assert_eq!(unanswered(), Some(rsync));
+        answer(OnQuota::Skip);
+        assert_eq!(unanswered(), None);
+        assert!(skip() && skip());
+        let report = take().unwrap();
+        assert_eq!(report, Report { failed: 2, skipped: 2, choice: Some(OnQuota::Skip) });
+        assert_eq!(report.describe(), "Destination quota exceeded: 2 file(s) failed; the remaining 2 were skipped.");
+        assert_eq!(report.to_json(), "{\"failed\":2,\"skipped\":2,\"action\":\"skip\"}");
+        assert_eq!(take(), None);
+        assert!(!skip());
+        assert_eq!(OnQuota::parse("retry"), None);
+        assert_eq!(OnQuota::parse("cancel"), Some(OnQuota::Cancel));
+    }
+}
@@ -0,0 +1,159 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+
+// ── Between two hosts ──────────────────────────────────────────────────
+//
+// A transfer between two hosts used to download every file to a folder
+// under /tmp and upload it again: twice the time, and as much local disk
+// as the largest file.  There are two quicker ways:
+//
+// - Direct: the source host sends the file itself, `ssh src "scp path
+//   dst:path"`, when it can reach the destination with a key of its own;
+// - scp -3: this machine's scp relays the file between the two hosts
+//   through memory, `scp -3 src:path dst:path`, touching no local disk.
+//
+// With `--relay auto` (the default; the window's Between two hosts) the
+// run tries them in that order, then the staging folder.  A way is
+// settled by the first file it sends and verifies; until then, a file it
+// fails is tried again the next way, and the reason is kept for the
+// result.  A file whose name the quicker way can't carry safely (see
+// `direct_safe` and `ssh::scp_literal`) is staged whatever the way.
+// Every copy is still checked by hashing it on both hosts.
+
+/// How files get from one host to the other.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Relay {
+    Direct,
+    Scp3,
+    Staged,
+}
+
+impl Relay {
+    pub fn name(self) -> &'static str {
+        match self {
+            Relay::Direct => "direct",
+            Relay::Scp3 => "scp3",
+            Relay::Staged => "staged",
+        }
+    }
+
+    /// What the status line and the summary call it.
+    pub fn label(self) -> &'static str {
+        match self {
+            Relay::Direct => "direct between the hosts",
+            Relay::Scp3 => "scp -3 through this machine",
+            Relay::Staged => "staged on this machine",
+        }
+    }
+}
+
+/// The way asked for, before Auto is settled.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RelayChoice {
+    #[default]
+    Auto,
+    Only(Relay),
+}
+
+impl RelayChoice {
+    /// `--relay`.
+    pub fn parse(word: &str) -> Option<RelayChoice> {
+        match word {
+            "auto" => Some(RelayChoice::Auto),
+            "direct" => Some(RelayChoice::Only(Relay::Direct)),
+            "scp3" => Some(RelayChoice::Only(Relay::Scp3)),
+            "staged" => Some(RelayChoice::Only(Relay::Staged)),
+            _ => None,
+        }
+    }
+
+    /// The ways to try, in order.
+    pub fn order(self) -> &'static [Relay] {
+        match self {
+            RelayChoice::Auto => &[Relay::Direct, Relay::Scp3, Relay::Staged],
+            RelayChoice::Only(Relay::Direct) => &[Relay::Direct],
+            RelayChoice::Only(Relay::Scp3) => &[Relay::Scp3],
+            RelayChoice::Only(Relay::Staged) => &[Relay::Staged],
+        }
+    }
+}
+
+/// Which way a run went, and why not the quicker ones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decision {
+    pub relay: Relay,
+    /// Why each way tried before it was given up.
+    pub fallbacks: Vec<String>,
+    /// Files staged for their names, whatever the way.
+    pub staged: usize,
+}
+
+impl Decision {
+    /// e.g. `Between hosts: scp -3 through this machine (direct: ssh: Could
+    /// not resolve hostname nas2)`.
+    pub fn describe(&self) -> String {
+        let mut text = format!("Between hosts: {}", self.relay.label());
+        if !self.fallbacks.is_empty() {
+            text.push_str(&format!(" ({})", self.fallbacks.join("; ")));
+        }
+        if self.staged > 0 {
+            text.push_str(&format!(", {} file(s) staged for their names", self.staged));
+        }
+        text
+    }
+}
+
+/// Whether `path` can go in a command the source host's scp hands to the
+/// destination's shell: an older scp quotes nothing, so only names with
+/// nothing a shell would read differently are sent directly.  A name
+/// that isn't UTF-8 is staged too.
+pub fn direct_safe(path: &Path) -> bool {
+    path.to_str().is_some_and(|path| !path.is_empty() && path.chars().all(|c| c.is_alphanumeric() || "/._-+,=@%:".contains(c)))
+}
+
+/// The shell command, run on the source host, copying `src` to `target`
+/// (`host:path`) with the source host's own scp.  Every word, like the
+/// destination's `-o` options `dst_args`, is already quoted.
+pub fn direct_command(src: &OsStr, target: &OsStr, dst_args: &[String]) -> OsString {
+    let mut words = vec!["scp", "-q", "-o", "BatchMode=yes", "-o", "ConnectTimeout=10"];
+    words.extend(dst_args.iter().map(String::as_str));
+    let mut command = OsString::from(format!("{} ", words.join(" ")));
+    command.push(src);
+    command.push(" ");
+    command.push(target);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn the_quicker_ways_come_first_and_plain_names_go_direct() {
+        assert_eq!(RelayChoice::parse("auto").unwrap().order(), [Relay::Direct, Relay::Scp3, Relay::Staged]);
+        assert_eq!(RelayChoice::parse("scp3").unwrap().order(), [Relay::Scp3]);
+        assert_eq!(RelayChoice::parse("tar"), None);
+
+        assert!(direct_safe(Path::new("/srv/photos/2024-06/IMG_0001.jpg")));
+        for path in ["/srv/new dir/a.txt", "/srv/it's.txt", "/srv/$HOME", "/srv/a;b", ""] {
+            assert!(!direct_safe(Path::new(path)), "{:?}", path);
+        }
+        assert!(!direct_safe(Path::new(OsStr::from_bytes(b"/srv/caf\xe9.txt"))));
+        assert_eq!(
+            direct_command("'/srv/a.txt'".as_ref(), "'nas2:/backup/a.txt'".as_ref(), &["-o".to_string(), "Port=2222".to_string()]),
+            "scp -q -o BatchMode=yes -o ConnectTimeout=10 -o Port=2222 '/srv/a.txt' 'nas2:/backup/a.txt'"
+        );
+
+        let decision =
+            Decision { relay: Relay::Scp3, fallbacks: vec!["direct: Host key verification failed.".to_string()], staged: 2 };
+        assert_eq!(
+            decision.describe(),
+            "Between hosts: scp -3 through this machine (direct: Host key verification failed.), 2 file(s) staged for their names"
+        );
+    }
+}
@@ -0,0 +1,147 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+// ── Remote locations ───────────────────────────────────────────────────
+//
+// A source or destination on another host is written the way scp takes
+// it, `[user@]host:path`, with an IPv6 address in brackets
+// (`[2001:db8::7]:/backup`), or as a URL, `ssh://[user@]host[:port]/path`,
+// which is the only way to give a port.  Anything whose part before the
+// first `:` holds a `/` is a local path, so `./notes:old` and
+// `/mnt/a:b` stay local.
+//
+// The workers name a host by `target()`, which ssh, scp and rsync all
+// accept in front of `:path`; the ssh module supplies the port and the
+// bare IPv6 address for each command to it.
+
+/// A parsed remote source or destination.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Remote {
+    pub user: Option<String>,
+    /// Without brackets, also for an IPv6 address.
+    pub host: String,
+    pub port: Option<u16>,
+    pub path: String,
+}
+
+impl Remote {
+    /// The remote location `text` names, or `None` for a local path.
+    pub fn parse(text: &str) -> Option<Remote> {
+        match text.strip_prefix("ssh://") {
+            Some(url) => parse_url(url),
+            None => parse_scp(text),
+        }
+    }
+
+    /// `user@host`, the host as ssh, scp and rsync are given it, with an
+    /// IPv6 address in brackets.
+    pub fn target(&self) -> String {
+        let host = if self.host.contains(':') { format!("[{}]", self.host) } else { self.host.clone() };
+        match &self.user {
+            Some(user) => format!("{}@{}", user, host),
+            None => host,
+        }
+    }
+}
+
+/// `user@` at the start of `text`, and the rest.  An empty user is no user.
+fn split_user(text: &str) -> (Option<&str>, &str) {
+    match text.split_once('@') {
+        Some((user, rest)) if !user.is_empty() && !user.contains(['/', ':', '[']) => (Some(user), rest),
+        _ => (None, text),
+    }
+}
+
+/// A bracketed IPv6 address at the start of `text`, and what follows it.
+fn split_bracketed(text: &str) -> Option<(&str, &str)> {
+    let (address, rest) = text.strip_prefix('[')?.split_once(']')?;
+    (!address.is_empty() && address.contains(':')).then_some((address, rest))
+}
+
+fn parse_scp(text: &str) -> Option<Remote> {
+    let (user, rest) = split_user(text);
+    let (host, path) = match split_bracketed(rest) {
+        Some((address, after)) => (address, after.strip_prefix(':')?),
+        None => rest.split_once(':')?,
+    };
+    if host.is_empty() || host.contains(['/', '@']) || path.is_empty() {
+        return None;
+    }
+    let user = user.map(str::to_string);
+    Some(Remote { user, host: host.to_string(), port: None, path: path.to_string() })
+}
+
+fn parse_url(url: &str) -> Option<Remote> {
+    let (authority, path) = url.split_once('/')?;
+    // `ssh://nas/~/backup` is relative to the home folder, as with scp.
+    let path = match path.strip_prefix("~/") {
+        Some(home) => format!("~/{}", home),
+        None => format!("/{}", path),
+    };
+    let (user, hostport) = split_user(authority);
+    let (host, port) = match split_bracketed(hostport) {
+        Some((address, "")) => (address, None),
+        Some((address, after)) => (address, Some(after.strip_prefix(':')?)),
+        None => match hostport.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (hostport, None),
+        },
+    };
+    if host.is_empty() || host.contains(['@', '[', ']']) {
+        return None;
+    }
+    let port = match port {
+        Some(port) => Some(port.parse::<u16>().ok().filter(|&p| p > 0)?),
+        None => None,
+    };
+    Some(Remote { user: user.map(str::to_string), host: host.to_string(), port, path })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(user: Option<&str>, host: &str, port: Option<u16>, path: &str) -> Option<Remote> {
+        Some(Remote { user: user.map(str::to_string), host: host.to_string(), port, path: path.to_string() })
+    }
+
+    #[test]
+    fn parses_scp_style_locations() {
+        assert_eq!(Remote::parse("nas:/backup"), remote(None, "nas", None, "/backup"));
+        assert_eq!(Remote::parse("ann@nas:photos"), remote(Some("ann"), "nas", None, "photos"));
+        assert_eq!(Remote::parse("nas:2222:/backup"), remote(None, "nas", None, "2222:/backup"));
+        assert_eq!(Remote::parse("[2001:db8::7]:/backup"), remote(None, "2001:db8::7", None, "/backup"));
+        assert_eq!(Remote::parse("ann@[fe80::1%eth0]:~/in"), remote(Some("ann"), "fe80::1%eth0", None, "~/in"));
+        assert_eq!(Remote::parse("[2001:db8::7]/backup"), None);
+    }
+
+    #[test]
+    fn parses_ssh_urls() {
+        assert_eq!(Remote::parse("ssh://ann@nas:2222/backup"), remote(Some("ann"), "nas", Some(2222), "/backup"));
+        assert_eq!(Remote::parse("ssh://nas/srv/data"), remote(None, "nas", None, "/srv/data"));
+        assert_eq!(Remote::parse("ssh://nas/~/in"), remote(None, "nas", None, "~/in"));
+        assert_eq!(Remote::parse("ssh://[2001:db8::7]:22022/b"), remote(None, "2001:db8::7", Some(22022), "/b"));
+        assert_eq!(Remote::parse("ssh://ann@[::1]/b"), remote(Some("ann"), "::1", None, "/b"));
+        assert_eq!(Remote::parse("ssh://nas/"), remote(None, "nas", None, "/"));
+        for bad in ["ssh://nas", "ssh://nas:ssh/b", "ssh://nas:0/b", "ssh://nas:70000/b", "ssh://:22/b"] {
+            assert_eq!(Remote::parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn local_paths_with_a_colon_stay_local() {
+        for local in ["/mnt/a:b", "./notes:old", "photos/2024:05", "relative", "/srv/data", "nas:", ":/x", "@nas:/x"] {
+            assert_eq!(Remote::parse(local), None, "{}", local);
+        }
+    }
+
+    #[test]
+    fn targets_bracket_ipv6() {
+        let target = |text: &str| Remote::parse(text).unwrap().target();
+        assert_eq!(target("ann@nas:/b"), "ann@nas");
+        assert_eq!(target("ssh://nas:2222/b"), "nas");
+        assert_eq!(target("ssh://ann@[2001:db8::7]:2222/b"), "ann@[2001:db8::7]");
+        assert_eq!(target("[::1]:/b"), "[::1]");
+    }
+}
@@ -0,0 +1,517 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use regex_lite::Regex;
+
+use crate::escape_non_utf8;
+use crate::paths::{self, Dir};
+use crate::timeutil::Date;
+
+// ── Rename rules ───────────────────────────────────────────────────────
+//
+// Small mechanical renames applied to destination names as files are
+// transferred: characters a web server chokes on, `.JPG` extensions, a
+// date in front of each photo.  Rules run in order on the part of the
+// destination path below the destination folder, after the path has been
+// built and before anything checks whether it already exists, so conflict
+// handling sees the final names.  "Remove spaces from filenames" is the
+// rule `replace-chars/ /`.
+//
+// A rule is written `kind` or `kind/argument/argument`.  A name can never
+// contain a slash, so the arguments never need escaping:
+//
+//   replace-chars/#&/_       each of `#` and `&` becomes `_` (or is removed
+//                            when nothing follows the last slash)
+//   case-fold-extension      `IMG_0001.JPG` becomes `IMG_0001.jpg`
+//   regex/^IMG_(\d+)/photo-$1   a substitution on each name
+//   date-prefix/_            `2026-03-14_IMG_0001.jpg`, from the source's
+//                            modification date (UTC, or local time with
+//                            `--timestamps local`)
+//
+// Character replacements and substitutions apply to every folder and file
+// name below the destination; the extension and date rules to file names
+// only.  Each built-in rule leaves an already renamed name alone, so a
+// pipeline without substitutions is idempotent.
+//
+// Local names that aren't UTF-8 keep their bytes.  The rules that can work
+// on bytes (ASCII character replacements, ASCII extensions, date prefixes)
+// still apply to them; substitutions, and replacements of other
+// characters, leave them alone.
+
+const FILE_NAME: &str = "rename-rules.conf";
+
+/// How many renames are kept as examples for the report.
+const SAMPLE_LIMIT: usize = 5;
+
+/// One step of the pipeline.
+#[derive(Clone, Debug)]
+pub enum Rule {
+    /// Replace each character of `chars` with `with` (nothing removes it).
+    ReplaceChars { chars: String, with: String },
+    /// Lower-case the file name's extension.
+    CaseFoldExtension,
+    /// Replace every match of `pattern` in each name.
+    Regex { pattern: Regex, replacement: String },
+    /// Put the source's modification date and `separator` before the file
+    /// name.  Sources whose date is unknown (remote ones) keep their name.
+    DatePrefix { separator: String },
+}
+
+impl PartialEq for Rule {
+    fn eq(&self, other: &Rule) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+impl Rule {
+    /// The rule behind "Remove spaces from filenames".
+    pub fn strip_spaces() -> Rule {
+        Rule::ReplaceChars { chars: " ".to_string(), with: String::new() }
+    }
+
+    /// Parse a rule written as `kind/argument/…`, or say what is wrong.
+    pub fn parse(spec: &str) -> Result<Rule, String> {
+        let mut parts = spec.split('/');
+        let kind = parts.next().unwrap_or_default().trim();
+        let args: Vec<&str> = parts.collect();
+        let arity = |n: usize, usage: &str| {
+            if args.len() == n {
+                Ok(())
+            } else {
+                Err(format!("'{}': expected {}", spec, usage))
+            }
+        };
+        match kind {
+            "replace-chars" => {
+                arity(2, "replace-chars/CHARACTERS/REPLACEMENT")?;
+                let (chars, with) = (args[0], args[1]);
+                if chars.is_empty() {
+                    return Err(format!("'{}': no characters to replace", spec));
+                }
+                // Otherwise a second pass would change the name again.
+                if with.chars().any(|c| chars.contains(c)) {
+                    return Err(format!("'{}': the replacement contains a character being replaced", spec));
+                }
+                Ok(Rule::ReplaceChars { chars: chars.to_string(), with: with.to_string() })
+            }
+            "case-fold-extension" => {
+                arity(0, "case-fold-extension with nothing after it")?;
+                Ok(Rule::CaseFoldExtension)
+            }
+            "regex" => {
+                arity(2, "regex/PATTERN/REPLACEMENT")?;
+                let pattern = Regex::new(args[0]).map_err(|e| format!("'{}': {}", spec, e))?;
+                Ok(Rule::Regex { pattern, replacement: args[1].to_string() })
+            }
+            "date-prefix" => match args[..] {
+                [] => Ok(Rule::DatePrefix { separator: "_".to_string() }),
+                [separator] => Ok(Rule::DatePrefix { separator: separator.to_string() }),
+                _ => Err(format!("'{}': expected date-prefix or date-prefix/SEPARATOR", spec)),
+            },
+            _ => Err(format!(
+                "'{}': unknown rule; use replace-chars, case-fold-extension, regex or date-prefix",
+                spec
+            )),
+        }
+    }
+
+    /// Whether the rule renames folders as well as files.
+    fn renames_folders(&self) -> bool {
+        matches!(self, Rule::ReplaceChars { .. } | Rule::Regex { .. })
+    }
+
+    fn apply(&self, name: &str, mtime: Option<SystemTime>) -> String {
+        match self {
+            Rule::ReplaceChars { chars, with } => name
+                .chars()
+                .map(|c| if chars.contains(c) { with.clone() } else { c.to_string() })
+                .collect(),
+            Rule::CaseFoldExtension => match name.rsplit_once('.') {
+                // A leading dot marks a hidden file, not an extension.
+                Some((stem, ext)) if !stem.is_empty() => format!("{}.{}", stem, ext.to_lowercase()),
+                _ => name.to_string(),
+            },
+            Rule::Regex { pattern, replacement } => pattern.replace_all(name, replacement.as_str()).into_owned(),
+            Rule::DatePrefix { separator } => match mtime {
+                Some(time) => {
+                    let prefix = format!("{}{}", Date::of(time), separator);
+                    if name.starts_with(&prefix) {
+                        name.to_string()
+                    } else {
+                        format!("{}{}", prefix, name)
+                    }
+                }
+                None => name.to_string(),
+            },
+        }
+    }
+
+    /// `apply` for a name that isn't UTF-8, by the rules that can work on
+    /// its bytes; `None` leaves the name alone.
+    fn apply_bytes(&self, name: &[u8], mtime: Option<SystemTime>) -> Option<Vec<u8>> {
+        match self {
+            Rule::ReplaceChars { chars, with } if chars.is_ascii() && with.is_ascii() => Some(
+                name.iter()
+                    .flat_map(|b| if chars.as_bytes().contains(b) { with.as_bytes() } else { std::slice::from_ref(b) })
+                    .copied()
+                    .collect(),
+            ),
+            Rule::CaseFoldExtension => {
+                // As above, a leading dot isn't an extension.
+                let dot = name.iter().rposition(|&b| b == b'.').filter(|&dot| dot > 0)?;
+                if !name[dot..].is_ascii() {
+                    return None;
+                }
+                let mut folded = name.to_vec();
+                folded[dot..].make_ascii_lowercase();
+                Some(folded)
+            }
+            Rule::DatePrefix { separator } => {
+                let prefix = format!("{}{}", Date::of(mtime?), separator);
+                Some(match name.starts_with(prefix.as_bytes()) {
+                    true => name.to_vec(),
+                    false => [prefix.as_bytes(), name].concat(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    /// The rule in the form `parse` reads.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::ReplaceChars { chars, with } => write!(f, "replace-chars/{}/{}", chars, with),
+            Rule::CaseFoldExtension => write!(f, "case-fold-extension"),
+            Rule::Regex { pattern, replacement } => write!(f, "regex/{}/{}", pattern.as_str(), replacement),
+            Rule::DatePrefix { separator } => write!(f, "date-prefix/{}", separator),
+        }
+    }
+}
+
+/// What a run renamed, for its report.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenameLog {
+    /// Files whose destination path the rules changed.
+    pub count: usize,
+    /// The first few of them.
+    pub samples: Vec<Sample>,
+}
+
+/// One renamed file, as paths relative to the destination.  Names that
+/// aren't UTF-8 have their other bytes written `\xNN` and are flagged.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub from: String,
+    pub to: String,
+    pub non_utf8: bool,
+}
+
+impl RenameLog {
+    /// e.g. "Renamed 12 file(s), e.g. a b.JPG → ab.jpg".
+    pub fn describe(&self) -> String {
+        let examples: Vec<String> = self.samples.iter().map(|s| format!("{} → {}", s.from, s.to)).collect();
+        if examples.is_empty() {
+            format!("Renamed {} file(s)", self.count)
+        } else {
+            format!("Renamed {} file(s), e.g. {}", self.count, examples.join(", "))
+        }
+    }
+}
+
+/// An ordered list of rules, shared with the worker, which records what
+/// it renames so the report can say so afterwards.
+#[derive(Debug, Default)]
+pub struct Renamer {
+    rules: Vec<Rule>,
+    /// The name given to the one file of a run whose destination names
+    /// the file itself; the rules don't touch it.
+    target: Option<String>,
+    log: Mutex<RenameLog>,
+}
+
+impl Renamer {
+    pub fn new(rules: Vec<Rule>) -> Renamer {
+        Renamer { rules, target: None, log: Mutex::default() }
+    }
+
+    /// Send the run's single file to `name`, directly in the destination
+    /// folder, whatever its own name and parent folders.
+    pub fn with_target(self, name: String) -> Renamer {
+        Renamer { target: Some(name), ..self }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// `rel`, a `/`-separated path below the destination folder ending in
+    /// a file name, with every rule applied.  `mtime` is the source's
+    /// modification time, when known.
+    pub fn apply(&self, rel: &str, mtime: Option<SystemTime>) -> String {
+        if let Some(name) = &self.target {
+            return name.clone();
+        }
+        let parts: Vec<&str> = rel.split('/').collect();
+        let last = parts.len() - 1;
+        parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                // The empty parts of `a//b` and the `.`/`..` components are
+                // not names.
+                if matches!(*part, "" | "." | "..") {
+                    return part.to_string();
+                }
+                self.apply_name(part, i == last, mtime)
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// `apply` for a local relative path, name by name, so that names that
+    /// aren't UTF-8 keep their bytes.
+    pub fn apply_path(&self, rel: &Path, mtime: Option<SystemTime>) -> PathBuf {
+        if let Some(text) = rel.to_str() {
+            return PathBuf::from(self.apply(text, mtime));
+        }
+        if let Some(name) = &self.target {
+            return PathBuf::from(name);
+        }
+        let last = rel.components().count().saturating_sub(1);
+        rel.components()
+            .enumerate()
+            .map(|(i, component)| match component {
+                Component::Normal(part) => match part.to_str() {
+                    Some(text) => PathBuf::from(self.apply_name(text, i == last, mtime)),
+                    None => {
+                        let renamed = self
+                            .rules_for(i == last)
+                            .fold(part.as_bytes().to_vec(), |name, rule| rule.apply_bytes(&name, mtime).unwrap_or(name));
+                        // As for `apply_name`.
+                        match renamed.as_slice() {
+                            b"" | b"." | b".." => PathBuf::from(part),
+                            _ => PathBuf::from(OsStr::from_bytes(&renamed)),
+                        }
+                    }
+                },
+                other => PathBuf::from(other.as_os_str()),
+            })
+            .collect()
+    }
+
+    /// The rules that apply to a file name, or to a folder name.
+    fn rules_for(&self, file: bool) -> impl Iterator<Item = &Rule> {
+        self.rules.iter().filter(move |rule| file || rule.renames_folders())
+    }
+
+    /// The rules applied to the single file or folder name `name`.
+    fn apply_name(&self, name: &str, file: bool, mtime: Option<SystemTime>) -> String {
+        let renamed = self.rules_for(file).fold(name.to_string(), |name, rule| rule.apply(&name, mtime));
+        // A rule that would leave no name at all is ignored.
+        if matches!(renamed.as_str(), "" | "." | "..") {
+            name.to_string()
+        } else {
+            renamed
+        }
+    }
+
+    /// `apply_path`, recording the rename for the report when it changes
+    /// `rel`.
+    pub fn rename_path(&self, rel: &Path, mtime: Option<SystemTime>) -> PathBuf {
+        let renamed = self.apply_path(rel, mtime);
+        if renamed != rel {
+            let (from, from_bad) = escape_non_utf8(rel.as_os_str().as_bytes());
+            let (to, to_bad) = escape_non_utf8(renamed.as_os_str().as_bytes());
+            self.record(Sample { from, to, non_utf8: from_bad || to_bad });
+        }
+        renamed
+    }
+
+    fn record(&self, sample: Sample) {
+        // Naming the target isn't a rename the rules made.
+        if self.target.is_some() {
+            return;
+        }
+        let mut log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        log.count += 1;
+        if log.samples.len() < SAMPLE_LIMIT {
+            log.samples.push(sample);
+        }
+    }
+
+    pub fn log(&self) -> RenameLog {
+        self.log.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Parse one rule per line, skipping blank lines and `#` comments.
+pub fn parse_rules(text: &str) -> Result<Vec<Rule>, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Rule::parse)
+        .collect()
+}
+
+pub fn rules_to_text(rules: &[Rule]) -> String {
+    let mut text = String::from("# Kosmokopy rename rules, applied in order\n");
+    for rule in rules {
+        text.push_str(&format!("{}\n", rule));
+    }
+    text
+}
+
+/// The rules saved from the editor; none when there is no file yet or it
+/// cannot be read.
+pub fn load() -> Vec<Rule> {
+    paths::dir(Dir::Config)
+        .and_then(|d| fs::read_to_string(d.join(FILE_NAME)).ok())
+        .and_then(|text| parse_rules(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(rules: &[Rule]) -> io::Result<()> {
+    fs::write(paths::file(Dir::Config, FILE_NAME)?, rules_to_text(rules))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn renamer(specs: &[&str]) -> Renamer {
+        Renamer::new(specs.iter().map(|s| Rule::parse(s).unwrap()).collect())
+    }
+
+    /// 2026-03-14 10:00 UTC.
+    fn pi_day() -> Option<SystemTime> {
+        Some(UNIX_EPOCH + Duration::from_secs(1_773_482_400))
+    }
+
+    #[test]
+    fn each_rule() {
+        let r = renamer(&["replace-chars/#&/_"]);
+        assert_eq!(r.apply("Q&A #2/notes #1.txt", None), "Q_A _2/notes _1.txt");
+        let r = renamer(&["replace-chars/ /"]);
+        assert_eq!(r.apply("My Photos/IMG 1.JPG", None), "MyPhotos/IMG1.JPG");
+        let r = renamer(&["case-fold-extension"]);
+        assert_eq!(r.apply("DCIM.OLD/IMG_0001.JPG", None), "DCIM.OLD/IMG_0001.jpg");
+        assert_eq!(r.apply(".BASHRC", None), ".BASHRC");
+        assert_eq!(r.apply("README", None), "README");
+        let r = renamer(&[r"regex/^IMG_(\d+)/photo-$1"]);
+        assert_eq!(r.apply("IMG_2024/IMG_0001.jpg", None), "photo-2024/photo-0001.jpg");
+        let r = renamer(&["date-prefix"]);
+        assert_eq!(r.apply("trip/a.jpg", pi_day()), "trip/2026-03-14_a.jpg");
+        assert_eq!(r.apply("trip/a.jpg", None), "trip/a.jpg");
+    }
+
+    #[test]
+    fn rules_run_in_order() {
+        let mtime = pi_day();
+        // The extension is folded before the substitution looks for it ...
+        let r = renamer(&["case-fold-extension", r"regex/\.jpg$/.jpeg"]);
+        assert_eq!(r.apply("A.JPG", mtime), "A.jpeg");
+        // ... and not when the substitution runs first.
+        let r = renamer(&[r"regex/\.jpg$/.jpeg", "case-fold-extension"]);
+        assert_eq!(r.apply("A.JPG", mtime), "A.jpg");
+        // A later rule sees the date an earlier one added.
+        let r = renamer(&["date-prefix/ ", "replace-chars/ /_"]);
+        assert_eq!(r.apply("a b.txt", mtime), "2026-03-14_a_b.txt");
+        let r = renamer(&["replace-chars/ /_", "date-prefix/ "]);
+        assert_eq!(r.apply("a b.txt", mtime), "2026-03-14 a_b.txt");
+    }
+
+    #[test]
+    fn built_in_rules_are_idempotent() {
+        let r = renamer(&["replace-chars/#& /_", "case-fold-extension", "date-prefix/-"]);
+        for rel in ["a b/C&D #1.JPG", "x.TAR.GZ", ".hidden", "plain"] {
+            let once = r.apply(rel, pi_day());
+            assert_eq!(r.apply(&once, pi_day()), once, "{}", rel);
+        }
+    }
+
+    #[test]
+    fn names_are_never_emptied() {
+        let r = renamer(&["replace-chars/#/", "regex/.*/"]);
+        assert_eq!(r.apply("###/x", None), "###/x");
+        assert_eq!(renamer(&["replace-chars/x/."]).apply("x/xx", None), "x/xx");
+        assert_eq!(renamer(&["replace-chars/a/b"]).apply("./a//a", None), "./b//b");
+    }
+
+    #[test]
+    fn renames_are_counted_and_sampled() {
+        let r = renamer(&["case-fold-extension"]);
+        for i in 0..8 {
+            r.rename_path(Path::new(&format!("{}.JPG", i)), None);
+        }
+        r.rename_path(Path::new("already.jpg"), None);
+        let log = r.log();
+        assert_eq!(log.count, 8);
+        assert_eq!(log.samples.len(), SAMPLE_LIMIT);
+        assert_eq!(log.samples[0], Sample { from: "0.JPG".to_string(), to: "0.jpg".to_string(), non_utf8: false });
+        assert!(log.describe().starts_with("Renamed 8 file(s), e.g. 0.JPG → 0.jpg, 1.JPG → 1.jpg"));
+    }
+
+    #[test]
+    fn names_that_are_not_utf8_keep_their_bytes() {
+        let rel = Path::new(OsStr::from_bytes(b"My Photos/IMG 1\xff.JPG"));
+        let r = renamer(&["replace-chars/ /_", "case-fold-extension", "date-prefix"]);
+        assert_eq!(r.apply_path(rel, pi_day()).as_os_str().as_bytes(), b"My_Photos/2026-03-14_IMG_1\xff.jpg");
+        // Substitutions, and replacements of other characters, leave them
+        // alone.
+        let r = renamer(&["regex/IMG/photo", "replace-chars/é/e"]);
+        assert_eq!(r.apply_path(rel, None), rel);
+        assert_eq!(r.apply_path(Path::new("IMG é.txt"), None), Path::new("photo e.txt"));
+
+        let r = renamer(&["replace-chars/ /"]);
+        assert_eq!(r.rename_path(rel, None).as_os_str().as_bytes(), b"MyPhotos/IMG1\xff.JPG");
+        assert_eq!(
+            r.log().samples,
+            [Sample { from: "My Photos/IMG 1\\xff.JPG".to_string(), to: "MyPhotos/IMG1\\xff.JPG".to_string(), non_utf8: true }]
+        );
+    }
+
+    #[test]
+    fn a_target_name_replaces_the_whole_path() {
+        let r = renamer(&["case-fold-extension"]).with_target("a.conf.new".to_string());
+        assert_eq!(r.rename_path(Path::new("etc/a.CONF"), None), Path::new("a.conf.new"));
+        assert_eq!(r.log().count, 0);
+    }
+
+    #[test]
+    fn specs_round_trip_and_bad_ones_are_refused() {
+        let text = "# comment\nreplace-chars/#&/_\n\ncase-fold-extension\nregex/^IMG_(\\d+)/photo-$1\ndate-prefix/_\n";
+        let rules = parse_rules(text).unwrap();
+        assert_eq!(rules.len(), 4);
+        assert_eq!(parse_rules(&rules_to_text(&rules)).unwrap(), rules);
+        assert_eq!(Rule::parse("replace-chars/ /").unwrap(), Rule::strip_spaces());
+
+        for bad in [
+            "replace-chars/#",
+            "replace-chars//_",
+            "replace-chars/ab/b",
+            "case-fold-extension/x",
+            "regex/(/x",
+            "date-prefix/_/x",
+            "upper-case",
+        ] {
+            assert!(Rule::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
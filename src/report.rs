@@ -0,0 +1,208 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::ffi::OsString;
+
+use crate::details::Details;
+use crate::stats::GroupStats;
+
+// ── What a run did ─────────────────────────────────────────────────────
+//
+// Every worker ends by sending one `TransferReport`: how many files it
+// copied, the files it skipped and the ones that failed, what the
+// exclusions left out, and the results per top-level source folder.  The
+// summary line, the result dialog and the CLI's JSON are all read from it.
+//
+// Skips and errors keep the `<source>: <reason>` text the workers wrote,
+// so what the user reads doesn't change.  `FileOutcome::from_message`
+// sorts each one by what happened to the file — identical at the
+// destination, kept for a conflict, skipped for another reason, failed —
+// the same way for the result dialog's groups, the JSON `outcomes` counts
+// and the detail report.  The lists keep their first entries only (see
+// `Details`), but every entry is counted by outcome as it arrives.
+
+/// What happened to one file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Copied,
+    /// The destination already held the same contents.
+    SkippedIdentical,
+    /// A different file at the destination was kept.
+    SkippedConflict,
+    /// Left alone for another reason, e.g. a link the policy skips.
+    Skipped(String),
+    /// Left out by the pattern named.
+    Excluded(String),
+    Failed(String),
+}
+
+impl Outcome {
+    /// What a skip or error `reason` says happened.
+    pub fn of(reason: &str, error: bool) -> Outcome {
+        match reason {
+            _ if error => Outcome::Failed(reason.to_string()),
+            "identical at destination" => Outcome::SkippedIdentical,
+            r if r.starts_with("already exists at destination")
+                || r.starts_with("different version exists at destination")
+                || r == "destination is newer" =>
+            {
+                Outcome::SkippedConflict
+            }
+            _ => Outcome::Skipped(reason.to_string()),
+        }
+    }
+}
+
+/// One file and what became of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileOutcome {
+    pub src: String,
+    pub dst: Option<String>,
+    pub outcome: Outcome,
+    pub bytes: Option<u64>,
+}
+
+impl FileOutcome {
+    /// A worker's skip or error message, `<source>: <reason>`.
+    pub fn from_message(message: &str, error: bool) -> FileOutcome {
+        let (src, reason) = message.split_once(": ").unwrap_or((message, ""));
+        FileOutcome { src: src.to_string(), dst: None, outcome: Outcome::of(reason, error), bytes: None }
+    }
+}
+
+/// How many files ended each way.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Tally {
+    pub copied: usize,
+    pub identical: usize,
+    pub conflicts: usize,
+    /// Skipped for other reasons.
+    pub skipped: usize,
+    pub excluded: usize,
+    pub failed: usize,
+}
+
+impl Tally {
+    /// Count one more file ending with `outcome`.
+    pub fn add(&mut self, outcome: &Outcome) {
+        match outcome {
+            Outcome::Copied => self.copied += 1,
+            Outcome::SkippedIdentical => self.identical += 1,
+            Outcome::SkippedConflict => self.conflicts += 1,
+            Outcome::Skipped(_) => self.skipped += 1,
+            Outcome::Excluded(_) => self.excluded += 1,
+            Outcome::Failed(_) => self.failed += 1,
+        }
+    }
+
+    /// The `outcomes` field of the CLI's result.
+    pub fn to_json(self) -> String {
+        format!(
+            "{{\"copied\":{},\"identical\":{},\"conflicts\":{},\"skipped\":{},\"excluded\":{},\"failed\":{}}}",
+            self.copied, self.identical, self.conflicts, self.skipped, self.excluded, self.failed
+        )
+    }
+}
+
+/// What a finished or cancelled run did; the last message of a worker.
+#[derive(Debug)]
+pub struct TransferReport {
+    pub copied: usize,
+    pub skipped: Details,
+    pub excluded_files: usize,
+    pub excluded_dirs: usize,
+    pub errors: Details,
+    /// Source paths of the files that errored (`host:/path` for remote sources).
+    pub failed: Vec<OsString>,
+    /// Results per top-level source folder, where the worker got that far.
+    pub groups: Vec<GroupStats>,
+}
+
+impl TransferReport {
+    /// A run that ended before sending anything: what its scan skipped
+    /// and left out.
+    pub fn nothing_sent(skipped: Details, excluded_files: usize, excluded_dirs: usize) -> TransferReport {
+        TransferReport {
+            copied: 0,
+            skipped,
+            excluded_files,
+            excluded_dirs,
+            errors: Details::errors(),
+            failed: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+
+    /// Every file counted by how it ended.
+    pub fn tally(&self) -> Tally {
+        let (skips, errors) = (self.skipped.tally(), self.errors.tally());
+        Tally {
+            copied: self.copied,
+            identical: skips.identical,
+            conflicts: skips.conflicts,
+            skipped: skips.skipped,
+            excluded: self.excluded_files,
+            failed: errors.failed,
+        }
+    }
+
+    /// The skipped files kept, sorted by outcome: identical, conflicts,
+    /// then the rest, each in the order they came.
+    pub fn skipped_by_outcome(&self) -> [(&'static str, Vec<&str>); 3] {
+        let mut groups = [("Identical at destination", Vec::new()), ("Existing file kept", Vec::new()), ("Skipped", Vec::new())];
+        for entry in self.skipped.iter() {
+            let group = match FileOutcome::from_message(entry, false).outcome {
+                Outcome::SkippedIdentical => 0,
+                Outcome::SkippedConflict => 1,
+                _ => 2,
+            };
+            groups[group].1.push(entry.as_str());
+        }
+        groups
+    }
+
+    /// Write out any entries still buffered for the spill files.
+    pub fn flush(&mut self) {
+        self.skipped.flush();
+        self.errors.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_and_errors_are_sorted_by_outcome() {
+        let mut report = TransferReport::nothing_sent(Details::skipped(), 2, 1);
+        report.copied = 5;
+        for entry in [
+            "/in/a.txt: identical at destination",
+            "/in/b.txt: already exists at destination",
+            "/in/c.txt: destination is newer",
+            "/in/docs/latest: symbolic link, skipped",
+            "/in/d.txt: identical at destination",
+        ] {
+            report.skipped.push(entry.to_string());
+        }
+        report.errors.push("/in/e.txt: copy failed: disk full".to_string());
+
+        assert_eq!(
+            report.tally(),
+            Tally { copied: 5, identical: 2, conflicts: 2, skipped: 1, excluded: 2, failed: 1 }
+        );
+        let [identical, conflicts, other] = report.skipped_by_outcome();
+        assert_eq!(identical.1, ["/in/a.txt: identical at destination", "/in/d.txt: identical at destination"]);
+        assert_eq!(conflicts.1.len(), 2);
+        assert_eq!(other.1, ["/in/docs/latest: symbolic link, skipped"]);
+        assert_eq!(
+            FileOutcome::from_message("/in/e.txt: copy failed: disk full", true).outcome,
+            Outcome::Failed("copy failed: disk full".to_string())
+        );
+        assert_eq!(
+            report.tally().to_json(),
+            "{\"copied\":5,\"identical\":2,\"conflicts\":2,\"skipped\":1,\"excluded\":2,\"failed\":1}"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use crate::timeutil::Date;
+
+// ── Dated archive folders and retention ────────────────────────────────
+//
+// A destination whose last folder name contains `{date}` (e.g.
+// `/backups/weekly-{date}`) is a template: each run goes into a folder
+// named with the day's date.  With `--retain N`, once a run finishes
+// cleanly the sibling folders named by the same template are sorted by the
+// date in their name and all but the newest N are deleted.  This deletes
+// directories, so every candidate has to pass `is_safe_target` first.
+
+/// The placeholder replaced by the date, as `YYYY-MM-DD` (UTC unless
+/// `--timestamps local`).
+pub const DATE_TOKEN: &str = "{date}";
+
+/// A folder name with one `{date}` in it, e.g. `weekly-{date}`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    prefix: String,
+    suffix: String,
+}
+
+impl Template {
+    /// Parse a folder name; `None` unless it holds exactly one `{date}`.
+    pub fn parse(name: &str) -> Option<Template> {
+        let (prefix, suffix) = name.split_once(DATE_TOKEN)?;
+        if suffix.contains(DATE_TOKEN) || name.contains('/') {
+            return None;
+        }
+        Some(Template { prefix: prefix.to_string(), suffix: suffix.to_string() })
+    }
+
+    pub fn expand(&self, date: Date) -> String {
+        format!("{}{}{}", self.prefix, date, self.suffix)
+    }
+
+    /// The date in `name` if the template produced it.
+    pub fn match_date(&self, name: &str) -> Option<Date> {
+        let date = name.strip_prefix(&self.prefix)?.strip_suffix(&self.suffix)?;
+        Date::parse(date)
+    }
+}
+
+/// Split a destination (local or `host:/path`) whose last component is a
+/// template into `(base, template)`, e.g. `nas:/backups` and `weekly-{date}`.
+pub fn split_dest(dst: &str) -> Option<(&str, Template)> {
+    let dst = dst.trim_end_matches('/');
+    let (base, last) = dst.rsplit_once('/')?;
+    let template = Template::parse(last)?;
+    // The folder a template names must live somewhere: not at `/`, and not
+    // directly under a bare `host:`.
+    (!base.is_empty() && !base.ends_with(':')).then_some((base, template))
+}
+
+/// The names to delete so that only the newest `keep` dated folders stay.
+/// Names the template didn't produce are never returned; folders with the
+/// same date are ordered by name.
+pub fn to_prune<'a>(names: &'a [String], template: &Template, keep: usize) -> Vec<&'a str> {
+    let mut dated: Vec<(Date, &str)> = names
+        .iter()
+        .filter_map(|n| template.match_date(n).map(|d| (d, n.as_str())))
+        .collect();
+    dated.sort();
+    let excess = dated.len().saturating_sub(keep);
+    dated.into_iter().take(excess).map(|(_, n)| n).collect()
+}
+
+/// Whether `target` may be deleted when pruning `base`: it must be a
+/// direct child of `base` (no `.`/`..` anywhere, nothing but one extra
+/// component) whose name the template produced.
+pub fn is_safe_target(base: &str, target: &str, template: &Template) -> bool {
+    let base = base.trim_end_matches('/');
+    if base.is_empty() || base.split('/').any(|c| c == "." || c == "..") {
+        return false;
+    }
+    let Some(name) = target.strip_prefix(base).and_then(|rest| rest.strip_prefix('/')) else {
+        return false;
+    };
+    !name.is_empty() && !name.contains('/') && name != "." && name != ".." && template.match_date(name).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weekly() -> Template {
+        Template::parse("weekly-{date}").unwrap()
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn templates() {
+        let t = Template::parse("pics-{date}.bak").unwrap();
+        let date = Date { year: 2026, month: 3, day: 7 };
+        assert_eq!(t.expand(date), "pics-2026-03-07.bak");
+        assert_eq!(t.match_date("pics-2026-03-07.bak"), Some(date));
+        assert_eq!(t.match_date("pics-2026-03-07"), None);
+        assert_eq!(t.match_date("other-2026-03-07.bak"), None);
+        assert_eq!(Template::parse("{date}-{date}"), None);
+        assert_eq!(Template::parse("weekly"), None);
+    }
+
+    #[test]
+    fn split_destinations() {
+        let (base, t) = split_dest("/backups/weekly-{date}/").unwrap();
+        assert_eq!((base, t), ("/backups", weekly()));
+        assert_eq!(split_dest("nas:/srv/weekly-{date}").unwrap().0, "nas:/srv");
+        assert!(split_dest("/weekly-{date}").is_none());
+        assert!(split_dest("nas:weekly-{date}").is_none());
+        assert!(split_dest("/backups/weekly").is_none());
+        assert!(split_dest("/backups/{date}/sub").is_none());
+    }
+
+    #[test]
+    fn prunes_oldest_by_embedded_date() {
+        // Listed out of order, with names the template didn't make.
+        let list = names(&[
+            "weekly-2026-10-17",
+            "weekly-2025-12-31",
+            "weekly-2026-01-07",
+            "weekly-2026-02-30",
+            "weekly-latest",
+            "monthly-2020-01-01",
+            "weekly-2026-10-10",
+        ]);
+        assert_eq!(to_prune(&list, &weekly(), 2), ["weekly-2025-12-31", "weekly-2026-01-07"]);
+        assert_eq!(to_prune(&list, &weekly(), 4), Vec::<&str>::new());
+        assert_eq!(to_prune(&list, &weekly(), 0).len(), 4);
+    }
+
+    #[test]
+    fn safety_guard() {
+        let t = weekly();
+        assert!(is_safe_target("/backups", "/backups/weekly-2026-01-07", &t));
+        assert!(is_safe_target("/backups/", "/backups/weekly-2026-01-07", &t));
+        for (base, target) in [
+            ("/backups", "/backups/weekly-2026-01-07/inner"),
+            ("/backups", "/backups"),
+            ("/backups", "/backups/"),
+            ("/backups", "/backupsweekly-2026-01-07"),
+            ("/backups", "/other/weekly-2026-01-07"),
+            ("/backups", "/backups/../weekly-2026-01-07"),
+            ("/backups/../etc", "/backups/../etc/weekly-2026-01-07"),
+            ("/backups", "/backups/keep-me"),
+            ("/backups", "/backups/weekly-9999-99-99"),
+            ("", "/weekly-2026-01-07"),
+            ("/", "/weekly-2026-01-07"),
+        ] {
+            assert!(!is_safe_target(base, target, &t), "{} {}", base, target);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cancel::CancelFlag;
+
+// ── Retrying a failed file ─────────────────────────────────────────────
+//
+// Over a flaky link a few scp or rsync calls fail part-way through a long
+// run.  With `--retries N` (the window's "Retries per file"), a remote
+// transfer sends a file whose call failed, or whose copy did not match,
+// up to N more times before giving up on it, waiting 1s, 2s, 4s and so on
+// before each attempt.  The wait looks at the cancel flag as it goes, so
+// Cancel still stops the run within a moment.  A file that failed every
+// attempt has its error say how many were made.
+
+/// How often a wait looks to see whether the run was cancelled.
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+/// The most retries `--retries` takes.
+pub const MAX_RETRIES: u32 = 10;
+
+/// The longest wait between two attempts.
+const MAX_DELAY: Duration = Duration::from_secs(64);
+
+/// The wait before retry number `retry` (from 1): 1s, 2s, 4s, …
+pub fn delay(retry: u32) -> Duration {
+    Duration::from_secs(1u64 << retry.saturating_sub(1).min(16)).min(MAX_DELAY)
+}
+
+/// `error`, saying how many attempts were made when there was more than one.
+pub fn gave_up(error: String, attempts: u32) -> String {
+    match attempts {
+        0 | 1 => error,
+        n => format!("{} (gave up after {} attempts)", error, n),
+    }
+}
+
+/// Run `send`, and again after each failure up to `retries` times.  The
+/// error is the last failure's, through `gave_up`.  Cancelling during a
+/// wait gives up at once.
+pub fn attempt<T>(retries: u32, cancel_flag: &CancelFlag, mut send: impl FnMut() -> Result<T, String>) -> Result<T, String> {
+    match send() {
+        Ok(sent) => Ok(sent),
+        Err(e) => again(retries, e, cancel_flag, send),
+    }
+}
+
+/// `attempt` for a file whose first attempt already failed with `error`.
+pub fn again<T>(
+    retries: u32,
+    error: String,
+    cancel_flag: &CancelFlag,
+    mut send: impl FnMut() -> Result<T, String>,
+) -> Result<T, String> {
+    let mut last = error;
+    let mut attempts = 1;
+    while attempts <= retries && wait(delay(attempts), cancel_flag) {
+        attempts += 1;
+        match send() {
+            Ok(sent) => return Ok(sent),
+            Err(e) => last = e,
+        }
+    }
+    Err(gave_up(last, attempts))
+}
+
+/// Sleep for `delay` unless the run is cancelled first; whether it wasn't.
+fn wait(delay: Duration, cancel_flag: &CancelFlag) -> bool {
+    let until = Instant::now() + delay;
+    while !cancel_flag.stopping() {
+        let now = Instant::now();
+        if now >= until {
+            return true;
+        }
+        thread::sleep(CANCEL_POLL.min(until - now));
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cancel::CancelIntent;
+
+    #[test]
+    fn waits_double_up_to_a_limit() {
+        assert_eq!(delay(1), Duration::from_secs(1));
+        assert_eq!(delay(2), Duration::from_secs(2));
+        assert_eq!(delay(3), Duration::from_secs(4));
+        assert_eq!(delay(40), MAX_DELAY);
+        assert_eq!(gave_up("a.txt: scp failed".to_string(), 1), "a.txt: scp failed");
+        assert_eq!(gave_up("a.txt: scp failed".to_string(), 3), "a.txt: scp failed (gave up after 3 attempts)");
+    }
+
+    #[test]
+    fn a_cancelled_run_gives_up_without_waiting() {
+        let cancel_flag = CancelFlag::default();
+        cancel_flag.request(CancelIntent::Stop);
+        let started = Instant::now();
+        assert!(!wait(Duration::from_secs(30), &cancel_flag));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,186 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::coalesce::group_thousands;
+use crate::shown;
+
+// ── Verifying a finished run again ─────────────────────────────────────
+//
+// Copying first and deleting the source by hand later is common; before
+// that deletion it is worth knowing the destination is still intact.  The
+// result dialog of a copy offers "Verify now": every file the run copied
+// is hashed again at both ends and the hashes compared.  While a GUI copy
+// runs, the workers note each verified copy here; the dialog takes the
+// record when the run finishes and keeps it until it closes.  Moves are
+// not recorded, since their sources are gone.
+
+/// How many remote paths one `sha256sum` call hashes.
+pub const HASH_BATCH: usize = 200;
+
+/// One end of a copied file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Location {
+    Local(PathBuf),
+    Remote { host: String, path: PathBuf },
+}
+
+impl Location {
+    pub fn remote(host: &str, path: &Path) -> Location {
+        Location::Remote { host: host.to_string(), path: path.to_path_buf() }
+    }
+
+    /// `/data/a.txt`, or `nas:/srv/a.txt`.
+    pub fn describe(&self) -> String {
+        match self {
+            Location::Local(path) => shown(path),
+            Location::Remote { host, path } => format!("{}:{}", host, shown(path)),
+        }
+    }
+}
+
+/// A file the run copied and verified.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Copied {
+    pub source: Location,
+    pub dest: Location,
+}
+
+/// `None` while no run is being recorded.
+static RECORD: Mutex<Option<Vec<Copied>>> = Mutex::new(None);
+
+/// Record the copies of the run starting now, forgetting any earlier ones.
+pub fn start() {
+    if let Ok(mut record) = RECORD.lock() {
+        *record = Some(Vec::new());
+    }
+}
+
+/// Note a verified copy, if a run is being recorded.
+pub fn note(source: Location, dest: Location) {
+    if let Ok(mut record) = RECORD.lock() {
+        if let Some(record) = record.as_mut() {
+            record.push(Copied { source, dest });
+        }
+    }
+}
+
+/// The copies recorded since `start`; recording stops.
+pub fn take() -> Vec<Copied> {
+    RECORD.lock().ok().and_then(|mut record| record.take()).unwrap_or_default()
+}
+
+/// Hashes of `hex_len` digits from `sha256sum`-style output (`shasum -a
+/// 256`, `b3sum` and `xxhsum -H2` print the same), by path.  Lines for
+/// names the tools had to escape (those with a backslash or a newline) are
+/// left out, so those files read as not hashed.  Paths keep their bytes.
+pub fn parse_hashes(output: &[u8], hex_len: usize) -> HashMap<PathBuf, String> {
+    output
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.starts_with(b"\\"))
+        .filter_map(|line| {
+            let (hash, rest) = line.split_at_checked(hex_len)?;
+            // Two spaces in text mode, ` *` in binary mode.
+            let path = rest.strip_prefix(b"  ").or_else(|| rest.strip_prefix(b" *"))?;
+            let hash = std::str::from_utf8(hash).ok().filter(|h| h.chars().all(|c| c.is_ascii_hexdigit()))?;
+            Some((PathBuf::from(OsStr::from_bytes(path)), hash.to_lowercase()))
+        })
+        .collect()
+}
+
+/// What verifying a run again found.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Outcome {
+    /// Files in the record.
+    pub total: usize,
+    pub matched: usize,
+    /// Destinations whose contents no longer match their sources.
+    pub differ: Vec<String>,
+    /// Files that could not be hashed at one end, with the reason.
+    pub failed: Vec<String>,
+    /// The dialog was closed before every file was checked.
+    pub cancelled: bool,
+}
+
+impl Outcome {
+    fn checked(&self) -> usize {
+        self.matched + self.differ.len() + self.failed.len()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        !self.cancelled && self.matched == self.total
+    }
+
+    /// `All 12 file(s) still match their sources.`, or what didn't.
+    pub fn describe(&self) -> String {
+        if self.is_clean() {
+            return format!("All {} file(s) still match their sources.", group_thousands(self.total));
+        }
+        let mut text = format!(
+            "{} of {} file(s) match their sources",
+            group_thousands(self.matched),
+            group_thousands(self.total)
+        );
+        if !self.differ.is_empty() {
+            text.push_str(&format!(", {} differ", group_thousands(self.differ.len())));
+        }
+        if !self.failed.is_empty() {
+            text.push_str(&format!(", {} could not be checked", group_thousands(self.failed.len())));
+        }
+        if self.cancelled {
+            text.push_str(&format!("; stopped after {}", group_thousands(self.checked())));
+        }
+        text.push('.');
+        text
+    }
+
+    /// One line per file that didn't match.
+    pub fn problems(&self) -> Vec<String> {
+        self.differ.iter().map(|d| format!("{}: differs from its source", d)).chain(self.failed.iter().cloned()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_listings_are_parsed_by_path() {
+        let a = "a".repeat(64);
+        let b = "B".repeat(64);
+        let output = format!("{a}  /srv/x y.txt\n{b} */srv/bin\n\\{a}  /srv/odd\\nname\nsha256sum: /srv/gone: No such file\n");
+        let hashes = parse_hashes(output.as_bytes(), 64);
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(hashes[Path::new("/srv/x y.txt")], a);
+        assert_eq!(hashes[Path::new("/srv/bin")], "b".repeat(64));
+        // A shorter hash, from `xxhsum -H2`, doesn't pass for a longer one.
+        let short = "c".repeat(32);
+        assert_eq!(parse_hashes(format!("{short}  /srv/x\n").as_bytes(), 32)[Path::new("/srv/x")], short);
+        assert!(parse_hashes(format!("{short}  /srv/x\n").as_bytes(), 64).is_empty());
+        // A name that isn't UTF-8 keeps its bytes.
+        let odd = parse_hashes(&[a.as_bytes(), b"  /srv/caf\xe9\n"].concat(), 64);
+        assert_eq!(odd[Path::new(OsStr::from_bytes(b"/srv/caf\xe9"))], a);
+    }
+
+    #[test]
+    fn outcomes_read_plainly() {
+        let clean = Outcome { total: 1204, matched: 1204, ..Outcome::default() };
+        assert_eq!(clean.describe(), "All 1,204 file(s) still match their sources.");
+        let mixed = Outcome {
+            total: 12,
+            matched: 8,
+            differ: vec!["/b/a.txt".to_string()],
+            failed: vec!["/b/c.txt: No such file or directory (os error 2)".to_string()],
+            cancelled: true,
+        };
+        assert!(!mixed.is_clean());
+        assert_eq!(mixed.describe(), "8 of 12 file(s) match their sources, 1 differ, 1 could not be checked; stopped after 10.");
+        assert_eq!(mixed.problems()[0], "/b/a.txt: differs from its source");
+    }
+}
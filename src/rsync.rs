@@ -0,0 +1,377 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ── rsync versions on each end ─────────────────────────────────────────
+//
+// rsync exits 0 even when an old protocol or a stripped-down build quietly
+// handles `-az --checksum` differently.  The workers run `rsync --version`
+// locally and on each host before transferring, so a missing rsync fails
+// up front and a downgrade is named in the summary.  Every file is still
+// verified by Kosmokopy's own comparison or SHA-256 check either way.
+
+/// rsync 3.0's protocol; older ones checksum with MD4 and predate several
+/// fixes to compressed transfers.
+pub const MIN_PROTOCOL: u32 = 30;
+
+/// What `rsync --version` reported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RsyncInfo {
+    pub version: String,
+    pub protocol: Option<u32>,
+    /// The BSD reimplementation (the `rsync` on recent macOS).
+    pub openrsync: bool,
+    /// Checksum algorithms offered, if listed (rsync 3.2+).
+    pub checksums: Vec<String>,
+}
+
+impl RsyncInfo {
+    /// e.g. `rsync 3.2.7, protocol 31`.
+    pub fn describe(&self) -> String {
+        let name = if self.openrsync { "openrsync" } else { "rsync" };
+        match self.protocol {
+            Some(p) => format!("{} {}, protocol {}", name, self.version, p),
+            None => format!("{} {}", name, self.version),
+        }
+    }
+}
+
+/// Parse the output of `rsync --version`; `None` if it names no version.
+pub fn parse_version(output: &str) -> Option<RsyncInfo> {
+    let mut version = None;
+    let mut protocol = None;
+    let mut checksums = Vec::new();
+    let mut lines = output.lines();
+    while let Some(line) = lines.next() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        for pair in words.windows(3) {
+            match pair {
+                [w, "version", v] if w.ends_with("rsync") && version.is_none() => version = Some(v.to_string()),
+                ["protocol", "version", p] => protocol = protocol.or_else(|| p.parse().ok()),
+                _ => {}
+            }
+        }
+        // openrsync puts the protocol first: "openrsync: protocol version 29".
+        if let ["openrsync:", "protocol", "version", p, ..] = words.as_slice() {
+            protocol = protocol.or_else(|| p.parse().ok());
+        }
+        if line.trim() == "Checksum list:" {
+            if let Some(list) = lines.next() {
+                checksums = list
+                    .split_whitespace()
+                    .filter(|w| !w.starts_with('('))
+                    .map(str::to_string)
+                    .collect();
+            }
+        }
+    }
+    Some(RsyncInfo {
+        version: version?,
+        protocol,
+        openrsync: output.contains("openrsync"),
+        checksums,
+    })
+}
+
+/// Warnings about `-az --checksum` for a transfer between `peers`, each
+/// named as it should appear in the message (`this machine`, `'nas'`).
+pub fn downgrade_warnings(peers: &[(String, RsyncInfo)]) -> Vec<String> {
+    let mut out = Vec::new();
+    for (place, info) in peers.iter().filter(|(_, i)| i.openrsync) {
+        out.push(format!(
+            "{} has {}, which supports only part of rsync's options; --checksum may not be honoured, \
+             so changed files are caught only by Kosmokopy's own verification",
+            place,
+            info.describe()
+        ));
+    }
+    let oldest = peers
+        .iter()
+        .filter_map(|(place, info)| info.protocol.map(|p| (p, place, info)))
+        .min_by_key(|(p, _, _)| *p);
+    if let Some((p, place, info)) = oldest.filter(|(p, _, _)| *p < MIN_PROTOCOL) {
+        out.push(format!(
+            "rsync protocol {} ({} has {}) predates rsync 3.0, so --checksum falls back to MD4 \
+             and compression is less reliable; files are still verified by Kosmokopy",
+            p,
+            place,
+            info.describe()
+        ));
+    }
+    out
+}
+
+/// Whether a failed `rsync --version` on a host means rsync isn't there.
+pub fn is_missing(exit_code: Option<i32>, stderr: &str) -> bool {
+    exit_code == Some(127) || stderr.contains("command not found") || stderr.contains("not found")
+}
+
+// ── One call for many files ────────────────────────────────────────────
+//
+// The rsync workers plan each file as before, then hand rsync the files
+// bound for one place in a single call rather than starting it per file.
+// A call takes a source folder, a destination folder and a list of paths
+// below both (`--files-from`), so files go together when their planned
+// destination keeps the tail of their source path: a tree copied with its
+// folders goes as one call, flattened files as one per source folder.  A
+// file whose name the plan changed shares no tail and keeps a call of its
+// own, as does every file of a Rename run.
+
+/// Files sent by one rsync call.
+#[derive(Debug, PartialEq)]
+pub struct Batch {
+    /// The folder the listed paths are below, on this machine.
+    pub from: PathBuf,
+    /// The folder they land below, here or on the host.
+    pub to: PathBuf,
+    /// Each file's place in the plan and its path below both folders.
+    pub files: Vec<(usize, PathBuf)>,
+}
+
+/// `src` and `dest` split at the longest tail of names they share, as
+/// `(source folder, destination folder, tail)`; `None` when even the file
+/// names differ.
+pub fn split(src: &Path, dest: &Path) -> Option<(PathBuf, PathBuf, PathBuf)> {
+    let a: Vec<Component> = src.components().collect();
+    let b: Vec<Component> = dest.components().collect();
+    let shared = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take(a.len().min(b.len()).saturating_sub(1))
+        .take_while(|(x, y)| x == y && matches!(x, Component::Normal(_)))
+        .count();
+    (shared > 0).then(|| {
+        (
+            a[..a.len() - shared].iter().collect(),
+            b[..b.len() - shared].iter().collect(),
+            a[a.len() - shared..].iter().collect(),
+        )
+    })
+}
+
+/// Put the file at `index` in the plan, going from `src` to `dest`, in
+/// the batch for its folders, starting one if need be.  `false` when it
+/// can't share a call.
+pub fn add(batches: &mut Vec<Batch>, index: usize, src: &Path, dest: &Path) -> bool {
+    let Some((from, to, tail)) = split(src, dest) else {
+        return false;
+    };
+    match batches.iter_mut().find(|b| b.from == from && b.to == to) {
+        Some(batch) => batch.files.push((index, tail)),
+        None => batches.push(Batch { from, to, files: vec![(index, tail)] }),
+    }
+    true
+}
+
+/// A batch's paths written out for `--files-from`, removed when dropped.
+pub struct FileList {
+    path: PathBuf,
+    names: HashMap<Vec<u8>, usize>,
+}
+
+impl FileList {
+    pub fn write(batch: &Batch) -> io::Result<FileList> {
+        static SEQ: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "kosmokopy-files-{}-{}",
+            std::process::id(),
+            SEQ.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut list = Vec::new();
+        for (_, tail) in &batch.files {
+            list.extend_from_slice(tail.as_os_str().as_bytes());
+            list.push(0);
+        }
+        fs::write(&path, list)?;
+        let names = batch.files.iter().map(|(i, tail)| (tail.as_os_str().as_bytes().to_vec(), *i)).collect();
+        Ok(FileList { path, names })
+    }
+
+    /// The options that make rsync send the listed paths, each to the same
+    /// path below the destination, and print each one's path once sent.
+    /// The folders are already there, so none are made for the paths.
+    pub fn args(&self) -> [OsString; 5] {
+        let mut files_from = OsString::from("--files-from=");
+        files_from.push(&self.path);
+        [files_from, "--from0".into(), "--relative".into(), "--no-implied-dirs".into(), "--out-format=%n".into()]
+    }
+
+    /// The place in the plan of the file a line of rsync's output names.
+    pub fn sent(&self, line: &[u8]) -> Option<usize> {
+        self.names.get(line).copied()
+    }
+}
+
+impl Drop for FileList {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Batch {
+    /// The line of the call's stderr about the file at `tail`: rsync quotes
+    /// the paths it fails on, made absolute, and writes into a hidden
+    /// `.name.XXXXXX` beside the destination.
+    pub fn error_about<'a>(&self, stderr: &'a str, tail: &Path) -> Option<&'a str> {
+        let mut names = Vec::new();
+        for folder in [&self.from, &self.to] {
+            let folders = [folder.clone(), std::path::absolute(folder).unwrap_or_else(|_| folder.clone())];
+            for folder in folders {
+                let path = folder.join(tail);
+                names.push(format!("\"{}\"", path.display()));
+                if let (Some(parent), Some(name)) = (path.parent(), path.file_name()) {
+                    names.push(format!("\"{}", parent.join(format!(".{}.", name.to_string_lossy())).display()));
+                }
+            }
+        }
+        stderr.lines().find(|line| names.iter().any(|n| line.contains(n.as_str()))).map(str::trim)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GNU_327: &str = "rsync  version 3.2.7  protocol version 31
+Copyright (C) 1996-2022 by Andrew Tridgell, Wayne Davison, and others.
+Web site: https://rsync.samba.org/
+Capabilities:
+    64-bit files, 64-bit inums, 64-bit timestamps, 64-bit long ints,
+    socketpairs, symlinks, symtimes, hardlinks, hardlink-specials,
+    hardlink-symlinks, IPv6, atimes, batchfiles, inplace, append, ACLs,
+    xattrs, optional secluded-args, iconv, prealloc, stop-at, no crtimes
+Optimizations:
+    SIMD-roll, no asm-roll, openssl-crypto, no asm-MD5
+Checksum list:
+    xxh128 xxh3 xxh64 (xxhash) md5 md4 sha1 none
+Compress list:
+    zstd lz4 zlibx zlib none
+";
+
+    const GNU_313: &str = "rsync  version 3.1.3  protocol version 31
+Copyright (C) 1996-2018 by Andrew Tridgell, Wayne Davison, and others.
+";
+
+    const APPLE_269: &str = "rsync  version 2.6.9  protocol version 29
+Copyright (C) 1996-2006 by Andrew Tridgell, Wayne Davison, and others.
+<http://rsync.samba.org/>
+";
+
+    const OPENRSYNC: &str = "openrsync: protocol version 29
+rsync version 2.6.9 compatible
+";
+
+    #[test]
+    fn parses_gnu_rsync() {
+        let info = parse_version(GNU_327).unwrap();
+        assert_eq!(info.version, "3.2.7");
+        assert_eq!(info.protocol, Some(31));
+        assert!(!info.openrsync);
+        assert_eq!(info.checksums, ["xxh128", "xxh3", "xxh64", "md5", "md4", "sha1", "none"]);
+        assert_eq!(info.describe(), "rsync 3.2.7, protocol 31");
+        assert!(parse_version(GNU_313).unwrap().checksums.is_empty());
+    }
+
+    #[test]
+    fn parses_old_and_bsd_rsync() {
+        let apple = parse_version(APPLE_269).unwrap();
+        assert_eq!((apple.version.as_str(), apple.protocol), ("2.6.9", Some(29)));
+        let open = parse_version(OPENRSYNC).unwrap();
+        assert!(open.openrsync);
+        assert_eq!(open.protocol, Some(29));
+        assert_eq!(open.describe(), "openrsync 2.6.9, protocol 29");
+        assert_eq!(parse_version("bash: rsync: command not found\n"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn modern_peers_need_no_warning() {
+        let peers = vec![
+            ("this machine".to_string(), parse_version(GNU_327).unwrap()),
+            ("'nas'".to_string(), parse_version(GNU_313).unwrap()),
+        ];
+        assert!(downgrade_warnings(&peers).is_empty());
+    }
+
+    #[test]
+    fn old_protocol_on_either_end_is_named() {
+        let peers = vec![
+            ("this machine".to_string(), parse_version(GNU_327).unwrap()),
+            ("'oldbox'".to_string(), parse_version(APPLE_269).unwrap()),
+        ];
+        let warnings = downgrade_warnings(&peers);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("rsync protocol 29 ('oldbox' has rsync 2.6.9, protocol 29)"));
+    }
+
+    #[test]
+    fn openrsync_is_named() {
+        let peers = vec![("this machine".to_string(), parse_version(OPENRSYNC).unwrap())];
+        let warnings = downgrade_warnings(&peers);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("this machine has openrsync 2.6.9"));
+        assert!(warnings[0].contains("--checksum may not be honoured"));
+    }
+
+    #[test]
+    fn missing_rsync() {
+        assert!(is_missing(Some(127), ""));
+        assert!(is_missing(Some(1), "sh: rsync: not found"));
+        assert!(!is_missing(Some(255), "ssh: connect to host nas port 22: Connection refused"));
+    }
+
+    #[test]
+    fn files_keeping_their_path_share_a_call() {
+        let mut batches = Vec::new();
+        assert!(add(&mut batches, 0, Path::new("/data/photos/a.jpg"), Path::new("/backup/photos/a.jpg")));
+        assert!(add(&mut batches, 1, Path::new("/data/photos/2024/b.jpg"), Path::new("/backup/photos/2024/b.jpg")));
+        // Flattened: one call per source folder.
+        assert!(add(&mut batches, 2, Path::new("/data/photos/2024/c.jpg"), Path::new("/flat/c.jpg")));
+        assert!(add(&mut batches, 3, Path::new("/data/photos/d.jpg"), Path::new("/flat/d.jpg")));
+        // Renamed on the way: no call to share.
+        assert!(!add(&mut batches, 4, Path::new("/data/photos/e.jpg"), Path::new("/backup/photos/e_1.jpg")));
+        assert_eq!(
+            batches,
+            [
+                Batch {
+                    from: "/data".into(),
+                    to: "/backup".into(),
+                    files: vec![(0, "photos/a.jpg".into()), (1, "photos/2024/b.jpg".into())],
+                },
+                Batch { from: "/data/photos/2024".into(), to: "/flat".into(), files: vec![(2, "c.jpg".into())] },
+                Batch { from: "/data/photos".into(), to: "/flat".into(), files: vec![(3, "d.jpg".into())] },
+            ]
+        );
+        // A folder is always left on each side.
+        assert_eq!(split(Path::new("/a/b"), Path::new("/a/b")), Some(("/".into(), "/".into(), "a/b".into())));
+        assert_eq!(split(Path::new("x/b"), Path::new("b")), None);
+    }
+
+    #[test]
+    fn a_call_lists_its_files_and_names_their_errors() {
+        let batch = Batch { from: "/data".into(), to: "/backup".into(), files: vec![(3, "a b/c.txt".into()), (7, "d.txt".into())] };
+        let list = FileList::write(&batch).unwrap();
+        let path = list.path.clone();
+        assert_eq!(fs::read(&path).unwrap(), b"a b/c.txt\0d.txt\0");
+        assert_eq!(list.sent(b"a b/c.txt"), Some(3));
+        assert_eq!(list.sent(b"c.txt"), None);
+        drop(list);
+        assert!(!path.exists());
+
+        let stderr = "rsync: [sender] send_files failed to open \"/data/a b/c.txt\": Permission denied (13)\n\
+                      rsync: [receiver] mkstemp \"/backup/.d.txt.Xy12ab\" failed: No space left on device (28)\n\
+                      rsync error: some files/attrs were not transferred (code 23)\n";
+        assert!(batch.error_about(stderr, Path::new("a b/c.txt")).unwrap().contains("Permission denied"));
+        assert!(batch.error_about(stderr, Path::new("d.txt")).unwrap().contains("No space left"));
+        assert_eq!(batch.error_about(stderr, Path::new("c.txt")), None);
+    }
+}
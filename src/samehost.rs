@@ -0,0 +1,186 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+// ── Moves within one host ──────────────────────────────────────────────
+//
+// Moving `nas:/data/a` to `nas:/data/b` used to download every byte and
+// upload it back.  When the source and destination are one account on
+// one machine (`ssh -G` resolves both the same way, so `nas` and
+// `ann@nas.lan` match), the move is done on the host instead, over the
+// shared connection: the files go in batches to one shell script each,
+// which for every file
+//
+// - renames it with `mv` when the source and the destination folder are
+//   on the same filesystem (by `stat`'s device number), or otherwise
+// - copies it with `cp -p`, compares the copy with `cmp` and only then
+//   removes the source; a copy that differs is removed again.
+//
+// The script prints one NUL-ended record per file as it finishes, so the
+// progress still moves file by file.  Conflicts are settled beforehand as
+// for any upload.  A rename can't be held back, so with the all-then-delete
+// strategy every file is copied and checked instead, as local moves are,
+// and the sources are removed once the whole run has copied.  Renames need
+// no room on the host; copies are checked for it first (see `devices`).
+
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+
+/// Files per script.
+pub const BATCH: usize = 100;
+
+/// How one file of a batch ended.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Moved {
+    /// Renamed into place.
+    Renamed,
+    /// Copied, checked and, unless kept, its source removed.
+    Copied,
+    /// Copied and checked, but the source couldn't be removed.
+    Undeleted(String),
+    /// The copy differed from its source and was removed.
+    Mismatch,
+    Failed(String),
+}
+
+impl Moved {
+    /// One record the script printed.
+    pub fn parse(record: &[u8]) -> Moved {
+        let text = String::from_utf8_lossy(record);
+        let (word, rest) = text.split_once(' ').unwrap_or((&text, ""));
+        let why = || rest.trim().to_string();
+        match word {
+            "renamed" => Moved::Renamed,
+            "copied" => Moved::Copied,
+            "undeleted" => Moved::Undeleted(why()),
+            "mismatch" => Moved::Mismatch,
+            _ => Moved::Failed(why()),
+        }
+    }
+}
+
+/// The shell script moving each `(source, destination)` of `pairs`, with
+/// paths quoted by `quote`.  With `keep_sources` nothing is renamed: every
+/// file is copied and keeps its source for the caller to remove.
+pub fn script(pairs: &[(PathBuf, PathBuf)], keep_sources: bool, quote: impl Fn(&OsStr) -> OsString) -> OsString {
+    let mut out = OsString::from(DEV);
+    out.push("one() {\n");
+    out.push(if keep_sources {
+        "  if ! e=$(cp -p -- \"$1\" \"$2\" 2>&1); then printf 'failed %s\\000' \"$e\"\n"
+    } else {
+        "  p=${2%/*}; [ \"$p\" = \"$2\" ] && p=.; [ -z \"$p\" ] && p=/\n\
+         \x20 s=$(dev \"$1\"); d=$(dev \"$p\")\n\
+         \x20 if [ -n \"$s\" ] && [ \"$s\" = \"$d\" ]; then\n\
+         \x20   if e=$(mv -f -- \"$1\" \"$2\" 2>&1); then printf 'renamed\\000'; else printf 'failed %s\\000' \"$e\"; fi\n\
+         \x20 elif ! e=$(cp -p -- \"$1\" \"$2\" 2>&1); then printf 'failed %s\\000' \"$e\"\n"
+    });
+    out.push("  elif ! cmp -s -- \"$1\" \"$2\"; then rm -f -- \"$2\"; printf 'mismatch\\000'\n");
+    out.push(if keep_sources {
+        "  else printf 'copied\\000'\n"
+    } else {
+        "  elif e=$(rm -f -- \"$1\" 2>&1); then printf 'copied\\000'\n  else printf 'undeleted %s\\000' \"$e\"\n"
+    });
+    out.push("  fi\n}\n");
+    for (source, dest) in pairs {
+        out.push("one ");
+        out.push(quote(source.as_os_str()));
+        out.push(" ");
+        out.push(quote(dest.as_os_str()));
+        out.push("\n");
+    }
+    out
+}
+
+/// The device number of a path, by `stat` as GNU or BSD spell it.
+const DEV: &str = "dev() { stat -c %d -- \"$1\" 2>/dev/null || stat -f %d -- \"$1\" 2>/dev/null; }\n";
+
+/// The script printing the device numbers of the folders `source` and
+/// `dest`, one line each, for `same_device`.  A folder not made yet is
+/// taken as the nearest one above it that is there.
+pub fn devices(source: &OsStr, dest: &OsStr, quote: impl Fn(&OsStr) -> OsString) -> OsString {
+    let mut out = OsString::from(DEV);
+    out.push(
+        "near() {\n\
+         \x20 p=$1\n\
+         \x20 while [ ! -e \"$p\" ]; do case $p in */*) p=${p%/*}; p=${p:-/};; *) p=.;; esac; done\n\
+         \x20 dev \"$p\"\n\
+         }\n",
+    );
+    for dir in [source, dest] {
+        out.push("near ");
+        out.push(quote(dir));
+        out.push("\n");
+    }
+    out
+}
+
+/// Whether `devices` found both folders on one filesystem; `None` when it
+/// could not tell.
+pub fn same_device(output: &str) -> Option<bool> {
+    let mut lines = output.lines().map(str::trim).filter(|l| !l.is_empty());
+    let (source, dest) = (lines.next()?, lines.next()?);
+    Some(source == dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process::Command;
+
+    fn quote(s: &OsStr) -> OsString {
+        OsString::from(format!("'{}'", s.to_str().unwrap().replace('\'', "'\\''")))
+    }
+
+    #[test]
+    fn each_file_is_renamed_and_reported() {
+        let dir = std::env::temp_dir().join(format!("kosmokopy_samehost_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("b")).unwrap();
+        fs::write(dir.join("it's a.txt"), "a").unwrap();
+        let path = |name: &str| dir.join(name);
+        let pairs = [
+            (path("it's a.txt"), path("b/it's a.txt")),
+            (path("missing.txt"), path("b/missing.txt")),
+        ];
+        let out = Command::new("sh").arg("-c").arg(script(&pairs, false, quote)).output().unwrap();
+        let records: Vec<Moved> = out.stdout.split(|&b| b == 0).filter(|r| !r.is_empty()).map(Moved::parse).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], Moved::Renamed);
+        assert!(matches!(&records[1], Moved::Failed(why) if why.contains("missing.txt")));
+        assert_eq!(fs::read_to_string(dir.join("b/it's a.txt")).unwrap(), "a");
+        assert!(!dir.join("it's a.txt").exists());
+
+        assert_eq!(Moved::parse(b"undeleted rm: Permission denied"), Moved::Undeleted("rm: Permission denied".to_string()));
+        assert!(script(&pairs, true, quote).to_str().unwrap().contains("else printf 'copied\\000'\n  fi"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn holding_sources_back_copies_every_file() {
+        let dir = std::env::temp_dir().join(format!("kosmokopy_samehost_keep_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("b")).unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        let pairs = [(dir.join("a.txt"), dir.join("b/a.txt"))];
+        let out = Command::new("sh").arg("-c").arg(script(&pairs, true, quote)).output().unwrap();
+        assert_eq!(out.stdout, b"copied\0");
+        assert!(!script(&pairs, true, quote).to_str().unwrap().contains("mv "));
+        assert_eq!(fs::read_to_string(dir.join("b/a.txt")).unwrap(), "a");
+        assert!(dir.join("a.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn devices_are_read_for_folders_not_made_yet() {
+        let dir = std::env::temp_dir();
+        let out = Command::new("sh")
+            .arg("-c")
+            .arg(devices(dir.as_os_str(), dir.join("kosmokopy-not-made/deeper").as_os_str(), quote))
+            .output()
+            .unwrap();
+        assert_eq!(same_device(&String::from_utf8_lossy(&out.stdout)), Some(true));
+        assert_eq!(same_device("2049\n66306\n"), Some(false));
+        assert_eq!(same_device("2049\n"), None);
+    }
+}
@@ -0,0 +1,194 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use crate::coalesce::group_thousands;
+use crate::timeutil::Timestamp;
+
+// ── Sampled verification ───────────────────────────────────────────────
+//
+// Verifying every file of a very large copy can take longer than the
+// window it has to run in.  With `--verify-sample`, only a random share
+// of the files is checked, plus every file above a size threshold; the
+// others are counted as copied once transferred.  The files are picked
+// when the worker sends its plan, by their place in it, from a seeded
+// generator, so a run can be repeated with the same choice.
+//
+// A move would delete the sources of files nobody checked, so sampling
+// is refused for moves.
+
+/// What `--verify-sample` asked for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampleSpec {
+    /// Share of the files to verify, in (0, 100].
+    pub percent: f64,
+    /// Files larger than this many bytes are always verified.
+    pub min_size: Option<u64>,
+    pub seed: u64,
+}
+
+/// How a sampled run went.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampleReport {
+    pub spec: SampleSpec,
+    /// Files verified.
+    pub verified: usize,
+}
+
+impl SampleReport {
+    /// `verified 1,042 of 98,300 files (sample, seed 7)`.
+    pub fn describe(&self, files: usize) -> String {
+        format!(
+            "verified {} of {} files (sample, seed {})",
+            group_thousands(self.verified),
+            group_thousands(files),
+            self.spec.seed
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Sample {
+    spec: SampleSpec,
+    chosen: Vec<bool>,
+    /// Places in the plan of the files verified; a relay checks each
+    /// file twice.
+    verified: HashSet<usize>,
+}
+
+static SAMPLE: Mutex<Option<Sample>> = Mutex::new(None);
+
+/// Verify only a sample of the files from now on.
+pub fn enable(spec: SampleSpec) {
+    if let Ok(mut sample) = SAMPLE.lock() {
+        *sample = Some(Sample { spec, chosen: Vec::new(), verified: HashSet::new() });
+    }
+}
+
+/// Pick the files to verify from the plan's sizes, in processing order.
+pub fn plan(sizes: &[Option<u64>]) {
+    if let Ok(mut guard) = SAMPLE.lock() {
+        if let Some(sample) = guard.as_mut() {
+            sample.chosen = choose(sizes, &sample.spec);
+        }
+    }
+}
+
+/// Whether the file at `index` in the plan is to be verified: always,
+/// unless sampling.
+pub fn includes(index: usize) -> bool {
+    match SAMPLE.lock() {
+        Ok(guard) => guard.as_ref().is_none_or(|s| s.chosen.get(index).copied().unwrap_or(true)),
+        Err(_) => true,
+    }
+}
+
+/// Count the file at `index` in the plan as verified.
+pub fn note_verified(index: usize) {
+    if let Ok(mut guard) = SAMPLE.lock() {
+        if let Some(sample) = guard.as_mut() {
+            sample.verified.insert(index);
+        }
+    }
+}
+
+/// How the sampled run went, or `None` when every file was verified.
+pub fn report() -> Option<SampleReport> {
+    let guard = SAMPLE.lock().ok()?;
+    guard.as_ref().map(|s| SampleReport { spec: s.spec, verified: s.verified.len() })
+}
+
+/// A seed for a run that did not give one.
+pub fn fresh_seed() -> u64 {
+    let nanos = Timestamp::now().as_nanos() as u64;
+    // Small numbers are easier to copy from a report into --verify-sample-seed.
+    SplitMix(nanos ^ u64::from(std::process::id())).next() % 1_000_000_000
+}
+
+/// Which of the files with `sizes` to verify: ceil(percent × N) of them
+/// uniformly at random, and every file above `min_size`.
+fn choose(sizes: &[Option<u64>], spec: &SampleSpec) -> Vec<bool> {
+    let n = sizes.len();
+    let k = ((spec.percent * n as f64 / 100.0).ceil() as usize).min(n);
+    let mut chosen: Vec<bool> = sizes
+        .iter()
+        .map(|s| matches!((s, spec.min_size), (Some(size), Some(min)) if *size > min))
+        .collect();
+    // Floyd's algorithm: k distinct indices without shuffling all n.
+    let mut rng = SplitMix(spec.seed);
+    let mut picked = HashSet::with_capacity(k);
+    for j in n - k..n {
+        let t = rng.below(j as u64 + 1) as usize;
+        let pick = if picked.contains(&t) { j } else { t };
+        picked.insert(pick);
+        chosen[pick] = true;
+    }
+    chosen
+}
+
+/// SplitMix64: small, fast, and the same on every platform.
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..bound`.
+    fn below(&mut self, bound: u64) -> u64 {
+        ((u128::from(self.next()) * u128::from(bound)) >> 64) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(percent: f64, min_size: Option<u64>, seed: u64) -> SampleSpec {
+        SampleSpec { percent, min_size, seed }
+    }
+
+    fn indices(chosen: &[bool]) -> Vec<usize> {
+        chosen.iter().enumerate().filter(|(_, c)| **c).map(|(i, _)| i).collect()
+    }
+
+    #[test]
+    fn a_fixed_seed_picks_the_same_files() {
+        let sizes = vec![Some(100); 20];
+        let picked = indices(&choose(&sizes, &spec(25.0, None, 42)));
+        assert_eq!(picked, vec![0, 2, 5, 6, 11]);
+        assert_eq!(indices(&choose(&sizes, &spec(25.0, None, 42))), picked);
+        assert_ne!(indices(&choose(&sizes, &spec(25.0, None, 43))), picked);
+    }
+
+    #[test]
+    fn the_share_is_rounded_up_and_large_files_always_count() {
+        let mut sizes = vec![Some(10); 98_300];
+        assert_eq!(indices(&choose(&sizes, &spec(1.0, None, 7))).len(), 983);
+        assert_eq!(indices(&choose(&sizes[..10], &spec(1.0, None, 7))).len(), 1);
+        assert_eq!(indices(&choose(&sizes[..10], &spec(100.0, None, 7))).len(), 10);
+
+        sizes.truncate(1000);
+        sizes[500] = Some(5_000_000);
+        sizes[501] = None;
+        let random = indices(&choose(&sizes, &spec(0.1, None, 7)));
+        assert_eq!(random.len(), 1);
+        let mut expected: Vec<usize> = random.into_iter().chain([500]).collect();
+        expected.sort();
+        expected.dedup();
+        assert_eq!(indices(&choose(&sizes, &spec(0.1, Some(1_000_000), 7))), expected);
+    }
+
+    #[test]
+    fn the_report_reads_plainly() {
+        let report = SampleReport { spec: spec(1.0, None, 7), verified: 1042 };
+        assert_eq!(report.describe(98_300), "verified 1,042 of 98,300 files (sample, seed 7)");
+    }
+}
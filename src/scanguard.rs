@@ -0,0 +1,322 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::coalesce::group_thousands;
+use crate::progress::{format_bytes, format_eta};
+
+// ── Scan guard ─────────────────────────────────────────────────────────
+//
+// A source pointed at `/` by accident, or at a tree with a mount loop
+// inside, keeps the scan going for hours while the window says
+// "Scanning…".  Once a local scan has seen more files than the limit (5
+// million by default) or run longer than the time limit (10 minutes), it
+// pauses and asks whether to go on: a dialog in the GUI, a prompt in the
+// CLI unless `--scan-limit-action` answers for it.  A run asks at most
+// once.  The limits come from settings.conf (`scan_limit_files`,
+// `scan_limit_secs`) and the CLI's `--scan-limit`/`--scan-time-limit`;
+// 0 turns a limit off.
+//
+// The guard also stops the scan as soon as the run is cancelled, and
+// passes on how many files (and bytes) the scan has found every quarter
+// of a second, so a long walk over a slow mount reads `Scanning… 12,430
+// files, 8.2 GiB` rather than sitting at `Scanning…`.
+
+pub const DEFAULT_FILES: usize = 5_000_000;
+pub const DEFAULT_SECS: u64 = 600;
+
+/// How often a scan passes on what it has found.
+const PROGRESS_EVERY: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    pub files: usize,
+    pub secs: u64,
+}
+
+impl Limits {
+    pub const NONE: Limits = Limits { files: 0, secs: 0 };
+    pub const DEFAULT: Limits = Limits { files: DEFAULT_FILES, secs: DEFAULT_SECS };
+}
+
+/// How the CLI answers when a limit is passed (`--scan-limit-action`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Ask,
+    Continue,
+    Abort,
+}
+
+impl Action {
+    pub fn parse(word: &str) -> Option<Action> {
+        match word {
+            "ask" => Some(Action::Ask),
+            "continue" => Some(Action::Continue),
+            "abort" => Some(Action::Abort),
+            _ => None,
+        }
+    }
+}
+
+/// Where a scan was when it passed a limit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trip {
+    pub files: usize,
+    pub elapsed: Duration,
+    /// The most deeply nested path reached so far.
+    pub deepest: String,
+    /// The time limit was passed, rather than the file limit.
+    pub by_time: bool,
+}
+
+impl Trip {
+    /// `the scan has found 5,000,001 files in 2:14; the deepest path so
+    /// far is /a/b/c`.
+    pub fn describe(&self) -> String {
+        format!(
+            "the scan has found {} file(s) in {}; the deepest path so far is {}",
+            group_thousands(self.files),
+            format_eta(self.elapsed),
+            self.deepest
+        )
+    }
+}
+
+/// What happened at the limit, for the log and the result.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub trip: Trip,
+    pub continued: bool,
+}
+
+impl Report {
+    /// `Scan passed its file limit at 5,000,001 files after 2:14;
+    /// continued`.
+    pub fn describe(&self) -> String {
+        format!(
+            "Scan passed its {} limit at {} file(s) after {}; {}",
+            if self.trip.by_time { "time" } else { "file" },
+            group_thousands(self.trip.files),
+            format_eta(self.trip.elapsed),
+            if self.continued { "continued" } else { "stopped" }
+        )
+    }
+}
+
+static REPORT: Mutex<Option<Report>> = Mutex::new(None);
+
+/// What the last run's guard did, if a limit was passed.
+pub fn report() -> Option<Report> {
+    REPORT.lock().ok()?.clone()
+}
+
+/// Forget the last run's report, e.g. when a new transfer starts.
+pub fn reset() {
+    if let Ok(mut report) = REPORT.lock() {
+        *report = None;
+    }
+}
+
+/// Why a guarded scan stopped early.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stopped {
+    Cancelled,
+    Declined(Trip),
+}
+
+impl Stopped {
+    pub fn message(&self) -> String {
+        match self {
+            Stopped::Cancelled => "Cancelled while scanning the source.".to_string(),
+            Stopped::Declined(trip) => format!("Scan stopped: {}.", trip.describe()),
+        }
+    }
+}
+
+/// `Scanning… 12,430 files, 8.2 GiB`.
+pub fn describe_found(files: usize, bytes: u64) -> String {
+    format!("Scanning… {} files, {}", group_thousands(files), format_bytes(bytes))
+}
+
+/// Watches one scan: counts what it visits and stops it when the run is
+/// cancelled or the user declines to go past a limit.
+pub struct ScanGuard<'a> {
+    limits: Limits,
+    started: Instant,
+    files: usize,
+    deepest: (usize, PathBuf),
+    asked: bool,
+    stop: Box<dyn Fn() -> bool + 'a>,
+    ask: Box<dyn FnMut(&Trip) -> bool + 'a>,
+    /// Files and bytes found so far, and when they were last passed on.
+    found: (usize, u64),
+    reported: Instant,
+    progress: Box<dyn FnMut(usize, u64) + 'a>,
+}
+
+impl<'a> ScanGuard<'a> {
+    /// A guard with `limits`.  `stop` tells whether the run was cancelled;
+    /// `ask` answers whether to go on past a limit.
+    pub fn with_limits(limits: Limits, stop: impl Fn() -> bool + 'a, ask: impl FnMut(&Trip) -> bool + 'a) -> Self {
+        ScanGuard {
+            limits,
+            started: Instant::now(),
+            files: 0,
+            deepest: (0, PathBuf::new()),
+            asked: false,
+            stop: Box::new(stop),
+            ask: Box::new(ask),
+            found: (0, 0),
+            reported: Instant::now(),
+            progress: Box::new(|_, _| {}),
+        }
+    }
+
+    /// Pass the files and bytes found to `progress` as the scan goes.
+    pub fn with_progress(mut self, progress: impl FnMut(usize, u64) + 'a) -> Self {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    /// Note the scan finding a file of `bytes` to transfer.
+    pub fn found(&mut self, bytes: u64) {
+        self.found.0 += 1;
+        self.found.1 += bytes;
+        if self.reported.elapsed() >= PROGRESS_EVERY {
+            self.flush();
+        }
+    }
+
+    /// Pass on what has been found, e.g. once the scan is done.
+    pub fn flush(&mut self) {
+        self.reported = Instant::now();
+        if self.found.0 > 0 {
+            (self.progress)(self.found.0, self.found.1);
+        }
+    }
+
+    /// A guard that never stops a scan, for previews and listings that are
+    /// not a run.
+    pub fn unlimited() -> ScanGuard<'static> {
+        ScanGuard::with_limits(Limits::NONE, || false, |_| true)
+    }
+
+    /// Note the scan reaching `path`, `depth` levels below the source.
+    pub fn visit(&mut self, path: &Path, depth: usize, is_file: bool) -> Result<(), Stopped> {
+        if (self.stop)() {
+            return Err(Stopped::Cancelled);
+        }
+        if is_file {
+            self.files += 1;
+        }
+        if depth > self.deepest.0 {
+            self.deepest = (depth, path.to_path_buf());
+        }
+        if self.asked {
+            return Ok(());
+        }
+        let elapsed = self.started.elapsed();
+        let by_files = self.limits.files > 0 && self.files > self.limits.files;
+        let by_time = self.limits.secs > 0 && elapsed.as_secs() >= self.limits.secs;
+        if !by_files && !by_time {
+            return Ok(());
+        }
+        self.asked = true;
+        let trip = Trip {
+            files: self.files,
+            elapsed,
+            deepest: self.deepest.1.display().to_string(),
+            by_time: !by_files,
+        };
+        let continued = (self.ask)(&trip);
+        if let Ok(mut report) = REPORT.lock() {
+            *report = Some(Report { trip: trip.clone(), continued });
+        }
+        if continued {
+            Ok(())
+        } else {
+            Err(Stopped::Declined(trip))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn walk(guard: &mut ScanGuard, files: usize) -> Result<(), Stopped> {
+        for i in 0..files {
+            let path = PathBuf::from(format!("/src/d{}/f{}", i % 3, i));
+            guard.visit(&path, 1 + i % 3, true)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn the_file_limit_asks_once() {
+        let asked = Cell::new(0);
+        let mut guard = ScanGuard::with_limits(Limits { files: 4, secs: 0 }, || false, |trip| {
+            asked.set(asked.get() + 1);
+            assert_eq!((trip.files, trip.by_time), (5, false));
+            assert_eq!(trip.deepest, "/src/d2/f2");
+            true
+        });
+        assert_eq!(walk(&mut guard, 20), Ok(()));
+        assert_eq!(asked.get(), 1);
+    }
+
+    #[test]
+    fn declining_stops_the_scan() {
+        let mut guard = ScanGuard::with_limits(Limits { files: 2, secs: 0 }, || false, |_| false);
+        match walk(&mut guard, 10) {
+            Err(Stopped::Declined(trip)) => {
+                assert_eq!(trip.files, 3);
+                assert!(Stopped::Declined(trip).message().starts_with("Scan stopped: the scan has found 3 file(s) in 0:00;"));
+            }
+            other => panic!("expected a declined scan, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cancelling_stops_the_scan_without_asking() {
+        let cancelled = Cell::new(false);
+        let mut guard = ScanGuard::with_limits(Limits { files: 1, secs: 0 }, || cancelled.get(), |_| panic!("asked"));
+        assert_eq!(guard.visit(Path::new("/src/a"), 1, true), Ok(()));
+        cancelled.set(true);
+        assert_eq!(guard.visit(Path::new("/src/b"), 1, true), Err(Stopped::Cancelled));
+        let mut unlimited = ScanGuard::unlimited();
+        assert_eq!(walk(&mut unlimited, 1000), Ok(()));
+    }
+
+    #[test]
+    fn what_was_found_is_passed_on_now_and_then() {
+        let seen = std::cell::RefCell::new(Vec::new());
+        let mut guard = ScanGuard::with_limits(Limits::NONE, || false, |_| true).with_progress(|files, bytes| seen.borrow_mut().push((files, bytes)));
+        guard.found(100);
+        guard.found(2048);
+        assert!(seen.borrow().is_empty());
+        std::thread::sleep(PROGRESS_EVERY);
+        guard.found(1);
+        guard.flush();
+        assert_eq!(*seen.borrow(), [(3, 2149), (3, 2149)]);
+        assert_eq!(describe_found(12_430, 3 << 30), "Scanning… 12,430 files, 3.0 GiB");
+    }
+
+    #[test]
+    fn reports_read_plainly() {
+        let trip = Trip { files: 5_000_001, elapsed: Duration::from_secs(134), deepest: "/a/b".to_string(), by_time: false };
+        assert_eq!(
+            Report { trip: trip.clone(), continued: true }.describe(),
+            "Scan passed its file limit at 5,000,001 file(s) after 2:14; continued"
+        );
+        assert_eq!(
+            Report { trip: Trip { by_time: true, ..trip }, continued: false }.describe(),
+            "Scan passed its time limit at 5,000,001 file(s) after 2:14; stopped"
+        );
+    }
+}
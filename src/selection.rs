@@ -0,0 +1,300 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+// ── Per-item selection of a scanned source folder ──────────────────────
+//
+// "Select items…" scans the source folder and shows it as a tree with a
+// checkbox per directory and file.  `ItemTree` holds the check state; the
+// GUI only renders it.  A directory's box is derived from the files below
+// it, so it is checked, unchecked or mixed.
+
+/// The check state shown for a tree item.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Check {
+    Checked,
+    Unchecked,
+    Mixed,
+}
+
+/// A directory or file in the scanned tree.
+#[derive(Debug)]
+pub struct Node {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub parent: Option<usize>,
+    /// Directories first, then files, each sorted by name.
+    pub children: Vec<usize>,
+    /// Files: whether the file is ticked.  Directories: unused.
+    checked: bool,
+    /// Files at or below this node, and how many of those are ticked.
+    files: usize,
+    checked_files: usize,
+}
+
+/// Scanned source folder with a check state per item.
+#[derive(Debug)]
+pub struct ItemTree {
+    nodes: Vec<Node>,
+}
+
+impl ItemTree {
+    /// Build the tree for `root` from every file found below it.  Files in
+    /// `kept` (what the exclusion patterns let through) start ticked; the
+    /// others start unticked.
+    pub fn build(root: &Path, all_files: &[PathBuf], kept: &HashSet<PathBuf>) -> ItemTree {
+        let mut nodes = vec![Node {
+            name: root
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| root.display().to_string()),
+            path: root.to_path_buf(),
+            is_dir: true,
+            parent: None,
+            children: Vec::new(),
+            checked: false,
+            files: 0,
+            checked_files: 0,
+        }];
+        let mut dirs: HashMap<PathBuf, usize> = HashMap::new();
+        dirs.insert(root.to_path_buf(), 0);
+
+        for file in all_files {
+            let Ok(rel) = file.strip_prefix(root) else { continue };
+            let mut parent = 0;
+            let mut dir_path = root.to_path_buf();
+            let components: Vec<_> = rel.components().collect();
+            let Some((name, dir_parts)) = components.split_last() else { continue };
+            for part in dir_parts {
+                dir_path.push(part);
+                parent = match dirs.get(&dir_path) {
+                    Some(&id) => id,
+                    None => {
+                        let id = nodes.len();
+                        nodes.push(Node {
+                            name: part.as_os_str().to_string_lossy().to_string(),
+                            path: dir_path.clone(),
+                            is_dir: true,
+                            parent: Some(parent),
+                            children: Vec::new(),
+                            checked: false,
+                            files: 0,
+                            checked_files: 0,
+                        });
+                        nodes[parent].children.push(id);
+                        dirs.insert(dir_path.clone(), id);
+                        id
+                    }
+                };
+            }
+            let checked = kept.contains(file);
+            let id = nodes.len();
+            nodes.push(Node {
+                name: name.as_os_str().to_string_lossy().to_string(),
+                path: file.clone(),
+                is_dir: false,
+                parent: Some(parent),
+                children: Vec::new(),
+                checked,
+                files: 1,
+                checked_files: usize::from(checked),
+            });
+            nodes[parent].children.push(id);
+            let mut up = Some(parent);
+            while let Some(dir) = up {
+                nodes[dir].files += 1;
+                nodes[dir].checked_files += usize::from(checked);
+                up = nodes[dir].parent;
+            }
+        }
+
+        let mut tree = ItemTree { nodes };
+        for id in 0..tree.nodes.len() {
+            let mut children = std::mem::take(&mut tree.nodes[id].children);
+            children.sort_by(|&a, &b| {
+                let (a, b) = (&tree.nodes[a], &tree.nodes[b]);
+                b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+            });
+            tree.nodes[id].children = children;
+        }
+        tree
+    }
+
+    /// The scanned folder itself.
+    pub const ROOT: usize = 0;
+
+    pub fn node(&self, id: usize) -> &Node {
+        &self.nodes[id]
+    }
+
+    /// Number of directories and files in the tree.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Files found below the root, and how many of them are ticked.
+    pub fn counts(&self) -> (usize, usize) {
+        let root = &self.nodes[Self::ROOT];
+        (root.files, root.checked_files)
+    }
+
+    pub fn state(&self, id: usize) -> Check {
+        let node = &self.nodes[id];
+        if node.checked_files == 0 {
+            Check::Unchecked
+        } else if node.checked_files == node.files {
+            Check::Checked
+        } else {
+            Check::Mixed
+        }
+    }
+
+    /// Tick or untick an item; for a directory, its whole subtree.
+    pub fn set_checked(&mut self, id: usize, checked: bool) {
+        let mut stack = vec![id];
+        let mut delta: isize = 0;
+        while let Some(n) = stack.pop() {
+            let node = &mut self.nodes[n];
+            if node.is_dir {
+                node.checked_files = if checked { node.files } else { 0 };
+                stack.extend(node.children.iter().copied());
+            } else if node.checked != checked {
+                node.checked = checked;
+                node.checked_files = usize::from(checked);
+                delta += if checked { 1 } else { -1 };
+            }
+        }
+        let mut up = self.nodes[id].parent;
+        while let Some(dir) = up {
+            let node = &mut self.nodes[dir];
+            node.checked_files = node.checked_files.saturating_add_signed(delta);
+            up = node.parent;
+        }
+    }
+
+    /// The ticked files, in tree order — the explicit file list the worker
+    /// transfers in place of the pattern results.
+    pub fn selected_files(&self) -> Vec<PathBuf> {
+        let mut out = Vec::new();
+        let mut stack = vec![Self::ROOT];
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            if node.checked_files == 0 {
+                continue;
+            }
+            if node.is_dir {
+                stack.extend(node.children.iter().rev().copied());
+            } else {
+                out.push(node.path.clone());
+            }
+        }
+        out
+    }
+
+    /// Exclusion patterns that leave out the unticked items: `/dir` for a
+    /// directory with nothing ticked below it, `file` for a single unticked
+    /// file.  Patterns match names anywhere under the source, so items that
+    /// share a name with an unticked one are left out as well.
+    pub fn exclusion_patterns(&self) -> Vec<String> {
+        let mut out: Vec<String> = Vec::new();
+        let mut stack = vec![Self::ROOT];
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            match self.state(n) {
+                Check::Checked => {}
+                Check::Unchecked if n != Self::ROOT => {
+                    let pattern = if node.is_dir { format!("/{}", node.name) } else { node.name.clone() };
+                    if !out.contains(&pattern) {
+                        out.push(pattern);
+                    }
+                }
+                _ => stack.extend(node.children.iter().rev().copied()),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> ItemTree {
+        let root = Path::new("/photos");
+        let files: Vec<PathBuf> = [
+            "2023/summer/beach.jpg",
+            "2023/summer/lake.jpg",
+            "2023/winter/snow.jpg",
+            "2024/raw/beach.cr2",
+            "notes.txt",
+        ]
+        .iter()
+        .map(|f| root.join(f))
+        .collect();
+        // The patterns left out the raw file.
+        let kept: HashSet<PathBuf> = files.iter().filter(|f| !f.ends_with("beach.cr2")).cloned().collect();
+        ItemTree::build(root, &files, &kept)
+    }
+
+    fn find(tree: &ItemTree, path: &str) -> usize {
+        (0..tree.node_count()).find(|&i| tree.node(i).path == Path::new(path)).unwrap()
+    }
+
+    #[test]
+    fn pattern_excluded_items_start_unchecked() {
+        let tree = sample();
+        assert_eq!(tree.counts(), (5, 4));
+        assert_eq!(tree.state(ItemTree::ROOT), Check::Mixed);
+        assert_eq!(tree.state(find(&tree, "/photos/2023")), Check::Checked);
+        assert_eq!(tree.state(find(&tree, "/photos/2024")), Check::Unchecked);
+        // Directories are listed before files.
+        let names: Vec<&str> =
+            tree.node(ItemTree::ROOT).children.iter().map(|&c| tree.node(c).name.as_str()).collect();
+        assert_eq!(names, ["2023", "2024", "notes.txt"]);
+    }
+
+    #[test]
+    fn unchecking_a_directory_drops_its_subtree() {
+        let mut tree = sample();
+        tree.set_checked(find(&tree, "/photos/2023/summer"), false);
+        assert_eq!(tree.state(find(&tree, "/photos/2023")), Check::Mixed);
+        assert_eq!(
+            tree.selected_files(),
+            [PathBuf::from("/photos/2023/winter/snow.jpg"), PathBuf::from("/photos/notes.txt")]
+        );
+        assert_eq!(tree.counts(), (5, 2));
+    }
+
+    #[test]
+    fn rechecking_restores_items_the_patterns_excluded() {
+        let mut tree = sample();
+        tree.set_checked(ItemTree::ROOT, true);
+        assert_eq!(tree.state(ItemTree::ROOT), Check::Checked);
+        assert_eq!(tree.selected_files().len(), 5);
+        tree.set_checked(find(&tree, "/photos/notes.txt"), false);
+        tree.set_checked(find(&tree, "/photos/notes.txt"), false);
+        assert_eq!(tree.counts(), (5, 4));
+    }
+
+    #[test]
+    fn unchecks_become_the_smallest_set_of_patterns() {
+        let mut tree = sample();
+        tree.set_checked(find(&tree, "/photos/2023/summer/lake.jpg"), false);
+        tree.set_checked(find(&tree, "/photos/2023/winter"), false);
+        assert_eq!(tree.exclusion_patterns(), ["lake.jpg", "/winter", "/2024"]);
+        tree.set_checked(ItemTree::ROOT, true);
+        assert!(tree.exclusion_patterns().is_empty());
+    }
+
+    #[test]
+    fn files_outside_the_root_are_ignored() {
+        let files = vec![PathBuf::from("/elsewhere/a.txt"), PathBuf::from("/photos/b.txt")];
+        let tree = ItemTree::build(Path::new("/photos"), &files, &files.iter().cloned().collect());
+        assert_eq!(tree.node_count(), 2);
+        assert_eq!(tree.selected_files(), [PathBuf::from("/photos/b.txt")]);
+    }
+}
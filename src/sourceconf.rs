@@ -0,0 +1,343 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config;
+use crate::patterns::{self, PatternKind};
+use crate::rename::Rule;
+use crate::ConflictMode;
+
+// ── Per-source options ─────────────────────────────────────────────────
+//
+// Different trees want different policies: a code folder always leaves
+// out `target/` and renames on conflict, a photo folder never overwrites.
+// A `.kosmokopy.toml` at the root of a folder source sets some options for
+// copies of that tree, one `key = value` per line:
+//
+//     exclude = ["target/", "~*.o"]
+//     include = ["*.rs"]
+//     conflict = "rename"
+//     rename_rules = ["case-fold-extension"]
+//     strip_spaces = true
+//
+// A flag or a GUI choice beats the file, and the file beats the built-in
+// defaults; exclusions add to the ones chosen.  Only these keys are read:
+// anything that could delete, move or run something (`move`, `mirror`,
+// `retain`, hooks) is never taken from a tree that may not be yours, and
+// is named as ignored.  `--no-source-config` (or `source_config = false`
+// in `settings.conf`) skips the file altogether.
+
+pub const FILE_NAME: &str = ".kosmokopy.toml";
+
+/// What a source's `.kosmokopy.toml` asks for.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SourceConfig {
+    /// Stored-form patterns, as `--exclude` and `--include` add them:
+    /// `exclude` takes `--exclude`'s forms (`~*.o` for a wildcard).
+    pub patterns: Vec<String>,
+    pub conflict: Option<ConflictMode>,
+    pub rename_rules: Vec<Rule>,
+    pub strip_spaces: bool,
+    /// What was not used and why, e.g. `mirror (not allowed)`.
+    pub ignored: Vec<String>,
+}
+
+impl SourceConfig {
+    pub fn parse(text: &str) -> SourceConfig {
+        let mut found = SourceConfig::default();
+        for (key, value) in config::entries(text) {
+            match key {
+                "exclude" | "include" => {
+                    let Some(list) = strings(value) else {
+                        found.ignored.push(format!("{} (expected a list of quoted patterns)", key));
+                        continue;
+                    };
+                    for pattern in list {
+                        let tidied = if key == "exclude" {
+                            patterns::normalize_stored(&pattern)
+                        } else {
+                            patterns::normalize(PatternKind::Include, &pattern)
+                        };
+                        match tidied {
+                            Ok(tidied) => {
+                                patterns::add_unique(&mut found.patterns, tidied);
+                            }
+                            Err(e) => found.ignored.push(format!("{} {}", key, e)),
+                        }
+                    }
+                }
+                "conflict" => match string(value).as_deref() {
+                    Some("skip") => found.conflict = Some(ConflictMode::Skip),
+                    Some("overwrite") => found.conflict = Some(ConflictMode::Overwrite),
+                    Some("rename") => found.conflict = Some(ConflictMode::Rename),
+                    Some("newer") => found.conflict = Some(ConflictMode::Newer),
+                    _ => found.ignored.push(format!(
+                        "conflict (expected \"skip\", \"overwrite\", \"rename\" or \"newer\", got {})",
+                        value
+                    )),
+                },
+                "rename_rules" => {
+                    let Some(list) = strings(value) else {
+                        found.ignored.push("rename_rules (expected a list of quoted rules)".to_string());
+                        continue;
+                    };
+                    for spec in list {
+                        match Rule::parse(&spec) {
+                            Ok(rule) => found.rename_rules.push(rule),
+                            Err(e) => found.ignored.push(format!("rename rule {}", e)),
+                        }
+                    }
+                }
+                "strip_spaces" => match config::flag(value) {
+                    Some(flag) => found.strip_spaces = flag,
+                    None => found.ignored.push(format!("strip_spaces (expected true or false, got {})", value)),
+                },
+                other => found.ignored.push(format!("{} (not allowed)", other)),
+            }
+        }
+        found
+    }
+
+    /// Fill in what `chosen` leaves open from this file; returns what
+    /// changed, e.g. `+3 exclusion(s)`, `conflict=rename`.
+    pub fn apply(&self, chosen: &mut Chosen) -> Vec<String> {
+        let mut changes = Vec::new();
+        let before = chosen.patterns.len();
+        for pattern in &self.patterns {
+            patterns::add_unique(&mut chosen.patterns, pattern.clone());
+        }
+        let added = &chosen.patterns[before..];
+        let includes = added.iter().filter(|p| PatternKind::decode(p).0 == PatternKind::Include).count();
+        if added.len() > includes {
+            changes.push(format!("+{} exclusion(s)", added.len() - includes));
+        }
+        if includes > 0 {
+            changes.push(format!("+{} include(s)", includes));
+        }
+        if let (None, Some(conflict)) = (chosen.conflict, self.conflict) {
+            chosen.conflict = Some(conflict);
+            changes.push(format!("conflict={}", conflict_name(conflict)));
+        }
+        if chosen.rename_rules.is_none() && !self.rename_rules.is_empty() {
+            chosen.rename_rules = Some(self.rename_rules.clone());
+            changes.push(format!("{} rename rule(s)", self.rename_rules.len()));
+        }
+        if self.strip_spaces && !chosen.strip_spaces {
+            chosen.strip_spaces = true;
+            changes.push("strip spaces".to_string());
+        }
+        changes
+    }
+}
+
+/// The options of a run that a source config may fill in, as flags or the
+/// GUI chose them: `None` where nothing was chosen, so the source config
+/// or else the built-in default applies.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Chosen {
+    pub patterns: Vec<String>,
+    pub conflict: Option<ConflictMode>,
+    pub rename_rules: Option<Vec<Rule>>,
+    /// Can only be turned on, so `false` leaves it open.
+    pub strip_spaces: bool,
+}
+
+pub fn conflict_name(mode: ConflictMode) -> &'static str {
+    match mode {
+        ConflictMode::Skip => "skip",
+        ConflictMode::Overwrite => "overwrite",
+        ConflictMode::Rename => "rename",
+        ConflictMode::Newer => "newer",
+    }
+}
+
+/// The `.kosmokopy.toml` at `root`, or `None` when there is none.
+pub fn load(root: &Path) -> Option<SourceConfig> {
+    match fs::read_to_string(root.join(FILE_NAME)) {
+        Ok(text) => Some(SourceConfig::parse(&text)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => Some(SourceConfig { ignored: vec![format!("{} ({})", FILE_NAME, e)], ..SourceConfig::default() }),
+    }
+}
+
+/// Apply the `.kosmokopy.toml` at `root` to `chosen`; returns the line
+/// that says what it did, or `None` when there is nothing to say.
+pub fn merge(root: &Path, chosen: &mut Chosen) -> Option<String> {
+    let found = load(root)?;
+    let changes = found.apply(chosen);
+    let mut note = Vec::new();
+    if !changes.is_empty() {
+        note.push(format!("options adjusted by source config: {}", changes.join(", ")));
+    }
+    if !found.ignored.is_empty() {
+        note.push(format!("source config ignored: {}", found.ignored.join("; ")));
+    }
+    let note = (!note.is_empty()).then(|| note.join(" · "))?;
+    crate::debug_log(&format!("{}: {}", root.join(FILE_NAME).display(), note));
+    Some(note)
+}
+
+// ── Values ─────────────────────────────────────────────────────────────
+//
+// The little TOML the file needs: quoted strings and one-line lists of
+// them, with an optional `# comment` after the value.  The remembered
+// window state (see `state`) is written and read with the same.
+
+/// `"text"` (with `\"`, `\\`, `\t` and `\n` escapes) or `'text'` at the
+/// start of `value`, and what follows it.
+fn quoted(value: &str) -> Option<(String, &str)> {
+    let mut chars = value.char_indices();
+    let quote = match chars.next()? {
+        (_, q @ ('"' | '\'')) => q,
+        _ => return None,
+    };
+    let mut text = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Some((text, &value[i + 1..])),
+            '\\' if quote == '"' => match chars.next()?.1 {
+                'n' => text.push('\n'),
+                't' => text.push('\t'),
+                c @ ('"' | '\\') => text.push(c),
+                _ => return None,
+            },
+            c => text.push(c),
+        }
+    }
+    None
+}
+
+/// Whether `rest` is only blanks and perhaps a comment.
+fn at_end(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+pub fn string(value: &str) -> Option<String> {
+    let (text, rest) = quoted(value)?;
+    at_end(rest).then_some(text)
+}
+
+/// `"text"`, escaped so that `string` reads back `text`.
+pub fn quote(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `["a", 'b']`; a trailing comma is allowed.
+pub fn strings(value: &str) -> Option<Vec<String>> {
+    let mut rest = value.strip_prefix('[')?.trim_start();
+    let mut list = Vec::new();
+    loop {
+        if let Some(after) = rest.strip_prefix(']') {
+            return at_end(after).then_some(list);
+        }
+        let (text, after) = quoted(rest)?;
+        list.push(text);
+        rest = after.trim_start();
+        match rest.strip_prefix(',') {
+            Some(after) => rest = after.trim_start(),
+            None if rest.starts_with(']') => {}
+            None => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_configs_read_only_their_keys() {
+        let found = SourceConfig::parse(
+            "# code folder\nexclude = [\"target/\", '~*.o', \"../b\"]  # build output\ninclude = [\"*.rs\",]\nconflict = \"rename\"\nrename_rules = [\"case-fold-extension\", \"bogus\"]\nstrip_spaces = true\nmirror = true\nhooks = [\"rm -rf ~\"]\nmove = true\n",
+        );
+        assert_eq!(found.patterns, ["/target", "~*.o", "+*.rs"]);
+        assert_eq!(found.conflict, Some(ConflictMode::Rename));
+        assert_eq!(found.rename_rules, vec![Rule::CaseFoldExtension]);
+        assert!(found.strip_spaces);
+        assert_eq!(found.ignored.len(), 5, "{:?}", found.ignored);
+        assert!(found.ignored[0].starts_with("exclude '../b' goes up"));
+        assert!(found.ignored[1].starts_with("rename rule 'bogus'"));
+        assert_eq!(found.ignored[2..], ["mirror (not allowed)", "hooks (not allowed)", "move (not allowed)"]);
+
+        assert_eq!(strings("[]"), Some(vec![]));
+        assert_eq!(strings("[\"a\\\"b\", 'c\\d']"), Some(vec!["a\"b".to_string(), "c\\d".to_string()]));
+        for bad in ["\"a\"", "[\"a\" \"b\"]", "[\"a\"", "[a]", "[\"a\"] x"] {
+            assert_eq!(strings(bad), None, "{}", bad);
+        }
+        assert_eq!(string("\"rename\" # why"), Some("rename".to_string()));
+        assert_eq!(string("rename"), None);
+    }
+
+    #[test]
+    fn choices_beat_the_source_config_which_beats_defaults() {
+        let source = SourceConfig {
+            patterns: vec!["/target".to_string(), "+*.rs".to_string()],
+            conflict: Some(ConflictMode::Rename),
+            rename_rules: vec![Rule::CaseFoldExtension],
+            strip_spaces: true,
+            ignored: Vec::new(),
+        };
+        let chosen_rules = vec![Rule::strip_spaces()];
+        // Each option: chosen and in the file, chosen only, in the file
+        // only, neither.
+        for (chosen_set, source_set) in [(true, true), (true, false), (false, true), (false, false)] {
+            let source = if source_set { source.clone() } else { SourceConfig::default() };
+            let mut chosen = Chosen::default();
+            if chosen_set {
+                chosen = Chosen {
+                    patterns: vec!["/target".to_string(), "/.git".to_string()],
+                    conflict: Some(ConflictMode::Skip),
+                    rename_rules: Some(chosen_rules.clone()),
+                    strip_spaces: true,
+                };
+            }
+            let changes = source.apply(&mut chosen);
+            let case = (chosen_set, source_set);
+
+            let conflict = match case {
+                (true, _) => Some(ConflictMode::Skip),
+                (false, true) => Some(ConflictMode::Rename),
+                (false, false) => None,
+            };
+            assert_eq!(chosen.conflict, conflict, "{:?}", case);
+            let rules = match case {
+                (true, _) => Some(chosen_rules.clone()),
+                (false, true) => Some(vec![Rule::CaseFoldExtension]),
+                (false, false) => None,
+            };
+            assert_eq!(chosen.rename_rules, rules, "{:?}", case);
+            assert_eq!(chosen.strip_spaces, chosen_set || source_set, "{:?}", case);
+            // Patterns add up, the chosen ones first.
+            let patterns: &[&str] = match case {
+                (true, true) => &["/target", "/.git", "+*.rs"],
+                (true, false) => &["/target", "/.git"],
+                (false, true) => &["/target", "+*.rs"],
+                (false, false) => &[],
+            };
+            assert_eq!(chosen.patterns, patterns, "{:?}", case);
+
+            let expected: &[&str] = match case {
+                (true, true) => &["+1 include(s)"],
+                (false, true) => &["+1 exclusion(s)", "+1 include(s)", "conflict=rename", "1 rename rule(s)", "strip spaces"],
+                _ => &[],
+            };
+            assert_eq!(changes, expected, "{:?}", case);
+        }
+    }
+}
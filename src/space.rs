@@ -0,0 +1,115 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use crate::preflight::deepest_existing;
+use crate::progress::format_bytes;
+
+// ── Free space at the destination ──────────────────────────────────────
+//
+// Nothing used to stop a 300 GB copy into a 50 GB partition until it had
+// failed halfway with hundreds of "No space left on device" errors.  Once
+// the plan is known, before any file is sent, the run compares the bytes
+// it will write with the space free at the destination: `statvfs` on the
+// deepest existing folder of a local destination, `df -P` over ssh for a
+// remote one.  Short of room, the run stops with "Needs 312.0 GiB,
+// destination has 48.0 GiB free" unless told to transfer anyway
+// (`--ignore-free-space`, or the window's Transfer anyway).
+//
+// Each file is counted rounded up to a whole 4 KiB block, and 1% more is
+// kept back for folders and the filesystem's own bookkeeping.  Files the
+// run will skip as already there are still counted, so the check errs on
+// asking too much.  A destination whose free space can't be learnt is not
+// held up.
+
+/// The block size files are rounded up to.
+const BLOCK: u64 = 4096;
+
+/// A destination without room for the planned files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shortfall {
+    /// The bytes the run would need there, margin included.
+    pub needed: u64,
+    pub free: u64,
+}
+
+impl Shortfall {
+    /// e.g. `Needs 312.0 GiB, destination has 48.0 GiB free`.
+    pub fn describe(&self) -> String {
+        format!("Needs {}, destination has {} free", format_bytes(self.needed), format_bytes(self.free))
+    }
+}
+
+/// The bytes files of `sizes` take at the destination, with the margin;
+/// a file of unknown size counts as one block.
+pub fn needed(sizes: &[Option<u64>]) -> u64 {
+    let blocks: u64 = sizes.iter().map(|s| s.unwrap_or(0).div_ceil(BLOCK).max(1) * BLOCK).sum();
+    blocks + blocks / 100
+}
+
+/// The shortfall when `free` bytes can't hold files of `sizes`.
+pub fn shortfall(sizes: &[Option<u64>], free: u64) -> Option<Shortfall> {
+    let needed = needed(sizes);
+    (needed > free).then_some(Shortfall { needed, free })
+}
+
+/// Bytes an unprivileged user can write on the filesystem holding the
+/// local folder `dir`, or the deepest of its ancestors that exists.
+#[allow(clippy::unnecessary_cast)]
+pub fn local_free(dir: &Path) -> Option<u64> {
+    let existing = deepest_existing(dir)?;
+    let path = CString::new(existing.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is a NUL-terminated string and `stat` a valid buffer
+    // for the call to fill.
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+/// Shell command printing `df -P` for the already quoted remote folder
+/// `dir`, or the deepest of its ancestors that exists.
+pub fn remote_command(quoted_dir: &str) -> String {
+    format!(
+        "p={}; while [ ! -d \"$p\" ]; do p=$(dirname \"$p\"); done; df -Pk \"$p\"",
+        quoted_dir
+    )
+}
+
+/// The free bytes in the output of `df -Pk`: the Available column of its
+/// last line, in KiB.
+pub fn parse_df(output: &str) -> Option<u64> {
+    let line = output.lines().rev().find(|l| !l.trim().is_empty())?;
+    // A long device name can push the numbers along, but never past the
+    // last five columns.
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let available = fields.len().checked_sub(3).and_then(|i| fields.get(i))?;
+    available.parse::<u64>().ok().map(|kib| kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_plan_too_big_for_the_space_falls_short() {
+        // Two files of one byte and one block, and one of unknown size.
+        let sizes = [Some(1), Some(4096), None];
+        assert_eq!(needed(&sizes), 3 * 4096 + 122);
+        assert_eq!(shortfall(&sizes, 3 * 4096 + 122), None);
+        let short = shortfall(&[Some(312 << 30)], 48 << 30).unwrap();
+        assert_eq!(short.describe(), "Needs 315.1 GiB, destination has 48.0 GiB free");
+
+        let df = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                  /dev/mapper/vg-home 488245288 437903744  50341544      90% /home\n";
+        assert_eq!(parse_df(df), Some(50341544 * 1024));
+        assert_eq!(parse_df("df: /nowhere: No such file or directory\n"), None);
+        assert!(remote_command("'/srv/new dir'").starts_with("p='/srv/new dir'; while"));
+        assert!(local_free(&std::env::temp_dir().join("kosmokopy-not-yet/made")).is_some());
+    }
+}
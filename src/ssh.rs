@@ -0,0 +1,713 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use crate::activity::TrackActivity;
+
+// ── SSH options ────────────────────────────────────────────────────────
+
+/// Set (in the environment) when ssh runs Kosmokopy as its askpass helper.
+pub const ASKPASS_ENV: &str = "KOSMOKOPY_ASKPASS";
+
+/// Options shared by every ssh/scp call: one reusable connection per host
+/// and a bounded connect time so unreachable hosts fail instead of hanging.
+/// The `ControlPath` comes from [`control_path_option`].
+const CONTROL: [&str; 6] = [
+    "-o", "ControlMaster=auto",
+    "-o", "ControlPersist=60",
+    "-o", "ConnectTimeout=10",
+];
+
+static ASKPASS: AtomicBool = AtomicBool::new(false);
+static ACCEPT_NEW: AtomicBool = AtomicBool::new(false);
+
+/// The `-o` arguments for an ssh or scp call to `host`.
+///
+/// Unless askpass was enabled, `BatchMode=yes` is added so a missing key
+/// makes ssh fail at once rather than wait for a password nobody can type.
+pub fn ctl_args(host: &str) -> Vec<String> {
+    let mut args: Vec<String> = CONTROL.iter().map(|a| a.to_string()).collect();
+    args.extend(["-o".to_string(), control_dir().option.clone()]);
+    if !ASKPASS.load(Ordering::SeqCst) {
+        args.extend(["-o".to_string(), "BatchMode=yes".to_string()]);
+    }
+    if ACCEPT_NEW.load(Ordering::SeqCst) {
+        args.extend(["-o".to_string(), "StrictHostKeyChecking=accept-new".to_string()]);
+    }
+    args.extend(host_args(host));
+    args
+}
+
+/// Ports given with `ssh://host:port/…`, by the host as the workers name
+/// it (`backup@nas`).
+static PORTS: Mutex<Vec<(String, u16)>> = Mutex::new(Vec::new());
+
+/// Reach `host` on `port` from now on, e.g. for `ssh://nas:2222/backup`.
+pub fn set_port(host: &str, port: u16) {
+    let mut ports = PORTS.lock().unwrap_or_else(|e| e.into_inner());
+    ports.retain(|(h, _)| h != host);
+    ports.push((host.to_string(), port));
+}
+
+fn port(host: &str) -> Option<u16> {
+    PORTS.lock().unwrap_or_else(|e| e.into_inner()).iter().find(|(h, _)| h == host).map(|(_, p)| *p)
+}
+
+/// `path` on `host` as the user would write it again, e.g. in a
+/// failed-files list: `host:path`, or `ssh://host:port/path` when the
+/// host has a port of its own.  The path's bytes are kept as they are.
+pub fn location(host: &str, path: &Path) -> OsString {
+    let mut out = OsString::from(match port(host) {
+        Some(port) if path.has_root() => format!("ssh://{}:{}", host, port),
+        Some(port) => format!("ssh://{}:{}/~/", host, port),
+        None => format!("{}:", host),
+    });
+    out.push(path);
+    out
+}
+
+/// Whether scp takes the remote `path` as it is.  scp in SFTP mode sends
+/// quotes, `$`, backticks and spaces through untouched, but expands glob
+/// characters and can't name a file holding a newline; such a file goes
+/// over `ssh … cat` instead.
+pub fn scp_literal(path: &Path) -> bool {
+    !path.as_os_str().as_bytes().iter().any(|b| b"*?[]\\\n".contains(b))
+}
+
+/// The `-o` arguments `host` needs of its own: its port when one was set,
+/// and, for an IPv6 address (bracketed, so `host:path` stays unambiguous
+/// for scp and rsync), the bare address ssh connects to.
+pub fn host_args(host: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let name = host.rsplit_once('@').map_or(host, |(_, name)| name);
+    if let Some(address) = name.strip_prefix('[').and_then(|n| n.strip_suffix(']')) {
+        // ssh expands `%` in HostName, and a zone index holds one.
+        args.extend(["-o".to_string(), format!("HostName={}", address.replace('%', "%%"))]);
+    }
+    if let Some(port) = port(host) {
+        args.extend(["-o".to_string(), format!("Port={}", port)]);
+    }
+    args
+}
+
+/// Trust hosts seen for the first time (`--accept-new-hostkeys`).  A host
+/// whose key has changed is still refused.
+pub fn accept_new_hostkeys() {
+    ACCEPT_NEW.store(true, Ordering::SeqCst);
+}
+
+/// The same options as an rsync `-e` remote shell to `host`.  rsync
+/// splits the command at spaces, so an argument holding one is
+/// single-quoted.
+pub fn rsync_shell(host: &str) -> String {
+    let args: Vec<String> =
+        ctl_args(host).into_iter().map(|a| if a.contains(' ') { format!("'{}'", a) } else { a }).collect();
+    format!("ssh {}", args.join(" "))
+}
+
+/// Route ssh password and passphrase prompts through `exe` (this binary,
+/// which shows a password dialog when started with [`ASKPASS_ENV`] set).
+///
+/// Must be called before any threads are started, since it changes the
+/// environment every ssh child inherits.
+pub fn enable_askpass(exe: &Path) {
+    std::env::set_var("SSH_ASKPASS", exe);
+    std::env::set_var("SSH_ASKPASS_REQUIRE", "force");
+    std::env::set_var(ASKPASS_ENV, "1");
+    ASKPASS.store(true, Ordering::SeqCst);
+}
+
+// ── Connection sockets ─────────────────────────────────────────────────
+//
+// The shared connection is reached through a socket, and anyone who can
+// open that socket can run commands on the host as the user.  The sockets
+// therefore live in a private (0700) directory: `$XDG_RUNTIME_DIR/kosmokopy`,
+// or, without a runtime directory, a fresh one under the temp directory.
+// `--ssh-control-dir` (or `ssh_control_dir` in settings.conf) picks
+// another.  Socket names start with the process id, so
+// `close_connections` only ends this process's connections.
+
+/// Chosen with [`set_control_dir`]; `None` for the default.
+static CONTROL_CHOICE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+struct ControlDir {
+    path: PathBuf,
+    /// The `ControlPath=…` option.
+    option: String,
+    /// Made by this process, so removed again at cleanup.
+    created: bool,
+}
+
+static CONTROL_DIR: OnceLock<ControlDir> = OnceLock::new();
+
+/// Keep the sockets in `dir` instead of the default.  Takes effect only
+/// before the first ssh call.
+pub fn set_control_dir(dir: PathBuf) {
+    if let Ok(mut choice) = CONTROL_CHOICE.lock() {
+        *choice = Some(dir);
+    }
+}
+
+/// The socket directory, created on first use.
+fn control_dir() -> &'static ControlDir {
+    CONTROL_DIR.get_or_init(|| {
+        let chosen = CONTROL_CHOICE.lock().ok().and_then(|c| c.clone());
+        let made = match chosen {
+            Some(dir) => make_private_dir(&dir, false).map(|created| (dir, created)),
+            None => match runtime_control_dir(std::env::var_os("XDG_RUNTIME_DIR")) {
+                Some(dir) => make_private_dir(&dir, true).map(|created| (dir, created)),
+                None => make_temp_control_dir().map(|dir| (dir, true)),
+            },
+        };
+        let (path, created) = made.unwrap_or_else(|(dir, e)| {
+            crate::debug_log(&format!("could not create ssh control directory {}: {}", dir.display(), e));
+            (dir, false)
+        });
+        ControlDir { option: control_path_option(&path, std::process::id()), path, created }
+    })
+}
+
+/// `$XDG_RUNTIME_DIR/kosmokopy`; a relative value is ignored, as the XDG
+/// spec asks.
+fn runtime_control_dir(runtime: Option<OsString>) -> Option<PathBuf> {
+    runtime.map(PathBuf::from).filter(|p| p.is_absolute()).map(|p| p.join("kosmokopy"))
+}
+
+/// Create `dir` (and its parents) readable by the owner alone, returning
+/// whether it was created.  With `tighten`, an existing `dir` is made
+/// private too; a folder the user named is left as it is.
+fn make_private_dir(dir: &Path, tighten: bool) -> Result<bool, (PathBuf, io::Error)> {
+    let fail = |e| (dir.to_path_buf(), e);
+    if dir.is_dir() {
+        if tighten {
+            fs::set_permissions(dir, fs::Permissions::from_mode(0o700)).map_err(fail)?;
+        }
+        return Ok(false);
+    }
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(dir).map_err(fail)?;
+    // The mode given is masked by the umask.
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700)).map_err(fail)?;
+    Ok(true)
+}
+
+/// A new private directory under the temp directory.  A name someone else
+/// already took is skipped, so the directory is always one made here.
+fn make_temp_control_dir() -> Result<PathBuf, (PathBuf, io::Error)> {
+    let base = std::env::temp_dir().join(format!("kosmokopy-ssh-{}", std::process::id()));
+    let mut last = io::Error::new(io::ErrorKind::AlreadyExists, "no free name");
+    for n in 0..100 {
+        let dir = if n == 0 { base.clone() } else { PathBuf::from(format!("{}-{}", base.display(), n)) };
+        match fs::DirBuilder::new().mode(0o700).create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => last = e,
+            Err(e) => return Err((dir, e)),
+        }
+    }
+    Err((base, last))
+}
+
+/// `value` as an ssh option value, quoted when it holds whitespace.
+fn quoted(value: String) -> String {
+    if value.contains(char::is_whitespace) {
+        format!("\"{}\"", value)
+    } else {
+        value
+    }
+}
+
+/// `path` with `%` doubled, so ssh doesn't expand it.
+fn literal(path: &Path) -> String {
+    path.display().to_string().replace('%', "%%")
+}
+
+/// `ControlPath=<dir>/ssh-<pid>-%h_%p_%r`.
+fn control_path_option(dir: &Path, pid: u32) -> String {
+    let dir = literal(dir);
+    format!("ControlPath={}", quoted(format!("{}/ssh-{}-%h_%p_%r", dir.trim_end_matches('/'), pid)))
+}
+
+/// End this process's shared connections (`ssh -O exit`) and remove the
+/// socket directory if it was made here.  Nothing to do when ssh was
+/// never used.
+pub fn close_connections() {
+    let Some(dir) = CONTROL_DIR.get() else { return };
+    let prefix = format!("ssh-{}-", std::process::id());
+    for entry in fs::read_dir(&dir.path).into_iter().flatten().flatten() {
+        if !entry.file_name().to_string_lossy().starts_with(&prefix) {
+            continue;
+        }
+        // With a literal ControlPath the destination is only used to read
+        // ssh_config; -O needs one all the same.
+        let _ = Command::new("ssh")
+            .args(["-o", &format!("ControlPath={}", quoted(literal(&entry.path()))), "-O", "exit", "kosmokopy"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .printed()
+            .status();
+    }
+    if dir.created {
+        let _ = fs::remove_dir(&dir.path);
+    }
+}
+
+// ── Authentication failures ────────────────────────────────────────────
+
+/// Whether ssh's stderr says the host refused every authentication method
+/// it was offered, e.g. `Permission denied (publickey,password).`
+pub fn is_auth_failure(stderr: &str) -> bool {
+    stderr.lines().any(|line| {
+        let line = line.trim();
+        (line.contains("Permission denied (") && line.contains(')'))
+            || line.contains("Too many authentication failures")
+    })
+}
+
+/// A targeted message for an ssh authentication failure, or `None` when
+/// `stderr` shows some other problem.
+pub fn auth_error(host: &str, stderr: &str) -> Option<String> {
+    is_auth_failure(stderr).then(|| {
+        format!(
+            "Authentication to '{}' failed — Kosmokopy requires key-based SSH auth; \
+             set up a key or use an ssh-agent.",
+            host
+        )
+    })
+}
+
+/// A targeted message when `stderr` shows a host key or authentication
+/// problem, or `None` for anything else.
+pub fn known_error(host: &str, stderr: &str) -> Option<String> {
+    host_key_problem(stderr)
+        .map(|problem| host_key_error(host, problem))
+        .or_else(|| auth_error(host, stderr))
+}
+
+/// The message for a failed connectivity check to `host`.
+pub fn connection_error(host: &str, stderr: &str) -> String {
+    known_error(host, stderr)
+        .unwrap_or_else(|| format!("SSH connection to '{}' failed: {}", host, stderr.trim()))
+}
+
+// ── Host keys ──────────────────────────────────────────────────────────
+//
+// Non-interactive ssh cannot answer the "are you sure you want to
+// continue connecting" prompt, so the first connection to a new host
+// fails with a bare "Host key verification failed".  The GUI offers the
+// host's fingerprints from `ssh-keyscan` to accept; a changed key is
+// never accepted here, only explained.
+
+/// Why ssh refused a host's key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HostKeyProblem {
+    /// The host is not in known_hosts yet.
+    Unknown,
+    /// known_hosts has a different (or revoked) key for the host.
+    Changed,
+}
+
+impl HostKeyProblem {
+    /// The `kind` of the CLI's error result.
+    pub fn kind(self) -> &'static str {
+        match self {
+            HostKeyProblem::Unknown => "host-key-unknown",
+            HostKeyProblem::Changed => "host-key-changed",
+        }
+    }
+}
+
+/// Whether ssh's stderr shows it refused the host's key, and why.
+pub fn host_key_problem(stderr: &str) -> Option<HostKeyProblem> {
+    if stderr.contains("REMOTE HOST IDENTIFICATION HAS CHANGED")
+        || stderr.contains("REVOKED HOST KEY DETECTED")
+        || (stderr.contains("Host key for ") && stderr.contains(" has changed"))
+    {
+        Some(HostKeyProblem::Changed)
+    } else if stderr.contains("Host key verification failed")
+        || (stderr.contains("host key is known for") && stderr.contains("strict checking"))
+    {
+        Some(HostKeyProblem::Unknown)
+    } else {
+        None
+    }
+}
+
+/// What to tell the user about a refused host key.
+pub fn host_key_error(host: &str, problem: HostKeyProblem) -> String {
+    match problem {
+        HostKeyProblem::Unknown => format!(
+            "'{0}' is not in your known hosts, so its identity cannot be confirmed. \
+             Connect once with 'ssh {0}' in a terminal and check the fingerprint it shows, \
+             or use --accept-new-hostkeys to trust hosts seen for the first time.",
+            host
+        ),
+        HostKeyProblem::Changed => format!(
+            "WARNING: the host key for '{0}' has changed since you last connected. \
+             Someone may be intercepting the connection, or the host may have been reinstalled. \
+             Kosmokopy will not connect. If you know why it changed, remove the old key with \
+             'ssh-keygen -R {0}', then connect once with 'ssh {0}' and check the new fingerprint.",
+            host
+        ),
+    }
+}
+
+/// A key a host offers, as `ssh-keyscan` printed it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostKey {
+    /// The known_hosts line.
+    pub line: String,
+    /// e.g. `SHA256:uNiVztksCsDhcc0u9e8BujQXVUpKZIDTMczCvj3tD2s (ED25519)`.
+    pub fingerprint: String,
+}
+
+/// Where ssh would connect for `host`, from `ssh -G host`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    pub user: String,
+    pub hostname: String,
+    pub port: String,
+    /// The first `UserKnownHostsFile`.
+    pub known_hosts: Option<PathBuf>,
+}
+
+/// Parse `ssh -G` output.
+pub fn parse_target(config: &str) -> Option<Target> {
+    let mut user = String::new();
+    let mut hostname = None;
+    let mut port = "22".to_string();
+    let mut known_hosts = None;
+    for line in config.lines() {
+        let Some((key, value)) = line.split_once(' ') else { continue };
+        match key {
+            "user" => user = value.to_string(),
+            "hostname" => hostname = Some(value.to_string()),
+            "port" => port = value.to_string(),
+            "userknownhostsfile" => {
+                known_hosts = value.split_whitespace().next().map(|f| match f.strip_prefix("~/") {
+                    Some(rest) => std::env::var_os("HOME").map_or_else(|| PathBuf::from(f), |h| Path::new(&h).join(rest)),
+                    None => PathBuf::from(f),
+                });
+            }
+            _ => {}
+        }
+    }
+    Some(Target { user, hostname: hostname?, port, known_hosts })
+}
+
+/// Whether two targets are one account on one machine, however the user
+/// wrote them (`nas`, `ann@nas.lan`).
+pub fn same_account(a: &Target, b: &Target) -> bool {
+    a.user == b.user && a.hostname.eq_ignore_ascii_case(&b.hostname) && a.port == b.port
+}
+
+/// Pair `ssh-keyscan` output with `ssh-keygen -lf -` run on it; both list
+/// the keys in the same order.
+pub fn pair_fingerprints(keyscan: &str, keygen: &str) -> Vec<HostKey> {
+    let lines = keyscan.lines().filter(|l| !l.trim().is_empty() && !l.starts_with('#'));
+    let fingerprints = keygen.lines().filter_map(|l| {
+        let words: Vec<&str> = l.split_whitespace().collect();
+        let kind = words.last().filter(|w| w.starts_with('('))?;
+        Some(format!("{} {}", words.get(1)?, kind))
+    });
+    lines
+        .zip(fingerprints)
+        .map(|(line, fingerprint)| HostKey { line: line.to_string(), fingerprint })
+        .collect()
+}
+
+/// Add `keys` to the known_hosts file at `path`, creating it (and a
+/// private `~/.ssh`) if needed.
+pub fn append_known_hosts(path: &Path, keys: &[HostKey]) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.exists()) {
+        fs::create_dir_all(dir)?;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    // Don't glue the first key onto a last line without a newline.
+    let ends_cleanly = fs::read(path).map(|b| b.last().is_none_or(|&c| c == b'\n')).unwrap_or(true);
+    let mut text = if ends_cleanly { String::new() } else { "\n".to_string() };
+    for key in keys {
+        text.push_str(&key.line);
+        text.push('\n');
+    }
+    file.write_all(text.as_bytes())
+}
+
+// ── Transfer stderr ────────────────────────────────────────────────────
+//
+// A failed scp or rsync says why on stderr, but often among lines that
+// only describe the connection.  The per-file error shows the one line
+// that explains the failure; the rest goes to the debug log.
+
+/// Longest stderr line put in an error entry.
+const MAX_SUMMARY_LEN: usize = 300;
+
+/// Whether a stderr line only describes the connection (host key notices,
+/// ControlMaster chatter) rather than the failure.
+fn is_noise(line: &str) -> bool {
+    line.is_empty()
+        || line.starts_with("Warning: Permanently added")
+        || line.starts_with("mux_client_")
+        || line.starts_with("muxclient:")
+        || line.starts_with("ControlSocket ")
+        || line.starts_with("control_persist_detach:")
+        || (line.starts_with("Shared connection to ") && line.ends_with(" closed."))
+        || (line.starts_with("Connection to ") && line.ends_with(" closed."))
+}
+
+/// The line of a failed transfer's stderr that explains it, or `None`
+/// when there is nothing but noise.  rsync ends with a generic
+/// `rsync error: … (code 23)` line, so an earlier, more specific one is
+/// preferred.
+pub fn stderr_summary(stderr: &str) -> Option<String> {
+    let lines: Vec<&str> = stderr.lines().map(str::trim).filter(|l| !is_noise(l)).collect();
+    let line = lines
+        .iter()
+        .find(|l| !l.starts_with("rsync error:"))
+        .or(lines.first())?;
+    if line.chars().count() <= MAX_SUMMARY_LEN {
+        return Some(line.to_string());
+    }
+    let mut out: String = line.chars().take(MAX_SUMMARY_LEN - 1).collect();
+    out.push('…');
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_denied_is_an_auth_failure() {
+        for stderr in [
+            "user@nas: Permission denied (publickey,password).\n",
+            "Warning: Permanently added 'nas' to the list of known hosts.\r\nuser@nas: Permission denied (publickey).\r\n",
+            "Permission denied (publickey,keyboard-interactive).",
+            "Received disconnect from 10.0.0.2 port 22:2: Too many authentication failures\n",
+        ] {
+            assert!(is_auth_failure(stderr), "{:?}", stderr);
+        }
+    }
+
+    #[test]
+    fn other_failures_are_not_auth_failures() {
+        for stderr in [
+            "ssh: connect to host nas port 22: Connection timed out\n",
+            "ssh: Could not resolve hostname nas: Name or service not known\n",
+            "scp: /srv/x: Permission denied\n",
+            "",
+        ] {
+            assert!(!is_auth_failure(stderr), "{:?}", stderr);
+        }
+    }
+
+    #[test]
+    fn connection_error_names_the_host() {
+        let msg = connection_error("nas", "user@nas: Permission denied (publickey,password).");
+        assert!(msg.starts_with("Authentication to 'nas' failed"));
+        assert!(msg.contains("ssh-agent"));
+        assert_eq!(
+            connection_error("nas", "ssh: connect to host nas port 22: Connection refused\n"),
+            "SSH connection to 'nas' failed: ssh: connect to host nas port 22: Connection refused"
+        );
+    }
+
+    const UNKNOWN_BATCH: &str = "No ED25519 host key is known for nas.lan and you have requested strict checking.\r\n\
+                                 Host key verification failed.\r\n";
+
+    const UNKNOWN_NO_TTY: &str = "Host key verification failed.\n";
+
+    const CHANGED: &str = "@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@
+@    WARNING: REMOTE HOST IDENTIFICATION HAS CHANGED!     @
+@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@
+IT IS POSSIBLE THAT SOMEONE IS DOING SOMETHING NASTY!
+Someone could be eavesdropping on you right now (man-in-the-middle attack)!
+It is also possible that a host key has just been changed.
+The fingerprint for the ED25519 key sent by the remote host is
+SHA256:uNiVztksCsDhcc0u9e8BujQXVUpKZIDTMczCvj3tD2s.
+Please contact your system administrator.
+Add correct host key in /home/ann/.ssh/known_hosts to get rid of this message.
+Offending ED25519 key in /home/ann/.ssh/known_hosts:12
+Host key for nas.lan has changed and you have requested strict checking.
+Host key verification failed.
+";
+
+    const REVOKED: &str = "@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@
+@       WARNING: REVOKED HOST KEY DETECTED!               @
+@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@
+The ED25519 host key for nas.lan is marked as revoked.
+Host key verification failed.
+";
+
+    #[test]
+    fn host_key_failures_are_classified() {
+        assert_eq!(host_key_problem(UNKNOWN_BATCH), Some(HostKeyProblem::Unknown));
+        assert_eq!(host_key_problem(UNKNOWN_NO_TTY), Some(HostKeyProblem::Unknown));
+        assert_eq!(host_key_problem(CHANGED), Some(HostKeyProblem::Changed));
+        assert_eq!(host_key_problem(REVOKED), Some(HostKeyProblem::Changed));
+        assert_eq!(host_key_problem("user@nas: Permission denied (publickey).\n"), None);
+        assert_eq!(host_key_problem("ssh: connect to host nas port 22: Connection refused\n"), None);
+        // A host added on the fly is not a failure.
+        assert_eq!(host_key_problem("Warning: Permanently added 'nas' (ED25519) to the list of known hosts.\n"), None);
+    }
+
+    #[test]
+    fn host_key_messages_say_what_to_do() {
+        let unknown = connection_error("nas", UNKNOWN_BATCH);
+        assert!(unknown.starts_with("'nas' is not in your known hosts"));
+        assert!(unknown.contains("--accept-new-hostkeys"));
+        let changed = connection_error("nas", CHANGED);
+        assert!(changed.starts_with("WARNING: the host key for 'nas' has changed"));
+        assert!(changed.contains("ssh-keygen -R nas"));
+        assert!(!changed.contains("--accept-new-hostkeys"));
+        assert_eq!(HostKeyProblem::Changed.kind(), "host-key-changed");
+    }
+
+    #[test]
+    fn keyscan_output_is_paired_with_fingerprints() {
+        let keyscan = "[nas.lan]:2222 ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPx\n\
+                       [nas.lan]:2222 ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABgQC\n";
+        let keygen = "256 SHA256:uNiVztksCsDhcc0u9e8BujQXVUpKZIDTMczCvj3tD2s [nas.lan]:2222 (ED25519)\n\
+                      3072 SHA256:Q3mEPkB0pHhDX3GnL0s7bRr8mVt8a1X2c3d4e5f6g7h [nas.lan]:2222 (RSA)\n";
+        let keys = pair_fingerprints(keyscan, keygen);
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].line, "[nas.lan]:2222 ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPx");
+        assert_eq!(keys[0].fingerprint, "SHA256:uNiVztksCsDhcc0u9e8BujQXVUpKZIDTMczCvj3tD2s (ED25519)");
+        assert_eq!(keys[1].fingerprint, "SHA256:Q3mEPkB0pHhDX3GnL0s7bRr8mVt8a1X2c3d4e5f6g7h (RSA)");
+        assert!(pair_fingerprints("", "").is_empty());
+    }
+
+    #[test]
+    fn ssh_config_gives_the_target() {
+        let config = "user ann\nhostname nas.lan\nport 2222\nuserknownhostsfile /etc/kk/known_hosts /etc/kk/known_hosts2\n";
+        let target = parse_target(config).unwrap();
+        assert_eq!((target.hostname.as_str(), target.port.as_str()), ("nas.lan", "2222"));
+        assert_eq!(target.known_hosts, Some(PathBuf::from("/etc/kk/known_hosts")));
+        assert_eq!(parse_target("port 22\n"), None);
+        let alias = parse_target("hostname NAS.lan\nuser ann\nport 2222\n").unwrap();
+        assert!(same_account(&target, &alias));
+        assert!(!same_account(&target, &parse_target("user bob\nhostname nas.lan\nport 2222\n").unwrap()));
+    }
+
+    #[test]
+    fn keys_are_appended_on_their_own_lines() {
+        let dir = std::env::temp_dir().join(format!("kosmokopy-knownhosts-{}", std::process::id()));
+        let path = dir.join(".ssh/known_hosts");
+        let key = |line: &str| HostKey { line: line.to_string(), fingerprint: String::new() };
+        append_known_hosts(&path, &[key("a ssh-ed25519 AAAA")]).unwrap();
+        fs::write(&path, "a ssh-ed25519 AAAA").unwrap();
+        append_known_hosts(&path, &[key("b ssh-rsa BBBB"), key("b ssh-ed25519 CCCC")]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a ssh-ed25519 AAAA\nb ssh-rsa BBBB\nb ssh-ed25519 CCCC\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn batch_mode_unless_askpass() {
+        assert!(ctl_args("nas").iter().any(|a| a == "BatchMode=yes"));
+        assert!(ctl_args("nas").iter().any(|a| a == "ConnectTimeout=10"));
+        assert!(rsync_shell("nas").starts_with("ssh -o ControlMaster=auto"));
+    }
+
+    #[test]
+    fn hosts_get_their_port_and_address() {
+        assert!(host_args("nas").is_empty());
+        set_port("backup@nas-ported", 2222);
+        assert_eq!(host_args("backup@nas-ported"), ["-o", "Port=2222"]);
+        assert!(host_args("nas-ported").is_empty());
+        set_port("backup@nas-ported", 2200);
+        assert!(rsync_shell("backup@nas-ported").ends_with(" -o Port=2200"));
+        assert_eq!(location("backup@nas-ported", Path::new("/srv/a.txt")), "ssh://backup@nas-ported:2200/srv/a.txt");
+        assert_eq!(location("backup@nas-ported", Path::new("in/a.txt")), "ssh://backup@nas-ported:2200/~/in/a.txt");
+        assert_eq!(location("nas", Path::new("/srv/a.txt")), "nas:/srv/a.txt");
+        assert_eq!(host_args("ann@[fe80::1%eth0]"), ["-o", "HostName=fe80::1%%eth0"]);
+        set_port("[2001:db8::7]", 22022);
+        assert_eq!(host_args("[2001:db8::7]"), ["-o", "HostName=2001:db8::7", "-o", "Port=22022"]);
+    }
+
+    #[test]
+    fn sockets_go_in_the_control_dir() {
+        assert_eq!(
+            control_path_option(Path::new("/run/user/1000/kosmokopy"), 42),
+            "ControlPath=/run/user/1000/kosmokopy/ssh-42-%h_%p_%r"
+        );
+        assert_eq!(
+            control_path_option(Path::new("/home/ann/100% sockets/"), 42),
+            "ControlPath=\"/home/ann/100%% sockets/ssh-42-%h_%p_%r\""
+        );
+        assert_eq!(runtime_control_dir(Some("/run/user/1000".into())), Some(PathBuf::from("/run/user/1000/kosmokopy")));
+        assert_eq!(runtime_control_dir(Some("run".into())), None);
+        assert_eq!(runtime_control_dir(None), None);
+        let option = control_dir().option.as_str();
+        assert!(ctl_args("nas").iter().any(|a| a == option));
+        assert!(!option.contains("/tmp/kosmokopy_ssh_"));
+        assert!(rsync_shell("nas").contains(" ControlPath="));
+        close_connections();
+        assert!(!control_dir().created || !control_dir().path.exists());
+    }
+
+    #[test]
+    fn control_dirs_are_private() {
+        let mode = |dir: &Path| fs::metadata(dir).unwrap().permissions().mode() & 0o777;
+        let root = std::env::temp_dir().join(format!("kosmokopy-controldir-{}", std::process::id()));
+        let dir = root.join("a/kosmokopy");
+        assert!(make_private_dir(&dir, false).unwrap());
+        assert_eq!(mode(&dir), 0o700);
+        assert!(!make_private_dir(&dir, false).unwrap());
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        make_private_dir(&dir, false).unwrap();
+        assert_eq!(mode(&dir), 0o755);
+        make_private_dir(&dir, true).unwrap();
+        assert_eq!(mode(&dir), 0o700);
+        let (first, second) = (make_temp_control_dir().unwrap(), make_temp_control_dir().unwrap());
+        assert_ne!(first, second);
+        assert_eq!((mode(&first), mode(&second)), (0o700, 0o700));
+        for d in [&root, &first, &second] {
+            fs::remove_dir_all(d).unwrap();
+        }
+    }
+
+    #[test]
+    fn stderr_summary_skips_connection_noise() {
+        let stderr = "Warning: Permanently added 'nas' (ED25519) to the list of known hosts.\r\n\
+                      mux_client_request_session: session request failed: Session open refused by peer\n\
+                      ControlSocket /tmp/kosmokopy_ssh_nas_22_ann already exists, disabling multiplexing\n\
+                      scp: /srv/photos/a.jpg: Permission denied\n";
+        assert_eq!(stderr_summary(stderr).as_deref(), Some("scp: /srv/photos/a.jpg: Permission denied"));
+        assert_eq!(stderr_summary("Shared connection to nas closed.\n\n"), None);
+        assert_eq!(stderr_summary(""), None);
+    }
+
+    #[test]
+    fn stderr_summary_prefers_rsyncs_specific_line() {
+        let stderr = "rsync: [sender] link_stat \"/data/a.txt\" failed: No such file or directory (2)\n\
+                      rsync error: some files/attrs were not transferred (see previous errors) (code 23) at main.c(1338) [sender=3.2.7]\n";
+        assert_eq!(
+            stderr_summary(stderr).as_deref(),
+            Some("rsync: [sender] link_stat \"/data/a.txt\" failed: No such file or directory (2)")
+        );
+        let alone = "rsync error: unexplained error (code 255) at io.c(231) [sender=3.2.7]\n";
+        assert_eq!(stderr_summary(alone).as_deref(), Some(alone.trim()));
+        let long = format!("scp: {}", "x".repeat(1000));
+        assert_eq!(stderr_summary(&long).unwrap().chars().count(), MAX_SUMMARY_LEN);
+    }
+
+    #[test]
+    fn scp_takes_names_without_globs_or_newlines_as_they_are() {
+        for path in ["/srv/it's $(touch x) `y`.txt", "/srv/ lead and trail ", "/srv/tab\there"] {
+            assert!(scp_literal(Path::new(path)), "{:?}", path);
+        }
+        for path in ["/srv/*.txt", "/srv/why?.txt", "/srv/[draft].txt", "/srv/back\\slash", "/srv/new\nline"] {
+            assert!(!scp_literal(Path::new(path)), "{:?}", path);
+        }
+    }
+}
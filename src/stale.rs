@@ -0,0 +1,305 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::BTreeSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::coalesce::group_thousands;
+use crate::shown;
+
+// ── Leftovers of an earlier run ────────────────────────────────────────
+//
+// A local copy is written as `<name>.kosmokopy-partial` and renamed once
+// complete, and the writability checks create `.kosmokopy-probe-<pid>`
+// for a moment.  A run that died leaves these behind, and a fresh run into
+// the same destination would mix an old half-copy with new data without
+// anyone noticing.  Before a destination is copied into, it and the
+// folders directly in it are searched for them, here or with `find` on
+// the host, and the run continues the partial copies (`resume`), deletes
+// the leftovers (`clean`) or leaves them (`ignore`).  Only the partial
+// copies that were found are continued; one deeper in the tree is started
+// over, as with `ignore`.  scp can't continue a file, so on a host
+// `resume` is taken as `ignore`.  The GUI asks; the CLI refuses to guess
+// without `--stale-artifacts`.  Only names of exactly these two patterns
+// are ever matched, so cleaning can't touch anything else.
+
+/// What a copy is called until it is complete: `<name>` + this.
+pub const PARTIAL_SUFFIX: &str = ".kosmokopy-partial";
+
+/// A writability probe: this + the process id.
+pub const PROBE_PREFIX: &str = ".kosmokopy-probe-";
+
+/// What to do about leftovers found at the destination.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnStale {
+    /// Continue each partial copy when its file is copied again.
+    Resume,
+    /// Delete the leftovers, then copy afresh.
+    Clean,
+    /// Leave them; a partial copy is started over when its file is copied.
+    Ignore,
+}
+
+impl OnStale {
+    pub fn parse(word: &str) -> Option<OnStale> {
+        match word {
+            "resume" => Some(OnStale::Resume),
+            "clean" => Some(OnStale::Clean),
+            "ignore" => Some(OnStale::Ignore),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OnStale::Resume => "resume",
+            OnStale::Clean => "clean",
+            OnStale::Ignore => "ignore",
+        }
+    }
+}
+
+/// Whether the file named `name` is one Kosmokopy leaves behind.
+pub fn is_ours(name: &OsStr) -> bool {
+    let name = name.as_bytes();
+    if let Some(pid) = name.strip_prefix(PROBE_PREFIX.as_bytes()) {
+        return !pid.is_empty() && pid.iter().all(u8::is_ascii_digit);
+    }
+    name.len() > PARTIAL_SUFFIX.len() && name.ends_with(PARTIAL_SUFFIX.as_bytes())
+}
+
+/// Where the copy to `dest` is written until it is complete.
+pub fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.as_os_str().to_os_string();
+    name.push(PARTIAL_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Leftovers found at a destination.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Found {
+    pub partials: Vec<PathBuf>,
+    pub probes: Vec<PathBuf>,
+    /// Whether the partial copies can be continued (local destinations).
+    pub resumable: bool,
+}
+
+impl Found {
+    fn push(&mut self, path: PathBuf) {
+        match path.file_name() {
+            Some(name) if !is_ours(name) => {}
+            Some(name) if name.as_bytes().starts_with(PROBE_PREFIX.as_bytes()) => self.probes.push(path),
+            Some(_) => self.partials.push(path),
+            None => {}
+        }
+    }
+
+    fn sorted(mut self) -> Found {
+        self.partials.sort();
+        self.probes.sort();
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.partials.is_empty() && self.probes.is_empty()
+    }
+
+    /// Whether `resume` would continue anything.
+    pub fn can_resume(&self) -> bool {
+        self.resumable && !self.partials.is_empty()
+    }
+
+    /// Every leftover, partial copies first.
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.partials.iter().chain(&self.probes)
+    }
+
+    /// e.g. `2 partial copies and 1 probe file`.
+    pub fn describe(&self) -> String {
+        describe_counts(self.partials.len(), self.probes.len())
+    }
+}
+
+fn describe_counts(partials: usize, probes: usize) -> String {
+    let count = |n: usize, one: &str, many: &str| format!("{} {}", group_thousands(n), if n == 1 { one } else { many });
+    let mut parts = Vec::new();
+    if partials > 0 {
+        parts.push(count(partials, "partial copy", "partial copies"));
+    }
+    if probes > 0 {
+        parts.push(count(probes, "probe file", "probe files"));
+    }
+    parts.join(" and ")
+}
+
+/// Leftovers in the local folder `dest` and the folders directly in it.
+/// Links are not followed.
+pub fn find_local(dest: &Path) -> Found {
+    let mut found = Found { resumable: true, ..Found::default() };
+    let mut dirs = vec![(dest.to_path_buf(), 1)];
+    while let Some((dir, depth)) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let Ok(kind) = entry.file_type() else { continue };
+            if kind.is_dir() {
+                if depth < 2 {
+                    dirs.push((entry.path(), depth + 1));
+                }
+            } else {
+                found.push(entry.path());
+            }
+        }
+    }
+    found.sorted()
+}
+
+/// The script listing the leftovers in the folder `dest` on a host and the
+/// folders directly in it, NUL-separated for `found_remote`, with the
+/// path quoted by `quote`.
+pub fn find_script(dest: &OsStr, quote: impl Fn(&OsStr) -> OsString) -> OsString {
+    let mut out = OsString::from("find ");
+    out.push(quote(dest));
+    out.push(format!(
+        " -maxdepth 2 -type f \\( -name '*{}' -o -name '{}*' \\) -print0 2>/dev/null; true",
+        PARTIAL_SUFFIX, PROBE_PREFIX
+    ));
+    out
+}
+
+/// The leftovers `find_script` listed.
+pub fn found_remote(listing: &[u8]) -> Found {
+    let mut found = Found::default();
+    for path in listing.split(|&b| b == 0).filter(|p| !p.is_empty()) {
+        found.push(PathBuf::from(OsStr::from_bytes(path)));
+    }
+    found.sorted()
+}
+
+/// What was found at the destination and done about it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decision {
+    pub partials: usize,
+    pub probes: usize,
+    pub choice: OnStale,
+    /// Leftovers deleted (`clean` only).
+    pub removed: usize,
+    /// Leftovers that could not be deleted, with why.
+    pub failed: Vec<String>,
+}
+
+impl Decision {
+    /// Act on `choice` for `found`, deleting with `remove` when cleaning.
+    pub fn apply(found: &Found, choice: OnStale, mut remove: impl FnMut(&[&Path]) -> Vec<(PathBuf, io::Error)>) -> Decision {
+        let mut decision = Decision {
+            partials: found.partials.len(),
+            probes: found.probes.len(),
+            choice,
+            removed: 0,
+            failed: Vec::new(),
+        };
+        if choice == OnStale::Clean {
+            let paths: Vec<&Path> = found.paths().map(PathBuf::as_path).collect();
+            let failed = remove(&paths);
+            decision.removed = paths.len() - failed.len();
+            decision.failed = failed.into_iter().map(|(path, e)| format!("{}: {}", shown(&path), e)).collect();
+        }
+        decision
+    }
+
+    /// e.g. `2 partial copies left by an earlier run: cleaned (2 removed)`.
+    pub fn describe(&self) -> String {
+        let done = match self.choice {
+            OnStale::Resume => "resumed".to_string(),
+            OnStale::Clean => format!("cleaned ({} removed)", group_thousands(self.removed)),
+            OnStale::Ignore => "left in place".to_string(),
+        };
+        format!("{} left by an earlier run: {}", describe_counts(self.partials, self.probes), done)
+    }
+}
+
+/// The partial copies `resume` continues: the ones that were found.
+static RESUMABLE: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Continue these partial copies from now on, and no others.
+pub fn set_resumable(partials: &[PathBuf]) {
+    *RESUMABLE.lock().unwrap() = partials.iter().cloned().collect();
+}
+
+/// Whether the partial copy at `partial` is one to continue.
+pub fn resumes(partial: &Path) -> bool {
+    RESUMABLE.lock().unwrap().contains(partial)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_our_own_names_match() {
+        for name in [".kosmokopy-probe-4211", "a.txt.kosmokopy-partial", ".x.kosmokopy-partial"] {
+            assert!(is_ours(OsStr::new(name)), "{}", name);
+        }
+        for name in [".kosmokopy-probe-", ".kosmokopy-probe-12x", ".kosmokopy-partial", ".kosmokopy.toml", "a.kosmokopy-partial.bak", "notes.txt"] {
+            assert!(!is_ours(OsStr::new(name)), "{}", name);
+        }
+        assert!(is_ours(OsStr::from_bytes(b"caf\xe9.kosmokopy-partial")));
+        assert_eq!(partial_path(Path::new("/dst/a b.txt")), Path::new("/dst/a b.txt.kosmokopy-partial"));
+    }
+
+    #[test]
+    fn the_top_two_levels_are_searched() {
+        let root = std::env::temp_dir().join(format!("kosmokopy-stale-{}", std::process::id()));
+        let deep = root.join("src/sub");
+        fs::create_dir_all(&deep).unwrap();
+        for path in [
+            root.join(".kosmokopy-probe-77"),
+            root.join("src/a.txt.kosmokopy-partial"),
+            root.join("src/.kosmokopy.toml"),
+            root.join("src/a.txt"),
+            deep.join("b.txt.kosmokopy-partial"),
+        ] {
+            fs::write(path, "x").unwrap();
+        }
+        let found = find_local(&root);
+        assert_eq!(found.partials, [root.join("src/a.txt.kosmokopy-partial")]);
+        assert_eq!(found.probes, [root.join(".kosmokopy-probe-77")]);
+        assert_eq!(found.describe(), "1 partial copy and 1 probe file");
+        assert!(find_local(&root.join("missing")).is_empty());
+
+        let script = find_script(root.as_os_str(), |p| OsString::from(format!("'{}'", p.to_str().unwrap())));
+        let out = std::process::Command::new("sh").arg("-c").arg(script).output().unwrap();
+        let remote = found_remote(&out.stdout);
+        assert_eq!((&remote.partials, &remote.probes), (&found.partials, &found.probes));
+        assert!(!remote.can_resume() && found.can_resume());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn only_cleaning_removes_anything() {
+        let found = Found {
+            partials: vec![PathBuf::from("/d/a.kosmokopy-partial")],
+            probes: vec![PathBuf::from("/d/.kosmokopy-probe-1")],
+            resumable: true,
+        };
+        let mut asked = Vec::new();
+        let decision = Decision::apply(&found, OnStale::Clean, |paths| {
+            asked = paths.iter().map(|p| p.to_path_buf()).collect();
+            vec![(PathBuf::from("/d/.kosmokopy-probe-1"), io::Error::from(io::ErrorKind::PermissionDenied))]
+        });
+        assert_eq!(asked, found.paths().cloned().collect::<Vec<_>>());
+        assert_eq!((decision.removed, decision.failed.len()), (1, 1));
+        assert_eq!(decision.describe(), "1 partial copy and 1 probe file left by an earlier run: cleaned (1 removed)");
+        for choice in [OnStale::Resume, OnStale::Ignore] {
+            let decision = Decision::apply(&found, choice, |_| panic!("nothing is removed"));
+            assert_eq!(decision.removed, 0);
+        }
+        assert_eq!(OnStale::parse("clean"), Some(OnStale::Clean));
+        assert_eq!(OnStale::parse("abort"), None);
+    }
+}
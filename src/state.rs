@@ -0,0 +1,157 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::fs;
+use std::io;
+
+use crate::automethod::MethodChoice;
+use crate::config;
+use crate::paths::{self, Dir};
+use crate::sourceconf::{conflict_name, quote, string, strings};
+use crate::{ConflictMode, TransferMode};
+
+// ── The window as it was last used ─────────────────────────────────────
+//
+// Most people copy to the same place with the same options every time.
+// With `remember_state` on (the default), starting a transfer saves the
+// window's choices to `state.toml` in the config directory, and the next
+// launch puts them back.  The file is a convenience: when it is missing
+// or unreadable nothing is restored, and a line that cannot be read
+// leaves that one choice at its default.  Exclusions are kept in their
+// stored form (`/dir`, `~pattern`, `~/pattern` and so on), exactly as the
+// list holds them.
+
+const FILE_NAME: &str = "state.toml";
+
+/// What the window was last started with; `None` where nothing was saved.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindowState {
+    /// The source as typed: a folder, a file or `host:path`.  A selection
+    /// that has no such text (several files, ticked items) is not kept.
+    pub source: Option<String>,
+    pub destination: Option<String>,
+    pub do_move: Option<bool>,
+    pub transfer_mode: Option<TransferMode>,
+    pub method: Option<MethodChoice>,
+    pub conflict: Option<ConflictMode>,
+    pub strip_spaces: Option<bool>,
+    pub patterns: Option<Vec<String>>,
+}
+
+impl WindowState {
+    pub fn parse(text: &str) -> WindowState {
+        let mut state = WindowState::default();
+        for (key, value) in config::entries(text) {
+            match key {
+                "source" => state.source = string(value),
+                "destination" => state.destination = string(value),
+                "move" => state.do_move = config::flag(value),
+                "mode" => state.transfer_mode = string(value).as_deref().and_then(parse_mode),
+                "method" => state.method = string(value).as_deref().and_then(MethodChoice::parse),
+                "conflict" => state.conflict = string(value).as_deref().and_then(parse_conflict),
+                "strip_spaces" => state.strip_spaces = config::flag(value),
+                "exclusions" => state.patterns = strings(value),
+                _ => {}
+            }
+        }
+        state
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# Kosmokopy: the last transfer started from the window\n");
+        let mut line = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                text.push_str(&format!("{} = {}\n", key, value));
+            }
+        };
+        line("source", self.source.as_deref().map(quote));
+        line("destination", self.destination.as_deref().map(quote));
+        line("move", self.do_move.map(|m| m.to_string()));
+        line("mode", self.transfer_mode.map(|m| quote(mode_name(m))));
+        line("method", self.method.map(|m| quote(m.as_str())));
+        line("conflict", self.conflict.map(|c| quote(conflict_name(c))));
+        line("strip_spaces", self.strip_spaces.map(|s| s.to_string()));
+        line(
+            "exclusions",
+            self.patterns.as_ref().map(|list| format!("[{}]", list.iter().map(|p| quote(p)).collect::<Vec<_>>().join(", "))),
+        );
+        text
+    }
+}
+
+/// `--mode`'s words.
+fn mode_name(mode: TransferMode) -> &'static str {
+    match mode {
+        TransferMode::FoldersAndFiles => "folders",
+        TransferMode::FilesOnly => "files",
+        TransferMode::FilesWithParent => "parent",
+    }
+}
+
+fn parse_mode(word: &str) -> Option<TransferMode> {
+    match word {
+        "folders" => Some(TransferMode::FoldersAndFiles),
+        "files" => Some(TransferMode::FilesOnly),
+        "parent" => Some(TransferMode::FilesWithParent),
+        _ => None,
+    }
+}
+
+fn parse_conflict(word: &str) -> Option<ConflictMode> {
+    match word {
+        "skip" => Some(ConflictMode::Skip),
+        "overwrite" => Some(ConflictMode::Overwrite),
+        "rename" => Some(ConflictMode::Rename),
+        "newer" => Some(ConflictMode::Newer),
+        _ => None,
+    }
+}
+
+/// The saved state, or nothing to restore when there is none.
+pub fn load() -> WindowState {
+    paths::dir(Dir::Config)
+        .and_then(|d| fs::read_to_string(d.join(FILE_NAME)).ok())
+        .map(|text| WindowState::parse(&text))
+        .unwrap_or_default()
+}
+
+pub fn save(state: &WindowState) -> io::Result<()> {
+    fs::write(paths::file(Dir::Config, FILE_NAME)?, state.to_text())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_with_every_pattern_form() {
+        let state = WindowState {
+            source: Some("/home/ann/Photos \"2024\"".to_string()),
+            destination: Some("nas:/backup/photos".to_string()),
+            do_move: Some(true),
+            transfer_mode: Some(TransferMode::FilesWithParent),
+            method: Some(MethodChoice::Rsync),
+            conflict: Some(ConflictMode::Overwrite),
+            strip_spaces: Some(false),
+            patterns: Some(
+                ["/target", "notes.txt", "~/cache*", "~*.log", "+*.jpg", "^docs/generated", "back\\slash"]
+                    .map(String::from)
+                    .to_vec(),
+            ),
+        };
+        assert_eq!(WindowState::parse(&state.to_text()), state);
+        assert_eq!(WindowState::parse(&WindowState::default().to_text()), WindowState::default());
+        let empty = WindowState { patterns: Some(Vec::new()), ..WindowState::default() };
+        assert_eq!(WindowState::parse(&empty.to_text()), empty);
+    }
+
+    #[test]
+    fn unreadable_lines_restore_nothing() {
+        let state = WindowState::parse(
+            "source = /no/quotes\ndestination = \"nas:/x\nmove = yes\nmode = \"sideways\"\nmethod = \"ftp\"\nconflict = \"merge\"\nexclusions = [\"/target\"\n\u{0}garbage\n",
+        );
+        assert_eq!(state, WindowState::default());
+        assert_eq!(WindowState::parse("move = false\nconflict = \"newer\"\n").conflict, Some(ConflictMode::Newer));
+    }
+}
@@ -0,0 +1,181 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::collections::{HashMap, HashSet};
+
+// ── Results per top-level folder ───────────────────────────────────────
+//
+// A run's files are grouped by the top-level folder they came from, so a
+// backup of `Photos/`, `Documents/` and `Projects/` can report how each
+// fared.  Groups follow the source tree even when the destination is
+// flattened.  Workers record copies as they happen; skips and errors are
+// matched to files at the end the same way the failed-files list is, by
+// the source path each message starts with.
+
+/// The group of files directly in the source folder.
+pub const ROOT_GROUP: &str = "(root)";
+
+/// The group of `path`: its first folder below `root`, or `(root)` for
+/// files directly in it.  Without a root (individually picked files) a
+/// file is grouped by its parent folder's name.
+pub fn group_name(root: Option<&str>, path: &str) -> String {
+    if let Some(root) = root {
+        let root = root.trim_end_matches('/');
+        if let Some(rel) = path.strip_prefix(root).and_then(|r| r.strip_prefix('/')) {
+            return match rel.split_once('/') {
+                Some((first, _)) if !first.is_empty() => first.to_string(),
+                _ => ROOT_GROUP.to_string(),
+            };
+        }
+    }
+    path.rsplit_once('/')
+        .and_then(|(parent, _)| parent.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(ROOT_GROUP)
+        .to_string()
+}
+
+/// How one group fared.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GroupStats {
+    pub name: String,
+    pub files: usize,
+    pub copied: usize,
+    pub skipped: usize,
+    /// Files with at least one error (a file copied but not deleted from
+    /// the source counts as both copied and an error).
+    pub errors: usize,
+    /// Bytes of the copied files.
+    pub bytes: u64,
+}
+
+/// Per-group bookkeeping for one run.
+pub struct RunStats {
+    /// Each file's source path (as messages start with) → group index, size.
+    files: HashMap<String, (usize, u64)>,
+    groups: Vec<GroupStats>,
+}
+
+impl RunStats {
+    /// `files` are the run's source paths with their sizes, if known.
+    pub fn new(root: Option<&str>, files: impl IntoIterator<Item = (String, Option<u64>)>) -> RunStats {
+        let mut stats = RunStats { files: HashMap::new(), groups: Vec::new() };
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for (path, size) in files {
+            let name = group_name(root, &path);
+            let g = *index.entry(name.clone()).or_insert_with(|| {
+                stats.groups.push(GroupStats { name, ..GroupStats::default() });
+                stats.groups.len() - 1
+            });
+            stats.groups[g].files += 1;
+            stats.files.insert(path, (g, size.unwrap_or(0)));
+        }
+        stats
+    }
+
+    /// Record that the file at `path` was copied (or moved).
+    pub fn copied(&mut self, path: &str) {
+        if let Some(&(g, bytes)) = self.files.get(path) {
+            self.groups[g].copied += 1;
+            self.groups[g].bytes += bytes;
+        }
+    }
+
+    /// The groups, `(root)` first and the rest by name, with skips and
+    /// errors counted from the run's messages.
+    pub fn finish(mut self, skipped: &[String], errors: &[String]) -> Vec<GroupStats> {
+        for (messages, errors) in [(skipped, false), (errors, true)] {
+            let mut seen: HashSet<&str> = HashSet::new();
+            for m in messages {
+                let Some(path) = m.match_indices(": ").map(|(i, _)| &m[..i]).find(|p| self.files.contains_key(*p))
+                else {
+                    continue;
+                };
+                if seen.insert(path) {
+                    let g = self.files[path].0;
+                    if errors {
+                        self.groups[g].errors += 1;
+                    } else {
+                        self.groups[g].skipped += 1;
+                    }
+                }
+            }
+        }
+        self.groups.sort_by(|a, b| (a.name != ROOT_GROUP, &a.name).cmp(&(b.name != ROOT_GROUP, &b.name)));
+        self.groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sized(paths: &[&str]) -> Vec<(String, Option<u64>)> {
+        paths.iter().map(|p| (p.to_string(), Some(10))).collect()
+    }
+
+    #[test]
+    fn groups_by_first_folder_below_the_root() {
+        let root = Some("/home/ann/backup");
+        assert_eq!(group_name(root, "/home/ann/backup/Photos/2024/a.jpg"), "Photos");
+        assert_eq!(group_name(root, "/home/ann/backup/Documents/cv.pdf"), "Documents");
+        assert_eq!(group_name(root, "/home/ann/backup/notes.txt"), ROOT_GROUP);
+        assert_eq!(group_name(Some("/home/ann/backup/"), "/home/ann/backup/Photos/a.jpg"), "Photos");
+        assert_eq!(group_name(Some("/"), "/etc/hosts"), "etc");
+    }
+
+    #[test]
+    fn picked_files_group_by_parent() {
+        assert_eq!(group_name(None, "/home/ann/Music/song.flac"), "Music");
+        assert_eq!(group_name(Some("/elsewhere"), "/home/ann/Music/song.flac"), "Music");
+        assert_eq!(group_name(None, "/song.flac"), ROOT_GROUP);
+        assert_eq!(group_name(None, "song.flac"), ROOT_GROUP);
+    }
+
+    #[test]
+    fn counts_copies_skips_and_errors_per_group() {
+        let files = sized(&[
+            "/src/Photos/a.jpg",
+            "/src/Photos/b.jpg",
+            "/src/Photos/old/c.jpg",
+            "/src/Documents/cv.pdf",
+            "/src/readme.txt",
+        ]);
+        let mut stats = RunStats::new(Some("/src"), files);
+        stats.copied("/src/Photos/a.jpg");
+        stats.copied("/src/readme.txt");
+        stats.copied("/src/Documents/cv.pdf");
+        stats.copied("/not/in/the/run");
+        let skipped = vec!["/src/Photos/b.jpg: identical at destination".to_string()];
+        let errors = vec![
+            "/src/Photos/old/c.jpg: Permission denied".to_string(),
+            "/src/Photos/old/c.jpg: verification failed".to_string(),
+            "/src/Documents/cv.pdf: transferred and verified but failed to delete source: busy".to_string(),
+            "Source is read-only: 1 file(s) were copied but could not be removed from the source".to_string(),
+        ];
+        let groups = stats.finish(&skipped, &errors);
+        let names: Vec<&str> = groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, [ROOT_GROUP, "Documents", "Photos"]);
+        let photos = &groups[2];
+        assert_eq!((photos.files, photos.copied, photos.skipped, photos.errors, photos.bytes), (3, 1, 1, 1, 10));
+        let docs = &groups[1];
+        assert_eq!((docs.copied, docs.errors), (1, 1));
+        assert_eq!((groups[0].copied, groups[0].bytes), (1, 10));
+    }
+
+    #[test]
+    fn paths_containing_colon_space() {
+        let files = sized(&["/src/a: b/x.txt"]);
+        let stats = RunStats::new(Some("/src"), files);
+        let groups = stats.finish(&[], &["/src/a: b/x.txt: No space left on device".to_string()]);
+        assert_eq!((groups[0].name.as_str(), groups[0].errors), ("a: b", 1));
+    }
+
+    #[test]
+    fn unknown_sizes_count_as_zero_bytes() {
+        let mut stats = RunStats::new(None, vec![("/m/x".to_string(), None)]);
+        stats.copied("/m/x");
+        assert_eq!(stats.finish(&[], &[])[0].bytes, 0);
+    }
+}
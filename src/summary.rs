@@ -0,0 +1,279 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::time::Duration;
+
+use crate::coalesce::group_thousands;
+use crate::json_string;
+use crate::progress::{format_bytes, format_eta, Phase};
+
+// ── Run summaries ──────────────────────────────────────────────────────
+//
+// The account of a finished or cancelled run, as the GUI's status line
+// and result dialog show it and as the CLI's `summary` field reports it.
+// Counts, plurals, sizes and durations are worded here and nowhere else,
+// so every place that reports a run says the same thing.
+
+/// How the run ended.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Ending {
+    #[default]
+    Finished,
+    /// Cancelled, taking effect in this phase.
+    Cancelled(Phase),
+}
+
+impl Ending {
+    fn name(self) -> &'static str {
+        match self {
+            Ending::Finished => "finished",
+            Ending::Cancelled(_) => "cancelled",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunSummary {
+    pub ending: Ending,
+    pub moved: bool,
+    pub copied: usize,
+    /// Bytes transferred, when the files' sizes were known.
+    pub bytes: Option<u64>,
+    pub elapsed: Option<Duration>,
+    pub skipped: usize,
+    pub errors: usize,
+    pub excluded_files: usize,
+    pub excluded_dirs: usize,
+    /// Files under the excluded folders, when they were counted (remote
+    /// sources only).
+    pub in_excluded_dirs: Option<usize>,
+    /// A move from a read-only source ran as a copy.
+    pub move_downgraded: bool,
+    /// Files transferred after verification was skipped.
+    pub unverified: usize,
+    /// Further sentences, without a full stop, e.g. what the rename rules
+    /// did.
+    pub notes: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// `1 file`, `1,204 files`.
+fn count(n: usize, one: &str, many: &str) -> String {
+    format!("{} {}", group_thousands(n), if n == 1 { one } else { many })
+}
+
+impl RunSummary {
+    /// The first sentence: what was transferred, and what was not.
+    /// `Copied 3 files (1.2 MiB) in 0:04, 1 skipped, 2 failed, nothing
+    /// excluded.`  A run cancelled before its first file says where it
+    /// stopped rather than `Copied 0 files`, which reads like a run with
+    /// nothing to do.
+    pub fn headline(&self) -> String {
+        let verb = if self.moved { "Moved" } else { "Copied" };
+        let mut line = format!("{} {}", verb, count(self.copied, "file", "files"));
+        if let Some(bytes) = self.bytes.filter(|_| self.copied > 0) {
+            line.push_str(&format!(" ({})", format_bytes(bytes)));
+        }
+        if let Some(elapsed) = self.elapsed {
+            line.push_str(&format!(" in {}", format_eta(elapsed)));
+        }
+        if let Ending::Cancelled(stage) = self.ending {
+            line = if self.copied == 0 {
+                format!("Cancelled during {} — no files were transferred", stage.name())
+            } else {
+                format!("Cancelled during {}. {} before stopping", stage.name(), line)
+            };
+        }
+        line.push_str(&format!(", {} skipped", group_thousands(self.skipped)));
+        if self.errors > 0 {
+            line.push_str(&format!(", {} failed", group_thousands(self.errors)));
+        }
+        let folders = |dirs| match self.in_excluded_dirs {
+            Some(held) => format!("{} holding {}", count(dirs, "folder", "folders"), count(held, "file", "files")),
+            None => count(dirs, "folder", "folders"),
+        };
+        let excluded = match (self.excluded_files, self.excluded_dirs) {
+            (0, 0) => "nothing excluded".to_string(),
+            (files, 0) => format!("{} excluded", count(files, "file", "files")),
+            (0, dirs) => format!("{} excluded", folders(dirs)),
+            (files, dirs) => format!("{} and {} excluded", count(files, "file", "files"), folders(dirs)),
+        };
+        format!("{}, {}.", line, excluded)
+    }
+
+    /// The sentences after the headline.
+    fn details(&self) -> Vec<String> {
+        let mut sentences = Vec::new();
+        if self.move_downgraded {
+            sentences.push("Source is read-only, so files were copied instead of moved.".to_string());
+        }
+        sentences.extend(self.notes.iter().map(|n| format!("{}.", n)));
+        if self.unverified > 0 {
+            sentences.push(format!(
+                "{} transferred without verification{}.",
+                count(self.unverified, "file", "files"),
+                if self.moved { "; their sources were kept" } else { "" }
+            ));
+        }
+        sentences.extend(self.warnings.iter().map(|w| format!("Warning: {}.", w)));
+        sentences
+    }
+
+    /// The whole summary as plain text.
+    pub fn to_human(&self) -> String {
+        std::iter::once(self.headline()).chain(self.details()).collect::<Vec<_>>().join(" ")
+    }
+
+    /// The summary as Pango markup, with the headline in bold.
+    pub fn to_markup(&self) -> String {
+        std::iter::once(format!("<b>{}</b>", escape_markup(&self.headline())))
+            .chain(self.details().iter().map(|s| escape_markup(s)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The summary as a JSON object: the counts it was made from and its
+    /// `text`, with the `stage` a cancelled run stopped in.
+    pub fn to_json_value(&self) -> String {
+        let stage = match self.ending {
+            Ending::Cancelled(stage) => format!(",\"stage\":\"{}\"", stage.name()),
+            Ending::Finished => String::new(),
+        };
+        format!(
+            "{{\"ending\":\"{}\"{},\"copied\":{},\"bytes\":{},\"elapsed_secs\":{},\"skipped\":{},\"errors\":{},\"excluded_files\":{},\"excluded_dirs\":{}{},\"text\":{}}}",
+            self.ending.name(),
+            stage,
+            self.copied,
+            self.bytes.map_or("null".to_string(), |b| b.to_string()),
+            self.elapsed.map_or("null".to_string(), |d| format!("{:.1}", d.as_secs_f64())),
+            self.skipped,
+            self.errors,
+            self.excluded_files,
+            self.excluded_dirs,
+            self.in_excluded_dirs.map(|n| format!(",\"files_in_excluded_dirs\":{}", n)).unwrap_or_default(),
+            json_string(&self.to_human())
+        )
+    }
+}
+
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn copied(n: usize) -> RunSummary {
+        RunSummary { copied: n, ..RunSummary::default() }
+    }
+
+    #[test]
+    fn nothing_at_all() {
+        assert_eq!(copied(0).to_human(), "Copied 0 files, 0 skipped, nothing excluded.");
+    }
+
+    #[test]
+    fn singular_counts() {
+        let summary = RunSummary { bytes: Some(2048), skipped: 1, errors: 1, excluded_files: 1, excluded_dirs: 1, ..copied(1) };
+        assert_eq!(summary.to_human(), "Copied 1 file (2.0 KiB), 1 skipped, 1 failed, 1 file and 1 folder excluded.");
+    }
+
+    #[test]
+    fn plural_counts_with_size_and_time() {
+        let summary = RunSummary {
+            moved: true,
+            bytes: Some(5 * 1024 * 1024 * 1024),
+            elapsed: Some(Duration::from_secs(3725)),
+            skipped: 12,
+            excluded_dirs: 3,
+            ..copied(1204)
+        };
+        assert_eq!(summary.to_human(), "Moved 1,204 files (5.0 GiB) in 1:02:05, 12 skipped, 3 folders excluded.");
+    }
+
+    #[test]
+    fn cancelled_runs_say_so() {
+        let summary = RunSummary {
+            ending: Ending::Cancelled(Phase::Transferring),
+            moved: true,
+            elapsed: Some(Duration::from_secs(42)),
+            excluded_files: 7,
+            unverified: 2,
+            ..copied(40)
+        };
+        assert_eq!(
+            summary.to_human(),
+            "Cancelled during transferring. Moved 40 files in 0:42 before stopping, 0 skipped, 7 files excluded. \
+             2 files transferred without verification; their sources were kept."
+        );
+    }
+
+    #[test]
+    fn cancelled_before_any_file_names_the_stage() {
+        for (stage, name) in [(Phase::Scanning, "scanning"), (Phase::Preflight, "preflight")] {
+            let summary = RunSummary { ending: Ending::Cancelled(stage), elapsed: Some(Duration::from_secs(3)), ..copied(0) };
+            assert_eq!(
+                summary.to_human(),
+                format!("Cancelled during {} — no files were transferred, 0 skipped, nothing excluded.", name)
+            );
+            assert!(summary.to_json_value().starts_with(&format!("{{\"ending\":\"cancelled\",\"stage\":\"{}\",", name)));
+        }
+        assert!(!copied(0).to_json_value().contains("stage"));
+    }
+
+    #[test]
+    fn remote_folder_exclusions_say_what_they_held() {
+        let summary = RunSummary { excluded_files: 2, excluded_dirs: 3, in_excluded_dirs: Some(14), ..copied(5) };
+        assert_eq!(summary.to_human(), "Copied 5 files, 0 skipped, 2 files and 3 folders holding 14 files excluded.");
+        assert!(summary.to_json_value().contains("\"excluded_dirs\":3,\"files_in_excluded_dirs\":14,"));
+        let summary = RunSummary { excluded_dirs: 1, in_excluded_dirs: Some(1), ..copied(0) };
+        assert_eq!(summary.to_human(), "Copied 0 files, 0 skipped, 1 folder holding 1 file excluded.");
+    }
+
+    #[test]
+    fn error_heavy_runs_keep_every_sentence() {
+        let summary = RunSummary {
+            errors: 2_914_301,
+            skipped: 3,
+            move_downgraded: true,
+            notes: vec!["Renamed 2 file(s)".to_string(), "Created 3 folder(s)".to_string()],
+            warnings: vec!["rsync 2.6.9 on nas is older than 3.0".to_string()],
+            ..copied(9)
+        };
+        assert_eq!(
+            summary.to_human(),
+            "Copied 9 files, 3 skipped, 2,914,301 failed, nothing excluded. \
+             Source is read-only, so files were copied instead of moved. \
+             Renamed 2 file(s). Created 3 folder(s). \
+             Warning: rsync 2.6.9 on nas is older than 3.0."
+        );
+    }
+
+    #[test]
+    fn markup_bolds_the_headline_and_escapes_text() {
+        let summary = RunSummary { warnings: vec!["host <a&b> unreachable".to_string()], ..copied(2) };
+        assert_eq!(
+            summary.to_markup(),
+            "<b>Copied 2 files, 0 skipped, nothing excluded.</b> Warning: host &lt;a&amp;b&gt; unreachable."
+        );
+    }
+
+    #[test]
+    fn json_carries_the_counts_and_text() {
+        let summary = RunSummary {
+            ending: Ending::Cancelled(Phase::Verifying),
+            bytes: Some(10),
+            elapsed: Some(Duration::from_millis(1500)),
+            warnings: vec!["say \"hi\"".to_string()],
+            ..copied(1)
+        };
+        assert_eq!(
+            summary.to_json_value(),
+            "{\"ending\":\"cancelled\",\"stage\":\"verifying\",\"copied\":1,\"bytes\":10,\"elapsed_secs\":1.5,\"skipped\":0,\"errors\":0,\
+             \"excluded_files\":0,\"excluded_dirs\":0,\"text\":\"Cancelled during verifying. Copied 1 file (10 B) in 0:01 before stopping, \
+             0 skipped, nothing excluded. Warning: say \\\"hi\\\".\"}"
+        );
+    }
+}
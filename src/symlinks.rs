@@ -0,0 +1,154 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::cell::RefCell;
+
+// ── Symbolic links in a source folder ──────────────────────────────────
+//
+// A folder scan meets links as well as files.  What becomes of them is
+// the run's policy (`--symlinks`, or the window's Symbolic links choice):
+//
+// - Skip, the default, leaves each one out and lists it among the skipped;
+// - Follow copies what a link points to as a real file, and walks into a
+//   linked folder; a link that points nowhere, or back to a folder above
+//   it, is skipped with that reason.  A move can't follow links;
+// - Preserve recreates the link itself at the destination, pointing where
+//   the original does, even when that is nowhere.
+//
+// The policy applies to links found inside a local source folder.  A file
+// picked by name is taken as picked, and a remote source lists regular
+// files only.  The links a scan leaves out are kept for the thread that
+// scanned, which is the worker that lists them.
+
+/// What a scan does with the links it meets.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Policy {
+    #[default]
+    Skip,
+    Follow,
+    Preserve,
+}
+
+impl Policy {
+    /// `--symlinks`.
+    pub fn parse(word: &str) -> Option<Policy> {
+        match word {
+            "skip" => Some(Policy::Skip),
+            "follow" => Some(Policy::Follow),
+            "preserve" => Some(Policy::Preserve),
+            _ => None,
+        }
+    }
+}
+
+/// Why a link was left out under Skip.
+pub const SKIPPED: &str = "symbolic link, skipped";
+/// Why a link was left out under Follow.
+pub const BROKEN: &str = "broken symbolic link, nothing to follow";
+/// Why a link to a folder above it was left out under Follow.
+pub const LOOP: &str = "symbolic link loops back to a folder above it";
+
+thread_local! {
+    /// Links this thread's last scan left out, as `path: reason`.
+    static LEFT_OUT: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Forget what an earlier scan on this thread left out; a scan starts here.
+pub fn start_scan() {
+    LEFT_OUT.with(|left_out| left_out.borrow_mut().clear());
+}
+
+/// Note that the scan left out the link at `path`, and why.
+pub fn note_left_out(path: &Path, reason: &str) {
+    LEFT_OUT.with(|left_out| left_out.borrow_mut().push(format!("{}: {}", path.display(), reason)));
+}
+
+/// The links this thread's last scan left out, for the run's skipped list.
+pub fn take() -> Vec<String> {
+    LEFT_OUT.with(|left_out| left_out.take())
+}
+
+/// Why a run can't use `policy`: following links on a move would delete
+/// what they point to, perhaps outside the source, and a file reached by
+/// two paths would be moved by the first and missing for the second.
+pub fn move_error(policy: Policy, do_move: bool) -> Option<&'static str> {
+    (do_move && policy == Policy::Follow)
+        .then_some("Following symbolic links only copies; to move, skip or preserve the links instead")
+}
+
+/// Whether `path` is itself a link, without following it.
+pub fn is_link(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
+
+/// Whether `path` is a link a run with `policy` recreates rather than
+/// copies.
+pub fn preserved(policy: Policy, path: &Path) -> bool {
+    policy == Policy::Preserve && is_link(path)
+}
+
+/// What rsync is told about links: `-a` copies them as links, which
+/// suits Preserve (and Skip, whose scans leave none); Follow wants what
+/// they point to.
+pub fn rsync_args(policy: Policy) -> &'static [&'static str] {
+    match policy {
+        Policy::Follow => &["--copy-links"],
+        Policy::Skip | Policy::Preserve => &[],
+    }
+}
+
+/// Make `dest` a link pointing where `link` does, replacing a file or
+/// link already there.  The planner has decided that it may go.
+pub fn recreate(link: &Path, dest: &Path) -> io::Result<()> {
+    let target = fs::read_link(link)?;
+    if fs::symlink_metadata(dest).is_ok_and(|m| !m.is_dir()) {
+        fs::remove_file(dest)?;
+    }
+    std::os::unix::fs::symlink(target, dest)
+}
+
+/// Whether `dest` is a link pointing where `link` does.
+pub fn same_target(link: &Path, dest: &Path) -> io::Result<bool> {
+    let target = fs::read_link(link)?;
+    Ok(is_link(dest) && fs::read_link(dest)? == target)
+}
+
+/// The shell command making `dest` a link to `target` on another host,
+/// replacing a file or link there.
+pub fn remote_script(target: &Path, dest: &Path, quote: impl Fn(&OsStr) -> OsString) -> OsString {
+    let dest = quote(dest.as_os_str());
+    let mut script = OsString::from("rm -f -- ");
+    script.push(&dest);
+    script.push(" && ln -s -- ");
+    script.push(quote(target.as_os_str()));
+    script.push(" ");
+    script.push(&dest);
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_are_recreated_even_when_broken() {
+        let dir = std::env::temp_dir().join(format!("kosmokopy_symlinks_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let link = dir.join("dangling");
+        std::os::unix::fs::symlink("../nowhere", &link).unwrap();
+        let dest = dir.join("copy");
+        fs::write(&dest, "in the way").unwrap();
+
+        recreate(&link, &dest).unwrap();
+        assert_eq!(fs::read_link(&dest).unwrap(), Path::new("../nowhere"));
+        assert!(same_target(&link, &dest).unwrap());
+        assert!(!same_target(&link, &dir.join("absent")).unwrap());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
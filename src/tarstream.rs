@@ -0,0 +1,222 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::throttle::Throttle;
+
+// ── Tar stream ─────────────────────────────────────────────────────────
+//
+// The tar method uploads a plan as one archive piped into `tar -xf -` on
+// the host, instead of one scp per file.  The archive is written here
+// rather than by a local `tar`, so each entry carries the exact path the
+// plan chose for it (renamed, flattened or with its parent folders) and
+// the local tar's flavour doesn't matter.  Entries are POSIX ustar, with
+// a pax header for a path or size ustar cannot hold; GNU tar, bsdtar and
+// busybox all read both.  Names are written as their bytes, UTF-8 or not.
+
+const BLOCK: usize = 512;
+/// Largest size a ustar header's 11 octal digits can hold.
+const USTAR_MAX_SIZE: u64 = 0o77_777_777_777;
+/// The name GNU tar gives its own pax headers.
+const PAX_NAME: &str = "././@PaxHeader";
+
+/// Write the open `file` to `out` as an entry named `name` (a relative
+/// path).  Returns the bytes read from the file.  A file that shrank or
+/// could not be read to the end is padded with zeros to the size its
+/// entry announced, and one that grew is cut there, so the archive stays
+/// whole and the copy fails its verification instead; an error is one
+/// writing to `out`.  The file is read within `throttle`'s limit.
+pub fn append_file(out: &mut impl Write, name: &Path, mut file: fs::File, throttle: Throttle) -> io::Result<u64> {
+    let meta = file.metadata()?;
+    let size = meta.len();
+    let mtime = meta.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map_or(0, |d| d.as_secs());
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+        meta.permissions().mode() & 0o7777
+    };
+    out.write_all(&header(name.as_os_str().as_bytes(), None, size, mode, mtime))?;
+
+    let mut buf = vec![0u8; throttle.chunk(1024 * 1024)];
+    let mut sent = 0u64;
+    let mut limited = (&mut file).take(size);
+    loop {
+        let n = match limited.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        out.write_all(&buf[..n])?;
+        throttle.spend(n);
+        sent += n as u64;
+    }
+    let read = sent;
+    while sent < size {
+        let n = (size - sent).min(buf.len() as u64) as usize;
+        out.write_all(&vec![0u8; n])?;
+        sent += n as u64;
+    }
+    out.write_all(&vec![0u8; padding(size)])?;
+    Ok(read)
+}
+
+/// Write a symbolic link to `target` as an entry named `name`.  Both go
+/// in as their bytes, whether or not they are UTF-8.
+pub fn append_link(out: &mut impl Write, name: &Path, target: &Path) -> io::Result<()> {
+    out.write_all(&header(name.as_os_str().as_bytes(), Some(target.as_os_str().as_bytes()), 0, 0o777, 0))
+}
+
+/// The two empty blocks that end an archive.
+pub fn finish(out: &mut impl Write) -> io::Result<()> {
+    out.write_all(&[0u8; 2 * BLOCK])?;
+    out.flush()
+}
+
+/// Zero bytes after `size` bytes of data, up to the next block.
+fn padding(size: u64) -> usize {
+    (BLOCK - (size % BLOCK as u64) as usize) % BLOCK
+}
+
+/// The header blocks for a regular file, or a link to `link`: a pax
+/// header first when the name, link or size does not fit ustar's fields.
+fn header(name: &[u8], link: Option<&[u8]>, size: u64, mode: u32, mtime: u64) -> Vec<u8> {
+    let split = split_name(name);
+    let mut records = Vec::new();
+    if split.is_none() {
+        records.extend(pax_record("path", name));
+    }
+    if let Some(link) = link.filter(|l| l.len() > 100) {
+        records.extend(pax_record("linkpath", link));
+    }
+    if size > USTAR_MAX_SIZE {
+        records.extend(pax_record("size", size.to_string().as_bytes()));
+    }
+    let mut out = Vec::new();
+    if !records.is_empty() {
+        out.extend(ustar_block((b"", PAX_NAME.as_bytes()), b"", records.len() as u64, 0o644, mtime, b'x'));
+        out.extend(&records);
+        out.extend(vec![0u8; padding(records.len() as u64)]);
+    }
+    let (prefix, last) = split.unwrap_or((b"", short_name(name)));
+    let (link, kind) = match link {
+        Some(link) => (short_name(link), b'2'),
+        None => (&b""[..], b'0'),
+    };
+    out.extend(ustar_block((prefix, last), link, size.min(USTAR_MAX_SIZE), mode, mtime, kind));
+    out
+}
+
+/// `name` as ustar's 155-byte prefix and 100-byte name, split at a slash,
+/// or `None` when it cannot be.
+fn split_name(name: &[u8]) -> Option<(&[u8], &[u8])> {
+    if name.len() <= 100 {
+        return Some((b"", name));
+    }
+    name.iter()
+        .enumerate()
+        .filter(|&(_, &b)| b == b'/')
+        .map(|(i, _)| (&name[..i], &name[i + 1..]))
+        .find(|(prefix, last)| prefix.len() <= 155 && !last.is_empty() && last.len() <= 100)
+}
+
+/// The end of `name` that fits in 100 bytes, for readers without pax,
+/// not starting inside a UTF-8 character.
+fn short_name(name: &[u8]) -> &[u8] {
+    let mut start = name.len().saturating_sub(100);
+    while name.get(start).is_some_and(|&b| b & 0xc0 == 0x80) {
+        start += 1;
+    }
+    &name[start..]
+}
+
+/// One pax record, `<length> <key>=<value>\n`, the length counting itself.
+fn pax_record(key: &str, value: &[u8]) -> Vec<u8> {
+    let body = [format!(" {}=", key).as_bytes(), value, b"\n"].concat();
+    let mut length = body.len() + 1;
+    while length.to_string().len() + body.len() != length {
+        length = length.to_string().len() + body.len();
+    }
+    [length.to_string().as_bytes(), &body].concat()
+}
+
+fn ustar_block((prefix, name): (&[u8], &[u8]), link: &[u8], size: u64, mode: u32, mtime: u64, kind: u8) -> [u8; BLOCK] {
+    let mut block = [0u8; BLOCK];
+    let mut put = |at: usize, bytes: &[u8]| block[at..at + bytes.len()].copy_from_slice(bytes);
+    put(0, name);
+    put(100, format!("{:07o}\0", mode).as_bytes());
+    put(108, b"0000000\0");
+    put(116, b"0000000\0");
+    put(124, format!("{:011o}\0", size).as_bytes());
+    put(136, format!("{:011o}\0", mtime.min(USTAR_MAX_SIZE)).as_bytes());
+    put(148, b"        ");
+    put(156, &[kind]);
+    put(157, link);
+    put(257, b"ustar\0");
+    put(263, b"00");
+    put(345, prefix);
+    let sum: u32 = block.iter().map(|&b| b as u32).sum();
+    block[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_hold_long_paths_and_count_their_own_checksum() {
+        let block = ustar_block((b"", b"a.txt"), b"", 5, 0o644, 1_700_000_000, b'0');
+        assert_eq!(&block[0..6], b"a.txt\0");
+        assert_eq!(&block[124..136], b"00000000005\0");
+        let stored = u32::from_str_radix(std::str::from_utf8(&block[148..154]).unwrap(), 8).unwrap();
+        let mut blank = block;
+        blank[148..156].copy_from_slice(b"        ");
+        assert_eq!(stored, blank.iter().map(|&b| b as u32).sum::<u32>());
+
+        let deep = format!("{}/{}", "d".repeat(120), "f".repeat(90)).into_bytes();
+        assert_eq!(split_name(&deep), Some((&deep[..120], &deep[121..])));
+        assert_eq!(header(&deep, None, 0, 0o644, 0).len(), BLOCK);
+        let long = "n".repeat(300).into_bytes();
+        assert_eq!(split_name(&long), None);
+        let with_pax = header(&long, None, 0, 0o644, 0);
+        assert_eq!(with_pax.len(), 3 * BLOCK);
+        assert_eq!(with_pax[156], b'x');
+        assert!(with_pax[BLOCK..2 * BLOCK].starts_with(&pax_record("path", &long)));
+
+        // A name that isn't UTF-8 goes in as its bytes.
+        let block = ustar_block((b"", b"caf\xe9.txt"), b"", 0, 0o644, 0, b'0');
+        assert_eq!(&block[0..9], b"caf\xe9.txt\0");
+    }
+
+    #[test]
+    fn link_targets_keep_their_bytes() {
+        use std::ffi::OsStr;
+
+        let mut out = Vec::new();
+        append_link(&mut out, Path::new("link"), Path::new(OsStr::from_bytes(b"caf\xe9.txt"))).unwrap();
+        assert_eq!(out.len(), BLOCK);
+        assert_eq!(out[156], b'2');
+        assert_eq!(&out[157..166], b"caf\xe9.txt\0");
+
+        // A long one goes in a pax record, still as its bytes.
+        let long = [&b"d/"[..], &[0xff; 120]].concat();
+        let mut out = Vec::new();
+        append_link(&mut out, Path::new("link"), Path::new(OsStr::from_bytes(&long))).unwrap();
+        assert_eq!(out.len(), 3 * BLOCK);
+        assert!(out[BLOCK..2 * BLOCK].starts_with(&pax_record("linkpath", &long)));
+    }
+
+    #[test]
+    fn pax_records_count_their_own_length() {
+        assert_eq!(pax_record("path", b"a"), b"9 path=a\n");
+        let record = pax_record("path", "x".repeat(95).as_bytes());
+        assert_eq!(record.len(), 105);
+        assert!(record.starts_with(b"105 "));
+    }
+}
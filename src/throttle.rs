@@ -0,0 +1,166 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ── Bandwidth limit and compression ────────────────────────────────────
+//
+// On a metered or shared link a run shouldn't take all of it.  With
+// `--bwlimit N` (the window's Bandwidth limit) transfers keep to about N
+// KB/s (units of 1024 bytes, as rsync counts them):
+//
+// - every rsync call is given `--bwlimit=N`;
+// - every scp call is given `-l`, which counts in Kbit/s, and only keeps
+//   to it itself, so uploads sent side by side (see `parallel`) share the
+//   limit out between them;
+// - the standard method's own copy loops, local copies and the tar
+//   stream, wait between chunks so that the files in flight together
+//   keep to the limit.
+//
+// A name scp can't carry, streamed through `cat` over ssh, isn't limited;
+// nor is a move on one host, which doesn't cross the link.  `--help` and
+// the window's tooltip say so.
+//
+// rsync compresses with `-z` for transfers to or from a host and not on
+// this machine, where it only costs CPU.  `--compress always` or `never`
+// (the window's Compress (-z)) says otherwise.  Both are options of the
+// run (see `options`); only the count of what the copy loops have sent is
+// shared between them.
+
+/// Whether rsync compresses what it sends.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Compress {
+    /// For transfers to or from a host only.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl Compress {
+    /// `--compress`.
+    pub fn parse(word: &str) -> Option<Compress> {
+        match word {
+            "auto" => Some(Compress::Auto),
+            "always" => Some(Compress::Always),
+            "never" => Some(Compress::Never),
+            _ => None,
+        }
+    }
+}
+
+/// The highest limit `--bwlimit` takes, in KB/s.
+pub const MAX_LIMIT: u64 = 10_000_000;
+
+/// A run's limit and compression.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Throttle {
+    /// The limit in KB/s; 0 for none.
+    pub limit: u64,
+    pub compress: Compress,
+}
+
+/// What the copy loops have sent since the limit last held them back.
+static BUCKET: Mutex<Option<Bucket>> = Mutex::new(None);
+
+impl Throttle {
+    /// The options for an rsync call, `remote` when one end is on a host.
+    pub fn rsync_args(self, remote: bool) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.compress == Compress::Always || (self.compress == Compress::Auto && remote) {
+            args.push("-z".to_string());
+        }
+        if self.limit > 0 {
+            args.push(format!("--bwlimit={}", self.limit));
+        }
+        args
+    }
+
+    /// The options for an scp call, one of `lanes` running side by side
+    /// (1 for a call on its own).
+    pub fn scp_args(self, lanes: usize) -> Vec<String> {
+        match self.limit {
+            0 => Vec::new(),
+            kb => vec!["-l".to_string(), (kb * 8 / lanes.max(1) as u64).max(1).to_string()],
+        }
+    }
+
+    /// How much a copy loop reads at a time: `most`, or under a limit a
+    /// quarter of a second's worth, so the waits stay short.
+    pub fn chunk(self, most: usize) -> usize {
+        match self.limit {
+            0 => most,
+            kb => usize::try_from(kb * 256).unwrap_or(most).clamp(4096, most),
+        }
+    }
+
+    /// Count `bytes` a copy loop has just sent, and wait as long as the
+    /// limit asks.
+    pub fn spend(self, bytes: usize) {
+        if self.limit == 0 {
+            return;
+        }
+        let now = Instant::now();
+        let wait = BUCKET
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_or_insert(Bucket { start: now, sent: 0 })
+            .take(bytes as u64, self.limit, now);
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Bytes sent since `start`, all loops together.
+#[derive(Debug)]
+struct Bucket {
+    start: Instant,
+    sent: u64,
+}
+
+impl Bucket {
+    /// Count `bytes` sent at `now`, returning how long to wait to keep to
+    /// `limit` KB/s.  Time nothing was sent in isn't made up for with a
+    /// burst: after a second idle, the count starts again.
+    fn take(&mut self, bytes: u64, limit: u64, now: Instant) -> Duration {
+        let due = |sent: u64| Duration::from_secs_f64(sent as f64 / (limit * 1024) as f64);
+        if now.saturating_duration_since(self.start) > due(self.sent) + Duration::from_secs(1) {
+            *self = Bucket { start: now, sent: 0 };
+        }
+        self.sent += bytes;
+        (self.start + due(self.sent)).saturating_duration_since(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_limit_reaches_rsync_scp_and_the_copy_loops() {
+        let throttle = |limit, compress| Throttle { limit, compress };
+        assert_eq!(throttle(0, Compress::Auto).rsync_args(true), ["-z"]);
+        assert!(throttle(0, Compress::Auto).rsync_args(false).is_empty());
+        assert_eq!(throttle(500, Compress::Always).rsync_args(false), ["-z", "--bwlimit=500"]);
+        assert_eq!(throttle(500, Compress::Never).rsync_args(true), ["--bwlimit=500"]);
+        assert_eq!(Compress::parse("sometimes"), None);
+        assert_eq!(throttle(500, Compress::Auto).scp_args(1), ["-l", "4000"]);
+        // Four files in flight share it.
+        assert_eq!(throttle(500, Compress::Auto).scp_args(4), ["-l", "1000"]);
+        assert_eq!(throttle(1, Compress::Auto).scp_args(32), ["-l", "1"]);
+        assert!(throttle(0, Compress::Auto).scp_args(4).is_empty());
+
+        // 100 KB/s: 200 KiB at once is two seconds' worth.
+        let start = Instant::now();
+        let mut bucket = Bucket { start, sent: 0 };
+        assert_eq!(bucket.take(200 * 1024, 100, start), Duration::from_secs(2));
+        assert_eq!(bucket.take(100 * 1024, 100, start + Duration::from_secs(2)), Duration::from_secs(1));
+        // Idle for a while: no burst to catch up.
+        let later = start + Duration::from_secs(60);
+        assert_eq!(bucket.take(50 * 1024, 100, later), Duration::from_millis(500));
+    }
+}
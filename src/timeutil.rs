@@ -0,0 +1,806 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// ── Times and dates ────────────────────────────────────────────────────
+//
+// Everything that reads a clock, compares modification times or turns a
+// time into a date goes through here, so the edges are handled once:
+//
+// - A `Timestamp` keeps whole nanoseconds, before 1970 as well as after,
+//   and comparing two never panics the way `SystemTime::duration_since`
+//   does when the clock has gone backwards.
+// - Modification times from different filesystems and hosts differ by
+//   their resolution (2 s on FAT, whole seconds from `scp -p`), so they
+//   are compared with a tolerance, or truncated to the coarser resolution.
+// - Dates and times are shown in UTC unless `--timestamps local` asks for
+//   the local time zone.  The zone is read from `TZ` or /etc/localtime
+//   (the TZif transitions, then the POSIX rule at the end of the file), so
+//   a time in the hour a DST change skips or repeats gets the right offset.
+// - Leap seconds don't exist in Unix time: a `:60` second is read as `:59`.
+
+/// A point in time: whole seconds since 1970-01-01 UTC (negative before
+/// it) and the nanoseconds after them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub secs: i64,
+    pub nanos: u32,
+}
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+impl Timestamp {
+    /// `time`, including times before 1970.
+    pub fn of(time: SystemTime) -> Timestamp {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => Timestamp { secs: after.as_secs() as i64, nanos: after.subsec_nanos() },
+            Err(before) => Timestamp::from_nanos(-(before.duration().as_nanos() as i128)),
+        }
+    }
+
+    pub fn now() -> Timestamp {
+        Timestamp::of(SystemTime::now())
+    }
+
+    pub fn from_nanos(nanos: i128) -> Timestamp {
+        Timestamp {
+            secs: nanos.div_euclid(NANOS_PER_SEC) as i64,
+            nanos: nanos.rem_euclid(NANOS_PER_SEC) as u32,
+        }
+    }
+
+    /// Nanoseconds since the epoch.
+    pub fn as_nanos(self) -> i128 {
+        self.secs as i128 * NANOS_PER_SEC + self.nanos as i128
+    }
+
+    pub fn as_secs_f64(self) -> f64 {
+        self.secs as f64 + self.nanos as f64 / 1e9
+    }
+
+    /// Parse seconds since the epoch as `date +%s` and find's `%T@` print
+    /// them: `1700000000`, `1700000000.1234567890`, `-12.5`.  Digits past
+    /// the ninth after the point are dropped.
+    pub fn parse_epoch(text: &str) -> Option<Timestamp> {
+        let text = text.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, "0"));
+        if whole.is_empty() || fraction.is_empty() || !(whole.bytes().chain(fraction.bytes())).all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let nanos: i128 = format!("{:0<9}", &fraction[..fraction.len().min(9)]).parse().ok()?;
+        let total = whole.parse::<i64>().ok()? as i128 * NANOS_PER_SEC + nanos;
+        Some(Timestamp::from_nanos(if negative { -total } else { total }))
+    }
+
+    /// How this time compares with `other` when times at most `tolerance`
+    /// apart count as the same.
+    pub fn compare(self, other: Timestamp, tolerance: Duration) -> Ordering {
+        let diff = self.as_nanos() - other.as_nanos();
+        if diff.unsigned_abs() <= tolerance.as_nanos() {
+            Ordering::Equal
+        } else {
+            diff.cmp(&0)
+        }
+    }
+
+    /// Later than `other` by more than `tolerance`.
+    pub fn is_newer(self, other: Timestamp, tolerance: Duration) -> bool {
+        self.compare(other, tolerance) == Ordering::Greater
+    }
+}
+
+// Not called outside the tests yet: the age filters truncate and parse
+// with these.
+#[allow(dead_code)]
+impl Timestamp {
+    /// The time rounded down to a multiple of `resolution`, as a
+    /// filesystem that keeps only that much would store it.
+    pub fn truncate(self, resolution: Duration) -> Timestamp {
+        let step = resolution.as_nanos() as i128;
+        if step == 0 {
+            return self;
+        }
+        let nanos = self.as_nanos();
+        Timestamp::from_nanos(nanos - nanos.rem_euclid(step))
+    }
+
+    /// Parse an RFC 3339 time: `2026-10-17T18:42:05Z`, with an optional
+    /// fraction of a second and any offset (`+02:00`).
+    pub fn parse_rfc3339(text: &str) -> Option<Timestamp> {
+        let text = text.trim();
+        if text.len() < 20 || !text.is_char_boundary(10) || !matches!(text.as_bytes()[10], b'T' | b't' | b' ') {
+            return None;
+        }
+        let date = Date::parse(&text[..10])?;
+        let time = &text[11..];
+        let (clock, zone) = match time.find(['Z', 'z', '+', '-']) {
+            Some(at) => time.split_at(at),
+            None => return None,
+        };
+        let offset = match zone {
+            "Z" | "z" => 0,
+            _ => {
+                let (hours, minutes) = zone[1..].split_once(':')?;
+                let secs = two_digits(hours)? as i64 * 3600 + two_digits(minutes)? as i64 * 60;
+                if zone.starts_with('-') { -secs } else { secs }
+            }
+        };
+        let (clock, fraction) = match clock.split_once('.') {
+            Some((clock, fraction)) => (clock, Some(fraction)),
+            None => (clock, None),
+        };
+        let mut parts = clock.split(':');
+        let (hour, minute, second) = (two_digits(parts.next()?)?, two_digits(parts.next()?)?, two_digits(parts.next()?)?);
+        if parts.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        let nanos = match fraction {
+            Some(f) if !f.is_empty() && f.bytes().all(|c| c.is_ascii_digit()) => {
+                format!("{:0<9}", &f[..f.len().min(9)]).parse::<i128>().ok()?
+            }
+            Some(_) => return None,
+            None => 0,
+        };
+        let secs = date.days() * 86_400 + (hour * 3600 + minute * 60 + second.min(59)) as i64 - offset;
+        Some(Timestamp::from_nanos(secs as i128 * NANOS_PER_SEC + nanos))
+    }
+}
+
+/// Seconds since the epoch, as `%T@` prints them: `1700000000.5`, or
+/// without a point when there is no fraction.  Parses back unchanged.
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nanos = self.as_nanos();
+        let sign = if nanos < 0 { "-" } else { "" };
+        let (whole, fraction) = (nanos.unsigned_abs() / NANOS_PER_SEC as u128, nanos.unsigned_abs() % NANOS_PER_SEC as u128);
+        if fraction == 0 {
+            write!(f, "{}{}", sign, whole)
+        } else {
+            write!(f, "{}{}.{}", sign, whole, format!("{:09}", fraction).trim_end_matches('0'))
+        }
+    }
+}
+
+fn two_digits(text: &str) -> Option<u32> {
+    (text.len() == 2 && text.bytes().all(|c| c.is_ascii_digit())).then(|| text.parse().ok())?
+}
+
+/// Parse a duration such as `90s`, `15m`, `2h`, `7d`, `1w` or `1d12h`.  A
+/// bare number counts in `bare` units (e.g. seconds or hours, whatever the
+/// option always took), and may have a fraction.
+pub fn parse_duration(text: &str, bare: Duration) -> Option<Duration> {
+    let text = text.trim();
+    if let Ok(number) = text.parse::<f64>() {
+        return (number >= 0.0).then(|| Duration::try_from_secs_f64(bare.as_secs_f64() * number).ok())?;
+    }
+    let mut total = Duration::ZERO;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let count: u64 = rest[..digits].parse().ok()?;
+        let unit = match rest[digits..].chars().next()? {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86_400,
+            'w' => 604_800,
+            _ => return None,
+        };
+        total = total.checked_add(Duration::from_secs(count.checked_mul(unit)?))?;
+        rest = &rest[digits + 1..];
+    }
+    (!text.is_empty()).then_some(total)
+}
+
+// ── Calendar dates ─────────────────────────────────────────────────────
+
+/// A calendar date, in the zone `--timestamps` chose.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Today's date.
+    pub fn today() -> Date {
+        Date::of(SystemTime::now())
+    }
+
+    /// The date of `time`.
+    pub fn of(time: SystemTime) -> Date {
+        civil(Timestamp::of(time)).date
+    }
+
+    /// The date `days` after 1970-01-01 (Howard Hinnant's civil-from-days).
+    fn from_days(days: i64) -> Date {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as u32;
+        Date { year, month, day }
+    }
+
+    /// Days from 1970-01-01 to this date (days-from-civil, the inverse).
+    fn days(self) -> i64 {
+        let year = self.year as i64 - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let mp = (self.month as i64 + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    fn is_leap_year(year: u32) -> bool {
+        year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+    }
+
+    fn days_in_month(year: u32, month: u32) -> u32 {
+        match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if Date::is_leap_year(year) => 29,
+            2 => 28,
+            _ => 0,
+        }
+    }
+
+    /// Parse exactly `YYYY-MM-DD`, rejecting dates that don't exist.
+    pub fn parse(s: &str) -> Option<Date> {
+        let b = s.as_bytes();
+        if b.len() != 10 || b[4] != b'-' || b[7] != b'-' {
+            return None;
+        }
+        let num = |r: std::ops::Range<usize>| -> Option<u32> {
+            let part = &s[r];
+            part.bytes().all(|c| c.is_ascii_digit()).then(|| part.parse().ok())?
+        };
+        let date = Date { year: num(0..4)?, month: num(5..7)?, day: num(8..10)? };
+        (1..=Date::days_in_month(date.year, date.month)).contains(&date.day).then_some(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+// ── UTC or local time ──────────────────────────────────────────────────
+
+/// The zone dates and times are shown in (`--timestamps`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timestamps {
+    Utc,
+    Local,
+}
+
+impl Timestamps {
+    pub fn parse(text: &str) -> Option<Timestamps> {
+        match text {
+            "utc" => Some(Timestamps::Utc),
+            "local" => Some(Timestamps::Local),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Timestamps::Utc => "utc",
+            Timestamps::Local => "local",
+        }
+    }
+}
+
+static LOCAL: AtomicBool = AtomicBool::new(false);
+
+/// Show dates and times in `zone` from now on.
+pub fn set_timestamps(zone: Timestamps) {
+    LOCAL.store(zone == Timestamps::Local, AtomicOrdering::SeqCst);
+}
+
+pub fn timestamps() -> Timestamps {
+    if LOCAL.load(AtomicOrdering::SeqCst) { Timestamps::Local } else { Timestamps::Utc }
+}
+
+/// A wall-clock reading of a `Timestamp` in some zone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Civil {
+    pub date: Date,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanos: u32,
+    /// Seconds east of UTC.
+    pub offset: i32,
+}
+
+impl Civil {
+    /// `Mon` to `Sun`.
+    pub fn weekday(&self) -> &'static str {
+        ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"][self.date.days().rem_euclid(7) as usize]
+    }
+
+    /// e.g. `2026-03-29T03:00:00+02:00`, or `…Z` in UTC, with the
+    /// fraction of a second when there is one.
+    #[allow(dead_code)]
+    pub fn rfc3339(&self) -> String {
+        let fraction = if self.nanos == 0 {
+            String::new()
+        } else {
+            format!(".{}", format!("{:09}", self.nanos).trim_end_matches('0'))
+        };
+        let zone = if self.offset == 0 {
+            "Z".to_string()
+        } else {
+            let sign = if self.offset < 0 { '-' } else { '+' };
+            let off = self.offset.unsigned_abs() / 60;
+            format!("{}{:02}:{:02}", sign, off / 60, off % 60)
+        };
+        format!("{}T{:02}:{:02}:{:02}{}{}", self.date, self.hour, self.minute, self.second, fraction, zone)
+    }
+}
+
+/// `time` on the clock `--timestamps` chose.
+pub fn civil(time: Timestamp) -> Civil {
+    match timestamps() {
+        Timestamps::Utc => civil_in(time, &Zone::utc()),
+        Timestamps::Local => civil_in(time, local_zone()),
+    }
+}
+
+fn civil_in(time: Timestamp, zone: &Zone) -> Civil {
+    let offset = zone.offset_at(time.secs);
+    let local = time.secs + offset as i64;
+    let of_day = local.rem_euclid(86_400) as u32;
+    Civil {
+        date: Date::from_days(local.div_euclid(86_400)),
+        hour: of_day / 3600,
+        minute: of_day / 60 % 60,
+        second: of_day % 60,
+        nanos: time.nanos,
+        offset,
+    }
+}
+
+// ── Time zones ─────────────────────────────────────────────────────────
+
+/// Offsets from UTC over time: TZif transitions, then a POSIX rule for
+/// everything after the last one.
+#[derive(Clone, Debug, PartialEq)]
+struct Zone {
+    /// `(seconds since the epoch, offset from then on)`, in order.
+    transitions: Vec<(i64, i32)>,
+    /// The offset before the first transition.
+    initial: i32,
+    rule: Option<Rule>,
+}
+
+/// A POSIX `TZ` rule such as `CET-1CEST,M3.5.0,M10.5.0/3`.
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    /// Standard time's offset, in seconds east of UTC.
+    standard: i32,
+    dst: Option<Dst>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Dst {
+    offset: i32,
+    start: Change,
+    end: Change,
+}
+
+/// The local time on the day of the year a rule switches.
+#[derive(Clone, Debug, PartialEq)]
+struct Change {
+    day: Day,
+    /// Seconds after local midnight; may be negative or past a day.
+    time: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Day {
+    /// `Jn`: 1 to 365, never counting 29 February.
+    Julian(u32),
+    /// `n`: 0 to 365, counting 29 February.
+    Zero(u32),
+    /// `Mm.w.d`: weekday `d` (0 is Sunday) of week `w` of month `m`, where
+    /// week 5 is the last.
+    Weekday { month: u32, week: u32, weekday: u32 },
+}
+
+static LOCAL_ZONE: OnceLock<Zone> = OnceLock::new();
+
+/// This machine's zone, from `TZ` or /etc/localtime; UTC when neither
+/// can be read.
+fn local_zone() -> &'static Zone {
+    LOCAL_ZONE.get_or_init(|| {
+        match std::env::var("TZ") {
+            Ok(tz) => Zone::from_tz(&tz),
+            Err(_) => fs::read("/etc/localtime").ok().and_then(|data| Zone::from_tzif(&data)),
+        }
+        .unwrap_or_else(Zone::utc)
+    })
+}
+
+impl Zone {
+    fn utc() -> Zone {
+        Zone { transitions: Vec::new(), initial: 0, rule: None }
+    }
+
+    /// A `TZ` value: a POSIX rule, or the name or path of a zone file,
+    /// with or without a leading `:`.
+    fn from_tz(tz: &str) -> Option<Zone> {
+        if tz.is_empty() {
+            return Some(Zone::utc());
+        }
+        if let Some(rule) = tz.strip_prefix(':').is_none().then(|| Rule::parse(tz)).flatten() {
+            return Some(Zone { transitions: Vec::new(), initial: rule.standard, rule: Some(rule) });
+        }
+        let name = tz.trim_start_matches(':');
+        if name.split('/').any(|part| part == "..") {
+            return None;
+        }
+        let path = if name.starts_with('/') { name.to_string() } else { format!("/usr/share/zoneinfo/{}", name) };
+        Zone::from_tzif(&fs::read(path).ok()?)
+    }
+
+    /// Read a compiled zone file (TZif, RFC 8536): its transitions, and
+    /// the POSIX rule after them when the file has one (version 2 on).
+    fn from_tzif(data: &[u8]) -> Option<Zone> {
+        if data.get(..4)? != b"TZif" {
+            return None;
+        }
+        let (v1, v1_len) = tzif_block(data, 4)?;
+        if data[4] == 0 {
+            return Some(v1);
+        }
+        let (mut zone, len) = tzif_block(data.get(v1_len..)?, 8)?;
+        let footer = data.get(v1_len + len..)?;
+        let footer = std::str::from_utf8(footer).ok()?.trim_matches('\n');
+        zone.rule = Rule::parse(footer);
+        Some(zone)
+    }
+
+    /// Seconds east of UTC at `secs` since the epoch.
+    fn offset_at(&self, secs: i64) -> i32 {
+        match self.transitions.partition_point(|&(at, _)| at <= secs) {
+            0 if self.transitions.is_empty() => self.rule.as_ref().map_or(self.initial, |r| r.offset_at(secs)),
+            0 => self.initial,
+            n if n == self.transitions.len() => self.rule.as_ref().map_or(self.transitions[n - 1].1, |r| r.offset_at(secs)),
+            n => self.transitions[n - 1].1,
+        }
+    }
+}
+
+/// One header and data block of a TZif file whose times are `width` bytes
+/// wide, and the block's length.
+fn tzif_block(data: &[u8], width: usize) -> Option<(Zone, usize)> {
+    let count = |i: usize| -> Option<usize> {
+        Some(u32::from_be_bytes(data.get(20 + 4 * i..24 + 4 * i)?.try_into().ok()?) as usize)
+    };
+    let (isut, isstd, leap, times, types, chars) = (count(0)?, count(1)?, count(2)?, count(3)?, count(4)?, count(5)?);
+    let len = 44 + times * width + times + types * 6 + chars + leap * (width + 4) + isstd + isut;
+    if data.len() < len || types == 0 {
+        return None;
+    }
+    let type_at = 44 + times * (width + 1);
+    let offset = |t: usize| -> Option<(i32, bool)> {
+        let entry = data.get(type_at + 6 * t..type_at + 6 * t + 6)?;
+        Some((i32::from_be_bytes(entry[..4].try_into().ok()?), entry[4] != 0))
+    };
+    let transitions = (0..times)
+        .map(|i| {
+            let at = &data[44 + i * width..44 + (i + 1) * width];
+            let at = if width == 8 {
+                i64::from_be_bytes(at.try_into().ok()?)
+            } else {
+                i32::from_be_bytes(at.try_into().ok()?) as i64
+            };
+            Some((at, offset(data[44 + times * width + i] as usize)?.0))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    // Before the first transition, the first standard-time type applies.
+    let initial = (0..types).filter_map(offset).find(|&(_, dst)| !dst).or_else(|| offset(0))?.0;
+    Some((Zone { transitions, initial, rule: None }, len))
+}
+
+impl Rule {
+    fn parse(text: &str) -> Option<Rule> {
+        let mut rest = text;
+        zone_name(&mut rest)?;
+        let standard = -clock_time(&mut rest)?;
+        if rest.is_empty() {
+            return Some(Rule { standard, dst: None });
+        }
+        zone_name(&mut rest)?;
+        let offset = if rest.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-') {
+            -clock_time(&mut rest)?
+        } else {
+            standard + 3600
+        };
+        // No dates given: the US rules, as glibc assumes.
+        let dates = if rest.is_empty() { ",M3.2.0,M11.1.0" } else { rest };
+        let mut rest = dates.strip_prefix(',')?;
+        let start = Change::parse(&mut rest)?;
+        rest = rest.strip_prefix(',')?;
+        let end = Change::parse(&mut rest)?;
+        rest.is_empty().then_some(Rule { standard, dst: Some(Dst { offset, start, end }) })
+    }
+
+    fn offset_at(&self, secs: i64) -> i32 {
+        let Some(dst) = &self.dst else {
+            return self.standard;
+        };
+        let year = Date::from_days((secs + self.standard as i64).div_euclid(86_400)).year;
+        let start = dst.start.day.in_year(year) * 86_400 + dst.start.time as i64 - self.standard as i64;
+        let end = dst.end.day.in_year(year) * 86_400 + dst.end.time as i64 - dst.offset as i64;
+        // In the southern hemisphere summer time spans the new year.
+        let summer = if start < end { start <= secs && secs < end } else { secs >= start || secs < end };
+        if summer { dst.offset } else { self.standard }
+    }
+}
+
+impl Change {
+    fn parse(rest: &mut &str) -> Option<Change> {
+        let day = if let Some(spec) = rest.strip_prefix('M') {
+            *rest = spec;
+            let month = number(rest)?;
+            *rest = rest.strip_prefix('.')?;
+            let week = number(rest)?;
+            *rest = rest.strip_prefix('.')?;
+            let weekday = number(rest)?;
+            ((1..=12).contains(&month) && (1..=5).contains(&week) && weekday <= 6)
+                .then_some(Day::Weekday { month, week, weekday })?
+        } else if let Some(spec) = rest.strip_prefix('J') {
+            *rest = spec;
+            Some(number(rest)?).filter(|n| (1..=365).contains(n)).map(Day::Julian)?
+        } else {
+            Some(number(rest)?).filter(|&n| n <= 365).map(Day::Zero)?
+        };
+        let time = match rest.strip_prefix('/') {
+            Some(spec) => {
+                *rest = spec;
+                clock_time(rest)?
+            }
+            None => 2 * 3600,
+        };
+        Some(Change { day, time })
+    }
+}
+
+impl Day {
+    /// Days from 1970-01-01 to this day of `year`.
+    fn in_year(&self, year: u32) -> i64 {
+        let january = Date { year, month: 1, day: 1 }.days();
+        match *self {
+            Day::Julian(n) => january + n as i64 - 1 + i64::from(Date::is_leap_year(year) && n >= 60),
+            Day::Zero(n) => january + n as i64,
+            Day::Weekday { month, week, weekday } => {
+                let first = Date { year, month, day: 1 }.days();
+                // 1970-01-01 was a Thursday.
+                let first_weekday = (first + 4).rem_euclid(7);
+                let mut day = first + (weekday as i64 - first_weekday).rem_euclid(7) + 7 * (week as i64 - 1);
+                while day >= first + Date::days_in_month(year, month) as i64 {
+                    day -= 7;
+                }
+                day
+            }
+        }
+    }
+}
+
+/// Skip a zone abbreviation: three or more letters, or anything in `<>`.
+fn zone_name(rest: &mut &str) -> Option<()> {
+    let len = match rest.strip_prefix('<') {
+        Some(quoted) => quoted.find('>')? + 2,
+        None => rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len()),
+    };
+    if len < 3 {
+        return None;
+    }
+    *rest = &rest[len..];
+    Some(())
+}
+
+/// `[+-]hh[:mm[:ss]]` in seconds.
+fn clock_time(rest: &mut &str) -> Option<i32> {
+    let sign = match rest.chars().next()? {
+        '-' => -1,
+        '+' => 1,
+        _ => 0,
+    };
+    if sign != 0 {
+        *rest = &rest[1..];
+    }
+    let mut secs = number(rest)? as i32 * 3600;
+    for scale in [60, 1] {
+        match rest.strip_prefix(':') {
+            Some(more) => {
+                *rest = more;
+                secs += number(rest)? as i32 * scale;
+            }
+            None => break,
+        }
+    }
+    Some(if sign < 0 { -secs } else { secs })
+}
+
+fn number(rest: &mut &str) -> Option<u32> {
+    let len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let value = rest[..len].parse().ok()?;
+    *rest = &rest[len..];
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> Timestamp {
+        Timestamp::parse_rfc3339(text).unwrap()
+    }
+
+    #[test]
+    fn dates_from_days() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(11_016).to_string(), "2000-02-29");
+        assert_eq!(Date::from_days(20_743).to_string(), "2026-10-17");
+        for days in [-25_000, -1, 0, 11_016, 20_743, 2_932_896] {
+            assert_eq!(Date::from_days(days).days(), days);
+        }
+    }
+
+    #[test]
+    fn only_real_dates_parse() {
+        assert_eq!(Date::parse("2024-02-29"), Some(Date { year: 2024, month: 2, day: 29 }));
+        for bad in ["2023-02-29", "2026-13-01", "2026-04-31", "2026-1-01", "2026-01-01x", "+026-01-01", "2026/01/01", ""] {
+            assert_eq!(Date::parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn epoch_seconds_round_trip() {
+        for (text, secs, nanos) in [
+            ("1700000000", 1_700_000_000, 0),
+            ("1700000000.5", 1_700_000_000, 500_000_000),
+            ("1700000000.000000001", 1_700_000_000, 1),
+            ("0", 0, 0),
+            ("-12.25", -13, 750_000_000),
+            ("-0.5", -1, 500_000_000),
+        ] {
+            let t = Timestamp::parse_epoch(text).unwrap();
+            assert_eq!((t.secs, t.nanos), (secs, nanos), "{}", text);
+            assert_eq!(t.to_string(), text);
+        }
+        // find's %T@ prints ten digits; the tenth is below a nanosecond.
+        assert_eq!(Timestamp::parse_epoch("1700000000.1234567899\n").unwrap().to_string(), "1700000000.123456789");
+        for bad in ["", "-", "1.", ".5", "1e9", "12:00", "1.2.3", "+5"] {
+            assert_eq!(Timestamp::parse_epoch(bad), None, "{}", bad);
+        }
+        let before = UNIX_EPOCH - Duration::from_millis(1500);
+        assert_eq!(Timestamp::of(before).to_string(), "-1.5");
+    }
+
+    #[test]
+    fn tolerance_and_truncation() {
+        let local = Timestamp::parse_epoch("1700000000.999999999").unwrap();
+        let fat = local.truncate(Duration::from_secs(2));
+        assert_eq!(fat.to_string(), "1700000000");
+        assert_eq!(local.truncate(Duration::from_secs(1)).to_string(), "1700000000");
+        assert_eq!(local.truncate(Duration::from_micros(1)).to_string(), "1700000000.999999");
+        assert_eq!(local.truncate(Duration::ZERO), local);
+        assert_eq!(Timestamp::parse_epoch("-0.5").unwrap().truncate(Duration::from_secs(1)).to_string(), "-1");
+
+        // A copy on FAT is up to 2 s older than its source, yet the same.
+        assert_eq!(local.compare(fat, Duration::from_secs(2)), Ordering::Equal);
+        assert!(local.is_newer(fat, Duration::from_millis(500)));
+        assert!(!fat.is_newer(local, Duration::ZERO));
+        assert_eq!(fat.compare(local, Duration::ZERO), Ordering::Less);
+        assert_eq!(local.compare(local, Duration::ZERO), Ordering::Equal);
+    }
+
+    #[test]
+    fn rfc3339_and_durations() {
+        assert_eq!(at("2026-10-17T18:42:05Z").to_string(), "1792262525");
+        assert_eq!(at("2026-10-17T20:42:05.25+02:00").to_string(), "1792262525.25");
+        assert_eq!(at("2026-10-17t13:42:05-05:00"), at("2026-10-17 18:42:05z"));
+        // No leap seconds in Unix time.
+        assert_eq!(at("2016-12-31T23:59:60Z"), at("2016-12-31T23:59:59Z"));
+        for bad in ["2026-10-17", "2026-10-17T18:42:05", "2026-02-30T00:00:00Z", "2026-10-17T24:00:00Z", "2026-10-17T18:42Z", "2026-10-17T18:42:05.Z"] {
+            assert_eq!(Timestamp::parse_rfc3339(bad), None, "{}", bad);
+        }
+        assert_eq!(civil_in(at("1969-12-31T23:59:59.5Z"), &Zone::utc()).rfc3339(), "1969-12-31T23:59:59.5Z");
+
+        let secs = Duration::from_secs(1);
+        assert_eq!(parse_duration("90", secs), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1.5", Duration::from_secs(3600)), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("90s", secs), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m", secs), Some(Duration::from_secs(900)));
+        assert_eq!(parse_duration("1d12h", secs), Some(Duration::from_secs(129_600)));
+        assert_eq!(parse_duration("1w", secs), Some(Duration::from_secs(604_800)));
+        for bad in ["", "-5", "5x", "h", "1h30", "1.5h", "inf"] {
+            assert_eq!(parse_duration(bad, secs), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn dst_boundaries_format_in_local_time() {
+        let berlin = Zone::from_tz("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        let local = |text: &str| civil_in(at(text), &berlin).rfc3339();
+        // The skipped hour: 02:00 CET is 03:00 CEST.
+        assert_eq!(local("2026-03-29T00:59:59Z"), "2026-03-29T01:59:59+01:00");
+        assert_eq!(local("2026-03-29T01:00:00Z"), "2026-03-29T03:00:00+02:00");
+        // The repeated hour: 02:30 happens twice.
+        assert_eq!(local("2026-10-25T00:30:00Z"), "2026-10-25T02:30:00+02:00");
+        assert_eq!(local("2026-10-25T01:30:00Z"), "2026-10-25T02:30:00+01:00");
+        // Dates change at local midnight, not UTC's.
+        assert_eq!(civil_in(at("2026-06-30T22:30:00Z"), &berlin).date.to_string(), "2026-07-01");
+        assert_eq!(civil_in(at("2026-06-30T22:30:00Z"), &berlin).weekday(), "Wed");
+
+        // Southern summer time spans the new year.
+        let sydney = Zone::from_tz("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        let local = |text: &str| civil_in(at(text), &sydney).rfc3339();
+        assert_eq!(local("2026-01-01T00:00:00Z"), "2026-01-01T11:00:00+11:00");
+        assert_eq!(local("2026-04-04T15:59:59Z"), "2026-04-05T02:59:59+11:00");
+        assert_eq!(local("2026-04-04T16:00:00Z"), "2026-04-05T02:00:00+10:00");
+        assert_eq!(local("2026-07-01T00:00:00Z"), "2026-07-01T10:00:00+10:00");
+
+        assert_eq!(Zone::from_tz("<+0530>-5:30").unwrap().offset_at(0), 19_800);
+        assert_eq!(Zone::from_tz(":../../etc/passwd"), None);
+        assert_eq!(Rule::parse("CET-1CEST,M3.5.0"), None);
+    }
+
+    #[test]
+    fn zone_files_use_transitions_then_their_rule() {
+        // A version 2 file: an empty version 1 block, then one transition
+        // to summer time in 2026 and the rule for the years after.
+        let mut data = Vec::new();
+        let header = |data: &mut Vec<u8>, times: u32, types: u32, chars: u32| {
+            data.extend_from_slice(b"TZif2");
+            data.extend_from_slice(&[0; 15]);
+            for count in [0, 0, 0, times, types, chars] {
+                data.extend_from_slice(&u32::to_be_bytes(count));
+            }
+        };
+        header(&mut data, 0, 1, 4);
+        data.extend_from_slice(&[0, 0, 0x0e, 0x10, 0, 0]);
+        data.extend_from_slice(b"CET\0");
+        header(&mut data, 1, 2, 9);
+        data.extend_from_slice(&i64::to_be_bytes(at("2026-03-29T01:00:00Z").secs));
+        data.push(1);
+        data.extend_from_slice(&[0, 0, 0x0e, 0x10, 0, 0, 0, 0, 0x1c, 0x20, 1, 4]);
+        data.extend_from_slice(b"CET\0CEST\0");
+        data.extend_from_slice(b"\nCET-1CEST,M3.5.0,M10.5.0/3\n");
+
+        let zone = Zone::from_tzif(&data).unwrap();
+        let local = |text: &str| civil_in(at(text), &zone).rfc3339();
+        assert_eq!(local("2026-01-15T12:00:00Z"), "2026-01-15T13:00:00+01:00");
+        assert_eq!(local("2026-03-29T01:00:00Z"), "2026-03-29T03:00:00+02:00");
+        // Past the last transition, the rule takes over.
+        assert_eq!(local("2026-10-25T01:30:00Z"), "2026-10-25T02:30:00+01:00");
+        assert_eq!(local("2027-07-01T00:00:00Z"), "2027-07-01T02:00:00+02:00");
+
+        assert_eq!(Zone::from_tzif(b"TZif2"), None);
+        assert_eq!(Zone::from_tzif(&data[..60]), None);
+    }
+}
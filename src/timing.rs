@@ -0,0 +1,300 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::json_string;
+
+// ── Where a remote run's time went ─────────────────────────────────────
+//
+// A slow transfer between hosts could be down to the source host, the
+// destination host or the link in between.  Every child command a worker
+// runs through `activity`'s tracked calls is timed here and put in a bucket
+// by what it is: `scp`/`rsync` from `host:` is a download from that host,
+// to `host:` an upload, `ssh host sha256sum …` hashing there, and any other
+// `ssh` call connection overhead (connecting, mkdir, listing, deleting).
+// Hashing files on this machine, such as a relay's staged copies, is local
+// IO.  A new transport is timed as soon as its commands are tracked.
+//
+// Like the destination folders, the record is process-wide: only one
+// transfer runs at a time.
+
+/// Single commands slower than this are written to the debug log.
+const SLOW_COMMAND: Duration = Duration::from_secs(1);
+
+/// What a command spent its time on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bucket {
+    Download,
+    Upload,
+    Hashing,
+    /// Connecting, making folders, listing, deleting.
+    Ssh,
+}
+
+impl Bucket {
+    const ALL: [Bucket; 4] = [Bucket::Download, Bucket::Upload, Bucket::Hashing, Bucket::Ssh];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Bucket::Download => "download",
+            Bucket::Upload => "upload",
+            Bucket::Hashing => "hashing",
+            Bucket::Ssh => "ssh",
+        }
+    }
+}
+
+/// The time spent with one host, by bucket.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HostTimes {
+    pub host: String,
+    times: [Duration; 4],
+}
+
+impl HostTimes {
+    pub fn get(&self, bucket: Bucket) -> Duration {
+        self.times[bucket as usize]
+    }
+
+    /// `source`, `dest`, `source and dest`, or `host` when nothing was
+    /// transferred either way.
+    fn role(&self) -> &'static str {
+        match (self.get(Bucket::Download) > Duration::ZERO, self.get(Bucket::Upload) > Duration::ZERO) {
+            (true, true) => "source and dest",
+            (true, false) => "source",
+            (false, true) => "dest",
+            (false, false) => "host",
+        }
+    }
+}
+
+/// A run's timings, per host in the order they were first reached.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Breakdown {
+    pub hosts: Vec<HostTimes>,
+    /// Hashing and staging on this machine.
+    pub local: Duration,
+}
+
+impl Breakdown {
+    fn add(&mut self, host: &str, bucket: Bucket, elapsed: Duration) {
+        let index = match self.hosts.iter().position(|h| h.host == host) {
+            Some(index) => index,
+            None => {
+                self.hosts.push(HostTimes { host: host.to_string(), ..HostTimes::default() });
+                self.hosts.len() - 1
+            }
+        };
+        self.hosts[index].times[bucket as usize] += elapsed;
+    }
+
+    /// `Time spent: source nas1: 41 min download, 6 min hashing; dest nas2:
+    /// 12 min upload; local: 2 min IO`, or `None` when no host was reached.
+    pub fn describe(&self) -> Option<String> {
+        if self.hosts.is_empty() {
+            return None;
+        }
+        let mut parts: Vec<String> = self
+            .hosts
+            .iter()
+            .map(|h| {
+                let times: Vec<String> = Bucket::ALL
+                    .iter()
+                    .filter(|&&b| h.get(b) > Duration::ZERO)
+                    .map(|&b| format!("{} {}", format_time(h.get(b)), b.name()))
+                    .collect();
+                format!("{} {}: {}", h.role(), h.host, times.join(", "))
+            })
+            .collect();
+        if self.local > Duration::ZERO {
+            parts.push(format!("local: {} IO", format_time(self.local)));
+        }
+        Some(format!("Time spent: {}", parts.join("; ")))
+    }
+
+    /// `{"hosts":[{"host":"nas1","download_secs":2460.0,…}],"local_secs":120.0}`
+    pub fn to_json_value(&self) -> String {
+        let hosts: Vec<String> = self
+            .hosts
+            .iter()
+            .map(|h| {
+                let times: Vec<String> = Bucket::ALL
+                    .iter()
+                    .map(|&b| format!("\"{}_secs\":{:.1}", b.name(), h.get(b).as_secs_f64()))
+                    .collect();
+                format!("{{\"host\":{},{}}}", json_string(&h.host), times.join(","))
+            })
+            .collect();
+        format!("{{\"hosts\":[{}],\"local_secs\":{:.1}}}", hosts.join(","), self.local.as_secs_f64())
+    }
+}
+
+/// `40 s`, `12 min` or `2 h 05 min`.
+fn format_time(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        0 => "<1 s".to_string(),
+        1..=59 => format!("{} s", secs),
+        60..=3599 => format!("{} min", secs / 60),
+        _ => format!("{} h {:02} min", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+fn record() -> &'static Mutex<Breakdown> {
+    static RECORD: OnceLock<Mutex<Breakdown>> = OnceLock::new();
+    RECORD.get_or_init(Mutex::default)
+}
+
+/// Forget the timings of the last run, e.g. when a new transfer starts.
+pub fn reset() {
+    if let Ok(mut record) = record().lock() {
+        *record = Breakdown::default();
+    }
+}
+
+/// The timings noted since the last `reset`, leaving the record empty.
+pub fn take() -> Breakdown {
+    record().lock().map(|mut record| std::mem::take(&mut *record)).unwrap_or_default()
+}
+
+/// Note time spent hashing or staging files on this machine.
+pub fn note_local(elapsed: Duration) {
+    if let Ok(mut record) = record().lock() {
+        record.local += elapsed;
+    }
+}
+
+/// Run `cmd` through `run`, noting how long it took against the host it
+/// reaches.  Commands that reach no host are not timed.
+pub fn timed<T>(cmd: &mut Command, run: impl FnOnce(&mut Command) -> T) -> T {
+    let Some((host, bucket)) = classify(cmd) else { return run(cmd) };
+    let started = Instant::now();
+    let result = run(cmd);
+    let elapsed = started.elapsed();
+    if elapsed >= SLOW_COMMAND {
+        crate::debug_log(&format!(
+            "{} {} took {:.1} s: {}",
+            host,
+            bucket.name(),
+            elapsed.as_secs_f64(),
+            crate::activity::describe_command(cmd)
+        ));
+    }
+    if let Ok(mut record) = record().lock() {
+        record.add(&host, bucket, elapsed);
+    }
+    result
+}
+
+/// The host `cmd` reaches and what it does there, or `None` for a command
+/// that reaches no host.  Wrappers such as `ionice -c3 nice -n19` are
+/// looked through.
+fn classify(cmd: &Command) -> Option<(String, Bucket)> {
+    let words: Vec<String> = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|w| w.to_string_lossy().to_string())
+        .collect();
+    let tool = words.iter().position(|w| matches!(w.rsplit('/').next(), Some("ssh" | "scp" | "rsync")))?;
+    let args = &words[tool + 1..];
+    if words[tool].ends_with("ssh") {
+        // `ssh [options] host [command…]`
+        let mut rest = args.iter();
+        let host = loop {
+            let arg = rest.next()?;
+            match arg.as_str() {
+                "-o" | "-p" | "-i" | "-l" | "-F" | "-J" => {
+                    rest.next();
+                }
+                a if a.starts_with('-') => {}
+                _ => break arg.clone(),
+            }
+        };
+        let hashing = rest.any(|w| ["sha256sum", "shasum", "b3sum", "xxhsum"].iter().any(|tool| w.contains(tool)));
+        return Some((host, if hashing { Bucket::Hashing } else { Bucket::Ssh }));
+    }
+    // `scp`/`rsync [options] source… destination`
+    let (dest, sources) = args.split_last()?;
+    match host_of(dest) {
+        Some(host) => Some((host.to_string(), Bucket::Upload)),
+        None => sources.iter().rev().find_map(|s| host_of(s)).map(|host| (host.to_string(), Bucket::Download)),
+    }
+}
+
+/// `nas` from `nas:/srv/a.txt`; option values such as
+/// `ControlPath=/tmp/…%h:%p` and local paths are not hosts.
+fn host_of(arg: &str) -> Option<&str> {
+    let (host, _) = arg.split_once(':')?;
+    (!host.is_empty() && !host.starts_with('-') && !host.contains(['/', ' ', '='])).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn command(words: &[&str]) -> Command {
+        let mut cmd = Command::new(words[0]);
+        cmd.args(&words[1..]);
+        cmd
+    }
+
+    #[test]
+    fn commands_are_put_in_buckets() {
+        let ctl = ["-o", "ControlPath=/tmp/kosmokopy_ssh_%h_%p_%r", "-o", "ConnectTimeout=10"];
+        let of = |words: &[&str]| classify(&command(words)).map(|(host, bucket)| (host, bucket.name()));
+        let at = |host: &str, bucket: &'static str| Some((host.to_string(), bucket));
+        assert_eq!(of(&["scp", ctl[0], ctl[1], "-q", "nas1:/srv/a.txt", "/tmp/relay/a.txt"]), at("nas1", "download"));
+        assert_eq!(of(&["ionice", "-c3", "nice", "-n19", "scp", "-q", "/tmp/a.txt", "nas2:/b/a.txt"]), at("nas2", "upload"));
+        assert_eq!(of(&["rsync", "-az", "-e", "ssh -o ControlPath=/tmp/x_%h:%p", "nas1:/srv/a", "/tmp/a"]), at("nas1", "download"));
+        assert_eq!(of(&["ssh", ctl[0], ctl[1], ctl[2], ctl[3], "nas2", "sha256sum '/b/a.txt' || shasum -a 256 '/b/a.txt'"]), at("nas2", "hashing"));
+        assert_eq!(of(&["ssh", ctl[0], ctl[1], "nas2", "mkdir -p '/b'"]), at("nas2", "ssh"));
+        assert_eq!(of(&["ssh", "-G", "nas1"]), at("nas1", "ssh"));
+        assert_eq!(of(&["sh", "-c", "sleep 1"]), None);
+        assert_eq!(of(&["scp", "/tmp/a", "/tmp/b"]), None);
+    }
+
+    #[test]
+    fn slow_commands_are_charged_to_their_host() {
+        // The runner sleeps instead of starting anything.
+        let run = |words: &[&str], ms: u64| timed(&mut command(words), |_| thread::sleep(Duration::from_millis(ms)));
+        run(&["scp", "-q", "timing-src:/a", "/tmp/a"], 120);
+        run(&["ssh", "timing-src", "sha256sum '/a'"], 40);
+        run(&["scp", "-q", "/tmp/a", "timing-dst:/a"], 60);
+        run(&["ssh", "timing-dst", "mkdir -p '/b'"], 20);
+        run(&["sh", "-c", "true"], 50);
+
+        let breakdown = take();
+        let host = |name: &str| breakdown.hosts.iter().find(|h| h.host == name).unwrap().clone();
+        let (src, dst) = (host("timing-src"), host("timing-dst"));
+        assert!(src.get(Bucket::Download) >= Duration::from_millis(120));
+        assert!(src.get(Bucket::Hashing) >= Duration::from_millis(40));
+        assert_eq!((src.get(Bucket::Upload), src.get(Bucket::Ssh)), (Duration::ZERO, Duration::ZERO));
+        assert!(dst.get(Bucket::Upload) >= Duration::from_millis(60));
+        assert!(dst.get(Bucket::Ssh) >= Duration::from_millis(20));
+        assert_eq!(dst.get(Bucket::Download), Duration::ZERO);
+        assert_eq!((src.role(), dst.role()), ("source", "dest"));
+    }
+
+    #[test]
+    fn breakdowns_read_plainly() {
+        let mut breakdown = Breakdown::default();
+        assert_eq!(breakdown.describe(), None);
+        breakdown.add("nas1", Bucket::Download, Duration::from_secs(41 * 60));
+        breakdown.add("nas1", Bucket::Hashing, Duration::from_secs(6 * 60 + 20));
+        breakdown.add("nas2", Bucket::Upload, Duration::from_secs(12 * 60));
+        breakdown.add("nas2", Bucket::Ssh, Duration::from_millis(300));
+        breakdown.local = Duration::from_secs(2 * 3600 + 5 * 60);
+        assert_eq!(
+            breakdown.describe().unwrap(),
+            "Time spent: source nas1: 41 min download, 6 min hashing; dest nas2: 12 min upload, <1 s ssh; local: 2 h 05 min IO"
+        );
+        assert!(breakdown.to_json_value().starts_with(
+            "{\"hosts\":[{\"host\":\"nas1\",\"download_secs\":2460.0,\"upload_secs\":0.0,\"hashing_secs\":380.0,\"ssh_secs\":0.0}"
+        ));
+        assert!(breakdown.to_json_value().ends_with(",\"local_secs\":7500.0}"));
+    }
+}
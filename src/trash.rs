@@ -0,0 +1,109 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+// ── Moving folders to the trash ────────────────────────────────────────
+//
+// `--retain-to-trash` moves the dated folders `--retain` prunes into the
+// freedesktop.org trash instead of deleting them, so a wrong template or
+// `--retain` count can be undone from the file manager.  The move is one
+// shell script, run here with `sh` or on the destination host over ssh,
+// so both sides trash the same way:
+//
+// - a folder on the same filesystem as the home trash
+//   (`$XDG_DATA_HOME/Trash`, or `~/.local/share/Trash`) goes there;
+// - any other goes to `.Trash-<uid>` at the top of its own filesystem,
+//   so it is renamed, never copied.
+//
+// Each gets a `.trashinfo` file naming where it came from, reserved with
+// `set -C` before the folder is moved so two trashed folders of the same
+// name don't overwrite each other's record.
+
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::OsStrExt;
+
+/// The script moving `target` to the trash, with paths quoted by `quote`.
+/// It exits non-zero, leaving the folder where it was, if it can't.
+pub fn script(target: &OsStr, quote: impl Fn(&OsStr) -> OsString) -> OsString {
+    let mut out = OsString::from("t=");
+    out.push(quote(target));
+    out.push("; u=");
+    out.push(quote(OsStr::new(&info_path(target))));
+    out.push("\n");
+    out.push(
+        "dev() { stat -c %d -- \"$1\" 2>/dev/null || stat -f %d -- \"$1\" 2>/dev/null; }\n\
+         case $t in /*) ;; *) t=$PWD/$t; u=$PWD/$u;; esac\n\
+         d=$(dev \"$t\") || exit 1\n\
+         h=${XDG_DATA_HOME:-$HOME/.local/share}/Trash\n\
+         if mkdir -p -- \"$h\" 2>/dev/null && [ \"$(dev \"$h\")\" = \"$d\" ]; then trash=$h\n\
+         else\n\
+         \x20 top=${t%/*}; top=${top:-/}\n\
+         \x20 while [ \"$top\" != / ]; do p=${top%/*}; p=${p:-/}; [ \"$(dev \"$p\")\" = \"$d\" ] || break; top=$p; done\n\
+         \x20 trash=${top%/}/.Trash-$(id -u)\n\
+         fi\n\
+         mkdir -p -- \"$trash/files\" \"$trash/info\" && chmod 700 -- \"$trash\" || exit 1\n\
+         n=${t##*/}; name=$n; i=1\n\
+         while ! (set -C; : >\"$trash/info/$name.trashinfo\") 2>/dev/null; do\n\
+         \x20 i=$((i + 1)); [ $i -gt 1000 ] && { echo \"no free name in $trash\" >&2; exit 1; }; name=$n.$i\n\
+         done\n\
+         printf '[Trash Info]\\nPath=%s\\nDeletionDate=%s\\n' \"$u\" \"$(date +%Y-%m-%dT%H:%M:%S)\" >\"$trash/info/$name.trashinfo\"\n\
+         mv -- \"$t\" \"$trash/files/$name\" || { rm -f -- \"$trash/info/$name.trashinfo\"; exit 1; }\n",
+    );
+    out
+}
+
+/// `path` as a `.trashinfo` `Path=` value: every byte but `/` and the
+/// URL-unreserved characters percent-encoded.
+fn info_path(path: &OsStr) -> String {
+    let mut out = String::new();
+    for &b in path.as_bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process::Command;
+
+    fn quote(s: &OsStr) -> OsString {
+        OsString::from(format!("'{}'", s.to_str().unwrap().replace('\'', "'\\''")))
+    }
+
+    #[test]
+    fn info_paths_are_url_encoded() {
+        assert_eq!(info_path(OsStr::new("/backups/weekly 1/it's")), "/backups/weekly%201/it%27s");
+        assert_eq!(info_path(OsStr::from_bytes(b"/a\xff")), "/a%FF");
+    }
+
+    #[test]
+    fn folders_go_to_the_home_trash_under_a_free_name() {
+        let dir = std::env::temp_dir().join(format!("kosmokopy_trash_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let share = dir.join("share");
+        for _ in 0..2 {
+            fs::create_dir_all(dir.join("weekly 1")).unwrap();
+            fs::write(dir.join("weekly 1/old.txt"), "old").unwrap();
+            let out = Command::new("sh")
+                .arg("-c")
+                .arg(script(dir.join("weekly 1").as_os_str(), quote))
+                .env("XDG_DATA_HOME", &share)
+                .output()
+                .unwrap();
+            assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        }
+        assert!(!dir.join("weekly 1").exists());
+        let trash = share.join("Trash");
+        assert_eq!(fs::read_to_string(trash.join("files/weekly 1/old.txt")).unwrap(), "old");
+        assert!(trash.join("files/weekly 1.2/old.txt").exists());
+        let info = fs::read_to_string(trash.join("info/weekly 1.2.trashinfo")).unwrap();
+        assert!(info.starts_with(&format!("[Trash Info]\nPath={}\nDeletionDate=", info_path(dir.join("weekly 1").as_os_str()))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+// Kosmokopy — GTK4 file copier/mover
+// Copyright (C) 2026 Dan Bright <dan@danbright.uk>
+// Licensed under the GNU General Public License v3.0
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// ── Checking a local copy ──────────────────────────────────────────────
+//
+// Each file a local worker writes is checked against its source before it
+// counts as copied.  How is the run's verification (`--verify`, or the
+// window's Verification choice):
+//
+// - Full, the default, reads both files and compares them byte for byte;
+// - Hash compares a hash of each, with the algorithm remote transfers use
+//   (`--hash-algo`);
+// - QuickSizeMtime compares sizes, and modification times too when they
+//   are preserved; it reads no data, so it can miss a corrupted copy;
+// - None trusts the copy as written.  A move deletes each source once its
+//   copy checks out, so a move can't go unchecked.
+//
+// A link kept as a link is checked by where it points whatever the mode,
+// and a run with the hash cache compares in full as it records.  Remote
+// transfers are always checked by hash.
+
+/// How a local copy is checked against its source.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum VerifyMode {
+    #[default]
+    Full,
+    Hash,
+    QuickSizeMtime,
+    None,
+}
+
+impl VerifyMode {
+    /// `--verify`.
+    pub fn parse(word: &str) -> Option<VerifyMode> {
+        match word {
+            "full" => Some(VerifyMode::Full),
+            "hash" => Some(VerifyMode::Hash),
+            "quick" => Some(VerifyMode::QuickSizeMtime),
+            "none" => Some(VerifyMode::None),
+            _ => None,
+        }
+    }
+}
+
+/// Why a run can't use `mode`: a move deletes sources whose copies were
+/// never checked.
+pub fn move_error(mode: VerifyMode, do_move: bool) -> Option<&'static str> {
+    (do_move && mode == VerifyMode::None)
+        .then_some("A move deletes each source once its copy checks out, so it can't skip verification")
+}
+
+/// Whether `b` has the size of `a` and, when the run preserves times
+/// (otherwise the copy carries the time it was written), its
+/// modification time.
+pub fn same_size_and_time(a: &Path, b: &Path, times_kept: bool) -> io::Result<bool> {
+    let (meta_a, meta_b) = (fs::metadata(a)?, fs::metadata(b)?);
+    Ok(meta_a.len() == meta_b.len() && (!times_kept || meta_a.modified()? == meta_b.modified()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_compares_size_and_kept_times() {
+        let dir = std::env::temp_dir().join(format!("kosmokopy_verify_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        fs::write(&a, "same size").unwrap();
+        fs::write(&b, "SAME SIZE").unwrap();
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        fs::File::options().write(true).open(&a).unwrap().set_modified(old).unwrap();
+
+        assert!(same_size_and_time(&a, &b, false).unwrap());
+        assert!(!same_size_and_time(&a, &b, true).unwrap());
+        fs::File::options().write(true).open(&b).unwrap().set_modified(old).unwrap();
+        assert!(same_size_and_time(&a, &b, true).unwrap());
+        fs::write(&b, "longer than before").unwrap();
+        assert!(!same_size_and_time(&a, &b, false).unwrap());
+
+        assert_eq!(VerifyMode::parse("quick"), Some(VerifyMode::QuickSizeMtime));
+        assert_eq!(VerifyMode::parse("bytes"), None);
+        assert!(move_error(VerifyMode::None, true).is_some());
+        assert!(move_error(VerifyMode::None, false).is_none());
+        assert!(move_error(VerifyMode::Hash, true).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
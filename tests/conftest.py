@@ -21,6 +21,9 @@ Environment variables (all optional — remote tests are skipped if unset):
   KOSMOKOPY_BIN                 Path to the kosmokopy binary.  Defaults to
                                   target/debug/kosmokopy relative to the
                                   project root.
+  KOSMOKOPY_HOME                Where the binary keeps settings and state.
+                                  Defaults to a fresh temporary directory so
+                                  tests never touch the real home directory.
 """
 
 import hashlib
@@ -48,6 +51,10 @@ _REPORTS_DIR = _PROJECT_ROOT / "tests" / "reports"
 
 # ── Configuration from environment ──────────────────────────────────────
 
+# Keep every run's settings, state and caches out of the real home
+# directory; the binary inherits this unless a test overrides it.
+os.environ.setdefault("KOSMOKOPY_HOME", tempfile.mkdtemp(prefix="kosmokopy_home_"))
+
 REMOTE_HOST = os.environ.get("KOSMOKOPY_TEST_REMOTE_HOST")
 REMOTE_PATH = os.environ.get("KOSMOKOPY_TEST_REMOTE_PATH")
 REMOTE_HOST2 = os.environ.get("KOSMOKOPY_TEST_REMOTE_HOST2")
@@ -83,14 +90,70 @@ requires_rsync = pytest.mark.skipif(
 def run_kosmokopy(
     *,
     src=None,
-    dst,
+    dst=None,
     src_files=None,
+    profile=None,
     move=False,
-    conflict="skip",
+    move_strategy=None,
+    remove_source_root=False,
+    conflict=None,
     strip_spaces=False,
+    rename_rule=None,
+    chmod_dirs=None,
+    preserve=False,
+    symlinks=None,
+    verify=None,
+    hash_algo=None,
     mode="folders",
     method="standard",
+    parallel=None,
+    retries=None,
     exclude=None,
+    exclude_from=None,
+    include=None,
+    only_user=None,
+    only_group=None,
+    exclude_user=None,
+    exclude_group=None,
+    files_keep_parents=None,
+    src_files_from=None,
+    failed_files_out=None,
+    detail_report=None,
+    case_insensitive_paths=False,
+    no_source_config=False,
+    null=False,
+    move_fallback=None,
+    recheck_conflicts=False,
+    no_recheck_rename=False,
+    no_coalesce_errors=False,
+    detail_limit=None,
+    retain=None,
+    retain_dry_run=False,
+    retain_to_trash=False,
+    on_unwritable=None,
+    stale_artifacts=None,
+    on_collision=None,
+    relay=None,
+    bwlimit=None,
+    compress=None,
+    on_quota=None,
+    hash_cache=None,
+    paranoid=False,
+    verify_sample=None,
+    verify_sample_min_size=None,
+    verify_sample_seed=None,
+    low_priority=False,
+    max_clock_skew=None,
+    timestamps=None,
+    accept_new_hostkeys=False,
+    file_timeout=None,
+    ssh_control_dir=None,
+    duplicate_window=None,
+    refuse_duplicate_runs=False,
+    scan_limit=None,
+    scan_limit_action=None,
+    env=None,
+    stdin=None,
 ):
     """
     Invoke ``kosmokopy --cli`` with the given options and return the
@@ -107,25 +170,144 @@ def run_kosmokopy(
         cmd += ["--src", str(src)]
     if src_files is not None:
         cmd += ["--src-files", ",".join(str(f) for f in src_files)]
+    if profile is not None:
+        cmd += ["--profile", profile]
 
-    cmd += ["--dst", str(dst)]
+    # A profile can supply the destination, mode and method.
+    if dst is not None:
+        cmd += ["--dst", str(dst)]
 
     if move:
         cmd.append("--move")
+    if move_strategy is not None:
+        cmd += ["--move-strategy", move_strategy]
+    if remove_source_root:
+        cmd.append("--remove-source-root")
 
-    cmd += ["--conflict", conflict]
+    if conflict is not None:
+        cmd += ["--conflict", conflict]
 
     if strip_spaces:
         cmd.append("--strip-spaces")
-
-    cmd += ["--mode", mode]
-    cmd += ["--method", method]
+    if rename_rule:
+        for rule in rename_rule:
+            cmd += ["--rename-rule", rule]
+    if chmod_dirs is not None:
+        cmd += ["--chmod-dirs", chmod_dirs]
+    if preserve:
+        cmd.append("--preserve")
+    if symlinks is not None:
+        cmd += ["--symlinks", symlinks]
+    if verify is not None:
+        cmd += ["--verify", verify]
+    if hash_algo is not None:
+        cmd += ["--hash-algo", hash_algo]
+
+    if mode is not None:
+        cmd += ["--mode", mode]
+    if method is not None:
+        cmd += ["--method", method]
+    if parallel is not None:
+        cmd += ["--parallel", str(parallel)]
+    if retries is not None:
+        cmd += ["--retries", str(retries)]
 
     if exclude:
         for pat in exclude:
             cmd += ["--exclude", pat]
 
-    result = subprocess.run(cmd, capture_output=True, text=True, timeout=120)
+    for path in exclude_from or []:
+        cmd += ["--exclude-from", str(path)]
+
+    if include:
+        for pat in include:
+            cmd += ["--include", pat]
+    for flag, owners in (
+        ("--only-user", only_user), ("--only-group", only_group),
+        ("--exclude-user", exclude_user), ("--exclude-group", exclude_group),
+    ):
+        for owner in owners or []:
+            cmd += [flag, str(owner)]
+
+    if files_keep_parents is not None:
+        cmd += ["--files-keep-parents", str(files_keep_parents)]
+
+    if src_files_from is not None:
+        cmd += ["--src-files-from", str(src_files_from)]
+    if failed_files_out is not None:
+        cmd += ["--failed-files-out", str(failed_files_out)]
+    if detail_report is not None:
+        cmd += ["--detail-report", str(detail_report)]
+    if case_insensitive_paths:
+        cmd.append("--case-insensitive-paths")
+    if no_source_config:
+        cmd.append("--no-source-config")
+    if null:
+        cmd.append("--null")
+    if move_fallback is not None:
+        cmd += ["--move-fallback", move_fallback]
+    if recheck_conflicts:
+        cmd.append("--recheck-conflicts")
+    if no_recheck_rename:
+        cmd.append("--no-recheck-rename")
+    if no_coalesce_errors:
+        cmd.append("--no-coalesce-errors")
+    if detail_limit is not None:
+        cmd += ["--detail-limit", str(detail_limit)]
+    if retain is not None:
+        cmd += ["--retain", str(retain)]
+    if retain_dry_run:
+        cmd.append("--retain-dry-run")
+    if retain_to_trash:
+        cmd.append("--retain-to-trash")
+    if on_unwritable is not None:
+        cmd += ["--on-unwritable", on_unwritable]
+    if stale_artifacts is not None:
+        cmd += ["--stale-artifacts", stale_artifacts]
+    if on_collision is not None:
+        cmd += ["--on-collision", on_collision]
+    if relay is not None:
+        cmd += ["--relay", relay]
+    if bwlimit is not None:
+        cmd += ["--bwlimit", str(bwlimit)]
+    if compress is not None:
+        cmd += ["--compress", compress]
+    if on_quota is not None:
+        cmd += ["--on-quota", on_quota]
+    if hash_cache is not None:
+        cmd += ["--hash-cache", str(hash_cache)]
+    if paranoid:
+        cmd.append("--paranoid")
+    if verify_sample is not None:
+        cmd += ["--verify-sample", str(verify_sample)]
+    if verify_sample_min_size is not None:
+        cmd += ["--verify-sample-min-size", str(verify_sample_min_size)]
+    if verify_sample_seed is not None:
+        cmd += ["--verify-sample-seed", str(verify_sample_seed)]
+    if low_priority:
+        cmd.append("--low-priority")
+    if max_clock_skew is not None:
+        cmd += ["--max-clock-skew", str(max_clock_skew)]
+    if timestamps is not None:
+        cmd += ["--timestamps", timestamps]
+    if accept_new_hostkeys:
+        cmd.append("--accept-new-hostkeys")
+    if file_timeout is not None:
+        cmd += ["--file-timeout", str(file_timeout)]
+    if ssh_control_dir is not None:
+        cmd += ["--ssh-control-dir", str(ssh_control_dir)]
+    if duplicate_window is not None:
+        cmd += ["--duplicate-window", str(duplicate_window)]
+    if refuse_duplicate_runs:
+        cmd.append("--refuse-duplicate-runs")
+    if scan_limit is not None:
+        cmd += ["--scan-limit", str(scan_limit)]
+    if scan_limit_action is not None:
+        cmd += ["--scan-limit-action", scan_limit_action]
+
+    if env is not None:
+        env = {**os.environ, **env}
+    result = subprocess.run(cmd, capture_output=True, text=True, timeout=120, env=env, input=stdin)
 
     # Parse the JSON line from stdout
     stdout = result.stdout.strip()
@@ -210,6 +392,17 @@ def sha256_of_file(path):
     return h.hexdigest()
 
 
+def tree_hashes(root):
+    """SHA-256 of every regular file under *root*, keyed by its path
+    relative to *root*.  Symlinks are skipped, as Kosmokopy skips them."""
+    root = Path(root)
+    return {
+        str(p.relative_to(root)): sha256_of_file(p)
+        for p in root.rglob("*")
+        if p.is_file() and not p.is_symlink()
+    }
+
+
 def sha256_remote(host, remote_path):
     """Return hex SHA-256 digest of a remote file via SSH."""
     r = subprocess.run(
@@ -348,6 +541,27 @@ def tmp_src_with_exclusions(tmp_path):
     return src
 
 
+@pytest.fixture
+def tmp_fixture_tree(tmp_path):
+    """A source tree with the names and layouts a transfer must keep:
+    nesting, spaces, unicode, dotfiles, an empty file, larger random files
+    and a symlink (which is not copied).  Returns ``(root, hashes)`` where
+    *hashes* maps each regular file's relative path to its SHA-256."""
+    src = tmp_path / "library"
+    for d in ["docs/drafts", "Photos 2024/Day One", "música/ñandú", ".config/app", "empty"]:
+        (src / d).mkdir(parents=True)
+    (src / "readme.txt").write_text("top level\n")
+    (src / ".hidden").write_text("dotfile\n")
+    (src / "docs" / "report final.md").write_text("# Report\n")
+    (src / "docs" / "drafts" / "v1.txt").write_bytes(b"")
+    (src / "Photos 2024" / "Day One" / "IMG 0001.raw").write_bytes(os.urandom(300_000))
+    (src / "Photos 2024" / "Day One" / "IMG 0002.raw").write_bytes(os.urandom(70_001))
+    (src / "música" / "ñandú" / "canción.flac").write_bytes(os.urandom(150_000))
+    (src / ".config" / "app" / "settings.json").write_text("{}")
+    (src / "docs" / "link to readme").symlink_to("../readme.txt")
+    return src, tree_hashes(src)
+
+
 @pytest.fixture
 def tmp_dst(tmp_path):
     """Empty destination directory."""
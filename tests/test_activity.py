@@ -0,0 +1,164 @@
+"""
+Activity tracking and stall warnings.
+
+A fake ``ssh`` that hangs is put first on ``PATH`` so the worker blocks in
+a child command; the CLI must report it as a possible stall via the
+NDJSON event stream on stderr without killing it.
+"""
+
+import json
+import os
+import subprocess
+
+from conftest import KOSMOKOPY_BIN
+
+
+def _fake_slow_ssh(bin_dir, seconds):
+    """Create an ``ssh`` stand-in that sleeps, then fails to connect."""
+    bin_dir.mkdir()
+    ssh = bin_dir / "ssh"
+    ssh.write_text(f"#!/bin/sh\nsleep {seconds}\necho 'connection timed out' >&2\nexit 255\n")
+    ssh.chmod(0o755)
+    return bin_dir
+
+
+def _run_with_events(tmp_src, dst, bin_dir, stall_after):
+    env = dict(os.environ, PATH=f"{bin_dir}{os.pathsep}{os.environ['PATH']}")
+    cmd = [
+        KOSMOKOPY_BIN, "--cli",
+        "--src", str(tmp_src), "--dst", dst, "--method", "standard",
+        "--events", "--stall-after", str(stall_after),
+    ]
+    proc = subprocess.run(cmd, capture_output=True, text=True, timeout=60, env=env)
+    events = [json.loads(line) for line in proc.stderr.splitlines() if line.startswith("{")]
+    return json.loads(proc.stdout.strip()), events
+
+
+class TestStallWarning:
+
+    def test_slow_command_raises_stall_event(self, tmp_src, tmp_path):
+        """A child command running past the threshold is reported once."""
+        bin_dir = _fake_slow_ssh(tmp_path / "bin", 3)
+        result, events = _run_with_events(tmp_src, "fakehost:/tmp/kk", bin_dir, 1)
+
+        # The command was left to finish on its own.
+        assert result["status"] == "error"
+        assert "connection timed out" in result["message"]
+
+        stalls = [e for e in events if e["event"] == "stall"]
+        assert len(stalls) == 1
+        assert stalls[0]["activity"].startswith("ssh fakehost date +%s")
+        assert "ControlPath" not in stalls[0]["activity"]
+        assert stalls[0]["seconds"] >= 1
+        # The connection check runs before any file is planned.
+        assert stalls[0]["phase"] == "scanning"
+        assert stalls[0]["message"].startswith("possible stall: ssh fakehost")
+
+    def test_no_stall_event_under_threshold(self, tmp_src, tmp_path):
+        """Commands that finish before the threshold raise nothing."""
+        bin_dir = _fake_slow_ssh(tmp_path / "bin", 0)
+        result, events = _run_with_events(tmp_src, "fakehost:/tmp/kk", bin_dir, 30)
+        assert result["status"] == "error"
+        assert not [e for e in events if e["event"] == "stall"]
+
+    def test_progress_events_carry_activity(self, tmp_src, tmp_dst, tmp_path):
+        """Each NDJSON progress event names the current activity."""
+        result, events = _run_with_events(tmp_src, str(tmp_dst), tmp_path, 120)
+        assert result["status"] == "finished"
+
+        progress = [e for e in events if e["event"] == "progress"]
+        assert len(progress) == result["copied"]
+        assert progress[-1]["done"] == progress[-1]["total"]
+        assert all(e["activity"] for e in progress)
+        # Each file is verified before its progress event, which is sent
+        # once the run is back to transferring.
+        assert all(e["phase"] == "transferring" for e in progress)
+
+    def test_progress_events_count_bytes(self, tmp_src, tmp_dst, tmp_path):
+        """Progress events count bytes as well as files."""
+        result, events = _run_with_events(tmp_src, str(tmp_dst), tmp_path, 120)
+        assert result["status"] == "finished"
+
+        progress = [e for e in events if e["event"] == "progress"]
+        total = sum(f.stat().st_size for f in tmp_src.rglob("*") if f.is_file())
+        assert all(e["bytes_total"] == total for e in progress)
+        done = [e["bytes_done"] for e in progress]
+        assert done == sorted(done)
+        assert done[-1] == total
+
+    def test_progress_events_carry_the_speed(self, tmp_src, tmp_dst, tmp_path):
+        """The speed and time left ride along, in bytes when sizes are known."""
+        result, events = _run_with_events(tmp_src, str(tmp_dst), tmp_path, 120)
+        assert result["status"] == "finished"
+
+        progress = [e for e in events if e["event"] == "progress"]
+        for e in progress:
+            assert {"bytes_per_sec", "files_per_sec", "eta_secs"} <= e.keys()
+            # Null until the samples span a moment, which a small run may not.
+            assert e["bytes_per_sec"] is None or e["bytes_per_sec"] >= 0
+            assert e["files_per_sec"] is None
+
+
+class TestScanEvents:
+
+    def test_scan_ends_with_what_it_found(self, tmp_src, tmp_dst, tmp_path):
+        """The scan reports the files and bytes it found before the first copy."""
+        result, events = _run_with_events(tmp_src, str(tmp_dst), tmp_path, 120)
+        assert result["status"] == "finished"
+
+        scans = [i for i, e in enumerate(events) if e["event"] == "scan"]
+        assert scans
+        last = events[scans[-1]]
+        assert last["files_found"] == result["copied"]
+        assert last["bytes_found"] == sum(f.stat().st_size for f in tmp_src.rglob("*") if f.is_file())
+        first_progress = next(i for i, e in enumerate(events) if e["event"] == "progress")
+        assert scans[-1] < first_progress
+
+
+class TestEventsFd:
+
+    def test_events_fd_keeps_events_off_stderr(self, tmp_src, tmp_dst):
+        """With --events-fd the events go down the given pipe only."""
+        read_end, write_end = os.pipe()
+        cmd = [
+            KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst),
+            "--method", "standard", "--events-fd", str(write_end),
+        ]
+        proc = subprocess.run(cmd, capture_output=True, text=True, timeout=60, pass_fds=(write_end,))
+        os.close(write_end)
+        with os.fdopen(read_end) as pipe:
+            events = [json.loads(line) for line in pipe.read().splitlines()]
+        result = json.loads(proc.stdout.strip())
+        assert result["status"] == "finished"
+        assert "event" not in proc.stderr
+        progress = [e for e in events if e["event"] == "progress"]
+        assert len(progress) == result["copied"]
+
+    def test_progress_names_are_aliases(self, tmp_src, tmp_dst):
+        """--progress-fd sends the same records, each tagged with its type."""
+        read_end, write_end = os.pipe()
+        cmd = [
+            KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst),
+            "--method", "standard", "--progress", "--progress-fd", str(write_end),
+        ]
+        proc = subprocess.run(cmd, capture_output=True, text=True, timeout=60, pass_fds=(write_end,))
+        os.close(write_end)
+        with os.fdopen(read_end) as pipe:
+            events = [json.loads(line) for line in pipe.read().splitlines()]
+        result = json.loads(proc.stdout.strip())
+        assert all(e["type"] == e["event"] for e in events)
+        progress = [e for e in events if e["type"] == "progress"]
+        assert len(progress) == result["copied"]
+        assert {"done", "total", "file"} <= progress[-1].keys()
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst), "--progress-fd", "1"],
+            capture_output=True, text=True, timeout=60,
+        )
+        assert proc.stderr.startswith("--progress-fd 1: "), proc.stderr
+
+    def test_events_fd_must_be_open(self, tmp_src, tmp_dst):
+        for fd in ("1", "none", "999"):
+            cmd = [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst), "--events-fd", fd]
+            proc = subprocess.run(cmd, capture_output=True, text=True, timeout=60)
+            assert proc.returncode == 1
+            assert proc.stderr.startswith(f"--events-fd {fd}: "), proc.stderr
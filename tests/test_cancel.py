@@ -3,15 +3,21 @@ Cancel / graceful-shutdown tests.
 
 These tests verify that sending SIGINT (Ctrl+C) to kosmokopy --cli
 causes the transfer to stop gracefully with status "cancelled", and
-that files already copied remain intact.
+that files already copied remain intact.  A child command the worker is
+waiting on is terminated too, and a second SIGINT gives up on a worker
+that still does not answer.
 """
 
+import json
 import os
+import signal
+import subprocess
 from pathlib import Path
 
 import pytest
 
 from conftest import (
+    KOSMOKOPY_BIN,
     run_kosmokopy,
     run_kosmokopy_with_cancel,
     sha256_of_file,
@@ -197,6 +203,293 @@ class TestImmediateCancel:
         assert result.get("errors", []) == []
 
 
+# ═══════════════════════════════════════════════════════════════════════
+#  Two-phase move (--move-strategy all-then-delete)
+# ═══════════════════════════════════════════════════════════════════════
+
+
+def _two_phase_move(src, dst, interrupt_on, *extra):
+    """Run a two-phase move with NDJSON events, sending SIGINT when the
+    first event named *interrupt_on* arrives.  Returns (result, events)."""
+    cmd = [
+        KOSMOKOPY_BIN, "--cli", "--src", str(src), "--dst", str(dst),
+        "--move", "--move-strategy", "all-then-delete", "--events", *extra,
+    ]
+    proc = subprocess.Popen(cmd, stdout=subprocess.PIPE, stderr=subprocess.PIPE, text=True)
+    events = []
+    for line in proc.stderr:
+        if not line.startswith("{"):
+            continue
+        event = json.loads(line)
+        events.append(event)
+        if event["event"] == interrupt_on and proc.poll() is None:
+            proc.send_signal(signal.SIGINT)
+            interrupt_on = None
+    stdout = proc.stdout.read()
+    proc.wait(timeout=30)
+    return json.loads(stdout.strip()), events
+
+
+@pytest.fixture
+def move_tree(tmp_path):
+    """Source tree for two-phase moves, with each file's expected content."""
+    src = tmp_path / "library"
+    src.mkdir()
+    contents = {}
+    for i in range(300):
+        data = os.urandom(16 * 1024)
+        (src / f"track_{i:04d}.bin").write_bytes(data)
+        contents[f"track_{i:04d}.bin"] = data
+    return src, contents
+
+
+class TestTwoPhaseMove:
+
+    def test_sources_deleted_only_after_everything_copied(self, move_tree, tmp_path):
+        src, contents = move_tree
+        dst = tmp_path / "dst"
+        result = run_kosmokopy(src=src, dst=dst, move=True, move_strategy="all-then-delete")
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["deletion"] == {"ran": True, "deleted": 300, "failed": 0}
+        assert list(src.iterdir()) == []
+        for name, data in contents.items():
+            assert (dst / "library" / name).read_bytes() == data
+
+    def test_copy_error_skips_deletion(self, move_tree, tmp_path):
+        """One file that cannot be copied keeps every source in place."""
+        src, contents = move_tree
+        dst = tmp_path / "dst"
+        (dst / "library" / "track_0150.bin").mkdir(parents=True)
+        (dst / "library" / "track_0150.bin" / "blocker").write_text("x")
+        result = run_kosmokopy(
+            src=src, dst=dst, move=True, move_strategy="all-then-delete", conflict="overwrite",
+        )
+        assert result["errors"]
+        assert result["deletion"] == {"ran": False}
+        for name, data in contents.items():
+            assert (src / name).read_bytes() == data
+
+    def test_deletions_are_reported_as_finalizing(self, move_tree, tmp_path):
+        """Progress through the deletions comes after every copy, as finalizing."""
+        src, _ = move_tree
+        dst = tmp_path / "dst"
+        result, events = _two_phase_move(src, dst, None)
+        assert result["status"] == "finished"
+        phases = [e["phase"] for e in events if e["event"] == "progress"]
+        assert phases == ["transferring"] * 300 + ["finalizing"] * 300
+
+    def test_cancel_during_copy_keeps_every_source(self, move_tree, tmp_path):
+        src, contents = move_tree
+        dst = tmp_path / "dst"
+        result, events = _two_phase_move(src, dst, "progress")
+        assert result["errors"] == []
+        if result["deletion"] == {"ran": False}:
+            # Cancelled before the deletion phase: nothing was removed.
+            assert result["status"] == "cancelled"
+            assert not [e for e in events if e["event"] == "phase"]
+            for name, data in contents.items():
+                assert (src / name).read_bytes() == data
+        else:
+            # The copy finished before the signal landed, so the copy is whole.
+            for name, data in contents.items():
+                assert (dst / "library" / name).read_bytes() == data
+
+    def test_ctrl_c_keeping_unverified_files_deletes_no_source(self, move_tree, tmp_path):
+        """With --on-cancel-during-verify keep, Ctrl+C mid-copy either skips
+        the remaining verification or cancels; no source is deleted either way."""
+        src, contents = move_tree
+        dst = tmp_path / "dst"
+        result, _ = _two_phase_move(src, dst, "progress", "--on-cancel-during-verify", "keep")
+        assert result["errors"] == []
+        if result["status"] == "finished":
+            unverified = result["unverified"]
+            assert unverified
+            assert result["copied"] + len(unverified) == 300
+            assert result["deletion"] == {"ran": False, "unverified": len(unverified)}
+        else:
+            assert result["status"] == "cancelled"
+            assert result["deletion"] == {"ran": False}
+        for name, data in contents.items():
+            assert (src / name).read_bytes() == data
+
+    def test_bad_cancel_policy_is_rejected(self, tmp_src, tmp_path):
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_path / "dst"),
+             "--on-cancel-during-verify", "maybe"],
+            capture_output=True, text=True, timeout=30,
+        )
+        assert proc.returncode == 1
+        assert "keep or discard" in proc.stderr
+
+    def test_cancel_between_phases_leaves_complete_copy(self, move_tree, tmp_path):
+        src, contents = move_tree
+        dst = tmp_path / "dst"
+        result, events = _two_phase_move(src, dst, "phase")
+        assert [e for e in events if e["event"] == "phase"] == [
+            {"type": "phase", "event": "phase", "phase": "delete", "total": 300}
+        ]
+        assert result["errors"] == []
+        for name, data in contents.items():
+            assert (dst / "library" / name).read_bytes() == data
+        remaining = [name for name in contents if (src / name).exists()]
+        deletion = result["deletion"]
+        if result["status"] == "cancelled":
+            assert deletion["cancelled"] is True
+            assert deletion["kept"] == len(remaining)
+            assert deletion["deleted"] + deletion["kept"] == 300
+        else:
+            assert deletion == {"ran": True, "deleted": 300, "failed": 0}
+            assert remaining == []
+        # Whatever is left at the source is untouched.
+        for name in remaining:
+            assert (src / name).read_bytes() == contents[name]
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Cancel while blocked in a child command
+# ═══════════════════════════════════════════════════════════════════════
+
+
+def _fake_hanging_rsync(bin_dir, ignore_term):
+    """An ``rsync`` stand-in that answers ``--version`` and otherwise marks
+    that it started and hangs, optionally ignoring SIGTERM.  It hangs in
+    its own process, so that stopping it also closes the stderr pipe the
+    worker reads."""
+    bin_dir.mkdir()
+    started = bin_dir / "started"
+    trap = "trap '' TERM INT\n" if ignore_term else ""
+    rsync = bin_dir / "rsync"
+    rsync.write_text(
+        "#!/bin/sh\n"
+        'if [ "$1" = --version ]; then echo "rsync  version 3.2.7  protocol version 31"; exit 0; fi\n'
+        f"{trap}touch {started}\nexec sleep 20\n"
+    )
+    rsync.chmod(0o755)
+    return bin_dir, started
+
+
+def _interrupt_blocked_copy(tmp_path, ignore_term, interrupts):
+    """Copy a large file through the hanging rsync and send SIGINT
+    *interrupts* times once it is running.  Returns (result, returncode,
+    seconds from the first SIGINT to exit)."""
+    import time
+
+    src = tmp_path / "big"
+    src.mkdir()
+    with open(src / "large.bin", "wb") as f:
+        f.write(os.urandom(8 * 1024 * 1024))
+    bin_dir, started = _fake_hanging_rsync(tmp_path / "bin", ignore_term)
+    env = dict(os.environ, PATH=f"{bin_dir}{os.pathsep}{os.environ['PATH']}")
+    cmd = [
+        KOSMOKOPY_BIN, "--cli", "--src", str(src), "--dst", str(tmp_path / "dst"),
+        "--method", "rsync",
+    ]
+    proc = subprocess.Popen(cmd, stdout=subprocess.PIPE, stderr=subprocess.PIPE, text=True, env=env)
+    deadline = time.monotonic() + 10
+    while not started.exists() and time.monotonic() < deadline:
+        time.sleep(0.05)
+    assert started.exists(), "rsync was never started"
+    sent = time.monotonic()
+    for _ in range(interrupts):
+        proc.send_signal(signal.SIGINT)
+        time.sleep(0.2)
+    stdout, _ = proc.communicate(timeout=15)
+    return json.loads(stdout.strip()), proc.returncode, time.monotonic() - sent
+
+
+class TestCancelBlockedChild:
+
+    def test_single_ctrl_c_stops_the_child(self, tmp_path):
+        """The running rsync is terminated, so the normal result comes back
+        long before the child would have finished."""
+        result, code, elapsed = _interrupt_blocked_copy(tmp_path, False, 1)
+        assert result["status"] == "cancelled"
+        assert result["stage"] == "transferring"
+        assert result["copied"] == 0
+        assert code == 0
+        assert elapsed < 5
+
+    def test_second_ctrl_c_gives_up_on_a_stuck_child(self, tmp_path):
+        """A child that ignores SIGTERM keeps the worker blocked; the second
+        Ctrl+C prints a snapshot after 5 seconds and exits 3."""
+        result, code, elapsed = _interrupt_blocked_copy(tmp_path, True, 2)
+        assert code == 3
+        assert result["status"] == "cancelled-forced"
+        assert result["stage"] == "transferring"
+        assert result["done"] == 0
+        assert result["total"] == 1
+        assert result["file"] is None
+        assert result["activity"].startswith("rsync -a --checksum")
+        assert "may be incomplete" in result["message"]
+        assert 5 <= elapsed < 12
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Where the cancel took effect
+# ═══════════════════════════════════════════════════════════════════════
+
+
+def _fake_ssh_hanging_on(bin_dir, marker):
+    """An ``ssh`` stand-in that answers the connection check, marks that
+    it started and hangs on a remote command containing *marker*, and
+    quietly succeeds at anything else."""
+    bin_dir.mkdir()
+    started = bin_dir / "started"
+    ssh = bin_dir / "ssh"
+    ssh.write_text(
+        "#!/bin/sh\n"
+        'for a; do cmd="$a"; done\n'
+        'case "$cmd" in\n'
+        '  "date +%s") date +%s ;;\n'
+        f"  *{_sq(marker)}*) touch {started}; exec sleep 20 ;;\n"
+        "esac\n"
+        "exit 0\n"
+    )
+    ssh.chmod(0o755)
+    return bin_dir, started
+
+
+def _cancel_once_started(src, dst, bin_dir, started):
+    """Run a transfer and send SIGINT once the fake ssh has started
+    hanging.  Returns (result, returncode)."""
+    import time
+
+    env = dict(os.environ, PATH=f"{bin_dir}{os.pathsep}{os.environ['PATH']}")
+    cmd = [KOSMOKOPY_BIN, "--cli", "--src", str(src), "--dst", str(dst)]
+    proc = subprocess.Popen(cmd, stdout=subprocess.PIPE, stderr=subprocess.PIPE, text=True, env=env)
+    deadline = time.monotonic() + 10
+    while not started.exists() and time.monotonic() < deadline:
+        time.sleep(0.05)
+    assert started.exists(), "the fake ssh never got to the command"
+    proc.send_signal(signal.SIGINT)
+    stdout, _ = proc.communicate(timeout=15)
+    return json.loads(stdout.strip()), proc.returncode
+
+
+class TestCancelStage:
+
+    def test_cancel_while_listing_a_remote_source(self, tmp_path):
+        bin_dir, started = _fake_ssh_hanging_on(tmp_path / "bin", "find ")
+        result, code = _cancel_once_started("fakehost:/data", tmp_path / "dst", bin_dir, started)
+        assert result["status"] == "cancelled"
+        assert result["stage"] == "scanning"
+        assert result["copied"] == 0
+        assert result["summary"]["stage"] == "scanning"
+        assert result["summary"]["text"].startswith("Cancelled during scanning — no files were transferred")
+        assert code == 0
+
+    def test_cancel_while_checking_destination_folders(self, tmp_src, tmp_path):
+        bin_dir, started = _fake_ssh_hanging_on(tmp_path / "bin", "while [ ! -d")
+        result, code = _cancel_once_started(tmp_src, "fakehost:/backup", bin_dir, started)
+        assert result["status"] == "cancelled"
+        assert result["stage"] == "preflight"
+        assert result["copied"] == 0
+        assert result["errors"] == []
+        assert result["summary"]["text"].startswith("Cancelled during preflight — no files were transferred")
+        assert code == 0
+
+
 # ═══════════════════════════════════════════════════════════════════════
 #  Normal completion (no cancel) still works
 # ═══════════════════════════════════════════════════════════════════════
@@ -0,0 +1,125 @@
+"""
+Man page and shell completion generation.
+
+``--generate-man`` and ``--generate-completions`` are built from the same
+option table the ``--cli`` parser uses; every public flag must show up in
+both.  Starting without ``--cli`` must say how to run headless rather than
+fail inside GTK.  ``--capabilities`` describes the same table as JSON.
+"""
+
+import json
+import os
+import shutil
+import subprocess
+import sys
+
+import pytest
+
+from conftest import KOSMOKOPY_BIN
+
+PUBLIC_FLAGS = [
+    "--src", "--dst", "--src-files", "--src-files-from", "--profile", "--move",
+    "--move-strategy", "--remove-source-root",
+    "--conflict", "--strip-spaces", "--rename-rule", "--rename-preview", "--chmod-dirs", "--preserve", "--mode", "--files-keep-parents",
+    "--method", "--parallel", "--retries", "--bwlimit", "--compress", "--exclude", "--exclude-from",
+    "--include", "--only-user", "--only-group", "--exclude-user", "--exclude-group", "--symlinks",
+    "--failed-files-out", "--detail-report", "--null", "--events", "--events-fd", "--progress", "--progress-fd",
+    "--stall-after", "--move-fallback", "--on-quota", "--on-unwritable", "--stale-artifacts", "--on-collision", "--on-cancel-during-verify",
+    "--duplicate-window", "--refuse-duplicate-runs", "--case-insensitive-paths", "--no-source-config", "--verify", "--hash-algo", "--hash-cache",
+    "--paranoid", "--verify-sample", "--verify-sample-min-size", "--verify-sample-seed",
+    "--low-priority", "--ignore-free-space", "--timestamps", "--max-clock-skew", "--accept-new-hostkeys", "--relay", "--file-timeout", "--ssh-control-dir", "--recheck-conflicts",
+    "--no-recheck-rename", "--scan-limit", "--scan-time-limit", "--scan-limit-action",
+    "--no-coalesce-errors", "--detail-limit", "--print-commands", "--list-only", "--fail-if-empty", "--dry-run",
+    "--retain", "--retain-dry-run", "--retain-to-trash",
+]
+
+
+def _generate(*args):
+    return subprocess.run(
+        [KOSMOKOPY_BIN, "--cli", *args], capture_output=True, text=True, timeout=30
+    )
+
+
+class TestGenerators:
+
+    def test_man_page_lists_every_flag(self):
+        proc = _generate("--generate-man")
+        assert proc.returncode == 0, proc.stderr
+        assert proc.stdout.startswith(".TH KOSMOKOPY 1")
+        assert ".SH \"JSON OUTPUT\"" in proc.stdout
+        for flag in PUBLIC_FLAGS:
+            assert flag.replace("-", "\\-") in proc.stdout, flag
+        assert "generate" not in proc.stdout
+
+    @pytest.mark.parametrize("shell", ["bash", "zsh", "fish"])
+    def test_completions_list_every_flag(self, shell):
+        proc = _generate("--generate-completions", shell)
+        assert proc.returncode == 0, proc.stderr
+        for flag in PUBLIC_FLAGS:
+            needle = f"-l {flag[2:]} " if shell == "fish" else flag
+            assert needle in proc.stdout, flag
+
+    def test_unknown_shell_is_rejected(self):
+        proc = _generate("--generate-completions", "tcsh")
+        assert proc.returncode == 1
+        assert proc.stdout == ""
+
+
+class TestCapabilities:
+
+    def _capabilities(self, path):
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--capabilities"], capture_output=True, text=True,
+            timeout=30, env={**os.environ, "PATH": path},
+        )
+        assert proc.returncode == 0, proc.stderr
+        return json.loads(proc.stdout)
+
+    def test_every_flag_is_listed_once(self):
+        caps = self._capabilities(os.environ.get("PATH", ""))
+        names = [o["name"] for group in caps["features"].values() for o in group]
+        for flag in PUBLIC_FLAGS + ["--capabilities", "--generate-man"]:
+            assert names.count(flag) == 1, flag
+        assert len(names) == len(set(names))
+        assert caps["hash_algorithms"] == ["sha256", "blake3", "xxh128"]
+        assert isinstance(caps["schema_version"], int)
+        assert "status" in caps["result_fields"]
+        conflict = next(o for o in caps["features"]["transfer"] if o["name"] == "--conflict")
+        assert conflict["choices"] == ["skip", "overwrite", "rename", "newer"]
+        rsync = next(t for t in caps["tools"] if t["name"] == "rsync")
+        if shutil.which("rsync"):
+            assert rsync["path"] and rsync["version"]
+
+    def test_missing_tools_degrade(self, tmp_path):
+        caps = self._capabilities(str(tmp_path))
+        transports = {t["name"]: t["available"] for t in caps["transports"]}
+        assert transports == {"local": True, "scp": False, "rsync": False}
+        for tool in caps["tools"]:
+            assert tool["path"] is None and tool["version"] is None, tool
+
+
+def _start_gui(*args):
+    """Run the binary without ``--cli`` and without a display."""
+    env = {k: v for k, v in os.environ.items() if k not in ("DISPLAY", "WAYLAND_DISPLAY", "GDK_BACKEND")}
+    return subprocess.run(
+        [KOSMOKOPY_BIN, *args], capture_output=True, text=True, timeout=30, env=env
+    )
+
+
+class TestStartingWithoutCli:
+
+    @pytest.mark.skipif(sys.platform == "darwin", reason="macOS needs no display variable")
+    def test_no_display_points_to_cli_mode(self):
+        proc = _start_gui()
+        assert proc.returncode == 4
+        assert "needs a display, but DISPLAY and WAYLAND_DISPLAY are not set" in proc.stderr
+        assert "kosmokopy --cli --src <path|host:path> --dst <path|host:path>" in proc.stderr
+
+    def test_cli_options_without_cli_name_the_command(self, tmp_path):
+        proc = _start_gui("--src", str(tmp_path / "My Photos"), "--dst", "host:/backup", "--move")
+        assert proc.returncode == 1
+        assert proc.stdout == ""
+        expected = "{} --cli --src '{}' --dst host:/backup --move".format(KOSMOKOPY_BIN, tmp_path / "My Photos")
+        assert expected in proc.stderr
+        # Nothing was moved or created.
+        assert not (tmp_path / "My Photos").exists()
@@ -1,11 +1,12 @@
 """
-Conflict-mode tests — Skip, Overwrite, Rename.
+Conflict-mode tests — Skip, Overwrite, Rename, Newest wins.
 
 Operations are performed by invoking ``kosmokopy --cli``.
 Verification is done in Python.
 """
 
 import os
+import time
 from pathlib import Path
 
 import pytest
@@ -57,6 +58,21 @@ class TestConflictSkipLocal:
         # Source deleted (identical at dest triggers delete-source)
         assert not src_file.exists()
 
+    def test_skipped_names_with_quotes_and_newlines(self, tmp_path, tmp_dst):
+        """Awkward names in the skipped list keep the result valid JSON."""
+        src = tmp_path / "odd"
+        src.mkdir()
+        names = ['say "hi".txt', "back\\slash.txt", "two\nlines.txt", "tab\there.txt"]
+        root = tmp_dst / src.name
+        root.mkdir()
+        for name in names:
+            (src / name).write_text("new\n")
+            (root / name).write_text("old\n")
+
+        result = run_kosmokopy(src=src, dst=tmp_dst, conflict="skip")
+        assert result["status"] == "finished", result
+        assert sorted(s.rsplit("/", 1)[-1].split(": ")[0] for s in result["skipped"]) == sorted(names)
+
 
 # ═══════════════════════════════════════════════════════════════════════
 #  Local conflict: Overwrite
@@ -208,6 +224,37 @@ class TestConflictRenameLocalRsync:
         assert (root / "hello_1.txt").read_text() == original_content
 
 
+# ═══════════════════════════════════════════════════════════════════════
+#  Local conflict: Newest wins
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestConflictNewerLocal:
+
+    def test_newer_source_replaces_older_destination(self, tmp_src, tmp_dst):
+        root = tmp_dst / tmp_src.name
+        root.mkdir(parents=True, exist_ok=True)
+        (root / "hello.txt").write_text("OLD CONTENT\n")
+        os.utime(root / "hello.txt", (1_600_000_000, 1_600_000_000))
+
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, conflict="newer")
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert (root / "hello.txt").read_text() == (tmp_src / "hello.txt").read_text()
+
+    def test_newer_destination_is_kept(self, tmp_src, tmp_dst):
+        root = tmp_dst / tmp_src.name
+        root.mkdir(parents=True, exist_ok=True)
+        (root / "hello.txt").write_text("EDITED AT DESTINATION\n")
+        os.utime(tmp_src / "hello.txt", (1_600_000_000, 1_600_000_000))
+
+        for method in ("standard", "rsync"):
+            result = run_kosmokopy(src=tmp_src, dst=tmp_dst, conflict="newer", method=method)
+            assert result["status"] == "finished"
+            assert any(s.endswith("hello.txt: destination is newer") for s in result["skipped"]), method
+            assert (root / "hello.txt").read_text() == "EDITED AT DESTINATION\n"
+
+
 # ═══════════════════════════════════════════════════════════════════════
 #  Remote conflict modes (SCP)
 # ═══════════════════════════════════════════════════════════════════════
@@ -348,3 +395,30 @@ class TestConflictRenameRemoteRsync:
         names = {Path(f).name for f in files}
         assert "hello.txt" in names
         assert "hello_1.txt" in names
+
+
+@requires_remote
+class TestConflictNewerRemote:
+
+    def test_newer_keeps_the_newer_side(self, tmp_path, remote_dest):
+        host, rdir = remote_dest
+        src = tmp_path / "src"
+        src.mkdir()
+        (src / "file.txt").write_text("FIRST\n")
+        run_kosmokopy(src=src, dst="{}:{}".format(host, rdir))
+
+        # An older source leaves the remote copy alone ...
+        (src / "file.txt").write_text("OLDER\n")
+        os.utime(src / "file.txt", (1_600_000_000, 1_600_000_000))
+        result = run_kosmokopy(src=src, dst="{}:{}".format(host, rdir), conflict="newer")
+        assert result["status"] == "finished"
+        assert any(s.endswith("file.txt: destination is newer") for s in result["skipped"])
+        assert remote_read(host, rdir + "/src/file.txt") == b"FIRST\n"
+
+        # ... and a newer one replaces it.
+        (src / "file.txt").write_text("NEWER\n")
+        future = time.time() + 3600
+        os.utime(src / "file.txt", (future, future))
+        result = run_kosmokopy(src=src, dst="{}:{}".format(host, rdir), conflict="newer")
+        assert result["copied"] == 1
+        assert remote_read(host, rdir + "/src/file.txt") == b"NEWER\n"
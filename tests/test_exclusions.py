@@ -209,3 +209,268 @@ class TestCombinedExclusions:
         assert "data.tmp" not in dst_names      # *.tmp file excluded
         assert "keep.txt" in dst_names
         assert "doc.txt" in dst_names
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Include-only patterns (quick filters)
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestIncludePatterns:
+
+    def test_include_matches_extension_case_insensitively(self, tmp_src_with_exclusions, tmp_dst):
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst, include=["*.jpg"],
+        )
+        assert result["status"] == "finished"
+        assert result["copied"] == 2
+
+        dst_names = {f.name for f in tmp_dst.rglob("*") if f.is_file()}
+        assert dst_names == {"PHOTO.JPG", "snapshot.jpg"}
+
+    def test_include_patterns_union(self, tmp_src_with_exclusions, tmp_dst):
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst, include=["*.jpg", "*.txt"],
+        )
+        assert result["status"] == "finished"
+
+        dst_names = {f.name for f in tmp_dst.rglob("*") if f.is_file()}
+        assert dst_names == {"PHOTO.JPG", "snapshot.jpg", "keep.txt", "doc.txt"}
+
+    def test_include_layered_on_exclusions(self, tmp_src_with_exclusions, tmp_dst):
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst,
+            include=["*.txt"], exclude=["/important"],
+        )
+        assert result["status"] == "finished"
+        assert result["excluded_dirs"] == 1
+
+        dst_names = {f.name for f in tmp_dst.rglob("*") if f.is_file()}
+        assert dst_names == {"keep.txt"}
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Path patterns (patterns with a slash inside)
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestPathPatterns:
+
+    @staticmethod
+    def _tree(tmp_path):
+        src = tmp_path / "project"
+        for rel in ["node_modules/left-pad/index.js", "web/node_modules/react/index.js",
+                    "web/app.js", "docs/generated/api.html", "docs/guide.md",
+                    "site/docs/generated/page.html", "logs/2024-03/app.log",
+                    "logs/2024-11/app.log", "logs/2024-03/keep.txt"]:
+            (src / rel).parent.mkdir(parents=True, exist_ok=True)
+            (src / rel).write_text(rel + "\n")
+        return src
+
+    @staticmethod
+    def _copied(dst):
+        root = dst / "project"
+        return {f.relative_to(root).as_posix() for f in root.rglob("*") if f.is_file()}
+
+    def test_double_star_excludes_the_folder_at_any_depth(self, tmp_path, tmp_dst):
+        src = self._tree(tmp_path)
+        result = run_kosmokopy(src=src, dst=tmp_dst, exclude=["**/node_modules/**"])
+        assert result["status"] == "finished"
+        assert result["excluded_dirs"] == 2
+
+        copied = self._copied(tmp_dst)
+        assert not any("node_modules" in rel for rel in copied)
+        assert "web/app.js" in copied
+
+    def test_leading_slash_anchors_at_the_source_root(self, tmp_path, tmp_dst):
+        src = self._tree(tmp_path)
+        result = run_kosmokopy(src=src, dst=tmp_dst, exclude=["/docs/generated"])
+        assert result["status"] == "finished"
+        assert result["excluded_dirs"] == 1
+
+        copied = self._copied(tmp_dst)
+        assert "docs/generated/api.html" not in copied
+        # A folder of the same name further down is not the one meant.
+        assert "site/docs/generated/page.html" in copied
+        assert "docs/guide.md" in copied
+
+    def test_star_and_question_mark_in_one_pattern(self, tmp_path, tmp_dst):
+        src = self._tree(tmp_path)
+        result = run_kosmokopy(src=src, dst=tmp_dst, exclude=["logs/2024-0?/*.log"])
+        assert result["status"] == "finished"
+        assert result["excluded_files"] == 1
+
+        copied = self._copied(tmp_dst)
+        assert "logs/2024-03/app.log" not in copied
+        assert {"logs/2024-11/app.log", "logs/2024-03/keep.txt"} <= copied
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Owner filters
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestOwnerFilters:
+    """Fixture files belong to the user running the tests."""
+
+    def test_only_own_files_keeps_everything(self, tmp_src_with_exclusions, tmp_dst):
+        total = sum(1 for f in tmp_src_with_exclusions.rglob("*") if f.is_file())
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst,
+            only_user=[os.getuid()], only_group=[os.getgid()],
+        )
+        assert result["status"] == "finished"
+        assert result["copied"] == total
+        assert result["filtered_by_owner"] == 0
+
+    def test_excluding_own_files_copies_nothing(self, tmp_src_with_exclusions, tmp_dst):
+        total = sum(1 for f in tmp_src_with_exclusions.rglob("*") if f.is_file())
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst, exclude_user=[os.getuid()],
+        )
+        assert result["status"] == "finished"
+        assert result["copied"] == 0
+        assert result["filtered_by_owner"] == total
+        assert result["excluded_files"] == 0
+
+    def test_other_owner_is_filtered(self, tmp_src_with_exclusions, tmp_dst):
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst, only_user=[os.getuid() + 4242],
+        )
+        assert result["status"] == "finished"
+        assert result["copied"] == 0
+        assert result["filtered_by_owner"] > 0
+
+    def test_unknown_name_fails_before_copying(self, tmp_src_with_exclusions, tmp_dst):
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst, only_group=["no-such-group-kosmokopy"],
+        )
+        assert result["status"] == "error"
+        assert "No group named 'no-such-group-kosmokopy'" in result["message"]
+        assert not any(tmp_dst.iterdir())
+
+    def test_no_filter_no_field(self, tmp_src_with_exclusions, tmp_dst):
+        result = run_kosmokopy(src=tmp_src_with_exclusions, dst=tmp_dst)
+        assert "filtered_by_owner" not in result
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Pattern normalization at parse time
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestPatternNormalization:
+
+    def test_trailing_slash_and_dot_slash_still_match(self, tmp_src_with_exclusions, tmp_dst):
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst, exclude=["/cache/", "./important/"],
+        )
+        assert result["status"] == "finished"
+        assert result["excluded_dirs"] == 2
+
+        dst_names = {f.name for f in tmp_dst.rglob("*") if f.is_file()}
+        assert "cached.dat" not in dst_names
+        assert "keep.txt" in dst_names
+
+    def test_duplicates_are_merged(self, tmp_src_with_exclusions, tmp_dst):
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst, exclude=["/cache", "/cache/", "cache/"],
+        )
+        assert result["status"] == "finished"
+        assert result["excluded_dirs"] == 1
+
+    @pytest.mark.parametrize("pattern", ["docs/../cache", "", "  ", "/"])
+    def test_unmatchable_patterns_fail_before_copying(self, tmp_src_with_exclusions, tmp_dst, pattern):
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst, exclude=[pattern],
+        )
+        assert result["status"] == "error"
+        assert result["message"].startswith("exit code 1: --exclude")
+        assert not any(tmp_dst.iterdir())
+
+    def test_include_path_names_the_pattern_to_use(self, tmp_src_with_exclusions, tmp_dst):
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst, include=["photos/*.jpg"],
+        )
+        assert "is a path" in result["message"]
+        assert "use '*.jpg' instead" in result["message"]
+
+
+class TestExcludeFrom:
+
+    def test_patterns_come_from_the_file(self, tmp_src_with_exclusions, tmp_dst, tmp_path):
+        junk = tmp_path / "junk.txt"
+        junk.write_text("# build junk\n\n*.tmp\n/cache\n~skip_*\n")
+        result = run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst, exclude_from=[junk, junk], exclude=["/cache"],
+        )
+        assert result["status"] == "finished"
+        assert (result["excluded_files"], result["excluded_dirs"]) == (2, 1)
+
+        dst_names = {f.name for f in tmp_dst.rglob("*") if f.is_file()}
+        assert not dst_names & {"data.tmp", "skip_me.log", "cached.dat"}
+        assert "keep.txt" in dst_names
+
+    def test_a_bad_line_fails_before_copying(self, tmp_src_with_exclusions, tmp_dst, tmp_path):
+        junk = tmp_path / "junk.txt"
+        junk.write_text("*.tmp\n../outside/x\n")
+        result = run_kosmokopy(src=tmp_src_with_exclusions, dst=tmp_dst, exclude_from=[junk])
+        assert result["status"] == "error"
+        assert "line 2:" in result["message"]
+        assert not any(tmp_dst.iterdir())
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Per-source options (.kosmokopy.toml)
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestSourceConfig:
+
+    @staticmethod
+    def _source_config(src, text):
+        (src / ".kosmokopy.toml").write_text(text)
+
+    def test_source_config_adds_exclusions(self, tmp_src_with_exclusions, tmp_dst):
+        self._source_config(tmp_src_with_exclusions, 'exclude = ["cache/"]\n')
+        result = run_kosmokopy(src=tmp_src_with_exclusions, dst=tmp_dst, exclude=["/important"])
+        assert result["status"] == "finished"
+        assert result["excluded_dirs"] == 2
+        assert result["source_config"] == "options adjusted by source config: +1 exclusion(s)"
+
+        dst_names = {f.name for f in tmp_dst.rglob("*") if f.is_file()}
+        assert "cached.dat" not in dst_names
+        assert "keep.txt" in dst_names
+
+    def test_flags_beat_the_source_config(self, tmp_path):
+        src = tmp_path / "src"
+        dst = tmp_path / "dst"
+        src.mkdir()
+        (dst / "src").mkdir(parents=True)
+        (src / "a.txt").write_text("new")
+        (dst / "src" / "a.txt").write_text("old")
+        self._source_config(src, 'conflict = "overwrite"\n')
+
+        result = run_kosmokopy(src=src, dst=dst, conflict="skip")
+        assert result["status"] == "finished"
+        assert (dst / "src" / "a.txt").read_text() == "old"
+        assert "source_config" not in result
+
+        result = run_kosmokopy(src=src, dst=dst)
+        assert result["source_config"] == "options adjusted by source config: conflict=overwrite"
+        assert (dst / "src" / "a.txt").read_text() == "new"
+
+    def test_unsafe_keys_are_ignored(self, tmp_src_with_exclusions, tmp_dst):
+        self._source_config(tmp_src_with_exclusions, "move = true\nmirror = true\n")
+        result = run_kosmokopy(src=tmp_src_with_exclusions, dst=tmp_dst)
+        assert result["status"] == "finished"
+        assert result["source_config"] == "source config ignored: move (not allowed); mirror (not allowed)"
+        # Copied, not moved.
+        assert (tmp_src_with_exclusions / "keep.txt").exists()
+
+    def test_no_source_config(self, tmp_src_with_exclusions, tmp_dst):
+        self._source_config(tmp_src_with_exclusions, 'exclude = ["cache/"]\n')
+        result = run_kosmokopy(src=tmp_src_with_exclusions, dst=tmp_dst, no_source_config=True)
+        assert result["status"] == "finished"
+        assert "source_config" not in result
+        assert "cached.dat" in {f.name for f in tmp_dst.rglob("*") if f.is_file()}
@@ -0,0 +1,60 @@
+"""
+Run history and duplicate-run detection.
+
+Each test gets its own ``KOSMOKOPY_HOME`` so only its own runs are in
+the history it checks against.
+"""
+
+from conftest import run_kosmokopy
+
+
+def _home(tmp_path):
+    return {"KOSMOKOPY_HOME": str(tmp_path / "kk")}
+
+
+def _duplicate_warnings(result):
+    return [w for w in result.get("warnings", []) if "identical transfer" in w]
+
+
+class TestDuplicateRuns:
+
+    def test_repeat_warns_and_still_runs(self, tmp_src, tmp_dst, tmp_path):
+        env = _home(tmp_path)
+        first = run_kosmokopy(src=tmp_src, dst=tmp_dst, env=env)
+        assert first["status"] == "finished"
+        assert _duplicate_warnings(first) == []
+
+        # Written differently, but the same transfer.
+        again = run_kosmokopy(src=f"{tmp_src}/", dst=f"{tmp_dst}//", env=env)
+        assert again["status"] == "finished"
+        [warning] = _duplicate_warnings(again)
+        assert f"({first['copied']} files copied)" in warning
+
+    def test_refuse_duplicate_runs(self, tmp_src, tmp_dst, tmp_path):
+        env = _home(tmp_path)
+        assert run_kosmokopy(src=tmp_src, dst=tmp_dst, env=env)["status"] == "finished"
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, refuse_duplicate_runs=True, env=env)
+        assert result["status"] == "error"
+        assert result["kind"] == "duplicate-run"
+
+    def test_different_options_are_not_duplicates(self, tmp_src, tmp_dst, tmp_path):
+        env = _home(tmp_path)
+        assert run_kosmokopy(src=tmp_src, dst=tmp_dst, env=env)["status"] == "finished"
+        result = run_kosmokopy(
+            src=tmp_src, dst=tmp_dst, conflict="overwrite", refuse_duplicate_runs=True, env=env,
+        )
+        assert result["status"] == "finished"
+        assert _duplicate_warnings(result) == []
+
+    def test_zero_window_turns_the_check_off(self, tmp_src, tmp_dst, tmp_path):
+        env = _home(tmp_path)
+        assert run_kosmokopy(src=tmp_src, dst=tmp_dst, env=env)["status"] == "finished"
+        result = run_kosmokopy(
+            src=tmp_src, dst=tmp_dst, duplicate_window=0, refuse_duplicate_runs=True, env=env,
+        )
+        assert result["status"] == "finished"
+
+    def test_bad_window_is_rejected(self, tmp_src, tmp_dst, tmp_path):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, duplicate_window="soon", env=_home(tmp_path))
+        assert result["status"] == "error"
+        assert "--duplicate-window" in result["message"]
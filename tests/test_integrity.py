@@ -9,6 +9,7 @@ Includes *negative* (corruption) tests that deliberately tamper with a
 copied file and confirm that our verification helpers detect the change.
 """
 
+import csv
 import hashlib
 import os
 import subprocess
@@ -110,6 +111,112 @@ class TestLocalRsyncIntegrity:
         assert sha256_of_file(dst / "src" / "big.bin") == expected
 
 
+# ═══════════════════════════════════════════════════════════════════════
+#  Hash cache — unchanged files skipped without being read
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestHashCache:
+
+    def test_cold_then_warm_run(self, tmp_src, tmp_dst, tmp_path):
+        cache = tmp_path / "hashes"
+        cold = run_kosmokopy(src=tmp_src, dst=tmp_dst, hash_cache=cache)
+        assert cold["status"] == "finished"
+        assert cold["copied"] == 6
+        assert cold["hash_cache"] == {"hits": 0, "misses": 0}
+        assert cache.read_text().startswith("kosmokopy-hash-cache 1 ")
+
+        warm = run_kosmokopy(src=tmp_src, dst=tmp_dst, hash_cache=cache)
+        assert warm["copied"] == 0
+        assert len(warm["skipped"]) == 6
+        assert all(s.endswith("identical at destination") for s in warm["skipped"])
+        assert warm["hash_cache"] == {"hits": 6, "misses": 0}
+
+    def test_changed_file_is_compared_again(self, tmp_src, tmp_dst, tmp_path):
+        cache = tmp_path / "hashes"
+        run_kosmokopy(src=tmp_src, dst=tmp_dst, hash_cache=cache)
+        (tmp_src / "hello.txt").write_text("Hello, Moon!!\n")
+
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, hash_cache=cache)
+        assert result["hash_cache"] == {"hits": 5, "misses": 1}
+        assert any("hello.txt: different version exists" in s for s in result["skipped"])
+
+    def test_paranoid_catches_change_hidden_from_the_cache(self, tmp_src, tmp_dst, tmp_path):
+        """Rewritten in place with size and mtime restored, a file looks
+        unchanged to the cache; --paranoid compares it anyway."""
+        cache = tmp_path / "hashes"
+        run_kosmokopy(src=tmp_src, dst=tmp_dst, hash_cache=cache)
+        target = tmp_dst / tmp_src.name / "notes.md"
+        before = target.stat()
+        with open(target, "r+b") as f:
+            f.write(b"X")
+        os.utime(target, ns=(before.st_atime_ns, before.st_mtime_ns))
+
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, hash_cache=cache, paranoid=True)
+        assert result["hash_cache"] == {"hits": 0, "misses": 6}
+        assert any("notes.md: different version exists" in s for s in result["skipped"])
+
+    def test_damaged_cache_starts_cold(self, tmp_src, tmp_dst, tmp_path):
+        cache = tmp_path / "hashes"
+        run_kosmokopy(src=tmp_src, dst=tmp_dst, hash_cache=cache)
+        cache.write_text(cache.read_text().replace("\t", " ", 1))
+
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, hash_cache=cache)
+        assert result["hash_cache"] == {"hits": 0, "misses": 6}
+        assert any("starting afresh" in w for w in result["warnings"])
+        assert len(result["skipped"]) == 6
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Sampled verification
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestVerifySample:
+
+    def test_half_the_files_are_verified(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, verify_sample=50, verify_sample_seed=7)
+        assert result["status"] == "finished"
+        assert result["copied"] == 6
+        sample = result["verify_sample"]
+        assert (sample["seed"], sample["verified"], sample["files"]) == (7, 3, 6)
+        assert sample["summary"] == "verified 3 of 6 files (sample, seed 7)"
+        for f in tmp_src.rglob("*"):
+            if f.is_file():
+                assert files_are_identical(f, tmp_dst / tmp_src.name / f.relative_to(tmp_src))
+
+    def test_files_above_the_size_threshold_are_always_verified(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(
+            src=tmp_src, dst=tmp_dst, verify_sample=1, verify_sample_min_size=0,
+        )
+        assert result["verify_sample"]["verified"] == 6
+
+    def test_a_seed_is_chosen_and_reported(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, verify_sample=10)
+        assert isinstance(result["verify_sample"]["seed"], int)
+        assert result["verify_sample"]["verified"] == 1
+
+    def test_the_detail_report_is_the_manifest(self, tmp_src, tmp_dst, tmp_path):
+        out = tmp_path / "report.csv"
+        run_kosmokopy(src=tmp_src, dst=tmp_dst, verify_sample=50, verify_sample_seed=7, detail_report=out)
+        lines = out.read_text().splitlines()
+        assert lines[-1] == "# verified 3 of 6 files (sample, seed 7)"
+        rows = list(csv.DictReader(lines[:-1]))
+        verified = [row for row in rows if row["hash"]]
+        assert len(verified) == 3
+        for row in verified:
+            assert row["hash"] == "sha256:" + sha256_of_file(row["source"])
+            assert row["reason"] == ""
+        assert [row["reason"] for row in rows if not row["hash"]] == ["not in the verification sample"] * 3
+
+    def test_move_refuses_sampling(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, move=True, verify_sample=10)
+        assert result["status"] == "error"
+        assert "--verify-sample cannot be used with --move" in result["message"]
+        assert (tmp_src / "hello.txt").exists()
+        assert not (tmp_dst / tmp_src.name).exists()
+
+
 # ═══════════════════════════════════════════════════════════════════════
 #  Move-mode integrity — source removed only when dest verified
 # ═══════════════════════════════════════════════════════════════════════
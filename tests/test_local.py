@@ -5,16 +5,24 @@ Operations are performed by invoking ``kosmokopy --cli``.
 Verification is done in Python.
 """
 
+import csv
+import json
 import os
+import shutil
+import subprocess
+import time
+from datetime import datetime, timezone
 from pathlib import Path
 
 import pytest
 
 from conftest import (
+    KOSMOKOPY_BIN,
     run_kosmokopy,
     requires_rsync,
     sha256_of_file,
     files_are_identical,
+    tree_hashes,
 )
 
 
@@ -160,6 +168,54 @@ class TestLocalMoveStandard:
             assert (root / rel).exists()
             assert not (tmp_src / rel).exists()
 
+    def test_move_reports_breakdown(self, tmp_src, tmp_dst):
+        """Same-filesystem moves are reported as renames."""
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, move=True)
+        assert result["status"] == "finished"
+        breakdown = result["move_breakdown"]
+        assert breakdown["renamed"] + breakdown["copied"] == 6
+        if os.stat(tmp_src).st_dev == os.stat(tmp_dst).st_dev:
+            assert breakdown == {"renamed": 6, "copied": 0, "copied_bytes": 0, "identical": 0}
+
+    def test_copy_has_no_move_breakdown(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst)
+        assert "move_breakdown" not in result
+
+    def test_move_removes_emptied_folders(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, move=True)
+        assert result["status"] == "finished"
+        assert result["source_dirs_removed"] == 2
+        assert "Removed 2 emptied source folder(s)" in result["summary"]["text"]
+        # The source folder itself stays, now empty.
+        assert tmp_src.is_dir()
+        assert list(tmp_src.iterdir()) == []
+
+    def test_folders_holding_excluded_files_stay(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, move=True, exclude=["~*.dat"])
+        assert result["status"] == "finished"
+        assert result["source_dirs_removed"] == 1
+        assert (tmp_src / "subdir" / "deep.dat").exists()
+        assert not (tmp_src / "subdir" / "level2").exists()
+
+    def test_remove_source_root(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, move=True, remove_source_root=True)
+        assert result["status"] == "finished"
+        assert result["source_dirs_removed"] == 3
+        assert not tmp_src.exists()
+
+    def test_copy_removes_no_folders(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, remove_source_root=True)
+        assert "source_dirs_removed" not in result
+        assert (tmp_src / "subdir" / "level2" / "bottom.txt").exists()
+
+    def test_summary_matches_the_counts(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, move=True)
+        summary = result["summary"]
+        assert summary["ending"] == "finished"
+        assert (summary["copied"], summary["skipped"], summary["errors"]) == (6, 0, 0)
+        assert summary["text"].startswith("Moved 6 files")
+        assert ", 0 skipped, nothing excluded." in summary["text"]
+
 
 # ═══════════════════════════════════════════════════════════════════════
 #  Rsync local transfers
@@ -196,6 +252,19 @@ class TestLocalCopyRsync:
         # All files flat
         assert all(f.is_file() for f in tmp_dst.iterdir())
 
+    @pytest.mark.parametrize("mode, calls", [("folders", 1), ("files", 3)])
+    def test_rsync_runs_once_per_source_folder_it_keeps(self, tmp_src, tmp_dst, mode, calls):
+        """A tree goes in one rsync call; flattened files in one per folder."""
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst),
+             "--method", "rsync", "--mode", mode, "--print-commands"],
+            capture_output=True, text=True, timeout=60,
+        )
+        assert json.loads(proc.stdout)["copied"] == 6
+        transfers = [line for line in proc.stderr.splitlines() if "): rsync -a" in line]
+        assert len(transfers) == calls
+        assert all("--files-from=" in line for line in transfers)
+
 
 @requires_rsync
 class TestLocalMoveRsync:
@@ -212,6 +281,8 @@ class TestLocalMoveRsync:
         for rel, h in originals.items():
             assert not (tmp_src / rel).exists()
             assert sha256_of_file(root / rel) == h
+        assert result["source_dirs_removed"] == 2
+        assert not (tmp_src / "subdir").exists()
 
 
 # ═══════════════════════════════════════════════════════════════════════
@@ -253,6 +324,240 @@ class TestStripSpaces:
         assert (root / "my file.txt").exists()
 
 
+# ═══════════════════════════════════════════════════════════════════════
+#  Files of one run with the same name
+# ═══════════════════════════════════════════════════════════════════════
+
+
+@pytest.fixture
+def reports(tmp_path):
+    """Two folders each holding a report.pdf, plus a notes file."""
+    src = tmp_path / "reports"
+    for name in ("a", "b"):
+        (src / name).mkdir(parents=True)
+        (src / name / "report.pdf").write_text(f"report from {name}\n")
+    (src / "notes.txt").write_text("notes\n")
+    return src
+
+
+class TestSameNameInRun:
+
+    def test_files_only_renames_apart(self, reports, tmp_dst):
+        result = run_kosmokopy(src=reports, dst=tmp_dst, mode="files")
+        assert result["status"] == "finished"
+        assert result["copied"] == 3
+        assert (tmp_dst / "report.pdf").read_text() == "report from a\n"
+        assert (tmp_dst / "report (from b).pdf").read_text() == "report from b\n"
+        assert any("renamed" in w and "report (from b).pdf" in w for w in result["warnings"])
+
+    def test_abort_copies_nothing(self, reports, tmp_dst):
+        result = run_kosmokopy(
+            src=reports, dst=tmp_dst, mode="files", on_collision="abort",
+        )
+        assert result["status"] == "error"
+        assert "same name" in result["message"]
+        assert str(reports / "a" / "report.pdf") in result["message"]
+        assert list(tmp_dst.iterdir()) == []
+
+    def test_folders_mode_keeps_them_apart(self, reports, tmp_dst):
+        result = run_kosmokopy(src=reports, dst=tmp_dst, on_collision="abort")
+        assert result["status"] == "finished"
+        assert result["copied"] == 3
+        assert "warnings" not in result
+
+    @pytest.mark.parametrize("mode", ["files", "folders"])
+    def test_removing_spaces_renames_apart(self, tmp_path, tmp_dst, mode):
+        src = tmp_path / "docs"
+        src.mkdir()
+        (src / "my file.txt").write_text("spaced\n")
+        (src / "myfile.txt").write_text("joined\n")
+        result = run_kosmokopy(src=src, dst=tmp_dst, mode=mode, strip_spaces=True)
+        assert result["status"] == "finished"
+        assert result["copied"] == 2
+        out = tmp_dst if mode == "files" else tmp_dst / "docs"
+        assert (out / "myfile.txt").read_text() == "spaced\n"
+        assert (out / "myfile (from docs).txt").read_text() == "joined\n"
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Free space at the destination
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestFreeSpace:
+
+    def test_a_plan_bigger_than_the_free_space_is_refused(self, tmp_path, tmp_dst):
+        src = tmp_path / "big"
+        src.mkdir()
+        (src / "small.txt").write_text("small\n")
+        # Sparse, so it takes no room here but would need all of it there.
+        with open(src / "huge.img", "wb") as f:
+            f.truncate(shutil.disk_usage(tmp_dst).free + (1 << 30))
+        result = run_kosmokopy(src=src, dst=tmp_dst)
+        assert result["status"] == "error"
+        assert result["kind"] == "no-space"
+        assert result["needed_bytes"] > result["free_bytes"]
+        assert result["message"].startswith("Needs ")
+        assert "destination has" in result["message"]
+        assert list(tmp_dst.iterdir()) == []
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Files with parent folder
+# ═══════════════════════════════════════════════════════════════════════
+
+
+@pytest.fixture
+def albums(tmp_path):
+    """Two years of photos whose album folders share names."""
+    src = tmp_path / "photos"
+    for year, season, name in [
+        ("2023", "summer", "beach.jpg"),
+        ("2023", "winter", "snow.jpg"),
+        ("2024", "summer", "beach.jpg"),
+        ("2024", "summer", "lake.jpg"),
+    ]:
+        f = src / year / season / name
+        f.parent.mkdir(parents=True, exist_ok=True)
+        f.write_text(f"{year} {season} {name}\n")
+    return src
+
+
+class TestFilesWithParent:
+
+    def test_files_land_under_their_parent_name(self, albums, tmp_dst):
+        result = run_kosmokopy(
+            src=albums, dst=tmp_dst, mode="parent", conflict="rename",
+        )
+        assert result["status"] == "finished"
+        assert result["copied"] == 4
+        assert sorted(p.name for p in tmp_dst.iterdir()) == ["summer", "winter"]
+        assert (tmp_dst / "winter" / "snow.jpg").exists()
+        assert (tmp_dst / "summer" / "lake.jpg").exists()
+
+    def test_same_parent_name_is_a_conflict(self, albums, tmp_dst):
+        """Two ``summer`` albums with a ``beach.jpg`` each go through the
+        conflict mode instead of silently overwriting one another."""
+        result = run_kosmokopy(src=albums, dst=tmp_dst, mode="parent")
+        assert result["status"] == "finished"
+        assert result["copied"] == 3
+        assert len(result["skipped"]) == 1
+        assert "beach.jpg" in result["skipped"][0]
+        assert not (tmp_dst / "summer" / "beach_1.jpg").exists()
+
+    def test_same_parent_name_renames(self, albums, tmp_dst):
+        result = run_kosmokopy(
+            src=albums, dst=tmp_dst, mode="parent", conflict="rename",
+        )
+        beaches = sorted(
+            p.read_text() for p in (tmp_dst / "summer").glob("beach*.jpg")
+        )
+        assert beaches == ["2023 summer beach.jpg\n", "2024 summer beach.jpg\n"]
+        assert result["errors"] == []
+
+    def test_individual_files_from_two_sources(self, albums, tmp_dst):
+        result = run_kosmokopy(
+            src_files=[
+                albums / "2023" / "summer" / "beach.jpg",
+                albums / "2024" / "summer" / "beach.jpg",
+            ],
+            dst=tmp_dst, mode="parent", conflict="rename",
+        )
+        assert result["copied"] == 2
+        assert (tmp_dst / "summer" / "beach.jpg").exists()
+        assert (tmp_dst / "summer" / "beach_1.jpg").exists()
+
+    @requires_rsync
+    def test_rsync_same_parent_name_is_a_conflict(self, albums, tmp_dst):
+        result = run_kosmokopy(
+            src=albums, dst=tmp_dst, mode="parent", method="rsync",
+        )
+        assert result["copied"] == 3
+        assert len(result["skipped"]) == 1
+
+    def test_strip_spaces_applies_to_parent(self, tmp_path, tmp_dst):
+        f = tmp_path / "src" / "road trip" / "map 1.png"
+        f.parent.mkdir(parents=True)
+        f.write_bytes(b"map")
+        result = run_kosmokopy(
+            src=tmp_path / "src", dst=tmp_dst, mode="parent", strip_spaces=True,
+        )
+        assert result["copied"] == 1
+        assert (tmp_dst / "roadtrip" / "map1.png").exists()
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Individual files keeping their parent folder names
+# ═══════════════════════════════════════════════════════════════════════
+
+
+@pytest.fixture
+def picked_files(tmp_path):
+    """Files picked from two trees whose ``photos`` folders share a name."""
+    files = []
+    for rel in ["a/x/photos/img.jpg", "b/y/photos/img.jpg", "b/y/photos/other.jpg"]:
+        f = tmp_path / "picked" / rel
+        f.parent.mkdir(parents=True, exist_ok=True)
+        f.write_text(rel)
+        files.append(f)
+    return files
+
+
+class TestFilesKeepParents:
+
+    def test_zero_keeps_files_flat(self, picked_files, tmp_dst):
+        result = run_kosmokopy(
+            src_files=picked_files, dst=tmp_dst, files_keep_parents=0,
+        )
+        assert result["copied"] == 2
+        assert len(result["skipped"]) == 1
+        assert sorted(p.name for p in tmp_dst.iterdir()) == ["img.jpg", "other.jpg"]
+
+    def test_one_level_collides_through_conflict_mode(self, picked_files, tmp_dst):
+        result = run_kosmokopy(
+            src_files=picked_files, dst=tmp_dst, files_keep_parents=1,
+            conflict="rename",
+        )
+        assert result["copied"] == 3
+        photos = tmp_dst / "photos"
+        assert sorted(p.name for p in photos.iterdir()) == ["img.jpg", "img_1.jpg", "other.jpg"]
+        assert (photos / "img.jpg").read_text() == "a/x/photos/img.jpg"
+
+    def test_two_levels_keep_colliding_parents_apart(self, picked_files, tmp_dst):
+        result = run_kosmokopy(
+            src_files=picked_files, dst=tmp_dst, files_keep_parents=2,
+        )
+        assert result["copied"] == 3
+        assert result["skipped"] == []
+        assert (tmp_dst / "x" / "photos" / "img.jpg").read_text() == "a/x/photos/img.jpg"
+        assert (tmp_dst / "y" / "photos" / "img.jpg").read_text() == "b/y/photos/img.jpg"
+
+    def test_applies_in_folders_mode(self, picked_files, tmp_dst):
+        result = run_kosmokopy(
+            src_files=picked_files, dst=tmp_dst, files_keep_parents=2,
+            mode="folders",
+        )
+        assert result["copied"] == 3
+        assert (tmp_dst / "y" / "photos" / "other.jpg").exists()
+
+    def test_strip_spaces_applies_to_kept_parents(self, tmp_path, tmp_dst):
+        f = tmp_path / "my trips" / "road trip" / "map.png"
+        f.parent.mkdir(parents=True)
+        f.write_bytes(b"map")
+        result = run_kosmokopy(
+            src_files=[f], dst=tmp_dst, files_keep_parents=2, strip_spaces=True,
+        )
+        assert result["copied"] == 1
+        assert (tmp_dst / "mytrips" / "roadtrip" / "map.png").exists()
+
+    def test_rejects_a_non_number(self, picked_files, tmp_dst):
+        result = run_kosmokopy(
+            src_files=picked_files, dst=tmp_dst, files_keep_parents="two",
+        )
+        assert result["status"] == "error"
+        assert "--files-keep-parents" in result["message"]
+
+
 # ═══════════════════════════════════════════════════════════════════════
 #  Single-file source
 # ═══════════════════════════════════════════════════════════════════════
@@ -269,7 +574,8 @@ class TestSingleFileCopy:
         f.write_text("only file\n")
         dst = tmp_path / "dst"
 
-        result = run_kosmokopy(src_files=[f], dst=dst, mode="files")
+        # The trailing slash makes the missing destination a folder.
+        result = run_kosmokopy(src_files=[f], dst=f"{dst}/", mode="files")
         assert result["status"] == "finished"
         assert result["copied"] == 1
         assert (dst / "only.txt").exists()
@@ -283,7 +589,7 @@ class TestSingleFileCopy:
         f.write_bytes(os.urandom(1024))
         dst = tmp_path / "dst"
 
-        result = run_kosmokopy(src_files=[f], dst=dst, mode="files", method="rsync")
+        result = run_kosmokopy(src_files=[f], dst=f"{dst}/", mode="files", method="rsync")
         assert result["status"] == "finished"
         assert result["copied"] == 1
         assert files_are_identical(f, dst / "only.bin")
@@ -297,7 +603,7 @@ class TestSingleFileCopy:
         expected_hash = sha256_of_file(f)
         dst = tmp_path / "dst"
 
-        result = run_kosmokopy(src_files=[f], dst=dst, mode="files", move=True)
+        result = run_kosmokopy(src_files=[f], dst=f"{dst}/", mode="files", move=True)
         assert result["status"] == "finished"
         assert result["copied"] == 1
         assert not f.exists()
@@ -314,3 +620,1466 @@ class TestSingleFileCopy:
         assert result["status"] == "finished"
         assert result["copied"] == 1
         assert (dst / "src" / "solo.txt").exists()
+
+
+class TestSingleFileTarget:
+    """A single file sent to a path that is not a folder lands at that path."""
+
+    @pytest.fixture
+    def conf(self, tmp_path):
+        src = tmp_path / "src"
+        src.mkdir()
+        f = src / "a.conf"
+        f.write_text("setting = 1\n")
+        return f
+
+    def test_existing_folder_without_slash(self, conf, tmp_dst):
+        result = run_kosmokopy(src_files=[conf], dst=tmp_dst)
+        assert result["copied"] == 1
+        assert files_are_identical(conf, tmp_dst / "a.conf")
+
+    def test_existing_folder_with_slash(self, conf, tmp_dst):
+        result = run_kosmokopy(src_files=[conf], dst=f"{tmp_dst}/")
+        assert result["copied"] == 1
+        assert files_are_identical(conf, tmp_dst / "a.conf")
+
+    def test_missing_path_with_slash_is_a_new_folder(self, conf, tmp_dst):
+        result = run_kosmokopy(src_files=[conf], dst=f"{tmp_dst / 'a.conf.new'}/")
+        assert result["copied"] == 1
+        assert files_are_identical(conf, tmp_dst / "a.conf.new" / "a.conf")
+
+    def test_missing_path_without_slash_is_the_file(self, conf, tmp_dst):
+        result = run_kosmokopy(src_files=[conf], dst=tmp_dst / "a.conf.new")
+        assert result["status"] == "finished"
+        assert result["copied"] == 1
+        assert (tmp_dst / "a.conf.new").is_file()
+        assert files_are_identical(conf, tmp_dst / "a.conf.new")
+        assert sorted(p.name for p in tmp_dst.iterdir()) == ["a.conf.new"]
+
+    def test_existing_file_follows_the_conflict_mode(self, conf, tmp_dst):
+        target = tmp_dst / "a.conf.new"
+        target.write_text("setting = 0\n")
+        skipped = run_kosmokopy(src_files=[conf], dst=target)
+        assert skipped["copied"] == 0
+        assert target.read_text() == "setting = 0\n"
+        renamed = run_kosmokopy(src_files=[conf], dst=target, conflict="rename")
+        assert renamed["copied"] == 1
+        assert files_are_identical(conf, tmp_dst / "a.conf_1.new")
+        overwritten = run_kosmokopy(src_files=[conf], dst=target, conflict="overwrite")
+        assert overwritten["copied"] == 1
+        assert files_are_identical(conf, target)
+
+    def test_move_to_a_new_name(self, conf, tmp_dst):
+        result = run_kosmokopy(src_files=[conf], dst=tmp_dst / "b.conf", move=True)
+        assert result["copied"] == 1
+        assert not conf.exists()
+        assert (tmp_dst / "b.conf").read_text() == "setting = 1\n"
+
+
+class TestDestinationIsAFile:
+    """An existing file is only a destination for a single file named to it."""
+
+    @pytest.fixture
+    def target(self, tmp_dst):
+        f = tmp_dst / "notes.txt"
+        f.write_text("keep me\n")
+        return f
+
+    def test_folder_source_is_refused(self, tmp_src, target):
+        result = run_kosmokopy(src=tmp_src, dst=target)
+        assert result["status"] == "error"
+        assert str(target) in result["message"]
+        assert "not a folder" in result["message"]
+        assert target.read_text() == "keep me\n"
+
+    def test_several_files_are_refused(self, tmp_path, target):
+        files = []
+        for name in ("a.txt", "b.txt"):
+            f = tmp_path / name
+            f.write_text(name)
+            files.append(f)
+        result = run_kosmokopy(src_files=files, dst=target)
+        assert result["status"] == "error"
+        assert "not a folder" in result["message"]
+        assert target.read_text() == "keep me\n"
+
+    def test_trailing_slash_is_refused(self, tmp_path, target):
+        f = tmp_path / "a.txt"
+        f.write_text("new\n")
+        result = run_kosmokopy(src_files=[f], dst=f"{target}/")
+        assert result["status"] == "error"
+        assert target.read_text() == "keep me\n"
+
+    def test_single_file_replaces_it_when_overwriting(self, tmp_path, target):
+        f = tmp_path / "a.txt"
+        f.write_text("new\n")
+        result = run_kosmokopy(src_files=[f], dst=target, conflict="overwrite")
+        assert result["status"] == "finished"
+        assert result["copied"] == 1
+        assert target.read_text() == "new\n"
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  --list-only
+# ═══════════════════════════════════════════════════════════════════════
+
+
+def _list_only(*args):
+    proc = subprocess.run(
+        [KOSMOKOPY_BIN, "--cli", "--list-only", *args],
+        capture_output=True, text=True, timeout=60,
+    )
+    return proc
+
+
+class TestListOnly:
+
+    EXCLUDE = ["/cache", "~/build*", "~*.log", "data.tmp"]
+
+    def test_listing_matches_a_real_run(self, tmp_src_with_exclusions, tmp_dst):
+        src = tmp_src_with_exclusions
+        args = ["--src", str(src)]
+        for pat in self.EXCLUDE:
+            args += ["--exclude", pat]
+        proc = _list_only(*args)
+        assert proc.returncode == 0
+        listed = {Path(line).relative_to(src) for line in proc.stdout.splitlines()}
+        assert "excluded" in proc.stderr
+
+        result = run_kosmokopy(src=src, dst=tmp_dst, exclude=self.EXCLUDE)
+        assert result["status"] == "finished"
+        copied_root = tmp_dst / src.name
+        copied = {p.relative_to(copied_root) for p in copied_root.rglob("*") if p.is_file()}
+        assert listed == copied
+        assert result["copied"] == len(listed)
+        assert f"{len(listed)} file(s)" in proc.stderr
+
+    def test_src_files_and_includes(self, tmp_src_with_exclusions, tmp_dst):
+        src = tmp_src_with_exclusions
+        files = f"{src / 'keep.txt'},{src / 'PHOTO.JPG'}"
+        proc = _list_only("--src-files", files, "--include", "*.jpg")
+        listed = proc.stdout.splitlines()
+
+        result = run_kosmokopy(
+            src_files=[src / "keep.txt", src / "PHOTO.JPG"], dst=tmp_dst,
+            include=["*.jpg"], mode="files",
+        )
+        copied = sorted(p.name for p in tmp_dst.iterdir())
+        assert sorted(Path(p).name for p in listed) == copied
+        assert result["copied"] == len(listed)
+
+    def test_null_delimited_and_nothing_written(self, tmp_src, tmp_path):
+        before = sorted(p.name for p in tmp_path.iterdir())
+        proc = _list_only("--src", str(tmp_src), "--null")
+        entries = [e for e in proc.stdout.split("\0") if e]
+        assert len(entries) == 6
+        assert "\n" not in proc.stdout
+        assert sorted(p.name for p in tmp_path.iterdir()) == before
+
+    def test_empty_selection(self, tmp_path):
+        empty = tmp_path / "empty"
+        empty.mkdir()
+        proc = _list_only("--src", str(empty))
+        assert proc.returncode == 0
+        assert proc.stdout == ""
+        assert "0 file(s)" in proc.stderr
+        assert _list_only("--src", str(empty), "--fail-if-empty").returncode == 1
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  --dry-run
+# ═══════════════════════════════════════════════════════════════════════
+
+
+def _dry_run(*args):
+    return subprocess.run(
+        [KOSMOKOPY_BIN, "--cli", "--dry-run", *args],
+        capture_output=True, text=True, timeout=60,
+    )
+
+
+class TestDryRun:
+
+    def test_plan_matches_a_real_run(self, tmp_src_with_exclusions, tmp_dst):
+        src = tmp_src_with_exclusions
+        args = ["--src", str(src), "--dst", str(tmp_dst)]
+        for pat in TestListOnly.EXCLUDE:
+            args += ["--exclude", pat]
+        proc = _dry_run(*args)
+        assert proc.returncode == 0, proc.stderr
+        plan = json.loads(proc.stdout)
+        assert {e["action"] for e in plan} == {"copy"}
+        assert list(tmp_dst.iterdir()) == []
+        assert "nothing was transferred" in proc.stderr
+
+        result = run_kosmokopy(src=src, dst=tmp_dst, exclude=TestListOnly.EXCLUDE)
+        assert result["copied"] == len(plan)
+        assert {e["dst"] for e in plan} == {str(p) for p in tmp_dst.rglob("*") if p.is_file()}
+
+    def test_conflicts_follow_the_conflict_mode(self, tmp_src, tmp_dst):
+        run_kosmokopy(src=tmp_src, dst=tmp_dst)
+        changed = tmp_dst / tmp_src.name / "hello.txt"
+        changed.write_text("edited at the destination\n")
+        args = ["--src", str(tmp_src), "--dst", str(tmp_dst)]
+
+        def actions(*extra):
+            proc = _dry_run(*args, *extra)
+            assert proc.returncode == 0, proc.stderr
+            return {Path(e["src"]).name: (e["action"], e["dst"]) for e in json.loads(proc.stdout)}
+
+        skip = actions()
+        assert skip["hello.txt"] == ("skip-existing", str(changed))
+        assert skip["notes.md"][0] == "skip-identical"
+        assert actions("--conflict", "overwrite")["hello.txt"] == ("overwrite", str(changed))
+        assert actions("--conflict", "rename")["hello.txt"] == ("rename", str(changed.with_name("hello_1.txt")))
+        assert actions("--move")["notes.md"][0] == "delete-source"
+        assert changed.read_text() == "edited at the destination\n"
+        assert (tmp_src / "notes.md").exists()
+
+    def test_renamed_and_flattened_names(self, tmp_src_with_spaces, tmp_dst):
+        proc = _dry_run("--src", str(tmp_src_with_spaces), "--dst", str(tmp_dst), "--mode", "files", "--strip-spaces")
+        assert proc.returncode == 0, proc.stderr
+        plan = json.loads(proc.stdout)
+        assert plan and all(Path(e["dst"]).parent == tmp_dst for e in plan)
+        assert not any(" " in Path(e["dst"]).name for e in plan)
+
+    def test_printing_commands_runs_nothing(self, tmp_src, tmp_dst):
+        proc = _dry_run("--src", str(tmp_src), "--dst", str(tmp_dst), "--method", "rsync", "--print-commands")
+        assert proc.returncode == 0, proc.stderr
+        # A local plan needs no command, and rsync is never started.
+        assert "[kosmokopy] command" not in proc.stderr
+        assert list(tmp_dst.iterdir()) == []
+
+    def test_names_that_are_not_utf8_are_escaped(self, tmp_path, tmp_dst):
+        src = tmp_path / "latin1"
+        src.mkdir()
+        (src / os.fsdecode(b"caf\xe9.txt")).write_text("x")
+        (src / "plain.txt").write_text("y")
+        proc = _dry_run("--src", str(src), "--dst", str(tmp_dst))
+        assert proc.returncode == 0, proc.stderr
+        plan = {Path(e["src"]).name: e for e in json.loads(proc.stdout)}
+        assert plan["caf\\xe9.txt"]["non_utf8"] is True
+        assert plan["caf\\xe9.txt"]["dst"] == str(tmp_dst / "latin1" / "caf\\xe9.txt")
+        assert "non_utf8" not in plan["plain.txt"]
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Failed-files list export and re-import
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestFailedFilesList:
+
+    def _block_one_file(self, tmp_src, tmp_dst):
+        """Make the destination of hello.txt a directory so its copy fails."""
+        blocker = tmp_dst / tmp_src.name / "hello.txt"
+        blocker.mkdir(parents=True)
+        (blocker / "keep").write_text("x")
+        return blocker
+
+    @pytest.mark.parametrize("null", [False, True])
+    def test_failed_list_names_only_failed_sources(self, tmp_src, tmp_dst, tmp_path, null):
+        """The exported list holds exactly the sources that failed."""
+        self._block_one_file(tmp_src, tmp_dst)
+        out = tmp_path / "failed.kosmokopy-failed"
+
+        result = run_kosmokopy(
+            src=tmp_src, dst=tmp_dst, conflict="overwrite",
+            failed_files_out=out, null=null,
+        )
+        assert result["status"] == "finished"
+        assert len(result["errors"]) >= 1
+
+        sep = "\0" if null else "\n"
+        entries = [e for e in out.read_text().split(sep) if e]
+        assert entries == [str(tmp_src / "hello.txt")]
+
+    def test_retry_from_failed_list(self, tmp_src, tmp_dst, tmp_path):
+        """Feeding the list back in retries just the failed file."""
+        blocker = self._block_one_file(tmp_src, tmp_dst)
+        out = tmp_path / "failed.kosmokopy-failed"
+        run_kosmokopy(src=tmp_src, dst=tmp_dst, conflict="overwrite", failed_files_out=out)
+
+        # Clear the blocker, then retry from the list into the same folder.
+        for f in blocker.iterdir():
+            f.unlink()
+        blocker.rmdir()
+        retry = run_kosmokopy(
+            src_files_from=out, dst=tmp_dst / tmp_src.name, mode="files",
+        )
+        assert retry["status"] == "finished"
+        assert retry["copied"] == 1
+        assert retry["errors"] == []
+        assert files_are_identical(tmp_src / "hello.txt", tmp_dst / tmp_src.name / "hello.txt")
+
+    def test_no_failures_writes_empty_list(self, tmp_src, tmp_dst, tmp_path):
+        """A clean run leaves an empty list rather than a stale one."""
+        out = tmp_path / "failed.kosmokopy-failed"
+        out.write_text("/stale/entry\n")
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, failed_files_out=out)
+        assert result["status"] == "finished"
+        assert out.read_text() == ""
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Destination folders created vs reused
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestDestinationFolders:
+
+    def test_created_and_reused_are_told_apart(self, tmp_src, tmp_dst):
+        """Only folders the run made are listed, and only they get the mode."""
+        existing = tmp_dst / tmp_src.name / "subdir"
+        existing.mkdir(parents=True)
+        existing.chmod(0o755)
+
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, chmod_dirs="750")
+        assert result["status"] == "finished"
+        assert result["dirs_created"] == [str(existing / "level2")]
+        assert result["dirs_reused"] == 2
+        assert (existing / "level2").stat().st_mode & 0o7777 == 0o750
+        assert existing.stat().st_mode & 0o7777 == 0o755
+
+    def test_second_run_creates_nothing(self, tmp_src, tmp_dst):
+        first = run_kosmokopy(src=tmp_src, dst=tmp_dst)
+        assert len(first["dirs_created"]) == 3
+        # Identical files are skipped without writing, so change them all.
+        for f in tmp_src.rglob("*"):
+            if f.is_file():
+                f.write_text("changed\n")
+        again = run_kosmokopy(src=tmp_src, dst=tmp_dst, conflict="overwrite")
+        assert again["dirs_created"] == []
+        assert again["dirs_reused"] == 3
+
+    def test_bad_mode_is_rejected(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, chmod_dirs="rwx")
+        assert result["status"] == "error"
+        assert "--chmod-dirs expects an octal mode" in result["message"]
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Preserving times and permissions
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestPreserve:
+
+    OLD = 1_600_000_000
+
+    def _age(self, tmp_src):
+        for path in [*tmp_src.rglob("*"), tmp_src]:
+            os.utime(path, (self.OLD, self.OLD))
+        (tmp_src / "subdir").chmod(0o750)
+
+    @pytest.mark.parametrize("move", [False, True])
+    def test_files_and_new_folders_keep_their_times(self, tmp_src, tmp_dst, move):
+        self._age(tmp_src)
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, preserve=True, move=move)
+        assert result["status"] == "finished"
+        assert "warnings" not in result
+
+        out = tmp_dst / tmp_src.name
+        for path in [*out.rglob("*"), out]:
+            assert int(path.stat().st_mtime) == self.OLD, path
+        assert (out / "subdir").stat().st_mode & 0o7777 == 0o750
+
+    def test_without_preserve_copies_are_new(self, tmp_src, tmp_dst):
+        self._age(tmp_src)
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst)
+        assert result["status"] == "finished"
+        assert int((tmp_dst / tmp_src.name / "hello.txt").stat().st_mtime) != self.OLD
+
+    def test_chmod_dirs_still_sets_the_mode(self, tmp_src, tmp_dst):
+        self._age(tmp_src)
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, preserve=True, chmod_dirs="700")
+        assert result["status"] == "finished"
+        subdir = tmp_dst / tmp_src.name / "subdir"
+        assert subdir.stat().st_mode & 0o7777 == 0o700
+        assert int(subdir.stat().st_mtime) == self.OLD
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  --symlinks
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestSymlinks:
+
+    @pytest.fixture
+    def linked_src(self, tmp_path):
+        src = tmp_path / "src"
+        (src / "real").mkdir(parents=True)
+        (src / "real" / "data.txt").write_text("data\n")
+        (src / "to_data.txt").symlink_to("real/data.txt")
+        (src / "to_real").symlink_to("real")
+        (src / "dangling").symlink_to("nowhere")
+        return src
+
+    def test_links_are_skipped_by_default(self, linked_src, tmp_dst):
+        result = run_kosmokopy(src=linked_src, dst=tmp_dst)
+        assert result["status"] == "finished"
+        assert result["copied"] == 1
+        out = tmp_dst / "src"
+        assert not os.path.lexists(out / "to_data.txt")
+        assert not os.path.lexists(out / "dangling")
+        assert sorted(s.rsplit("/", 1)[1] for s in result["skipped"]) == [
+            "dangling: symbolic link, skipped",
+            "to_data.txt: symbolic link, skipped",
+            "to_real: symbolic link, skipped",
+        ]
+
+    def test_follow_copies_what_links_point_to(self, linked_src, tmp_dst):
+        result = run_kosmokopy(src=linked_src, dst=tmp_dst, symlinks="follow")
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        out = tmp_dst / "src"
+        for copy in (out / "to_data.txt", out / "to_real" / "data.txt"):
+            assert not copy.is_symlink()
+            assert copy.read_text() == "data\n"
+        assert [s.rsplit("/", 1)[1] for s in result["skipped"]] == [
+            "dangling: broken symbolic link, nothing to follow",
+        ]
+
+    def test_follow_refuses_a_move(self, linked_src, tmp_dst):
+        result = run_kosmokopy(src=linked_src, dst=tmp_dst, symlinks="follow", move=True)
+        assert result["status"] == "error"
+        assert "Following symbolic links only copies" in result["message"]
+        assert (linked_src / "real" / "data.txt").exists()
+
+    def test_follow_skips_a_link_back_up_the_tree(self, linked_src, tmp_dst):
+        (linked_src / "real" / "up").symlink_to("..")
+        result = run_kosmokopy(src=linked_src, dst=tmp_dst, symlinks="follow")
+        assert result["status"] == "finished"
+        assert any(s.endswith("up: symbolic link loops back to a folder above it") for s in result["skipped"])
+
+    @pytest.mark.parametrize("move", [False, True])
+    def test_preserve_recreates_links_even_broken_ones(self, linked_src, tmp_dst, move):
+        result = run_kosmokopy(src=linked_src, dst=tmp_dst, symlinks="preserve", move=move)
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["copied"] == 4
+        out = tmp_dst / "src"
+        assert os.readlink(out / "to_data.txt") == "real/data.txt"
+        assert os.readlink(out / "to_real") == "real"
+        assert os.readlink(out / "dangling") == "nowhere"
+        assert (out / "to_data.txt").read_text() == "data\n"
+        assert os.path.lexists(linked_src / "dangling") != move
+
+    def test_preserve_finds_recreated_links_identical(self, linked_src, tmp_dst):
+        run_kosmokopy(src=linked_src, dst=tmp_dst, symlinks="preserve")
+        again = run_kosmokopy(src=linked_src, dst=tmp_dst, symlinks="preserve")
+        assert again["copied"] == 0
+        assert again["errors"] == []
+        assert all(s.endswith(": identical at destination") for s in again["skipped"])
+
+    def test_unknown_policy_is_refused(self, linked_src, tmp_dst):
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(linked_src), "--dst", str(tmp_dst),
+             "--symlinks", "copy"],
+            capture_output=True, text=True, timeout=60,
+        )
+        assert proc.returncode == 1
+        assert "--symlinks" in proc.stderr
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  --verify
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestVerifyMode:
+
+    @pytest.mark.parametrize("verify", ["full", "hash", "quick", "none"])
+    def test_each_mode_copies_the_files(self, tmp_src, tmp_dst, verify):
+        hashes = tree_hashes(tmp_src)
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, verify=verify)
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["copied"] == len(hashes)
+        assert tree_hashes(tmp_dst / tmp_src.name) == hashes
+
+    @requires_rsync
+    @pytest.mark.parametrize("verify", ["hash", "quick"])
+    def test_a_move_checks_the_cheaper_way(self, tmp_src, tmp_dst, verify):
+        hashes = tree_hashes(tmp_src)
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, verify=verify, move=True, method="rsync")
+        assert result["status"] == "finished"
+        assert result["copied"] == len(hashes)
+        assert tree_hashes(tmp_src) == {}
+        assert tree_hashes(tmp_dst / tmp_src.name) == hashes
+
+    def test_a_move_cannot_skip_verification(self, tmp_src, tmp_dst):
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst),
+             "--move", "--verify", "none"],
+            capture_output=True, text=True, timeout=60,
+        )
+        assert proc.returncode == 1
+        assert "--verify none cannot be used with --move" in proc.stderr
+        assert not any(tmp_dst.iterdir())
+
+    def test_unknown_mode_is_refused(self, tmp_src, tmp_dst):
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst),
+             "--verify", "bytes"],
+            capture_output=True, text=True, timeout=60,
+        )
+        assert proc.returncode == 1
+        assert "--verify" in proc.stderr
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Why a transfer command failed
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestTransferStderr:
+
+    def _fake_failing_rsync(self, bin_dir):
+        """An ``rsync`` stand-in that answers ``--version`` but fails every
+        transfer, with connection noise around the real reason."""
+        bin_dir.mkdir()
+        rsync = bin_dir / "rsync"
+        rsync.write_text(
+            "#!/bin/sh\n"
+            "if [ \"$1\" = --version ]; then echo 'rsync  version 3.2.7  protocol version 31'; exit 0; fi\n"
+            "echo 'Warning: Permanently added nas (ED25519) to the list of known hosts.' >&2\n"
+            "echo 'rsync: [receiver] mkstemp \"x\" failed: No space left on device (28)' >&2\n"
+            "echo 'rsync error: some files/attrs were not transferred (code 23) at main.c(1338)' >&2\n"
+            "exit 23\n"
+        )
+        rsync.chmod(0o755)
+        return bin_dir
+
+    def test_stderr_reason_lands_in_the_error(self, tmp_src, tmp_dst, tmp_path):
+        """Each error names the failing line of stderr, which is not passed through."""
+        bin_dir = self._fake_failing_rsync(tmp_path / "bin")
+        env = dict(os.environ, PATH=f"{bin_dir}{os.pathsep}{os.environ['PATH']}")
+        env.pop("KOSMOKOPY_DEBUG", None)
+        cmd = [
+            KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst),
+            "--method", "rsync",
+        ]
+        proc = subprocess.run(cmd, capture_output=True, text=True, timeout=60, env=env)
+        result = json.loads(proc.stdout.strip())
+
+        assert result["status"] == "finished"
+        assert result["copied"] == 0
+        assert result["errors"]
+        for error in result["errors"]:
+            assert "rsync failed (exit code 23): rsync: [receiver] mkstemp" in error
+            assert "No space left on device" in error
+            assert "Permanently added" not in error
+        assert "No space left on device" not in proc.stderr
+
+
+class TestLowPriority:
+
+    def test_local_copy_reports_what_was_applied(self, tmp_src, tmp_dst, tmp_path):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, low_priority=True)
+        assert result["status"] == "finished"
+        assert result["copied"] > 0
+        low = result["low_priority"]
+        assert set(low) == {"nice", "io_idle", "child_prefix"}
+        assert "low_priority" not in run_kosmokopy(src=tmp_src, dst=tmp_path / "plain")
+
+    def test_missing_tools_are_skipped(self, tmp_src, tmp_dst, tmp_path):
+        """With none of renice, ionice or nice on PATH the copy still runs."""
+        empty = tmp_path / "empty-bin"
+        empty.mkdir()
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, low_priority=True, env={"PATH": str(empty)})
+        assert result["status"] == "finished"
+        assert result["copied"] > 0
+        assert result["low_priority"] == {"nice": False, "io_idle": False, "child_prefix": ""}
+
+    @pytest.mark.skipif(not shutil.which("rsync") or not shutil.which("nice"), reason="needs rsync and nice")
+    def test_rsync_runs_niced(self, tmp_src, tmp_dst, tmp_path):
+        bin_dir = tmp_path / "bin"
+        bin_dir.mkdir()
+        log = tmp_path / "niceness"
+        rsync = bin_dir / "rsync"
+        rsync.write_text(
+            "#!/bin/sh\n"
+            f"[ \"$1\" = --version ] || nice >> '{log}'\n"
+            f"exec '{shutil.which('rsync')}' \"$@\"\n"
+        )
+        rsync.chmod(0o755)
+        env = {"PATH": f"{bin_dir}{os.pathsep}{os.environ['PATH']}"}
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, method="rsync", low_priority=True, env=env)
+        assert result["status"] == "finished"
+        assert result["copied"] > 0
+        assert "nice -n19" in result["low_priority"]["child_prefix"]
+        assert set(log.read_text().split()) == {"19"}
+
+
+class TestBandwidthLimit:
+
+    def test_standard_copy_keeps_to_the_limit(self, tmp_path):
+        src = tmp_path / "src"
+        src.mkdir()
+        (src / "big.bin").write_bytes(os.urandom(1024 * 1024))
+        started = time.monotonic()
+        result = run_kosmokopy(src=src, dst=tmp_path / "dst", bwlimit=512)
+        # 1 MiB at 512 KB/s takes two seconds.
+        assert time.monotonic() - started >= 1.8
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert sha256_of_file(tmp_path / "dst" / "src" / "big.bin") == sha256_of_file(src / "big.bin")
+
+    @requires_rsync
+    @pytest.mark.parametrize("compress, z", [(None, False), ("always", True)])
+    def test_rsync_is_given_the_limit(self, tmp_src, tmp_dst, compress, z):
+        extra = ["--compress", compress] if compress else []
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst),
+             "--method", "rsync", "--bwlimit", "2000", "--print-commands", *extra],
+            capture_output=True, text=True, timeout=60,
+        )
+        assert json.loads(proc.stdout)["copied"] == 6
+        transfers = [line for line in proc.stderr.splitlines() if "): rsync -a" in line]
+        assert transfers
+        assert all(" --bwlimit=2000 " in line for line in transfers)
+        assert all((" -z " in line) == z for line in transfers)
+
+    def test_bad_limit_is_refused(self, tmp_src, tmp_dst):
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst), "--bwlimit", "fast"],
+            capture_output=True, text=True, timeout=60,
+        )
+        assert proc.returncode == 1
+        assert "--bwlimit expects KB/s" in proc.stderr
+
+
+class TestClockSkew:
+
+    def _skewed_ssh(self, bin_dir, skew):
+        """An ``ssh`` stand-in whose host clock is ``skew`` seconds off and
+        that refuses every other command."""
+        bin_dir.mkdir()
+        ssh = bin_dir / "ssh"
+        ssh.write_text(
+            "#!/bin/sh\n"
+            "for a; do last=\"$a\"; done\n"
+            f"if [ \"$last\" = 'date +%s' ]; then echo $(( $(date +%s) + {skew} )); exit 0; fi\n"
+            "echo 'connection refused' >&2\n"
+            "exit 255\n"
+        )
+        ssh.chmod(0o755)
+        return {"PATH": f"{bin_dir}{os.pathsep}{os.environ['PATH']}"}
+
+    def _run(self, tmp_src, env, *extra):
+        cmd = [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", "fakehost:/tmp/kk", *extra]
+        proc = subprocess.run(cmd, capture_output=True, text=True, timeout=60, env={**os.environ, **env})
+        return json.loads(proc.stdout.strip()), proc.stderr
+
+    def test_large_skew_is_a_warning(self, tmp_src, tmp_path):
+        env = self._skewed_ssh(tmp_path / "bin", -600)
+        _, stderr = self._run(tmp_src, env)
+        assert "Warning: The clock on fakehost is 600s behind this machine's" in stderr
+
+    def test_small_skew_is_not_mentioned(self, tmp_src, tmp_path):
+        env = self._skewed_ssh(tmp_path / "bin", 5)
+        _, stderr = self._run(tmp_src, env)
+        assert "clock on fakehost" not in stderr
+
+    def test_max_clock_skew_refuses_the_host(self, tmp_src, tmp_path):
+        env = self._skewed_ssh(tmp_path / "bin", 600)
+        result, _ = self._run(tmp_src, env, "--max-clock-skew", "60")
+        assert result["status"] == "error"
+        assert "600s ahead of this machine's" in result["message"]
+        assert "--max-clock-skew" in result["message"]
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Source and destination are the same file
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestSameFileGuard:
+
+    @pytest.mark.parametrize("move", [False, True])
+    def test_symlinked_destination_subdir(self, tmp_src, tmp_dst, move):
+        """A destination folder linked back to the source touches nothing."""
+        before = {p: sha256_of_file(p) for p in tmp_src.rglob("*") if p.is_file()}
+        (tmp_dst / tmp_src.name).symlink_to(tmp_src)
+
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, conflict="overwrite", move=move)
+        assert result["status"] == "finished"
+        assert result["copied"] == 0
+        assert result["errors"] == []
+        assert len(result["skipped"]) == len(before)
+        assert all("same file" in s for s in result["skipped"])
+        assert {p: sha256_of_file(p) for p in before} == before
+
+    @pytest.mark.parametrize("typed", ["{src}/", "{src}/./", "{link}"])
+    def test_destination_that_is_the_source_folder(self, tmp_src, tmp_path, typed):
+        (tmp_path / "link").symlink_to(tmp_src)
+        dst = typed.format(src=tmp_src, link=tmp_path / "link")
+        result = run_kosmokopy(src=tmp_src, dst=dst)
+        assert result["status"] == "error"
+        assert result["message"] == (
+            f"Source {tmp_src} and destination {dst} are the same folder, {tmp_src}; "
+            "choose a different destination"
+        )
+        assert sorted(p.name for p in tmp_src.iterdir()) == ["data.bin", "hello.txt", "notes.md", "subdir"]
+
+    @pytest.mark.parametrize("mode", ["folders", "files"])
+    def test_destination_inside_the_source_folder(self, tmp_src, tmp_path, mode):
+        (tmp_path / "link").symlink_to(tmp_src)
+        for dst in (tmp_src / "backup", f"{tmp_path / 'link'}/subdir/backup/"):
+            result = run_kosmokopy(src=tmp_src, dst=dst, mode=mode)
+            assert result["status"] == "error"
+            assert result["message"] == (
+                f"Destination {dst} is inside the source folder {tmp_src}, so the run would copy its own copies; "
+                "choose a destination outside it"
+            )
+        assert not (tmp_src / "backup").exists()
+        assert not (tmp_src / "subdir" / "backup").exists()
+
+    def test_case_only_counts_when_flagged(self, tmp_src):
+        shouted = tmp_src.with_name(tmp_src.name.upper())
+        assert run_kosmokopy(src=tmp_src, dst=shouted, case_insensitive_paths=True)["status"] == "error"
+        assert not shouted.exists()
+        assert run_kosmokopy(src=tmp_src, dst=shouted)["status"] == "finished"
+
+    def test_hardlinked_destination_file(self, tmp_src, tmp_dst):
+        """Only the hard-linked file is skipped; the rest are copied."""
+        dest_root = tmp_dst / tmp_src.name
+        dest_root.mkdir()
+        os.link(tmp_src / "hello.txt", dest_root / "hello.txt")
+
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, conflict="overwrite")
+        assert result["status"] == "finished"
+        assert result["copied"] == 5
+        assert result["skipped"] == [f"{tmp_src / 'hello.txt'}: source and destination are the same file"]
+        assert (tmp_src / "hello.txt").read_text() == "Hello, World!\n"
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Coalesced errors
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestCoalescedErrors:
+
+    @pytest.fixture
+    def blocked(self, tmp_path):
+        """1000 source files whose destination folder is a plain file."""
+        src = tmp_path / "many"
+        src.mkdir()
+        for i in range(1000):
+            (src / f"file{i:04}.txt").write_text(str(i))
+        dst = tmp_path / "dst"
+        dst.mkdir()
+        (dst / "many").write_text("not a folder")
+        return src, dst
+
+    def test_identical_failures_are_bounded(self, blocked, tmp_path):
+        """A run of identical failures ends in one summary entry."""
+        src, dst = blocked
+        out = tmp_path / "failed.kosmokopy-failed"
+        result = run_kosmokopy(src=src, dst=dst, failed_files_out=out)
+        assert result["status"] == "finished"
+        errors = result["errors"]
+        assert len(errors) == 21
+        assert all(isinstance(e, str) for e in errors[:20])
+        summary = errors[-1]
+        assert summary["coalesced"] is True
+        assert summary["count"] == 980
+        assert summary["message"].startswith("…and 980 more files failed with ")
+        # The failed-files list still names every file.
+        assert len([e for e in out.read_text().split("\n") if e]) == 1000
+
+    def test_no_coalesce_lists_every_error(self, blocked):
+        src, dst = blocked
+        result = run_kosmokopy(src=src, dst=dst, no_coalesce_errors=True)
+        assert len(result["errors"]) == 1000
+        assert all(isinstance(e, str) for e in result["errors"])
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Results per top-level folder
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestGroups:
+
+    @pytest.fixture
+    def backup(self, tmp_path):
+        src = tmp_path / "backup"
+        (src / "Photos" / "2024").mkdir(parents=True)
+        (src / "Documents").mkdir()
+        (src / "Projects").mkdir()
+        (src / "Photos" / "a.jpg").write_bytes(b"a" * 100)
+        (src / "Photos" / "2024" / "b.jpg").write_bytes(b"b" * 50)
+        (src / "Documents" / "cv.pdf").write_text("cv")
+        (src / "Projects" / "x.txt").write_text("x")
+        (src / "notes.txt").write_text("notes")
+        return src
+
+    def _by_name(self, result):
+        return {g["name"]: g for g in result["groups"]}
+
+    def test_each_folder_is_reported(self, backup, tmp_path):
+        dst = tmp_path / "dst"
+        (dst / "backup" / "Documents").mkdir(parents=True)
+        (dst / "backup" / "Documents" / "cv.pdf").write_text("cv")
+        (dst / "backup" / "Projects").write_text("not a folder")
+
+        result = run_kosmokopy(src=backup, dst=dst)
+        assert result["status"] == "finished"
+        assert [g["name"] for g in result["groups"]] == ["(root)", "Documents", "Photos", "Projects"]
+        groups = self._by_name(result)
+        assert groups["Photos"] == {
+            "name": "Photos", "files": 2, "copied": 2, "skipped": 0, "errors": 0, "bytes": 150,
+        }
+        assert groups["Documents"]["skipped"] == 1
+        assert groups["Projects"]["errors"] == 1
+        assert groups["(root)"]["copied"] == 1
+        assert sum(g["copied"] for g in result["groups"]) == result["copied"]
+
+    def test_flattened_runs_keep_source_groups(self, backup, tmp_path):
+        result = run_kosmokopy(src=backup, dst=tmp_path / "flat", mode="files")
+        groups = self._by_name(result)
+        assert groups["Photos"]["copied"] == 2
+        assert set(groups) == {"(root)", "Documents", "Photos", "Projects"}
+
+    def test_picked_files_group_by_parent(self, backup, tmp_path):
+        result = run_kosmokopy(
+            src_files=[backup / "Photos" / "a.jpg", backup / "notes.txt"],
+            dst=tmp_path / "picked",
+        )
+        assert [g["name"] for g in result["groups"]] == ["Photos", "backup"]
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Dated destination folders with retention
+# ═══════════════════════════════════════════════════════════════════════
+
+
+OLD_WEEKLIES = ["weekly-2025-12-31", "weekly-2026-01-07", "weekly-2024-06-30"]
+
+
+class TestRetention:
+
+    @pytest.fixture
+    def backups(self, tmp_path):
+        base = tmp_path / "backups"
+        base.mkdir()
+        for name in OLD_WEEKLIES + ["weekly-latest", "monthly-2020-01-01"]:
+            (base / name).mkdir()
+            (base / name / "old.txt").write_text(name)
+        return base
+
+    def _today_folder(self, base):
+        return base / f"weekly-{datetime.now(timezone.utc):%Y-%m-%d}"
+
+    def test_keeps_newest_dated_folders(self, tmp_src, backups):
+        result = run_kosmokopy(src=tmp_src, dst=f"{backups}/weekly-{{date}}", retain=2)
+        assert result["status"] == "finished"
+        assert result["copied"] == 6
+        today = self._today_folder(backups)
+        assert (today / tmp_src.name / "hello.txt").exists()
+        # Sorted by the date in the name: today and 2026-01-07 survive.
+        assert sorted(result["pruned"]) == [
+            str(backups / "weekly-2024-06-30"), str(backups / "weekly-2025-12-31"),
+        ]
+        remaining = sorted(p.name for p in backups.iterdir())
+        assert remaining == sorted([today.name, "weekly-2026-01-07", "weekly-latest", "monthly-2020-01-01"])
+
+    def test_dry_run_deletes_nothing(self, tmp_src, backups):
+        result = run_kosmokopy(
+            src=tmp_src, dst=f"{backups}/weekly-{{date}}", retain=1, retain_dry_run=True,
+        )
+        assert result["prune_dry_run"] is True
+        assert len(result["pruned"]) == 3
+        for name in OLD_WEEKLIES:
+            assert (backups / name / "old.txt").exists()
+
+    def test_pruned_folders_can_go_to_the_trash(self, tmp_src, backups, tmp_path):
+        share = tmp_path / "share"
+        result = run_kosmokopy(
+            src=tmp_src, dst=f"{backups}/weekly-{{date}}", retain=2, retain_to_trash=True,
+            env={"XDG_DATA_HOME": str(share)},
+        )
+        assert result["errors"] == []
+        assert len(result["pruned"]) == 2
+        trash = share / "Trash"
+        assert (trash / "files" / "weekly-2024-06-30" / "old.txt").read_text() == "weekly-2024-06-30"
+        info = (trash / "info" / "weekly-2025-12-31.trashinfo").read_text()
+        assert f"Path={backups}/weekly-2025-12-31\n" in info
+        assert not (backups / "weekly-2024-06-30").exists()
+
+    def test_no_pruning_after_errors(self, tmp_src, backups):
+        # Block one file so the run finishes with an error.
+        blocker = self._today_folder(backups) / tmp_src.name / "hello.txt"
+        blocker.mkdir(parents=True)
+        (blocker / "keep").write_text("x")
+        result = run_kosmokopy(
+            src=tmp_src, dst=f"{backups}/weekly-{{date}}", conflict="overwrite", retain=1,
+        )
+        assert result["errors"]
+        assert "pruned" not in result
+        for name in OLD_WEEKLIES:
+            assert (backups / name).exists()
+
+    def test_retain_needs_a_template(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, retain=2)
+        assert result["status"] == "error"
+        assert "{date}" in result["message"]
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Move from a read-only source
+# ═══════════════════════════════════════════════════════════════════════
+
+
+@pytest.fixture
+def ro_src(tmp_src, tmp_path):
+    """A read-only bind mount of tmp_src, or skip if one can't be made."""
+    mnt = tmp_path / "ro_mount"
+    mnt.mkdir()
+    ok = subprocess.run(["mount", "--bind", str(tmp_src), str(mnt)], capture_output=True)
+    if ok.returncode != 0:
+        pytest.skip("cannot bind-mount (needs root)")
+    ro = subprocess.run(["mount", "-o", "remount,bind,ro", str(mnt)], capture_output=True)
+    if ro.returncode != 0:
+        subprocess.run(["umount", str(mnt)])
+        pytest.skip("cannot remount read-only")
+    yield mnt
+    subprocess.run(["umount", str(mnt)])
+
+
+READ_ONLY = {"KOSMOKOPY_TEST_READ_ONLY_SOURCE": "1"}
+
+
+class TestReadOnlySourceMove:
+
+    def test_move_fails_by_default(self, tmp_src, tmp_dst):
+        """Without a fallback, moving a read-only source fails up front."""
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, move=True, env=READ_ONLY)
+        assert result["status"] == "error"
+        assert "read-only" in result["message"]
+        assert "--move-fallback copy" in result["message"]
+        assert list(tmp_dst.iterdir()) == []
+
+    def test_fallback_copy_reports_downgrade(self, tmp_src, tmp_dst):
+        """--move-fallback copy copies everything and says so."""
+        result = run_kosmokopy(
+            src=tmp_src, dst=tmp_dst, move=True, move_fallback="copy", env=READ_ONLY,
+        )
+        assert result["status"] == "finished"
+        assert result["copied"] == 6
+        assert result["errors"] == []
+        assert result["move_downgraded"] is True
+        # Sources are untouched
+        assert (tmp_src / "hello.txt").exists()
+
+    def test_plain_copy_has_no_downgrade_field(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, env=READ_ONLY)
+        assert result["status"] == "finished"
+        assert "move_downgraded" not in result
+
+    def test_real_read_only_mount_is_detected(self, ro_src, tmp_dst):
+        result = run_kosmokopy(src=ro_src, dst=tmp_dst, move=True)
+        assert result["status"] == "error"
+        assert "read-only" in result["message"]
+
+    def test_mid_run_read_only_collapses_errors(self, ro_src, tmp_dst):
+        """A source that turns read-only mid-run yields one summary line."""
+        # Pretend the up-front check passed, as if the source was remounted
+        # read-only after the transfer started.
+        result = run_kosmokopy(
+            src=ro_src, dst=tmp_dst, move=True,
+            env={"KOSMOKOPY_TEST_READ_ONLY_SOURCE": "0"},
+        )
+        assert result["status"] == "finished"
+        assert result["copied"] == 6
+        assert len(result["errors"]) == 1
+        assert "6 file(s)" in result["errors"][0]
+        assert (ro_src / "hello.txt").exists()
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Unwritable destination folders
+# ═══════════════════════════════════════════════════════════════════════
+
+
+@pytest.mark.skipif(os.geteuid() == 0, reason="permission bits do not stop root")
+class TestUnwritablePreflight:
+
+    @pytest.fixture
+    def locked(self, tmp_path):
+        """A source tree whose `raw` folder already exists at the
+        destination, read-only."""
+        src = tmp_path / "photos"
+        (src / "raw" / "2024").mkdir(parents=True)
+        (src / "edited").mkdir()
+        (src / "raw" / "a.cr2").write_text("a")
+        (src / "raw" / "2024" / "b.cr2").write_text("b")
+        (src / "edited" / "a.jpg").write_text("c")
+        (src / "notes.txt").write_text("d")
+        dst = tmp_path / "dst"
+        raw = dst / "photos" / "raw"
+        raw.mkdir(parents=True)
+        raw.chmod(0o555)
+        yield src, dst
+        raw.chmod(0o755)
+
+    def test_abort_is_the_default(self, locked):
+        src, dst = locked
+        result = run_kosmokopy(src=src, dst=dst)
+        assert result["status"] == "error"
+        assert "Nothing was copied" in result["message"]
+        assert str(dst / "photos" / "raw") + " (2 files)" in result["message"]
+        assert not (dst / "photos" / "notes.txt").exists()
+        assert not (dst / "photos" / "edited").exists()
+
+    def test_skip_leaves_out_the_subtree(self, locked):
+        src, dst = locked
+        result = run_kosmokopy(src=src, dst=dst, on_unwritable="skip")
+        assert result["status"] == "finished"
+        assert result["copied"] == 2
+        assert result["errors"] == []
+        assert len(result["skipped"]) == 2
+        assert all("is not writable" in s for s in result["skipped"])
+        assert result["unwritable"] == [{"dir": str(dst / "photos" / "raw"), "files": 2}]
+        assert (dst / "photos" / "notes.txt").read_text() == "d"
+        assert (dst / "photos" / "edited" / "a.jpg").read_text() == "c"
+
+    def test_continue_fails_file_by_file(self, locked):
+        src, dst = locked
+        result = run_kosmokopy(src=src, dst=dst, on_unwritable="continue")
+        assert result["status"] == "finished"
+        assert result["copied"] == 2
+        assert len(result["errors"]) == 2
+        assert result["unwritable"] == [{"dir": str(dst / "photos" / "raw"), "files": 2}]
+
+    def test_writable_destination_reports_nothing(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst)
+        assert result["status"] == "finished"
+        assert "unwritable" not in result
+
+
+class TestStaleArtifacts:
+
+    @pytest.fixture
+    def leftovers(self, tmp_src, tmp_dst):
+        """A destination an earlier run died in: half of hello.txt, a
+        probe file, and two files of ours in name only."""
+        root = tmp_dst / tmp_src.name
+        root.mkdir()
+        (root / "hello.txt.kosmokopy-partial").write_text("Hello, ")
+        (tmp_dst / ".kosmokopy-probe-4242").write_text("")
+        (root / ".kosmokopy.toml").write_text("keep me\n")
+        (tmp_dst / ".kosmokopy-probe-notes").write_text("keep me\n")
+        return tmp_src, tmp_dst, root
+
+    def test_the_cli_refuses_to_guess(self, leftovers):
+        src, dst, root = leftovers
+        result = run_kosmokopy(src=src, dst=dst, mode="folders")
+        assert result["status"] == "error"
+        assert result["kind"] == "stale-artifacts"
+        assert "1 partial copy and 1 probe file" in result["message"]
+        assert not (root / "hello.txt").exists()
+        assert (root / "hello.txt.kosmokopy-partial").exists()
+
+    def test_clean_removes_only_our_own_files(self, leftovers):
+        src, dst, root = leftovers
+        result = run_kosmokopy(src=src, dst=dst, mode="folders", stale_artifacts="clean")
+        assert result["status"] == "finished"
+        assert result["copied"] == 6
+        assert result["stale_artifacts"] == {"partials": 1, "probes": 1, "choice": "clean", "removed": 2, "failed": []}
+        assert not (root / "hello.txt.kosmokopy-partial").exists()
+        assert not (dst / ".kosmokopy-probe-4242").exists()
+        assert (root / ".kosmokopy.toml").read_text() == "keep me\n"
+        assert (dst / ".kosmokopy-probe-notes").read_text() == "keep me\n"
+        assert (root / "hello.txt").read_text() == "Hello, World!\n"
+
+    def test_ignore_leaves_them(self, leftovers):
+        src, dst, root = leftovers
+        result = run_kosmokopy(src=src, dst=dst, mode="folders", stale_artifacts="ignore")
+        assert result["status"] == "finished"
+        assert result["stale_artifacts"]["choice"] == "ignore"
+        assert (dst / ".kosmokopy-probe-4242").exists()
+        assert (root / "hello.txt").read_text() == "Hello, World!\n"
+        # Copying hello.txt wrote its own partial copy over the old one.
+        assert not (root / "hello.txt.kosmokopy-partial").exists()
+
+    def test_resume_continues_a_matching_partial_copy(self, leftovers):
+        src, dst, root = leftovers
+        result = run_kosmokopy(src=src, dst=dst, mode="folders", stale_artifacts="resume")
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["stale_artifacts"]["choice"] == "resume"
+        assert (root / "hello.txt").read_text() == "Hello, World!\n"
+        assert not (root / "hello.txt.kosmokopy-partial").exists()
+
+    def test_resume_starts_over_when_the_partial_copy_differs(self, leftovers):
+        src, dst, root = leftovers
+        (root / "hello.txt.kosmokopy-partial").write_text("Howdy, ")
+        result = run_kosmokopy(src=src, dst=dst, mode="folders", stale_artifacts="resume")
+        assert result["status"] == "finished"
+        assert (root / "hello.txt").read_text() == "Hello, World!\n"
+
+    def test_resume_continues_only_the_partial_copies_found(self, leftovers):
+        src, dst, root = leftovers
+        # Below the two levels searched, so never offered for resuming.
+        (root / "subdir").mkdir()
+        (root / "subdir" / "nested.txt.kosmokopy-partial").write_text("I am ")
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(src), "--dst", str(dst), "--stale-artifacts", "resume"],
+            capture_output=True, text=True, timeout=60, env=dict(os.environ, KOSMOKOPY_DEBUG="1"),
+        )
+        assert json.loads(proc.stdout)["stale_artifacts"]["partials"] == 1
+        assert "resuming {}".format(root / "hello.txt") in proc.stderr
+        assert "nested.txt at" not in proc.stderr
+        assert (root / "subdir" / "nested.txt").read_text() == "I am nested.\n"
+
+    def test_a_clean_destination_reports_nothing(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst)
+        assert result["status"] == "finished"
+        assert "stale_artifacts" not in result
+        assert not list(tmp_dst.rglob("*.kosmokopy-partial"))
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Fixture tree round trips
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestFixtureTree:
+
+    def test_copy_reproduces_every_file(self, tmp_fixture_tree, tmp_dst):
+        src, hashes = tmp_fixture_tree
+        result = run_kosmokopy(src=src, dst=tmp_dst)
+        assert result["status"] == "finished"
+        assert result["copied"] == len(hashes) == 8
+        assert result["errors"] == []
+        assert tree_hashes(tmp_dst / "library") == hashes
+        assert not (tmp_dst / "library" / "docs" / "link to readme").exists()
+
+    def test_second_copy_finds_no_differences(self, tmp_fixture_tree, tmp_dst):
+        src, hashes = tmp_fixture_tree
+        run_kosmokopy(src=src, dst=tmp_dst)
+        again = run_kosmokopy(src=src, dst=tmp_dst)
+        assert again["copied"] == 0
+        assert again["errors"] == []
+        identical = [s for s in again["skipped"] if not s.endswith(": symbolic link, skipped")]
+        assert len(identical) == len(hashes)
+        assert all(s.endswith(": identical at destination") for s in identical)
+
+    @pytest.mark.parametrize("strategy", ["per-file", "all-then-delete"])
+    def test_move_empties_source_and_completes_destination(self, tmp_fixture_tree, tmp_dst, strategy):
+        src, hashes = tmp_fixture_tree
+        result = run_kosmokopy(src=src, dst=tmp_dst, move=True, move_strategy=strategy)
+        assert result["status"] == "finished"
+        assert result["copied"] == len(hashes)
+        assert tree_hashes(src) == {}
+        assert tree_hashes(tmp_dst / "library") == hashes
+
+    @requires_rsync
+    def test_rsync_copy_reproduces_every_file(self, tmp_fixture_tree, tmp_dst):
+        src, hashes = tmp_fixture_tree
+        result = run_kosmokopy(src=src, dst=tmp_dst, method="rsync")
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert tree_hashes(tmp_dst / "library") == hashes
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  --parallel
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestParallel:
+
+    def _many(self, root, count):
+        for i in range(count):
+            sub = root / "many" / "d{}".format(i % 5)
+            sub.mkdir(parents=True, exist_ok=True)
+            (sub / "f{}.txt".format(i)).write_text("file {}\n".format(i) * (i + 1))
+        return root / "many"
+
+    @pytest.mark.parametrize("workers", [1, 8])
+    def test_copies_every_file_once(self, tmp_path, tmp_dst, workers):
+        src = self._many(tmp_path, 60)
+        result = run_kosmokopy(src=src, dst=tmp_dst, parallel=workers)
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["copied"] == 60
+        assert tree_hashes(tmp_dst / "many") == tree_hashes(src)
+
+    def test_same_named_files_get_distinct_names(self, tmp_path, tmp_dst):
+        files = []
+        for i in range(12):
+            d = tmp_path / "src{}".format(i)
+            d.mkdir()
+            (d / "same.txt").write_text("copy {}\n".format(i))
+            files.append(d / "same.txt")
+        result = run_kosmokopy(src_files=files, dst=tmp_dst, mode="files", conflict="rename", parallel=8)
+        assert result["status"] == "finished"
+        assert result["copied"] == 12
+        contents = sorted(f.read_text() for f in tmp_dst.iterdir())
+        assert contents == sorted("copy {}\n".format(i) for i in range(12))
+
+    def test_move_empties_the_source(self, tmp_path, tmp_dst):
+        src = self._many(tmp_path, 30)
+        hashes = tree_hashes(src)
+        result = run_kosmokopy(src=src, dst=tmp_dst, move=True, parallel=8)
+        assert result["status"] == "finished"
+        assert result["copied"] == 30
+        assert tree_hashes(tmp_dst / "many") == hashes
+
+    def test_out_of_range_counts_are_refused(self, tmp_src, tmp_dst):
+        for bad in ["0", "33", "many"]:
+            proc = subprocess.run(
+                [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst), "--parallel", bad],
+                capture_output=True, text=True, timeout=60,
+            )
+            assert proc.returncode == 1
+            assert "--parallel expects" in proc.stderr
+        assert not any(tmp_dst.iterdir())
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  --detail-limit
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestDetailLimit:
+
+    def test_long_skip_lists_are_cut_short(self, tmp_src, tmp_dst):
+        first = run_kosmokopy(src=tmp_src, dst=tmp_dst)
+        again = run_kosmokopy(src=tmp_src, dst=tmp_dst, detail_limit=2)
+        assert again["status"] == "finished"
+        assert len(again["skipped"]) == 2
+        assert again["truncated"] == {
+            "skipped": {"shown": 2, "total": first["copied"], "file": None},
+        }
+        assert "truncated" not in first
+
+    def test_outcomes_count_every_file(self, tmp_src, tmp_dst):
+        first = run_kosmokopy(src=tmp_src, dst=tmp_dst)
+        again = run_kosmokopy(src=tmp_src, dst=tmp_dst, detail_limit=2)
+        assert first["outcomes"]["copied"] == first["copied"]
+        assert again["outcomes"] == {
+            "copied": 0, "identical": first["copied"], "conflicts": 0,
+            "skipped": 0, "excluded": 0, "failed": 0,
+        }
+
+    def test_the_rest_are_written_out_when_logging(self, tmp_src, tmp_dst, tmp_path):
+        env = {"KOSMOKOPY_HOME": str(tmp_path / "kk"), "KOSMOKOPY_DEBUG": "1"}
+        first = run_kosmokopy(src=tmp_src, dst=tmp_dst, env=env)
+        again = run_kosmokopy(src=tmp_src, dst=tmp_dst, detail_limit=2, duplicate_window=0, env=env)
+        spilled = Path(again["truncated"]["skipped"]["file"]).read_text().splitlines()
+        assert len(spilled) == first["copied"] - 2
+        assert not set(spilled) & set(again["skipped"])
+        assert all(s.endswith(": identical at destination") for s in spilled)
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Scan limits
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestScanLimit:
+
+    @pytest.fixture
+    def sprawling(self, tmp_path):
+        """A tree both deep and wide: 5 levels, 4 files at each."""
+        src = tmp_path / "sprawl"
+        level = src
+        for depth in range(5):
+            level = level / "d{}".format(depth)
+            level.mkdir(parents=True)
+            for n in range(4):
+                (level / "f{}.txt".format(n)).write_text("{} {}\n".format(depth, n))
+        return src
+
+    def test_abort_stops_before_copying(self, sprawling, tmp_dst):
+        result = run_kosmokopy(src=sprawling, dst=tmp_dst, scan_limit=5, scan_limit_action="abort")
+        assert result["status"] == "error"
+        assert result["kind"] == "scan-limit"
+        assert result["message"].startswith("Scan stopped: the scan has found 6 file(s)")
+        assert not any(tmp_dst.iterdir())
+
+    def test_continue_copies_everything_and_reports_it(self, sprawling, tmp_dst):
+        result = run_kosmokopy(src=sprawling, dst=tmp_dst, scan_limit=5, scan_limit_action="continue")
+        assert result["status"] == "finished"
+        assert result["copied"] == 20
+        report = result["scan_limit"]
+        assert (report["files"], report["by_time"], report["continued"]) == (6, False, True)
+        assert "/sprawl/d0/d1" in report["deepest"]
+        assert "Scan passed its file limit at 6 file(s)" in result["summary"]["text"]
+
+    @pytest.mark.parametrize("answer, finished", [("y\n", True), ("n\n", False), ("", False)])
+    def test_ask_reads_the_answer_from_stdin(self, sprawling, tmp_dst, answer, finished):
+        result = run_kosmokopy(src=sprawling, dst=tmp_dst, scan_limit=5, stdin=answer)
+        assert result["status"] == ("finished" if finished else "error")
+        assert bool(list(tmp_dst.rglob("*.txt"))) == finished
+
+    def test_under_the_limit_nothing_is_asked(self, sprawling, tmp_dst):
+        result = run_kosmokopy(src=sprawling, dst=tmp_dst, scan_limit=20, scan_limit_action="abort")
+        assert result["status"] == "finished"
+        assert "scan_limit" not in result
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  File names that aren't UTF-8
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestNonUtf8Names:
+
+    NAME = b"a b\xff.TXT"
+
+    @pytest.fixture
+    def latin1_src(self, tmp_path):
+        src = tmp_path / "src"
+        (src / "sub").mkdir(parents=True)
+        path = os.path.join(os.fsencode(src / "sub"), self.NAME)
+        with open(path, "wb") as f:
+            f.write(b"not utf-8\n")
+        return src
+
+    def _names(self, root):
+        return sorted(os.listdir(os.fsencode(root / "src" / "sub")))
+
+    def test_copy_keeps_the_bytes(self, latin1_src, tmp_dst):
+        result = run_kosmokopy(src=latin1_src, dst=tmp_dst)
+        assert result["status"] == "finished"
+        assert result["copied"] == 1
+        assert self._names(tmp_dst) == [self.NAME]
+
+    def test_rules_rename_around_the_bad_byte(self, latin1_src, tmp_dst):
+        result = run_kosmokopy(
+            src=latin1_src, dst=tmp_dst, strip_spaces=True, rename_rule=["case-fold-extension"],
+        )
+        assert result["status"] == "finished"
+        assert self._names(tmp_dst) == [b"ab\xff.txt"]
+        assert result["renamed_samples"] == [
+            {"from": "src/sub/a b\\xff.TXT", "to": "src/sub/ab\\xff.txt", "non_utf8": True},
+        ]
+
+    def test_move_deletes_the_source(self, latin1_src, tmp_dst):
+        result = run_kosmokopy(src=latin1_src, dst=tmp_dst, move=True)
+        assert result["status"] == "finished"
+        assert self._names(tmp_dst) == [self.NAME]
+        # Emptied of its one file, the folder goes too.
+        assert not (latin1_src / "sub").exists()
+
+    def test_list_only_prints_the_bytes(self, latin1_src):
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--list-only", "--src", str(latin1_src)],
+            capture_output=True, timeout=60,
+        )
+        assert proc.returncode == 0
+        assert proc.stdout == os.path.join(os.fsencode(latin1_src / "sub"), self.NAME) + b"\n"
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Per-file detail reports
+# ═══════════════════════════════════════════════════════════════════════
+
+
+class TestDetailReport:
+
+    AWKWARD = 'a, "quoted"\nname.txt'
+
+    def _rows(self, path):
+        with open(path, newline="") as f:
+            return list(csv.DictReader(f))
+
+    def test_csv_survives_commas_quotes_and_newlines(self, tmp_src, tmp_dst, tmp_path):
+        (tmp_src / self.AWKWARD).write_text("awkward\n")
+        out = tmp_path / "report.csv"
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, detail_report=out)
+        assert result["status"] == "finished"
+        rows = self._rows(out)
+        assert len(rows) == 7
+        awkward = next(row for row in rows if row["source"] == str(tmp_src / self.AWKWARD))
+        assert awkward["destination"] == str(tmp_dst / tmp_src.name / self.AWKWARD)
+        assert awkward["action"] == "copied"
+        assert awkward["size"] == "8"
+        assert result["detail_report"] == {"path": str(out), "records": 7}
+
+    def test_counts_match_the_summary(self, tmp_src_with_exclusions, tmp_dst, tmp_path):
+        src = tmp_src_with_exclusions
+        run_kosmokopy(src=src, dst=tmp_dst, exclude=["/cache"])
+        (src / "keep.txt").write_text("changed\n")
+        (src / "new.txt").write_text("new\n")
+        out = tmp_path / "report.csv"
+        result = run_kosmokopy(
+            src=src, dst=tmp_dst, exclude=["/cache", "skip_me.log", "~/build*"], detail_report=out,
+        )
+        summary = result["summary"]
+        assert summary["skipped"] > 0
+        actions = [row["action"] for row in self._rows(out)]
+        count = lambda *names: sum(actions.count(n) for n in names)
+        assert count("copied", "renamed") == summary["copied"] == 1
+        assert count("skipped", "identical", "conflict-skipped") == summary["skipped"]
+        assert count("excluded") == summary["excluded_files"] + summary["excluded_dirs"] == 3
+        assert count("error") == summary["errors"] == 0
+
+    def test_exclusions_name_their_pattern(self, tmp_src_with_exclusions, tmp_dst, tmp_path):
+        out = tmp_path / "report.csv"
+        run_kosmokopy(
+            src=tmp_src_with_exclusions, dst=tmp_dst, exclude=["/cache", "skip_me.log"], detail_report=out,
+        )
+        reasons = {Path(row["source"]).name: row["reason"] for row in self._rows(out) if row["action"] == "excluded"}
+        assert reasons == {"cache": "folder excluded by /cache", "skip_me.log": "excluded by skip_me.log"}
+
+    def test_renames_say_what_to(self, tmp_src, tmp_dst, tmp_path):
+        run_kosmokopy(src=tmp_src, dst=tmp_dst)
+        (tmp_src / "hello.txt").write_text("Hello again\n")
+        out = tmp_path / "report.csv"
+        run_kosmokopy(src=tmp_src, dst=tmp_dst, conflict="rename", detail_report=out)
+        renamed = [row for row in self._rows(out) if row["action"] == "renamed"]
+        assert len(renamed) == 1
+        assert renamed[0]["source"] == str(tmp_src / "hello.txt")
+        assert renamed[0]["reason"] == f"renamed to {Path(renamed[0]['destination']).name}"
+
+    def test_jsonl_and_tsv_hold_the_same_records(self, tmp_src, tmp_path):
+        by_format = {}
+        for ext in ("jsonl", "tsv"):
+            out = tmp_path / f"report.{ext}"
+            result = run_kosmokopy(src=tmp_src, dst=tmp_path / f"dst-{ext}", detail_report=out)
+            assert result["detail_report"]["records"] == 6
+            by_format[ext] = out.read_text().splitlines()
+        records = [json.loads(line) for line in by_format["jsonl"]]
+        assert {r["action"] for r in records} == {"copied"}
+        assert all(r["hash"] == "sha256:" + sha256_of_file(r["source"]) for r in records)
+        assert by_format["tsv"][0].split("\t") == ["source", "destination", "action", "reason", "size", "hash"]
+        assert len(by_format["tsv"]) == 7
+
+    def test_hash_verification_fills_the_hash_column(self, tmp_src, tmp_dst, tmp_path):
+        out = tmp_path / "report.csv"
+        run_kosmokopy(src=tmp_src, dst=tmp_dst, verify="hash", detail_report=out)
+        rows = self._rows(out)
+        assert len(rows) == 6
+        for row in rows:
+            assert row["hash"] == "sha256:" + sha256_of_file(row["source"])
+
+    def test_the_hash_cache_fills_the_hash_column(self, tmp_src, tmp_dst, tmp_path):
+        cache = tmp_path / "hashes"
+        out = tmp_path / "first.csv"
+        run_kosmokopy(src=tmp_src, dst=tmp_dst, hash_cache=cache, detail_report=out)
+        assert all(row["hash"] == "sha256:" + sha256_of_file(row["source"]) for row in self._rows(out))
+        # The second run finds every file identical from the cache alone.
+        out = tmp_path / "second.csv"
+        run_kosmokopy(src=tmp_src, dst=tmp_dst, hash_cache=cache, detail_report=out)
+        rows = self._rows(out)
+        assert {row["action"] for row in rows} == {"identical"}
+        assert all(row["hash"] == "sha256:" + sha256_of_file(row["source"]) for row in rows)
+
+    def test_other_extensions_are_refused(self, tmp_src, tmp_dst, tmp_path):
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", str(tmp_dst),
+             "--detail-report", str(tmp_path / "report.txt")],
+            capture_output=True, text=True, timeout=60,
+        )
+        assert proc.returncode == 1
+        assert "--detail-report expects" in proc.stderr
+        assert not any(tmp_dst.iterdir())
@@ -0,0 +1,58 @@
+"""
+Settings and state locations.
+
+``KOSMOKOPY_HOME`` must move every directory the binary writes to, so a
+run with a throwaway ``HOME`` leaves that ``HOME`` exactly as it was.
+"""
+
+import json
+import os
+import subprocess
+
+from conftest import KOSMOKOPY_BIN, run_kosmokopy
+
+
+def _print_paths(env):
+    proc = subprocess.run(
+        [KOSMOKOPY_BIN, "--cli", "--print-paths"],
+        capture_output=True, text=True, timeout=30, env=env,
+    )
+    assert proc.returncode == 0, proc.stderr
+    return json.loads(proc.stdout)
+
+
+def _isolated_env(tmp_path, **extra):
+    home = tmp_path / "home"
+    home.mkdir()
+    env = {k: v for k, v in os.environ.items() if not k.startswith("XDG_")}
+    env.update(HOME=str(home), **extra)
+    return home, env
+
+
+class TestPaths:
+
+    def test_override_moves_every_directory(self, tmp_path):
+        kk_home = tmp_path / "kk"
+        home, env = _isolated_env(tmp_path, KOSMOKOPY_HOME=str(kk_home))
+        paths = _print_paths(env)
+        assert set(paths) == {"config", "state", "cache", "data"}
+        for name, path in paths.items():
+            assert path == str(kk_home / name)
+
+    def test_xdg_defaults_without_override(self, tmp_path):
+        home, env = _isolated_env(tmp_path)
+        env.pop("KOSMOKOPY_HOME", None)
+        env["XDG_CACHE_HOME"] = str(tmp_path / "xdg_cache")
+        paths = _print_paths(env)
+        assert paths["config"] == str(home / ".config" / "kosmokopy")
+        assert paths["state"] == str(home / ".local" / "state" / "kosmokopy")
+        assert paths["cache"] == str(tmp_path / "xdg_cache" / "kosmokopy")
+        assert paths["data"] == str(home / ".local" / "share" / "kosmokopy")
+
+    def test_transfer_leaves_home_untouched(self, tmp_src, tmp_dst, tmp_path):
+        kk_home = tmp_path / "kk"
+        home, env = _isolated_env(tmp_path, KOSMOKOPY_HOME=str(kk_home))
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, env=env)
+        assert result["status"] == "finished"
+        _print_paths(env)
+        assert list(home.iterdir()) == []
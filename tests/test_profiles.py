@@ -0,0 +1,64 @@
+"""
+Named transfer profiles, run with ``--cli --profile <name>``.
+
+Profiles are written here the way the window saves them, into the
+``profiles`` folder of a per-test ``KOSMOKOPY_HOME``.
+"""
+
+from conftest import run_kosmokopy
+
+
+def _home(tmp_path):
+    return {"KOSMOKOPY_HOME": str(tmp_path / "kk")}
+
+
+def _save_profile(tmp_path, name, text):
+    folder = tmp_path / "kk" / "config" / "profiles"
+    folder.mkdir(parents=True, exist_ok=True)
+    (folder / f"{name}.toml").write_text(text)
+
+
+class TestProfiles:
+
+    def test_profile_supplies_the_whole_run(self, tmp_src, tmp_dst, tmp_path):
+        _save_profile(tmp_path, "photos-to-nas", (
+            f'source = "{tmp_src}"\n'
+            f'destination = "{tmp_dst}"\n'
+            'mode = "files"\n'
+            'method = "standard"\n'
+            'exclusions = ["/subdir", "~*.md"]\n'
+        ))
+        result = run_kosmokopy(profile="photos-to-nas", mode=None, method=None, env=_home(tmp_path))
+        assert result["status"] == "finished", result
+        assert sorted(p.name for p in tmp_dst.iterdir()) == ["data.bin", "hello.txt"]
+
+    def test_flags_override_the_profile(self, tmp_src, tmp_dst, tmp_path):
+        other = tmp_path / "elsewhere"
+        other.mkdir()
+        _save_profile(tmp_path, "weekly", (
+            f'source = "{tmp_src}"\n'
+            f'destination = "{other}"\n'
+            'mode = "files"\n'
+            'exclusions = ["/subdir"]\n'
+        ))
+        result = run_kosmokopy(
+            profile="weekly", dst=tmp_dst, mode="folders", exclude=["~*.bin"], env=_home(tmp_path),
+        )
+        assert result["status"] == "finished", result
+        assert list(other.iterdir()) == []
+        # --exclude replaced the profile's exclusions rather than adding to them.
+        assert (tmp_dst / "source" / "subdir" / "level2" / "bottom.txt").exists()
+        assert not (tmp_dst / "source" / "data.bin").exists()
+
+    def test_a_file_source_is_a_one_file_run(self, tmp_src, tmp_dst, tmp_path):
+        _save_profile(tmp_path, "one", f'source = "{tmp_src / "hello.txt"}"\n')
+        result = run_kosmokopy(profile="one", dst=tmp_dst, env=_home(tmp_path))
+        assert result["status"] == "finished", result
+        assert [p.name for p in tmp_dst.iterdir()] == ["hello.txt"]
+
+    def test_unknown_profile_names_the_saved_ones(self, tmp_dst, tmp_path):
+        _save_profile(tmp_path, "weekly", "")
+        _save_profile(tmp_path, "photos", "")
+        result = run_kosmokopy(profile="monthly", dst=tmp_dst, env=_home(tmp_path))
+        assert result["status"] == "error"
+        assert "no profile named 'monthly'; saved profiles: photos, weekly" in result["message"]
@@ -5,13 +5,16 @@ All file operations are performed through ``kosmokopy --cli``.
 Results are verified in Python via SSH helper functions.
 """
 
+import json
 import os
+import stat
 import subprocess
 from pathlib import Path
 
 import pytest
 
 from conftest import (
+    KOSMOKOPY_BIN,
     run_kosmokopy,
     requires_remote,
     requires_remote2,
@@ -590,6 +593,51 @@ class TestRemoteConflicts:
         assert remote_file_exists(host, rdir + "/src/file.txt")
         assert remote_file_exists(host, rdir + "/src/file_1.txt")
 
+    @staticmethod
+    def _two_sources_one_name(tmp_path):
+        """Two different files that land on the same path in Files Only mode."""
+        src = tmp_path / "src"
+        (src / "a").mkdir(parents=True)
+        (src / "b").mkdir(parents=True)
+        (src / "a" / "same.txt").write_text("from a\n")
+        (src / "b" / "same.txt").write_text("from b\n")
+        return src
+
+    @pytest.mark.parametrize("method", ["standard", "rsync"])
+    @pytest.mark.parametrize("recheck", [False, True])
+    @pytest.mark.parametrize("no_recheck_rename", [False, True])
+    def test_duplicate_destination_renamed(self, tmp_path, remote_dest, method, recheck, no_recheck_rename):
+        """The second file mapping to a path uploaded this run is renamed."""
+        host, rdir = remote_dest
+        src = self._two_sources_one_name(tmp_path)
+        result = run_kosmokopy(
+            src=src, dst="{}:{}".format(host, rdir), mode="files",
+            conflict="rename", method=method, recheck_conflicts=recheck,
+            no_recheck_rename=no_recheck_rename,
+        )
+        assert result["status"] == "finished"
+        assert result["copied"] == 2
+        contents = {remote_read(host, rdir + "/same.txt"), remote_read(host, rdir + "/same_1.txt")}
+        assert contents == {b"from a\n", b"from b\n"}
+
+    @pytest.mark.parametrize("method", ["standard", "rsync"])
+    def test_duplicate_destination_skipped(self, tmp_path, remote_dest, method):
+        """With Skip, the second file is skipped rather than overwriting the first."""
+        host, rdir = remote_dest
+        src = self._two_sources_one_name(tmp_path)
+        result = run_kosmokopy(
+            src=src, dst="{}:{}".format(host, rdir), mode="files",
+            conflict="skip", method=method,
+        )
+        assert result["status"] == "finished"
+        assert result["copied"] == 1
+        assert len(result["skipped"]) == 1
+        # The skipped file is the one whose content did not land remotely
+        skipped_path = Path(result["skipped"][0].split(": ")[0])
+        uploaded = remote_read(host, rdir + "/same.txt")
+        assert uploaded in {b"from a\n", b"from b\n"}
+        assert skipped_path.read_bytes() != uploaded
+
 
 # ═══════════════════════════════════════════════════════════════════════
 #  Remote exclusions
@@ -644,3 +692,1014 @@ class TestStripSpacesRemote:
         assert remote_file_exists(host, rdir + "/sourcespaces/myfile.txt")
         assert remote_file_exists(host, rdir + "/sourcespaces/anotherdoc.pdf")
         assert remote_file_exists(host, rdir + "/sourcespaces/subfolder/innerfile.txt")
+
+    def test_strip_spaces_keeps_spaced_destination_base(self, tmp_src_with_spaces, remote_dest):
+        """Only components below the destination base are renamed."""
+        host, rdir = remote_dest
+        spaced = rdir + "/My Backups"
+        result = run_kosmokopy(
+            src=tmp_src_with_spaces,
+            dst="{}:{}".format(host, spaced),
+            strip_spaces=True,
+        )
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+
+        assert remote_file_exists(host, spaced + "/sourcespaces/myfile.txt")
+        assert remote_file_exists(host, spaced + "/sourcespaces/subfolder/innerfile.txt")
+        assert not remote_file_exists(host, rdir + "/MyBackups")
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Host key failures (a stand-in ssh; no remote host needed)
+# ═══════════════════════════════════════════════════════════════════════
+
+_CHANGED_KEY_STDERR = """\
+@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@
+@    WARNING: REMOTE HOST IDENTIFICATION HAS CHANGED!     @
+@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@
+IT IS POSSIBLE THAT SOMEONE IS DOING SOMETHING NASTY!
+Host key for fakehost has changed and you have requested strict checking.
+Host key verification failed.
+"""
+
+_UNKNOWN_KEY_STDERR = """\
+No ED25519 host key is known for fakehost and you have requested strict checking.
+Host key verification failed.
+"""
+
+
+def _run_with_failing_ssh(tmp_path, stderr, env=None, **kwargs):
+    """Upload through an ssh that fails with *stderr*; returns the result
+    and the arguments each ssh call got."""
+    bin_dir = tmp_path / "bin"
+    bin_dir.mkdir()
+    (tmp_path / "ssh_stderr").write_text(stderr)
+    log = tmp_path / "ssh_calls"
+    ssh = bin_dir / "ssh"
+    ssh.write_text(
+        "#!/bin/sh\n"
+        'echo "$@" >> {}\n'.format(_sq(str(log)))
+        + "cat {} >&2\n".format(_sq(str(tmp_path / "ssh_stderr")))
+        + "exit 255\n"
+    )
+    ssh.chmod(0o755)
+    src = tmp_path / "src"
+    src.mkdir()
+    (src / "a.txt").write_text("a")
+    env = {"PATH": "{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]), **(env or {})}
+    result = run_kosmokopy(src=src, dst="fakehost:/srv/backup", env=env, **kwargs)
+    return result, log.read_text().splitlines()
+
+
+def _control_path(call):
+    """The ControlPath an ssh call was given."""
+    args = call.split()
+    return next(a for a in args if a.startswith("ControlPath="))[len("ControlPath="):]
+
+
+class TestHostKeyErrors:
+
+    def _run(self, tmp_path, stderr, **kwargs):
+        return _run_with_failing_ssh(tmp_path, stderr, **kwargs)
+
+    def test_changed_key_is_refused_with_a_warning(self, tmp_path):
+        result, _ = self._run(tmp_path, _CHANGED_KEY_STDERR)
+        assert result["status"] == "error"
+        assert result["kind"] == "host-key-changed"
+        assert result["message"].startswith("WARNING: the host key for 'fakehost' has changed")
+        assert "ssh-keygen -R fakehost" in result["message"]
+
+    def test_unknown_host_says_how_to_trust_it(self, tmp_path):
+        result, _ = self._run(tmp_path, _UNKNOWN_KEY_STDERR)
+        assert result["status"] == "error"
+        assert result["kind"] == "host-key-unknown"
+        assert "--accept-new-hostkeys" in result["message"]
+
+    def test_accept_new_hostkeys_is_passed_to_ssh(self, tmp_path):
+        result, calls = self._run(tmp_path, _UNKNOWN_KEY_STDERR, accept_new_hostkeys=True)
+        assert result["kind"] == "host-key-unknown"
+        assert "StrictHostKeyChecking=accept-new" in calls[0]
+
+    def test_other_failures_have_no_kind(self, tmp_path):
+        result, _ = self._run(tmp_path, "ssh: connect to host fakehost port 22: Connection refused\n")
+        assert result["status"] == "error"
+        assert "kind" not in result
+        assert "Connection refused" in result["message"]
+
+
+class TestControlSockets:
+    """Where the ssh connection sockets go (a stand-in ssh records it)."""
+
+    def test_default_is_a_private_folder_in_the_runtime_dir(self, tmp_path):
+        run = tmp_path / "run"
+        (run / "kosmokopy").mkdir(parents=True, mode=0o755)
+        os.chmod(run / "kosmokopy", 0o755)
+        _, calls = _run_with_failing_ssh(tmp_path, "", env={"XDG_RUNTIME_DIR": str(run)})
+        path = _control_path(calls[0])
+        assert path.startswith(str(run / "kosmokopy" / "ssh-"))
+        assert path.endswith("-%h_%p_%r")
+        assert stat.S_IMODE((run / "kosmokopy").stat().st_mode) == 0o700
+
+    def test_without_a_runtime_dir_a_private_temp_folder_is_used_and_removed(self, tmp_path):
+        tmp = tmp_path / "tmp"
+        tmp.mkdir()
+        _, calls = _run_with_failing_ssh(tmp_path, "", env={"XDG_RUNTIME_DIR": "", "TMPDIR": str(tmp)})
+        path = _control_path(calls[0])
+        assert path.startswith(str(tmp / "kosmokopy-ssh-"))
+        assert "/tmp/kosmokopy_ssh_" not in calls[0]
+        assert list(tmp.iterdir()) == []
+
+    def test_a_chosen_folder_is_used_for_every_call(self, tmp_path):
+        chosen = tmp_path / "sockets"
+        chosen.mkdir(mode=0o750)
+        os.chmod(chosen, 0o750)
+        _, calls = _run_with_failing_ssh(tmp_path, "", ssh_control_dir=chosen)
+        assert calls
+        for call in calls:
+            assert _control_path(call).startswith(str(chosen / "ssh-"))
+        # A folder the user named is left as it was.
+        assert stat.S_IMODE(chosen.stat().st_mode) == 0o750
+
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Printed commands
+# ═══════════════════════════════════════════════════════════════════════
+
+_STAGES = ["scanning", "preflight", "transferring", "verifying", "finalizing"]
+
+
+def _recording_fakes(tmp_path):
+    """``ssh`` and ``scp`` stand-ins that log the argv they were given,
+    NUL-separated with each call ending in ``\\x1e\\n``, answer the clock
+    check and otherwise quietly succeed."""
+    bin_dir = tmp_path / "bin"
+    bin_dir.mkdir()
+    log = tmp_path / "argv_log"
+    for name in ("ssh", "scp"):
+        fake = bin_dir / name
+        fake.write_text(
+            "#!/bin/sh\n"
+            "{{ printf '%s\\0' \"${{0##*/}}\" \"$@\"; printf '\\036\\n'; }} >> {}\n".format(_sq(str(log)))
+            + 'for a; do cmd="$a"; done\n'
+            + 'if [ "$cmd" = "date +%s" ]; then date +%s; fi\n'
+            + "exit 0\n"
+        )
+        fake.chmod(0o755)
+    return bin_dir, log
+
+
+def _logical_line(text):
+    """*text* up to its first newline outside single quotes."""
+    quoted = False
+    i = 0
+    while i < len(text):
+        if text[i] == "\\" and not quoted:
+            i += 2
+            continue
+        if text[i] == "'":
+            quoted = not quoted
+        elif text[i] == "\n" and not quoted:
+            return text[:i]
+        i += 1
+    return text
+
+
+def _printed_commands(stderr):
+    """``(number, stage, argv)`` for each command ``--print-commands``
+    printed, argv as bytes."""
+    import re
+    import shlex
+
+    text = stderr.decode("utf-8", "surrogateescape")
+    parts = re.split(r"^\[kosmokopy\] command (\d+) \((\w+(?:, suppressed)?)\): ", text, flags=re.M)[1:]
+    return [
+        (int(number), stage, [os.fsencode(word) for word in shlex.split(_logical_line(body))])
+        for number, stage, body in zip(parts[0::3], parts[1::3], parts[2::3])
+    ]
+
+
+class TestPrintCommands:
+    """``--print-commands`` prints what each external command is given."""
+
+    def _run(self, tmp_path, *extra):
+        bin_dir, log = _recording_fakes(tmp_path)
+        src = tmp_path / "src"
+        (src / "sub dir").mkdir(parents=True)
+        (src / "it's $(touch pwned) `x`.txt").write_text("a")
+        (src / "sub dir" / 'say "hi"\n*.txt').write_text("b")
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        cmd = [KOSMOKOPY_BIN, "--cli", "--src", str(src), "--dst", "fakehost:/backup/new dir", "--mode", "folders", *extra]
+        proc = subprocess.run(cmd, capture_output=True, timeout=60, env=env, cwd=tmp_path)
+        calls = log.read_bytes().split(b"\x1e\n")[:-1] if log.exists() else []
+        return proc, [call.split(b"\0")[:-1] for call in calls]
+
+    def test_printed_argv_is_what_ran(self, tmp_path):
+        proc, ran = self._run(tmp_path, "--print-commands")
+        printed = _printed_commands(proc.stderr)
+        # Uploads run side by side, so they may start in another order
+        # than they were printed in.
+        assert sorted(argv for _, _, argv in printed) == sorted(ran)
+        assert [number for number, _, _ in printed] == list(range(1, len(ran) + 1))
+        uploads = [(stage, argv) for _, stage, argv in printed if argv[0] == b"scp"]
+        assert {stage for stage, _ in uploads} == {"transferring"}
+        assert [argv[-1] for _, argv in uploads] == [b"fakehost:/backup/new dir/src/it's $(touch pwned) `x`.txt"]
+        # scp would expand the `*` and can't carry the newline.
+        streamed = [argv[-1] for _, _, argv in printed if argv[0] == b"ssh" and argv[-1].startswith(b"cat > ")]
+        assert streamed == [b"cat > '/backup/new dir/src/sub dir/say \"hi\"\n*.txt'"]
+        stages = [stage for _, stage, _ in printed]
+        assert stages[0] == "scanning"
+        assert stages == sorted(stages, key=_STAGES.index)
+        # Nothing in the quoted names was run by a shell here.
+        assert not (tmp_path / "pwned").exists()
+
+    def test_nothing_is_printed_without_the_flag(self, tmp_path):
+        proc, ran = self._run(tmp_path)
+        assert ran
+        assert b"[kosmokopy] command" not in proc.stderr
+
+    def test_scp_is_given_the_limit_in_kbit(self, tmp_path):
+        _, ran = self._run(tmp_path, "--bwlimit", "500", "--parallel", "1")
+        uploads = [argv for argv in ran if argv[0] == b"scp"]
+        assert uploads
+        assert all(argv[argv.index(b"-l") + 1] == b"4000" for argv in uploads)
+
+    def test_files_at_once_share_the_limit(self, tmp_path):
+        _, ran = self._run(tmp_path, "--bwlimit", "500", "--parallel", "4")
+        uploads = [argv for argv in ran if argv[0] == b"scp"]
+        assert uploads
+        assert all(argv[argv.index(b"-l") + 1] == b"1000" for argv in uploads)
+
+    def test_downloads_one_at_a_time_get_the_whole_limit(self, tmp_path):
+        log = tmp_path / "scp_argv"
+        scp = 'echo "$@" >> {}\nfor a; do src="$dst"; dst="$a"; done\ncp "${{src#*:}}" "${{dst#*:}}"\n'.format(_sq(str(log)))
+        bin_dir, _ = _local_fakes(tmp_path, tools={"scp": scp})
+        src = tmp_path / "src"
+        src.mkdir()
+        for i in range(3):
+            (src / "f{}.txt".format(i)).write_text("file {}\n".format(i))
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        cmd = [KOSMOKOPY_BIN, "--cli", "--src", "fakehost:{}".format(src), "--dst", str(tmp_path / "dst"),
+               "--method", "standard", "--bwlimit", "500", "--parallel", "4"]
+        result = json.loads(subprocess.run(cmd, capture_output=True, timeout=60, env=env).stdout)
+        assert result["copied"] == 3
+        fetches = log.read_text().splitlines()
+        assert len(fetches) == 3
+        assert all(" -l 4000 " in line for line in fetches)
+
+
+# ═══════════════════════════════════════════════════════════════════════
+#  Batched upload verification (stand-ins that act on this machine)
+# ═══════════════════════════════════════════════════════════════════════
+
+
+def _local_fakes(tmp_path, corrupt=None, fail_batch=False, flaky=None, tools=None):
+    """``ssh`` that runs its command here and ``scp`` that copies here,
+    logging each ssh command.  *corrupt* names a file scp damages on the
+    way; with *fail_batch* a batched ``sha256sum --`` fails like a lost
+    connection; *flaky* is a file name and how many scp calls for it fail
+    before one goes through; *tools* maps other program names to their
+    shell scripts."""
+    bin_dir = tmp_path / "bin"
+    bin_dir.mkdir()
+    log = tmp_path / "ssh_log"
+    (bin_dir / "ssh").write_text(
+        "#!/bin/sh\n"
+        'for a; do cmd="$a"; done\n'
+        "printf '%s\\036\\n' \"$cmd\" >> {}\n".format(_sq(str(log)))
+        + ('case "$cmd" in *"sha256sum --"*) exit 255;; esac\n' if fail_batch else "")
+        + 'exec sh -c "$cmd"\n'
+    )
+    tries = tmp_path / "scp_tries"
+    (bin_dir / "scp").write_text(
+        "#!/bin/sh\n"
+        'for a; do src="$dst"; dst="$a"; done\n'
+        + ('case "$dst" in *{}) n=$(cat {} 2>/dev/null || echo 0)\n'
+           '  if [ "$n" -lt {} ]; then echo $((n + 1)) > {}; echo "lost connection" >&2; exit 1; fi;; esac\n'
+           .format(flaky[0], _sq(str(tries)), flaky[1], _sq(str(tries))) if flaky else "")
+        + 'cp "${src#*:}" "${dst#*:}"\n'
+        + ('case "$dst" in *{}) echo damaged >> "${{dst#*:}}";; esac\n'.format(corrupt) if corrupt else "")
+    )
+    for name, script in (tools or {}).items():
+        (bin_dir / name).write_text("#!/bin/sh\n" + script)
+    for fake in bin_dir.iterdir():
+        fake.chmod(0o755)
+    return bin_dir, log
+
+
+class TestBatchedVerification:
+    """Uploads are hashed on the host in one call rather than one per file."""
+
+    def _upload(self, tmp_path, *extra, **fakes):
+        bin_dir, log = _local_fakes(tmp_path, **fakes)
+        src = tmp_path / "src"
+        src.mkdir()
+        for i in range(12):
+            (src / "f{}.txt".format(i)).write_text("file {}\n".format(i))
+        dst = tmp_path / "dst"
+        dst.mkdir()
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(src), "--dst", "fakehost:{}".format(dst),
+             "--method", "standard", *extra],
+            capture_output=True, text=True, timeout=60, env=env,
+        )
+        ssh_calls = log.read_text().split("\x1e\n")[:-1] if log.exists() else []
+        hashing = [c for c in ssh_calls if "sha256sum" in c]
+        return json.loads(proc.stdout), src, dst / "src", hashing
+
+    def test_one_call_hashes_every_copy(self, tmp_path):
+        result, src, copies, hashing = self._upload(tmp_path)
+        assert result["status"] == "finished"
+        assert result["copied"] == 12
+        assert result["errors"] == []
+        assert len(hashing) == 1
+        assert sorted(p.name for p in copies.iterdir()) == sorted(p.name for p in src.iterdir())
+
+    def test_a_damaged_copy_is_removed_and_its_source_kept(self, tmp_path):
+        result, src, copies, _ = self._upload(tmp_path, "--move", corrupt="f7.txt")
+        assert result["copied"] == 11
+        assert len(result["errors"]) == 1
+        assert "f7.txt: integrity check failed" in result["errors"][0]
+        assert [p.name for p in src.iterdir()] == ["f7.txt"]
+        assert not (copies / "f7.txt").exists()
+
+    def test_a_host_without_the_chosen_tool_is_asked_once(self, tmp_path):
+        result, _, copies, _ = self._upload(tmp_path, "--hash-algo", "xxh128")
+        assert result["status"] == "finished"
+        assert result["copied"] == 12
+        assert result["errors"] == []
+        ssh_calls = (tmp_path / "ssh_log").read_text().split("\x1e\n")[:-1]
+        assert len([c for c in ssh_calls if c.startswith("command -v xxhsum")]) == 1
+
+    def test_a_mismatch_names_the_algorithm(self, tmp_path):
+        # A b3sum whose every hash is wrong.
+        wrong = 'shift; for f; do printf "%064d  %s\\n" 0 "$f"; done\n'
+        result, src, copies, _ = self._upload(tmp_path, "--move", "--hash-algo", "blake3", tools={"b3sum": wrong})
+        assert result["copied"] == 0
+        assert len(result["errors"]) == 12
+        assert all("integrity check failed — blake3 hash mismatch" in e for e in result["errors"])
+        assert len(list(src.iterdir())) == 12
+
+    def test_a_flaky_file_is_sent_again(self, tmp_path):
+        result, src, copies, _ = self._upload(tmp_path, "--retries", "2", flaky=("f3.txt", 2))
+        assert result["status"] == "finished"
+        assert result["copied"] == 12
+        assert result["errors"] == []
+        assert (copies / "f3.txt").read_text() == (src / "f3.txt").read_text()
+
+    def test_a_file_failing_every_attempt_says_how_many(self, tmp_path):
+        result, _, _, _ = self._upload(tmp_path, "--retries", "1", flaky=("f3.txt", 5))
+        assert result["copied"] == 11
+        assert len(result["errors"]) == 1
+        assert "f3.txt: scp failed" in result["errors"][0]
+        assert result["errors"][0].endswith("(gave up after 2 attempts)")
+
+    def test_a_mismatch_is_sent_again_before_giving_up(self, tmp_path):
+        result, src, _, _ = self._upload(tmp_path, "--move", "--retries", "1", corrupt="f7.txt")
+        assert result["copied"] == 11
+        assert "hash mismatch" in result["errors"][0]
+        assert result["errors"][0].endswith("(gave up after 2 attempts)")
+        assert [p.name for p in src.iterdir()] == ["f7.txt"]
+
+    def test_the_detail_report_holds_each_verified_hash(self, tmp_path):
+        report = tmp_path / "report.jsonl"
+        result, src, _, _ = self._upload(tmp_path, "--detail-report", str(report))
+        assert result["copied"] == 12
+        records = [json.loads(line) for line in report.read_text().splitlines()]
+        assert len(records) == 12
+        for record in records:
+            assert record["hash"] == "sha256:" + sha256_of_file(record["source"])
+
+    def test_a_failed_batch_falls_back_to_one_file_at_a_time(self, tmp_path):
+        result, _, _, hashing = self._upload(tmp_path, fail_batch=True)
+        assert result["status"] == "finished"
+        assert result["copied"] == 12
+        assert result["errors"] == []
+        assert len(hashing) == 1 + 12
+
+
+class TestFreeSpace:
+    """An upload too big for the host's free space doesn't start."""
+
+    # `df -Pk` on a host with 1 KiB left.
+    FULL = "echo 'Filesystem 1024-blocks Used Available Capacity Mounted on'; echo '/dev/sdz 100 99 1 99% /srv'\n"
+
+    def _upload(self, tmp_path, *extra):
+        bin_dir, _ = _local_fakes(tmp_path, tools={"df": self.FULL})
+        src = tmp_path / "src"
+        src.mkdir()
+        for i in range(3):
+            (src / "f{}.txt".format(i)).write_text("file {}\n".format(i))
+        dst = tmp_path / "dst"
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(src), "--dst", "fakehost:{}".format(dst / "new"),
+             "--method", "standard", *extra],
+            capture_output=True, text=True, timeout=60, env=env,
+        )
+        return json.loads(proc.stdout), dst
+
+    def test_a_full_host_is_refused_before_copying(self, tmp_path):
+        result, dst = self._upload(tmp_path)
+        assert result["status"] == "error"
+        assert result["kind"] == "no-space"
+        assert result["free_bytes"] == 1024
+        assert result["needed_bytes"] > 3 * 4096
+        assert result["message"].startswith("Needs 12.1 KiB, destination has 1.0 KiB free")
+        assert not dst.exists()
+
+    def test_ignore_free_space_goes_ahead(self, tmp_path):
+        result, dst = self._upload(tmp_path, "--ignore-free-space")
+        assert result["status"] == "finished"
+        assert result["copied"] == 3
+
+
+class TestHostileNames:
+    """Names no shell or scp would take as they are still arrive intact."""
+
+    NAMES = ["two\nlines.txt", "it's.txt", "$HOME.txt", "`id`.txt", "star*.txt",
+             "tab\there.txt", "back\\slash.txt", " padded ", "[draft]?.txt"]
+
+    @pytest.fixture
+    def hostile(self, tmp_path):
+        bin_dir, _ = _local_fakes(tmp_path)
+        src = tmp_path / "src"
+        src.mkdir()
+        for i, name in enumerate(self.NAMES):
+            (src / name).write_text("file {}\n".format(i))
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+
+        def run(source, dest):
+            proc = subprocess.run(
+                [KOSMOKOPY_BIN, "--cli", "--src", source, "--dst", dest, "--method", "standard"],
+                capture_output=True, text=True, timeout=60, env=env, cwd=tmp_path,
+            )
+            return json.loads(proc.stdout)
+
+        return run, src, tmp_path / "dst"
+
+    def _assert_copied(self, result, src, copies):
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["skipped"] == []
+        assert result["copied"] == len(self.NAMES)
+        assert sorted(p.name for p in copies.iterdir()) == sorted(self.NAMES)
+        for name in self.NAMES:
+            assert (copies / name).read_bytes() == (src / name).read_bytes()
+
+    def test_upload(self, hostile):
+        run, src, dst = hostile
+        result = run(str(src), "fakehost:{}".format(dst))
+        self._assert_copied(result, src, dst / "src")
+
+    def test_download(self, hostile):
+        run, src, dst = hostile
+        result = run("fakehost:{}".format(src), str(dst))
+        self._assert_copied(result, src, dst / "src")
+
+
+class TestNonUtf8Names:
+    """A remote name that isn't UTF-8 keeps its bytes both ways and the
+    result says it was escaped."""
+
+    NAME = b"caf\xe9 \xff.bin"
+
+    @pytest.fixture
+    def latin1(self, tmp_path):
+        bin_dir, _ = _local_fakes(tmp_path)
+        src = tmp_path / "src"
+        src.mkdir()
+        (src / "plain.txt").write_text("plain\n")
+        with open(os.path.join(os.fsencode(src), self.NAME), "wb") as f:
+            f.write(b"latin-1\n")
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+
+        def run(source, dest):
+            proc = subprocess.run(
+                [KOSMOKOPY_BIN, b"--cli", b"--src", source, b"--dst", dest, b"--method", b"standard"],
+                capture_output=True, timeout=60, env=env,
+            )
+            return json.loads(proc.stdout)
+
+        return run, src, tmp_path / "dst"
+
+    def _assert_copied(self, result, copies):
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["copied"] == 2
+        assert result["non_utf8"] is True
+        assert sorted(os.listdir(os.fsencode(copies))) == sorted([b"plain.txt", self.NAME])
+        with open(os.path.join(os.fsencode(copies), self.NAME), "rb") as f:
+            assert f.read() == b"latin-1\n"
+
+    def test_upload(self, latin1):
+        run, src, dst = latin1
+        result = run(os.fsencode(src), b"fakehost:" + os.fsencode(dst))
+        self._assert_copied(result, dst / "src")
+
+    def test_download(self, latin1):
+        run, src, dst = latin1
+        result = run(b"fakehost:" + os.fsencode(src), os.fsencode(dst))
+        self._assert_copied(result, dst / "src")
+
+
+class TestRemoteDryRun:
+    """``--dry-run`` plans uploads and downloads from the listing and the
+    lookup, and nothing is written on either side."""
+
+    NAME = b"caf\xe9.txt"
+
+    @pytest.fixture
+    def plan(self, tmp_path):
+        bin_dir, log = _local_fakes(tmp_path)
+        src = tmp_path / "src"
+        src.mkdir()
+        (src / "same.txt").write_text("new\n")
+        (src / "fresh.txt").write_text("fresh\n")
+        with open(os.path.join(os.fsencode(src), self.NAME), "wb") as f:
+            f.write(b"latin-1\n")
+        dst = tmp_path / "dst"
+        (dst / "src").mkdir(parents=True)
+        (dst / "src" / "same.txt").write_text("old\n")
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+
+        def run(source, dest, *extra):
+            proc = subprocess.run(
+                [KOSMOKOPY_BIN, b"--cli", b"--dry-run", b"--src", source, b"--dst", dest, *extra],
+                capture_output=True, timeout=60, env=env,
+            )
+            assert proc.returncode == 0, proc.stderr
+            entries = json.loads(proc.stdout)
+            return {e["src"].rsplit("/", 1)[1]: e for e in entries}, proc
+
+        return run, src, dst, log
+
+    def _unchanged(self, src, dst):
+        assert sorted(os.listdir(os.fsencode(src))) == sorted([b"same.txt", b"fresh.txt", self.NAME])
+        assert sorted(os.listdir(dst / "src")) == ["same.txt"]
+        assert (dst / "src" / "same.txt").read_text() == "old\n"
+
+    def test_upload(self, plan):
+        run, src, dst, log = plan
+        entries, _ = run(os.fsencode(src), b"fakehost:" + os.fsencode(dst))
+        assert entries["same.txt"]["action"] == "skip-existing"
+        assert entries["fresh.txt"] == {
+            "src": "{}/fresh.txt".format(src), "dst": "fakehost:{}/src/fresh.txt".format(dst), "action": "copy",
+        }
+        assert entries["caf\\xe9.txt"]["dst"] == "fakehost:{}/src/caf\\xe9.txt".format(dst)
+        assert entries["caf\\xe9.txt"]["non_utf8"] is True
+        assert "non_utf8" not in entries["fresh.txt"]
+        renamed, _ = run(os.fsencode(src), b"fakehost:" + os.fsencode(dst), b"--conflict", b"rename")
+        assert renamed["same.txt"]["action"] == "rename"
+        assert renamed["same.txt"]["dst"] == "fakehost:{}/src/same_1.txt".format(dst)
+        overwritten, _ = run(os.fsencode(src), b"fakehost:" + os.fsencode(dst), b"--conflict", b"overwrite")
+        assert overwritten["same.txt"]["action"] == "overwrite"
+        assert overwritten["fresh.txt"]["action"] == "copy"
+        self._unchanged(src, dst)
+        assert b"mkdir" not in log.read_bytes()
+
+    def test_download(self, plan):
+        run, src, dst, _ = plan
+        entries, _ = run(b"fakehost:" + os.fsencode(src), os.fsencode(dst), b"--conflict", b"rename")
+        assert entries["same.txt"]["src"] == "fakehost:{}/same.txt".format(src)
+        assert entries["same.txt"]["dst"] == str(dst / "src" / "same_1.txt")
+        assert entries["fresh.txt"]["action"] == "copy"
+        assert entries["caf\\xe9.txt"]["non_utf8"] is True
+        self._unchanged(src, dst)
+
+    def test_writing_commands_are_printed_as_suppressed(self, plan):
+        run, src, dst, _ = plan
+        _, proc = run(os.fsencode(src), b"fakehost:" + os.fsencode(dst), b"--print-commands")
+        printed = _printed_commands(proc.stderr)
+        suppressed = [argv for _, stage, argv in printed if stage.endswith(", suppressed")]
+        assert sorted(argv[-1] for argv in suppressed if argv[0] == b"scp") == sorted(
+            b"fakehost:" + os.path.join(os.fsencode(dst / "src"), name) for name in (b"fresh.txt", self.NAME)
+        )
+        assert any(argv[0] == b"ssh" and b"mkdir" in argv[-1] for argv in suppressed)
+        self._unchanged(src, dst)
+
+
+class TestRelayStrategies:
+    """Files between two hosts skip the local staging folder when they can."""
+
+    # An scp failing when the source host runs it (without this machine's
+    # connection options), like a host that can't reach the destination.
+    NO_DIRECT = ('case "$*" in *ControlMaster*) ;; *) echo "ssh: Could not resolve hostname nas2" >&2; exit 1;; esac\n'
+                 'for a; do src="$dst"; dst="$a"; done\n'
+                 'cp "${src#*:}" "${dst#*:}"\n')
+
+    def _relay(self, tmp_path, *extra, tools=None):
+        bin_dir, log = _local_fakes(tmp_path, tools=tools)
+        src = tmp_path / "src"
+        (src / "sub dir").mkdir(parents=True)
+        for i in range(3):
+            (src / "f{}.txt".format(i)).write_text("file {}\n".format(i))
+        (src / "sub dir" / "it's.txt").write_text("quoted\n")
+        dst = tmp_path / "dst"
+        dst.mkdir()
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", "nas1:{}".format(src), "--dst", "nas2:{}".format(dst),
+             "--method", "standard", *extra],
+            capture_output=True, text=True, timeout=60, env=env,
+        )
+        ssh_calls = log.read_text().split("\x1e\n")[:-1] if log.exists() else []
+        result = json.loads(proc.stdout)
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["copied"] == 4
+        for f in src.rglob("*.txt"):
+            assert (dst / "src" / f.relative_to(src)).read_text() == f.read_text()
+        return result, ssh_calls
+
+    def test_the_source_host_sends_directly(self, tmp_path):
+        result, ssh_calls = self._relay(tmp_path)
+        # The name with a space and a quote goes through the staging folder.
+        assert result["relay"] == {"strategy": "direct", "fallbacks": [], "staged_for_names": 1}
+        assert len([c for c in ssh_calls if c.startswith("scp -q -o BatchMode=yes")]) == 3
+
+    def test_scp_3_when_the_hosts_cannot_reach_each_other(self, tmp_path):
+        result, _ = self._relay(tmp_path, tools={"scp": self.NO_DIRECT})
+        assert result["relay"]["strategy"] == "scp3"
+        assert len(result["relay"]["fallbacks"]) == 1
+        assert result["relay"]["fallbacks"][0].startswith("direct: ")
+        assert "Could not resolve hostname nas2" in result["relay"]["fallbacks"][0]
+
+    def test_staged_when_asked(self, tmp_path):
+        result, ssh_calls = self._relay(tmp_path, "--relay", "staged")
+        assert result["relay"] == {"strategy": "staged", "fallbacks": [], "staged_for_names": 0}
+        assert not [c for c in ssh_calls if c.startswith("scp ")]
+
+
+class TestStripSpacesCommands:
+    """--strip-spaces renames what is below the destination folder, never
+    the folder itself, in the commands the workers run."""
+
+    def test_upload_keeps_the_spaced_destination(self, tmp_path):
+        bin_dir, log = _recording_fakes(tmp_path)
+        src = tmp_path / "my src"
+        (src / "sub dir").mkdir(parents=True)
+        (src / "a file.txt").write_text("a")
+        (src / "sub dir" / "b file.txt").write_text("b")
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(src), "--dst", "fakehost:/backup/My Backups",
+             "--method", "standard", "--strip-spaces"],
+            capture_output=True, timeout=60, env=env,
+        )
+        calls = [call.split(b"\0")[:-1] for call in log.read_bytes().split(b"\x1e\n")[:-1]]
+        targets = sorted(argv[-1] for argv in calls if argv[0] == b"scp")
+        assert targets == [
+            b"fakehost:/backup/My Backups/mysrc/afile.txt",
+            b"fakehost:/backup/My Backups/mysrc/subdir/bfile.txt",
+        ]
+
+    @pytest.mark.parametrize("relay", ["scp3", "staged"])
+    def test_relay_keeps_the_spaced_destination(self, tmp_path, relay):
+        argv_log = tmp_path / "scp_argv"
+        # scp as in _local_fakes, logging the last two arguments it is given.
+        scp = ("printf '%s\\036\\n' \"$@\" | tail -n 2 >> {}\n"
+               'for a; do src="$dst"; dst="$a"; done\n'
+               'cp "${{src#*:}}" "${{dst#*:}}"\n').format(_sq(str(argv_log)))
+        bin_dir, _ = _local_fakes(tmp_path, tools={"scp": scp})
+        src = tmp_path / "my src"
+        (src / "sub dir").mkdir(parents=True)
+        (src / "sub dir" / "b_file.txt").write_text("b")
+        dst = tmp_path / "My Backups"
+        dst.mkdir()
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", "nas1:{}".format(src), "--dst", "nas2:{}".format(dst),
+             "--method", "standard", "--strip-spaces", "--relay", relay],
+            capture_output=True, text=True, timeout=60, env=env,
+        )
+        result = json.loads(proc.stdout)
+        assert result["errors"] == []
+        assert result["relay"]["strategy"] == relay
+        assert (dst / "mysrc" / "subdir" / "b_file.txt").read_text() == "b"
+        assert not (tmp_path / "MyBackups").exists()
+        sent = argv_log.read_text().split("\x1e\n")[:-1]
+        if relay == "scp3":
+            assert sent == ["nas1:{}".format(src / "sub dir" / "b_file.txt"),
+                            "nas2:{}".format(dst / "mysrc" / "subdir" / "b_file.txt")]
+        else:
+            assert sent[-1] == "nas2:{}".format(dst / "mysrc" / "subdir" / "b_file.txt")
+
+
+class TestMoveWithinHost:
+    """A move from one folder of a host to another is done on the host."""
+
+    # A `stat` putting everything below dst on another filesystem.
+    OTHER_FS = 'for a; do f="$a"; done\ncase "$f" in *dst*) echo 2;; *) echo 1;; esac\n'
+
+    def _move(self, tmp_path, *extra, tools=None, dst_host="fakehost", **env_vars):
+        bin_dir, log = _local_fakes(tmp_path, tools=tools)
+        src = tmp_path / "src"
+        (src / "sub").mkdir(parents=True)
+        for i in range(3):
+            (src / "f{}.txt".format(i)).write_text("file {}\n".format(i))
+        (src / "sub" / "it's.txt").write_text("quoted\n")
+        dst = tmp_path / "dst"
+        dst.mkdir(exist_ok=True)
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]), **env_vars)
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", "fakehost:{}".format(src), "--dst", "{}:{}".format(dst_host, dst),
+             "--method", "standard", "--move", *extra],
+            capture_output=True, text=True, timeout=60, env=env,
+        )
+        ssh_calls = log.read_text().split("\x1e\n")[:-1] if log.exists() else []
+        return json.loads(proc.stdout), dst / "src", ssh_calls, tmp_path / "scp_tries"
+
+    def test_files_are_renamed_in_one_call(self, tmp_path):
+        result, moved, ssh_calls, _ = self._move(tmp_path)
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["copied"] == 4
+        assert (moved / "sub" / "it's.txt").read_text() == "quoted\n"
+        assert not [f for f in (tmp_path / "src").rglob("*") if f.is_file()]
+        assert len([c for c in ssh_calls if "\none " in c]) == 1
+        assert "relay" not in result
+
+    def test_another_filesystem_is_copied_checked_and_removed(self, tmp_path):
+        result, moved, ssh_calls, _ = self._move(tmp_path, tools={"stat": self.OTHER_FS})
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["copied"] == 4
+        assert sorted(f.name for f in moved.rglob("*.txt")) == ["f0.txt", "f1.txt", "f2.txt", "it's.txt"]
+        assert not [f for f in (tmp_path / "src").rglob("*") if f.is_file()]
+
+    def test_existing_files_are_kept(self, tmp_path):
+        (tmp_path / "dst" / "src").mkdir(parents=True)
+        (tmp_path / "dst" / "src" / "f1.txt").write_text("already here\n")
+        result, moved, _, _ = self._move(tmp_path, "--conflict", "skip")
+        assert result["copied"] == 3
+        assert result["skipped"] == ["{}: already exists at destination".format(tmp_path / "src" / "f1.txt")]
+        assert (moved / "f1.txt").read_text() == "already here\n"
+        assert (tmp_path / "src" / "f1.txt").exists()
+
+    def test_nothing_is_staged_here(self, tmp_path):
+        # No staging folder can be made under this TMPDIR.
+        blocked = tmp_path / "not-a-dir"
+        blocked.write_text("")
+        result, moved, _, _ = self._move(tmp_path, TMPDIR=str(blocked / "tmp"))
+        assert result["status"] == "finished"
+        assert result["copied"] == 4
+
+    def test_holding_sources_back_copies_instead_of_renaming(self, tmp_path):
+        result, moved, ssh_calls, _ = self._move(tmp_path, "--move-strategy", "all-then-delete")
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["copied"] == 4
+        assert result["deletion"]["deleted"] == 4
+        assert not any("mv -f" in c for c in ssh_calls)
+        assert sorted(f.name for f in moved.rglob("*.txt")) == ["f0.txt", "f1.txt", "f2.txt", "it's.txt"]
+        assert not [f for f in (tmp_path / "src").rglob("*") if f.is_file()]
+
+    def test_free_space_is_checked_unless_every_file_is_renamed(self, tmp_path):
+        tools = {"df": TestFreeSpace.FULL, "stat": self.OTHER_FS}
+        result, moved, _, _ = self._move(tmp_path, tools=tools)
+        assert result["kind"] == "no-space"
+        assert not moved.exists()
+        assert len(list((tmp_path / "src").rglob("*.txt"))) == 4
+
+        renamed = tmp_path / "renamed"
+        renamed.mkdir()
+        result, _, _, _ = self._move(renamed, tools={"df": TestFreeSpace.FULL})
+        assert result["status"] == "finished"
+        assert result["copied"] == 4
+
+
+class TestTarStream:
+    """--method tar sends an upload as one tar stream over ssh."""
+
+    def _upload(self, tmp_path, *extra):
+        bin_dir, log = _local_fakes(tmp_path)
+        src = tmp_path / "src"
+        (src / "deep" / ("d" * 120)).mkdir(parents=True)
+        for i in range(5):
+            (src / "f{}.txt".format(i)).write_text("file {}\n".format(i))
+        (src / "deep" / ("d" * 120) / ("f" * 90)).write_bytes(os.urandom(70_000))
+        (src / "run.sh").write_text("#!/bin/sh\n")
+        (src / "run.sh").chmod(0o755)
+        dst = tmp_path / "dst"
+        dst.mkdir(exist_ok=True)
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(src), "--dst", "fakehost:{}".format(dst),
+             "--method", "tar", *extra],
+            capture_output=True, text=True, timeout=60, env=env,
+        )
+        ssh_calls = log.read_text().split("\x1e\n")[:-1] if log.exists() else []
+        return json.loads(proc.stdout), src, dst / "src", ssh_calls
+
+    def test_one_stream_carries_every_file(self, tmp_path):
+        result, src, copies, ssh_calls = self._upload(tmp_path)
+        assert result["status"] == "finished"
+        assert result["copied"] == 7
+        assert result["errors"] == []
+        assert len([c for c in ssh_calls if c.startswith("tar -xf -")]) == 1
+        for f in src.rglob("*"):
+            if f.is_file():
+                copy = copies / f.relative_to(src)
+                assert sha256_of_file(copy) == sha256_of_file(f)
+        assert os.stat(copies / "run.sh").st_mode & 0o111
+
+    def test_skip_leaves_existing_files_out_of_the_stream(self, tmp_path):
+        copies = tmp_path / "dst" / "src"
+        copies.mkdir(parents=True)
+        (copies / "f2.txt").write_text("kept\n")
+        result, _, _, _ = self._upload(tmp_path, "--conflict", "skip")
+        assert result["copied"] == 6
+        assert len(result["skipped"]) == 1
+        assert (copies / "f2.txt").read_text() == "kept\n"
+
+    def test_a_move_deletes_verified_sources(self, tmp_path):
+        result, src, _, _ = self._upload(tmp_path, "--move")
+        assert result["copied"] == 7
+        assert list(src.iterdir()) == []
+        assert result["source_dirs_removed"] == 2
+
+    @pytest.mark.parametrize("extra", [("--conflict", "rename"), ("--conflict", "newer")])
+    def test_other_conflict_choices_are_refused(self, tmp_path, extra):
+        result, _, copies, ssh_calls = self._upload(tmp_path, *extra)
+        assert result["status"] == "error"
+        assert "skip or overwrite" in result["message"]
+        assert ssh_calls == []
+        assert not copies.exists()
+
+    def test_a_local_destination_is_refused(self, tmp_path):
+        src = tmp_path / "src"
+        src.mkdir()
+        (src / "a.txt").write_text("a\n")
+        result = run_kosmokopy(src=src, dst=tmp_path / "dst", method="tar")
+        assert result["status"] == "error"
+        assert "only uploads" in result["message"]
+
+
+class TestEmptiedRemoteSource:
+    """A move from a folder on a host removes the folders it emptied there."""
+
+    def _download(self, tmp_path, *extra, excluded=True):
+        bin_dir, log = _local_fakes(tmp_path)
+        src = tmp_path / "src"
+        (src / "a" / "b").mkdir(parents=True)
+        (src / "top.txt").write_text("top\n")
+        (src / "a" / "b" / "deep.txt").write_text("deep\n")
+        if excluded:
+            (src / "kept").mkdir()
+            (src / "kept" / "skip.log").write_text("excluded\n")
+        dst = tmp_path / "dst"
+        dst.mkdir()
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", "fakehost:{}".format(src), "--dst", str(dst),
+             "--method", "standard", "--move", "--exclude", "~*.log", *extra],
+            capture_output=True, text=True, timeout=60, env=env,
+        )
+        ssh_calls = log.read_text().split("\x1e\n")[:-1] if log.exists() else []
+        return json.loads(proc.stdout), src, [c for c in ssh_calls if "rmdir" in c]
+
+    def test_emptied_folders_go_in_one_call(self, tmp_path):
+        result, src, rmdir_calls = self._download(tmp_path)
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["source_dirs_removed"] == 2
+        assert len(rmdir_calls) == 1
+        assert sorted(p.name for p in src.iterdir()) == ["kept"]
+
+    def test_the_source_folder_goes_when_asked(self, tmp_path):
+        result, src, _ = self._download(tmp_path, "--remove-source-root", excluded=False)
+        assert result["status"] == "finished"
+        assert result["source_dirs_removed"] == 3
+        assert not src.exists()
+
+
+class TestRemoteScanProgress:
+    """A remote listing reports what it has found before the first file."""
+
+    def test_listing_reports_files_and_bytes(self, tmp_path):
+        bin_dir, _ = _local_fakes(tmp_path)
+        src = tmp_path / "src"
+        (src / "sub").mkdir(parents=True)
+        (src / "a.txt").write_text("aaaa\n")
+        (src / "sub" / "b.txt").write_text("bbbbbbbbb\n")
+        (src / "sub" / "c.log").write_text("c\n")
+        dst = tmp_path / "dst"
+        dst.mkdir()
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", "fakehost:{}".format(src), "--dst", str(dst),
+             "--method", "standard", "--exclude", "~*.log", "--events"],
+            capture_output=True, text=True, timeout=60, env=env,
+        )
+        result = json.loads(proc.stdout)
+        assert result["status"] == "finished"
+        assert result["copied"] == 2
+        events = [json.loads(line) for line in proc.stderr.splitlines() if line.startswith("{")]
+        scans = [e for e in events if e["event"] == "scan"]
+        # Counted as the listing arrives, before the exclusions apply.
+        assert scans[-1] == {"type": "scan", "event": "scan", "files_found": 3, "bytes_found": 17}
+
+
+class TestRemoteRetention:
+    """--retain prunes old dated folders on the host."""
+
+    def _run(self, tmp_path, *extra, **env_vars):
+        bin_dir, _ = _local_fakes(tmp_path)
+        src = tmp_path / "src"
+        src.mkdir()
+        (src / "a.txt").write_text("a\n")
+        backups = tmp_path / "backups"
+        for name in ["weekly-2024-06-30", "weekly-2025-12-31", "weekly-x\nweekly-2020-01-01"]:
+            (backups / name).mkdir(parents=True)
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]), **env_vars)
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(src), "--dst", "fakehost:{}/weekly-{{date}}".format(backups),
+             "--method", "standard", "--retain", "2", *extra],
+            capture_output=True, text=True, timeout=60, env=env,
+        )
+        return json.loads(proc.stdout), backups
+
+    def test_names_holding_newlines_are_listed_whole(self, tmp_path):
+        result, backups = self._run(tmp_path)
+        assert result["errors"] == []
+        assert result["pruned"] == ["fakehost:{}/weekly-2024-06-30".format(backups)]
+        assert (backups / "weekly-x\nweekly-2020-01-01").is_dir()
+        assert not (backups / "weekly-2024-06-30").exists()
+
+    def test_pruned_folders_can_go_to_the_trash(self, tmp_path):
+        share = tmp_path / "share"
+        result, backups = self._run(tmp_path, "--retain-to-trash", XDG_DATA_HOME=str(share))
+        assert result["errors"] == []
+        assert len(result["pruned"]) == 1
+        assert (share / "Trash" / "files" / "weekly-2024-06-30").is_dir()
+        assert (share / "Trash" / "info" / "weekly-2024-06-30.trashinfo").exists()
+        assert not (backups / "weekly-2024-06-30").exists()
+
+
+class TestRemoteStaleArtifacts:
+    """Leftovers of an earlier run are looked for on the host too."""
+
+    def _upload(self, tmp_src, tmp_path, *extra):
+        bin_dir, _ = _local_fakes(tmp_path)
+        dst = tmp_path / "dst"
+        (dst / tmp_src.name).mkdir(parents=True)
+        (dst / tmp_src.name / "hello.txt.kosmokopy-partial").write_text("Hello, ")
+        (dst / ".kosmokopy.toml").write_text("keep me\n")
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(tmp_src), "--dst", "fakehost:{}".format(dst),
+             "--method", "standard", *extra],
+            capture_output=True, text=True, timeout=60, env=env,
+        )
+        return json.loads(proc.stdout), dst / tmp_src.name
+
+    def test_the_cli_refuses_to_guess(self, tmp_src, tmp_path):
+        result, root = self._upload(tmp_src, tmp_path)
+        assert result["kind"] == "stale-artifacts"
+        assert "fakehost:" in result["message"]
+        assert not (root / "hello.txt").exists()
+
+    def test_clean_removes_them_on_the_host(self, tmp_src, tmp_path):
+        result, root = self._upload(tmp_src, tmp_path, "--stale-artifacts", "clean")
+        assert result["status"] == "finished"
+        assert result["stale_artifacts"]["removed"] == 1
+        assert not (root / "hello.txt.kosmokopy-partial").exists()
+        assert (root.parent / ".kosmokopy.toml").exists()
+
+    def test_scp_cannot_resume(self, tmp_src, tmp_path):
+        result, root = self._upload(tmp_src, tmp_path, "--stale-artifacts", "resume")
+        assert result["status"] == "finished"
+        assert result["stale_artifacts"]["choice"] == "ignore"
+        assert (root / "hello.txt").read_text() == "Hello, World!\n"
+
+
+class TestSymlinkUploads:
+    """--symlinks preserve recreates links on the host."""
+
+    @pytest.mark.parametrize("method", ["standard", "tar"])
+    def test_links_are_recreated_on_the_host(self, tmp_path, method):
+        bin_dir, _ = _local_fakes(tmp_path)
+        src = tmp_path / "src"
+        src.mkdir()
+        (src / "data.txt").write_text("data\n")
+        (src / "to_data.txt").symlink_to("data.txt")
+        (src / "dangling").symlink_to("nowhere")
+        dst = tmp_path / "dst"
+        dst.mkdir()
+        env = dict(os.environ, PATH="{}{}{}".format(bin_dir, os.pathsep, os.environ["PATH"]))
+        proc = subprocess.run(
+            [KOSMOKOPY_BIN, "--cli", "--src", str(src), "--dst", "fakehost:{}".format(dst),
+             "--method", method, "--symlinks", "preserve"],
+            capture_output=True, text=True, timeout=60, env=env,
+        )
+        result = json.loads(proc.stdout)
+        assert result["status"] == "finished"
+        assert result["errors"] == []
+        assert result["copied"] == 3
+        copies = dst / "src"
+        assert os.readlink(copies / "to_data.txt") == "data.txt"
+        assert os.readlink(copies / "dangling") == "nowhere"
+        assert not (copies / "data.txt").is_symlink()
@@ -0,0 +1,89 @@
+"""
+Rename rules (--rename-rule) and --rename-preview.
+"""
+
+import os
+import subprocess
+
+from conftest import KOSMOKOPY_BIN, run_kosmokopy
+
+
+def _preview(*args):
+    return subprocess.run(
+        [KOSMOKOPY_BIN, "--cli", "--rename-preview", *args],
+        capture_output=True, text=True, timeout=60,
+    )
+
+
+def _photos(tmp_path):
+    src = tmp_path / "Camera Roll"
+    src.mkdir()
+    (src / "IMG 0001.JPG").write_bytes(b"one")
+    (src / "IMG 0002.Jpeg").write_bytes(b"two")
+    (src / "notes.txt").write_text("keep\n")
+    return src
+
+
+class TestRenameRules:
+
+    def test_rules_run_in_order(self, tmp_path, tmp_dst):
+        src = _photos(tmp_path)
+        result = run_kosmokopy(
+            src=src, dst=tmp_dst, strip_spaces=True,
+            rename_rule=["case-fold-extension", r"regex/^IMG(\d+)/photo-$1"],
+        )
+        assert result["status"] == "finished"
+        copied = sorted(p.name for p in (tmp_dst / "CameraRoll").iterdir())
+        assert copied == ["notes.txt", "photo-0001.jpg", "photo-0002.jpeg"]
+        assert result["renamed"] == 3
+        assert {"from": "Camera Roll/IMG 0001.JPG", "to": "CameraRoll/photo-0001.jpg"} in result["renamed_samples"]
+
+    def test_existing_files_are_checked_under_their_new_names(self, tmp_path, tmp_dst):
+        src = _photos(tmp_path)
+        rules = ["case-fold-extension"]
+        assert run_kosmokopy(src=src, dst=tmp_dst, rename_rule=rules)["copied"] == 3
+        again = run_kosmokopy(src=src, dst=tmp_dst, rename_rule=rules)
+        assert again["status"] == "finished"
+        assert again["copied"] == 0
+        assert len(again["skipped"]) == 3
+
+    def test_no_renames_leave_the_result_unchanged(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, rename_rule=["replace-chars/#/_"])
+        assert result["status"] == "finished"
+        assert "renamed" not in result
+
+    def test_date_prefix_follows_timestamps(self, tmp_path, tmp_dst):
+        src = tmp_path / "src"
+        src.mkdir()
+        photo = src / "beach.jpg"
+        photo.write_bytes(b"sand")
+        # 23:30 UTC on 28 March is already 00:30 on the 29th in Berlin.
+        os.utime(photo, (1774740600, 1774740600))
+        berlin = {"TZ": "CET-1CEST,M3.5.0,M10.5.0/3"}
+        utc = run_kosmokopy(src=src, dst=tmp_dst / "utc", rename_rule=["date-prefix"], env=berlin)
+        local = run_kosmokopy(
+            src=src, dst=tmp_dst / "local", rename_rule=["date-prefix"], timestamps="local", env=berlin,
+        )
+        assert utc["status"] == local["status"] == "finished"
+        assert (tmp_dst / "utc" / "src" / "2026-03-28_beach.jpg").exists()
+        assert (tmp_dst / "local" / "src" / "2026-03-29_beach.jpg").exists()
+
+    def test_bad_rule_is_rejected(self, tmp_src, tmp_dst):
+        result = run_kosmokopy(src=tmp_src, dst=tmp_dst, rename_rule=["replace-chars/ab/a"])
+        assert result["status"] == "error"
+        assert "--rename-rule" in result["message"]
+        assert not any(tmp_dst.iterdir())
+
+
+class TestRenamePreview:
+
+    def test_preview_lists_renames_without_copying(self, tmp_path, tmp_dst):
+        src = _photos(tmp_path)
+        proc = _preview("--src", str(src), "--rename-rule", "case-fold-extension")
+        assert proc.returncode == 0
+        assert sorted(proc.stdout.splitlines()) == [
+            "Camera Roll/IMG 0001.JPG → Camera Roll/IMG 0001.jpg",
+            "Camera Roll/IMG 0002.Jpeg → Camera Roll/IMG 0002.jpeg",
+        ]
+        assert "2 of 3 file(s) would be renamed" in proc.stderr
+        assert not any(tmp_dst.iterdir())